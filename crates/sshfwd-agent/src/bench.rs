@@ -0,0 +1,89 @@
+//! `--bench-server`: the remote end of `sshfwd bench` (protocol in
+//! `sshfwd_common::bench`). Listens on an ephemeral loopback port until stdin
+//! closes, which happens when the SSH channel goes away.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use sshfwd_common::bench::{self, DOWNLOAD, ECHO, HEADER_LEN, UPLOAD};
+
+pub fn serve() -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut stdout = io::stdout();
+    writeln!(stdout, "{}", listener.local_addr()?.port())?;
+    stdout.flush()?;
+
+    thread::spawn(|| {
+        let _ = io::copy(&mut io::stdin(), &mut io::sink());
+        std::process::exit(0);
+    });
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            let _ = handle(stream);
+        });
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut header = [0; HEADER_LEN];
+    stream.read_exact(&mut header)?;
+    match bench::parse_header(&header) {
+        (ECHO, _) => {
+            io::copy(&mut stream.try_clone()?, &mut stream)?;
+        }
+        (UPLOAD, length) => {
+            let received = io::copy(&mut (&stream).take(length), &mut io::sink())?;
+            stream.write_all(&received.to_be_bytes())?;
+        }
+        (DOWNLOAD, length) => {
+            io::copy(&mut io::repeat(0).take(length), &mut stream)?;
+        }
+        (op, _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown bench op {op:#x}"),
+            ))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect(listener: &TcpListener, op: u8, length: u64) -> TcpStream {
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        thread::spawn(move || handle(server));
+        client.write_all(&bench::header(op, length)).unwrap();
+        client
+    }
+
+    #[test]
+    fn serves_each_op() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mut echo = connect(&listener, ECHO, 0);
+        let mut buf = [0; 4];
+        echo.write_all(b"ping").unwrap();
+        echo.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        let mut upload = connect(&listener, UPLOAD, 100_000);
+        upload.write_all(&[7; 100_000]).unwrap();
+        let mut ack = [0; 8];
+        upload.read_exact(&mut ack).unwrap();
+        assert_eq!(u64::from_be_bytes(ack), 100_000);
+
+        let mut download = connect(&listener, DOWNLOAD, 70_000);
+        let mut received = Vec::new();
+        download.read_to_end(&mut received).unwrap();
+        assert_eq!(received.len(), 70_000);
+    }
+}
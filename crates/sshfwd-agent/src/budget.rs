@@ -0,0 +1,85 @@
+//! Bounded scan overhead on loaded hosts.
+//!
+//! `--nice <n>` lowers the agent's CPU priority once at startup. With
+//! `--scan-budget <ms>`, a scan that takes longer than the budget makes the
+//! following scans shallow: no `/proc/*/fd` walk for unknown sockets (cached
+//! owners still apply) and no probes. As many scans are shallow as the slow
+//! one overran the budget, so deep work averages out to about one budget per
+//! interval.
+
+use std::time::Duration;
+
+/// Most consecutive shallow scans, so new ports still get attributed on a
+/// host that is slow for good.
+const MAX_SHALLOW: u32 = 10;
+
+/// Apply `--nice`; returns the error to report when the kernel refuses
+/// (raising priority needs root).
+pub fn renice(nice: i32) -> Result<(), String> {
+    // SAFETY: plain syscall; `who = 0` is this process.
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+    if rc != 0 {
+        return Err(format!(
+            "cannot set nice {nice}: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct ScanBudget {
+    limit: Duration,
+    /// Shallow scans still owed for the last overrun.
+    shallow: u32,
+}
+
+impl ScanBudget {
+    pub fn new(limit: Duration) -> Self {
+        Self { limit, shallow: 0 }
+    }
+
+    /// Whether the next scan must skip deep work; counts it as taken.
+    pub fn next_is_shallow(&mut self) -> bool {
+        let shallow = self.shallow > 0;
+        self.shallow = self.shallow.saturating_sub(1);
+        shallow
+    }
+
+    /// Record how long a deep scan took.
+    pub fn record(&mut self, took: Duration) {
+        if took > self.limit && !self.limit.is_zero() {
+            let overruns = took.as_nanos() / self.limit.as_nanos();
+            self.shallow = u32::try_from(overruns).unwrap_or(u32::MAX).min(MAX_SHALLOW);
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overruns_buy_shallow_scans() {
+        let ms = Duration::from_millis;
+        let mut budget = ScanBudget::new(ms(100));
+        budget.record(ms(80));
+        assert!(!budget.next_is_shallow());
+
+        budget.record(ms(350));
+        assert!(budget.next_is_shallow());
+        assert!(budget.next_is_shallow());
+        assert!(budget.next_is_shallow());
+        assert!(!budget.next_is_shallow());
+
+        budget.record(Duration::from_secs(60));
+        let shallow = std::iter::from_fn(|| Some(budget.next_is_shallow()))
+            .take_while(|s| *s)
+            .count();
+        assert_eq!(shallow, MAX_SHALLOW as usize);
+    }
+}
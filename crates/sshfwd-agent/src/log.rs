@@ -0,0 +1,236 @@
+//! Minimal file logger with size-based rotation.
+//!
+//! The agent ships as a tiny static binary, so this stays dependency-free:
+//! one global logger, appending timestamped lines, rotating `agent.log` →
+//! `agent.log.1` → … when the file grows past `MAX_BYTES`.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use sshfwd_common::time;
+
+/// Rotate once the active log reaches this size.
+const MAX_BYTES: u64 = 1024 * 1024;
+/// Rotated files kept alongside the active log (`.1` is the newest).
+const KEEP: usize = 3;
+
+static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
+
+/// Shared with the client, which checks `--agent-log-level` before
+/// passing it on.
+pub use sshfwd_common::types::LogLevel as Level;
+
+struct Logger {
+    path: PathBuf,
+    level: Level,
+    max_bytes: u64,
+    file: Option<File>,
+    /// `(dev, inode)` of `file`, to notice when another agent sharing the
+    /// log has rotated it out from under us.
+    file_id: Option<(u64, u64)>,
+    /// `(uid, gid)` to hand new log files to when running elevated, so the
+    /// unprivileged user can still rotate and clean them up.
+    owner: Option<(u32, u32)>,
+}
+
+impl Logger {
+    fn new(path: PathBuf, level: Level, max_bytes: u64) -> Self {
+        Self {
+            path,
+            level,
+            max_bytes,
+            file: None,
+            file_id: None,
+            owner: None,
+        }
+    }
+
+    fn write(&mut self, level: Level, message: &str) -> io::Result<()> {
+        if level > self.level {
+            return Ok(());
+        }
+        // The pid tells apart agents of concurrent sessions sharing one log.
+        let line = format!(
+            "{} {:<5} [{}] {message}\n",
+            timestamp(),
+            level.label(),
+            std::process::id()
+        );
+
+        let current = fs::metadata(&self.path).ok();
+        if current.as_ref().map(|m| (m.dev(), m.ino())) != self.file_id {
+            self.file = None;
+        }
+        let size = current.map_or(0, |m| m.len());
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            self.file = None;
+            rotate(&self.path, KEEP)?;
+        }
+
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            let meta = file.metadata()?;
+            self.file_id = Some((meta.dev(), meta.ino()));
+            self.file = Some(file);
+            if let Some((uid, gid)) = self.owner {
+                std::os::unix::fs::chown(&self.path, Some(uid), Some(gid))?;
+            }
+        }
+        let file = self.file.as_mut().expect("log file opened above");
+        file.write_all(line.as_bytes())
+    }
+}
+
+/// Enable logging to `path`, with files owned by `owner` when set. Only the
+/// first call takes effect.
+pub fn init(path: PathBuf, level: Level, owner: Option<(u32, u32)>) {
+    let mut logger = Logger::new(path, level, MAX_BYTES);
+    logger.owner = owner;
+    let _ = LOGGER.set(Mutex::new(logger));
+}
+
+/// Path of the active log file, if logging is enabled.
+pub fn path() -> Option<PathBuf> {
+    let logger = LOGGER.get()?.lock().ok()?;
+    Some(logger.path.clone())
+}
+
+/// Append a line at `level`. Failures are swallowed: logging must never
+/// interfere with scanning.
+pub fn log(level: Level, message: &str) {
+    if let Some(Ok(mut logger)) = LOGGER.get().map(Mutex::lock) {
+        let _ = logger.write(level, message);
+    }
+}
+
+pub fn error(message: impl AsRef<str>) {
+    log(Level::Error, message.as_ref());
+}
+
+pub fn warn(message: impl AsRef<str>) {
+    log(Level::Warn, message.as_ref());
+}
+
+pub fn info(message: impl AsRef<str>) {
+    log(Level::Info, message.as_ref());
+}
+
+pub fn debug(message: impl AsRef<str>) {
+    log(Level::Debug, message.as_ref());
+}
+
+/// The last `lines` lines of the log, reaching into `.1` if the active file
+/// was rotated recently.
+pub fn tail(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let current = fs::read_to_string(path)?;
+    let mut out: Vec<String> = current.lines().map(str::to_string).collect();
+    if out.len() < lines {
+        if let Ok(previous) = fs::read_to_string(rotated(path, 1)) {
+            let mut older: Vec<String> = previous.lines().map(str::to_string).collect();
+            older.append(&mut out);
+            out = older;
+        }
+    }
+    let skip = out.len().saturating_sub(lines);
+    Ok(out.split_off(skip))
+}
+
+/// Shift `path.{n}` → `path.{n+1}` (dropping the oldest), then `path` → `path.1`.
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    let _ = fs::remove_file(rotated(path, keep));
+    for n in (1..keep).rev() {
+        let from = rotated(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(path, 1))
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// UTC `YYYY-MM-DDTHH:MM:SSZ`.
+fn timestamp() -> String {
+    time::format_utc(time::epoch_secs(SystemTime::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sshfwd-agent-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn level_filtering_and_parse() {
+        assert_eq!(Level::parse("WARNING"), Some(Level::Warn));
+        assert_eq!(Level::parse("trace"), None);
+
+        let dir = temp_dir("level");
+        let path = dir.join("agent.log");
+        let mut logger = Logger::new(path.clone(), Level::Info, MAX_BYTES);
+        logger.write(Level::Debug, "hidden").unwrap();
+        logger.write(Level::Warn, "shown").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("hidden"));
+        assert!(content.contains(&format!("WARN  [{}] shown", std::process::id())));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rotates_by_size_and_tails_across_files() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("agent.log");
+        // Each line is ~34 bytes, so every write after the first rotates.
+        let mut logger = Logger::new(path.clone(), Level::Debug, 64);
+        for i in 0..10 {
+            logger.write(Level::Info, &format!("line {i}")).unwrap();
+        }
+        assert!(rotated(&path, 1).exists());
+        assert!(rotated(&path, KEEP).exists());
+        assert!(!rotated(&path, KEEP + 1).exists());
+
+        let tail = tail(&path, 2).unwrap();
+        assert_eq!(tail.len(), 2);
+        assert!(tail[0].ends_with("line 8"));
+        assert!(tail[1].ends_with("line 9"));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn follows_rotation_by_another_logger() {
+        let dir = temp_dir("shared");
+        let path = dir.join("agent.log");
+        let mut first = Logger::new(path.clone(), Level::Debug, MAX_BYTES);
+        let mut second = Logger::new(path.clone(), Level::Debug, 64);
+        first.write(Level::Info, "first 0").unwrap();
+        // Rotates the file `first` still has open.
+        second.write(Level::Info, "second 0").unwrap();
+        first.write(Level::Info, "first 1").unwrap();
+
+        let active = fs::read_to_string(&path).unwrap();
+        assert!(active.contains("second 0"), "{active}");
+        assert!(active.contains("first 1"), "{active}");
+        let previous = fs::read_to_string(rotated(&path, 1)).unwrap();
+        assert!(previous.contains("first 0"), "{previous}");
+        assert!(!previous.contains("first 1"), "{previous}");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
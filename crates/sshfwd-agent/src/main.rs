@@ -1,11 +1,40 @@
-use std::io::{self, Write};
+mod bench;
+mod budget;
+mod log;
+mod sandbox;
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+
+use budget::ScanBudget;
 
-use sshfwd_common::scanner::create_scanner;
-use sshfwd_common::types::{AgentError, AgentErrorKind, AgentResponse};
+use sshfwd_common::frame::Framing;
+use sshfwd_common::probe::Prober;
+use sshfwd_common::redact::Redactor;
+use sshfwd_common::scanner::{bindings, create_scanner};
+use sshfwd_common::signature;
+use sshfwd_common::types::{
+    AgentCommand, AgentError, AgentErrorKind, AgentHello, AgentResponse, CommandReply, KillSignal,
+    SCHEMA_VERSION,
+};
 
+/// Default time between scans; `--interval <secs>` overrides it.
 const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_AGENT_NAME: &str = "sshfwd-agent";
+
+/// Set once the hello has announced MessagePack framing; every later
+/// response is a frame.
+static MSGPACK: AtomicBool = AtomicBool::new(false);
+/// Seconds between scans: `--interval`, then whatever `SetInterval` says.
+static INTERVAL_SECS: AtomicU64 = AtomicU64::new(SCAN_INTERVAL.as_secs());
+/// Pids owning a listening port in the latest scan sent to the client: the
+/// only ones `Kill` may signal.
+static LISTENING_PIDS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -15,36 +44,167 @@ fn main() {
         return;
     }
 
+    if args.iter().any(|a| a == "--uninstall") {
+        uninstall(state_dir(&args));
+        return;
+    }
+
+    if args.iter().any(|a| a == "--bench-server") {
+        if let Err(e) = bench::serve() {
+            eprintln!("bench server: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let once = args.iter().any(|a| a == "--once");
+    if let Some(secs) = arg_value(&args, "--interval")
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+    {
+        INTERVAL_SECS.store(secs, Ordering::Relaxed);
+    }
+    let invoking_user = invoking_user();
+    let mut prober = args.iter().any(|a| a == "--probe").then(Prober::default);
+    // `--redact <key,key>` replaces the default keys; an empty list keeps
+    // only URL-password masking.
+    // `--only-mine`: drop other users' ports before they leave the host.
+    let only_mine = args.iter().any(|a| a == "--only-mine");
+    let redactor = arg_value(&args, "--redact")
+        .map_or_else(Redactor::default, |keys| Redactor::new(keys.split(',')));
+    let mut budget = arg_value(&args, "--scan-budget")
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .filter(|&ms| ms > 0)
+        .map(|ms| ScanBudget::new(Duration::from_millis(ms)));
 
-    write_pid_file();
+    if let Some(path) = arg_value(&args, "--log-file") {
+        let level = arg_value(&args, "--log-level")
+            .and_then(log::Level::parse)
+            .unwrap_or(log::Level::Info);
+        log::init(expand_home(path), level, invoking_user);
+    }
+    log::info(format!(
+        "sshfwd-agent {} starting (pid {}, args: {})",
+        env!("CARGO_PKG_VERSION"),
+        std::process::id(),
+        args[1..].join(" ")
+    ));
+
+    if let Err(message) = verify_self() {
+        log::error(&message);
+        emit(&AgentResponse::Error(AgentError {
+            kind: AgentErrorKind::IntegrityFailed,
+            message,
+        }));
+        std::process::exit(1);
+    }
+
+    if let Some(nice) = arg_value(&args, "--nice").and_then(|n| n.parse::<i32>().ok()) {
+        if let Err(message) = budget::renice(nice) {
+            log::warn(&message);
+            eprintln!("{message}");
+        }
+    }
+
+    let pid_file = state_dir(&args).map(|dir| {
+        prune_pid_files(&dir);
+        write_pid_file(&dir, session(&args), invoking_user)
+    });
+
+    // Before any thread exists, so the command reader inherits the sandbox.
+    // stderr lines reach the client as warnings.
+    bindings::remember_interfaces();
+    let needs = sandbox::Needs {
+        file_writes: log::path().is_some(),
+        sockets: prober.is_some(),
+    };
+    if let Err(message) = sandbox::harden(args.iter().any(|a| a == "--seccomp"), needs) {
+        log::warn(&message);
+        eprintln!("{message}");
+    }
+
+    // Rescan commands wake the scan loop early.
+    let (rescan_tx, rescan_rx) = mpsc::channel();
+    if !once {
+        spawn_command_reader(rescan_tx);
+    }
 
     let mut scanner = create_scanner();
-    let stdout = io::stdout();
+    let hello = AgentResponse::Hello(AgentHello {
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: SCHEMA_VERSION,
+        capabilities: scanner.capabilities(),
+        // `--framing msgpack`; anything else (or nothing) stays on JSON.
+        framing: arg_value(&args, "--framing")
+            .and_then(Framing::parse)
+            .unwrap_or_default(),
+    });
+    // A closed stdout is noticed by the first scan's emit.
+    emit(&hello);
 
     loop {
+        let started = Instant::now();
+        let scanned_at = sshfwd_common::time::epoch_secs(SystemTime::now());
+        let shallow = budget.as_mut().is_some_and(ScanBudget::next_is_shallow);
+        scanner.set_shallow(shallow);
         let response = match scanner.scan() {
-            Ok(result) => AgentResponse::Ok(result),
-            Err(e) => AgentResponse::Error(e),
-        };
-
-        let line = match serde_json::to_string(&response) {
-            Ok(json) => json,
+            Ok(mut result) => {
+                if let Some(prober) = prober.as_mut().filter(|_| !shallow) {
+                    prober.annotate(&mut result.ports);
+                }
+                if let Some((uid, _)) = invoking_user {
+                    result.uid = Some(uid);
+                }
+                if only_mine {
+                    // Unattributed ports can't be shown to be ours.
+                    let own = result.uid;
+                    result
+                        .ports
+                        .retain(|p| p.process.as_ref().is_some_and(|proc| Some(proc.uid) == own));
+                }
+                for process in result.ports.iter_mut().filter_map(|p| p.process.as_mut()) {
+                    process.cmdline = redactor.redact(&process.cmdline);
+                    if let Some((uid, _)) = invoking_user {
+                        process.elevated = process.uid != uid;
+                    }
+                }
+                let took = started.elapsed();
+                result.scan_duration_ms = Some(took.as_millis() as u64);
+                result.scanned_at = Some(scanned_at);
+                if let Some(budget) = budget.as_mut().filter(|_| !shallow) {
+                    budget.record(took);
+                }
+                if shallow {
+                    result.warnings.push(format!(
+                        "over the {}ms scan budget: new processes and probes skipped",
+                        budget.as_ref().map_or(0, |b| b.limit().as_millis())
+                    ));
+                }
+                for warning in &result.warnings {
+                    log::warn(format!("scan {}: {warning}", result.scan_index));
+                }
+                log::debug(format!(
+                    "scan {}: {} ports in {took:?}{}",
+                    result.scan_index,
+                    result.ports.len(),
+                    if shallow { " (shallow)" } else { "" }
+                ));
+                *LISTENING_PIDS.lock().unwrap_or_else(|e| e.into_inner()) = result
+                    .ports
+                    .iter()
+                    .filter_map(|p| p.process.as_ref().map(|proc| proc.pid))
+                    .collect();
+                AgentResponse::Ok(result)
+            }
             Err(e) => {
-                let err_response = AgentResponse::Error(AgentError {
-                    kind: AgentErrorKind::ScanFailed,
-                    message: format!("failed to serialize scan result: {e}"),
-                });
-                serde_json::to_string(&err_response).unwrap()
+                log::error(format!("scan failed ({:?}): {}", e.kind, e.message));
+                AgentResponse::Error(e)
             }
         };
 
         // Exit on broken pipe (SSH disconnect)
-        let mut handle = stdout.lock();
-        if writeln!(handle, "{line}").is_err() {
-            break;
-        }
-        if handle.flush().is_err() {
+        if !emit(&response) {
+            log::info("stdout closed, exiting");
             break;
         }
 
@@ -52,18 +212,404 @@ fn main() {
             break;
         }
 
-        thread::sleep(SCAN_INTERVAL);
+        wait_for_next_scan(
+            &rescan_rx,
+            Duration::from_secs(INTERVAL_SECS.load(Ordering::Relaxed)),
+        );
+    }
+
+    if let Some(Some(path)) = pid_file {
+        let _ = std::fs::remove_file(path);
     }
 }
 
-fn write_pid_file() {
-    let dir = match home_dir() {
-        Some(h) => h.join(".sshfwd"),
-        None => return,
+/// Write a response on stdout: a JSON line, or a frame once the hello has
+/// switched to MessagePack. Returns false if stdout is closed.
+fn emit(response: &AgentResponse) -> bool {
+    // Hold the lock across write + flush so responses from the scan loop and
+    // the command reader never interleave, and none slips between the hello
+    // and the switch.
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let framing = if MSGPACK.load(Ordering::Relaxed) {
+        Framing::Msgpack
+    } else {
+        Framing::Json
     };
-    let _ = std::fs::create_dir_all(&dir);
-    let pid_file = dir.join("agent.pid");
-    let _ = std::fs::write(pid_file, std::process::id().to_string());
+    let bytes = framing.encode(response).unwrap_or_else(|e| {
+        let err_response = AgentResponse::Error(AgentError {
+            kind: AgentErrorKind::ScanFailed,
+            message: format!("failed to serialize response: {e}"),
+        });
+        framing.encode(&err_response).unwrap()
+    });
+    let written = handle.write_all(&bytes).is_ok() && handle.flush().is_ok();
+    if let AgentResponse::Hello(hello) = response {
+        MSGPACK.store(hello.framing == Framing::Msgpack, Ordering::Relaxed);
+    }
+    written
+}
+
+/// Sleep until the interval elapses or a `Rescan` arrives. Rescans queued
+/// while scanning collapse into one.
+fn wait_for_next_scan(rescan: &mpsc::Receiver<()>, interval: Duration) {
+    match rescan.recv_timeout(interval) {
+        Ok(()) => while rescan.try_recv().is_ok() {},
+        Err(mpsc::RecvTimeoutError::Timeout) => {}
+        // Command reader gone (stdin closed): plain interval.
+        Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(interval),
+    }
+}
+
+/// Read `AgentCommand`s from stdin (one JSON line each) and reply on stdout.
+/// Stdin closing (client gone) simply ends the reader thread.
+fn spawn_command_reader(rescan: mpsc::Sender<()>) {
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<AgentCommand>(&line) {
+                Ok(cmd) => {
+                    let reply = handle_command(cmd, &rescan);
+                    log::info(format!(
+                        "command {:?}: {} ({})",
+                        reply.command,
+                        if reply.success { "ok" } else { "failed" },
+                        reply.message
+                    ));
+                    AgentResponse::Reply(reply)
+                }
+                Err(e) => {
+                    // Never the line itself: whatever landed on stdin by
+                    // mistake (a password, say) must not reach the log.
+                    log::warn(format!(
+                        "unrecognized command ({} bytes, {:?} error at column {})",
+                        line.len(),
+                        e.classify(),
+                        e.column()
+                    ));
+                    // A tagged command is one from a newer client; anything
+                    // else is garbage.
+                    let tagged = serde_json::from_str::<serde_json::Value>(&line)
+                        .is_ok_and(|v| v.get("command").is_some_and(|c| c.is_string()));
+                    AgentResponse::Error(AgentError {
+                        kind: if tagged {
+                            AgentErrorKind::NotImplemented
+                        } else {
+                            AgentErrorKind::ParseError
+                        },
+                        message: format!("unrecognized command: {e}"),
+                    })
+                }
+            };
+            if !emit(&response) {
+                break;
+            }
+        }
+    });
+}
+
+fn handle_command(command: AgentCommand, rescan: &mpsc::Sender<()>) -> CommandReply {
+    let (success, message, output) = match &command {
+        AgentCommand::Kill { pid, signal } => {
+            let (success, message) = kill_process(*pid, *signal);
+            (success, message, Vec::new())
+        }
+        AgentCommand::LogTail { lines } => match log::path() {
+            Some(path) => match log::tail(&path, *lines) {
+                Ok(output) => (true, path.display().to_string(), output),
+                Err(e) => (
+                    false,
+                    format!("failed to read {}: {e}", path.display()),
+                    Vec::new(),
+                ),
+            },
+            None => (
+                false,
+                "logging is disabled (agent started without --log-file)".to_string(),
+                Vec::new(),
+            ),
+        },
+        AgentCommand::Rescan => match rescan.send(()) {
+            Ok(()) => (true, "rescan scheduled".to_string(), Vec::new()),
+            Err(_) => (false, "scan loop has stopped".to_string(), Vec::new()),
+        },
+        AgentCommand::SetInterval { secs: 0 } => {
+            (false, "interval must be positive".to_string(), Vec::new())
+        }
+        AgentCommand::SetInterval { secs } => {
+            INTERVAL_SECS.store(*secs, Ordering::Relaxed);
+            (true, format!("scanning every {secs}s"), Vec::new())
+        }
+    };
+    CommandReply {
+        command,
+        success,
+        message,
+        output,
+    }
+}
+
+/// `AgentCommand::Kill`: signal a process the client was shown listening,
+/// never an arbitrary pid.
+fn kill_process(pid: u32, signal: KillSignal) -> (bool, String) {
+    if pid <= 1 || pid == std::process::id() {
+        return (false, format!("refusing to signal pid {pid}"));
+    }
+    let listening = LISTENING_PIDS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(&pid);
+    if !listening {
+        return (
+            false,
+            format!("refusing to signal pid {pid}: not listening in the latest scan"),
+        );
+    }
+    send_signal(pid, signal)
+}
+
+fn send_signal(pid: u32, signal: KillSignal) -> (bool, String) {
+    let Ok(raw_pid) = libc::pid_t::try_from(pid) else {
+        return (false, format!("invalid pid {pid}"));
+    };
+    let sig = match signal {
+        KillSignal::Term => libc::SIGTERM,
+        KillSignal::Kill => libc::SIGKILL,
+    };
+    if unsafe { libc::kill(raw_pid, sig) } == 0 {
+        (true, format!("sent {signal} to {pid}"))
+    } else {
+        let err = io::Error::last_os_error();
+        (false, format!("failed to send {signal} to {pid}: {err}"))
+    }
+}
+
+/// Check this binary against `<exe>.sig` when built with a public key, so a
+/// tampered binary refuses to run instead of reporting ports.
+fn verify_self() -> Result<(), String> {
+    let Some(public_key) = signature::AGENT_PUBLIC_KEY else {
+        return Ok(());
+    };
+    let exe = std::env::current_exe().map_err(|e| format!("cannot locate own binary: {e}"))?;
+    let data = std::fs::read(&exe).map_err(|e| format!("cannot read {}: {e}", exe.display()))?;
+    let mut sig_path = exe.clone().into_os_string();
+    sig_path.push(signature::SIGNATURE_SUFFIX);
+    let sig = std::fs::read(&sig_path).ok();
+    signature::verify(public_key, &data, sig.as_deref())
+        .map_err(|e| format!("{} failed verification: {e}", exe.display()))
+}
+
+/// `--uninstall`: stop a running agent and remove everything in the state dir
+/// that the agent or client deployed, plus this binary wherever it lives.
+/// Unrelated files are left in place.
+fn uninstall(dir: Option<PathBuf>) {
+    let Some(dir) = dir else {
+        eprintln!("HOME is not set");
+        std::process::exit(1);
+    };
+    let name = own_name();
+
+    let pid_files = pid_files(&dir);
+    for (pid, _) in &pid_files {
+        if *pid != std::process::id() && is_agent_process(*pid, &name) {
+            let (_, message) = send_signal(*pid, KillSignal::Term);
+            println!("{message}");
+        }
+    }
+
+    let mut targets: Vec<PathBuf> = pid_files.into_iter().map(|(_, path)| path).collect();
+    targets.push(dir.join("agent.pid"));
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name == "agent.log" || file_name.starts_with("agent.log.") {
+                targets.push(path);
+            } else if path.is_dir() {
+                targets.push(path.join(&name));
+                targets.push(path.join(format!("{name}{}", signature::SIGNATURE_SUFFIX)));
+                // Upload temp files: `<name>.tmp` or `<name>.<session>.tmp`.
+                let tmp_prefix = format!("{name}.");
+                for tmp in std::fs::read_dir(&path).into_iter().flatten().flatten() {
+                    let tmp_name = tmp.file_name().to_string_lossy().into_owned();
+                    if tmp_name.starts_with(&tmp_prefix) && tmp_name.ends_with(".tmp") {
+                        targets.push(tmp.path());
+                    }
+                }
+            }
+        }
+    }
+    // The running binary may live in a fallback location (/tmp, $XDG_RUNTIME_DIR)
+    // when the home directory is mounted noexec.
+    let exe = std::env::current_exe().ok();
+    if let Some(exe) = &exe {
+        if !targets.contains(exe) {
+            targets.push(exe.clone());
+            let mut sig = exe.clone().into_os_string();
+            sig.push(signature::SIGNATURE_SUFFIX);
+            targets.push(PathBuf::from(sig));
+        }
+    }
+
+    for path in targets {
+        if std::fs::remove_file(&path).is_ok() {
+            println!("removed {}", path.display());
+            if let Some(parent) = path.parent().filter(|p| *p != dir) {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+    }
+
+    // <base>/<arch>/sshfwd-agent: drop the base too if it is now empty.
+    if let Some(base) = exe.as_deref().and_then(|e| e.parent()?.parent()) {
+        if base != dir && std::fs::remove_dir(base).is_ok() {
+            println!("removed {}", base.display());
+        }
+    }
+
+    // Only succeeds if nothing unrelated lives there.
+    if std::fs::remove_dir(&dir).is_ok() {
+        println!("removed {}", dir.display());
+    }
+}
+
+/// Whether `pid` is a running agent named `name` (guards against a recycled pid).
+fn is_agent_process(pid: u32, name: &str) -> bool {
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).or_else(|_| {
+        std::process::Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "comm="])
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+    });
+    comm.is_ok_and(|c| {
+        let c = c.trim();
+        let c = c.rsplit('/').next().unwrap_or(c);
+        // Linux truncates comm to 15 bytes.
+        c == name || (name.len() > 15 && name.as_bytes().starts_with(c.as_bytes()) && c.len() == 15)
+    })
+}
+
+/// File name this binary was installed under (configurable on the client).
+fn own_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| DEFAULT_AGENT_NAME.to_string())
+}
+
+/// Directory for the pid file (and default log): `--state-dir`, relative to
+/// `$HOME` unless absolute, defaulting to `~/.sshfwd`.
+fn state_dir(args: &[String]) -> Option<PathBuf> {
+    match arg_value(args, "--state-dir") {
+        Some(dir) => {
+            let dir = expand_home(dir);
+            if dir.is_absolute() {
+                Some(dir)
+            } else {
+                home_dir().map(|h| h.join(dir))
+            }
+        }
+        None => home_dir().map(|h| h.join(".sshfwd")),
+    }
+}
+
+/// `--session <id>` from the client, if it is safe to use as a file name.
+fn session(args: &[String]) -> Option<&str> {
+    arg_value(args, "--session").filter(|id| {
+        !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Write `sessions/<id>.pid` (or the shared `agent.pid` for clients that don't
+/// pass `--session`) so each client only ever kills its own agent.
+fn write_pid_file(dir: &Path, session: Option<&str>, owner: Option<(u32, u32)>) -> Option<PathBuf> {
+    let pid_file = match session {
+        Some(id) => dir.join("sessions").join(format!("{id}.pid")),
+        None => dir.join("agent.pid"),
+    };
+    std::fs::create_dir_all(pid_file.parent()?).ok()?;
+    std::fs::write(&pid_file, std::process::id().to_string()).ok()?;
+    if let Some((uid, gid)) = owner {
+        let _ = std::os::unix::fs::chown(&pid_file, Some(uid), Some(gid));
+    }
+    Some(pid_file)
+}
+
+/// `(uid, gid)` of the user who elevated us with sudo or doas, when running
+/// as root that way. Ports owned by anyone else are marked `elevated`.
+fn invoking_user() -> Option<(u32, u32)> {
+    if unsafe { libc::geteuid() } != 0 {
+        return None;
+    }
+    let env_id = |name| std::env::var(name).ok()?.parse::<u32>().ok();
+    if let (Some(uid), Some(gid)) = (env_id("SUDO_UID"), env_id("SUDO_GID")) {
+        return Some((uid, gid));
+    }
+    let user = std::ffi::CString::new(std::env::var("DOAS_USER").ok()?).ok()?;
+    // SAFETY: getpwnam returns null or a pointer to static storage, read at once.
+    let pw = unsafe { libc::getpwnam(user.as_ptr()) };
+    if pw.is_null() {
+        return None;
+    }
+    Some(unsafe { ((*pw).pw_uid, (*pw).pw_gid) })
+}
+
+/// Every pid file in `dir` with the pid it names.
+fn pid_files(dir: &Path) -> Vec<(u32, PathBuf)> {
+    let sessions = std::fs::read_dir(dir.join("sessions"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "pid"));
+    std::iter::once(dir.join("agent.pid"))
+        .chain(sessions)
+        .filter_map(|path| {
+            let pid = std::fs::read_to_string(&path).ok()?.trim().parse().ok()?;
+            Some((pid, path))
+        })
+        .collect()
+}
+
+/// Drop session pid files whose agent is gone (killed, or the host rebooted).
+fn prune_pid_files(dir: &Path) {
+    for (pid, path) in pid_files(dir) {
+        if !is_alive(pid) && path.parent() != Some(dir) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Signal 0 probes for existence; EPERM means alive but owned by someone else.
+fn is_alive(pid: u32) -> bool {
+    let Ok(raw_pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    let rc = unsafe { libc::kill(raw_pid, 0) };
+    rc == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Value following `flag` on the command line.
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Expand a leading `~/`, in case the remote shell passed it through quoted.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
 }
 
 fn home_dir() -> Option<std::path::PathBuf> {
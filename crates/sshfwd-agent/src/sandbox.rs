@@ -0,0 +1,296 @@
+//! Privilege hardening applied once startup (pid file, logger) is done.
+//!
+//! `PR_SET_NO_NEW_PRIVS` is always set on Linux: nothing the agent could
+//! exec may gain privileges through setuid bits or file capabilities. With
+//! `--seccomp` a filter additionally fails (EPERM) every syscall the scan loop
+//! never needs: exec, ptrace, credential changes, mounts, namespaces, kernel
+//! modules. Without `--log-file` it also refuses opening files for writing,
+//! and without `--probe` creating sockets, leaving reads of `/proc` and
+//! writes to stdout. Signals (`Kill` commands) stay allowed.
+
+/// What the agent still needs after startup.
+#[derive(Debug, Clone, Copy)]
+pub struct Needs {
+    /// Log rotation reopens and renames files.
+    pub file_writes: bool,
+    /// `--probe` connects to listening ports.
+    pub sockets: bool,
+}
+
+/// Set `PR_SET_NO_NEW_PRIVS` and, when `seccomp` is set, install the filter.
+/// Must run before any thread is spawned so they all inherit it.
+#[cfg(target_os = "linux")]
+pub fn harden(seccomp: bool, needs: Needs) -> Result<(), String> {
+    // SAFETY: plain prctl calls; the filter outlives the call that copies it.
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(format!(
+            "PR_SET_NO_NEW_PRIVS failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    if !seccomp {
+        return Ok(());
+    }
+    let Some(arch) = linux::AUDIT_ARCH else {
+        return Err("seccomp filter is not available on this architecture".to_string());
+    };
+    let mut filter = linux::filter(arch, needs);
+    let program = libc::sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_mut_ptr(),
+    };
+    let rc = unsafe {
+        libc::prctl(
+            libc::PR_SET_SECCOMP,
+            libc::SECCOMP_MODE_FILTER,
+            &program as *const libc::sock_fprog,
+        )
+    };
+    if rc != 0 {
+        return Err(format!(
+            "installing seccomp filter failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn harden(seccomp: bool, _needs: Needs) -> Result<(), String> {
+    if seccomp {
+        return Err("seccomp is only available on Linux".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use libc::{
+        sock_filter, BPF_ABS, BPF_JEQ, BPF_JMP, BPF_JSET, BPF_K, BPF_LD, BPF_RET, BPF_W, O_CREAT,
+        O_RDWR, O_TRUNC, O_WRONLY, SECCOMP_RET_ALLOW, SECCOMP_RET_ERRNO,
+    };
+
+    use super::Needs;
+
+    /// `AUDIT_ARCH_*` of the architectures agents are built for.
+    pub const AUDIT_ARCH: Option<u32> = if cfg!(target_arch = "x86_64") {
+        Some(0xC000_003E)
+    } else if cfg!(target_arch = "aarch64") {
+        Some(0xC000_00B7)
+    } else if cfg!(target_arch = "arm") {
+        Some(0x4000_0028)
+    } else if cfg!(target_arch = "riscv64") {
+        Some(0xC000_00F3)
+    } else {
+        None
+    };
+
+    // Offsets into `struct seccomp_data` (argument words are little-endian).
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+    const fn arg_low(index: u32) -> u32 {
+        16 + index * 8
+    }
+
+    const DENY: u32 = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+    const WRITE_FLAGS: u32 = (O_WRONLY | O_RDWR | O_CREAT | O_TRUNC) as u32;
+
+    /// Syscalls the agent has no business making once it is scanning.
+    const DENIED: &[libc::c_long] = &[
+        libc::SYS_execve,
+        libc::SYS_execveat,
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_setuid,
+        libc::SYS_setgid,
+        libc::SYS_setreuid,
+        libc::SYS_setregid,
+        libc::SYS_setresuid,
+        libc::SYS_setresgid,
+        libc::SYS_setgroups,
+        libc::SYS_setfsuid,
+        libc::SYS_setfsgid,
+        #[cfg(target_arch = "arm")]
+        libc::SYS_setuid32,
+        #[cfg(target_arch = "arm")]
+        libc::SYS_setgid32,
+        #[cfg(target_arch = "arm")]
+        libc::SYS_setresuid32,
+        #[cfg(target_arch = "arm")]
+        libc::SYS_setresgid32,
+        #[cfg(target_arch = "arm")]
+        libc::SYS_setgroups32,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_reboot,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+    ];
+
+    /// Syscalls creating or renaming files, refused without logging.
+    const FILE_WRITES: &[libc::c_long] = &[
+        #[cfg(any(target_arch = "x86_64", target_arch = "arm"))]
+        libc::SYS_creat,
+        #[cfg(any(target_arch = "x86_64", target_arch = "arm"))]
+        libc::SYS_rename,
+        #[cfg(not(target_arch = "riscv64"))]
+        libc::SYS_renameat,
+        libc::SYS_renameat2,
+        libc::SYS_mkdirat,
+        libc::SYS_openat2,
+    ];
+
+    fn load(offset: u32) -> sock_filter {
+        stmt((BPF_LD | BPF_W | BPF_ABS) as u16, offset)
+    }
+
+    fn ret(action: u32) -> sock_filter {
+        stmt(BPF_RET as u16 | BPF_K as u16, action)
+    }
+
+    fn stmt(code: u16, k: u32) -> sock_filter {
+        sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    /// `if (acc <op> k) fall through to the next instruction, else skip one`.
+    fn jump_unless(op: u32, k: u32) -> sock_filter {
+        sock_filter {
+            code: (BPF_JMP | op | BPF_K) as u16,
+            jt: 0,
+            jf: 1,
+            k,
+        }
+    }
+
+    /// Deny `nr` (the accumulator holds the syscall number).
+    fn deny_nr(program: &mut Vec<sock_filter>, nr: libc::c_long) {
+        program.push(jump_unless(BPF_JEQ, nr as u32));
+        program.push(ret(DENY));
+    }
+
+    /// Deny `nr` when its `flags_arg` has any write flag set.
+    fn deny_write_open(program: &mut Vec<sock_filter>, nr: libc::c_long, flags_arg: u32) {
+        program.push(load(NR));
+        program.push(sock_filter {
+            code: (BPF_JMP | BPF_JEQ | BPF_K) as u16,
+            jt: 0,
+            jf: 3,
+            k: nr as u32,
+        });
+        program.push(load(arg_low(flags_arg)));
+        program.push(jump_unless(BPF_JSET, WRITE_FLAGS));
+        program.push(ret(DENY));
+    }
+
+    pub fn filter(arch: u32, needs: Needs) -> Vec<sock_filter> {
+        let mut program = vec![
+            load(ARCH),
+            // Skip the deny when the arch matches.
+            sock_filter {
+                code: (BPF_JMP | BPF_JEQ | BPF_K) as u16,
+                jt: 1,
+                jf: 0,
+                k: arch,
+            },
+            ret(DENY),
+            load(NR),
+        ];
+        // x32 syscalls share the x86_64 arch value, flagged in the number.
+        if cfg!(target_arch = "x86_64") {
+            program.push(sock_filter {
+                code: (BPF_JMP | libc::BPF_JGE | BPF_K) as u16,
+                jt: 0,
+                jf: 1,
+                k: 0x4000_0000,
+            });
+            program.push(ret(DENY));
+        }
+        for &nr in DENIED {
+            deny_nr(&mut program, nr);
+        }
+        if !needs.sockets {
+            deny_nr(&mut program, libc::SYS_socket);
+        }
+        if !needs.file_writes {
+            for &nr in FILE_WRITES {
+                deny_nr(&mut program, nr);
+            }
+            deny_write_open(&mut program, libc::SYS_openat, 2);
+            #[cfg(any(target_arch = "x86_64", target_arch = "arm"))]
+            deny_write_open(&mut program, libc::SYS_open, 1);
+        }
+        program.push(ret(SECCOMP_RET_ALLOW));
+        program
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_jumps_stay_in_bounds() {
+        let needs = Needs {
+            file_writes: false,
+            sockets: false,
+        };
+        let program = linux::filter(0xC000_003E, needs);
+        assert!(program.len() < usize::from(u16::MAX));
+        for (i, insn) in program.iter().enumerate() {
+            if insn.code & 0x07 == libc::BPF_JMP as u16 {
+                assert!(i + 1 + usize::from(insn.jt.max(insn.jf)) < program.len());
+            }
+        }
+        let last = program.last().unwrap();
+        assert_eq!(last.k, libc::SECCOMP_RET_ALLOW);
+
+        let relaxed = linux::filter(
+            0xC000_003E,
+            Needs {
+                file_writes: true,
+                sockets: true,
+            },
+        );
+        assert!(relaxed.len() < program.len());
+    }
+
+    #[test]
+    fn interfaces_are_remembered_through_the_sandbox() {
+        use sshfwd_common::scanner::bindings;
+
+        let before = bindings::local_interfaces();
+        bindings::remember_interfaces();
+        // Filters and no_new_privs apply to this test's thread only.
+        std::thread::spawn(move || {
+            let needs = Needs {
+                file_writes: true,
+                sockets: false,
+            };
+            harden(true, needs).unwrap();
+            assert!(std::net::UdpSocket::bind("127.0.0.1:0").is_err());
+            assert_eq!(bindings::local_interfaces(), before);
+        })
+        .join()
+        .unwrap();
+    }
+}
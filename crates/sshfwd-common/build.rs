@@ -0,0 +1,22 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Written by the release workflow before publishing, so crates.io builds
+/// verify agent signatures without `SSHFWD_AGENT_PUBKEY` in the environment.
+const PUBKEY_FILE: &str = "agent-pubkey.hex";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=SSHFWD_AGENT_PUBKEY");
+    println!("cargo:rerun-if-changed={PUBKEY_FILE}");
+    if env::var_os("SSHFWD_AGENT_PUBKEY").is_some() {
+        return;
+    }
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    if let Ok(key) = fs::read_to_string(manifest_dir.join(PUBKEY_FILE)) {
+        let key = key.trim();
+        if !key.is_empty() {
+            println!("cargo:rustc-env=SSHFWD_AGENT_PUBKEY={key}");
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Wire format between `sshfwd bench` and the agent's `--bench-server`.
+//!
+//! The agent prints the loopback port it listens on as a single line on
+//! stdout. Every connection then starts with a `HEADER_LEN`-byte header: one
+//! op byte and a big-endian `u64` length.
+//!
+//! - `ECHO`: the rest of the connection is echoed back (length unused).
+//! - `UPLOAD`: the client sends `length` bytes; the agent answers with the
+//!   number it received as a big-endian `u64`.
+//! - `DOWNLOAD`: the agent sends `length` bytes.
+
+pub const ECHO: u8 = b'e';
+pub const UPLOAD: u8 = b'u';
+pub const DOWNLOAD: u8 = b'd';
+
+pub const HEADER_LEN: usize = 9;
+
+pub fn header(op: u8, length: u64) -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[0] = op;
+    header[1..].copy_from_slice(&length.to_be_bytes());
+    header
+}
+
+pub fn parse_header(header: &[u8; HEADER_LEN]) -> (u8, u64) {
+    let mut length = [0; 8];
+    length.copy_from_slice(&header[1..]);
+    (header[0], u64::from_be_bytes(length))
+}
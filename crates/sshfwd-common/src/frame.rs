@@ -0,0 +1,241 @@
+//! Framing of the agent's stdout.
+//!
+//! JSON lines are the default: easy to read in a terminal or a log. The
+//! client may ask for MessagePack frames instead (`--framing msgpack`),
+//! which are smaller for large scans and survive stray output: every frame
+//! starts with `MAGIC`, so text a shell profile or a library prints between
+//! frames is read around instead of corrupting the stream. The agent's hello
+//! is always a JSON line and says which framing follows it, so an agent that
+//! doesn't know the flag simply stays on JSON.
+
+use serde::{Deserialize, Serialize};
+
+/// Starts every frame. 0xC1 is never used by MessagePack and can't start a
+/// UTF-8 character, so text output never contains it.
+pub const MAGIC: [u8; 4] = [0xC1, b's', b'f', b'w'];
+/// Frames above this size are taken as a corrupt stream, not a scan.
+pub const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+const HEADER: usize = MAGIC.len() + 4;
+
+/// How the agent writes responses after its hello.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Framing {
+    /// One JSON object per line.
+    #[default]
+    Json,
+    /// `MAGIC`, a big-endian `u32` length, then a MessagePack map.
+    Msgpack,
+}
+
+impl Framing {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "msgpack" => Some(Self::Msgpack),
+            _ => None,
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        *self == Self::Json
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Msgpack => "msgpack",
+        }
+    }
+
+    /// `value` as this framing writes it, terminator or header included.
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => {
+                let mut line = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+                line.push(b'\n');
+                Ok(line)
+            }
+            Self::Msgpack => {
+                // Named fields, so `#[serde(default)]` keeps working across
+                // versions as it does for JSON.
+                let payload = rmp_serde::to_vec_named(value).map_err(|e| e.to_string())?;
+                let len = u32::try_from(payload.len()).map_err(|e| e.to_string())?;
+                let mut frame = Vec::with_capacity(HEADER + payload.len());
+                frame.extend_from_slice(&MAGIC);
+                frame.extend_from_slice(&len.to_be_bytes());
+                frame.extend_from_slice(&payload);
+                Ok(frame)
+            }
+        }
+    }
+}
+
+/// A unit read off the agent's stdout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    /// A line of text: a JSON response, or stray output.
+    Line(String),
+    /// A MessagePack payload.
+    Frame(Vec<u8>),
+}
+
+/// Splits the agent's stdout into `Chunk`s as bytes arrive. The framing can
+/// change mid-stream (after the hello); buffered bytes are read with the new
+/// one.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    framing: Framing,
+    buf: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next complete chunk, if one is buffered.
+    pub fn next_chunk(&mut self) -> Result<Option<Chunk>, String> {
+        let newline = self.buf.iter().position(|&b| b == b'\n');
+        let magic = match self.framing {
+            Framing::Json => None,
+            Framing::Msgpack => self.buf.windows(MAGIC.len()).position(|w| w == MAGIC),
+        };
+        match (magic, newline) {
+            (Some(0), _) => self.frame(),
+            // Text before the next frame: a line of it, or what's left.
+            (Some(at), Some(end)) if end < at => Ok(Some(self.line(end + 1))),
+            (Some(at), _) => Ok(Some(self.line(at))),
+            (None, Some(end)) => Ok(Some(self.line(end + 1))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Whatever is left once stdout has closed, as a line.
+    pub fn finish(&mut self) -> Option<Chunk> {
+        (!self.buf.is_empty()).then(|| self.line(self.buf.len()))
+    }
+
+    fn frame(&mut self) -> Result<Option<Chunk>, String> {
+        let Some(header) = self.buf.get(MAGIC.len()..HEADER) else {
+            return Ok(None);
+        };
+        let len = u32::from_be_bytes(header.try_into().expect("4 bytes")) as usize;
+        if len > MAX_FRAME {
+            return Err(format!("frame of {len} bytes exceeds {MAX_FRAME}"));
+        }
+        if self.buf.len() < HEADER + len {
+            return Ok(None);
+        }
+        let payload = self.buf[HEADER..HEADER + len].to_vec();
+        self.buf.drain(..HEADER + len);
+        Ok(Some(Chunk::Frame(payload)))
+    }
+
+    fn line(&mut self, end: usize) -> Chunk {
+        let line: Vec<u8> = self.buf.drain(..end).collect();
+        let line = String::from_utf8_lossy(&line);
+        Chunk::Line(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+}
+
+/// Decode a frame's payload.
+pub fn decode<T: for<'de> Deserialize<'de>>(payload: &[u8]) -> Result<T, String> {
+    rmp_serde::from_slice(payload).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        AgentHello, AgentResponse, ListeningPort, Protocol, ScanResult, SCHEMA_VERSION,
+    };
+
+    fn scan() -> AgentResponse {
+        AgentResponse::Ok(ScanResult {
+            agent_version: "0.3.1".into(),
+            hostname: "box".into(),
+            username: "dev".into(),
+            is_root: false,
+            uid: Some(1000),
+            ports: vec![ListeningPort {
+                protocol: Protocol::Tcp6,
+                local_addr: "::".into(),
+                port: 8080,
+                process: None,
+                probe: None,
+                bindings: Vec::new(),
+                stats: None,
+            }],
+            warnings: Vec::new(),
+            scan_index: 4,
+            scan_duration_ms: Some(12),
+            scanned_at: None,
+            host: None,
+        })
+    }
+
+    #[test]
+    fn msgpack_frames_round_trip_between_stray_output() {
+        let hello = AgentResponse::Hello(AgentHello {
+            agent_version: "0.3.1".into(),
+            schema_version: SCHEMA_VERSION,
+            capabilities: vec!["tcp".into()],
+            framing: Framing::Msgpack,
+        });
+        let mut stream = Framing::Json.encode(&hello).unwrap();
+        stream.extend_from_slice(b"motd: welcome\npartial");
+        stream.extend(Framing::Msgpack.encode(&scan()).unwrap());
+
+        let mut decoder = Decoder::default();
+        // Byte by byte, as the worst case of arrival.
+        let mut chunks = Vec::new();
+        for byte in stream {
+            decoder.push(&[byte]);
+            while let Some(chunk) = decoder.next_chunk().unwrap() {
+                if chunks.is_empty() {
+                    decoder.set_framing(Framing::Msgpack);
+                }
+                chunks.push(chunk);
+            }
+        }
+        assert_eq!(chunks.len(), 4);
+        assert!(
+            matches!(&chunks[0], Chunk::Line(line) if line.contains("\"framing\":\"msgpack\""))
+        );
+        assert_eq!(chunks[1], Chunk::Line("motd: welcome".into()));
+        assert_eq!(chunks[2], Chunk::Line("partial".into()));
+        let Chunk::Frame(payload) = &chunks[3] else {
+            panic!("expected a frame: {:?}", chunks[3]);
+        };
+        assert_eq!(decode::<AgentResponse>(payload).unwrap(), scan());
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn json_framing_is_lines() {
+        let mut decoder = Decoder::default();
+        decoder.push(&Framing::Json.encode(&scan()).unwrap());
+        decoder.push(b"tail");
+        let Some(Chunk::Line(line)) = decoder.next_chunk().unwrap() else {
+            panic!("expected a line");
+        };
+        assert_eq!(
+            serde_json::from_str::<AgentResponse>(&line).unwrap(),
+            scan()
+        );
+        assert_eq!(decoder.next_chunk().unwrap(), None);
+        assert_eq!(decoder.finish(), Some(Chunk::Line("tail".into())));
+
+        let mut decoder = Decoder::default();
+        decoder.set_framing(Framing::Msgpack);
+        decoder.push(&MAGIC);
+        decoder.push(&u32::MAX.to_be_bytes());
+        assert!(decoder.next_chunk().is_err());
+    }
+}
@@ -1,2 +1,10 @@
+pub mod base64;
+pub mod bench;
+pub mod frame;
+pub mod probe;
+pub mod redact;
 pub mod scanner;
+pub mod signature;
+pub mod time;
+pub mod token;
 pub mod types;
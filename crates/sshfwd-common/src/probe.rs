@@ -0,0 +1,436 @@
+//! Lightweight service probing of listening ports from the host itself.
+//!
+//! Each port is probed once per (port, pid) pair: wait briefly for a
+//! server-first banner, otherwise send a `HEAD` request, and fall back to a
+//! TLS 1.2 ClientHello to learn whether the port speaks TLS and which ALPN
+//! protocol it selects.
+//!
+//! Probes run on their own threads. A scan waits for them only up to a
+//! fixed budget; slower answers are attached on a later scan.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::types::{ListeningPort, ServiceProbe};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+const READ_TIMEOUT: Duration = Duration::from_millis(400);
+/// At most this many probes are in flight at once.
+const MAX_PARALLEL: usize = 16;
+const MAX_BANNER_LEN: usize = 80;
+/// How long one scan waits for probes. A probe can take several read
+/// timeouts, and the scan must still answer well inside the client's
+/// discovery timeout.
+const SCAN_BUDGET: Duration = Duration::from_secs(1);
+
+type Key = (u16, Option<u32>);
+
+/// Caches probe results so only newly appeared listeners are contacted.
+pub struct Prober {
+    cache: HashMap<Key, Option<ServiceProbe>>,
+    in_flight: HashSet<Key>,
+    budget: Duration,
+    tx: Sender<(Key, Option<ServiceProbe>)>,
+    rx: Receiver<(Key, Option<ServiceProbe>)>,
+}
+
+impl Default for Prober {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            cache: HashMap::new(),
+            in_flight: HashSet::new(),
+            budget: SCAN_BUDGET,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl Prober {
+    /// Fill `ListeningPort::probe` for every port, starting probes for unseen
+    /// ones. Returns within the scan budget; ports whose probe hasn't
+    /// answered by then go out without one.
+    pub fn annotate(&mut self, ports: &mut [ListeningPort]) {
+        let key = |p: &ListeningPort| (p.port, p.process.as_ref().map(|proc| proc.pid));
+        let deadline = Instant::now() + self.budget;
+
+        // One probe per (port, pid) even when it listens on both tcp and tcp6.
+        let pending: HashMap<Key, SocketAddr> = ports
+            .iter()
+            .filter(|p| !self.cache.contains_key(&key(p)) && !self.in_flight.contains(&key(p)))
+            .map(|p| (key(p), probe_addr(&p.local_addr, p.port)))
+            .collect();
+        let room = MAX_PARALLEL.saturating_sub(self.in_flight.len());
+        for (key, addr) in pending.into_iter().take(room) {
+            let tx = self.tx.clone();
+            let spawned = std::thread::Builder::new()
+                .name("probe".to_string())
+                .spawn(move || {
+                    tx.send((key, probe(addr))).ok();
+                });
+            if spawned.is_ok() {
+                self.in_flight.insert(key);
+            }
+        }
+
+        while !self.in_flight.is_empty() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.rx.recv_timeout(left) {
+                Ok((key, result)) => {
+                    self.in_flight.remove(&key);
+                    self.cache.insert(key, result);
+                }
+                Err(_) => break,
+            }
+        }
+
+        // Forget listeners that went away so a restarted service is re-probed.
+        let live: HashSet<_> = ports.iter().map(key).collect();
+        self.cache.retain(|k, _| live.contains(k));
+
+        for port in ports.iter_mut() {
+            port.probe = self.cache.get(&key(port)).cloned().flatten();
+        }
+    }
+}
+
+/// Wildcard binds are reached via loopback; specific binds via their address.
+fn probe_addr(local_addr: &str, port: u16) -> SocketAddr {
+    let ip = match local_addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) if v4.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Ok(IpAddr::V6(v6)) if v6.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        Ok(ip) => ip,
+        Err(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    };
+    SocketAddr::new(ip, port)
+}
+
+fn connect(addr: SocketAddr) -> Option<TcpStream> {
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(READ_TIMEOUT)).ok()?;
+    Some(stream)
+}
+
+/// Read whatever arrives within the read timeout (None on timeout/close).
+fn read_some(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let mut buf = [0u8; 1024];
+    match stream.read(&mut buf) {
+        Ok(n) if n > 0 => Some(buf[..n].to_vec()),
+        _ => None,
+    }
+}
+
+fn probe(addr: SocketAddr) -> Option<ServiceProbe> {
+    let mut stream = connect(addr)?;
+
+    // 1. Server-first protocols (SSH, SMTP, FTP, MySQL, ...) greet immediately.
+    if let Some(banner) = read_some(&mut stream) {
+        return Some(classify_banner(&banner));
+    }
+
+    // 2. Client-first: try plain HTTP.
+    let request = b"HEAD / HTTP/1.0\r\nHost: localhost\r\nUser-Agent: sshfwd-agent\r\n\r\n";
+    if stream.write_all(request).is_ok() {
+        if let Some(reply) = read_some(&mut stream) {
+            if !is_tls_record(&reply) && !is_plain_http_to_https(&reply) {
+                return Some(classify_response(&reply));
+            }
+        }
+    }
+
+    // 3. TLS on a fresh connection (the HEAD request poisoned the first one).
+    let mut stream = connect(addr)?;
+    stream.write_all(&client_hello()).ok()?;
+    let reply = read_some(&mut stream)?;
+    if reply.first() == Some(&0x16) {
+        return Some(ServiceProbe::Tls {
+            alpn: parse_server_hello_alpn(&reply),
+        });
+    }
+    is_tls_record(&reply).then_some(ServiceProbe::Tls { alpn: None })
+}
+
+/// Describe an unsolicited greeting.
+fn classify_banner(data: &[u8]) -> ServiceProbe {
+    if data.starts_with(b"HTTP/") {
+        return classify_response(data);
+    }
+    let first_line = data.split(|b| *b == b'\n').next().unwrap_or_default();
+    let text = String::from_utf8_lossy(first_line).trim().to_string();
+    let printable = !text.is_empty() && text.chars().all(|c| !c.is_control());
+    ServiceProbe::Banner {
+        text: if printable {
+            truncate(&text)
+        } else {
+            format!("binary ({} bytes)", data.len())
+        },
+    }
+}
+
+/// Describe a reply to our HTTP request.
+fn classify_response(data: &[u8]) -> ServiceProbe {
+    if data.starts_with(b"HTTP/") {
+        let status = data.split(|b| *b == b'\n').next().unwrap_or_default();
+        ServiceProbe::Http {
+            status: String::from_utf8_lossy(status).trim().to_string(),
+        }
+    } else {
+        classify_banner(data)
+    }
+}
+
+/// TLS record header: content type (handshake/alert) + major version 3.
+fn is_tls_record(data: &[u8]) -> bool {
+    matches!(data, [0x15 | 0x16, 0x03, ..])
+}
+
+/// nginx and friends answer plain HTTP on a TLS port with a 400 explaining so.
+fn is_plain_http_to_https(data: &[u8]) -> bool {
+    String::from_utf8_lossy(data).contains("plain HTTP request was sent to HTTPS port")
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() > MAX_BANNER_LEN {
+        let cut: String = text.chars().take(MAX_BANNER_LEN - 1).collect();
+        format!("{cut}…")
+    } else {
+        text.to_string()
+    }
+}
+
+/// A minimal TLS 1.2 ClientHello offering common ECDHE suites and ALPN
+/// `h2`/`http/1.1`. TLS 1.3 is deliberately not offered: its ALPN answer is
+/// encrypted, whereas a 1.2 ServerHello carries it in the clear.
+fn client_hello() -> Vec<u8> {
+    fn ext(kind: u16, body: &[u8]) -> Vec<u8> {
+        let mut out = kind.to_be_bytes().to_vec();
+        out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    let sni_name = b"localhost";
+    let mut sni = ((sni_name.len() + 3) as u16).to_be_bytes().to_vec();
+    sni.push(0); // host_name
+    sni.extend_from_slice(&(sni_name.len() as u16).to_be_bytes());
+    sni.extend_from_slice(sni_name);
+
+    let mut alpn_list = Vec::new();
+    for proto in [&b"h2"[..], b"http/1.1"] {
+        alpn_list.push(proto.len() as u8);
+        alpn_list.extend_from_slice(proto);
+    }
+    let mut alpn = (alpn_list.len() as u16).to_be_bytes().to_vec();
+    alpn.extend_from_slice(&alpn_list);
+
+    let mut extensions = Vec::new();
+    extensions.extend(ext(0x0000, &sni));
+    extensions.extend(ext(
+        0x000a,
+        &[0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18],
+    )); // groups
+    extensions.extend(ext(0x000b, &[0x01, 0x00])); // ec_point_formats: uncompressed
+    extensions.extend(ext(
+        0x000d,
+        &[
+            0x00, 0x0c, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03, 0x08, 0x05, 0x05, 0x01,
+        ],
+    )); // signature_algorithms
+    extensions.extend(ext(0x0010, &alpn));
+    extensions.extend(ext(0xff01, &[0x00])); // renegotiation_info
+
+    let suites: &[u16] = &[
+        0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0x009c, 0x002f,
+    ];
+
+    let mut hello = vec![0x03, 0x03]; // TLS 1.2
+    hello.extend_from_slice(&[0x5a; 32]); // random (not security relevant here)
+    hello.push(0); // session id
+    hello.extend_from_slice(&((suites.len() * 2) as u16).to_be_bytes());
+    for suite in suites {
+        hello.extend_from_slice(&suite.to_be_bytes());
+    }
+    hello.extend_from_slice(&[0x01, 0x00]); // compression: null
+    hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    hello.extend(extensions);
+
+    let mut handshake = vec![0x01]; // ClientHello
+    handshake.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend(hello);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend(handshake);
+    record
+}
+
+/// Extract the ALPN protocol selected in a ServerHello, if any.
+fn parse_server_hello_alpn(data: &[u8]) -> Option<String> {
+    fn take<'a>(data: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+        if data.len() < n {
+            return None;
+        }
+        let (head, tail) = data.split_at(n);
+        *data = tail;
+        Some(head)
+    }
+    fn u16_at(b: &[u8]) -> usize {
+        u16::from_be_bytes([b[0], b[1]]) as usize
+    }
+
+    let mut d = data;
+    let header = take(&mut d, 5)?; // record header
+    if header[0] != 0x16 {
+        return None;
+    }
+    let hs = take(&mut d, 4)?; // handshake header
+    if hs[0] != 0x02 {
+        return None;
+    }
+    take(&mut d, 2 + 32)?; // version + random
+    let sid_len = take(&mut d, 1)?[0] as usize;
+    take(&mut d, sid_len + 2 + 1)?; // session id, cipher, compression
+    let ext_len = u16_at(take(&mut d, 2)?);
+    let mut exts = take(&mut d, ext_len)?;
+    while !exts.is_empty() {
+        let kind = u16_at(take(&mut exts, 2)?);
+        let len = u16_at(take(&mut exts, 2)?);
+        let mut body = take(&mut exts, len)?;
+        if kind == 0x0010 {
+            take(&mut body, 2)?; // list length
+            let name_len = take(&mut body, 1)?[0] as usize;
+            return Some(String::from_utf8_lossy(take(&mut body, name_len)?).into_owned());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banner_is_first_line() {
+        let probe = classify_banner(b"SSH-2.0-OpenSSH_9.6\r\n");
+        assert_eq!(
+            probe,
+            ServiceProbe::Banner {
+                text: "SSH-2.0-OpenSSH_9.6".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn binary_banner_is_summarized() {
+        let probe = classify_banner(&[0x4a, 0x00, 0x00, 0x00, 0x0a, 0x38]);
+        assert_eq!(
+            probe,
+            ServiceProbe::Banner {
+                text: "binary (6 bytes)".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn http_status_line() {
+        let probe = classify_response(b"HTTP/1.1 404 Not Found\r\nServer: x\r\n\r\n");
+        assert_eq!(
+            probe,
+            ServiceProbe::Http {
+                status: "HTTP/1.1 404 Not Found".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn tls_record_detection() {
+        assert!(is_tls_record(&[0x15, 0x03, 0x01, 0x00, 0x02]));
+        assert!(is_tls_record(&[0x16, 0x03, 0x03]));
+        assert!(!is_tls_record(b"HTTP/1.1"));
+    }
+
+    #[test]
+    fn client_hello_is_well_formed() {
+        let hello = client_hello();
+        assert_eq!(&hello[..3], &[0x16, 0x03, 0x01]);
+        assert_eq!(
+            u16::from_be_bytes([hello[3], hello[4]]) as usize,
+            hello.len() - 5
+        );
+        assert_eq!(hello[5], 0x01);
+    }
+
+    #[test]
+    fn server_hello_alpn() {
+        let alpn_ext = [0x00, 0x10, 0x00, 0x05, 0x00, 0x03, 0x02, b'h', b'2'];
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0u8; 32]);
+        body.push(0); // session id
+        body.extend_from_slice(&[0xc0, 0x2f, 0x00]);
+        body.extend_from_slice(&(alpn_ext.len() as u16).to_be_bytes());
+        body.extend_from_slice(&alpn_ext);
+        let mut hs = vec![0x02];
+        hs.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        hs.extend(body);
+        let mut record = vec![0x16, 0x03, 0x03];
+        record.extend_from_slice(&(hs.len() as u16).to_be_bytes());
+        record.extend(hs);
+
+        assert_eq!(parse_server_hello_alpn(&record).as_deref(), Some("h2"));
+        assert_eq!(parse_server_hello_alpn(&record[..20]), None);
+    }
+
+    #[test]
+    fn slow_probes_land_on_a_later_scan() {
+        use crate::types::Protocol;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            let _ = stream.write_all(b"SSH-2.0-slow\r\n");
+            std::thread::sleep(Duration::from_secs(1));
+        });
+        let mut ports = vec![ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: "127.0.0.1".to_string(),
+            port,
+            process: None,
+            bindings: Vec::new(),
+            probe: None,
+            stats: None,
+        }];
+        let mut prober = Prober {
+            budget: Duration::from_millis(50),
+            ..Prober::default()
+        };
+
+        let started = Instant::now();
+        prober.annotate(&mut ports);
+        assert!(started.elapsed() < Duration::from_millis(150));
+        assert_eq!(ports[0].probe, None);
+
+        std::thread::sleep(Duration::from_millis(400));
+        prober.annotate(&mut ports);
+        assert_eq!(
+            ports[0].probe,
+            Some(ServiceProbe::Banner {
+                text: "SSH-2.0-slow".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn wildcard_binds_probe_loopback() {
+        assert_eq!(probe_addr("0.0.0.0", 80).to_string(), "127.0.0.1:80");
+        assert_eq!(probe_addr("::", 80).to_string(), "[::1]:80");
+        assert_eq!(probe_addr("10.0.0.5", 80).to_string(), "10.0.0.5:80");
+    }
+}
@@ -0,0 +1,193 @@
+//! A process listening on several addresses with one port has a socket per
+//! address; fold them into one `ListeningPort` carrying every `Binding`, each
+//! named after the interface that owns its address.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+
+use crate::types::{Binding, ListeningPort};
+
+/// Interface name by local address.
+pub type Interfaces = HashMap<IpAddr, String>;
+
+/// Merge ports with the same protocol, number and process into one, with
+/// `bindings` widest first and `local_addr` the widest of them.
+pub fn group(ports: Vec<ListeningPort>, interfaces: &Interfaces) -> Vec<ListeningPort> {
+    let mut index = HashMap::new();
+    let mut grouped: Vec<ListeningPort> = Vec::new();
+    for port in ports {
+        let key = (
+            port.protocol,
+            port.port,
+            port.process.as_ref().map(|p| p.pid),
+        );
+        let binding = Binding {
+            interface: interface_of(&port.local_addr, interfaces),
+            addr: port.local_addr.clone(),
+        };
+        match index.get(&key) {
+            Some(&i) => {
+                let existing: &mut ListeningPort = &mut grouped[i];
+                existing.bindings.push(binding);
+                if existing.probe.is_none() {
+                    existing.probe = port.probe;
+                }
+                // Established counts are per port already; only the accept
+                // queues are per socket.
+                if let (Some(total), Some(stats)) = (&mut existing.stats, port.stats) {
+                    total.accept_queue += stats.accept_queue;
+                    total.backlog += stats.backlog;
+                }
+            }
+            None => {
+                index.insert(key, grouped.len());
+                grouped.push(ListeningPort {
+                    bindings: vec![binding],
+                    ..port
+                });
+            }
+        }
+    }
+    for port in &mut grouped {
+        port.bindings
+            .sort_by_key(|b| (width(&b.addr), b.addr.clone()));
+        port.bindings.dedup_by(|a, b| a.addr == b.addr);
+        port.local_addr = port.bindings[0].addr.clone();
+    }
+    grouped
+}
+
+/// Wildcards first, loopback last.
+fn width(addr: &str) -> u8 {
+    match parse(addr) {
+        Some(ip) if ip.is_unspecified() => 0,
+        Some(ip) if ip.is_loopback() => 2,
+        _ => 1,
+    }
+}
+
+/// `addr` as an IP, with IPv4-mapped IPv6 addresses as IPv4.
+fn parse(addr: &str) -> Option<IpAddr> {
+    match addr.parse::<IpAddr>().ok()? {
+        IpAddr::V6(v6) => Some(v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4)),
+        ip => Some(ip),
+    }
+}
+
+/// The interface owning `addr`; wildcards belong to none in particular.
+fn interface_of(addr: &str, interfaces: &Interfaces) -> Option<String> {
+    let ip = parse(addr).filter(|ip| !ip.is_unspecified())?;
+    interfaces.get(&ip).cloned()
+}
+
+/// Interfaces listed by `remember_interfaces`.
+static REMEMBERED: OnceLock<Interfaces> = OnceLock::new();
+
+/// List the interfaces now, for `local_interfaces` to fall back on once
+/// they can't be listed any more: `getifaddrs` needs a netlink socket,
+/// which the agent's seccomp filter refuses without `--probe`.
+pub fn remember_interfaces() {
+    REMEMBERED.get_or_init(|| list_interfaces().unwrap_or_default());
+}
+
+/// The host's interface addresses: listed afresh, else as remembered, else
+/// none.
+pub fn local_interfaces() -> Interfaces {
+    list_interfaces()
+        .or_else(|| REMEMBERED.get().cloned())
+        .unwrap_or_default()
+}
+
+fn list_interfaces() -> Option<Interfaces> {
+    let mut interfaces = Interfaces::new();
+    let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: the list getifaddrs allocates is only read before freeifaddrs,
+    // and each `ifa_addr` is cast to the sockaddr type its family names.
+    unsafe {
+        if libc::getifaddrs(&mut head) != 0 {
+            return None;
+        }
+        let mut cursor = head;
+        while let Some(entry) = cursor.as_ref() {
+            cursor = entry.ifa_next;
+            let Some(addr) = entry.ifa_addr.as_ref() else {
+                continue;
+            };
+            let ip = match i32::from(addr.sa_family) {
+                libc::AF_INET => {
+                    let sin = &*(entry.ifa_addr as *const libc::sockaddr_in);
+                    IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)))
+                }
+                libc::AF_INET6 => {
+                    let sin6 = &*(entry.ifa_addr as *const libc::sockaddr_in6);
+                    IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr))
+                }
+                _ => continue,
+            };
+            let name = std::ffi::CStr::from_ptr(entry.ifa_name).to_string_lossy();
+            interfaces.entry(ip).or_insert_with(|| name.into_owned());
+        }
+        libc::freeifaddrs(head);
+    }
+    Some(interfaces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ConnectionStats, Protocol};
+
+    fn socket(addr: &str, port: u16, accept_queue: u32) -> ListeningPort {
+        ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: addr.into(),
+            port,
+            process: None,
+            probe: None,
+            bindings: Vec::new(),
+            stats: Some(ConnectionStats {
+                accept_queue,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn sockets_of_one_port_become_its_bindings() {
+        let interfaces = Interfaces::from([
+            ("127.0.0.1".parse().unwrap(), "lo".to_string()),
+            ("10.0.0.5".parse().unwrap(), "eth0".to_string()),
+        ]);
+        let ports = group(
+            vec![
+                socket("127.0.0.1", 8080, 1),
+                socket("22.0.0.1", 22, 0),
+                socket("10.0.0.5", 8080, 2),
+            ],
+            &interfaces,
+        );
+        assert_eq!(ports.len(), 2);
+        let web = &ports[0];
+        assert_eq!(web.local_addr, "10.0.0.5");
+        let bindings: Vec<String> = web.bindings.iter().map(ToString::to_string).collect();
+        assert_eq!(bindings, ["eth0:10.0.0.5", "lo:127.0.0.1"]);
+        assert_eq!(web.stats.unwrap().accept_queue, 3);
+        assert_eq!(ports[1].bindings()[0].interface, None);
+    }
+
+    #[test]
+    fn wildcards_lead_and_mapped_addresses_resolve() {
+        let interfaces = Interfaces::from([("192.168.1.2".parse().unwrap(), "wlan0".to_string())]);
+        let ports = group(
+            vec![
+                socket("::ffff:192.168.1.2", 5000, 0),
+                socket("0.0.0.0", 5000, 0),
+            ],
+            &interfaces,
+        );
+        assert_eq!(ports[0].local_addr, "0.0.0.0");
+        assert_eq!(ports[0].bindings[0].interface, None);
+        assert_eq!(ports[0].bindings[1].interface.as_deref(), Some("wlan0"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Which container a process belongs to. Its `/proc/[pid]/cgroup` names the
+//! runtime and id; Docker's state dir adds the name and image, and maps the
+//! container IP `docker-proxy` forwards to back to a container.
+//!
+//! Pure parsing — no OS-specific syscalls, testable on any platform.
+#![allow(dead_code)]
+
+use serde_json::Value;
+
+use crate::types::ContainerInfo;
+
+/// One directory per container, each with a `config.v2.json`.
+pub const DOCKER_STATE_DIR: &str = "/var/lib/docker/containers";
+
+/// cgroup path segment prefixes (systemd driver) and their runtime.
+const SCOPE_PREFIXES: [(&str, &str); 4] = [
+    ("docker-", "docker"),
+    ("libpod-", "podman"),
+    ("cri-containerd-", "containerd"),
+    ("crio-", "cri-o"),
+];
+
+/// The container in a `/proc/[pid]/cgroup`, from either the systemd driver's
+/// `docker-<id>.scope` segments or the cgroupfs driver's `/docker/<id>`.
+pub fn from_cgroup(cgroup: &str) -> Option<ContainerInfo> {
+    cgroup.lines().find_map(|line| {
+        let path = line.splitn(3, ':').nth(2)?;
+        let segments: Vec<&str> = path.split('/').collect();
+        segments.iter().enumerate().rev().find_map(|(i, segment)| {
+            let segment = segment.strip_suffix(".scope").unwrap_or(segment);
+            let scoped = SCOPE_PREFIXES.iter().find_map(|(prefix, runtime)| {
+                let id = segment.strip_prefix(prefix)?;
+                Some((*runtime, id))
+            });
+            let (runtime, id) = scoped.or_else(|| match segments.get(i.wrapping_sub(1)) {
+                Some(&"docker") => Some(("docker", segment)),
+                _ => None,
+            })?;
+            is_container_id(id).then(|| ContainerInfo {
+                runtime: runtime.to_string(),
+                id: id.to_string(),
+                name: None,
+                image: None,
+            })
+        })
+    })
+}
+
+fn is_container_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The container address `docker-proxy` forwards a published port to.
+pub fn proxied_ip(cmdline: &str) -> Option<&str> {
+    let mut args = cmdline.split_whitespace();
+    args.find(|&arg| arg == "-container-ip")?;
+    args.next()
+}
+
+/// What a Docker `config.v2.json` says about its container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DockerConfig {
+    pub id: String,
+    pub name: Option<String>,
+    pub image: Option<String>,
+    /// Addresses on the container's networks.
+    pub ips: Vec<String>,
+}
+
+impl DockerConfig {
+    pub fn parse(json: &str) -> Option<Self> {
+        let config: Value = serde_json::from_str(json).ok()?;
+        let text = |value: &Value| value.as_str().filter(|s| !s.is_empty()).map(String::from);
+        let ips = config["NetworkSettings"]["Networks"]
+            .as_object()
+            .into_iter()
+            .flat_map(|networks| networks.values())
+            .filter_map(|network| text(&network["IPAddress"]))
+            .collect();
+        Some(Self {
+            id: text(&config["ID"])?,
+            // Docker stores names with a leading slash.
+            name: text(&config["Name"]).map(|name| name.trim_start_matches('/').to_string()),
+            image: text(&config["Config"]["Image"]),
+            ips,
+        })
+    }
+
+    pub fn into_info(self) -> ContainerInfo {
+        ContainerInfo {
+            runtime: "docker".to_string(),
+            id: self.id,
+            name: self.name,
+            image: self.image,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f4e8a1b2c9d0e7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f";
+
+    #[test]
+    fn cgroup_paths_of_each_runtime() {
+        let runtime = |cgroup: &str| from_cgroup(cgroup).map(|c| (c.runtime, c.id));
+        let docker = Some(("docker".to_string(), ID.to_string()));
+        assert_eq!(
+            runtime(&format!("0::/system.slice/docker-{ID}.scope")),
+            docker
+        );
+        assert_eq!(
+            runtime(&format!("12:memory:/docker/{ID}\n0::/docker/{ID}")),
+            docker
+        );
+        assert_eq!(
+            runtime(&format!(
+                "0::/user.slice/user-1000.slice/user@1000.service/user.slice/libpod-{ID}.scope/container"
+            ))
+            .unwrap()
+            .0,
+            "podman"
+        );
+        assert_eq!(
+            runtime(&format!(
+                "0::/kubepods.slice/kubepods-pod1.slice/cri-containerd-{ID}.scope"
+            ))
+            .unwrap()
+            .0,
+            "containerd"
+        );
+        assert_eq!(
+            runtime("0::/user.slice/user-1000.slice/session-2.scope"),
+            None
+        );
+        assert_eq!(runtime("0::/system.slice/docker-abc.scope"), None);
+    }
+
+    #[test]
+    fn docker_proxy_and_config() {
+        let cmdline = "/usr/bin/docker-proxy -proto tcp -host-ip 0.0.0.0 -host-port 8080 \
+                       -container-ip 172.17.0.2 -container-port 80";
+        assert_eq!(proxied_ip(cmdline), Some("172.17.0.2"));
+        assert_eq!(proxied_ip("/usr/bin/node server.js"), None);
+
+        let json = format!(
+            r#"{{"ID":"{ID}","Name":"/web","Config":{{"Image":"nginx:1.27"}},
+               "NetworkSettings":{{"Networks":{{"bridge":{{"IPAddress":"172.17.0.2"}},
+               "none":{{"IPAddress":""}}}}}}}}"#
+        );
+        let config = DockerConfig::parse(&json).unwrap();
+        assert_eq!(config.ips, ["172.17.0.2"]);
+        let info = config.into_info();
+        assert_eq!(info.to_string(), "docker web (nginx:1.27)");
+    }
+}
@@ -0,0 +1,166 @@
+//! What the host is (OS, kernel, container runtimes) and how it's doing
+//! (uptime, load), for the `HostInfo` in every scan. What it is doesn't change
+//! while the agent runs and is read once.
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use crate::types::HostInfo;
+
+/// Paths whose presence means a runtime is installed: its socket for root
+/// installs, its binary otherwise (rootless podman has no system socket).
+const RUNTIMES: [(&str, &[&str]); 3] = [
+    ("docker", &["/var/run/docker.sock", "/run/docker.sock"]),
+    (
+        "podman",
+        &[
+            "/run/podman/podman.sock",
+            "/usr/bin/podman",
+            "/usr/local/bin/podman",
+        ],
+    ),
+    (
+        "containerd",
+        &[
+            "/run/containerd/containerd.sock",
+            "/var/run/containerd/containerd.sock",
+        ],
+    ),
+];
+
+/// Left by a kubelet: its state dir and kubeadm's or k3s's config.
+const KUBELET_PATHS: [&str; 3] = [
+    "/var/lib/kubelet/config.yaml",
+    "/etc/kubernetes/kubelet.conf",
+    "/var/lib/rancher/k3s/agent/kubelet.kubeconfig",
+];
+
+/// The distribution from `/etc/os-release`: `PRETTY_NAME`, else `NAME` and
+/// `VERSION_ID`.
+pub fn parse_os_release(content: &str) -> Option<String> {
+    let value = |key: &str| {
+        content.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            let value = value.trim().trim_matches(['"', '\'']);
+            (!value.is_empty()).then(|| value.to_string())
+        })
+    };
+    value("PRETTY_NAME").or_else(|| match (value("NAME"), value("VERSION_ID")) {
+        (Some(name), Some(version)) => Some(format!("{name} {version}")),
+        (name, _) => name,
+    })
+}
+
+/// Whole seconds from `/proc/uptime` (`"12345.67 98765.43"`).
+pub fn parse_uptime(content: &str) -> Option<u64> {
+    let secs: f64 = content.split_whitespace().next()?.parse().ok()?;
+    Some(secs as u64)
+}
+
+/// Boot time in epoch seconds from `sysctl -n kern.boottime`
+/// (`"{ sec = 1700000000, usec = 123 } Tue Nov 14 22:13:20 2023"`).
+pub fn parse_boottime(output: &str) -> Option<u64> {
+    let rest = output.split("sec = ").nth(1)?;
+    rest.split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// The kernel's release string (`uname -r`).
+pub fn kernel_release() -> Option<String> {
+    let mut name: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut name) } != 0 {
+        return None;
+    }
+    let release = unsafe { std::ffi::CStr::from_ptr(name.release.as_ptr()) };
+    Some(release.to_string_lossy().into_owned())
+}
+
+/// 1, 5 and 15 minute load averages as `uptime` prints them.
+pub fn load_avg() -> Option<String> {
+    let mut load = [0f64; 3];
+    if unsafe { libc::getloadavg(load.as_mut_ptr(), 3) } != 3 {
+        return None;
+    }
+    Some(format!("{:.2} {:.2} {:.2}", load[0], load[1], load[2]))
+}
+
+/// The runtimes installed and whether a kubelet is, by which paths `exists`.
+pub fn detect_containers(exists: impl Fn(&str) -> bool) -> (Vec<String>, bool) {
+    let runtimes = RUNTIMES
+        .iter()
+        .filter(|(_, paths)| paths.iter().any(|p| exists(p)))
+        .map(|(runtime, _)| runtime.to_string())
+        .collect();
+    (runtimes, KUBELET_PATHS.iter().any(|p| exists(p)))
+}
+
+/// What doesn't change while the agent runs, given the OS name.
+pub fn static_info(os: Option<String>) -> HostInfo {
+    let (container_runtimes, kubernetes) = detect_containers(|p| Path::new(p).exists());
+    HostInfo {
+        os,
+        kernel: kernel_release(),
+        container_runtimes,
+        kubernetes,
+        ..Default::default()
+    }
+}
+
+/// `static_info` with this moment's uptime and load filled in.
+pub fn snapshot(static_info: &HostInfo, uptime_secs: Option<u64>) -> HostInfo {
+    HostInfo {
+        uptime_secs,
+        load_avg: load_avg(),
+        ..static_info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_release_names() {
+        let ubuntu = "NAME=\"Ubuntu\"\nVERSION_ID=\"24.04\"\nPRETTY_NAME=\"Ubuntu 24.04.1 LTS\"\n";
+        assert_eq!(
+            parse_os_release(ubuntu).as_deref(),
+            Some("Ubuntu 24.04.1 LTS")
+        );
+        let minimal = "NAME=Alpine Linux\nVERSION_ID=3.20.3\nPRETTY_NAME=\"\"\n";
+        assert_eq!(
+            parse_os_release(minimal).as_deref(),
+            Some("Alpine Linux 3.20.3")
+        );
+        assert_eq!(parse_os_release("ID=custom\n"), None);
+    }
+
+    #[test]
+    fn container_runtimes_and_kubelet() {
+        let (runtimes, kubernetes) = detect_containers(|p| {
+            [
+                "/run/containerd/containerd.sock",
+                "/etc/kubernetes/kubelet.conf",
+            ]
+            .contains(&p)
+        });
+        assert_eq!(runtimes, ["containerd"]);
+        assert!(kubernetes);
+        let (runtimes, kubernetes) =
+            detect_containers(|p| p == "/var/run/docker.sock" || p == "/usr/bin/podman");
+        assert_eq!(runtimes, ["docker", "podman"]);
+        assert!(!kubernetes);
+    }
+
+    #[test]
+    fn uptime_and_boottime() {
+        assert_eq!(parse_uptime("350735.47 234388.90\n"), Some(350735));
+        assert_eq!(parse_uptime(""), None);
+        assert_eq!(
+            parse_boottime("{ sec = 1700000000, usec = 123 } Tue Nov 14 22:13:20 2023\n"),
+            Some(1700000000)
+        );
+        assert_eq!(parse_boottime("kern.boottime: unknown"), None);
+    }
+}
@@ -1,18 +1,38 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
-use crate::types::{AgentError, AgentErrorKind, ListeningPort, ProcessInfo, Protocol, ScanResult};
+use crate::types::{
+    AgentError, ConnectionStats, ContainerInfo, HostInfo, ListeningPort, ProcessInfo, Protocol,
+    ScanResult,
+};
 
-use super::proc_net_tcp::{dedup_entries, parse_proc_net_tcp};
+use super::bindings;
+use super::container::{self, DockerConfig};
+use super::host;
+use super::proc_net_tcp::{dedup_entries, parse_established, parse_proc_net_tcp};
+use super::proc_pid::{filter_environ, parse_btime, parse_stat_starttime};
 use super::Scanner;
 
 pub struct LinuxScanner {
     scan_index: u64,
+    owners: OwnerCache,
+    shallow: bool,
+    /// OS and kernel, read once.
+    host: HostInfo,
 }
 
 impl LinuxScanner {
     pub fn new() -> Self {
-        Self { scan_index: 0 }
+        Self {
+            scan_index: 0,
+            owners: OwnerCache::default(),
+            shallow: false,
+            host: host::static_info(
+                fs::read_to_string("/etc/os-release")
+                    .ok()
+                    .and_then(|c| host::parse_os_release(&c)),
+            ),
+        }
     }
 }
 
@@ -26,10 +46,8 @@ impl Scanner for LinuxScanner {
     fn scan(&mut self) -> Result<ScanResult, AgentError> {
         let mut warnings = Vec::new();
 
-        let tcp_content = fs::read_to_string("/proc/net/tcp").map_err(|e| AgentError {
-            kind: AgentErrorKind::ScanFailed,
-            message: format!("failed to read /proc/net/tcp: {e}"),
-        })?;
+        let tcp_content = fs::read_to_string("/proc/net/tcp")
+            .map_err(|e| AgentError::io("failed to read /proc/net/tcp", &e))?;
 
         let tcp6_content = fs::read_to_string("/proc/net/tcp6").unwrap_or_default();
 
@@ -38,19 +56,46 @@ impl Scanner for LinuxScanner {
 
         entries = dedup_entries(entries);
 
+        let mut established = parse_established(&tcp_content, Protocol::Tcp);
+        for (port, stats) in parse_established(&tcp6_content, Protocol::Tcp6) {
+            let total = established.entry(port).or_default();
+            total.established += stats.established;
+            total.recv_queue += stats.recv_queue;
+            total.send_queue += stats.send_queue;
+        }
+
         let inode_uid_map: HashMap<u64, u32> = entries.iter().map(|e| (e.inode, e.uid)).collect();
 
-        let inode_to_process = map_inodes_to_processes(&inode_uid_map, &mut warnings);
+        let clock = BootClock::read();
+        let walk = |targets: &HashMap<u64, u32>, warnings: &mut Vec<String>| {
+            map_inodes_to_processes(targets, &clock, warnings)
+        };
+        let inode_to_process = self.owners.resolve(
+            &inode_uid_map,
+            |inode, owner| owner.still_holds(inode),
+            (!self.shallow).then_some(walk),
+            &mut warnings,
+        );
 
         let ports: Vec<ListeningPort> = entries
             .into_iter()
-            .map(|entry| ListeningPort {
-                protocol: entry.protocol,
-                local_addr: entry.local_addr,
-                port: entry.port,
-                process: inode_to_process.get(&entry.inode).cloned(),
+            .map(|entry| {
+                let active = established.get(&entry.port).copied().unwrap_or_default();
+                ListeningPort {
+                    protocol: entry.protocol,
+                    local_addr: entry.local_addr,
+                    port: entry.port,
+                    process: inode_to_process.get(&entry.inode).cloned(),
+                    probe: None,
+                    bindings: Vec::new(),
+                    stats: Some(ConnectionStats {
+                        accept_queue: entry.rx_queue,
+                        ..active
+                    }),
+                }
             })
             .collect();
+        let ports = bindings::group(ports, &bindings::local_interfaces());
 
         let hostname = fs::read_to_string("/etc/hostname")
             .unwrap_or_default()
@@ -59,26 +104,134 @@ impl Scanner for LinuxScanner {
         let uid = unsafe { libc::getuid() };
         let username = get_username(uid);
         let is_root = uid == 0;
+        let uptime = fs::read_to_string("/proc/uptime")
+            .ok()
+            .and_then(|c| host::parse_uptime(&c));
 
         let result = ScanResult {
             agent_version: env!("CARGO_PKG_VERSION").to_string(),
             hostname,
             username,
             is_root,
+            uid: Some(uid),
             ports,
             warnings,
             scan_index: self.scan_index,
+            scan_duration_ms: None,
+            scanned_at: None,
+            host: Some(host::snapshot(&self.host, uptime)),
         };
         self.scan_index += 1;
         Ok(result)
     }
+
+    fn set_shallow(&mut self, shallow: bool) {
+        self.shallow = shallow;
+    }
+}
+
+/// Converts `/proc/[pid]/stat` start ticks into epoch seconds.
+struct BootClock {
+    btime: Option<u64>,
+    ticks_per_sec: u64,
+}
+
+impl BootClock {
+    fn read() -> Self {
+        let btime = fs::read_to_string("/proc/stat")
+            .ok()
+            .and_then(|c| parse_btime(&c));
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        Self {
+            btime,
+            ticks_per_sec: u64::try_from(ticks).ok().filter(|t| *t > 0).unwrap_or(100),
+        }
+    }
+
+    fn start_time(&self, pid: u32) -> Option<u64> {
+        let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        let ticks = parse_stat_starttime(&stat)?;
+        Some(self.btime? + ticks / self.ticks_per_sec)
+    }
+}
+
+/// The process holding a listening socket, and the fd it holds it on.
+#[derive(Debug, Clone)]
+struct Owner {
+    pid: u32,
+    fd: String,
+    info: ProcessInfo,
+}
+
+impl Owner {
+    /// One `readlink`: the process still exists and the fd still points at
+    /// the socket.
+    fn still_holds(&self, inode: u64) -> bool {
+        fs::read_link(format!("/proc/{}/fd/{}", self.pid, self.fd))
+            .is_ok_and(|link| link.to_string_lossy() == format!("socket:[{inode}]"))
+    }
+}
+
+/// Socket owners remembered between scans, so the `/proc/*/fd` walk (heavy
+/// on hosts with thousands of processes) only runs when a socket appears
+/// that no earlier walk has seen, or its cached owner let go of it.
+#[derive(Debug, Default)]
+struct OwnerCache {
+    owners: HashMap<u64, Owner>,
+    /// Inodes the last walk could not attribute (e.g. another user's
+    /// process without root); not worth another walk on their own.
+    unresolved: HashSet<u64>,
+    /// Warnings from the last walk, repeated while it is reused.
+    walk_warnings: Vec<String>,
+}
+
+impl OwnerCache {
+    /// Owners of `targets` (inode → socket uid). Cached owners are checked
+    /// with `holds`; `walk` runs over the remaining inodes only when one of
+    /// them is new. Without `walk` (a shallow scan) they stay unattributed
+    /// until a later scan walks.
+    fn resolve(
+        &mut self,
+        targets: &HashMap<u64, u32>,
+        holds: impl Fn(u64, &Owner) -> bool,
+        walk: Option<impl FnOnce(&HashMap<u64, u32>, &mut Vec<String>) -> HashMap<u64, Owner>>,
+        warnings: &mut Vec<String>,
+    ) -> HashMap<u64, ProcessInfo> {
+        self.owners
+            .retain(|inode, owner| targets.contains_key(inode) && holds(*inode, owner));
+        self.unresolved.retain(|inode| targets.contains_key(inode));
+
+        let unknown: HashMap<u64, u32> = targets
+            .iter()
+            .filter(|(inode, _)| !self.owners.contains_key(inode))
+            .map(|(&inode, &uid)| (inode, uid))
+            .collect();
+        let walk = walk.filter(|_| unknown.keys().any(|i| !self.unresolved.contains(i)));
+        if let Some(walk) = walk {
+            self.walk_warnings.clear();
+            let found = walk(&unknown, &mut self.walk_warnings);
+            self.unresolved = unknown
+                .into_keys()
+                .filter(|inode| !found.contains_key(inode))
+                .collect();
+            self.owners.extend(found);
+        }
+        warnings.extend(self.walk_warnings.iter().cloned());
+
+        self.owners
+            .iter()
+            .map(|(&inode, owner)| (inode, owner.info.clone()))
+            .collect()
+    }
 }
 
-/// Map socket inodes to process information by walking /proc/[pid]/fd/.
+/// Find the processes holding the sockets in `inode_uid_map` by walking
+/// /proc/[pid]/fd/.
 fn map_inodes_to_processes(
     inode_uid_map: &HashMap<u64, u32>,
+    clock: &BootClock,
     warnings: &mut Vec<String>,
-) -> HashMap<u64, ProcessInfo> {
+) -> HashMap<u64, Owner> {
     let mut result = HashMap::new();
 
     let proc_dir = match fs::read_dir("/proc") {
@@ -89,8 +242,7 @@ fn map_inodes_to_processes(
         }
     };
 
-    let target_uids: std::collections::HashSet<u32> = inode_uid_map.values().copied().collect();
-    let target_inodes: std::collections::HashSet<u64> = inode_uid_map.keys().copied().collect();
+    let target_uids: HashSet<u32> = inode_uid_map.values().copied().collect();
 
     for entry in proc_dir.flatten() {
         let name = entry.file_name();
@@ -135,9 +287,15 @@ fn map_inodes_to_processes(
                     Ok(v) => v,
                     Err(_) => continue,
                 };
-                if target_inodes.contains(&inode) && !result.contains_key(&inode) {
-                    let info = read_process_info(pid, proc_uid);
-                    result.insert(inode, info);
+                if inode_uid_map.contains_key(&inode) && !result.contains_key(&inode) {
+                    result.insert(
+                        inode,
+                        Owner {
+                            pid,
+                            fd: fd_entry.file_name().to_string_lossy().into_owned(),
+                            info: read_process_info(pid, proc_uid, clock),
+                        },
+                    );
                 }
             }
         }
@@ -157,7 +315,7 @@ fn read_uid_from_status(path: &str) -> Option<u32> {
     None
 }
 
-fn read_process_info(pid: u32, uid: u32) -> ProcessInfo {
+fn read_process_info(pid: u32, uid: u32, clock: &BootClock) -> ProcessInfo {
     let name = fs::read_to_string(format!("/proc/{pid}/comm"))
         .unwrap_or_default()
         .trim()
@@ -169,12 +327,64 @@ fn read_process_info(pid: u32, uid: u32) -> ProcessInfo {
         .trim()
         .to_string();
 
+    let read_link = |what: &str| {
+        fs::read_link(format!("/proc/{pid}/{what}"))
+            .ok()
+            .map(|p| p.to_string_lossy().into_owned())
+    };
+
+    let env = fs::read(format!("/proc/{pid}/environ"))
+        .map(|e| filter_environ(&e))
+        .unwrap_or_default();
+
+    let container = read_container(pid, &name, &cmdline);
+
     ProcessInfo {
         pid,
         name,
         cmdline,
         uid,
+        exe: read_link("exe"),
+        cwd: read_link("cwd"),
+        start_time: clock.start_time(pid),
+        env,
+        elevated: false,
+        container,
+    }
+}
+
+/// The container `pid` runs in, or for `docker-proxy` the one it publishes.
+/// Docker's state dir is root-only, so unelevated agents only get the
+/// runtime and id.
+fn read_container(pid: u32, name: &str, cmdline: &str) -> Option<ContainerInfo> {
+    if name == "docker-proxy" {
+        let ip = container::proxied_ip(cmdline)?;
+        return docker_configs()
+            .find(|config| config.ips.iter().any(|i| i == ip))
+            .map(DockerConfig::into_info);
+    }
+    let cgroup = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    let info = container::from_cgroup(&cgroup)?;
+    if info.runtime != "docker" {
+        return Some(info);
     }
+    let config = fs::read_to_string(format!(
+        "{}/{}/config.v2.json",
+        container::DOCKER_STATE_DIR,
+        info.id
+    ))
+    .ok()
+    .and_then(|json| DockerConfig::parse(&json));
+    Some(config.map_or(info, DockerConfig::into_info))
+}
+
+fn docker_configs() -> impl Iterator<Item = DockerConfig> {
+    fs::read_dir(container::DOCKER_STATE_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path().join("config.v2.json")).ok())
+        .filter_map(|json| DockerConfig::parse(&json))
 }
 
 fn get_username(uid: u32) -> String {
@@ -192,3 +402,113 @@ fn get_username(uid: u32) -> String {
     }
     format!("uid:{uid}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn owner(pid: u32) -> Owner {
+        Owner {
+            pid,
+            fd: "3".into(),
+            info: ProcessInfo {
+                pid,
+                name: format!("p{pid}"),
+                cmdline: String::new(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            },
+        }
+    }
+
+    #[test]
+    fn owner_cache_walks_only_for_new_inodes() {
+        let mut cache = OwnerCache::default();
+        let walks = Cell::new(0);
+        let walk = |found: Vec<(u64, u32)>| {
+            let walks = &walks;
+            move |targets: &HashMap<u64, u32>, warnings: &mut Vec<String>| {
+                walks.set(walks.get() + 1);
+                warnings.push("permission denied reading /proc/9/fd".into());
+                found
+                    .into_iter()
+                    .filter(|(inode, _)| targets.contains_key(inode))
+                    .map(|(inode, pid)| (inode, owner(pid)))
+                    .collect()
+            }
+        };
+        let targets = |inodes: &[u64]| -> HashMap<u64, u32> {
+            inodes.iter().map(|&inode| (inode, 1000)).collect()
+        };
+
+        // First scan walks; inode 12 belongs to nobody we can see.
+        let mut warnings = Vec::new();
+        let owners = cache.resolve(
+            &targets(&[11, 12]),
+            |_, _| true,
+            Some(walk(vec![(11, 100)])),
+            &mut warnings,
+        );
+        assert_eq!(walks.get(), 1);
+        assert_eq!(owners[&11].pid, 100);
+        assert!(!owners.contains_key(&12));
+
+        // Nothing new: served from the cache, walk warnings repeated.
+        let mut warnings = Vec::new();
+        let owners = cache.resolve(
+            &targets(&[11, 12]),
+            |_, _| true,
+            Some(walk(vec![])),
+            &mut warnings,
+        );
+        assert_eq!(walks.get(), 1);
+        assert_eq!(owners[&11].name, "p100");
+        assert_eq!(warnings.len(), 1);
+
+        // The owner let go of the socket: walk again and find the new one.
+        let owners = cache.resolve(
+            &targets(&[11, 12]),
+            |_, owner| owner.pid != 100,
+            Some(walk(vec![(11, 200)])),
+            &mut Vec::new(),
+        );
+        assert_eq!(walks.get(), 2);
+        assert_eq!(owners[&11].pid, 200);
+
+        // A new socket appears; closed ones are forgotten.
+        let owners = cache.resolve(
+            &targets(&[13]),
+            |_, _| true,
+            Some(walk(vec![(13, 300)])),
+            &mut Vec::new(),
+        );
+        assert_eq!(walks.get(), 3);
+        assert_eq!(owners.len(), 1);
+        assert!(cache.unresolved.is_empty());
+
+        // Shallow scans leave new sockets for the next deep one.
+        type Walk = fn(&HashMap<u64, u32>, &mut Vec<String>) -> HashMap<u64, Owner>;
+        let owners = cache.resolve(
+            &targets(&[13, 14]),
+            |_, _| true,
+            None::<Walk>,
+            &mut Vec::new(),
+        );
+        assert_eq!(owners.len(), 1);
+        assert!(cache.unresolved.is_empty());
+        let owners = cache.resolve(
+            &targets(&[13, 14]),
+            |_, _| true,
+            Some(walk(vec![(14, 400)])),
+            &mut Vec::new(),
+        );
+        assert_eq!(walks.get(), 4);
+        assert_eq!(owners.len(), 2);
+    }
+}
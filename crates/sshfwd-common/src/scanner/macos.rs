@@ -1,20 +1,51 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::process::Command;
 
-use crate::types::{AgentError, AgentErrorKind, ListeningPort, ProcessInfo, Protocol, ScanResult};
+use crate::types::{
+    AgentError, AgentErrorKind, ConnectionStats, HostInfo, ListeningPort, ProcessInfo, Protocol,
+    ScanResult,
+};
 
+use super::bindings;
+use super::host;
+use super::netstat;
 use super::Scanner;
 
 pub struct MacosScanner {
     scan_index: u64,
+    /// OS and kernel, read once.
+    host: HostInfo,
+    /// Epoch seconds, from `kern.boottime`.
+    boot_time: Option<u64>,
+    /// Process details by pid, kept while the pid listens so each scan only
+    /// runs `ps`/`lsof` for processes it hasn't seen yet.
+    processes: HashMap<u32, ProcessInfo>,
 }
 
 impl MacosScanner {
     pub fn new() -> Self {
-        Self { scan_index: 0 }
+        let sw_vers = |flag: &str| command_output("sw_vers", &[flag]);
+        let os = match (sw_vers("-productName"), sw_vers("-productVersion")) {
+            (Some(name), Some(version)) => Some(format!("{name} {version}")),
+            (name, _) => name,
+        };
+        Self {
+            scan_index: 0,
+            host: host::static_info(os),
+            boot_time: command_output("sysctl", &["-n", "kern.boottime"])
+                .and_then(|out| host::parse_boottime(&out)),
+            processes: HashMap::new(),
+        }
     }
 }
 
+/// Trimmed stdout of a command that succeeded with some.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
+
 impl Default for MacosScanner {
     fn default() -> Self {
         Self::new()
@@ -23,7 +54,7 @@ impl Default for MacosScanner {
 
 impl Scanner for MacosScanner {
     fn scan(&mut self) -> Result<ScanResult, AgentError> {
-        let ports = scan_listening_ports()?;
+        let ports = scan_listening_ports(&mut self.processes)?;
 
         let hostname = Command::new("hostname")
             .output()
@@ -42,15 +73,21 @@ impl Scanner for MacosScanner {
             .unwrap_or_else(|| format!("uid:{uid}"))
             .trim()
             .to_string();
+        let now = crate::time::epoch_secs(std::time::SystemTime::now());
+        let uptime = self.boot_time.map(|boot| now.saturating_sub(boot));
 
         let result = ScanResult {
             agent_version: env!("CARGO_PKG_VERSION").to_string(),
             hostname,
             username,
             is_root: uid == 0,
+            uid: Some(uid),
             ports,
             warnings: vec![],
             scan_index: self.scan_index,
+            scan_duration_ms: None,
+            scanned_at: None,
+            host: Some(host::snapshot(&self.host, uptime)),
         };
         self.scan_index += 1;
         Ok(result)
@@ -62,14 +99,13 @@ impl Scanner for MacosScanner {
 /// Example lsof output line:
 ///   node  1234 user  18u  IPv4  0x...  0t0  TCP *:3000 (LISTEN)
 ///   ruby  5678 user  10u  IPv6  0x...  0t0  TCP localhost:4000 (LISTEN)
-fn scan_listening_ports() -> Result<Vec<ListeningPort>, AgentError> {
+fn scan_listening_ports(
+    processes: &mut HashMap<u32, ProcessInfo>,
+) -> Result<Vec<ListeningPort>, AgentError> {
     let output = Command::new("lsof")
         .args(["-nP", "-iTCP", "-sTCP:LISTEN"])
         .output()
-        .map_err(|e| AgentError {
-            kind: AgentErrorKind::ScanFailed,
-            message: format!("lsof failed: {e}"),
-        })?;
+        .map_err(|e| AgentError::io("lsof failed", &e))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -81,24 +117,59 @@ fn scan_listening_ports() -> Result<Vec<ListeningPort>, AgentError> {
 
     let stdout = String::from_utf8_lossy(&output.stdout);
 
-    // pid → ProcessInfo cache so we only read cmdline once per pid
-    let mut process_cache: HashMap<u32, ProcessInfo> = HashMap::new();
-
     let mut ports = Vec::new();
     for line in stdout.lines().skip(1) {
         // skip header
-        if let Some(port) = parse_lsof_line(line, &mut process_cache) {
+        if let Some(port) = parse_lsof_line(line, processes) {
             ports.push(port);
         }
     }
+    // Forget processes that stopped listening, so a reused pid is read afresh.
+    let listening: HashSet<u32> = ports
+        .iter()
+        .filter_map(|p| p.process.as_ref().map(|info| info.pid))
+        .collect();
+    processes.retain(|pid, _| listening.contains(pid));
 
-    // Deduplicate: same port may appear multiple times (IPv4 and IPv6 listeners)
+    // One entry per address a process binds; then the same port may still
+    // appear twice (IPv4 and IPv6 listeners).
+    let mut ports = bindings::group(ports, &bindings::local_interfaces());
     ports.sort_by_key(|p| p.port);
     ports.dedup_by_key(|p| p.port);
 
+    attach_connection_stats(&mut ports);
+
     Ok(ports)
 }
 
+/// Fill `stats` from `netstat`; ports stay `None` if netstat is unavailable.
+fn attach_connection_stats(ports: &mut [ListeningPort]) {
+    let run = |args: &[&str]| {
+        Command::new("netstat")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+    };
+    let Some(active) = run(&["-an", "-p", "tcp"]) else {
+        return;
+    };
+    let established = netstat::parse_established(&active);
+    let queues = run(&["-Lan", "-p", "tcp"])
+        .map(|out| netstat::parse_listen_queues(&out))
+        .unwrap_or_default();
+
+    for port in ports {
+        let (accept_queue, backlog) = queues.get(&port.port).copied().unwrap_or_default();
+        port.stats = Some(ConnectionStats {
+            accept_queue,
+            backlog,
+            ..established.get(&port.port).copied().unwrap_or_default()
+        });
+    }
+}
+
 /// Parse one lsof output line into a `ListeningPort`.
 ///
 /// lsof columns (space-separated, variable width):
@@ -132,6 +203,13 @@ fn parse_lsof_line(
         .map(|(addr, _)| addr.trim_matches(['[', ']']).to_string())
         .unwrap_or_default();
 
+    // A different command under a cached pid means the pid was reused.
+    if process_cache
+        .get(&pid)
+        .is_some_and(|info| info.name != command)
+    {
+        process_cache.remove(&pid);
+    }
     let process = Some(
         process_cache
             .entry(pid)
@@ -144,6 +222,9 @@ fn parse_lsof_line(
         local_addr,
         port,
         process,
+        probe: None,
+        bindings: Vec::new(),
+        stats: None,
     })
 }
 
@@ -160,6 +241,27 @@ fn read_process_info(pid: u32, command: &str) -> ProcessInfo {
 
     let uid = unsafe { libc::getuid() };
 
+    // `comm` is the full executable path on macOS; `etime` is elapsed run time.
+    let ps_field = |field: &str| {
+        Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", field])
+            .output()
+            .ok()
+            .and_then(|o| String::from_utf8(o.stdout).ok())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    let start_time = ps_field("etime=")
+        .and_then(|e| parse_etime(&e))
+        .and_then(|elapsed| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()?
+                .as_secs();
+            now.checked_sub(elapsed)
+        });
+
     ProcessInfo {
         pid,
         name: command.to_string(),
@@ -169,5 +271,34 @@ fn read_process_info(pid: u32, command: &str) -> ProcessInfo {
             cmdline
         },
         uid,
+        exe: ps_field("comm="),
+        cwd: read_cwd(pid),
+        start_time,
+        env: Default::default(),
+        elevated: false,
+        container: None,
     }
 }
+
+/// Working directory via `lsof -Fn` (prints `p<pid>`, `fcwd`, `n<path>`).
+fn read_cwd(pid: u32) -> Option<String> {
+    let output = Command::new("lsof")
+        .args(["-a", "-p", &pid.to_string(), "-d", "cwd", "-Fn"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.strip_prefix('n').map(str::to_string))
+}
+
+/// Parse `ps -o etime` output (`[[dd-]hh:]mm:ss`) into seconds.
+fn parse_etime(etime: &str) -> Option<u64> {
+    let (days, rest) = match etime.split_once('-') {
+        Some((d, r)) => (d.parse::<u64>().ok()?, r),
+        None => (0, etime),
+    };
+    let secs = rest
+        .split(':')
+        .try_fold(0u64, |acc, part| Some(acc * 60 + part.parse::<u64>().ok()?))?;
+    Some(days * 86_400 + secs)
+}
@@ -1,7 +1,15 @@
 use crate::types::{AgentError, ScanResult};
 
 // Pure parsing logic — always compiled for testing on any platform
+pub mod bindings;
+pub mod container;
+pub mod host;
+pub mod netstat;
 pub mod proc_net_tcp;
+pub mod proc_pid;
+pub mod registry;
+
+pub use registry::{Registry, Source};
 
 #[cfg(target_os = "linux")]
 pub mod linux;
@@ -11,17 +19,23 @@ pub mod macos;
 
 pub trait Scanner {
     fn scan(&mut self) -> Result<ScanResult, AgentError>;
+
+    /// Skip expensive process attribution in the following scans; sockets
+    /// only known from earlier scans keep their owner. No-op where every
+    /// scan costs the same.
+    fn set_shallow(&mut self, _shallow: bool) {}
 }
 
-/// Create the platform-appropriate scanner.
-pub fn create_scanner() -> Box<dyn Scanner> {
+/// Create the platform-appropriate scanner, as a registry without extra
+/// sources.
+pub fn create_scanner() -> Registry {
     #[cfg(target_os = "linux")]
     {
-        Box::new(linux::LinuxScanner::new())
+        Registry::new(Box::new(linux::LinuxScanner::new()))
     }
     #[cfg(target_os = "macos")]
     {
-        Box::new(macos::MacosScanner::new())
+        Registry::new(Box::new(macos::MacosScanner::new()))
     }
     #[cfg(not(any(target_os = "linux", target_os = "macos")))]
     {
@@ -0,0 +1,95 @@
+// Pure BSD `netstat` parsing (macOS) — no OS-specific syscalls, testable on any platform.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use crate::types::ConnectionStats;
+
+/// Aggregate established connections by local port from `netstat -an -p tcp`.
+///
+/// Example line:
+///   tcp4       0     12  127.0.0.1.5432    127.0.0.1.50000   ESTABLISHED
+pub fn parse_established(output: &str) -> HashMap<u16, ConnectionStats> {
+    let mut by_port: HashMap<u16, ConnectionStats> = HashMap::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || !fields[0].starts_with("tcp") || fields[5] != "ESTABLISHED" {
+            continue;
+        }
+        let (Ok(recv), Ok(send)) = (fields[1].parse::<u64>(), fields[2].parse::<u64>()) else {
+            continue;
+        };
+        let Some(port) = port_of(fields[3]) else {
+            continue;
+        };
+        let stats = by_port.entry(port).or_default();
+        stats.established += 1;
+        stats.recv_queue += recv;
+        stats.send_queue += send;
+    }
+    by_port
+}
+
+/// Listen queue sizes by port from `netstat -Lan -p tcp`.
+///
+/// Example line (`qlen/incqlen/maxqlen`):
+///   0/0/128        *.3000
+///
+/// Returns `port → (accept_queue, backlog)`.
+pub fn parse_listen_queues(output: &str) -> HashMap<u16, (u32, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let queues = fields.next()?;
+            let port = port_of(fields.next()?)?;
+            let mut parts = queues.split('/');
+            let qlen: u32 = parts.next()?.parse().ok()?;
+            let _incqlen = parts.next()?;
+            let maxqlen: u32 = parts.next()?.parse().ok()?;
+            Some((port, (qlen, maxqlen)))
+        })
+        .collect()
+}
+
+/// BSD netstat separates the port with a dot: `127.0.0.1.5432`, `*.3000`, `::1.8080`.
+fn port_of(addr: &str) -> Option<u16> {
+    addr.rsplit('.').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn established_aggregates_by_local_port() {
+        let output = "\
+Active Internet connections (including servers)
+Proto Recv-Q Send-Q  Local Address          Foreign Address        (state)
+tcp4       0     12  127.0.0.1.5432         127.0.0.1.50000        ESTABLISHED
+tcp4       4      0  127.0.0.1.5432         127.0.0.1.50001        ESTABLISHED
+tcp6       0      0  ::1.8080               ::1.50002              TIME_WAIT
+tcp4       0      0  *.5432                 *.*                    LISTEN
+";
+        let stats = parse_established(output);
+        assert_eq!(stats.len(), 1);
+        let s = &stats[&5432];
+        assert_eq!(s.established, 2);
+        assert_eq!(s.recv_queue, 4);
+        assert_eq!(s.send_queue, 12);
+    }
+
+    #[test]
+    fn listen_queues_by_port() {
+        let output = "\
+Current listen queue sizes (qlen/incqlen/maxqlen)
+Listen         Local Address
+0/0/128        *.3000
+2/0/511        127.0.0.1.5432
+";
+        let queues = parse_listen_queues(output);
+        assert_eq!(queues.len(), 2);
+        assert_eq!(queues[&3000], (0, 128));
+        assert_eq!(queues[&5432], (2, 511));
+    }
+}
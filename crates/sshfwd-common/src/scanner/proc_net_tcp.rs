@@ -4,9 +4,10 @@
 use std::collections::HashMap;
 use std::net::{Ipv4Addr, Ipv6Addr};
 
-use crate::types::Protocol;
+use crate::types::{ConnectionStats, Protocol};
 
 const LISTEN_STATE: &str = "0A";
+const ESTABLISHED_STATE: &str = "01";
 
 /// A parsed entry from /proc/net/tcp or /proc/net/tcp6.
 #[derive(Debug, Clone)]
@@ -16,6 +17,11 @@ pub struct TcpEntry {
     pub port: u16,
     pub uid: u32,
     pub inode: u64,
+    /// For a listening socket: connections waiting to be accepted.
+    pub rx_queue: u32,
+    /// Unsent bytes; always 0 for listening sockets (the kernel only exposes
+    /// the max backlog through sock_diag).
+    pub tx_queue: u32,
 }
 
 /// Parse /proc/net/tcp or /proc/net/tcp6 content.
@@ -41,6 +47,9 @@ pub fn parse_proc_net_tcp(content: &str, protocol: Protocol) -> Vec<TcpEntry> {
             None => continue,
         };
 
+        // Field 4 (index 4) is tx_queue:rx_queue in hex
+        let (tx_queue, rx_queue) = parse_queues(fields[4]).unwrap_or((0, 0));
+
         // Field 7 (index 7) is UID
         let uid: u32 = match fields[7].parse() {
             Ok(v) => v,
@@ -59,32 +68,63 @@ pub fn parse_proc_net_tcp(content: &str, protocol: Protocol) -> Vec<TcpEntry> {
             port,
             uid,
             inode,
+            rx_queue,
+            tx_queue,
         });
     }
     entries
 }
 
+/// Aggregate established connections by local port.
+///
+/// Returns `port → ConnectionStats` with `established`, `recv_queue` and
+/// `send_queue` filled; the listen-side fields are merged in by the caller.
+pub fn parse_established(content: &str, protocol: Protocol) -> HashMap<u16, ConnectionStats> {
+    let mut by_port: HashMap<u16, ConnectionStats> = HashMap::new();
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 12 || fields[3] != ESTABLISHED_STATE {
+            continue;
+        }
+        let Some((_, port)) = parse_address(fields[1], protocol) else {
+            continue;
+        };
+        let (tx_queue, rx_queue) = parse_queues(fields[4]).unwrap_or((0, 0));
+        let stats = by_port.entry(port).or_default();
+        stats.established += 1;
+        stats.recv_queue += u64::from(rx_queue);
+        stats.send_queue += u64::from(tx_queue);
+    }
+    by_port
+}
+
+/// Parse the `tx_queue:rx_queue` hex pair.
+fn parse_queues(field: &str) -> Option<(u32, u32)> {
+    let (tx, rx) = field.split_once(':')?;
+    Some((
+        u32::from_str_radix(tx, 16).ok()?,
+        u32::from_str_radix(rx, 16).ok()?,
+    ))
+}
+
 /// Parse a hex address:port pair from /proc/net/tcp.
 fn parse_address(addr_port: &str, protocol: Protocol) -> Option<(String, u16)> {
     let (addr_hex, port_hex) = addr_port.split_once(':')?;
     let port = u16::from_str_radix(port_hex, 16).ok()?;
 
-    let addr_str = match protocol {
-        Protocol::Tcp => {
-            if addr_hex.len() != 8 {
-                return None;
-            }
-            let addr_u32 = u32::from_str_radix(addr_hex, 16).ok()?;
-            // /proc/net/tcp stores addresses in host byte order (little-endian on x86)
-            let ip = Ipv4Addr::from(addr_u32.swap_bytes());
-            ip.to_string()
+    let addr_str = if protocol.is_ipv6() {
+        if addr_hex.len() != 32 {
+            return None;
         }
-        Protocol::Tcp6 => {
-            if addr_hex.len() != 32 {
-                return None;
-            }
-            parse_ipv6_addr(addr_hex)?
+        parse_ipv6_addr(addr_hex)?
+    } else {
+        if addr_hex.len() != 8 {
+            return None;
         }
+        let addr_u32 = u32::from_str_radix(addr_hex, 16).ok()?;
+        // /proc/net/tcp stores addresses in host byte order (little-endian on x86)
+        let ip = Ipv4Addr::from(addr_u32.swap_bytes());
+        ip.to_string()
     };
 
     Some((addr_str, port))
@@ -192,6 +232,8 @@ mod tests {
                 port: 8080,
                 uid: 1000,
                 inode: 111,
+                rx_queue: 0,
+                tx_queue: 0,
             },
             TcpEntry {
                 protocol: Protocol::Tcp6,
@@ -199,6 +241,8 @@ mod tests {
                 port: 8080,
                 uid: 1000,
                 inode: 222,
+                rx_queue: 0,
+                tx_queue: 0,
             },
         ];
         let deduped = dedup_entries(entries);
@@ -214,6 +258,8 @@ mod tests {
                 port: 1337,
                 uid: 108,
                 inode: 111,
+                rx_queue: 0,
+                tx_queue: 0,
             },
             TcpEntry {
                 protocol: Protocol::Tcp6,
@@ -221,6 +267,8 @@ mod tests {
                 port: 1337,
                 uid: 108,
                 inode: 222,
+                rx_queue: 0,
+                tx_queue: 0,
             },
         ];
         let deduped = dedup_entries(entries);
@@ -234,6 +282,34 @@ mod tests {
         assert_eq!(normalize_addr("::"), "::");
     }
 
+    #[test]
+    fn parse_listen_queues() {
+        let content = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0050 00000000:0000 0A 00000080:00000003 00:00000000 00000000     0        0 67890 1 0000000000000000 100 0 0 10 0
+";
+        let entries = parse_proc_net_tcp(content, Protocol::Tcp);
+        assert_eq!(entries[0].tx_queue, 0x80);
+        assert_eq!(entries[0].rx_queue, 3);
+    }
+
+    #[test]
+    fn parse_established_counts_by_local_port() {
+        let content = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 0100007F:D904 01 00000010:00000020 00:00000000 00000000  1000        0 11111 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:D905 01 00000000:00000001 00:00000000 00000000  1000        0 11112 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:1F90 00000000:0000 0A 00000080:00000000 00:00000000 00000000  1000        0 11113 1 0000000000000000 100 0 0 10 0
+   3: 0100007F:D906 0100007F:0050 06 00000000:00000000 00:00000000 00000000  1000        0 0 1 0000000000000000 100 0 0 10 0
+";
+        let stats = parse_established(content, Protocol::Tcp);
+        assert_eq!(stats.len(), 1);
+        let s = &stats[&8080];
+        assert_eq!(s.established, 2);
+        assert_eq!(s.send_queue, 16);
+        assert_eq!(s.recv_queue, 33);
+    }
+
     #[test]
     fn parse_empty_content() {
         let entries = parse_proc_net_tcp("", Protocol::Tcp);
@@ -0,0 +1,80 @@
+// Pure /proc/[pid] parsing — no OS-specific syscalls, testable on any platform.
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+/// Environment variables worth surfacing: they tell apart otherwise identical
+/// processes (which port a node app was told to use, which env it runs in, and
+/// whether it runs inside a container).
+pub const ENV_WHITELIST: &[&str] = &[
+    "PORT",
+    "NODE_ENV",
+    "RAILS_ENV",
+    "container",
+    "KUBERNETES_SERVICE_HOST",
+];
+
+/// Extract the `starttime` field (clock ticks since boot) from `/proc/[pid]/stat`.
+///
+/// The `comm` field may contain spaces and parentheses, so fields are counted
+/// from the last `)` rather than split naively.
+pub fn parse_stat_starttime(stat: &str) -> Option<u64> {
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    // Fields after comm start at field 3 (state); starttime is field 22.
+    after_comm.split_whitespace().nth(22 - 3)?.parse().ok()
+}
+
+/// Extract `btime` (boot time, seconds since epoch) from `/proc/stat`.
+pub fn parse_btime(proc_stat: &str) -> Option<u64> {
+    proc_stat
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Keep only whitelisted variables from a NUL-separated `/proc/[pid]/environ`.
+pub fn filter_environ(environ: &[u8]) -> BTreeMap<String, String> {
+    environ
+        .split(|b| *b == 0)
+        .filter_map(|entry| {
+            let entry = std::str::from_utf8(entry).ok()?;
+            let (key, value) = entry.split_once('=')?;
+            ENV_WHITELIST
+                .contains(&key)
+                .then(|| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starttime_from_stat() {
+        let stat = "1234 (node) S 1 1234 1234 0 -1 4194560 2533 0 0 0 12 3 0 0 20 0 11 0 987654 1094713344 12000 18446744073709551615";
+        assert_eq!(parse_stat_starttime(stat), Some(987654));
+    }
+
+    #[test]
+    fn starttime_with_parens_in_comm() {
+        let stat = "42 (weird) name) S 1 42 42 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 555 0 0 0";
+        assert_eq!(parse_stat_starttime(stat), Some(555));
+    }
+
+    #[test]
+    fn btime_from_proc_stat() {
+        let content = "cpu  1 2 3 4\nintr 0\nbtime 1700000000\nprocesses 99\n";
+        assert_eq!(parse_btime(content), Some(1_700_000_000));
+        assert_eq!(parse_btime("cpu 1 2 3\n"), None);
+    }
+
+    #[test]
+    fn environ_keeps_only_whitelisted() {
+        let environ = b"PATH=/usr/bin\0PORT=3000\0NODE_ENV=production\0SECRET=x\0";
+        let env = filter_environ(environ);
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["PORT"], "3000");
+        assert_eq!(env["NODE_ENV"], "production");
+    }
+}
@@ -0,0 +1,146 @@
+//! The agent's scanner: the platform socket scanner plus any number of extra
+//! discovery sources (container port mappings, Unix sockets, custom probes).
+//!
+//! A source only reports ports; hostname, user and process attribution stay
+//! with the platform scanner. Each component names a capability, and the
+//! agent lists them in its hello so the client knows what the scans can
+//! contain.
+
+use crate::types::{AgentError, ListeningPort, ScanResult};
+
+use super::Scanner;
+
+/// TCP and TCP6 listeners, from the platform scanner.
+pub const TCP: &str = "tcp";
+
+/// A discovery source beside the platform scanner.
+pub trait Source: Send {
+    /// Capability advertised in the hello, e.g. `docker`.
+    fn capability(&self) -> &str;
+
+    /// Ports found by this source. `shallow` as for `Scanner::set_shallow`;
+    /// an error becomes a scan warning and the scan goes on.
+    fn ports(&mut self, shallow: bool) -> Result<Vec<ListeningPort>, String>;
+}
+
+pub struct Registry {
+    platform: Box<dyn Scanner + Send>,
+    sources: Vec<Box<dyn Source>>,
+    shallow: bool,
+}
+
+impl Registry {
+    pub fn new(platform: Box<dyn Scanner + Send>) -> Self {
+        Self {
+            platform,
+            sources: Vec::new(),
+            shallow: false,
+        }
+    }
+
+    /// Add a source; its ports follow the platform scanner's in each scan.
+    pub fn register(&mut self, source: Box<dyn Source>) {
+        self.sources.push(source);
+    }
+
+    /// What the scans can contain, platform scanner first.
+    pub fn capabilities(&self) -> Vec<String> {
+        std::iter::once(TCP)
+            .chain(self.sources.iter().map(|s| s.capability()))
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn scan(&mut self) -> Result<ScanResult, AgentError> {
+        let mut result = self.platform.scan()?;
+        for source in &mut self.sources {
+            match source.ports(self.shallow) {
+                Ok(ports) => result.ports.extend(ports),
+                Err(e) => result
+                    .warnings
+                    .push(format!("{}: {e}", source.capability())),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn set_shallow(&mut self, shallow: bool) {
+        self.shallow = shallow;
+        self.platform.set_shallow(shallow);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Protocol;
+
+    struct Fixed(Vec<u16>);
+
+    fn port(port: u16) -> ListeningPort {
+        ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: "127.0.0.1".into(),
+            port,
+            process: None,
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
+        }
+    }
+
+    impl Scanner for Fixed {
+        fn scan(&mut self) -> Result<ScanResult, AgentError> {
+            Ok(ScanResult {
+                agent_version: "0.1.0".into(),
+                hostname: "box".into(),
+                username: "dev".into(),
+                is_root: false,
+                uid: None,
+                ports: self.0.iter().map(|&p| port(p)).collect(),
+                warnings: Vec::new(),
+                scan_index: 0,
+                scan_duration_ms: None,
+                scanned_at: None,
+                host: None,
+            })
+        }
+    }
+
+    struct Containers {
+        fail: bool,
+    }
+
+    impl Source for Containers {
+        fn capability(&self) -> &str {
+            "docker"
+        }
+
+        fn ports(&mut self, shallow: bool) -> Result<Vec<ListeningPort>, String> {
+            if self.fail {
+                return Err("daemon not running".into());
+            }
+            Ok(if shallow { vec![] } else { vec![port(8080)] })
+        }
+    }
+
+    #[test]
+    fn sources_add_ports_and_capabilities() {
+        let mut registry = Registry::new(Box::new(Fixed(vec![22])));
+        registry.register(Box::new(Containers { fail: false }));
+        assert_eq!(registry.capabilities(), ["tcp", "docker"]);
+
+        let ports = |r: &mut Registry| -> Vec<u16> {
+            r.scan().unwrap().ports.iter().map(|p| p.port).collect()
+        };
+        assert_eq!(ports(&mut registry), [22, 8080]);
+        registry.set_shallow(true);
+        assert_eq!(ports(&mut registry), [22]);
+
+        let mut registry = Registry::new(Box::new(Fixed(vec![22])));
+        registry.register(Box::new(Containers { fail: true }));
+        let scan = registry.scan().unwrap();
+        assert_eq!(scan.ports.len(), 1);
+        assert_eq!(scan.warnings, ["docker: daemon not running"]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Ed25519 signatures over agent binaries.
+//!
+//! Release builds set `SSHFWD_AGENT_PUBKEY` (hex) at compile time and ship a
+//! raw 64-byte `sshfwd-agent.sig` next to each agent, produced by
+//! `scripts/build-agents.sh` from `SSHFWD_SIGNING_KEY`. The client checks the
+//! signature before uploading, and the agent checks its own binary on startup.
+//! Without the variable, `build.rs` reads `agent-pubkey.hex` (written by the
+//! release workflow before publishing). Builds without a public key (local
+//! development) skip verification.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+
+/// Hex-encoded public key baked in at build time, if any.
+pub const AGENT_PUBLIC_KEY: Option<&str> = option_env!("SSHFWD_AGENT_PUBKEY");
+
+/// File name suffix for the detached signature (`<binary>.sig`).
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The compiled-in public key is not 32 hex-encoded bytes.
+    BadPublicKey,
+    /// No signature was shipped with the binary.
+    MissingSignature,
+    /// The signature is not 64 bytes.
+    MalformedSignature,
+    /// The signature does not match the binary.
+    Mismatch,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadPublicKey => write!(f, "embedded public key is invalid"),
+            Self::MissingSignature => write!(f, "signature is missing"),
+            Self::MalformedSignature => write!(f, "signature is malformed"),
+            Self::Mismatch => write!(f, "signature does not match binary"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verify `data` against `signature` with the hex-encoded `public_key`.
+pub fn verify(public_key: &str, data: &[u8], signature: Option<&[u8]>) -> Result<(), VerifyError> {
+    let key_bytes: [u8; 32] = decode_hex(public_key.trim())
+        .and_then(|b| b.try_into().ok())
+        .ok_or(VerifyError::BadPublicKey)?;
+    let key = VerifyingKey::from_bytes(&key_bytes).map_err(|_| VerifyError::BadPublicKey)?;
+
+    let signature = signature.ok_or(VerifyError::MissingSignature)?;
+    let signature =
+        Signature::from_slice(signature).map_err(|_| VerifyError::MalformedSignature)?;
+
+    key.verify_strict(data, &signature)
+        .map_err(|_| VerifyError::Mismatch)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let chunks = s.as_bytes().chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+    chunks
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn keypair() -> (SigningKey, String) {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let public: String = signing
+            .verifying_key()
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        (signing, public)
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let (signing, public) = keypair();
+        let binary = b"\x7fELF fake agent";
+        let sig = signing.sign(binary).to_bytes();
+        assert_eq!(verify(&public, binary, Some(&sig)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_tampering_and_bad_inputs() {
+        let (signing, public) = keypair();
+        let sig = signing.sign(b"original").to_bytes();
+        assert_eq!(
+            verify(&public, b"tampered", Some(&sig)),
+            Err(VerifyError::Mismatch)
+        );
+        assert_eq!(
+            verify(&public, b"original", None),
+            Err(VerifyError::MissingSignature)
+        );
+        assert_eq!(
+            verify(&public, b"original", Some(&sig[..10])),
+            Err(VerifyError::MalformedSignature)
+        );
+        assert_eq!(
+            verify("abcd", b"original", Some(&sig)),
+            Err(VerifyError::BadPublicKey)
+        );
+    }
+}
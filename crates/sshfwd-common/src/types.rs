@@ -1,19 +1,175 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::frame::Framing;
+
 /// A listening port discovered on the remote host.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ListeningPort {
     pub protocol: Protocol,
+    /// The bound IP, or for `Protocol::Unix` the socket's path (`@name` for
+    /// an abstract socket, as `ss` writes it). With several `bindings`, the
+    /// widest of them.
     pub local_addr: String,
+    /// 0 for `Protocol::Unix`.
     pub port: u16,
     pub process: Option<ProcessInfo>,
+    /// Every address the process listens on with this port and protocol,
+    /// widest first. Empty from agents that report one address per port;
+    /// see `ListeningPort::bindings()`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<Binding>,
+    /// What the agent learned by connecting to the port (only with `--probe`).
+    #[serde(default)]
+    pub probe: Option<ServiceProbe>,
+    /// Connection activity on this socket, when the platform exposes it.
+    #[serde(default)]
+    pub stats: Option<ConnectionStats>,
+}
+
+impl ListeningPort {
+    /// The addresses the port is bound to, falling back to `local_addr`.
+    pub fn bindings(&self) -> Vec<Binding> {
+        if self.bindings.is_empty() {
+            vec![Binding {
+                addr: self.local_addr.clone(),
+                interface: None,
+            }]
+        } else {
+            self.bindings.clone()
+        }
+    }
+
+    /// Whether every address the port is bound to is loopback: only the
+    /// remote host itself can reach it, so a forward is the way in.
+    pub fn is_loopback_only(&self) -> bool {
+        !self.protocol.is_unix()
+            && self.bindings().iter().all(|b| {
+                b.addr
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| ip.to_canonical().is_loopback())
+            })
+    }
+
+    /// Where the socket listens: `addr:port`, `[addr]:port` for IPv6, or a
+    /// Unix socket's path.
+    pub fn bind(&self) -> String {
+        if self.protocol.is_unix() {
+            self.local_addr.clone()
+        } else if self.local_addr.contains(':') {
+            format!("[{}]:{}", self.local_addr, self.port)
+        } else {
+            format!("{}:{}", self.local_addr, self.port)
+        }
+    }
+}
+
+/// One address a listening port is bound to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Binding {
+    pub addr: String,
+    /// The network interface carrying `addr`; none for wildcard addresses or
+    /// when the agent couldn't tell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.interface {
+            Some(interface) => write!(f, "{interface}:{}", self.addr),
+            None => f.write_str(&self.addr),
+        }
+    }
+}
+
+/// Activity counters for a listening socket at scan time.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Established connections whose local port is this port.
+    pub established: u32,
+    /// Connections waiting in the accept queue.
+    pub accept_queue: u32,
+    /// Maximum accept backlog (0 when the platform doesn't report it).
+    pub backlog: u32,
+    /// Bytes received but not yet read, summed over established connections.
+    pub recv_queue: u64,
+    /// Bytes sent but not yet acknowledged, summed over established connections.
+    pub send_queue: u64,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+/// Result of probing a listening port from the remote host.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ServiceProbe {
+    /// Answered an HTTP request; `status` is the status line.
+    Http { status: String },
+    /// Completed a TLS handshake; `alpn` is the negotiated protocol, if any.
+    Tls { alpn: Option<String> },
+    /// Sent a greeting without being asked (SSH, SMTP, databases, ...).
+    Banner { text: String },
+}
+
+impl std::fmt::Display for ServiceProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http { status } => write!(f, "{status}"),
+            Self::Tls { alpn: Some(alpn) } => write!(f, "TLS ({alpn})"),
+            Self::Tls { alpn: None } => write!(f, "TLS"),
+            Self::Banner { text } => write!(f, "{text}"),
+        }
+    }
+}
+
+/// Socket protocol of a listening port. Variants are ordered the way the
+/// table lists ports sharing a number.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum Protocol {
     Tcp,
     Tcp6,
+    Udp,
+    Udp6,
+    /// A Unix domain stream socket; `ListeningPort::local_addr` is its path.
+    Unix,
+    /// A protocol this build doesn't know, from a newer agent. Only the
+    /// port is lost, not the scan.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Protocol {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Tcp6 => "tcp6",
+            Self::Udp => "udp",
+            Self::Udp6 => "udp6",
+            Self::Unix => "unix",
+            Self::Unknown => "?",
+        }
+    }
+
+    /// Whether SSH can forward the port: only TCP travels over a channel.
+    pub fn is_tcp(self) -> bool {
+        matches!(self, Self::Tcp | Self::Tcp6)
+    }
+
+    pub fn is_ipv6(self) -> bool {
+        matches!(self, Self::Tcp6 | Self::Udp6)
+    }
+
+    /// Whether the socket has a path instead of an address and port.
+    pub fn is_unix(self) -> bool {
+        self == Self::Unix
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Information about the process owning a listening socket.
@@ -23,6 +179,73 @@ pub struct ProcessInfo {
     pub name: String,
     pub cmdline: String,
     pub uid: u32,
+    /// Resolved executable path, when readable.
+    #[serde(default)]
+    pub exe: Option<String>,
+    /// Working directory, when readable.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Process start time in seconds since the Unix epoch.
+    #[serde(default)]
+    pub start_time: Option<u64>,
+    /// Whitelisted environment variables (see `scanner::proc_pid::ENV_WHITELIST`).
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Owned by another user, so only attributable because the agent runs
+    /// elevated (via sudo/doas).
+    #[serde(default)]
+    pub elevated: bool,
+    /// The container the process runs in, or publishes a port of
+    /// (`docker-proxy`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerInfo>,
+}
+
+impl ProcessInfo {
+    /// How the table and notifications name the process: the command line,
+    /// led by its container. A port proxy's own command line says nothing,
+    /// so it is named by the container alone.
+    pub fn command(&self) -> String {
+        match &self.container {
+            Some(container) if self.name == "docker-proxy" => container.to_string(),
+            Some(container) => format!("[{}] {}", container.label(), self.cmdline),
+            None => self.cmdline.clone(),
+        }
+    }
+}
+
+/// A container, as far as the agent could tell: the runtime and id come
+/// from the process's cgroup, name and image from the runtime's state files
+/// (readable by root only, for Docker).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContainerInfo {
+    /// `docker`, `podman`, `containerd` or `cri-o`.
+    pub runtime: String,
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+impl ContainerInfo {
+    /// The name, else the short id as `docker ps` prints it.
+    pub fn label(&self) -> &str {
+        match &self.name {
+            Some(name) => name,
+            None => &self.id[..self.id.len().min(12)],
+        }
+    }
+}
+
+impl std::fmt::Display for ContainerInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.runtime, self.label())?;
+        if let Some(image) = &self.image {
+            write!(f, " ({image})")?;
+        }
+        Ok(())
+    }
 }
 
 /// A single scan snapshot from the agent.
@@ -32,17 +255,164 @@ pub struct ScanResult {
     pub hostname: String,
     pub username: String,
     pub is_root: bool,
+    /// Uid of the user the scan is for: the SSH user, even when the agent
+    /// runs elevated. Older agents don't send it.
+    #[serde(default)]
+    pub uid: Option<u32>,
     pub ports: Vec<ListeningPort>,
     pub warnings: Vec<String>,
     pub scan_index: u64,
+    /// Milliseconds the agent spent on this scan, probes included. Older
+    /// agents don't send it.
+    #[serde(default)]
+    pub scan_duration_ms: Option<u64>,
+    /// When the scan started, in seconds since the Unix epoch by the remote
+    /// host's clock. Older agents don't send it.
+    #[serde(default)]
+    pub scanned_at: Option<u64>,
+    /// The host's OS, kernel, uptime and load. Older agents don't send it.
+    #[serde(default)]
+    pub host: Option<HostInfo>,
+}
+
+/// What the remote host runs and how busy it is; each part is `None` when
+/// the platform doesn't say.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HostInfo {
+    /// Distribution and version, e.g. `Ubuntu 24.04.1 LTS` or `macOS 14.6`.
+    #[serde(default)]
+    pub os: Option<String>,
+    /// Kernel release, as `uname -r` prints it.
+    #[serde(default)]
+    pub kernel: Option<String>,
+    #[serde(default)]
+    pub uptime_secs: Option<u64>,
+    /// 1, 5 and 15 minute load averages, as `uptime` prints them.
+    #[serde(default)]
+    pub load_avg: Option<String>,
+    /// Container runtimes installed (`docker`, `podman`, `containerd`), by
+    /// their sockets or binaries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub container_runtimes: Vec<String>,
+    /// The host runs a kubelet, so it's a Kubernetes node.
+    #[serde(default)]
+    pub kubernetes: bool,
+}
+
+impl HostInfo {
+    /// Whether scanning by container or namespace would find anything.
+    pub fn runs_containers(&self) -> bool {
+        self.kubernetes || !self.container_runtimes.is_empty()
+    }
+}
+
+/// How much the agent writes to its log (`--log-level`), most severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    /// The names `parse` accepts, for error messages.
+    pub const NAMES: &'static str = "error, warn, info or debug";
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" | "warning" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    /// As written in the log.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+        }
+    }
+}
+
+/// Version of the agent protocol, announced in the hello. Bumped only for
+/// changes an older client can't safely ignore; added fields with defaults
+/// and `serde(other)` variants don't count.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The agent's first line, before any scan.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AgentHello {
+    pub agent_version: String,
+    /// `SCHEMA_VERSION` of the agent, covering every response after this
+    /// one; 0 from agents that predate it.
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Discovery sources of the agent's scanner (`scanner::registry::TCP`,
+    /// ...); scans contain nothing the list doesn't name.
+    pub capabilities: Vec<String>,
+    /// How the responses after this one are framed: what the client asked
+    /// for, if the agent supports it. Older agents don't send it.
+    #[serde(default, skip_serializing_if = "Framing::is_json")]
+    pub framing: Framing,
 }
 
 /// Top-level response envelope from the agent (one per JSON line).
+// Nearly every response is a scan; boxing it would only add an allocation.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "status", rename_all = "lowercase")]
 pub enum AgentResponse {
+    Hello(AgentHello),
     Ok(ScanResult),
     Error(AgentError),
+    Reply(CommandReply),
+}
+
+/// A command sent from the client to the agent over stdin (one per JSON line).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum AgentCommand {
+    /// Send a signal to the process with the given PID.
+    Kill { pid: u32, signal: KillSignal },
+    /// Return the last `lines` lines of the agent's log file.
+    LogTail { lines: usize },
+    /// Scan now instead of waiting for the next interval.
+    Rescan,
+    /// Scan every `secs` seconds from now on (the client backing off).
+    SetInterval { secs: u64 },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KillSignal {
+    Term,
+    Kill,
+}
+
+impl std::fmt::Display for KillSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Term => write!(f, "SIGTERM"),
+            Self::Kill => write!(f, "SIGKILL"),
+        }
+    }
+}
+
+/// The agent's answer to an `AgentCommand`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandReply {
+    pub command: AgentCommand,
+    pub success: bool,
+    pub message: String,
+    /// Multi-line payload (e.g. log lines for `LogTail`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output: Vec<String>,
 }
 
 /// An error reported by the agent.
@@ -52,12 +422,47 @@ pub struct AgentError {
     pub message: String,
 }
 
+impl AgentError {
+    /// An I/O failure while doing `what`, classified by its cause.
+    pub fn io(what: &str, e: &std::io::Error) -> Self {
+        Self {
+            kind: AgentErrorKind::of_io(e),
+            message: format!("{what}: {e}"),
+        }
+    }
+}
+
+/// What went wrong, so the client can react: suggest elevation, scan less
+/// often, or just report it.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentErrorKind {
     ScanFailed,
     PermissionDenied,
     Unsupported,
+    /// The agent binary failed its signature check and refused to run.
+    IntegrityFailed,
+    /// Reading the OS's socket or process tables failed.
+    IoError,
+    /// A command or OS output the agent couldn't make sense of.
+    ParseError,
+    /// Out of file descriptors, processes or memory.
+    ResourceLimit,
+    /// A well-formed command this agent version doesn't implement.
+    NotImplemented,
+    /// A kind this build doesn't know, from a newer agent.
+    #[serde(other)]
+    Unknown,
+}
+
+impl AgentErrorKind {
+    pub fn of_io(e: &std::io::Error) -> Self {
+        match e.raw_os_error() {
+            Some(libc::EMFILE | libc::ENFILE | libc::ENOMEM | libc::EAGAIN) => Self::ResourceLimit,
+            _ if e.kind() == std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            _ => Self::IoError,
+        }
+    }
 }
 
 impl std::fmt::Display for AgentErrorKind {
@@ -66,6 +471,12 @@ impl std::fmt::Display for AgentErrorKind {
             Self::ScanFailed => write!(f, "scan_failed"),
             Self::PermissionDenied => write!(f, "permission_denied"),
             Self::Unsupported => write!(f, "unsupported"),
+            Self::IntegrityFailed => write!(f, "integrity_failed"),
+            Self::IoError => write!(f, "io_error"),
+            Self::ParseError => write!(f, "parse_error"),
+            Self::ResourceLimit => write!(f, "resource_limit"),
+            Self::NotImplemented => write!(f, "not_implemented"),
+            Self::Unknown => write!(f, "unknown"),
         }
     }
 }
@@ -80,6 +491,7 @@ mod tests {
             hostname: "server1".to_string(),
             username: "deploy".to_string(),
             is_root: false,
+            uid: Some(1000),
             ports: vec![
                 ListeningPort {
                     protocol: Protocol::Tcp,
@@ -90,6 +502,23 @@ mod tests {
                         name: "postgres".to_string(),
                         cmdline: "/usr/lib/postgresql/15/bin/postgres".to_string(),
                         uid: 108,
+                        exe: Some("/usr/lib/postgresql/15/bin/postgres".to_string()),
+                        cwd: Some("/var/lib/postgresql".to_string()),
+                        start_time: Some(1_700_000_000),
+                        env: BTreeMap::from([("PORT".to_string(), "5432".to_string())]),
+                        elevated: false,
+                        container: None,
+                    }),
+                    probe: Some(ServiceProbe::Banner {
+                        text: "binary (8 bytes)".to_string(),
+                    }),
+                    bindings: Vec::new(),
+                    stats: Some(ConnectionStats {
+                        established: 3,
+                        accept_queue: 0,
+                        backlog: 128,
+                        recv_queue: 0,
+                        send_queue: 12,
                     }),
                 },
                 ListeningPort {
@@ -97,13 +526,39 @@ mod tests {
                     local_addr: "::".to_string(),
                     port: 8080,
                     process: None,
+                    probe: Some(ServiceProbe::Http {
+                        status: "HTTP/1.1 200 OK".to_string(),
+                    }),
+                    bindings: Vec::new(),
+                    stats: None,
                 },
             ],
             warnings: vec!["permission denied reading /proc/999/fd".to_string()],
             scan_index: 42,
+            scan_duration_ms: Some(12),
+            scanned_at: None,
+            host: None,
         }
     }
 
+    #[test]
+    fn loopback_only_needs_every_binding_on_loopback() {
+        let ports = sample_scan_result().ports;
+        assert!(ports[0].is_loopback_only());
+        assert!(!ports[1].is_loopback_only());
+
+        let mut mixed = ports[0].clone();
+        mixed.bindings = ["127.0.0.1", "::ffff:127.0.0.1", "10.0.0.5"]
+            .map(|addr| Binding {
+                addr: addr.to_string(),
+                interface: None,
+            })
+            .to_vec();
+        assert!(!mixed.is_loopback_only());
+        mixed.bindings.pop();
+        assert!(mixed.is_loopback_only());
+    }
+
     #[test]
     fn scan_result_round_trip() {
         let result = sample_scan_result();
@@ -138,9 +593,13 @@ mod tests {
             hostname: "h".to_string(),
             username: "u".to_string(),
             is_root: true,
+            uid: Some(0),
             ports: vec![],
             warnings: vec![],
             scan_index: 0,
+            scan_duration_ms: None,
+            scanned_at: None,
+            host: None,
         });
         let json = serde_json::to_string(&response).unwrap();
         let value: serde_json::Value = serde_json::from_str(&json).unwrap();
@@ -160,10 +619,185 @@ mod tests {
         assert_eq!(value["kind"], "permission_denied");
     }
 
+    #[test]
+    fn agent_response_hello_json_structure() {
+        let json = r#"{"status":"hello","agent_version":"0.2.0","schema_version":1,"capabilities":["tcp","docker"]}"#;
+        let response: AgentResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            response,
+            AgentResponse::Hello(AgentHello {
+                agent_version: "0.2.0".to_string(),
+                schema_version: 1,
+                capabilities: vec!["tcp".to_string(), "docker".to_string()],
+                framing: Framing::Json,
+            })
+        );
+        assert_eq!(serde_json::to_string(&response).unwrap(), json);
+
+        let json = r#"{"status":"hello","agent_version":"0.1.0","capabilities":[],"extra":true}"#;
+        let AgentResponse::Hello(hello) = serde_json::from_str(json).unwrap() else {
+            panic!("expected a hello");
+        };
+        assert_eq!(hello.schema_version, 0);
+    }
+
+    #[test]
+    fn agent_command_round_trip() {
+        let cmd = AgentCommand::Kill {
+            pid: 4321,
+            signal: KillSignal::Term,
+        };
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(json, r#"{"command":"kill","pid":4321,"signal":"term"}"#);
+        let deserialized: AgentCommand = serde_json::from_str(&json).unwrap();
+        assert_eq!(cmd, deserialized);
+    }
+
+    #[test]
+    fn agent_response_reply_json_structure() {
+        let response = AgentResponse::Reply(CommandReply {
+            command: AgentCommand::Kill {
+                pid: 1,
+                signal: KillSignal::Kill,
+            },
+            success: false,
+            message: "refusing to signal pid 1".to_string(),
+            output: Vec::new(),
+        });
+        let json = serde_json::to_string(&response).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["status"], "reply");
+        assert_eq!(value["command"]["command"], "kill");
+        assert!(value.get("output").is_none());
+        let deserialized: AgentResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(response, deserialized);
+    }
+
+    #[test]
+    fn log_tail_reply_round_trip() {
+        let cmd = AgentCommand::LogTail { lines: 200 };
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(json, r#"{"command":"log_tail","lines":200}"#);
+        assert_eq!(
+            serde_json::to_string(&AgentCommand::Rescan).unwrap(),
+            r#"{"command":"rescan"}"#
+        );
+
+        let response = AgentResponse::Reply(CommandReply {
+            command: cmd,
+            success: true,
+            message: "2 lines".to_string(),
+            output: vec!["first".to_string(), "second".to_string()],
+        });
+        let json = serde_json::to_string(&response).unwrap();
+        let deserialized: AgentResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(response, deserialized);
+    }
+
+    #[test]
+    fn process_info_without_metadata_fields() {
+        // Older agents don't send exe/cwd/start_time/env.
+        let json = r#"{"pid":1,"name":"init","cmdline":"/sbin/init","uid":0}"#;
+        let info: ProcessInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.exe, None);
+        assert_eq!(info.start_time, None);
+        assert!(info.env.is_empty());
+    }
+
+    #[test]
+    fn service_probe_json_structure() {
+        let probe = ServiceProbe::Tls {
+            alpn: Some("h2".to_string()),
+        };
+        let value = serde_json::to_value(&probe).unwrap();
+        assert_eq!(value["kind"], "tls");
+        assert_eq!(value["alpn"], "h2");
+        assert_eq!(probe.to_string(), "TLS (h2)");
+    }
+
     #[test]
     fn protocol_serialization() {
         assert_eq!(serde_json::to_string(&Protocol::Tcp).unwrap(), "\"tcp\"");
         assert_eq!(serde_json::to_string(&Protocol::Tcp6).unwrap(), "\"tcp6\"");
+        assert_eq!(serde_json::to_string(&Protocol::Udp6).unwrap(), "\"udp6\"");
+    }
+
+    #[test]
+    fn unknown_protocols_keep_the_scan() {
+        let json = r#"{"protocol":"sctp","local_addr":"0.0.0.0","port":9,"process":null}"#;
+        let port: ListeningPort = serde_json::from_str(json).unwrap();
+        assert_eq!(port.protocol, Protocol::Unknown);
+        assert!(!port.protocol.is_tcp());
+        let udp: Protocol = serde_json::from_str("\"udp\"").unwrap();
+        assert_eq!(udp, Protocol::Udp);
+    }
+
+    #[test]
+    fn agent_error_kinds() {
+        let error = AgentError::io(
+            "failed to read /proc/net/tcp",
+            &std::io::Error::from_raw_os_error(libc::EMFILE),
+        );
+        assert_eq!(error.kind, AgentErrorKind::ResourceLimit);
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            AgentErrorKind::of_io(&denied),
+            AgentErrorKind::PermissionDenied
+        );
+        let newer: AgentErrorKind = serde_json::from_str("\"quota_exceeded\"").unwrap();
+        assert_eq!(newer, AgentErrorKind::Unknown);
+        assert_eq!(
+            serde_json::to_string(&AgentErrorKind::NotImplemented).unwrap(),
+            "\"not_implemented\""
+        );
+    }
+
+    #[test]
+    fn unix_sockets_bind_to_a_path() {
+        let json = r#"{"protocol":"unix","local_addr":"/run/postgresql/.s.PGSQL.5432","port":0,"process":null}"#;
+        let socket: ListeningPort = serde_json::from_str(json).unwrap();
+        assert!(socket.protocol.is_unix() && !socket.protocol.is_tcp());
+        assert_eq!(socket.bind(), "/run/postgresql/.s.PGSQL.5432");
+        assert!(serde_json::to_string(&socket)
+            .unwrap()
+            .contains(r#""protocol":"unix""#));
+
+        let tcp6 = ListeningPort {
+            protocol: Protocol::Tcp6,
+            local_addr: "::1".into(),
+            port: 8080,
+            ..socket
+        };
+        assert_eq!(tcp6.bind(), "[::1]:8080");
+    }
+
+    #[test]
+    fn containers_name_their_processes() {
+        let json = r#"{"pid":7,"name":"docker-proxy","cmdline":"/usr/bin/docker-proxy -proto tcp","uid":0}"#;
+        let mut proxy: ProcessInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(proxy.container, None);
+        assert_eq!(proxy.command(), "/usr/bin/docker-proxy -proto tcp");
+        assert!(!serde_json::to_string(&proxy).unwrap().contains("container"));
+
+        let container = ContainerInfo {
+            runtime: "docker".into(),
+            id: "3f4e8a1b2c9d0e7f6a5b".into(),
+            name: Some("web".into()),
+            image: Some("nginx:1.27".into()),
+        };
+        proxy.container = Some(container.clone());
+        assert_eq!(proxy.command(), "docker web (nginx:1.27)");
+
+        let node = ProcessInfo {
+            name: "node".into(),
+            cmdline: "node server.js".into(),
+            container: Some(ContainerInfo {
+                name: None,
+                ..container
+            }),
+            ..proxy
+        };
+        assert_eq!(node.command(), "[3f4e8a1b2c9d] node server.js");
     }
 
     #[test]
@@ -173,6 +807,9 @@ mod tests {
             local_addr: "0.0.0.0".to_string(),
             port: 80,
             process: None,
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
         };
         let json = serde_json::to_string(&port).unwrap();
         let deserialized: ListeningPort = serde_json::from_str(&json).unwrap();
@@ -0,0 +1,77 @@
+//! Noticing that the machine slept (or the process was stopped).
+//!
+//! Sessions rarely survive a laptop lid being closed, but nothing fails
+//! until keepalives and timeouts catch up. Code that wakes up regularly can
+//! instead compare each wake-up with the previous one: a gap far longer than
+//! its own period means time passed without it.
+
+use std::time::{Duration, Instant, SystemTime};
+
+/// Gaps between checks from this long count as a sleep; well above any
+/// caller's check period.
+pub const SLEEP_GAP: Duration = Duration::from_secs(30);
+
+/// Remembers the last check, by both clocks: the monotonic one stops while
+/// the machine is suspended on Linux and macOS, the wall clock doesn't (but
+/// may be set), so the larger of the two gaps is used.
+#[derive(Debug, Clone, Copy)]
+pub struct SleepDetector {
+    wall: SystemTime,
+    mono: Instant,
+}
+
+impl SleepDetector {
+    pub fn new() -> Self {
+        Self {
+            wall: SystemTime::now(),
+            mono: Instant::now(),
+        }
+    }
+
+    /// How long ago the previous check was, when that is a sleep.
+    pub fn check(&mut self) -> Option<Duration> {
+        self.observe(SystemTime::now(), Instant::now())
+    }
+
+    fn observe(&mut self, wall: SystemTime, mono: Instant) -> Option<Duration> {
+        // A wall clock set backwards counts as no time at all.
+        let wall_gap = wall.duration_since(self.wall).unwrap_or_default();
+        let mono_gap = mono.saturating_duration_since(self.mono);
+        self.wall = wall;
+        self.mono = mono;
+        let gap = wall_gap.max(mono_gap);
+        (gap >= SLEEP_GAP).then_some(gap)
+    }
+}
+
+impl Default for SleepDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_gaps_on_either_clock_are_sleeps() {
+        let mut detector = SleepDetector::new();
+        let (wall, mono) = (detector.wall, detector.mono);
+        let secs = Duration::from_secs;
+
+        assert_eq!(detector.observe(wall + secs(5), mono + secs(5)), None);
+        // Suspended: only the wall clock moved on.
+        assert_eq!(
+            detector.observe(wall + secs(605), mono + secs(6)),
+            Some(secs(600))
+        );
+        // Stopped: both did.
+        assert_eq!(
+            detector.observe(wall + secs(645), mono + secs(46)),
+            Some(secs(40))
+        );
+        // The wall clock was set back an hour.
+        assert_eq!(detector.observe(wall - secs(3600), mono + secs(47)), None);
+    }
+}
@@ -0,0 +1,436 @@
+//! A pretend host for trying sshfwd without SSH (`sshfwd --demo`).
+//!
+//! [`run_host`] stands in for `engine::run_host`: it takes the same command
+//! channels and reports to the same sinks, but its scans come from a
+//! [`Scenery`] of made-up services that start and stop over time. Local
+//! forwards bind real listeners that answer with a short HTTP page, so
+//! opening one in a browser shows something; reverse forwards only report
+//! being up.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use sshfwd_common::frame::Framing;
+use sshfwd_common::scanner::registry::TCP;
+use sshfwd_common::types::{
+    AgentCommand, AgentHello, CommandReply, HostInfo, ListeningPort, ProcessInfo, Protocol,
+    ScanResult, SCHEMA_VERSION,
+};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::task::AbortHandle;
+
+use crate::engine::{HostCommands, SessionEvent};
+use crate::forward::{ForwardCommand, ForwardEvent, ForwardKey, ForwardKind};
+use crate::sink::EventSink;
+
+/// What the demo host is called in tabs, titles and `--output json`.
+pub const DESTINATION: &str = "demo@sshfwd";
+
+const HOSTNAME: &str = "sshfwd-demo";
+const USERNAME: &str = "demo";
+const UID: u32 = 1000;
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+/// One in this many scans flips each flaky service.
+const FLIP_ODDS: u64 = 4;
+
+/// A made-up service: `flaky` ones come and go between scans.
+struct Service {
+    port: u16,
+    name: &'static str,
+    cmdline: &'static str,
+    uid: u32,
+    flaky: bool,
+}
+
+const SERVICES: &[Service] = &[
+    Service {
+        port: 22,
+        name: "sshd",
+        cmdline: "/usr/sbin/sshd -D",
+        uid: 0,
+        flaky: false,
+    },
+    Service {
+        port: 3000,
+        name: "node",
+        cmdline: "node node_modules/.bin/vite --port 3000",
+        uid: UID,
+        flaky: true,
+    },
+    Service {
+        port: 5432,
+        name: "postgres",
+        cmdline: "postgres -D /var/lib/postgresql/data",
+        uid: 999,
+        flaky: false,
+    },
+    Service {
+        port: 6379,
+        name: "redis-server",
+        cmdline: "redis-server *:6379",
+        uid: 999,
+        flaky: false,
+    },
+    Service {
+        port: 8000,
+        name: "python3",
+        cmdline: "python3 -m http.server 8000",
+        uid: UID,
+        flaky: true,
+    },
+    Service {
+        port: 8080,
+        name: "java",
+        cmdline: "java -jar build/libs/api.jar --server.port=8080",
+        uid: UID,
+        flaky: true,
+    },
+    Service {
+        port: 9229,
+        name: "node",
+        cmdline: "node --inspect=127.0.0.1:9229 server.js",
+        uid: UID,
+        flaky: true,
+    },
+];
+
+/// The demo host's listening services. Deterministic for a given seed.
+pub struct Scenery {
+    /// Pid of each `SERVICES` entry while it is up.
+    pids: Vec<Option<u32>>,
+    next_pid: u32,
+    rng: u64,
+    scan_index: u64,
+}
+
+impl Scenery {
+    pub fn new(seed: u64) -> Self {
+        let mut scenery = Self {
+            pids: vec![None; SERVICES.len()],
+            next_pid: 1200,
+            // xorshift is stuck at zero.
+            rng: seed | 1,
+            scan_index: 0,
+        };
+        for i in 0..SERVICES.len() {
+            scenery.pids[i] = Some(scenery.spawn());
+        }
+        scenery
+    }
+
+    fn spawn(&mut self) -> u32 {
+        self.next_pid += 1 + (self.random() % 40) as u32;
+        self.next_pid
+    }
+
+    fn random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Advance one scan interval: flaky services may start or stop; killed
+    /// ones come back under a new pid.
+    pub fn tick(&mut self) {
+        for (i, service) in SERVICES.iter().enumerate() {
+            let flip = self.random().is_multiple_of(FLIP_ODDS);
+            match self.pids[i] {
+                Some(_) if service.flaky && flip => self.pids[i] = None,
+                None if flip || !service.flaky => self.pids[i] = Some(self.spawn()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Stop the service running as `pid`, as a kill would.
+    pub fn kill(&mut self, pid: u32) -> bool {
+        match self.pids.iter_mut().find(|p| **p == Some(pid)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn scan(&mut self) -> ScanResult {
+        self.scan_index += 1;
+        let ports = SERVICES
+            .iter()
+            .zip(&self.pids)
+            .filter_map(|(service, pid)| {
+                let pid = (*pid)?;
+                Some(ListeningPort {
+                    protocol: Protocol::Tcp,
+                    local_addr: "127.0.0.1".into(),
+                    port: service.port,
+                    process: Some(ProcessInfo {
+                        pid,
+                        name: service.name.into(),
+                        cmdline: service.cmdline.into(),
+                        uid: service.uid,
+                        exe: None,
+                        cwd: None,
+                        start_time: None,
+                        env: Default::default(),
+                        elevated: false,
+                        container: None,
+                    }),
+                    probe: None,
+                    bindings: Vec::new(),
+                    stats: None,
+                })
+            })
+            .collect();
+        ScanResult {
+            agent_version: env!("CARGO_PKG_VERSION").to_string(),
+            hostname: HOSTNAME.into(),
+            username: USERNAME.into(),
+            is_root: false,
+            uid: Some(UID),
+            ports,
+            warnings: Vec::new(),
+            scan_index: self.scan_index,
+            scan_duration_ms: Some(1),
+            scanned_at: Some(sshfwd_common::time::epoch_secs(SystemTime::now())),
+            host: Some(HostInfo {
+                os: Some("Ubuntu 24.04.1 LTS".into()),
+                kernel: Some("6.8.0-45-generic".into()),
+                // A box up for a few days, one scan interval per scan.
+                uptime_secs: Some(4 * 86_400 + self.scan_index * 2),
+                load_avg: Some("0.42 0.37 0.31".into()),
+                container_runtimes: vec!["docker".into()],
+                kubernetes: false,
+            }),
+        }
+    }
+}
+
+/// Serve the demo host until the forward command channel closes.
+pub async fn run_host(
+    mut commands: HostCommands,
+    events: impl EventSink<SessionEvent>,
+    forward_events: impl EventSink<ForwardEvent>,
+) {
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    let mut scenery = Scenery::new(seed);
+    let mut listeners: HashMap<ForwardKey, AbortHandle> = HashMap::new();
+    let mut interval = tokio::time::interval(SCAN_INTERVAL);
+
+    events.emit(SessionEvent::Reconnected);
+    events.emit(SessionEvent::Hello(AgentHello {
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: SCHEMA_VERSION,
+        capabilities: vec![TCP.to_string()],
+        framing: Framing::Json,
+    }));
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                events.emit(SessionEvent::Scan(scenery.scan()));
+                scenery.tick();
+            }
+            Some(command) = commands.agent.recv() => {
+                let reply = answer(&mut scenery, command);
+                let rescan =
+                    reply.success && !matches!(reply.command, AgentCommand::LogTail { .. });
+                events.emit(SessionEvent::Reply(reply));
+                if rescan {
+                    events.emit(SessionEvent::Scan(scenery.scan()));
+                }
+            }
+            command = commands.forwards.recv() => {
+                let Some(command) = command else { break };
+                forward(command, &mut listeners, &forward_events).await;
+            }
+        }
+    }
+
+    for (_, handle) in listeners {
+        handle.abort();
+    }
+}
+
+fn answer(scenery: &mut Scenery, command: AgentCommand) -> CommandReply {
+    let (success, message, output) = match &command {
+        AgentCommand::Kill { pid, signal } if scenery.kill(*pid) => {
+            (true, format!("sent {signal} to {pid}"), Vec::new())
+        }
+        AgentCommand::Kill { pid, .. } => (false, format!("no process {pid}"), Vec::new()),
+        AgentCommand::LogTail { .. } => (
+            true,
+            "demo agent log".into(),
+            vec!["demo mode: no agent is running".into()],
+        ),
+        AgentCommand::Rescan => (true, "rescanned".into(), Vec::new()),
+        AgentCommand::SetInterval { secs } => (true, format!("scanning every {secs}s"), Vec::new()),
+    };
+    CommandReply {
+        command,
+        success,
+        message,
+        output,
+    }
+}
+
+async fn forward(
+    command: ForwardCommand,
+    listeners: &mut HashMap<ForwardKey, AbortHandle>,
+    events: &impl EventSink<ForwardEvent>,
+) {
+    match command {
+        ForwardCommand::Start {
+            kind,
+            remote_port,
+            local_port,
+            ..
+        }
+        | ForwardCommand::Reactivate {
+            kind,
+            remote_port,
+            local_port,
+            ..
+        } => {
+            let key = ForwardKey { kind, remote_port };
+            if let Some(handle) = listeners.remove(&key) {
+                handle.abort();
+            }
+            if kind == ForwardKind::Reverse {
+                events.emit(ForwardEvent::Started {
+                    kind,
+                    remote_port,
+                    local_port,
+                });
+                return;
+            }
+            match TcpListener::bind(("127.0.0.1", local_port)).await {
+                Ok(listener) => {
+                    let local_port = listener.local_addr().map_or(local_port, |a| a.port());
+                    let task = tokio::spawn(serve(listener, remote_port));
+                    listeners.insert(key, task.abort_handle());
+                    events.emit(ForwardEvent::Started {
+                        kind,
+                        remote_port,
+                        local_port,
+                    });
+                }
+                Err(e) => events.emit(ForwardEvent::BindError {
+                    kind,
+                    remote_port,
+                    message: e.to_string(),
+                }),
+            }
+        }
+        ForwardCommand::Stop { kind, remote_port } => {
+            if let Some(handle) = listeners.remove(&ForwardKey { kind, remote_port }) {
+                handle.abort();
+            }
+            events.emit(ForwardEvent::Stopped { kind, remote_port });
+        }
+        ForwardCommand::Pause { kind, remote_port } => {
+            if let Some(handle) = listeners.remove(&ForwardKey { kind, remote_port }) {
+                handle.abort();
+            }
+            events.emit(ForwardEvent::Paused { kind, remote_port });
+        }
+    }
+}
+
+/// Answer every connection with a one-line HTTP page naming the port.
+async fn serve(listener: TcpListener, remote_port: u16) {
+    let body = format!("sshfwd demo: remote port {remote_port}\n");
+    let response = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: {}\r\n\
+         connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    while let Ok((mut stream, _)) = listener.accept().await {
+        let response = response.clone();
+        tokio::spawn(async move {
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::mpsc;
+
+    fn ports(scenery: &mut Scenery) -> Vec<u16> {
+        scenery.scan().ports.iter().map(|p| p.port).collect()
+    }
+
+    #[test]
+    fn flaky_services_come_and_go() {
+        let mut scenery = Scenery::new(7);
+        assert_eq!(ports(&mut scenery).len(), SERVICES.len());
+
+        let mut seen_without = Vec::new();
+        for _ in 0..50 {
+            scenery.tick();
+            let now = ports(&mut scenery);
+            for service in SERVICES {
+                if !service.flaky {
+                    assert!(now.contains(&service.port), "{} went away", service.name);
+                } else if !now.contains(&service.port) && !seen_without.contains(&service.port) {
+                    seen_without.push(service.port);
+                }
+            }
+        }
+        assert_eq!(
+            seen_without.len(),
+            SERVICES.iter().filter(|s| s.flaky).count()
+        );
+
+        let scan = scenery.scan();
+        let postgres = scan.ports.iter().find(|p| p.port == 5432).unwrap();
+        let pid = postgres.process.as_ref().unwrap().pid;
+        assert!(scenery.kill(pid));
+        assert!(!ports(&mut scenery).contains(&5432));
+        assert!(!scenery.kill(pid));
+        scenery.tick();
+        assert!(ports(&mut scenery).contains(&5432));
+    }
+
+    #[tokio::test]
+    async fn local_forwards_answer_over_http() {
+        let (forwards_tx, forwards) = mpsc::unbounded_channel();
+        let (_agent_tx, agent) = mpsc::unbounded_channel();
+        let (events, _events_rx) = mpsc::unbounded_channel::<SessionEvent>();
+        let (forward_events, mut forward_rx) = mpsc::unbounded_channel::<ForwardEvent>();
+        let host = tokio::spawn(run_host(
+            HostCommands { forwards, agent },
+            events,
+            forward_events,
+        ));
+
+        forwards_tx
+            .send(ForwardCommand::Start {
+                kind: ForwardKind::Local,
+                remote_port: 3000,
+                local_port: 0,
+                remote_host: DESTINATION.into(),
+            })
+            .unwrap();
+        let Some(ForwardEvent::Started { local_port, .. }) = forward_rx.recv().await else {
+            panic!("forward did not start");
+        };
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", local_port))
+            .await
+            .unwrap();
+        let mut page = String::new();
+        stream.read_to_string(&mut page).await.unwrap();
+        assert!(page.ends_with("sshfwd demo: remote port 3000\n"), "{page}");
+
+        drop(forwards_tx);
+        host.await.unwrap();
+    }
+}
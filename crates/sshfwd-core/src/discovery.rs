@@ -0,0 +1,426 @@
+pub mod hub;
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::sync::mpsc;
+
+use sshfwd_common::frame::{self, Chunk, Decoder};
+use sshfwd_common::types::{
+    AgentCommand, AgentError, AgentHello, AgentResponse, CommandReply, ScanResult, SCHEMA_VERSION,
+};
+
+use crate::error::DiscoveryError;
+use crate::ssh::agent::{AgentManager, AgentOptions};
+use crate::ssh::session::Session;
+
+pub use hub::{DiscoveryHub, HubEvent};
+
+/// Consecutive unparseable lines tolerated before the stream is declared broken.
+/// Stray output (shell profiles echoing, MOTD) is usually a handful of lines.
+const MAX_CONSECUTIVE_PARSE_FAILURES: u32 = 20;
+/// Stray lines are quoted in warnings up to this many characters.
+const MAX_QUOTED_LINE: usize = 120;
+/// Bytes read from the agent's stdout at a time.
+const READ_CHUNK: usize = 16 * 1024;
+/// Agent stderr lines kept for the error when the agent exits.
+const STDERR_TAIL_LINES: usize = 5;
+
+/// Agent scan interval unless configured.
+pub const DEFAULT_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Scan cadence and the silence thresholds derived from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveryTiming {
+    pub scan_interval: Duration,
+    /// No scan for this long → UI shows discovery as stale (3 scans by default).
+    pub stale_after: Duration,
+    /// No agent output for this long → respawn the agent (6 scans by default).
+    pub timeout: Duration,
+    /// Respawns on one session without a scan in between before reconnecting.
+    pub max_respawns: u32,
+}
+
+impl DiscoveryTiming {
+    /// Timing for a scan interval, with thresholds at their defaults.
+    pub fn with_interval(scan_interval: Duration) -> Self {
+        let scan_interval = scan_interval.max(Duration::from_secs(1));
+        Self {
+            scan_interval,
+            stale_after: scan_interval * 3,
+            timeout: scan_interval * 6,
+            max_respawns: 3,
+        }
+    }
+
+    /// Timing from optional settings in seconds; unset thresholds are derived
+    /// from the scan interval.
+    pub fn from_secs(
+        scan_interval: Option<u64>,
+        stale_after: Option<u64>,
+        timeout: Option<u64>,
+        max_respawns: Option<u32>,
+    ) -> Self {
+        let secs = |s: u64| Duration::from_secs(s.max(1));
+        let defaults = Self::with_interval(scan_interval.map_or(DEFAULT_SCAN_INTERVAL, secs));
+        Self {
+            stale_after: stale_after.map_or(defaults.stale_after, secs),
+            timeout: timeout.map_or(defaults.timeout, secs),
+            max_respawns: max_respawns.unwrap_or(defaults.max_respawns),
+            ..defaults
+        }
+    }
+}
+
+impl DiscoveryTiming {
+    /// The next, slower interval for an agent short of resources: double
+    /// `current`, up to where scans still arrive before data counts as stale.
+    /// `None` once there.
+    pub fn backoff(&self, current: Duration) -> Option<Duration> {
+        let max = self
+            .stale_after
+            .saturating_sub(self.scan_interval)
+            .max(self.scan_interval);
+        let next = (current * 2).min(max);
+        (next > current).then_some(next)
+    }
+}
+
+impl Default for DiscoveryTiming {
+    fn default() -> Self {
+        Self::with_interval(DEFAULT_SCAN_INTERVAL)
+    }
+}
+
+/// Events produced by the discovery stream.
+#[derive(Debug)]
+pub enum DiscoveryEvent {
+    /// The agent started; sent once per stream, before any scan.
+    Hello(AgentHello),
+    Scan(ScanResult),
+    Warning(String),
+    /// The agent reported a failure and carries on; its kind says how to react.
+    AgentError(AgentError),
+    Reply(CommandReply),
+    Error(DiscoveryError),
+}
+
+/// Active discovery session — reads agent stdout as JSON lines or frames
+/// (see `sshfwd_common::frame`), writes `AgentCommand`s to the agent's stdin
+/// as JSON lines and surfaces its stderr as warnings.
+pub struct DiscoveryStream {
+    stdout: DuplexStream,
+    read_buf: Vec<u8>,
+    decoder: Decoder,
+    writer: Pin<Box<dyn AsyncWrite + Send>>,
+    stderr: mpsc::UnboundedReceiver<String>,
+    stderr_tail: VecDeque<String>,
+    parser: LineParser,
+    lenient_schema: bool,
+    /// An event to return before reading on.
+    queued: Option<DiscoveryEvent>,
+    _session: Session, // Keep the SSH connection alive
+}
+
+impl DiscoveryStream {
+    /// Deploy the agent and start the discovery stream.
+    ///
+    /// If `options.local_path` is provided, uses that binary directly (development override).
+    /// Otherwise, uses embedded or prebuilt binaries.
+    pub async fn start(session: Session, options: &AgentOptions) -> Result<Self, DiscoveryError> {
+        let manager = AgentManager::new(session.clone());
+
+        let stdio = manager
+            .deploy_and_spawn(options)
+            .await
+            .map_err(DiscoveryError::Ssh)?;
+
+        Ok(Self {
+            stdout: stdio.stdout,
+            read_buf: vec![0; READ_CHUNK],
+            decoder: Decoder::default(),
+            writer: stdio.stdin,
+            stderr: stdio.stderr,
+            stderr_tail: VecDeque::new(),
+            parser: LineParser::default(),
+            lenient_schema: options.lenient_schema,
+            queued: None,
+            _session: session,
+        })
+    }
+
+    /// Send a command to the agent. The reply arrives later as `DiscoveryEvent::Reply`.
+    pub async fn send_command(&mut self, command: &AgentCommand) -> Result<(), DiscoveryError> {
+        let mut line = serde_json::to_string(command)
+            .map_err(|e| DiscoveryError::Parse(format!("failed to encode command: {e}")))?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| DiscoveryError::Parse(format!("I/O error: {e}")))?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| DiscoveryError::Parse(format!("I/O error: {e}")))
+    }
+
+    /// Read the next event from the agent stream.
+    /// Returns None when the stream is exhausted.
+    pub async fn next_event(&mut self) -> Option<DiscoveryEvent> {
+        if let Some(event) = self.queued.take() {
+            return Some(event);
+        }
+        loop {
+            match self.decoder.next_chunk() {
+                Ok(Some(chunk)) => return Some(self.handle(chunk)),
+                Ok(None) => {}
+                Err(e) => return Some(DiscoveryEvent::Error(DiscoveryError::Parse(e))),
+            }
+            // Stderr first, so whatever the agent said before dying is logged
+            // before the end of stdout is reported.
+            let read = tokio::select! {
+                biased;
+                Some(line) = self.stderr.recv() => {
+                    self.remember_stderr(line.clone());
+                    return Some(DiscoveryEvent::Warning(format!("agent stderr: {line}")));
+                }
+                read = self.stdout.read(&mut self.read_buf) => read,
+            };
+            match read {
+                Ok(0) => {
+                    if let Some(chunk) = self.decoder.finish() {
+                        return Some(self.handle(chunk));
+                    }
+                    while let Ok(line) = self.stderr.try_recv() {
+                        self.remember_stderr(line);
+                    }
+                    return Some(DiscoveryEvent::Error(DiscoveryError::StreamEnded {
+                        stderr: self.stderr_tail.drain(..).collect(),
+                    }));
+                }
+                Ok(n) => self.decoder.push(&self.read_buf[..n]),
+                Err(e) => {
+                    return Some(DiscoveryEvent::Error(DiscoveryError::Parse(format!(
+                        "I/O error: {e}"
+                    ))))
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, chunk: Chunk) -> DiscoveryEvent {
+        let event = match chunk {
+            Chunk::Line(line) => self.parser.parse(&line),
+            Chunk::Frame(payload) => self.parser.parse_frame(&payload),
+        };
+        let DiscoveryEvent::Hello(hello) = event else {
+            return event;
+        };
+        match check_schema(&hello, self.lenient_schema) {
+            Ok(warning) => self.queued = warning.map(DiscoveryEvent::Warning),
+            Err(e) => return DiscoveryEvent::Error(e),
+        }
+        // The hello is the last JSON line when it announces frames.
+        self.decoder.set_framing(hello.framing);
+        DiscoveryEvent::Hello(hello)
+    }
+
+    fn remember_stderr(&mut self, line: String) {
+        if self.stderr_tail.len() == STDERR_TAIL_LINES {
+            self.stderr_tail.pop_front();
+        }
+        self.stderr_tail.push_back(line);
+    }
+}
+
+/// Turns agent stdout lines into events, skipping stray non-protocol output.
+#[derive(Debug, Default)]
+struct LineParser {
+    consecutive_failures: u32,
+}
+
+impl LineParser {
+    fn parse(&mut self, line: &str) -> DiscoveryEvent {
+        let response = match serde_json::from_str::<AgentResponse>(line) {
+            Ok(response) => response,
+            // Valid protocol framing with content we can't decode: an agent
+            // of another version, not stray output.
+            Err(e) if is_response(line) => {
+                return DiscoveryEvent::Error(DiscoveryError::AgentIncompatible(format!(
+                    "{e}: {}",
+                    quote_line(line)
+                )));
+            }
+            Err(e) => {
+                self.consecutive_failures += 1;
+                let quoted = quote_line(line);
+                if self.consecutive_failures >= MAX_CONSECUTIVE_PARSE_FAILURES {
+                    return DiscoveryEvent::Error(DiscoveryError::Parse(format!(
+                        "{} unparseable lines in a row, last: {e}: {quoted}",
+                        self.consecutive_failures
+                    )));
+                }
+                return DiscoveryEvent::Warning(format!("skipped non-agent output: {quoted}"));
+            }
+        };
+        self.consecutive_failures = 0;
+        Self::event(response)
+    }
+
+    /// A frame carries a response by construction; one that doesn't decode
+    /// comes from an incompatible agent.
+    fn parse_frame(&mut self, payload: &[u8]) -> DiscoveryEvent {
+        match frame::decode::<AgentResponse>(payload) {
+            Ok(response) => {
+                self.consecutive_failures = 0;
+                Self::event(response)
+            }
+            Err(e) => DiscoveryEvent::Error(DiscoveryError::AgentIncompatible(format!(
+                "{e} (in a {} byte frame)",
+                payload.len()
+            ))),
+        }
+    }
+
+    fn event(response: AgentResponse) -> DiscoveryEvent {
+        match response {
+            AgentResponse::Hello(hello) => DiscoveryEvent::Hello(hello),
+            AgentResponse::Ok(scan) => DiscoveryEvent::Scan(scan),
+            AgentResponse::Error(e) => DiscoveryEvent::AgentError(e),
+            AgentResponse::Reply(reply) => DiscoveryEvent::Reply(reply),
+        }
+    }
+}
+
+/// Refuses an agent with a newer protocol schema, or, when lenient, accepts
+/// it with a warning. Older agents are always fine: fields they don't send
+/// have defaults.
+fn check_schema(hello: &AgentHello, lenient: bool) -> Result<Option<String>, DiscoveryError> {
+    if hello.schema_version <= SCHEMA_VERSION {
+        return Ok(None);
+    }
+    if !lenient {
+        return Err(DiscoveryError::AgentTooNew {
+            agent_version: hello.agent_version.clone(),
+            schema_version: hello.schema_version,
+        });
+    }
+    Ok(Some(format!(
+        "agent {} speaks protocol schema {}, this sshfwd only {SCHEMA_VERSION}; \
+         what it doesn't understand is ignored",
+        hello.agent_version, hello.schema_version
+    )))
+}
+
+/// Whether `line` is a JSON object tagged like an `AgentResponse`.
+fn is_response(line: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(line)
+        .is_ok_and(|value| value.get("status").is_some_and(|s| s.is_string()))
+}
+
+/// `line` trimmed and cut to `MAX_QUOTED_LINE` characters, debug-quoted.
+fn quote_line(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(MAX_QUOTED_LINE) {
+        Some((cut, _)) => format!("{:?}…", &line[..cut]),
+        None => format!("{line:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_follow_scan_interval() {
+        let timing = DiscoveryTiming::default();
+        assert_eq!(timing.stale_after, Duration::from_secs(6));
+        assert_eq!(timing.timeout, Duration::from_secs(12));
+
+        let timing = DiscoveryTiming::from_secs(Some(10), None, Some(45), None);
+        assert_eq!(timing.stale_after, Duration::from_secs(30));
+        assert_eq!(timing.timeout, Duration::from_secs(45));
+        assert_eq!(timing.max_respawns, 3);
+    }
+
+    #[test]
+    fn backoff_stops_short_of_stale() {
+        let timing = DiscoveryTiming::default();
+        assert_eq!(
+            timing.backoff(timing.scan_interval),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(timing.backoff(Duration::from_secs(4)), None);
+
+        let timing = DiscoveryTiming::from_secs(Some(1), Some(20), None, None);
+        let steps: Vec<u64> =
+            std::iter::successors(Some(timing.scan_interval), |&d| timing.backoff(d))
+                .map(|d| d.as_secs())
+                .collect();
+        assert_eq!(steps, [1, 2, 4, 8, 16, 19]);
+    }
+
+    #[test]
+    fn stray_lines_warn_until_too_many_in_a_row() {
+        let mut parser = LineParser::default();
+        assert!(matches!(
+            parser.parse("Welcome to Ubuntu 24.04 LTS"),
+            DiscoveryEvent::Warning(w) if w.contains("\"Welcome to Ubuntu")
+        ));
+        // A valid line resets the streak.
+        let reply =
+            r#"{"status":"reply","command":{"command":"rescan"},"success":true,"message":""}"#;
+        assert!(matches!(parser.parse(reply), DiscoveryEvent::Reply(_)));
+        for _ in 1..MAX_CONSECUTIVE_PARSE_FAILURES {
+            assert!(matches!(parser.parse("noise"), DiscoveryEvent::Warning(_)));
+        }
+        assert!(matches!(
+            parser.parse("noise"),
+            DiscoveryEvent::Error(DiscoveryError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_responses_mean_an_incompatible_agent() {
+        let mut parser = LineParser::default();
+        assert!(matches!(
+            parser.parse(r#"{"status":"goodbye","reason":"newer agent"}"#),
+            DiscoveryEvent::Error(e @ DiscoveryError::AgentIncompatible(_)) if e.hint().is_some()
+        ));
+        // JSON that isn't a response is still just stray output.
+        assert!(matches!(
+            parser.parse(r#"{"motd":"hello"}"#),
+            DiscoveryEvent::Warning(_)
+        ));
+    }
+
+    #[test]
+    fn newer_schemas_are_refused_unless_lenient() {
+        let hello = |schema_version| AgentHello {
+            agent_version: "9.0.0".into(),
+            schema_version,
+            capabilities: Vec::new(),
+            framing: Default::default(),
+        };
+        assert!(matches!(check_schema(&hello(0), false), Ok(None)));
+        assert!(matches!(
+            check_schema(&hello(SCHEMA_VERSION), false),
+            Ok(None)
+        ));
+        let newer = hello(SCHEMA_VERSION + 1);
+        assert!(matches!(
+            check_schema(&newer, false),
+            Err(e @ DiscoveryError::AgentTooNew { .. })
+                if e.hint().is_some_and(|h| h.contains("9.0.0"))
+        ));
+        assert!(check_schema(&newer, true).unwrap().is_some());
+    }
+
+    #[test]
+    fn long_stray_lines_are_cut() {
+        let quoted = quote_line(&"é".repeat(500));
+        assert_eq!(quoted.chars().count(), MAX_QUOTED_LINE + 3);
+    }
+}
@@ -0,0 +1,149 @@
+//! Several discovery streams — one per host — merged into one channel.
+//!
+//! Each stream is driven by its own task; events come back tagged with the
+//! destination they belong to, so a frontend can serve many hosts from a
+//! single receive loop.
+
+use std::collections::HashMap;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use sshfwd_common::types::AgentCommand;
+
+use super::{DiscoveryEvent, DiscoveryStream};
+
+/// A discovery event and the destination whose agent produced it.
+#[derive(Debug)]
+pub struct HubEvent {
+    pub destination: String,
+    pub event: DiscoveryEvent,
+}
+
+struct HubStream {
+    commands: mpsc::UnboundedSender<AgentCommand>,
+    task: JoinHandle<()>,
+}
+
+/// Owns a set of `DiscoveryStream`s keyed by destination.
+///
+/// A stream's task ends after it reports a `DiscoveryEvent::Error`; reconnecting
+/// and re-adding the host is up to the caller. Dropping the hub stops all streams.
+pub struct DiscoveryHub {
+    streams: HashMap<String, HubStream>,
+    tx: mpsc::UnboundedSender<HubEvent>,
+    rx: mpsc::UnboundedReceiver<HubEvent>,
+}
+
+impl DiscoveryHub {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            streams: HashMap::new(),
+            tx,
+            rx,
+        }
+    }
+
+    /// Start driving `stream` for `destination`, replacing any stream already
+    /// registered under that name. Must be called within a tokio runtime.
+    pub fn add(&mut self, destination: impl Into<String>, stream: DiscoveryStream) {
+        let destination = destination.into();
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(pump(
+            destination.clone(),
+            stream,
+            command_rx,
+            self.tx.clone(),
+        ));
+        if let Some(old) = self
+            .streams
+            .insert(destination, HubStream { commands, task })
+        {
+            old.task.abort();
+        }
+    }
+
+    /// Stop and forget the stream for `destination`. Returns whether it existed.
+    pub fn remove(&mut self, destination: &str) -> bool {
+        match self.streams.remove(destination) {
+            Some(stream) => {
+                stream.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Queue a command for one host's agent; the reply arrives as a
+    /// `DiscoveryEvent::Reply` tagged with that host. Returns `false` when the
+    /// destination is unknown or its stream has ended.
+    pub fn send_command(&self, destination: &str, command: AgentCommand) -> bool {
+        self.streams
+            .get(destination)
+            .is_some_and(|s| s.commands.send(command).is_ok())
+    }
+
+    /// Destinations with a registered stream, including ones that have ended
+    /// but were not removed.
+    pub fn destinations(&self) -> impl Iterator<Item = &str> {
+        self.streams.keys().map(String::as_str)
+    }
+
+    /// Whether the stream for `destination` is still running.
+    pub fn is_running(&self, destination: &str) -> bool {
+        self.streams
+            .get(destination)
+            .is_some_and(|s| !s.task.is_finished())
+    }
+
+    /// Next event from any stream, in arrival order. Never returns `None`
+    /// while the hub is alive; with no running streams it waits for `add`.
+    pub async fn next_event(&mut self) -> Option<HubEvent> {
+        self.rx.recv().await
+    }
+}
+
+impl Default for DiscoveryHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DiscoveryHub {
+    fn drop(&mut self) {
+        for stream in self.streams.values() {
+            stream.task.abort();
+        }
+    }
+}
+
+/// Forward one stream's events and feed it queued commands until it errors
+/// or the hub goes away.
+async fn pump(
+    destination: String,
+    mut stream: DiscoveryStream,
+    mut commands: mpsc::UnboundedReceiver<AgentCommand>,
+    tx: mpsc::UnboundedSender<HubEvent>,
+) {
+    loop {
+        let event = tokio::select! {
+            Some(command) = commands.recv() => match stream.send_command(&command).await {
+                Ok(()) => continue,
+                Err(e) => DiscoveryEvent::Warning(format!("failed to send {command:?}: {e}")),
+            },
+            event = stream.next_event() => match event {
+                Some(event) => event,
+                None => break,
+            },
+        };
+        let fatal = matches!(event, DiscoveryEvent::Error(_));
+        let tagged = HubEvent {
+            destination: destination.clone(),
+            event,
+        };
+        if tx.send(tagged).is_err() || fatal {
+            break;
+        }
+    }
+}
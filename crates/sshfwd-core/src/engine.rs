@@ -0,0 +1,397 @@
+//! One host's session loop: discovery, forwarding and transparent reconnect.
+//!
+//! This is what the TUI, `--output json` and `sshfwd service` run per host.
+//! Everything it has to say goes to two [`EventSink`]s — [`SessionEvent`]s for
+//! scans and connection state, [`ForwardEvent`]s from the `ForwardManager` —
+//! so a frontend only converts events into its own messages.
+
+use std::time::Duration;
+
+use sshfwd_common::scanner;
+use sshfwd_common::types::{
+    AgentCommand, AgentError, AgentErrorKind, AgentHello, CommandReply, ScanResult,
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::clock::SleepDetector;
+use crate::discovery::{DiscoveryEvent, DiscoveryStream, DiscoveryTiming};
+use crate::error::{DiscoveryError, SshError};
+use crate::forward::access::ListenOptions;
+use crate::forward::tuning::TunnelOptions;
+use crate::forward::{ForwardCommand, ForwardEvent, ForwardManager};
+use crate::sink::EventSink;
+use crate::ssh;
+use crate::ssh::agent::AgentOptions;
+use crate::ssh::session::{Backend, ConnectStage, IncomingForward, Session};
+
+const LOCAL_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+/// How often a session cycle checks whether the machine slept.
+const SLEEP_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Discovery and connection state of one host.
+#[derive(Debug)]
+pub enum SessionEvent {
+    /// A (re)started agent and what its scans can contain.
+    Hello(AgentHello),
+    /// A remote scan.
+    Scan(ScanResult),
+    /// A scan of this machine's listening ports (for reverse forwards).
+    LocalScan(ScanResult),
+    LocalScanError(String),
+    Warning(String),
+    /// A failure the agent reported; discovery carries on.
+    AgentError(AgentError),
+    /// The agent's answer to an `AgentCommand`.
+    Reply(CommandReply),
+    /// The discovery stream failed; the session is about to be replaced.
+    Error(DiscoveryError),
+    /// Discovery timed out on a live session; the agent is being respawned.
+    Stalled,
+    /// The machine slept for this long; the session is being replaced
+    /// without waiting for it to time out.
+    Resumed(Duration),
+    ConnectionLost,
+    Reconnecting,
+    /// A session is up, the first one included: time to (re)start reverse
+    /// forwards.
+    Reconnected,
+}
+
+/// How a host is reached and served; fixed for the life of its loop.
+#[derive(Debug, Clone)]
+pub struct HostSettings {
+    pub destination: String,
+    pub agent: AgentOptions,
+    pub timing: DiscoveryTiming,
+    pub listen: ListenOptions,
+    pub tunnel: TunnelOptions,
+    pub transport: Transport,
+}
+
+/// How `connect` reaches a destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transport {
+    pub backend: Backend,
+    /// Attach to a connection another process shares, else share this one
+    /// (see `ssh::mux`). The system backend multiplexes through OpenSSH
+    /// instead and ignores this.
+    pub mux: bool,
+}
+
+/// Command channels the frontend keeps the senders of. They outlive
+/// reconnects, so commands queued while the host is down are not lost.
+pub struct HostCommands {
+    pub forwards: mpsc::UnboundedReceiver<ForwardCommand>,
+    pub agent: mpsc::UnboundedReceiver<AgentCommand>,
+}
+
+/// A first session with its agent already running (see `connect` and
+/// `DiscoveryStream::start`).
+pub struct StartedSession {
+    pub session: Session,
+    pub stream: DiscoveryStream,
+    /// Receiver paired with the `forwarded_tx` the session was connected with.
+    pub forwarded: mpsc::UnboundedReceiver<IncomingForward>,
+}
+
+/// Connect to `destination` over `transport`, reporting progress to
+/// `on_stage`.
+pub async fn connect(
+    destination: &str,
+    forwarded_tx: Option<mpsc::UnboundedSender<IncomingForward>>,
+    transport: Transport,
+    on_stage: &(dyn Fn(ConnectStage) + Send + Sync),
+) -> Result<Session, SshError> {
+    match transport.backend {
+        Backend::System => Session::connect_system(destination, on_stage).await,
+        Backend::Russh if transport.mux => {
+            Session::connect_shared(destination, forwarded_tx, on_stage).await
+        }
+        Backend::Russh => Session::connect_reporting(destination, forwarded_tx, on_stage).await,
+    }
+}
+
+/// Serve a host forever: session cycles wrapped in a reconnect loop.
+pub async fn run_host(
+    started: StartedSession,
+    mut commands: HostCommands,
+    events: impl EventSink<SessionEvent>,
+    forward_events: impl EventSink<ForwardEvent>,
+    settings: HostSettings,
+) {
+    let StartedSession {
+        mut session,
+        mut stream,
+        forwarded: mut forwarded_rx,
+    } = started;
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        // Notify model of fresh session — triggers Reverse forward reactivation
+        // (also fires on initial startup so persisted Reverse forwards activate).
+        events.emit(SessionEvent::Reconnected);
+
+        // Offer the session to later sshfwd processes for this destination
+        // while it lasts.
+        let shared = settings
+            .transport
+            .mux
+            .then(|| ssh::mux::share(&session, &settings.destination))
+            .flatten();
+
+        // Run one session cycle (blocks until stream ends).
+        run_session_cycle(
+            stream,
+            session.clone(),
+            forwarded_rx,
+            &mut commands,
+            &events,
+            forward_events.clone(),
+            &settings,
+        )
+        .await;
+
+        // Session ended — notify model.
+        events.emit(SessionEvent::ConnectionLost);
+        drop(shared);
+
+        // Reconnect with backoff; first attempt is immediate.
+        (session, forwarded_rx) = reconnect_with_backoff(&settings, &events, &mut backoff).await;
+
+        // Deploy agent on the new session.
+        stream = loop {
+            match DiscoveryStream::start(session.clone(), &settings.agent).await {
+                Ok(s) => break s,
+                Err(_) => {
+                    // Agent deploy failed — treat as another connection loss.
+                    events.emit(SessionEvent::ConnectionLost);
+                    (session, forwarded_rx) =
+                        reconnect_with_backoff(&settings, &events, &mut backoff).await;
+                }
+            }
+        };
+        // Reconnected is sent at the top of the loop, before run_session_cycle.
+    }
+}
+
+/// Run one session cycle: drive discovery and ForwardManager concurrently.
+///
+/// A discovery timeout (`timing.timeout`) respawns the agent on the same session, leaving forwards
+/// untouched. Returns when the stream errors, the session cannot host a new
+/// agent, respawns keep timing out, or the machine slept (see `clock`). The caller is responsible for
+/// reconnecting and calling this again with a fresh session / stream.
+/// `forwarded_rx` is consumed so callers can provide a fresh one on reconnect.
+async fn run_session_cycle(
+    mut stream: DiscoveryStream,
+    session: Session,
+    mut forwarded_rx: mpsc::UnboundedReceiver<IncomingForward>,
+    commands: &mut HostCommands,
+    events: &impl EventSink<SessionEvent>,
+    forward_events: impl EventSink<ForwardEvent>,
+    settings: &HostSettings,
+) {
+    let timing = settings.timing;
+
+    // Spawn local port scanner (aborted when this cycle ends).
+    let local_scan = spawn_local_scan(events.clone());
+
+    let manager = ForwardManager::new(session.clone(), forward_events)
+        .with_listen_options(settings.listen.clone())
+        .with_tunnel_options(settings.tunnel);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+
+    let manager_fut = manager.run(&mut commands.forwards, &mut forwarded_rx, shutdown_rx);
+    tokio::pin!(manager_fut);
+    let mut respawns = 0;
+    let mut interval = timing.scan_interval;
+    let mut sleep_check = tokio::time::interval(SLEEP_CHECK_INTERVAL);
+    let mut sleep = SleepDetector::new();
+
+    loop {
+        tokio::select! {
+            _ = &mut manager_fut => {
+                // Manager exited (cmd_tx closed or shutdown signal already fired).
+                break;
+            }
+            _ = sleep_check.tick() => {
+                if let Some(slept) = sleep.check() {
+                    // The server has most likely dropped us meanwhile; waiting
+                    // for keepalives to notice only delays the reconnect.
+                    events.emit(SessionEvent::Resumed(slept));
+                    let _ = shutdown_tx.send(());
+                    (&mut manager_fut).await;
+                    break;
+                }
+            }
+            Some(cmd) = commands.agent.recv() => {
+                if let Err(e) = stream.send_command(&cmd).await {
+                    events.emit(SessionEvent::Warning(e.to_string()));
+                }
+            }
+            result = tokio::time::timeout(timing.timeout, stream.next_event()) => {
+                match result {
+                    Ok(Some(DiscoveryEvent::Scan(scan))) => {
+                        respawns = 0;
+                        events.emit(SessionEvent::Scan(scan));
+                    }
+                    Ok(Some(DiscoveryEvent::Hello(hello))) => {
+                        events.emit(SessionEvent::Hello(hello));
+                    }
+                    Ok(Some(DiscoveryEvent::Warning(w))) => {
+                        events.emit(SessionEvent::Warning(w));
+                    }
+                    Ok(Some(DiscoveryEvent::AgentError(e))) => {
+                        let backoff = (e.kind == AgentErrorKind::ResourceLimit)
+                            .then(|| timing.backoff(interval))
+                            .flatten();
+                        events.emit(SessionEvent::AgentError(e));
+                        if let Some(slower) = backoff {
+                            interval = slower;
+                            let secs = slower.as_secs();
+                            let command = AgentCommand::SetInterval { secs };
+                            if let Err(e) = stream.send_command(&command).await {
+                                events.emit(SessionEvent::Warning(e.to_string()));
+                            } else {
+                                events.emit(SessionEvent::Warning(format!(
+                                    "agent is short of resources, scanning every {secs}s"
+                                )));
+                            }
+                        }
+                    }
+                    Ok(Some(DiscoveryEvent::Reply(reply))) => {
+                        events.emit(SessionEvent::Reply(reply));
+                    }
+                    Ok(Some(DiscoveryEvent::Error(e))) => {
+                        events.emit(SessionEvent::Error(e));
+                        let _ = shutdown_tx.send(());
+                        (&mut manager_fut).await;
+                        break;
+                    }
+                    Err(_) => {
+                        // No event within the timeout. The session may still be
+                        // fine (agent hung or killed), so respawn the agent on it while
+                        // the manager keeps serving forwards.
+                        respawns += 1;
+                        let respawned = if respawns > timing.max_respawns {
+                            None
+                        } else {
+                            events.emit(SessionEvent::Stalled);
+                            let start = tokio::time::timeout(
+                                timing.timeout,
+                                DiscoveryStream::start(session.clone(), &settings.agent),
+                            );
+                            tokio::select! {
+                                _ = &mut manager_fut => break,
+                                result = start => match result {
+                                    Ok(Ok(new_stream)) => Some(new_stream),
+                                    Ok(Err(e)) => {
+                                        events.emit(SessionEvent::Warning(format!(
+                                            "agent respawn failed: {e}"
+                                        )));
+                                        None
+                                    }
+                                    Err(_) => None,
+                                },
+                            }
+                        };
+                        match respawned {
+                            Some(new_stream) => stream = new_stream,
+                            None => {
+                                // Session is gone or unusable — hand over to reconnect.
+                                events.emit(SessionEvent::Error(DiscoveryError::Timeout(
+                                    timing.timeout,
+                                )));
+                                let _ = shutdown_tx.send(());
+                                (&mut manager_fut).await;
+                                break;
+                            }
+                        }
+                    }
+                    Ok(None) => unreachable!("next_event always returns Some"),
+                }
+            }
+        }
+    }
+
+    local_scan.abort();
+}
+
+/// Reconnect with exponential backoff until a new SSH session is established.
+/// Returns the new session together with its paired forwarded-channel receiver.
+/// The first attempt is immediate; sleep only occurs after a failed attempt.
+async fn reconnect_with_backoff(
+    settings: &HostSettings,
+    events: &impl EventSink<SessionEvent>,
+    backoff: &mut Duration,
+) -> (Session, mpsc::UnboundedReceiver<IncomingForward>) {
+    loop {
+        events.emit(SessionEvent::Reconnecting);
+
+        let (ftx, frx) = mpsc::unbounded_channel::<IncomingForward>();
+        match connect(
+            &settings.destination,
+            Some(ftx),
+            settings.transport,
+            &|_| {},
+        )
+        .await
+        {
+            Ok(new_session) => {
+                *backoff = Duration::from_secs(1);
+                return (new_session, frx);
+            }
+            Err(_) => {
+                tokio::time::sleep(*backoff).await;
+                *backoff = (*backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Spawns a background task that scans local listening ports every 2 seconds.
+/// Returns a JoinHandle that can be aborted to stop the scan.
+fn spawn_local_scan(events: impl EventSink<SessionEvent>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(LOCAL_SCAN_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = tokio::task::spawn_blocking(|| {
+                let mut scanner = scanner::create_scanner();
+                scanner.scan()
+            })
+            .await;
+            match result {
+                Ok(Ok(scan)) => events.emit(SessionEvent::LocalScan(scan)),
+                Ok(Err(e)) => {
+                    events.emit(SessionEvent::LocalScanError(format!(
+                        "{}: {}",
+                        e.kind, e.message
+                    )));
+                }
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_scan_reports_to_the_sink() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<SessionEvent>();
+        let task = spawn_local_scan(tx);
+        let event = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        task.abort();
+        assert!(matches!(
+            event,
+            SessionEvent::LocalScan(_) | SessionEvent::LocalScanError(_)
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+
+use sshfwd_common::types::AgentErrorKind;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SshError {
+    #[error("failed to connect to {destination}: {source}")]
+    Connection {
+        destination: String,
+        source: russh::Error,
+    },
+
+    /// Every key from ssh-agent, `IdentityFile` and the default key files
+    /// was refused.
+    #[error("authentication failed for {user}@{host}: no key accepted (tried {})", tried_list(.tried))]
+    AuthExhausted {
+        user: String,
+        host: String,
+        tried: Vec<String>,
+    },
+
+    /// The server's key differs from the one `~/.ssh/known_hosts` records.
+    #[error("host key for {host} does not match ~/.ssh/known_hosts line {line}")]
+    HostKeyMismatch { host: String, line: usize },
+
+    #[error("SSH config error: {0}")]
+    Config(String),
+
+    #[error("remote command failed: {0}")]
+    Remote(russh::Error),
+
+    #[error("agent deployment failed: {0}")]
+    AgentDeploy(String),
+
+    /// The agent uploaded fine but could not run from any install location.
+    #[error("cannot execute the agent in {}: noexec mount?", .dirs.join(", "))]
+    NoExec { dirs: Vec<String> },
+
+    /// The server refused to open another channel on the connection.
+    #[error("server refused a new channel: {0}")]
+    ChannelLimit(String),
+
+    #[error("SFTP error: {0}")]
+    Sftp(String),
+
+    #[error("no agent binary bundled for {0}")]
+    AgentUnavailable(String),
+
+    #[error("agent download failed: {0}")]
+    Download(String),
+
+    #[error("sudo requires a password to run the agent")]
+    SudoPasswordRequired,
+
+    #[error("could not elevate the agent: {0}")]
+    Elevation(String),
+
+    #[error("shared connection error: {0}")]
+    Mux(String),
+
+    #[error("system ssh: {0}")]
+    System(String),
+
+    #[error("local I/O error for {path}: {source}")]
+    LocalIo {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl SshError {
+    /// What the user can do about it, for errors with a known remedy.
+    pub fn hint(&self) -> Option<String> {
+        Some(match self {
+            Self::AuthExhausted { user, host, .. } => format!(
+                "Add your public key to ~/.ssh/authorized_keys of {user}@{host} \
+                 (ssh-copy-id), load it into ssh-agent, or name it with IdentityFile \
+                 in ~/.ssh/config. Password and keyboard-interactive logins need \
+                 --ssh-backend system."
+            ),
+            Self::HostKeyMismatch { host, .. } => format!(
+                "The host presents a different key than last time: it was \
+                 reinstalled, or someone is in the middle. If you expect the \
+                 change, remove the old key with `ssh-keygen -R {host}` and \
+                 connect again."
+            ),
+            Self::NoExec { .. } => "Point --remote-dir (or [agent] remote_dir) at a \
+                 directory on a filesystem that allows executables, e.g. one under \
+                 your home directory."
+                .to_string(),
+            Self::ChannelLimit(_) => "The server's MaxSessions limit (10 by default) is \
+                 reached, often by other programs sharing the connection. Close some, \
+                 raise MaxSessions in sshd_config, or lower [tunnel] max_opens."
+                .to_string(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    #[error("SSH error: {0}")]
+    Ssh(#[from] SshError),
+
+    /// The agent's stdout closed; `stderr` holds its last stderr lines (a panic
+    /// message or dynamic-linker error usually explains why).
+    #[error("agent stream ended unexpectedly{}", stderr_suffix(.stderr))]
+    StreamEnded { stderr: Vec<String> },
+
+    #[error("no output from agent for {}s", .0.as_secs())]
+    Timeout(std::time::Duration),
+
+    #[error("failed to parse agent response: {0}")]
+    Parse(String),
+
+    /// The agent answered in a protocol this client doesn't speak.
+    #[error("agent is incompatible with this sshfwd: {0}")]
+    AgentIncompatible(String),
+
+    /// The agent's hello announced a newer `SCHEMA_VERSION` than this client
+    /// knows, and `AgentOptions::lenient_schema` is off.
+    #[error(
+        "agent {agent_version} speaks protocol schema {schema_version}, this sshfwd only {}",
+        sshfwd_common::types::SCHEMA_VERSION
+    )]
+    AgentTooNew {
+        agent_version: String,
+        schema_version: u32,
+    },
+}
+
+impl DiscoveryError {
+    /// What the user can do about it, for errors with a known remedy.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::Ssh(e) => e.hint(),
+            Self::AgentIncompatible(_) => Some(
+                "The agent comes from another sshfwd version. Rebuild the binary given \
+                 to --agent-path, or run `sshfwd cleanup <host>` to remove stale \
+                 installs and connect again."
+                    .to_string(),
+            ),
+            Self::AgentTooNew { agent_version, .. } => Some(format!(
+                "Please update sshfwd to {agent_version} or newer. To connect anyway, \
+                 ignoring what this version doesn't understand, pass --lenient-schema \
+                 or set `lenient_schema = true` under [agent]."
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// What the user can do about an error the agent reported, where the kind
+/// suggests something.
+pub fn agent_error_hint(kind: AgentErrorKind) -> Option<String> {
+    match kind {
+        AgentErrorKind::PermissionDenied => Some(
+            "The agent can't read every socket or process as this user. Run it \
+             elevated with --sudo-agent (or `sudo = true` under [agent])."
+                .to_string(),
+        ),
+        AgentErrorKind::ResourceLimit => Some(
+            "The remote host is short of file descriptors, processes or memory; \
+             sshfwd scans less often until it reconnects."
+                .to_string(),
+        ),
+        AgentErrorKind::IntegrityFailed => Some(
+            "The installed agent doesn't match its signature. Run `sshfwd cleanup <host>` \
+             to remove it and connect again for a fresh copy."
+                .to_string(),
+        ),
+        AgentErrorKind::NotImplemented | AgentErrorKind::Unknown => {
+            Some("The agent comes from another sshfwd version; update the older side.".to_string())
+        }
+        _ => None,
+    }
+}
+
+fn tried_list(tried: &[String]) -> String {
+    if tried.is_empty() {
+        "no keys found".to_string()
+    } else {
+        tried.join(", ")
+    }
+}
+
+fn stderr_suffix(stderr: &[String]) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!("; agent stderr: {}", stderr.join(" | "))
+    }
+}
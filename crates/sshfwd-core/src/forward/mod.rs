@@ -0,0 +1,759 @@
+pub mod access;
+pub mod persistence;
+pub mod pool;
+pub mod tuning;
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::Instrument;
+
+use crate::sink::EventSink;
+use crate::ssh::session::{IncomingForward, Session};
+use access::ListenOptions;
+use pool::{OpenPool, OpenWait};
+use tuning::TunnelOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum ForwardKind {
+    #[default]
+    #[serde(alias = "local")]
+    Local,
+    #[serde(alias = "reverse")]
+    Reverse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ForwardKey {
+    pub kind: ForwardKind,
+    pub remote_port: u16,
+}
+
+impl ForwardKey {
+    pub fn local(remote_port: u16) -> Self {
+        Self {
+            kind: ForwardKind::Local,
+            remote_port,
+        }
+    }
+
+    pub fn reverse(remote_port: u16) -> Self {
+        Self {
+            kind: ForwardKind::Reverse,
+            remote_port,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardStatus {
+    Active,
+    Paused,
+    Starting,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForwardEntry {
+    pub local_port: u16,
+    pub status: ForwardStatus,
+    pub active_connections: u32,
+    /// Time local-forward connections spent queued for a channel open.
+    pub open_wait: OpenWait,
+}
+
+#[derive(Debug)]
+pub enum ForwardCommand {
+    Start {
+        kind: ForwardKind,
+        remote_port: u16,
+        local_port: u16,
+        remote_host: String,
+    },
+    Stop {
+        kind: ForwardKind,
+        remote_port: u16,
+    },
+    Reactivate {
+        kind: ForwardKind,
+        remote_port: u16,
+        local_port: u16,
+        remote_host: String,
+    },
+    Pause {
+        kind: ForwardKind,
+        remote_port: u16,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ForwardEvent {
+    Started {
+        kind: ForwardKind,
+        remote_port: u16,
+        local_port: u16,
+    },
+    Stopped {
+        kind: ForwardKind,
+        remote_port: u16,
+    },
+    Paused {
+        kind: ForwardKind,
+        remote_port: u16,
+    },
+    BindError {
+        kind: ForwardKind,
+        remote_port: u16,
+        message: String,
+    },
+    ConnectionCountChanged {
+        kind: ForwardKind,
+        remote_port: u16,
+        count: u32,
+    },
+    /// A connection was refused by `ListenOptions` before reaching the remote.
+    ConnectionDenied {
+        kind: ForwardKind,
+        remote_port: u16,
+        peer: String,
+        reason: String,
+    },
+    /// One tunneled connection finished.
+    ConnectionClosed {
+        kind: ForwardKind,
+        remote_port: u16,
+        /// Address of the client that connected (`ip:port`).
+        peer: String,
+        traffic: Traffic,
+        duration: Duration,
+        /// Time spent queued for a channel-open slot (always zero for
+        /// reverse forwards, whose channels the server opens).
+        open_wait: Duration,
+    },
+}
+
+/// Bytes moved through one tunneled connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Traffic {
+    pub to_remote: u64,
+    pub from_remote: u64,
+}
+
+/// How often changed connection counts are reported.
+const COUNT_TICK: Duration = Duration::from_millis(500);
+
+/// Open connections of one forward, updated by its connection tasks, and the
+/// value last sent as `ConnectionCountChanged`.
+#[derive(Default)]
+struct ConnCount {
+    live: Arc<AtomicU32>,
+    reported: u32,
+}
+
+struct ListenerHandle {
+    local_port: u16,
+    remote_host: String,
+    abort_handle: tokio::task::AbortHandle,
+}
+
+/// Owns the local listeners and remote `tcpip-forward`s of one session.
+///
+/// Driven by `ForwardCommand`s; progress is reported as `ForwardEvent`s to
+/// the `EventSink` it was created with, so a frontend can feed them into its
+/// own event loop.
+pub struct ForwardManager<S> {
+    session: Session,
+    event_tx: S,
+    listeners: HashMap<ForwardKey, ListenerHandle>,
+    /// Maps remote_port → local_port for active reverse forwards.
+    reverse_map: HashMap<u16, u16>,
+    listen: Arc<ListenOptions>,
+    tunnel: TunnelOptions,
+    pool: OpenPool,
+    counts: HashMap<ForwardKey, ConnCount>,
+}
+
+impl<S: EventSink<ForwardEvent>> ForwardManager<S> {
+    pub fn new(session: Session, event_tx: S) -> Self {
+        Self {
+            session,
+            event_tx,
+            listeners: HashMap::new(),
+            reverse_map: HashMap::new(),
+            listen: Arc::default(),
+            tunnel: TunnelOptions::default(),
+            pool: OpenPool::new(TunnelOptions::default().max_opens),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Bind address and peer restrictions for local forward listeners.
+    pub fn with_listen_options(mut self, listen: ListenOptions) -> Self {
+        self.listen = Arc::new(listen);
+        self
+    }
+
+    /// Copy buffer size, socket options and channel-open limit for tunneled
+    /// connections.
+    pub fn with_tunnel_options(mut self, tunnel: TunnelOptions) -> Self {
+        self.tunnel = tunnel;
+        self.pool = OpenPool::new(tunnel.max_opens);
+        self
+    }
+
+    /// Drive the forward manager for one session lifetime.
+    ///
+    /// `cmd_rx` and `forwarded_rx` are borrowed so the sidecar can reuse them
+    /// across reconnect cycles without recreating the command channel.
+    /// `shutdown_rx` fires when the sidecar wants a graceful teardown (e.g.
+    /// the discovery stream ended).  On shutdown all local listener tasks are
+    /// aborted so their ports are released before the next session binds them.
+    pub async fn run(
+        mut self,
+        cmd_rx: &mut mpsc::UnboundedReceiver<ForwardCommand>,
+        forwarded_rx: &mut mpsc::UnboundedReceiver<IncomingForward>,
+        mut shutdown_rx: tokio::sync::oneshot::Receiver<()>,
+    ) {
+        let mut count_tick = tokio::time::interval(COUNT_TICK);
+        count_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut shutdown_rx => {
+                    // Abort all local listeners so ports are released for the next session.
+                    for (_, handle) in self.listeners.drain() {
+                        handle.abort_handle.abort();
+                    }
+                    break;
+                }
+                cmd_opt = cmd_rx.recv() => {
+                    match cmd_opt {
+                        Some(cmd) => self.handle_command(cmd).await,
+                        None => break,
+                    }
+                }
+                inc_opt = forwarded_rx.recv() => {
+                    if let Some(inc) = inc_opt {
+                        self.handle_incoming(inc);
+                    }
+                }
+                _ = count_tick.tick() => self.report_counts(),
+            }
+        }
+    }
+
+    /// Send `ConnectionCountChanged` for every forward whose count moved
+    /// since the last tick: at most one event per forward however many
+    /// connections came and went.
+    fn report_counts(&mut self) {
+        for (key, count) in &mut self.counts {
+            let live = count.live.load(Ordering::Relaxed);
+            if live != count.reported {
+                count.reported = live;
+                self.event_tx.emit(ForwardEvent::ConnectionCountChanged {
+                    kind: key.kind,
+                    remote_port: key.remote_port,
+                    count: live,
+                });
+            }
+        }
+    }
+
+    /// Start counting `key`'s connections from zero. Connections of an
+    /// aborted listener never decrement, so each listener gets a new counter;
+    /// the last reported value is kept so the drop to zero is still sent.
+    fn reset_count(&mut self, key: ForwardKey) -> Arc<AtomicU32> {
+        let count = self.counts.entry(key).or_default();
+        count.live = Arc::default();
+        count.live.clone()
+    }
+
+    async fn handle_command(&mut self, cmd: ForwardCommand) {
+        match cmd {
+            ForwardCommand::Start {
+                kind,
+                remote_port,
+                local_port,
+                remote_host,
+            } => {
+                let key = ForwardKey { kind, remote_port };
+                match kind {
+                    ForwardKind::Local => self.handle_start_local(key, local_port, remote_host),
+                    ForwardKind::Reverse => self.handle_start_reverse(key, local_port).await,
+                }
+            }
+            ForwardCommand::Stop { kind, remote_port } => {
+                let key = ForwardKey { kind, remote_port };
+                match kind {
+                    ForwardKind::Local => self.handle_stop_local(key),
+                    ForwardKind::Reverse => self.handle_stop_reverse(key).await,
+                }
+            }
+            ForwardCommand::Reactivate {
+                kind,
+                remote_port,
+                local_port,
+                remote_host,
+            } => {
+                let key = ForwardKey { kind, remote_port };
+                match kind {
+                    ForwardKind::Local => {
+                        let port = self
+                            .listeners
+                            .get(&key)
+                            .map_or(local_port, |h| h.local_port);
+                        self.handle_start_local(key, port, remote_host);
+                    }
+                    ForwardKind::Reverse => self.handle_start_reverse(key, local_port).await,
+                }
+            }
+            ForwardCommand::Pause { kind, remote_port } => {
+                let key = ForwardKey { kind, remote_port };
+                match kind {
+                    ForwardKind::Local => self.handle_pause_local(key),
+                    ForwardKind::Reverse => {
+                        unreachable!("reconcile_forwards never pauses Reverse forwards")
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_start_local(&mut self, key: ForwardKey, local_port: u16, remote_host: String) {
+        if let Some(handle) = self.listeners.remove(&key) {
+            handle.abort_handle.abort();
+        }
+
+        let remote_port = key.remote_port;
+        let kind = key.kind;
+        let session = self.session.clone();
+        let event_tx = self.event_tx.clone();
+        let host = remote_host.clone();
+        let listen = self.listen.clone();
+        let tunnel = self.tunnel;
+        let pool = self.pool.clone();
+        let conn_count = self.reset_count(key);
+
+        let join_handle = tokio::spawn(async move {
+            let listener = match TcpListener::bind((listen.bind_address(), local_port)).await {
+                Ok(l) => l,
+                Err(e) => {
+                    event_tx.emit(ForwardEvent::BindError {
+                        kind,
+                        remote_port,
+                        message: e.to_string(),
+                    });
+                    return;
+                }
+            };
+
+            let actual_port = listener.local_addr().map(|a| a.port()).unwrap_or(0);
+            event_tx.emit(ForwardEvent::Started {
+                kind,
+                remote_port,
+                local_port: actual_port,
+            });
+
+            let mut connections = JoinSet::new();
+
+            loop {
+                tokio::select! {
+                    accept_result = listener.accept() => {
+                        match accept_result {
+                            Ok((mut local_stream, peer)) => {
+                                if let Err(e) = tunnel.apply(&local_stream) {
+                                    tracing::debug!("socket options on {peer}: {e}");
+                                }
+                                let session = session.clone();
+                                let host = host.clone();
+                                let event_tx = event_tx.clone();
+                                let conn_count = conn_count.clone();
+                                let listen = listen.clone();
+                                let pool = pool.clone();
+
+                                connections.spawn(async move {
+                                    let prefix = match listen.admit(&mut local_stream, peer.ip()).await {
+                                        Ok(prefix) => prefix,
+                                        Err(reason) => {
+                                            event_tx.emit(ForwardEvent::ConnectionDenied {
+                                                kind,
+                                                remote_port,
+                                                peer: peer.to_string(),
+                                                reason,
+                                            });
+                                            return Ok(());
+                                        }
+                                    };
+
+                                    conn_count.fetch_add(1, Ordering::Relaxed);
+                                    let opened = Instant::now();
+                                    let (slot, open_wait) = pool.acquire().await;
+                                    let (traffic, result) = tunnel_connection(
+                                        local_stream,
+                                        &prefix,
+                                        slot,
+                                        &session,
+                                        &host,
+                                        remote_port,
+                                        tunnel.buffer_size(),
+                                    )
+                                    .await;
+                                    event_tx.emit(ForwardEvent::ConnectionClosed {
+                                        kind,
+                                        remote_port,
+                                        peer: peer.to_string(),
+                                        traffic,
+                                        duration: opened.elapsed(),
+                                        open_wait,
+                                    });
+
+                                    conn_count.fetch_sub(1, Ordering::Relaxed);
+                                    result
+                                });
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    Some(_) = connections.join_next() => {
+                        // Connection finished, count already updated in task
+                    }
+                }
+            }
+        });
+
+        let abort_handle = join_handle.abort_handle();
+        self.listeners.insert(
+            key,
+            ListenerHandle {
+                local_port,
+                remote_host,
+                abort_handle,
+            },
+        );
+    }
+
+    fn handle_stop_local(&mut self, key: ForwardKey) {
+        if let Some(handle) = self.listeners.remove(&key) {
+            handle.abort_handle.abort();
+        }
+        self.counts.remove(&key);
+        self.event_tx.emit(ForwardEvent::Stopped {
+            kind: key.kind,
+            remote_port: key.remote_port,
+        });
+    }
+
+    fn handle_pause_local(&mut self, key: ForwardKey) {
+        if let Some(handle) = self.listeners.remove(&key) {
+            handle.abort_handle.abort();
+            self.reset_count(key);
+            // Re-insert to preserve local_port/remote_host for the Reactivate path.
+            // abort_handle is already aborted (abort() on it is a no-op); it is kept
+            // only because ListenerHandle requires one — handle_start_local will abort
+            // it again harmlessly when reactivating.
+            self.listeners.insert(
+                key,
+                ListenerHandle {
+                    local_port: handle.local_port,
+                    remote_host: handle.remote_host,
+                    abort_handle: handle.abort_handle,
+                },
+            );
+        }
+        self.event_tx.emit(ForwardEvent::Paused {
+            kind: key.kind,
+            remote_port: key.remote_port,
+        });
+    }
+
+    async fn handle_start_reverse(&mut self, key: ForwardKey, local_port: u16) {
+        let remote_port = key.remote_port;
+
+        match self.session.tcpip_forward(remote_port).await {
+            Ok(bound_port) => {
+                // bound_port may differ from remote_port if remote_port was 0
+                self.reverse_map.insert(bound_port, local_port);
+                self.reset_count(ForwardKey::reverse(bound_port));
+                self.event_tx.emit(ForwardEvent::Started {
+                    kind: ForwardKind::Reverse,
+                    remote_port: bound_port,
+                    local_port,
+                });
+            }
+            Err(e) => {
+                self.event_tx.emit(ForwardEvent::BindError {
+                    kind: ForwardKind::Reverse,
+                    remote_port,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+
+    async fn handle_stop_reverse(&mut self, key: ForwardKey) {
+        let remote_port = key.remote_port;
+        self.reverse_map.remove(&remote_port);
+        self.counts.remove(&key);
+        // Best-effort cancel — don't fail the stop if the session is dead
+        let _ = self.session.cancel_tcpip_forward(remote_port).await;
+        self.event_tx.emit(ForwardEvent::Stopped {
+            kind: ForwardKind::Reverse,
+            remote_port,
+        });
+    }
+
+    fn handle_incoming(&self, inc: IncomingForward) {
+        let Some(&local_port) = self.reverse_map.get(&inc.remote_port) else {
+            return; // Unknown port — ignore
+        };
+
+        let event_tx = self.event_tx.clone();
+        let tunnel = self.tunnel;
+        let conn_count = self
+            .counts
+            .get(&ForwardKey::reverse(inc.remote_port))
+            .map(|c| c.live.clone())
+            .unwrap_or_default();
+        let span = tracing::info_span!(
+            "forward.reverse_tunnel",
+            remote_port = inc.remote_port,
+            local_port,
+            peer = %inc.originator,
+            bytes_to_remote = tracing::field::Empty,
+            bytes_from_remote = tracing::field::Empty,
+        );
+        tokio::spawn(
+            async move {
+                let local_stream =
+                    match tokio::net::TcpStream::connect(("127.0.0.1", local_port)).await {
+                        Ok(s) => s,
+                        Err(_) => return,
+                    };
+                if let Err(e) = tunnel.apply(&local_stream) {
+                    tracing::debug!("socket options on localhost:{local_port}: {e}");
+                }
+
+                let opened = Instant::now();
+                conn_count.fetch_add(1, Ordering::Relaxed);
+                let (traffic, _) = splice(
+                    local_stream,
+                    inc.channel.into_stream(),
+                    tunnel.buffer_size(),
+                )
+                .await;
+                conn_count.fetch_sub(1, Ordering::Relaxed);
+                record_traffic(&traffic);
+                event_tx.emit(ForwardEvent::ConnectionClosed {
+                    kind: ForwardKind::Reverse,
+                    remote_port: inc.remote_port,
+                    peer: inc.originator,
+                    traffic,
+                    duration: opened.elapsed(),
+                    open_wait: Duration::ZERO,
+                });
+            }
+            .instrument(span),
+        );
+    }
+}
+
+/// Compare current scan ports against tracked forwards and produce
+/// Pause/Reactivate commands. Also updates entry statuses in-place.
+pub fn reconcile_forwards(
+    forwards: &mut HashMap<ForwardKey, ForwardEntry>,
+    current_remote_ports: &HashSet<u16>,
+    remote_host: &str,
+) -> Vec<ForwardCommand> {
+    let mut commands = Vec::new();
+
+    for (key, entry) in forwards.iter() {
+        if key.kind != ForwardKind::Local {
+            continue;
+        }
+        let remote_port = key.remote_port;
+        match entry.status {
+            ForwardStatus::Active | ForwardStatus::Starting => {
+                if !current_remote_ports.contains(&remote_port) {
+                    commands.push(ForwardCommand::Pause {
+                        kind: key.kind,
+                        remote_port,
+                    });
+                }
+            }
+            ForwardStatus::Paused => {
+                if current_remote_ports.contains(&remote_port) {
+                    commands.push(ForwardCommand::Reactivate {
+                        kind: key.kind,
+                        remote_port,
+                        local_port: entry.local_port,
+                        remote_host: remote_host.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Update statuses for the commands we just produced
+    for cmd in &commands {
+        match cmd {
+            ForwardCommand::Pause { kind, remote_port } => {
+                if let Some(entry) = forwards.get_mut(&ForwardKey {
+                    kind: *kind,
+                    remote_port: *remote_port,
+                }) {
+                    entry.status = ForwardStatus::Paused;
+                }
+            }
+            ForwardCommand::Reactivate {
+                kind, remote_port, ..
+            } => {
+                if let Some(entry) = forwards.get_mut(&ForwardKey {
+                    kind: *kind,
+                    remote_port: *remote_port,
+                }) {
+                    entry.status = ForwardStatus::Starting;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    commands
+}
+
+/// Tunnel one accepted connection; `prefix` (bytes already read from the
+/// client during admission) is sent to the remote first. `slot` is returned
+/// to the open pool once the channel open is answered.
+#[tracing::instrument(
+    name = "forward.tunnel",
+    skip(local_stream, prefix, slot, session),
+    fields(bytes_to_remote, bytes_from_remote)
+)]
+async fn tunnel_connection(
+    local_stream: tokio::net::TcpStream,
+    prefix: &[u8],
+    slot: tokio::sync::OwnedSemaphorePermit,
+    session: &Session,
+    remote_host: &str,
+    remote_port: u16,
+    buffer_size: usize,
+) -> (
+    Traffic,
+    Result<(), Box<dyn std::error::Error + Send + Sync>>,
+) {
+    let opened = session.open_direct_tcpip(remote_host, remote_port).await;
+    drop(slot);
+    let mut channel_stream = match opened {
+        Ok(stream) => stream,
+        Err(e) => return (Traffic::default(), Err(e.into())),
+    };
+    if let Err(e) = channel_stream.write_all(prefix).await {
+        return (Traffic::default(), Err(e.into()));
+    }
+    let (mut traffic, result) = splice(local_stream, channel_stream, buffer_size).await;
+    traffic.to_remote += prefix.len() as u64;
+    record_traffic(&traffic);
+    (traffic, result.map_err(Into::into))
+}
+
+fn record_traffic(traffic: &Traffic) {
+    let span = tracing::Span::current();
+    span.record("bytes_to_remote", traffic.to_remote);
+    span.record("bytes_from_remote", traffic.from_remote);
+}
+
+/// Copy between the local socket and the SSH channel until either direction
+/// ends, reading up to `buffer_size` bytes at a time and counting the bytes
+/// moved each way.
+async fn splice<L, R>(local: L, remote: R, buffer_size: usize) -> (Traffic, io::Result<()>)
+where
+    L: AsyncRead + AsyncWrite,
+    R: AsyncRead + AsyncWrite,
+{
+    let (to_remote, from_remote) = (AtomicU64::new(0), AtomicU64::new(0));
+    let (local_r, mut local_w) = tokio::io::split(local);
+    let (remote_r, mut remote_w) = tokio::io::split(remote);
+    let mut local_r = BufReader::with_capacity(
+        buffer_size,
+        Counted {
+            inner: local_r,
+            count: &to_remote,
+        },
+    );
+    let mut remote_r = BufReader::with_capacity(
+        buffer_size,
+        Counted {
+            inner: remote_r,
+            count: &from_remote,
+        },
+    );
+
+    let result = tokio::select! {
+        r = tokio::io::copy_buf(&mut local_r, &mut remote_w) => r,
+        r = tokio::io::copy_buf(&mut remote_r, &mut local_w) => r,
+    };
+    let traffic = Traffic {
+        to_remote: to_remote.load(Ordering::Relaxed),
+        from_remote: from_remote.load(Ordering::Relaxed),
+    };
+    (traffic, result.map(drop))
+}
+
+/// Reader that tallies the bytes read through it.
+struct Counted<'a, R> {
+    inner: R,
+    count: &'a AtomicU64,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Counted<'_, R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - before;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn splice_counts_both_directions() {
+        let (mut client, local) = tokio::io::duplex(64);
+        let (remote, mut server) = tokio::io::duplex(64);
+        let splice = tokio::spawn(splice(local, remote, 4));
+
+        let mut buf = [0u8; 16];
+        client.write_all(b"hello").await.unwrap();
+        server.read_exact(&mut buf[..5]).await.unwrap();
+        server.write_all(b"world!!").await.unwrap();
+        client.read_exact(&mut buf[..7]).await.unwrap();
+        drop(client);
+
+        let (traffic, result) = splice.await.unwrap();
+        assert!(result.is_ok());
+        assert_eq!(
+            traffic,
+            Traffic {
+                to_remote: 5,
+                from_remote: 7
+            }
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Bound on concurrent `direct-tcpip` channel opens.
+//!
+//! A browser loading a page through a forward can start dozens of
+//! connections at once, and some servers throttle or refuse bursts of channel
+//! opens. Each local-forward connection takes a slot from the session's
+//! `OpenPool` before asking for its channel and gives it back as soon as the
+//! open is answered, so the limit applies to pending opens, not to open
+//! tunnels. Connections beyond the limit queue in arrival order (tokio's
+//! semaphore is fair).
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Channel opens in flight per session unless configured otherwise.
+pub const DEFAULT_MAX_OPENS: usize = 16;
+
+/// Waits shorter than this don't count as queued.
+const QUEUED_AFTER: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone)]
+pub struct OpenPool {
+    permits: Arc<Semaphore>,
+}
+
+impl OpenPool {
+    /// `limit` is raised to 1.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(limit.max(1))),
+        }
+    }
+
+    /// Wait for a slot; returns it with the time spent queued. Drop the
+    /// permit once the channel open has been answered.
+    pub async fn acquire(&self) -> (OwnedSemaphorePermit, Duration) {
+        let start = Instant::now();
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("open pool semaphore is never closed");
+        (permit, start.elapsed())
+    }
+}
+
+/// Channel-open wait times of one forward.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenWait {
+    pub opens: u32,
+    /// Opens that had to wait for a slot.
+    pub queued: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl OpenWait {
+    pub fn record(&mut self, wait: Duration) {
+        self.opens += 1;
+        if wait >= QUEUED_AFTER {
+            self.queued += 1;
+        }
+        self.total += wait;
+        self.max = self.max.max(wait);
+    }
+
+    pub fn average(&self) -> Duration {
+        self.total / self.opens.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn opens_beyond_the_limit_queue() {
+        let pool = OpenPool::new(0);
+        let (first, wait) = pool.acquire().await;
+        let mut stats = OpenWait::default();
+        stats.record(wait);
+
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire().await.1 }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!queued.is_finished());
+        drop(first);
+        let wait = queued.await.unwrap();
+        assert!(wait >= Duration::from_millis(20));
+        stats.record(wait);
+
+        assert_eq!((stats.opens, stats.queued), (2, 1));
+        assert_eq!(stats.max, wait);
+        assert_eq!(stats.average(), stats.total / 2);
+    }
+}
@@ -0,0 +1,94 @@
+//! Socket and copy-buffer settings for tunneled connections.
+//!
+//! The defaults keep the operating system's socket behavior and tokio's 8 KiB
+//! copy buffer. Latency-sensitive forwards (databases, RPC) want
+//! `nodelay`; bulk transfers (file sync, backups) move more per syscall and
+//! per SSH channel packet with a larger `buffer_size`; long-idle connections
+//! behind NAT or firewalls survive with `keepalive`.
+
+use std::io;
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::TcpStream;
+
+use super::pool::DEFAULT_MAX_OPENS;
+
+/// tokio's own `io::copy` buffer size.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+/// Smallest accepted copy buffer; smaller values are raised to it.
+pub const MIN_BUFFER_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TunnelOptions {
+    /// Bytes copied per read in each direction.
+    pub buffer_size: usize,
+    /// Set `TCP_NODELAY` on local sockets (disable Nagle's algorithm).
+    pub nodelay: bool,
+    /// Idle time before TCP keepalive probes start on local sockets; `None`
+    /// leaves keepalive off.
+    pub keepalive: Option<Duration>,
+    /// Channel opens in flight per session; more connections queue (see
+    /// `forward::pool`).
+    pub max_opens: usize,
+}
+
+impl Default for TunnelOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            nodelay: false,
+            keepalive: None,
+            max_opens: DEFAULT_MAX_OPENS,
+        }
+    }
+}
+
+impl TunnelOptions {
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size.max(MIN_BUFFER_SIZE)
+    }
+
+    /// Apply the socket options to an accepted (local forward) or connected
+    /// (reverse forward) local socket.
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        if self.nodelay {
+            stream.set_nodelay(true)?;
+        }
+        if let Some(idle) = self.keepalive {
+            let keepalive = TcpKeepalive::new().with_time(idle);
+            SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn options_reach_the_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+
+        TunnelOptions::default().apply(&accepted).unwrap();
+        assert!(!accepted.nodelay().unwrap());
+        assert!(!SockRef::from(&accepted).keepalive().unwrap());
+
+        let options = TunnelOptions {
+            buffer_size: 16,
+            nodelay: true,
+            keepalive: Some(Duration::from_secs(30)),
+            max_opens: 1,
+        };
+        options.apply(&client).unwrap();
+        assert!(client.nodelay().unwrap());
+        assert!(SockRef::from(&client).keepalive().unwrap());
+        assert_eq!(options.buffer_size(), MIN_BUFFER_SIZE);
+    }
+}
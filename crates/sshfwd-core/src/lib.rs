@@ -0,0 +1,83 @@
+//! Remote port discovery and SSH port forwarding, without the TUI.
+//!
+//! This is the engine behind the `sshfwd` terminal UI, for tools that want to
+//! embed it (editor plugins, bots, scripts):
+//!
+//! - [`ssh::session::Session`] — an SSH connection honouring `~/.ssh/config`
+//! - [`ssh::mux`] — one session shared with other processes over a Unix socket
+//! - [`ssh::agent::AgentManager`] — deploys and spawns the remote agent
+//! - [`discovery::DiscoveryStream`] — the agent's scan results as async events
+//! - [`discovery::DiscoveryHub`] — several hosts' streams merged into one,
+//!   each event tagged with its destination
+//! - [`forward::ForwardManager`] — local and reverse port forwards on a session,
+//!   driven by [`forward::ForwardCommand`]s
+//! - [`sink::EventSink`] — where the manager reports [`forward::ForwardEvent`]s:
+//!   a crossbeam or tokio channel, or a closure wrapped in [`sink::FnSink`]
+//! - [`engine::run_host`] — discovery, forwarding and reconnect for one host,
+//!   reporting [`engine::SessionEvent`]s and forward events to two sinks
+//! - [`demo::run_host`] — the same for a made-up host, without SSH
+//!
+//! ```no_run
+//! use sshfwd_core::discovery::{DiscoveryEvent, DiscoveryStream};
+//! use sshfwd_core::ssh::agent::AgentOptions;
+//! use sshfwd_core::ssh::session::Session;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let session = Session::connect("dev@build-box", None).await?;
+//! let options = AgentOptions {
+//!     destination: "dev@build-box".into(),
+//!     download_agent: true,
+//!     ..Default::default()
+//! };
+//! let mut stream = DiscoveryStream::start(session, &options).await?;
+//! while let Some(event) = stream.next_event().await {
+//!     if let DiscoveryEvent::Scan(scan) = event {
+//!         for port in &scan.ports {
+//!             println!("{} {}", port.port, port.local_addr);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Without `AgentOptions::embedded` the agent comes from `prebuilt-agents/`
+//! next to the executable, the download cache, or a release download.
+//!
+//! Forwarding needs no agent. The manager runs until the command channel
+//! closes or `shutdown` fires; reverse forwards arrive through the channel
+//! handed to the session:
+//!
+//! ```no_run
+//! use sshfwd_core::forward::{ForwardCommand, ForwardEvent, ForwardKind, ForwardManager};
+//! use sshfwd_core::sink::FnSink;
+//! use sshfwd_core::ssh::session::Session;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let (forwarded_tx, mut forwarded_rx) = tokio::sync::mpsc::unbounded_channel();
+//! let session = Session::connect("dev@build-box", Some(forwarded_tx)).await?;
+//! let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+//! let (_shutdown, shutdown_rx) = tokio::sync::oneshot::channel();
+//!
+//! cmd_tx.send(ForwardCommand::Start {
+//!     kind: ForwardKind::Local,
+//!     remote_port: 5432,
+//!     local_port: 15432,
+//!     remote_host: "localhost".into(),
+//! })?;
+//! let events = FnSink(|event: ForwardEvent| println!("{event:?}"));
+//! ForwardManager::new(session, events)
+//!     .run(&mut cmd_rx, &mut forwarded_rx, shutdown_rx)
+//!     .await;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod clock;
+pub mod demo;
+pub mod discovery;
+pub mod engine;
+pub mod error;
+pub mod forward;
+pub mod sink;
+pub mod ssh;
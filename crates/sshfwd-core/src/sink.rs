@@ -0,0 +1,78 @@
+//! Where the engine reports what happens.
+//!
+//! [`ForwardManager`](crate::forward::ForwardManager) takes any
+//! [`EventSink`] for its [`ForwardEvent`](crate::forward::ForwardEvent)s. Channel
+//! senders whose message type converts from the event work as they are (the
+//! TUI passes a `crossbeam_channel::Sender<app::Message>`); [`FnSink`] wraps a
+//! closure for everything else.
+
+/// Receives events of type `E`. Called from tokio tasks, so it must not
+/// block; events sent after the receiving side is gone are dropped.
+pub trait EventSink<E>: Clone + Send + 'static {
+    fn emit(&self, event: E);
+}
+
+impl<E, M: From<E> + Send + 'static> EventSink<E> for crossbeam_channel::Sender<M> {
+    fn emit(&self, event: E) {
+        let _ = self.send(M::from(event));
+    }
+}
+
+impl<E, M: From<E> + Send + 'static> EventSink<E> for tokio::sync::mpsc::UnboundedSender<M> {
+    fn emit(&self, event: E) {
+        let _ = self.send(M::from(event));
+    }
+}
+
+/// A closure as an [`EventSink`].
+#[derive(Debug, Clone, Copy)]
+pub struct FnSink<F>(pub F);
+
+impl<E, F: Fn(E) + Clone + Send + 'static> EventSink<E> for FnSink<F> {
+    fn emit(&self, event: E) {
+        (self.0)(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, PartialEq)]
+    struct Wrapped(u32);
+
+    impl From<u32> for Wrapped {
+        fn from(n: u32) -> Self {
+            Wrapped(n)
+        }
+    }
+
+    fn emit_all(sink: impl EventSink<u32>) {
+        sink.emit(1);
+        sink.clone().emit(2);
+    }
+
+    #[test]
+    fn channels_and_closures_receive_events() {
+        let (tx, rx) = crossbeam_channel::unbounded::<Wrapped>();
+        emit_all(tx);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [Wrapped(1), Wrapped(2)]);
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<u32>();
+        emit_all(tx);
+        assert_eq!((rx.try_recv().ok(), rx.try_recv().ok()), (Some(1), Some(2)));
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        emit_all(FnSink({
+            let seen = seen.clone();
+            move |n| seen.lock().unwrap().push(n)
+        }));
+        assert_eq!(*seen.lock().unwrap(), [1, 2]);
+
+        // A closed channel just drops the event.
+        let (tx, rx) = crossbeam_channel::unbounded::<u32>();
+        drop(rx);
+        emit_all(tx);
+    }
+}
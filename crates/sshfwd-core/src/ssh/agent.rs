@@ -0,0 +1,1681 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use russh_sftp::protocol::FileAttributes;
+use sha2::{Digest, Sha256};
+use sshfwd_common::frame::Framing;
+use sshfwd_common::signature::{self, SIGNATURE_SUFFIX};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::SshError;
+use crate::ssh::delta::{self, Delta};
+use crate::ssh::install_dirs;
+use crate::ssh::release;
+use crate::ssh::session::{CommandOutput, ExecStdio, Session, STDIN_CHUNK};
+
+/// Defaults; both can be overridden via `AgentOptions` (config file or CLI).
+const REMOTE_AGENT_DIR: &str = ".sshfwd";
+const REMOTE_AGENT_NAME: &str = "sshfwd-agent";
+/// Linux truncates `/proc/<pid>/comm` to 15 bytes.
+const COMM_MAX_LEN: usize = 15;
+
+/// Detected remote platform (OS + architecture, plus libc on Linux).
+#[derive(Debug, Clone)]
+pub struct Platform {
+    pub os: String,
+    pub arch: String,
+    /// `None` when undetectable (or not Linux).
+    pub libc: Option<Libc>,
+}
+
+/// C library of a Linux host. Agents are static musl builds that run on
+/// either; glibc builds (`<os>-<arch>-gnu`) are optional and preferred on
+/// glibc hosts because they resolve users through NSS (LDAP, sssd).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    Musl,
+    Glibc,
+}
+
+impl Platform {
+    /// Returns the target triple of the default agent build, e.g. "x86_64-unknown-linux-musl".
+    pub fn target_triple(&self) -> String {
+        let arch = match self.arch.as_str() {
+            "riscv64" => "riscv64gc",
+            other => other,
+        };
+        let vendor_os = match (self.os.as_str(), self.arch.as_str()) {
+            ("linux", "armv7") => "unknown-linux-musleabihf",
+            ("linux", _) => "unknown-linux-musl",
+            ("darwin", _) => "apple-darwin",
+            _ => "unknown-unknown",
+        };
+        format!("{arch}-{vendor_os}")
+    }
+
+    /// `<os>-<arch>`, the `prebuilt-agents/` directory of the default build.
+    pub fn label(&self) -> String {
+        format!("{}-{}", self.os, self.arch)
+    }
+
+    /// `prebuilt-agents/` directories to try, most specific first.
+    pub fn agent_dirs(&self) -> Vec<String> {
+        let label = self.label();
+        match self.libc {
+            Some(Libc::Glibc) => vec![format!("{label}-gnu"), label],
+            _ => vec![label],
+        }
+    }
+}
+
+/// Client-side settings for deploying and running the agent.
+#[derive(Debug, Clone, Default)]
+pub struct AgentOptions {
+    /// Development override for the agent binary (`--agent-path`).
+    pub local_path: Option<PathBuf>,
+    /// Have the agent probe listening ports for HTTP/TLS/banners (`--probe`).
+    pub probe: bool,
+    /// `user@host` as given on the command line; keys the remembered install dir.
+    pub destination: String,
+    /// Remote directory for the agent binary, pid file and log (`--remote-dir` or
+    /// `agent.remote_dir` in the config). Replaces `~/.sshfwd` when set.
+    pub remote_dir: Option<String>,
+    /// Remote binary name (`--agent-name` or `agent.name`; default `sshfwd-agent`).
+    pub agent_name: Option<String>,
+    /// Print an upload progress bar to stderr (only before the TUI takes over).
+    pub upload_progress: bool,
+    /// Told how far an agent upload has got, for frontends that draw their
+    /// own progress.
+    pub on_upload: Option<UploadProgress>,
+    /// Agent log level; `Some` enables logging to `<remote dir>/agent.log` (`--agent-log`).
+    pub log_level: Option<String>,
+    /// Identifies this sshfwd process across reconnects (see `new_session_id`), so
+    /// concurrent sessions to one host each manage only their own agent.
+    pub session_id: String,
+    /// Run the agent through `sudo`/`doas` for full process visibility (`--sudo-agent`).
+    pub sudo: bool,
+    /// Password handed to `sudo -A` when passwordless elevation isn't allowed.
+    pub sudo_password: Option<Secret>,
+    /// Fetch the agent from GitHub releases when none is bundled for the
+    /// platform (`--download-agent`, or confirmed at the prompt).
+    pub download_agent: bool,
+    /// Flag/variable names whose values the agent masks in command lines
+    /// (`agent.redact`); `None` keeps the agent's defaults, empty disables them.
+    pub redact: Option<Vec<String>>,
+    /// Only report ports owned by the SSH user (`--only-mine` or `agent.only_mine`).
+    pub only_mine: bool,
+    /// Confine the agent with a seccomp filter (`--agent-seccomp` or `agent.seccomp`).
+    pub seccomp: bool,
+    /// Framing to ask the agent for (`--agent-framing` or `agent.framing`);
+    /// the hello says whether it took.
+    pub framing: Framing,
+    /// Accept agents with a newer protocol schema, with a warning, instead
+    /// of failing with `DiscoveryError::AgentTooNew` (`--lenient-schema` or
+    /// `agent.lenient_schema`).
+    pub lenient_schema: bool,
+    /// Seconds between scans (`--scan-interval` or `discovery.scan_interval`);
+    /// `None` keeps the agent's built-in default.
+    pub scan_interval: Option<u64>,
+    /// Nice value the agent applies to itself (`--agent-nice` or `agent.nice`).
+    pub nice: Option<i32>,
+    /// Milliseconds a scan may take before the following ones skip process
+    /// lookups and probes (`agent.scan_budget`).
+    pub scan_budget: Option<u64>,
+    /// Run the agent as the `sshfwd bench` traffic endpoint instead of
+    /// scanning (`--bench-server`).
+    pub bench: bool,
+    /// Agent binaries compiled into the frontend, tried before `prebuilt-agents/`.
+    pub embedded: Option<EmbeddedAgents>,
+}
+
+/// Lookups into agent binaries a frontend embeds at build time, keyed by
+/// `prebuilt-agents/` directory name (e.g. `linux-x86_64`).
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedAgents {
+    pub binary: fn(&str) -> Option<&'static [u8]>,
+    pub signature: fn(&str) -> Option<&'static [u8]>,
+}
+
+/// A password that stays out of `Debug` output.
+#[derive(Clone)]
+pub struct Secret(pub String);
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// Upload progress callback: bytes sent and total bytes of the transfer.
+#[derive(Clone)]
+pub struct UploadProgress(pub Arc<dyn Fn(usize, usize) + Send + Sync>);
+
+impl std::fmt::Debug for UploadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UploadProgress")
+    }
+}
+
+/// How commands that must run as root are issued on this host.
+enum Elevation {
+    None,
+    /// `sudo -n` or `doas -n` (no password needed).
+    Passwordless(&'static str),
+    /// `sudo -A`, with the password on the first line of stdin (see `wrap`).
+    SudoPassword(Secret),
+}
+
+impl Elevation {
+    /// Shell command running `command` this way.
+    ///
+    /// With a password, the shell reads it off stdin itself and sudo gets it
+    /// from a throwaway askpass script. `sudo -S` would leave it on stdin
+    /// whenever sudo doesn't ask (cached credentials, `NOPASSWD`), where the
+    /// agent would read it as its first command.
+    fn wrap(&self, command: &str) -> String {
+        match self {
+            Elevation::None => command.to_string(),
+            Elevation::Passwordless(prefix) => format!("{prefix} {command}"),
+            Elevation::SudoPassword(_) => format!(
+                r#"(IFS= read -r p && d=$(mktemp -d) || exit 1; \
+                 printf '%s\n' '#!/bin/sh' 'printf "%s\n" "$SSHFWD_SUDO_PASSWORD"' > "$d/askpass"; \
+                 chmod 700 "$d/askpass"; \
+                 SSHFWD_SUDO_PASSWORD="$p" SUDO_ASKPASS="$d/askpass" sudo -A -p '' {command}; \
+                 s=$?; rm -rf "$d"; exit $s)"#
+            ),
+        }
+    }
+
+    /// Bytes to write to stdin before anything else.
+    fn stdin_preamble(&self) -> Option<String> {
+        match self {
+            Elevation::SudoPassword(password) => Some(format!("{}\n", password.0)),
+            _ => None,
+        }
+    }
+}
+
+impl AgentOptions {
+    /// A fresh id for this sshfwd process: local pid plus start time, which is
+    /// unique enough across the machines sharing a remote account.
+    pub fn new_session_id() -> String {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        format!("{:x}-{:x}", std::process::id(), nanos & 0xffff_ffff_ffff)
+    }
+
+    /// Directory holding the pid file and log (relative paths are under `$HOME`).
+    fn state_dir(&self) -> String {
+        self.remote_dir
+            .as_deref()
+            .map(normalize_remote_dir)
+            .unwrap_or_else(|| REMOTE_AGENT_DIR.to_string())
+    }
+
+    fn agent_name(&self) -> &str {
+        self.agent_name.as_deref().unwrap_or(REMOTE_AGENT_NAME)
+    }
+
+    /// `<state dir>/sessions/<id>.pid`; the shared `agent.pid` without a session.
+    fn pid_file(&self) -> String {
+        if self.session_id.is_empty() {
+            format!("{}/agent.pid", self.state_dir())
+        } else {
+            format!("{}/sessions/{}.pid", self.state_dir(), self.session_id)
+        }
+    }
+
+    /// Tag for this session's upload temp files: the session id, or the
+    /// local pid without one.
+    fn upload_owner(&self) -> String {
+        if self.session_id.is_empty() {
+            format!("p{:x}", std::process::id())
+        } else {
+            self.session_id.clone()
+        }
+    }
+
+    fn log_file(&self) -> String {
+        format!("{}/agent.log", self.state_dir())
+    }
+
+    /// Quote `path` for the remote shell, anchoring relative paths at `"$HOME"`
+    /// when the agent will run elevated.
+    fn path_arg(&self, path: &str) -> String {
+        if self.sudo && !path.starts_with('/') {
+            format!("\"$HOME\"/'{path}'")
+        } else {
+            format!("'{path}'")
+        }
+    }
+
+    /// Command-line arguments passed to the remote agent.
+    fn agent_args(&self) -> String {
+        let mut args = String::new();
+        if !self.session_id.is_empty() {
+            args.push_str(&format!(" --session {}", self.session_id));
+        }
+        if self.bench {
+            args.push_str(" --bench-server");
+        }
+        if self.probe {
+            args.push_str(" --probe");
+        }
+        if self.only_mine {
+            args.push_str(" --only-mine");
+        }
+        if let Some(keys) = &self.redact {
+            // Keys are flag fragments; anything else could break the quoting.
+            let keys: Vec<&str> = keys
+                .iter()
+                .map(String::as_str)
+                .filter(|k| {
+                    k.chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                })
+                .collect();
+            args.push_str(&format!(" --redact '{}'", keys.join(",")));
+        }
+        if self.seccomp {
+            args.push_str(" --seccomp");
+        }
+        if !self.framing.is_json() {
+            args.push_str(&format!(" --framing {}", self.framing.as_str()));
+        }
+        if let Some(secs) = self.scan_interval {
+            args.push_str(&format!(" --interval {secs}"));
+        }
+        if let Some(nice) = self.nice {
+            args.push_str(&format!(" --nice {nice}"));
+        }
+        if let Some(ms) = self.scan_budget {
+            args.push_str(&format!(" --scan-budget {ms}"));
+        }
+        // Under sudo `$HOME` may be root's, so relative paths are anchored to
+        // the login user's home before elevating.
+        if self.remote_dir.is_some() || self.sudo {
+            args.push_str(&format!(
+                " --state-dir {}",
+                self.path_arg(&self.state_dir())
+            ));
+        }
+        if let Some(level) = &self.log_level {
+            args.push_str(&format!(
+                " --log-file {} --log-level {level}",
+                self.path_arg(&self.log_file())
+            ));
+        }
+        args
+    }
+}
+
+/// Agent binary plus its detached ed25519 signature, if one was shipped.
+struct AgentBinary {
+    bytes: Cow<'static, [u8]>,
+    signature: Option<Cow<'static, [u8]>>,
+}
+
+/// Manages the remote agent binary lifecycle.
+pub struct AgentManager {
+    session: Session,
+}
+
+impl AgentManager {
+    pub fn new(session: Session) -> Self {
+        Self { session }
+    }
+
+    /// Ensure the agent binary is up-to-date on the remote host, then spawn it.
+    /// Returns the agent's stdio (stdout stream, stdin writer, stderr lines).
+    ///
+    /// If `options.local_path` is provided, reads the binary from that file (development
+    /// override). Otherwise, uses the embedded binary for the detected platform, falling back
+    /// to `prebuilt-agents/` directory.
+    #[tracing::instrument(
+        name = "agent.deploy",
+        skip_all,
+        fields(destination = %options.destination),
+        err
+    )]
+    pub async fn deploy_and_spawn(&self, options: &AgentOptions) -> Result<ExecStdio, SshError> {
+        let platform = self.detect_platform().await?;
+        let mut binary = self.resolve_agent_binary(&platform, options).await?;
+
+        let remembered = install_dirs::load_install_dir(&options.destination);
+        let mut failures = Vec::new();
+        let mut noexec = Vec::new();
+
+        for base in self
+            .install_candidates(options.remote_dir.as_deref(), remembered.as_deref())
+            .await
+        {
+            let remote_dir = format!("{base}/{}", platform.arch);
+            let remote_path = format!("{remote_dir}/{}", options.agent_name());
+
+            let mut runs = match self
+                .install(&binary, &remote_dir, &remote_path, options)
+                .await
+            {
+                Ok(runs) => runs,
+                Err(e) => {
+                    failures.push(format!("{base}: {e}"));
+                    continue;
+                }
+            };
+            // A `-gnu` build may need a newer glibc than the host has; the
+            // static musl build runs regardless.
+            if !runs {
+                if let Some(musl) = self.musl_fallback(&platform, &binary, options).await {
+                    tracing::debug!(
+                        "{} agent doesn't run, trying the musl build",
+                        platform.label()
+                    );
+                    binary = musl;
+                    runs = self
+                        .install(&binary, &remote_dir, &remote_path, options)
+                        .await
+                        .unwrap_or(false);
+                }
+            }
+
+            // A `noexec` mount accepts the upload but refuses to run it.
+            if !runs {
+                failures.push(format!("{base}: cannot execute (noexec mount?)"));
+                noexec.push(base);
+                continue;
+            }
+
+            let is_default = remembered.is_none() && base == options.state_dir();
+            if !is_default && remembered.as_deref() != Some(base.as_str()) {
+                install_dirs::save_install_dir(&options.destination, &base);
+            }
+
+            let elevation = self.elevation(options).await?;
+
+            // Kill any stale agent before spawning
+            self.kill_stale_agent(options, &elevation).await;
+
+            return self.spawn_agent(&remote_path, options, &elevation).await;
+        }
+
+        if !noexec.is_empty() && noexec.len() == failures.len() {
+            return Err(SshError::NoExec { dirs: noexec });
+        }
+        Err(SshError::AgentDeploy(format!(
+            "no usable install location: {}",
+            failures.join("; ")
+        )))
+    }
+
+    /// Put `binary` and its signature at `remote_path`; whether it then runs.
+    async fn install(
+        &self,
+        binary: &AgentBinary,
+        remote_dir: &str,
+        remote_path: &str,
+        options: &AgentOptions,
+    ) -> Result<bool, SshError> {
+        let hash = sha256_hex(&binary.bytes);
+        self.ensure_uploaded(&binary.bytes, &hash, remote_dir, remote_path, options)
+            .await?;
+        // The agent checks itself against `<binary>.sig` on startup.
+        if let Some(sig) = &binary.signature {
+            self.write_signature(remote_path, sig, options).await?;
+        }
+        Ok(self.can_exec(remote_path).await)
+    }
+
+    /// The default (musl) build for a glibc host whose `-gnu` build `binary`
+    /// is; `None` when `binary` already is the musl build or there is none.
+    async fn musl_fallback(
+        &self,
+        platform: &Platform,
+        binary: &AgentBinary,
+        options: &AgentOptions,
+    ) -> Option<AgentBinary> {
+        if platform.libc != Some(Libc::Glibc) || options.local_path.is_some() {
+            return None;
+        }
+        let musl = Platform {
+            libc: None,
+            ..platform.clone()
+        };
+        self.resolve_agent_binary(&musl, options)
+            .await
+            .ok()
+            .filter(|musl| musl.bytes != binary.bytes)
+    }
+
+    /// Upload the agent to `remote_path` unless an identical binary is already there.
+    async fn ensure_uploaded(
+        &self,
+        agent_bytes: &[u8],
+        local_hash: &str,
+        remote_dir: &str,
+        remote_path: &str,
+        options: &AgentOptions,
+    ) -> Result<(), SshError> {
+        let needs_upload = match self.remote_hash(remote_path).await {
+            Ok(remote_hash) => remote_hash != local_hash,
+            Err(_) => true,
+        };
+
+        if needs_upload {
+            if let Err(exec_err) = self
+                .upload(agent_bytes, local_hash, remote_dir, remote_path, options)
+                .await
+            {
+                // Restricted shells may forbid pipelines but still allow SFTP.
+                self.upload_sftp(agent_bytes, local_hash, remote_dir, remote_path, options)
+                    .await
+                    .map_err(|sftp_err| {
+                        SshError::AgentDeploy(format!(
+                            "{exec_err}; SFTP fallback also failed: {sftp_err}"
+                        ))
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remote base directories to try, in order (see `candidate_dirs`).
+    async fn install_candidates(
+        &self,
+        configured: Option<&str>,
+        remembered: Option<&str>,
+    ) -> Vec<String> {
+        let env = self
+            .session
+            .exec(r#"printf '%s\n' "$XDG_RUNTIME_DIR" "$(id -u)""#)
+            .await
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+            .unwrap_or_default();
+        let mut lines = env.lines();
+        let xdg = lines.next().filter(|l| !l.is_empty());
+        let uid = lines.next().filter(|l| !l.is_empty());
+        candidate_dirs(configured, remembered, xdg, uid)
+    }
+
+    /// Whether the uploaded binary can actually run.
+    async fn can_exec(&self, remote_path: &str) -> bool {
+        self.session
+            .exec(&format!("'{remote_path}' --version"))
+            .await
+            .is_ok_and(|o| o.success)
+    }
+
+    /// Resolve the agent binary and its signature. Priority:
+    /// 1. Explicit local path override (--agent-path, signature from `<path>.sig`)
+    /// 2. Binary embedded by the frontend (`options.embedded`)
+    /// 3. Local prebuilt-agents/ directory, or the workspace's own
+    ///    `--profile release-agent` build for the default target triple
+    /// 4. Cache of agents downloaded from GitHub releases, downloading first
+    ///    when `options.download_agent` allows it
+    ///
+    /// When the build has a public key, bundled binaries must carry a valid
+    /// signature. An `--agent-path` override may be unsigned (local builds), but
+    /// a signature next to it still has to match.
+    async fn resolve_agent_binary(
+        &self,
+        platform: &Platform,
+        options: &AgentOptions,
+    ) -> Result<AgentBinary, SshError> {
+        // 1. Explicit override
+        if let Some(path) = options.local_path.as_deref() {
+            let bytes = read_local(path).await?;
+            let signature = read_signature(path).await?;
+            let binary = AgentBinary {
+                bytes: Cow::Owned(bytes),
+                signature: signature.map(Cow::Owned),
+            };
+            if binary.signature.is_some() {
+                verify_binary(&binary, &path.display().to_string())?;
+            }
+            return Ok(binary);
+        }
+
+        let label = platform.label();
+
+        // 2. Embedded binary
+        if let Some(embedded) = options.embedded {
+            for dir in platform.agent_dirs() {
+                if let Some(bytes) = (embedded.binary)(&dir) {
+                    let binary = AgentBinary {
+                        bytes: Cow::Borrowed(bytes),
+                        signature: (embedded.signature)(&dir).map(Cow::Borrowed),
+                    };
+                    verify_binary(&binary, &format!("embedded {dir}"))?;
+                    return Ok(binary);
+                }
+            }
+        }
+
+        // 3. Fallback to prebuilt-agents/ directory next to the executable,
+        //    then a workspace build (`target/<triple>/release-agent/`) when
+        //    sshfwd itself runs from `target/<profile>/`
+        let exe = std::env::current_exe().unwrap_or_default();
+        let exe_dir = exe.parent().unwrap_or_else(|| Path::new("."));
+        let prebuilt_paths: Vec<PathBuf> = platform
+            .agent_dirs()
+            .iter()
+            .map(|dir| {
+                exe_dir
+                    .join("prebuilt-agents")
+                    .join(dir)
+                    .join(REMOTE_AGENT_NAME)
+            })
+            .chain(exe_dir.parent().map(|target_dir| {
+                target_dir
+                    .join(platform.target_triple())
+                    .join("release-agent")
+                    .join(REMOTE_AGENT_NAME)
+            }))
+            .collect();
+
+        // 4. Previously downloaded release asset, or download it now
+        let cached_path = release::cached_path(&platform.os, &platform.arch);
+        let path = if let Some(prebuilt) = prebuilt_paths.into_iter().find(|p| p.exists()) {
+            prebuilt
+        } else if cached_path.exists() {
+            cached_path
+        } else if options.download_agent {
+            let (os, arch) = (platform.os.clone(), platform.arch.clone());
+            tokio::task::spawn_blocking(move || release::download(&os, &arch))
+                .await
+                .map_err(|e| SshError::Download(e.to_string()))??
+        } else {
+            return Err(SshError::AgentUnavailable(label));
+        };
+
+        let bytes = read_local(&path).await?;
+        let signature = read_signature(&path).await?;
+        let binary = AgentBinary {
+            bytes: Cow::Owned(bytes),
+            signature: signature.map(Cow::Owned),
+        };
+        verify_binary(&binary, &path.display().to_string())?;
+        Ok(binary)
+    }
+
+    /// Write the detached signature to `<remote_path>.sig`, over exec or SFTP.
+    async fn write_signature(
+        &self,
+        remote_path: &str,
+        sig: &[u8],
+        options: &AgentOptions,
+    ) -> Result<(), SshError> {
+        let sig_path = format!("{remote_path}{SIGNATURE_SUFFIX}");
+        let tmp_path = tmp_path(&sig_path, &sha256_hex(sig), &options.upload_owner());
+        let via_exec = self
+            .session
+            .exec_with_stdin(
+                &format!("cat > '{tmp_path}' && mv '{tmp_path}' '{sig_path}'"),
+                sig,
+                |_| {},
+            )
+            .await;
+        if via_exec.is_ok_and(|o| o.success) {
+            return Ok(());
+        }
+
+        let sftp = self.session.sftp().await?;
+        let mut file = sftp
+            .create(sig_path.as_str())
+            .await
+            .map_err(|e| SshError::Sftp(e.to_string()))?;
+        file.write_all(sig)
+            .await
+            .map_err(|e| SshError::Sftp(format!("write {sig_path}: {e}")))?;
+        file.shutdown()
+            .await
+            .map_err(|e| SshError::Sftp(format!("close {sig_path}: {e}")))?;
+        let _ = sftp.close().await;
+        Ok(())
+    }
+
+    /// Detect the remote system OS and architecture via `uname -sm`, and on
+    /// Linux the libc from `ldd --version` or a musl dynamic loader.
+    #[tracing::instrument(name = "agent.detect_platform", skip_all, err)]
+    pub async fn detect_platform(&self) -> Result<Platform, SshError> {
+        let output = self
+            .session
+            .exec("uname -sm; (ldd --version 2>&1; ls /lib/ld-musl-* 2>/dev/null) | head -n 3")
+            .await?;
+
+        let raw = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut lines = raw.lines();
+
+        let mut parts = lines.next().unwrap_or_default().split_whitespace();
+        let raw_os = parts
+            .next()
+            .ok_or_else(|| SshError::AgentDeploy("could not detect remote OS".to_string()))?;
+        let raw_arch = parts.next().ok_or_else(|| {
+            SshError::AgentDeploy("could not detect remote architecture".to_string())
+        })?;
+
+        let os = normalize_os(raw_os);
+        let arch = normalize_arch(raw_arch);
+        let libc = if os == "linux" {
+            parse_libc(&lines.collect::<Vec<_>>().join("\n"))
+        } else {
+            None
+        };
+
+        Ok(Platform { os, arch, libc })
+    }
+
+    /// Get SHA256 hash of the remote agent binary.
+    async fn remote_hash(&self, remote_path: &str) -> Result<String, SshError> {
+        // Try sha256sum first (Linux), fall back to openssl (macOS)
+        let cmd = format!(
+            "sha256sum '{remote_path}' 2>/dev/null || openssl dgst -sha256 '{remote_path}' 2>/dev/null"
+        );
+        let output = self.session.exec(&cmd).await?;
+
+        if !output.success {
+            return Err(SshError::AgentDeploy(format!(
+                "remote agent not found at {remote_path}"
+            )));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // sha256sum output: "hash  filename"
+        // openssl output: "SHA256(filename)= hash"
+        let hash = stdout
+            .split_whitespace()
+            .find(|s| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| {
+                SshError::AgentDeploy(format!("could not parse remote hash: {stdout}"))
+            })?;
+
+        Ok(hash.to_string())
+    }
+
+    /// Upload the agent binary to the remote host atomically.
+    ///
+    /// An older agent at `remote_path` is updated by block delta when enough
+    /// of it can be reused (see `delta`). The transfer is gzipped when the
+    /// remote has `gzip`, and the resulting temp file is hash-checked before
+    /// it replaces the old agent; a delta that fails the check is retried as
+    /// a full upload.
+    #[tracing::instrument(
+        name = "agent.upload",
+        skip(self, bytes, expected_hash, options),
+        fields(size = bytes.len()),
+        err
+    )]
+    async fn upload(
+        &self,
+        bytes: &[u8],
+        expected_hash: &str,
+        remote_dir: &str,
+        remote_path: &str,
+        options: &AgentOptions,
+    ) -> Result<(), SshError> {
+        let show_progress = options.upload_progress;
+
+        // Ensure directory exists
+        self.session
+            .exec(&format!("mkdir -p '{remote_dir}'"))
+            .await?;
+
+        // Write to a file only this session touches. A partial left by an
+        // interrupted run is taken over by renaming it, which only one
+        // session can do.
+        let tmp_path = tmp_path(remote_path, expected_hash, &options.upload_owner());
+        let _ = self
+            .session
+            .exec(&claim_partial(
+                &partial_path(remote_path, expected_hash),
+                &tmp_path,
+            ))
+            .await;
+        let _ = self
+            .session
+            .exec(&stale_tmp_cleanup(remote_path, &tmp_path))
+            .await;
+
+        let has_gzip = self
+            .session
+            .exec("command -v gzip >/dev/null 2>&1")
+            .await
+            .is_ok_and(|o| o.success);
+
+        // Keep whatever an interrupted upload already wrote, then append the rest
+        let offset = self.resumable_offset(&tmp_path, bytes).await;
+        if offset > 0 && show_progress {
+            eprintln!(
+                "Resuming agent upload at {} of {}",
+                format_mb(offset),
+                format_mb(bytes.len())
+            );
+        }
+        let delta = match offset {
+            0 => self.plan_delta(remote_path, bytes).await,
+            _ => None,
+        };
+        if let Some(delta) = &delta {
+            tracing::debug!(reused = delta.reused, "agent delta update");
+            if show_progress {
+                eprintln!(
+                    "Updating agent in place: reusing {} of {}",
+                    format_mb(delta.reused),
+                    format_mb(bytes.len())
+                );
+            }
+        }
+
+        let transfer = Transfer {
+            bytes,
+            expected_hash,
+            has_gzip,
+            tmp_path: &tmp_path,
+            remote_path,
+            show_progress,
+            on_progress: options.on_upload.as_ref(),
+        };
+        let transferred = match self.transfer(&transfer, offset, delta.as_ref()).await {
+            Err(e) if delta.is_some() => {
+                tracing::debug!("agent delta update failed, uploading in full: {e}");
+                self.transfer(&transfer, 0, None).await
+            }
+            result => result,
+        };
+        if let Err(e) = transferred {
+            // Leave what arrived for a later run to resume, if it's ours.
+            let _ = self
+                .session
+                .exec(&format!(
+                    "[ -f '{tmp_path}' ] && mv -f '{tmp_path}' '{}'",
+                    partial_path(remote_path, expected_hash)
+                ))
+                .await;
+            return Err(e);
+        }
+
+        // Atomic mv + chmod
+        let output = self
+            .session
+            .exec(&format!(
+                "mv '{tmp_path}' '{remote_path}' && chmod +x '{remote_path}'"
+            ))
+            .await?;
+
+        if !output.success {
+            return Err(SshError::AgentDeploy(format!(
+                "failed to install agent: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Block-delta plan against the agent installed at `remote_path`; `None`
+    /// when there is none, the remote lacks the tools, or little would be
+    /// reused.
+    async fn plan_delta(&self, remote_path: &str, bytes: &[u8]) -> Option<Delta> {
+        let output = self
+            .session
+            .exec(&delta::block_hashes_command(remote_path))
+            .await
+            .ok()
+            .filter(|o| o.success)?;
+        let hashes = delta::parse_block_hashes(&String::from_utf8_lossy(&output.stdout));
+        Delta::plan(bytes, &hashes)
+    }
+
+    /// Write the new binary to `tmp_path` (appending `bytes` from `offset`
+    /// on, or assembling it from `delta`) and verify what landed on disk.
+    async fn transfer(
+        &self,
+        t: &Transfer<'_>,
+        offset: usize,
+        delta: Option<&Delta>,
+    ) -> Result<(), SshError> {
+        let (bytes, tmp_path) = (t.bytes, t.tmp_path);
+        let literal_path = format!("{tmp_path}.delta");
+        let (data, target, redirect) = match delta {
+            Some(delta) => (&delta.literal[..], literal_path.as_str(), ">"),
+            None if offset > 0 => (&bytes[offset..], tmp_path, ">>"),
+            None => (bytes, tmp_path, ">"),
+        };
+
+        let (mut command, payload) = if t.has_gzip {
+            let compressed = gzip(data)
+                .map_err(|e| SshError::AgentDeploy(format!("failed to compress agent: {e}")))?;
+            (
+                format!("gzip -dc {redirect} '{target}'"),
+                Cow::Owned(compressed),
+            )
+        } else {
+            (format!("cat {redirect} '{target}'"), Cow::Borrowed(data))
+        };
+        if let Some(delta) = delta {
+            command = format!(
+                "({command} && {}); s=$?; rm -f '{literal_path}'; exit $s",
+                delta.assemble_command(t.remote_path, &literal_path, tmp_path)
+            );
+        }
+
+        // Upload via stdin pipe to temp file
+        let total = payload.len();
+        let output = self
+            .session
+            .exec_with_stdin(&command, &payload, |sent| {
+                if t.show_progress {
+                    eprint!("\r{}", progress_line(sent, total));
+                }
+                if let Some(UploadProgress(on_progress)) = t.on_progress {
+                    on_progress(sent, total);
+                }
+            })
+            .await;
+        if t.show_progress {
+            eprintln!();
+        }
+        let output = output?;
+
+        if !output.success {
+            return Err(SshError::AgentDeploy(format!(
+                "failed to upload agent: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        // Verify what actually landed on disk before installing it
+        let uploaded_hash = self.remote_hash(tmp_path).await?;
+        if uploaded_hash != t.expected_hash {
+            let _ = self.session.exec(&format!("rm -f '{tmp_path}'")).await;
+            return Err(SshError::AgentDeploy(format!(
+                "uploaded agent is corrupt (sha256 {uploaded_hash}, expected {})",
+                t.expected_hash
+            )));
+        }
+        Ok(())
+    }
+
+    /// Length of a previous partial upload at `tmp_path` that is a prefix of
+    /// `bytes` (0 if there is none, or it doesn't match).
+    async fn resumable_offset(&self, tmp_path: &str, bytes: &[u8]) -> usize {
+        let cmd = format!(
+            "wc -c < '{tmp_path}' 2>/dev/null && \
+             (sha256sum '{tmp_path}' 2>/dev/null || openssl dgst -sha256 '{tmp_path}' 2>/dev/null)"
+        );
+        let Ok(output) = self.session.exec(&cmd).await else {
+            return 0;
+        };
+        if !output.success {
+            return 0;
+        }
+        match parse_size_and_hash(&String::from_utf8_lossy(&output.stdout)) {
+            Some((len, hash))
+                if len > 0 && len < bytes.len() && sha256_hex(&bytes[..len]) == hash =>
+            {
+                len
+            }
+            _ => 0,
+        }
+    }
+
+    /// Upload the agent through the SFTP subsystem: temp file → chmod → rename.
+    ///
+    /// Hosts that need this path usually can't run `sha256sum` either, so the
+    /// upload is checked by size instead of hash.
+    async fn upload_sftp(
+        &self,
+        bytes: &[u8],
+        hash: &str,
+        remote_dir: &str,
+        remote_path: &str,
+        options: &AgentOptions,
+    ) -> Result<(), SshError> {
+        let sftp = self.session.sftp().await?;
+        let sftp_err = |e: russh_sftp::client::error::Error| SshError::Sftp(e.to_string());
+
+        // SFTP has no `mkdir -p`; existing components just fail harmlessly.
+        let mut dir = String::new();
+        for part in remote_dir.split('/') {
+            if !dir.is_empty() {
+                dir.push('/');
+            }
+            dir.push_str(part);
+            let _ = sftp.create_dir(dir.as_str()).await;
+        }
+
+        let tmp_path = tmp_path(remote_path, hash, &options.upload_owner());
+        let mut file = sftp.create(tmp_path.as_str()).await.map_err(sftp_err)?;
+        let mut sent = 0;
+        for chunk in bytes.chunks(STDIN_CHUNK) {
+            file.write_all(chunk)
+                .await
+                .map_err(|e| SshError::Sftp(format!("write {tmp_path}: {e}")))?;
+            sent += chunk.len();
+            if let Some(UploadProgress(on_progress)) = &options.on_upload {
+                on_progress(sent, bytes.len());
+            }
+        }
+        file.shutdown()
+            .await
+            .map_err(|e| SshError::Sftp(format!("close {tmp_path}: {e}")))?;
+
+        let size = sftp
+            .metadata(tmp_path.as_str())
+            .await
+            .map_err(sftp_err)?
+            .size;
+        if size != Some(bytes.len() as u64) {
+            let _ = sftp.remove_file(tmp_path.as_str()).await;
+            return Err(SshError::AgentDeploy(format!(
+                "SFTP upload truncated ({size:?} of {} bytes)",
+                bytes.len()
+            )));
+        }
+
+        // Same check as the exec upload: never install a corrupt binary.
+        let uploaded_hash = self.remote_hash(&tmp_path).await?;
+        if uploaded_hash != hash {
+            let _ = sftp.remove_file(tmp_path.as_str()).await;
+            return Err(SshError::AgentDeploy(format!(
+                "uploaded agent is corrupt (sha256 {uploaded_hash}, expected {hash})"
+            )));
+        }
+
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = Some(0o755);
+        sftp.set_metadata(tmp_path.as_str(), attrs)
+            .await
+            .map_err(sftp_err)?;
+
+        // SFTP v3 rename refuses to overwrite an existing file.
+        let _ = sftp.remove_file(remote_path).await;
+        sftp.rename(tmp_path.as_str(), remote_path)
+            .await
+            .map_err(sftp_err)?;
+
+        let _ = sftp.close().await;
+        Ok(())
+    }
+
+    /// Kill the agent left over from an earlier cycle of this session. Agents of
+    /// other sshfwd sessions have their own pid files and are left alone.
+    async fn kill_stale_agent(&self, options: &AgentOptions, elevation: &Elevation) {
+        let output = match self
+            .session
+            .exec(&format!("cat '{}'", options.pid_file()))
+            .await
+        {
+            Ok(o) if o.success => o,
+            _ => return,
+        };
+
+        if let Ok(pid) = String::from_utf8_lossy(&output.stdout).trim().parse() {
+            self.kill_if_agent(pid, options.agent_name(), elevation)
+                .await;
+        }
+    }
+
+    /// Send SIGTERM to `pid` if it is still an agent named `name` (pids get reused).
+    async fn kill_if_agent(&self, pid: u32, name: &str, elevation: &Elevation) {
+        let verify_cmd =
+            format!("cat /proc/{pid}/comm 2>/dev/null || ps -p {pid} -o comm= 2>/dev/null");
+        let output = match self.session.exec(&verify_cmd).await {
+            Ok(o) => o,
+            Err(_) => return,
+        };
+
+        let comm = String::from_utf8_lossy(&output.stdout);
+        if comm_matches(&comm, name) {
+            let _ = self.exec_as(elevation, &format!("kill {pid}")).await;
+        }
+    }
+
+    /// Spawn the remote agent as a persistent process.
+    async fn spawn_agent(
+        &self,
+        remote_path: &str,
+        options: &AgentOptions,
+        elevation: &Elevation,
+    ) -> Result<ExecStdio, SshError> {
+        let mut command = elevation.wrap(&format!("'{remote_path}'{}", options.agent_args()));
+        if !matches!(elevation, Elevation::None) {
+            // Create the state dirs as the login user so root doesn't own them.
+            command = format!(
+                "mkdir -p {}/sessions && {command}",
+                options.path_arg(&options.state_dir())
+            );
+        }
+        let mut stream = self.session.exec_streaming(&command).await?;
+
+        // The shell reads the password line; the agent gets the rest of stdin.
+        if let Some(preamble) = elevation.stdin_preamble() {
+            stream
+                .stdin
+                .write_all(preamble.as_bytes())
+                .await
+                .map_err(|e| SshError::Elevation(format!("failed to send password: {e}")))?;
+            stream
+                .stdin
+                .flush()
+                .await
+                .map_err(|e| SshError::Elevation(format!("failed to send password: {e}")))?;
+        }
+        Ok(stream)
+    }
+
+    /// Work out how to run the agent as root (only with `options.sudo`).
+    /// Passwordless `sudo -n`/`doas -n` is preferred; otherwise the password in
+    /// `options.sudo_password` is checked with `sudo -A`.
+    async fn elevation(&self, options: &AgentOptions) -> Result<Elevation, SshError> {
+        if !options.sudo {
+            return Ok(Elevation::None);
+        }
+        for prefix in ["sudo -n", "doas -n"] {
+            let ok = self
+                .session
+                .exec(&format!("{prefix} true"))
+                .await
+                .is_ok_and(|o| o.success);
+            if ok {
+                return Ok(Elevation::Passwordless(prefix));
+            }
+        }
+
+        let Some(password) = &options.sudo_password else {
+            return Err(SshError::SudoPasswordRequired);
+        };
+        let elevation = Elevation::SudoPassword(password.clone());
+        let output = self.exec_as(&elevation, "true").await?;
+        if output.success {
+            Ok(elevation)
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(SshError::Elevation(if stderr.is_empty() {
+                "sudo rejected the password".to_string()
+            } else {
+                stderr
+            }))
+        }
+    }
+
+    /// Run `command` with `elevation`.
+    async fn exec_as(
+        &self,
+        elevation: &Elevation,
+        command: &str,
+    ) -> Result<CommandOutput, SshError> {
+        let command = elevation.wrap(command);
+        match elevation.stdin_preamble() {
+            Some(preamble) => {
+                self.session
+                    .exec_with_stdin(&command, preamble.as_bytes(), |_| {})
+                    .await
+            }
+            None => self.session.exec(&command).await,
+        }
+    }
+
+    /// Remove every trace of the agent from the remote host: stop running agents
+    /// (of every session), delete binaries (all architectures and install
+    /// locations), pid files and logs, then drop empty directories.
+    ///
+    /// Returns entries left behind in the state dir (files sshfwd did not create).
+    pub async fn uninstall(
+        &self,
+        options: &AgentOptions,
+        remembered: Option<&str>,
+    ) -> Result<Vec<String>, SshError> {
+        let state_dir = options.state_dir();
+        let name = options.agent_name();
+        // Elevated agents can only be stopped as root; best effort otherwise.
+        let elevation = self.elevation(options).await.unwrap_or(Elevation::None);
+
+        // Every session's agent, plus the shared pid file older versions used.
+        if let Ok(output) = self
+            .session
+            .exec(&format!(
+                "cat '{state_dir}/agent.pid' '{state_dir}'/sessions/*.pid 2>/dev/null"
+            ))
+            .await
+        {
+            for pid in String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .filter_map(|p| p.parse().ok())
+            {
+                self.kill_if_agent(pid, name, &elevation).await;
+            }
+        }
+
+        let mut dirs = format!(r#"'{state_dir}' "$XDG_RUNTIME_DIR/sshfwd" "/tmp/sshfwd-$(id -u)""#);
+        if let Some(dir) = remembered {
+            dirs.push_str(&format!(" '{dir}'"));
+        }
+        let cmd = format!(
+            "for d in {dirs}; do \
+               rm -f \"$d\"/*/'{name}' \"$d\"/*/'{name}'.*tmp \"$d\"/*/'{name}{SIGNATURE_SUFFIX}'; \
+               rmdir \"$d\"/* \"$d\" 2>/dev/null; \
+             done; \
+             rm -f '{state_dir}/agent.pid' '{state_dir}'/sessions/*.pid '{state_dir}/agent.log' '{state_dir}'/agent.log.*; \
+             rmdir '{state_dir}/sessions' 2>/dev/null; \
+             rmdir '{state_dir}' 2>/dev/null; \
+             ls -A '{state_dir}' 2>/dev/null; true"
+        );
+        let output = self.session.exec(&cmd).await?;
+        if !output.success {
+            return Err(SshError::AgentDeploy(format!(
+                "cleanup failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Kill the remote agent gracefully (for shutdown).
+    #[allow(dead_code)]
+    pub async fn kill_remote_agent(&self, options: &AgentOptions) {
+        let elevation = self.elevation(options).await.unwrap_or(Elevation::None);
+        self.kill_stale_agent(options, &elevation).await;
+    }
+}
+
+/// What `AgentManager::transfer` writes, and where.
+struct Transfer<'a> {
+    bytes: &'a [u8],
+    expected_hash: &'a str,
+    has_gzip: bool,
+    tmp_path: &'a str,
+    remote_path: &'a str,
+    show_progress: bool,
+    on_progress: Option<&'a UploadProgress>,
+}
+
+/// Check `binary` against the public key baked into this build, if any.
+fn verify_binary(binary: &AgentBinary, label: &str) -> Result<(), SshError> {
+    let Some(public_key) = signature::AGENT_PUBLIC_KEY else {
+        return Ok(());
+    };
+    signature::verify(public_key, &binary.bytes, binary.signature.as_deref()).map_err(|e| {
+        SshError::AgentDeploy(format!("agent binary {label} failed verification: {e}"))
+    })
+}
+
+async fn read_local(path: &Path) -> Result<Vec<u8>, SshError> {
+    tokio::fs::read(path).await.map_err(|e| SshError::LocalIo {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Read `<path>.sig` if present.
+async fn read_signature(path: &Path) -> Result<Option<Vec<u8>>, SshError> {
+    let mut sig_path = path.as_os_str().to_owned();
+    sig_path.push(SIGNATURE_SUFFIX);
+    let sig_path = PathBuf::from(sig_path);
+    match tokio::fs::read(&sig_path).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(SshError::LocalIo {
+            path: sig_path,
+            source: e,
+        }),
+    }
+}
+
+/// Install locations in priority order: the user's `remote_dir` (or `~/.sshfwd`),
+/// the one that worked last time, then `$XDG_RUNTIME_DIR/sshfwd` and
+/// `/tmp/sshfwd-<uid>` for hosts whose home is mounted `noexec`.
+fn candidate_dirs(
+    configured: Option<&str>,
+    remembered: Option<&str>,
+    xdg_runtime_dir: Option<&str>,
+    uid: Option<&str>,
+) -> Vec<String> {
+    let mut dirs: Vec<String> = Vec::new();
+    let mut push = |dir: String| {
+        if !dir.is_empty() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    };
+    match configured {
+        Some(dir) => push(normalize_remote_dir(dir)),
+        None => push(REMOTE_AGENT_DIR.to_string()),
+    }
+    if let Some(dir) = remembered {
+        push(normalize_remote_dir(dir));
+    }
+    if let Some(xdg) = xdg_runtime_dir {
+        push(format!("{}/sshfwd", xdg.trim_end_matches('/')));
+    }
+    if let Some(uid) = uid {
+        push(format!("/tmp/sshfwd-{uid}"));
+    }
+    dirs
+}
+
+/// Whether `ps`/`/proc` output names the agent binary. `ps` may print a full
+/// path (macOS) and Linux truncates `comm` to 15 bytes.
+fn comm_matches(comm: &str, name: &str) -> bool {
+    let comm = comm.trim();
+    let comm = comm.rsplit('/').next().unwrap_or(comm);
+    if comm == name {
+        return true;
+    }
+    let mut end = COMM_MAX_LEN.min(name.len());
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name.len() > COMM_MAX_LEN && comm == &name[..end]
+}
+
+/// Remote commands run in `$HOME`, so `~/x` becomes the relative `x`.
+fn normalize_remote_dir(dir: &str) -> String {
+    let dir = dir.trim();
+    let dir = match dir {
+        "~" => ".",
+        _ => dir.strip_prefix("~/").unwrap_or(dir),
+    };
+    match dir.trim_end_matches('/') {
+        "" if dir.starts_with('/') => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Parse `wc -c` output followed by `sha256sum`/`openssl dgst` output.
+fn parse_size_and_hash(stdout: &str) -> Option<(usize, String)> {
+    let mut lines = stdout.lines();
+    let len = lines.next()?.trim().parse().ok()?;
+    let hash = lines
+        .flat_map(str::split_whitespace)
+        .find(|s| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()))?;
+    Some((len, hash.to_string()))
+}
+
+/// `[=========>          ]  45%  1.2/2.6 MB`
+fn progress_line(sent: usize, total: usize) -> String {
+    const WIDTH: usize = 30;
+    let ratio = if total == 0 {
+        1.0
+    } else {
+        sent as f64 / total as f64
+    };
+    let filled = ((ratio * WIDTH as f64) as usize).min(WIDTH);
+    let head = if filled < WIDTH { ">" } else { "" };
+    format!(
+        "Uploading agent [{}{head}{}] {:>3}%  {}/{}",
+        "=".repeat(filled),
+        " ".repeat(WIDTH - filled - head.len()),
+        (ratio * 100.0) as u32,
+        format_mb(sent),
+        format_mb(total)
+    )
+}
+
+fn format_mb(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 2), Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// This session's upload temp file: named after the content and `owner`, so
+/// sessions uploading the same build never write or delete each other's file.
+fn tmp_path(remote_path: &str, content_hash: &str, owner: &str) -> String {
+    let hash = &content_hash[..content_hash.len().min(16)];
+    format!("{remote_path}.{hash}.{owner}.tmp")
+}
+
+/// Where a failed upload leaves its partial file, named only by content so a
+/// later run of any session can resume it after `claim_partial`.
+fn partial_path(remote_path: &str, content_hash: &str) -> String {
+    let hash = &content_hash[..content_hash.len().min(16)];
+    format!("{remote_path}.{hash}.tmp")
+}
+
+/// Take over `partial` as `own` unless this session already has its own
+/// file (a reconnect). `mv` is an atomic rename, so of several sessions
+/// claiming one partial only one gets it.
+fn claim_partial(partial: &str, own: &str) -> String {
+    format!("[ -e '{own}' ] || mv '{partial}' '{own}' 2>/dev/null; true")
+}
+
+/// Remove temp files next to `remote_path` other than `keep`: leftovers of
+/// interrupted uploads of other builds. Recent ones are spared, as another
+/// session may still be writing them.
+fn stale_tmp_cleanup(remote_path: &str, keep: &str) -> String {
+    let (dir, name) = remote_path.rsplit_once('/').unwrap_or((".", remote_path));
+    let keep = keep.rsplit('/').next().unwrap_or(keep);
+    format!(
+        "find '{dir}' -maxdepth 1 -name '{name}.*.tmp' ! -name '{keep}' -mmin +10 \
+         -exec rm -f {{}} + 2>/dev/null"
+    )
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    let result = hasher.finalize();
+    hex_encode(&result)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+}
+
+/// Normalize OS name from `uname -s` output.
+fn normalize_os(raw: &str) -> String {
+    match raw {
+        "Linux" => "linux".to_string(),
+        "Darwin" => "darwin".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
+/// Normalize architecture from `uname -m` output.
+fn normalize_arch(raw: &str) -> String {
+    match raw {
+        "arm64" | "aarch64" => "aarch64".to_string(),
+        "x86_64" | "amd64" => "x86_64".to_string(),
+        // armv8l: 32-bit userland on a 64-bit core, runs armv7 binaries.
+        "armv7l" | "armv7" | "armv7hl" | "armv8l" | "armhf" => "armv7".to_string(),
+        "riscv64" | "riscv64gc" => "riscv64".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
+/// libc from `ldd --version` output (musl prints its banner to stderr) or a
+/// listed `/lib/ld-musl-*` loader.
+fn parse_libc(output: &str) -> Option<Libc> {
+    let output = output.to_ascii_lowercase();
+    if output.contains("musl") {
+        Some(Libc::Musl)
+    } else if output.contains("glibc") || output.contains("gnu libc") {
+        Some(Libc::Glibc)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidate_dirs_order_and_dedup() {
+        assert_eq!(
+            candidate_dirs(None, None, Some("/run/user/1000/"), Some("1000")),
+            vec![".sshfwd", "/run/user/1000/sshfwd", "/tmp/sshfwd-1000"]
+        );
+        assert_eq!(
+            candidate_dirs(
+                Some("~/bin/sshfwd/"),
+                Some("/tmp/sshfwd-1000"),
+                None,
+                Some("1000")
+            ),
+            vec!["bin/sshfwd", "/tmp/sshfwd-1000"]
+        );
+        assert_eq!(normalize_remote_dir("~"), ".");
+        assert_eq!(normalize_remote_dir("/opt/sshfwd"), "/opt/sshfwd");
+    }
+
+    #[test]
+    fn comm_matching() {
+        assert!(comm_matches("sshfwd-agent\n", "sshfwd-agent"));
+        assert!(comm_matches(
+            "/Users/me/.sshfwd/aarch64/sshfwd-agent",
+            "sshfwd-agent"
+        ));
+        assert!(comm_matches("corp-port-scann", "corp-port-scanner-agent"));
+        assert!(!comm_matches("sshfwd-agen", "sshfwd-agent"));
+        assert!(!comm_matches("bash", "sshfwd-agent"));
+    }
+
+    #[test]
+    fn agent_args_follow_remote_dir() {
+        let mut options = AgentOptions {
+            log_level: Some("debug".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.agent_args(),
+            " --log-file '.sshfwd/agent.log' --log-level debug"
+        );
+        options.remote_dir = Some("/opt/sshfwd/".into());
+        assert_eq!(options.pid_file(), "/opt/sshfwd/agent.pid");
+        assert_eq!(
+            options.agent_args(),
+            " --state-dir '/opt/sshfwd' --log-file '/opt/sshfwd/agent.log' --log-level debug"
+        );
+
+        options.scan_interval = Some(5);
+        assert!(options
+            .agent_args()
+            .starts_with(" --interval 5 --state-dir"));
+
+        options.redact = Some(vec!["dsn".into(), "x'; rm".into(), "api_key".into()]);
+        assert!(options
+            .agent_args()
+            .starts_with(" --redact 'dsn,api_key' --interval 5"));
+        options.redact = None;
+
+        options.nice = Some(10);
+        options.scan_budget = Some(200);
+        assert!(options
+            .agent_args()
+            .starts_with(" --interval 5 --nice 10 --scan-budget 200 --state-dir"));
+        options.nice = None;
+        options.scan_budget = None;
+
+        options.session_id = "1f-abc".into();
+        assert_eq!(options.pid_file(), "/opt/sshfwd/sessions/1f-abc.pid");
+        assert!(options
+            .agent_args()
+            .starts_with(" --session 1f-abc --interval 5 --state-dir"));
+        options.bench = true;
+        assert!(options
+            .agent_args()
+            .starts_with(" --session 1f-abc --bench-server --interval 5"));
+    }
+
+    #[test]
+    fn sudo_anchors_relative_paths_at_login_home() {
+        let mut options = AgentOptions {
+            sudo: true,
+            log_level: Some("info".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.agent_args(),
+            r#" --state-dir "$HOME"/'.sshfwd' --log-file "$HOME"/'.sshfwd/agent.log' --log-level info"#
+        );
+        options.remote_dir = Some("/opt/sshfwd".into());
+        assert!(options
+            .agent_args()
+            .starts_with(" --state-dir '/opt/sshfwd'"));
+    }
+
+    #[test]
+    fn secrets_stay_out_of_debug_output() {
+        assert_eq!(format!("{:?}", Secret("hunter2".into())), "Secret(***)");
+    }
+
+    #[test]
+    fn sudo_password_never_reaches_the_command_line() {
+        let password = Elevation::SudoPassword(Secret("hunter2".into()));
+        let command = password.wrap("'/opt/a' --once");
+        assert!(!command.contains("hunter2"));
+        assert!(command.starts_with("(IFS= read -r p "));
+        assert!(command.contains(r#"SUDO_ASKPASS="$d/askpass" sudo -A -p '' '/opt/a' --once;"#));
+        assert_eq!(password.stdin_preamble().as_deref(), Some("hunter2\n"));
+        assert_eq!(
+            Elevation::Passwordless("doas -n").wrap("kill 7"),
+            "doas -n kill 7"
+        );
+        assert_eq!(Elevation::None.wrap("kill 7"), "kill 7");
+    }
+
+    #[test]
+    fn upload_temp_files_are_named_by_content_and_session() {
+        let hash = sha256_hex(b"agent");
+        let tmp = tmp_path(".sshfwd/x/sshfwd-agent", &hash, "1f-abc");
+        assert_eq!(
+            tmp,
+            format!(".sshfwd/x/sshfwd-agent.{}.1f-abc.tmp", &hash[..16])
+        );
+        assert_ne!(tmp, tmp_path(".sshfwd/x/sshfwd-agent", &hash, "2e-def"));
+        let partial = partial_path(".sshfwd/x/sshfwd-agent", &hash);
+        assert_eq!(
+            partial,
+            format!(".sshfwd/x/sshfwd-agent.{}.tmp", &hash[..16])
+        );
+        assert_eq!(
+            claim_partial(&partial, &tmp),
+            format!("[ -e '{tmp}' ] || mv '{partial}' '{tmp}' 2>/dev/null; true")
+        );
+        assert_eq!(
+            stale_tmp_cleanup(".sshfwd/x/sshfwd-agent", &tmp),
+            format!(
+                "find '.sshfwd/x' -maxdepth 1 -name 'sshfwd-agent.*.tmp' ! -name \
+                 'sshfwd-agent.{}.1f-abc.tmp' -mmin +10 -exec rm -f {{}} + 2>/dev/null",
+                &hash[..16]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_partial_upload_probe() {
+        let hash = "a".repeat(64);
+        assert_eq!(
+            parse_size_and_hash(&format!("  1048576\n{hash}  .sshfwd/x/sshfwd-agent.tmp\n")),
+            Some((1_048_576, hash.clone()))
+        );
+        assert_eq!(
+            parse_size_and_hash(&format!("12\nSHA2-256(f.tmp)= {hash}\n")),
+            Some((12, hash))
+        );
+        assert_eq!(parse_size_and_hash(""), None);
+        assert_eq!(parse_size_and_hash("12\n"), None);
+    }
+
+    #[test]
+    fn progress_line_bounds() {
+        assert!(progress_line(0, 100).contains("[>    "));
+        assert!(progress_line(0, 100).contains("  0%"));
+        let done = progress_line(2 * 1024 * 1024, 2 * 1024 * 1024);
+        assert!(done.contains(&format!("[{}]", "=".repeat(30))));
+        assert!(done.contains("100%  2.0 MB/2.0 MB"));
+    }
+
+    #[test]
+    fn gzip_round_trips_and_shrinks() {
+        use std::io::Read;
+        let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 7) as u8).collect();
+        let compressed = gzip(&data).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(sha256_hex(&decoded), sha256_hex(&data));
+    }
+
+    #[test]
+    fn normalize_os_values() {
+        assert_eq!(normalize_os("Linux"), "linux");
+        assert_eq!(normalize_os("Darwin"), "darwin");
+        assert_eq!(normalize_os("FreeBSD"), "freebsd");
+    }
+
+    #[test]
+    fn normalize_arch_values() {
+        assert_eq!(normalize_arch("x86_64"), "x86_64");
+        assert_eq!(normalize_arch("amd64"), "x86_64");
+        assert_eq!(normalize_arch("aarch64"), "aarch64");
+        assert_eq!(normalize_arch("arm64"), "aarch64");
+        assert_eq!(normalize_arch("armv7l"), "armv7");
+        assert_eq!(normalize_arch("armv8l"), "armv7");
+        assert_eq!(normalize_arch("riscv64"), "riscv64");
+        assert_eq!(normalize_arch("mips"), "mips");
+    }
+
+    #[test]
+    fn parse_libc_values() {
+        assert_eq!(
+            parse_libc("ldd (Ubuntu GLIBC 2.35-0ubuntu3.8) 2.35\nCopyright (C) 2022"),
+            Some(Libc::Glibc)
+        );
+        assert_eq!(
+            parse_libc("ldd (GNU libc) 2.17\nCopyright"),
+            Some(Libc::Glibc)
+        );
+        assert_eq!(
+            parse_libc("musl libc (aarch64)\nVersion 1.2.4\n/lib/ld-musl-aarch64.so.1"),
+            Some(Libc::Musl)
+        );
+        assert_eq!(parse_libc("/lib/ld-musl-armhf.so.1"), Some(Libc::Musl));
+        assert_eq!(parse_libc("sh: ldd: not found"), None);
+    }
+
+    #[test]
+    fn platform_target_triple() {
+        let p = Platform {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            libc: None,
+        };
+        assert_eq!(p.target_triple(), "x86_64-unknown-linux-musl");
+
+        let p = Platform {
+            os: "darwin".to_string(),
+            arch: "aarch64".to_string(),
+            libc: None,
+        };
+        assert_eq!(p.target_triple(), "aarch64-apple-darwin");
+
+        let p = Platform {
+            os: "linux".to_string(),
+            arch: "aarch64".to_string(),
+            libc: None,
+        };
+        assert_eq!(p.target_triple(), "aarch64-unknown-linux-musl");
+
+        let p = Platform {
+            os: "linux".to_string(),
+            arch: "armv7".to_string(),
+            libc: Some(Libc::Glibc),
+        };
+        assert_eq!(p.target_triple(), "armv7-unknown-linux-musleabihf");
+        assert_eq!(p.agent_dirs(), vec!["linux-armv7-gnu", "linux-armv7"]);
+        // What `musl_fallback` resolves when the -gnu build doesn't run.
+        let musl = Platform { libc: None, ..p };
+        assert_eq!(musl.agent_dirs(), vec!["linux-armv7"]);
+
+        let p = Platform {
+            os: "linux".to_string(),
+            arch: "riscv64".to_string(),
+            libc: Some(Libc::Musl),
+        };
+        assert_eq!(p.target_triple(), "riscv64gc-unknown-linux-musl");
+        assert_eq!(p.agent_dirs(), vec!["linux-riscv64"]);
+    }
+}
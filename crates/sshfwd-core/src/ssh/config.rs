@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use ssh2_config::{ParseRule, SshConfig};
+
+pub struct ResolvedConfig {
+    pub hostname: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub proxy_jump: Option<String>,
+    pub identity_files: Vec<PathBuf>,
+}
+
+/// Resolve SSH config for a host by parsing `~/.ssh/config`.
+///
+/// Returns default (empty) config if the file is missing or unparseable.
+pub fn resolve_host_config(host: &str) -> ResolvedConfig {
+    let cfg = SshConfig::parse_default_file(
+        ParseRule::ALLOW_UNKNOWN_FIELDS | ParseRule::ALLOW_UNSUPPORTED_FIELDS,
+    )
+    .ok();
+
+    let params = cfg.as_ref().map(|c| c.query(host));
+
+    match params {
+        Some(params) => {
+            let identity_files = params
+                .identity_file
+                .as_ref()
+                .map(|files| files.iter().map(|p| expand_tilde(p)).collect())
+                .unwrap_or_default();
+
+            let proxy_jump = params
+                .proxy_jump
+                .as_ref()
+                .and_then(|jumps| jumps.first().map(|j| j.to_string()));
+
+            ResolvedConfig {
+                hostname: params.host_name.clone(),
+                port: params.port,
+                user: params.user.clone(),
+                proxy_jump,
+                identity_files,
+            }
+        }
+        None => ResolvedConfig {
+            hostname: None,
+            port: None,
+            user: None,
+            proxy_jump: None,
+            identity_files: Vec::new(),
+        },
+    }
+}
+
+/// `Host` aliases from `~/.ssh/config`, in file order; patterns (`*`, `?`,
+/// `!`) are skipped since they can't be connected to by name.
+pub fn host_aliases() -> Vec<String> {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    std::fs::read_to_string(Path::new(&home).join(".ssh").join("config"))
+        .map(|text| parse_host_aliases(&text))
+        .unwrap_or_default()
+}
+
+fn parse_host_aliases(text: &str) -> Vec<String> {
+    let mut aliases: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let Some((keyword, rest)) = line.split_once(|c: char| c.is_whitespace() || c == '=') else {
+            continue;
+        };
+        if !keyword.eq_ignore_ascii_case("host") {
+            continue;
+        }
+        for alias in rest.split_whitespace().map(|a| a.trim_matches('"')) {
+            if !alias.is_empty()
+                && !alias.contains(['*', '?', '!'])
+                && !aliases.iter().any(|a| a == alias)
+            {
+                aliases.push(alias.to_string());
+            }
+        }
+    }
+    aliases
+}
+
+/// ProxyJump hops in front of `destination`, outermost first (a hop's own
+/// ProxyJump comes before it). Empty for direct connections.
+pub fn jump_hosts(destination: &str) -> Vec<String> {
+    jump_chain(destination, |host| resolve_host_config(host).proxy_jump)
+}
+
+fn jump_chain(destination: &str, proxy_jump: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut current = destination.to_string();
+    loop {
+        let (_, host) = parse_destination(&current);
+        match proxy_jump(&host) {
+            // A cycle would make connecting fail anyway; stop listing it.
+            Some(hop) if hop != destination && !chain.contains(&hop) => {
+                chain.insert(0, hop.clone());
+                current = hop;
+            }
+            _ => return chain,
+        }
+    }
+}
+
+/// Parse `user@host` into `(Option<user>, host)`.
+pub fn parse_destination(destination: &str) -> (Option<String>, String) {
+    if let Some((user, host)) = destination.split_once('@') {
+        (Some(user.to_string()), host.to_string())
+    } else {
+        (None, destination.to_string())
+    }
+}
+
+/// Expand leading `~` to `$HOME` in a path.
+fn expand_tilde(path: &Path) -> PathBuf {
+    let s = path.to_string_lossy();
+    if let Some(rest) = s.strip_prefix("~/") {
+        if let Ok(home) = std::env::var("HOME") {
+            return PathBuf::from(format!("{home}/{rest}"));
+        }
+    }
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump_chain_lists_outermost_hop_first() {
+        let proxy_jump = |host: &str| match host {
+            "db" => Some("ops@bastion".to_string()),
+            "bastion" => Some("gateway".to_string()),
+            "loop" => Some("loop".to_string()),
+            _ => None,
+        };
+        assert_eq!(jump_chain("me@db", proxy_jump), ["gateway", "ops@bastion"]);
+        assert!(jump_chain("web", proxy_jump).is_empty());
+        assert!(jump_chain("loop", proxy_jump).is_empty());
+    }
+
+    #[test]
+    fn host_aliases_skip_patterns() {
+        let text = "\
+Host *
+    ServerAliveInterval 30
+Host web1 web2 !bad
+  HostName 10.0.0.1
+host=db
+Match host gw
+Host staging-* web1
+";
+        assert_eq!(parse_host_aliases(text), ["web1", "web2", "db"]);
+    }
+}
@@ -0,0 +1,182 @@
+//! Block-level delta for agent updates over slow links.
+//!
+//! The remote splits its installed agent into `BLOCK`-sized pieces and
+//! reports their SHA-256 (`block_hashes_command`, plain `split` plus
+//! `sha256sum`/`openssl`). Blocks of the new binary found among them are
+//! copied remotely with `dd`; only the others are uploaded, concatenated.
+//! Matching is by whole aligned blocks, which suits executables: their
+//! segments are page-aligned, so unchanged pages usually stay on a `BLOCK`
+//! boundary even when earlier code grew. The assembled file still has to pass
+//! the usual SHA-256 check before it is installed.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::agent::sha256_hex;
+
+/// One page: the alignment of executable segments.
+pub const BLOCK: usize = 4096;
+
+/// Below this share of reused bytes a full (gzipped) upload is as good.
+const MIN_REUSE_PERCENT: usize = 25;
+
+/// Shell command printing the hash of every `BLOCK` of `remote_path`, in
+/// order, one per line.
+pub fn block_hashes_command(remote_path: &str) -> String {
+    format!(
+        "d=$(mktemp -d) && split -b {BLOCK} -a 5 '{remote_path}' \"$d/b\" && \
+         (cd \"$d\" && (sha256sum b* 2>/dev/null || openssl dgst -sha256 b*)); \
+         s=$?; rm -rf \"$d\"; exit $s"
+    )
+}
+
+/// Block hashes from `block_hashes_command` output (`sha256sum` or
+/// `openssl dgst` format).
+pub fn parse_block_hashes(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            line.split(|c: char| c.is_whitespace() || c == '=')
+                .find(|s| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()))
+                .map(str::to_ascii_lowercase)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// From the installed binary.
+    Old,
+    /// From the uploaded literal blocks.
+    Literal,
+}
+
+/// `count` blocks starting at block `skip` of `source`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    source: Source,
+    skip: usize,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct Delta {
+    runs: Vec<Run>,
+    /// The blocks that must be uploaded, in order.
+    pub literal: Vec<u8>,
+    /// Bytes taken from the installed binary.
+    pub reused: usize,
+}
+
+impl Delta {
+    /// Plan `new` against the installed binary's block hashes; `None` when
+    /// too little of it can be reused.
+    pub fn plan(new: &[u8], old_hashes: &[String]) -> Option<Delta> {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (i, hash) in old_hashes.iter().enumerate() {
+            index.entry(hash.as_str()).or_insert(i);
+        }
+
+        let mut runs: Vec<Run> = Vec::new();
+        let mut literal = Vec::new();
+        let mut reused = 0;
+        for block in new.chunks(BLOCK) {
+            // A short final block never matches: the old file's last block
+            // would have to be exactly as short.
+            let old = (block.len() == BLOCK)
+                .then(|| index.get(sha256_hex(block).as_str()).copied())
+                .flatten();
+            let (source, skip) = match old {
+                Some(i) => {
+                    reused += block.len();
+                    (Source::Old, i)
+                }
+                None => {
+                    literal.extend_from_slice(block);
+                    (Source::Literal, literal.len().div_ceil(BLOCK) - 1)
+                }
+            };
+            match runs.last_mut() {
+                Some(run) if run.source == source && run.skip + run.count == skip => {
+                    run.count += 1;
+                }
+                _ => runs.push(Run {
+                    source,
+                    skip,
+                    count: 1,
+                }),
+            }
+        }
+
+        (reused * 100 >= new.len() * MIN_REUSE_PERCENT && reused > 0).then_some(Delta {
+            runs,
+            literal,
+            reused,
+        })
+    }
+
+    /// Shell command writing the new binary to `out` from `old` and the
+    /// uploaded `literal` file.
+    pub fn assemble_command(&self, old: &str, literal: &str, out: &str) -> String {
+        let mut script = String::from("{");
+        for run in &self.runs {
+            let file = match run.source {
+                Source::Old => old,
+                Source::Literal => literal,
+            };
+            let _ = write!(
+                script,
+                " dd if='{file}' bs={BLOCK} skip={} count={} 2>/dev/null &&",
+                run.skip, run.count
+            );
+        }
+        script.push_str(&format!(" true; }} > '{out}'"));
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(fills: &[u8]) -> Vec<u8> {
+        fills.iter().flat_map(|&b| [b; BLOCK]).collect()
+    }
+
+    fn hashes(data: &[u8]) -> Vec<String> {
+        data.chunks(BLOCK).map(sha256_hex).collect()
+    }
+
+    #[test]
+    fn plan_reuses_shifted_blocks() {
+        let old = blocks(&[1, 2, 3, 4]);
+        let mut new = blocks(&[9, 1, 2, 3, 8]);
+        new.extend_from_slice(b"tail");
+
+        let delta = Delta::plan(&new, &hashes(&old)).unwrap();
+        assert_eq!(delta.reused, 3 * BLOCK);
+        assert_eq!(delta.literal.len(), 2 * BLOCK + 4);
+        assert_eq!(
+            delta.assemble_command("/a/old", "/a/lit", "/a/new"),
+            format!(
+                "{{ dd if='/a/lit' bs={BLOCK} skip=0 count=1 2>/dev/null && \
+                 dd if='/a/old' bs={BLOCK} skip=0 count=3 2>/dev/null && \
+                 dd if='/a/lit' bs={BLOCK} skip=1 count=2 2>/dev/null && true; }} > '/a/new'"
+            )
+        );
+
+        assert!(Delta::plan(&blocks(&[5, 6, 7, 8, 1]), &hashes(&old)).is_none());
+    }
+
+    #[test]
+    fn block_hash_output_formats() {
+        let hash = "ab".repeat(32);
+        assert_eq!(
+            parse_block_hashes(&format!(
+                "{hash}  baaaaa\nSHA2-256(baaaab)= {}\n\n",
+                hash.to_uppercase()
+            )),
+            vec![hash.clone(), hash]
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Remembers, per destination, which remote directory could actually run the
+//! agent (hosts with a `noexec` home need `/tmp` or `$XDG_RUNTIME_DIR`).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+type InstallDirsFile = HashMap<String, String>;
+
+fn install_dirs_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home)
+        .join(".sshfwd")
+        .join("install_dirs.json")
+}
+
+pub fn load_install_dir(destination: &str) -> Option<String> {
+    let data = fs::read_to_string(install_dirs_path()).ok()?;
+    let file: InstallDirsFile = serde_json::from_str(&data).ok()?;
+    file.get(destination).cloned()
+}
+
+pub fn save_install_dir(destination: &str, dir: &str) {
+    let path = install_dirs_path();
+
+    // Read existing file to preserve other destinations
+    let mut file: InstallDirsFile = fs::read_to_string(&path)
+        .ok()
+        .and_then(|d| serde_json::from_str(&d).ok())
+        .unwrap_or_default();
+
+    file.insert(destination.to_string(), dir.to_string());
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    if let Ok(json) = serde_json::to_string_pretty(&file) {
+        let _ = fs::write(&path, json);
+    }
+}
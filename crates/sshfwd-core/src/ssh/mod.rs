@@ -0,0 +1,8 @@
+pub mod agent;
+pub mod config;
+pub mod delta;
+pub mod install_dirs;
+pub mod mux;
+pub mod release;
+pub mod session;
+pub mod system;
@@ -0,0 +1,436 @@
+//! Sharing one SSH connection between sshfwd processes.
+//!
+//! The first process for a destination serves its session on
+//! `~/.sshfwd/mux/<hash>.sock` ([`share`]); later ones attach to it
+//! ([`Session::connect_shared`]) instead of authenticating again. Each
+//! operation of an attached session is one socket connection: a JSON request
+//! line, a JSON reply line, then either raw bytes (`direct_tcpip`,
+//! `subsystem`) or, for `exec`, stdin from the client and framed
+//! stdout/stderr/exit status from the owner.
+//!
+//! Reverse forwards need the owner's `forwarded_tx`, so attached sessions
+//! refuse them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use russh::ChannelMsg;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+use super::agent::sha256_hex;
+use super::session::{
+    split_lines, CommandOutput, ExecStdio, Session, TunnelStream, STDIN_CHUNK, STDOUT_PIPE_BYTES,
+};
+use crate::error::SshError;
+
+/// Frame tags for `exec` output (tag byte, big-endian `u32` length, payload).
+const FRAME_STDOUT: u8 = 0;
+const FRAME_STDERR: u8 = 1;
+/// Payload is the exit status as a big-endian `u32`.
+const FRAME_EXIT: u8 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    /// Is anyone serving this socket?
+    Ping,
+    DirectTcpip {
+        host: String,
+        port: u16,
+    },
+    Subsystem {
+        name: String,
+    },
+    Exec {
+        command: String,
+    },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Reply {
+    error: Option<String>,
+}
+
+/// `~/.sshfwd/mux/<hash>.sock`, named after a hash of the whole
+/// destination so that destinations differing only in characters a file
+/// name can't hold don't share a socket.
+pub fn socket_path(destination: &str) -> PathBuf {
+    let name = &sha256_hex(destination.as_bytes())[..16];
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home)
+        .join(".sshfwd")
+        .join("mux")
+        .join(format!("{name}.sock"))
+}
+
+/// Serves a session until dropped; removes the socket file on drop.
+pub struct MuxServer {
+    path: PathBuf,
+    task: AbortHandle,
+}
+
+impl Drop for MuxServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Serve `session` to other sshfwd processes connecting to `destination`.
+/// `None` when the session is itself attached or runs on the system `ssh`
+/// (see `Session::is_direct`), another process already
+/// serves the destination, or the socket cannot be bound. Needs a Tokio
+/// runtime.
+pub fn share(session: &Session, destination: &str) -> Option<MuxServer> {
+    if !session.is_direct() {
+        return None;
+    }
+    let path = socket_path(destination);
+    if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+        return None;
+    }
+    // Nobody answers: a leftover from a process that didn't shut down cleanly.
+    let _ = std::fs::remove_file(&path);
+    let dir = path.parent()?;
+    std::fs::create_dir_all(dir).ok()?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)).ok()?;
+    }
+    let listener = UnixListener::bind(&path).ok()?;
+
+    let session = session.clone();
+    let task = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve_client(session.clone(), stream));
+        }
+    });
+    Some(MuxServer {
+        path,
+        task: task.abort_handle(),
+    })
+}
+
+/// The socket of a live owner for `destination`, if there is one.
+pub(super) async fn attach(destination: &str) -> Option<PathBuf> {
+    let path = socket_path(destination);
+    if !path.exists() {
+        return None;
+    }
+    send_request(&path, &Request::Ping).await.ok()?;
+    Some(path)
+}
+
+async fn serve_client(session: Session, stream: UnixStream) {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+        return;
+    }
+    let request = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => request,
+        Err(e) => {
+            let _ = send_reply(&mut stream, Some(format!("bad request: {e}"))).await;
+            return;
+        }
+    };
+    match request {
+        Request::Ping => {
+            let _ = send_reply(&mut stream, None).await;
+        }
+        Request::DirectTcpip { host, port } => {
+            let tunnel = session.open_direct_tcpip(&host, port).await;
+            relay(stream, tunnel).await;
+        }
+        Request::Subsystem { name } => {
+            let tunnel = session.subsystem(&name).await;
+            relay(stream, tunnel).await;
+        }
+        Request::Exec { command } => match session.exec_channel(&command).await {
+            Ok(channel) => {
+                if send_reply(&mut stream, None).await.is_ok() {
+                    serve_exec(stream, channel).await;
+                }
+            }
+            Err(e) => {
+                let _ = send_reply(&mut stream, Some(e.to_string())).await;
+            }
+        },
+    }
+}
+
+async fn send_reply(stream: &mut BufReader<UnixStream>, error: Option<String>) -> io::Result<()> {
+    let mut line = serde_json::to_string(&Reply { error }).map_err(io::Error::other)?;
+    line.push('\n');
+    stream.get_mut().write_all(line.as_bytes()).await
+}
+
+/// Copy bytes both ways between the client and a freshly opened channel.
+async fn relay(mut stream: BufReader<UnixStream>, tunnel: Result<TunnelStream, SshError>) {
+    match tunnel {
+        Ok(mut tunnel) => {
+            if send_reply(&mut stream, None).await.is_ok() {
+                let _ = tokio::io::copy_bidirectional(&mut stream, &mut tunnel).await;
+            }
+        }
+        Err(e) => {
+            let _ = send_reply(&mut stream, Some(e.to_string())).await;
+        }
+    }
+}
+
+/// Client bytes become the command's stdin (closing the socket's write side
+/// sends EOF); its output goes back as frames until the channel closes.
+async fn serve_exec(stream: BufReader<UnixStream>, channel: russh::Channel<russh::client::Msg>) {
+    let (client_r, mut client_w) = tokio::io::split(stream);
+    let (mut reader, writer) = channel.split();
+    let stdin = async {
+        if writer.data(client_r).await.is_ok() {
+            let _ = writer.eof().await;
+        }
+        std::future::pending::<()>().await
+    };
+    let output = async {
+        while let Some(msg) = reader.wait().await {
+            let written = match msg {
+                ChannelMsg::Data { data } => write_frame(&mut client_w, FRAME_STDOUT, &data).await,
+                ChannelMsg::ExtendedData { data, ext: 1 } => {
+                    write_frame(&mut client_w, FRAME_STDERR, &data).await
+                }
+                ChannelMsg::ExitStatus { exit_status } => {
+                    write_frame(&mut client_w, FRAME_EXIT, &exit_status.to_be_bytes()).await
+                }
+                _ => Ok(()),
+            };
+            if written.is_err() {
+                break;
+            }
+        }
+    };
+    tokio::select! {
+        _ = stdin => {}
+        _ = output => {}
+    }
+    let _ = writer.close().await;
+}
+
+async fn write_frame(w: &mut (impl AsyncWrite + Unpin), tag: u8, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len()).map_err(io::Error::other)?;
+    w.write_u8(tag).await?;
+    w.write_u32(len).await?;
+    w.write_all(payload).await
+}
+
+/// The next frame, or `None` once the owner closed the connection.
+async fn read_frame(r: &mut (impl AsyncRead + Unpin)) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let tag = match r.read_u8().await {
+        Ok(tag) => tag,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let len = r.read_u32().await?;
+    let mut payload = vec![0; len as usize];
+    r.read_exact(&mut payload).await?;
+    Ok(Some((tag, payload)))
+}
+
+fn mux_error(socket: &Path, e: impl std::fmt::Display) -> SshError {
+    SshError::Mux(format!("{}: {e}", socket.display()))
+}
+
+/// Send `request` and wait for the owner's reply.
+async fn send_request(socket: &Path, request: &Request) -> Result<BufReader<UnixStream>, SshError> {
+    let stream = UnixStream::connect(socket)
+        .await
+        .map_err(|e| mux_error(socket, e))?;
+    let mut stream = BufReader::new(stream);
+    let mut line = serde_json::to_string(request).map_err(|e| mux_error(socket, e))?;
+    line.push('\n');
+    stream
+        .get_mut()
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| mux_error(socket, e))?;
+    let mut reply = String::new();
+    stream
+        .read_line(&mut reply)
+        .await
+        .map_err(|e| mux_error(socket, e))?;
+    let reply: Reply = serde_json::from_str(&reply).map_err(|e| mux_error(socket, e))?;
+    match reply.error {
+        Some(e) => Err(SshError::Mux(e)),
+        None => Ok(stream),
+    }
+}
+
+pub(super) async fn open_direct_tcpip(
+    socket: &Path,
+    host: &str,
+    port: u16,
+) -> Result<TunnelStream, SshError> {
+    let request = Request::DirectTcpip {
+        host: host.to_string(),
+        port,
+    };
+    send_request(socket, &request)
+        .await
+        .map(TunnelStream::Shared)
+}
+
+pub(super) async fn subsystem(socket: &Path, name: &str) -> Result<TunnelStream, SshError> {
+    let request = Request::Subsystem {
+        name: name.to_string(),
+    };
+    send_request(socket, &request)
+        .await
+        .map(TunnelStream::Shared)
+}
+
+/// Run `command` through the owner. With `stdin`, it is written (reporting
+/// progress like `Session::exec_with_stdin`); EOF follows either way, so a
+/// command reading stdin never waits on it. Succeeds only on a zero exit
+/// frame: a connection that closes without one is a failure.
+pub(super) async fn exec(
+    socket: &Path,
+    command: &str,
+    stdin: Option<&[u8]>,
+    mut on_progress: impl FnMut(usize),
+) -> Result<CommandOutput, SshError> {
+    let request = Request::Exec {
+        command: command.to_string(),
+    };
+    let stream = send_request(socket, &request).await?;
+    let (mut r, mut w) = tokio::io::split(stream);
+    if let Some(data) = stdin {
+        let mut sent = 0;
+        for chunk in data.chunks(STDIN_CHUNK) {
+            w.write_all(chunk).await.map_err(|e| mux_error(socket, e))?;
+            sent += chunk.len();
+            on_progress(sent);
+        }
+    }
+    w.shutdown().await.map_err(|e| mux_error(socket, e))?;
+
+    let mut output = CommandOutput {
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+        success: false,
+    };
+    while let Some((tag, payload)) = read_frame(&mut r).await.map_err(|e| mux_error(socket, e))? {
+        match tag {
+            FRAME_STDOUT => output.stdout.extend_from_slice(&payload),
+            FRAME_STDERR => output.stderr.extend_from_slice(&payload),
+            FRAME_EXIT => output.success = payload == [0; 4],
+            _ => {}
+        }
+    }
+    Ok(output)
+}
+
+/// `Session::exec_streaming` through the owner.
+pub(super) async fn exec_streaming(socket: &Path, command: &str) -> Result<ExecStdio, SshError> {
+    let request = Request::Exec {
+        command: command.to_string(),
+    };
+    let stream = send_request(socket, &request).await?;
+    let (mut r, w) = tokio::io::split(stream);
+    let (mut stdout_tx, stdout) = tokio::io::duplex(STDOUT_PIPE_BYTES);
+    let (stderr_tx, stderr) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut partial = Vec::new();
+        while let Ok(Some((tag, payload))) = read_frame(&mut r).await {
+            let open = match tag {
+                // Fails once the stdout reader is gone.
+                FRAME_STDOUT => stdout_tx.write_all(&payload).await.is_ok(),
+                FRAME_STDERR => {
+                    for line in split_lines(&mut partial, &payload) {
+                        let _ = stderr_tx.send(line);
+                    }
+                    true
+                }
+                _ => true,
+            };
+            if !open {
+                break;
+            }
+        }
+        if !partial.is_empty() {
+            let _ = stderr_tx.send(String::from_utf8_lossy(&partial).trim_end().to_string());
+        }
+    });
+
+    Ok(ExecStdio {
+        stdout,
+        stdin: Box::pin(w),
+        stderr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socket_names_are_file_safe_and_distinct() {
+        let path = socket_path("ops@[::1]:2222");
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(path.parent().unwrap().ends_with(".sshfwd/mux"));
+        assert_eq!(name.len(), "0123456789abcdef.sock".len());
+        assert!(name
+            .trim_end_matches(".sock")
+            .bytes()
+            .all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(path, socket_path("ops@[::1]:2222"));
+        // Formerly both `ops_host_22.sock`.
+        assert_ne!(socket_path("ops@host:22"), socket_path("ops@host/22"));
+    }
+
+    #[tokio::test]
+    async fn exec_frames_round_trip() {
+        let (mut a, mut b) = tokio::io::duplex(1024);
+        write_frame(&mut a, FRAME_STDOUT, b"hello").await.unwrap();
+        write_frame(&mut a, FRAME_EXIT, &1u32.to_be_bytes())
+            .await
+            .unwrap();
+        drop(a);
+        assert_eq!(
+            read_frame(&mut b).await.unwrap(),
+            Some((FRAME_STDOUT, b"hello".to_vec()))
+        );
+        assert_eq!(
+            read_frame(&mut b).await.unwrap(),
+            Some((FRAME_EXIT, vec![0, 0, 0, 1]))
+        );
+        assert_eq!(read_frame(&mut b).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn exec_fails_when_the_owner_closes_without_an_exit_frame() {
+        let socket = std::env::temp_dir().join(format!("sshfwd-mux-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let owner = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            send_reply(&mut stream, None).await.unwrap();
+            write_frame(stream.get_mut(), FRAME_STDOUT, b"partial")
+                .await
+                .unwrap();
+            // EOF arrives even without stdin.
+            let mut rest = Vec::new();
+            stream.read_to_end(&mut rest).await.unwrap();
+        });
+
+        let output = exec(&socket, "true", None, |_| {}).await.unwrap();
+        owner.await.unwrap();
+        let _ = std::fs::remove_file(&socket);
+        assert_eq!(output.stdout, b"partial");
+        assert!(!output.success);
+    }
+}
@@ -0,0 +1,159 @@
+//! Agent binaries fetched from GitHub releases, for platforms that have no
+//! embedded or prebuilt agent.
+//!
+//! Each release carries `sshfwd-agent-<os>-<arch>` with a `.sha256` and a
+//! `.sig`. The checksum only proves integrity, so builds with an embedded
+//! public key refuse a download without a signature; the signature itself is
+//! checked before upload like any other agent. Downloads are cached under
+//! `~/.sshfwd/agents/<version>/<os>-<arch>/`, so the network is only touched
+//! once per version and platform.
+
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+use sshfwd_common::signature::{self, SIGNATURE_SUFFIX};
+
+use crate::error::SshError;
+
+const RELEASE_URL: &str = "https://github.com/gogoout/sshfwd.rs/releases/download";
+/// Agents are a few MiB; anything far larger is not what we asked for.
+const MAX_DOWNLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Release asset name for a platform, e.g. `sshfwd-agent-linux-x86_64`.
+fn asset_name(os: &str, arch: &str) -> String {
+    format!("sshfwd-agent-{os}-{arch}")
+}
+
+/// Where the agent for this version and platform is cached.
+pub fn cached_path(os: &str, arch: &str) -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home)
+        .join(".sshfwd")
+        .join("agents")
+        .join(env!("CARGO_PKG_VERSION"))
+        .join(format!("{os}-{arch}"))
+        .join("sshfwd-agent")
+}
+
+/// Download the agent for `os`/`arch` matching this sshfwd version, verify its
+/// checksum and store it (plus its signature, if published) in the cache.
+/// Blocking; run it off the async runtime.
+pub fn download(os: &str, arch: &str) -> Result<PathBuf, SshError> {
+    let base = format!(
+        "{RELEASE_URL}/v{}/{}",
+        env!("CARGO_PKG_VERSION"),
+        asset_name(os, arch)
+    );
+
+    let checksum = fetch(&format!("{base}.sha256"))?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&checksum))
+        .ok_or_else(|| SshError::Download(format!("malformed checksum file at {base}.sha256")))?;
+
+    let binary = fetch(&base)?;
+    let actual = crate::ssh::agent::sha256_hex(&binary);
+    if actual != expected {
+        return Err(SshError::Download(format!(
+            "checksum mismatch for {base}: expected {expected}, got {actual}"
+        )));
+    }
+    let signature = require_signature(
+        signature::AGENT_PUBLIC_KEY,
+        fetch(&format!("{base}{SIGNATURE_SUFFIX}")),
+    )?;
+
+    let path = cached_path(os, arch);
+    let io_err = |source| SshError::LocalIo {
+        path: path.clone(),
+        source,
+    };
+    let dir = path.parent().expect("cache path has a parent");
+    fs::create_dir_all(dir).map_err(io_err)?;
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, &binary).map_err(io_err)?;
+    fs::rename(&tmp, &path).map_err(io_err)?;
+
+    let mut sig_path = path.clone().into_os_string();
+    sig_path.push(SIGNATURE_SUFFIX);
+    match signature {
+        Some(sig) => fs::write(&sig_path, sig).map_err(io_err)?,
+        None => {
+            let _ = fs::remove_file(&sig_path);
+        }
+    }
+    Ok(path)
+}
+
+fn fetch(url: &str) -> Result<Vec<u8>, SshError> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| SshError::Download(format!("{url}: {e}")))?;
+    let mut body = Vec::new();
+    response
+        .into_body()
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES)
+        .read_to_end(&mut body)
+        .map_err(|e| SshError::Download(format!("{url}: {e}")))?;
+    Ok(body)
+}
+
+/// A build that verifies agents needs the `.sig`; others install unsigned
+/// agents as before.
+fn require_signature(
+    public_key: Option<&str>,
+    fetched: Result<Vec<u8>, SshError>,
+) -> Result<Option<Vec<u8>>, SshError> {
+    match (public_key, fetched) {
+        (_, Ok(sig)) => Ok(Some(sig)),
+        (Some(_), Err(e)) => Err(SshError::Download(format!(
+            "no signature for the agent, refusing to install it ({e})"
+        ))),
+        (None, Err(_)) => Ok(None),
+    }
+}
+
+/// First 64-hex-digit token of a `sha256sum`-style file.
+fn parse_checksum(content: &str) -> Option<String> {
+    content
+        .split_whitespace()
+        .find(|t| t.len() == 64 && t.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_file_formats() {
+        let hash = "AB".repeat(32);
+        assert_eq!(
+            parse_checksum(&format!("{hash}  sshfwd-agent-linux-x86_64\n")),
+            Some("ab".repeat(32))
+        );
+        assert_eq!(parse_checksum(&"a".repeat(64)), Some("a".repeat(64)));
+        assert_eq!(parse_checksum("404: Not Found"), None);
+    }
+
+    #[test]
+    fn missing_signature_fails_only_when_verifying() {
+        let missing = || Err(SshError::Download("404 Not Found".into()));
+        assert!(require_signature(Some("ab"), missing()).is_err());
+        assert_eq!(require_signature(None, missing()).unwrap(), None);
+        assert_eq!(
+            require_signature(Some("ab"), Ok(vec![1, 2])).unwrap(),
+            Some(vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn asset_and_cache_layout() {
+        assert_eq!(asset_name("linux", "armv7"), "sshfwd-agent-linux-armv7");
+        let path = cached_path("linux", "armv7");
+        assert!(path.ends_with(format!(
+            "agents/{}/linux-armv7/sshfwd-agent",
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+}
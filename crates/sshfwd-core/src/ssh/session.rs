@@ -0,0 +1,797 @@
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use russh::client::{self, Msg};
+use russh::{ChannelMsg, ChannelStream};
+use russh_sftp::client::SftpSession;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, DuplexStream, ReadBuf};
+use tokio::net::UnixStream;
+use tokio::sync::{mpsc, Mutex};
+use tracing::Instrument;
+
+use super::{config, mux, system};
+use crate::error::SshError;
+
+/// Chunk size for `exec_with_stdin` writes (granularity of progress reports).
+pub(super) const STDIN_CHUNK: usize = 32 * 1024;
+/// Buffer between the channel pump and the stdout reader of `exec_streaming`.
+pub(super) const STDOUT_PIPE_BYTES: usize = 64 * 1024;
+/// Remote listeners deliver their connections to the process that owns the
+/// SSH connection, so attached sessions cannot request them.
+const REVERSE_UNSHARED: &str = "reverse forwards need the sshfwd process that owns the connection";
+
+/// Output from a remote command execution.
+pub struct CommandOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub success: bool,
+}
+
+/// Stdio of a long-running remote command (see `Session::exec_streaming`).
+pub struct ExecStdio {
+    pub stdout: DuplexStream,
+    pub stdin: Pin<Box<dyn AsyncWrite + Send>>,
+    /// Stderr, one line per message; closed when the command exits.
+    pub stderr: mpsc::UnboundedReceiver<String>,
+}
+
+/// A byte stream to a remote endpoint: an SSH channel, one relayed by the
+/// process owning a shared connection (see `mux`), or an `ssh` process's
+/// stdio (see `system`).
+pub enum TunnelStream {
+    Channel(ChannelStream<Msg>),
+    Shared(BufReader<UnixStream>),
+    Process(system::ProcessStream),
+}
+
+impl AsyncRead for TunnelStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Channel(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Shared(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Process(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TunnelStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Channel(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Shared(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Process(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Channel(s) => Pin::new(s).poll_flush(cx),
+            Self::Shared(s) => Pin::new(s).poll_flush(cx),
+            Self::Process(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Channel(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Shared(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Process(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+/// SSH implementation behind a session that doesn't attach to a shared one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// russh, in process: no dependency on the local OpenSSH install.
+    #[default]
+    Russh,
+    /// The `ssh` binary (see `system`), for setups only OpenSSH handles.
+    System,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "russh" => Ok(Self::Russh),
+            "system" => Ok(Self::System),
+            _ => Err(format!(
+                "unknown SSH backend {s:?}, expected `russh` or `system`"
+            )),
+        }
+    }
+}
+
+/// How far `Session::connect_reporting` has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectStage {
+    /// Opening the TCP connection (or ProxyJump tunnel) and the SSH handshake.
+    Connecting,
+    /// Trying the ssh-agent and key files.
+    Authenticating,
+}
+
+fn ignore_stage(_: ConnectStage) {}
+
+/// An incoming reverse-forwarded connection from the SSH server.
+pub struct IncomingForward {
+    pub remote_port: u16,
+    /// `address:port` of the peer that connected to the remote listener.
+    pub originator: String,
+    pub channel: russh::Channel<russh::client::Msg>,
+}
+
+/// russh client handler — checks host keys and dispatches reverse-forward channels.
+struct ClientHandler {
+    forwarded_tx: Option<tokio::sync::mpsc::UnboundedSender<IncomingForward>>,
+    /// Where the key is looked up in `~/.ssh/known_hosts`.
+    host: String,
+    port: u16,
+}
+
+impl client::Handler for ClientHandler {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &russh::keys::PublicKey,
+    ) -> Result<bool, Self::Error> {
+        // Hosts missing from known_hosts are accepted (nothing is recorded);
+        // a key contradicting a recorded one is refused.
+        match russh::keys::check_known_hosts(&self.host, self.port, server_public_key) {
+            Err(e @ russh::keys::Error::KeyChanged { .. }) => Err(e.into()),
+            _ => Ok(true),
+        }
+    }
+
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: russh::Channel<russh::client::Msg>,
+        _connected_address: &str,
+        connected_port: u32,
+        originator_address: &str,
+        originator_port: u32,
+        _session: &mut russh::client::Session,
+    ) -> Result<(), Self::Error> {
+        if let Some(tx) = &self.forwarded_tx {
+            let _ = tx.send(IncomingForward {
+                remote_port: match u16::try_from(connected_port) {
+                    Ok(p) => p,
+                    Err(_) => return Ok(()),
+                },
+                originator: format!("{originator_address}:{originator_port}"),
+                channel,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// SSH session backed by russh (pure Rust, zero child processes).
+///
+/// The handle is `Arc<Mutex<Handle>>` rather than bare `Arc<Handle>` because
+/// `Handle::tcpip_forward` takes `&mut self` — it awaits a reply on `Handle::receiver`,
+/// which requires exclusive access. `Handle` is not `Clone`, so `Mutex` is the only
+/// way to call `&mut self` methods through a shared reference.
+/// `_jump_session` keeps any ProxyJump hop alive for the connection's lifetime.
+///
+/// A session attached to another process's connection (`connect_shared`)
+/// only holds that process's socket; every operation goes through it. One
+/// from `connect_system` holds an OpenSSH control master instead.
+#[derive(Clone)]
+pub struct Session {
+    link: Link,
+}
+
+#[derive(Clone)]
+enum Link {
+    Direct {
+        handle: Arc<Mutex<client::Handle<ClientHandler>>>,
+        _jump_session: Option<Box<Session>>,
+    },
+    Shared(PathBuf),
+    System(Arc<system::Master>),
+}
+
+/// Where a session's operations go.
+enum Route<'a> {
+    Russh(&'a Arc<Mutex<client::Handle<ClientHandler>>>),
+    Shared(&'a Path),
+    System(&'a system::Master),
+}
+
+impl Session {
+    /// Like `connect`, but attach to the connection of another sshfwd
+    /// process sharing `destination` (`mux::share`) when there is one.
+    /// Attached sessions cannot reverse-forward.
+    /// `on_stage` as for `connect_reporting`; an attach reports nothing.
+    pub async fn connect_shared(
+        destination: &str,
+        forwarded_tx: Option<tokio::sync::mpsc::UnboundedSender<IncomingForward>>,
+        on_stage: &(dyn Fn(ConnectStage) + Send + Sync),
+    ) -> Result<Self, SshError> {
+        if let Some(socket) = mux::attach(destination).await {
+            tracing::info!(destination, socket = %socket.display(), "attached to shared connection");
+            return Ok(Self {
+                link: Link::Shared(socket),
+            });
+        }
+        Self::connect_reporting(destination, forwarded_tx, on_stage).await
+    }
+
+    /// Connect through the system `ssh` binary (see `system`). Such sessions
+    /// cannot reverse-forward or be shared with other processes.
+    pub async fn connect_system(
+        destination: &str,
+        on_stage: &(dyn Fn(ConnectStage) + Send + Sync),
+    ) -> Result<Self, SshError> {
+        let span = tracing::info_span!("ssh.connect_system", destination);
+        let master = system::connect(destination, on_stage)
+            .instrument(span)
+            .await?;
+        Ok(Self {
+            link: Link::System(Arc::new(master)),
+        })
+    }
+
+    /// Whether this session goes through another process's connection.
+    pub fn is_shared(&self) -> bool {
+        matches!(self.link, Link::Shared(_))
+    }
+
+    /// Whether this session is run by russh in this process, so it can be
+    /// shared (`mux::share`) and reverse-forward.
+    pub fn is_direct(&self) -> bool {
+        matches!(self.link, Link::Direct { .. })
+    }
+
+    fn route(&self) -> Route<'_> {
+        match &self.link {
+            Link::Direct { handle, .. } => Route::Russh(handle),
+            Link::Shared(socket) => Route::Shared(socket),
+            Link::System(master) => Route::System(master),
+        }
+    }
+
+    /// The russh handle; `unsupported` explains why other sessions fail.
+    fn handle(
+        &self,
+        unsupported: &str,
+    ) -> Result<&Arc<Mutex<client::Handle<ClientHandler>>>, SshError> {
+        match self.route() {
+            Route::Russh(handle) => Ok(handle),
+            Route::Shared(_) => Err(SshError::Mux(unsupported.into())),
+            Route::System(_) => Err(SshError::System(unsupported.into())),
+        }
+    }
+
+    /// Connect and authenticate to a remote host, respecting ~/.ssh/config.
+    ///
+    /// Handles ProxyJump by recursively connecting through jump hosts and
+    /// tunneling via `channel_open_direct_tcpip`.
+    ///
+    /// `forwarded_tx` receives incoming reverse-forwarded connections from the server.
+    /// Pass `None` to disable reverse forwarding (e.g., for ProxyJump hops).
+    pub fn connect(
+        destination: &str,
+        forwarded_tx: Option<tokio::sync::mpsc::UnboundedSender<IncomingForward>>,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, SshError>> + Send + '_>> {
+        Self::connect_reporting(destination, forwarded_tx, &ignore_stage)
+    }
+
+    /// `connect`, calling `on_stage` as it progresses (for startup progress
+    /// displays). ProxyJump hops report nothing of their own.
+    pub fn connect_reporting<'a>(
+        destination: &'a str,
+        forwarded_tx: Option<tokio::sync::mpsc::UnboundedSender<IncomingForward>>,
+        on_stage: &'a (dyn Fn(ConnectStage) + Send + Sync),
+    ) -> Pin<Box<dyn Future<Output = Result<Self, SshError>> + Send + 'a>> {
+        let span = tracing::info_span!("ssh.connect", destination);
+        Box::pin(
+            async move {
+                on_stage(ConnectStage::Connecting);
+                let (explicit_user, host) = config::parse_destination(destination);
+                let cfg = config::resolve_host_config(&host);
+
+                let user = explicit_user
+                    .or(cfg.user)
+                    .unwrap_or_else(|| std::env::var("USER").unwrap_or_else(|_| "root".into()));
+                let resolved_host = cfg.hostname.unwrap_or_else(|| host.to_string());
+                let resolved_port = cfg.port.unwrap_or(22);
+
+                let (mut handle, jump_session) = if let Some(ref jump_dest) = cfg.proxy_jump {
+                    // ProxyJump: connect through the jump host, then tunnel.
+                    // The intermediate jump hop gets None; the final tunnel hop receives forwards.
+                    let jump = Session::connect(jump_dest, None).await?;
+
+                    let tunnel = jump
+                        .open_direct_tcpip(&resolved_host, resolved_port)
+                        .await
+                        .map_err(|e| match e {
+                            SshError::Remote(source) => SshError::Connection {
+                                destination: destination.to_string(),
+                                source,
+                            },
+                            e => e,
+                        })?;
+
+                    let config = Arc::new(client::Config::default());
+                    let handle = client::connect_stream(
+                        config,
+                        tunnel,
+                        ClientHandler {
+                            forwarded_tx: forwarded_tx.clone(),
+                            host: resolved_host.clone(),
+                            port: resolved_port,
+                        },
+                    )
+                    .await
+                    .map_err(|e| connect_error(destination, &resolved_host, e))?;
+
+                    (handle, Some(Box::new(jump)))
+                } else {
+                    // Direct TCP connection
+                    let addr = format!("{resolved_host}:{resolved_port}");
+                    let stream = tokio::net::TcpStream::connect(&addr).await.map_err(|e| {
+                        SshError::Config(format!("failed to connect to {addr}: {e}"))
+                    })?;
+
+                    let config = Arc::new(client::Config::default());
+                    let handle = client::connect_stream(
+                        config,
+                        stream,
+                        ClientHandler {
+                            forwarded_tx: forwarded_tx.clone(),
+                            host: resolved_host.clone(),
+                            port: resolved_port,
+                        },
+                    )
+                    .await
+                    .map_err(|e| connect_error(destination, &resolved_host, e))?;
+
+                    (handle, None)
+                };
+
+                // Authenticate with the raw handle before wrapping in Arc<Mutex>
+                on_stage(ConnectStage::Authenticating);
+                let mut tried = Vec::new();
+                if !authenticate(&mut handle, &user, &cfg.identity_files, &mut tried).await? {
+                    return Err(SshError::AuthExhausted {
+                        user,
+                        host: resolved_host,
+                        tried,
+                    });
+                }
+
+                Ok(Self {
+                    link: Link::Direct {
+                        handle: Arc::new(Mutex::new(handle)),
+                        _jump_session: jump_session,
+                    },
+                })
+            }
+            .instrument(span),
+        )
+    }
+
+    /// Open a direct-tcpip channel for port forwarding.
+    #[tracing::instrument(name = "ssh.channel_open", skip(self), err)]
+    pub async fn open_direct_tcpip(&self, host: &str, port: u16) -> Result<TunnelStream, SshError> {
+        let handle = match self.route() {
+            Route::Russh(handle) => handle,
+            Route::Shared(socket) => return mux::open_direct_tcpip(socket, host, port).await,
+            Route::System(master) => return master.open_direct_tcpip(host, port),
+        };
+        let channel = handle
+            .lock()
+            .await
+            .channel_open_direct_tcpip(host.to_string(), port as u32, "127.0.0.1", 0)
+            .await
+            .map_err(|e| channel_open_error(e, false))?;
+        Ok(TunnelStream::Channel(channel.into_stream()))
+    }
+
+    /// Open a session channel running `command`.
+    pub(super) async fn exec_channel(
+        &self,
+        command: &str,
+    ) -> Result<russh::Channel<Msg>, SshError> {
+        let handle = self.handle("exec_channel needs a russh session")?;
+        let channel = handle
+            .lock()
+            .await
+            .channel_open_session()
+            .await
+            .map_err(|e| channel_open_error(e, true))?;
+        channel
+            .exec(true, command)
+            .await
+            .map_err(SshError::Remote)?;
+        Ok(channel)
+    }
+
+    /// Open a session channel for the subsystem `name` (e.g. `sftp`).
+    pub(super) async fn subsystem(&self, name: &str) -> Result<TunnelStream, SshError> {
+        let handle = match self.route() {
+            Route::Russh(handle) => handle,
+            Route::Shared(socket) => return mux::subsystem(socket, name).await,
+            Route::System(master) => return master.subsystem(name),
+        };
+        let channel = handle
+            .lock()
+            .await
+            .channel_open_session()
+            .await
+            .map_err(|e| channel_open_error(e, true))?;
+        channel
+            .request_subsystem(true, name)
+            .await
+            .map_err(SshError::Remote)?;
+        Ok(TunnelStream::Channel(channel.into_stream()))
+    }
+
+    /// Execute a command and collect all output.
+    #[tracing::instrument(name = "ssh.exec", skip(self), err)]
+    pub async fn exec(&self, command: &str) -> Result<CommandOutput, SshError> {
+        match self.route() {
+            Route::Russh(_) => {}
+            Route::Shared(socket) => return mux::exec(socket, command, None, |_| {}).await,
+            Route::System(master) => return master.exec(command, None, |_| {}).await,
+        }
+        let mut channel = self.exec_channel(command).await?;
+        collect_channel_output(&mut channel).await
+    }
+
+    /// Execute a long-running command and return its stdio.
+    ///
+    /// A background task pumps the channel: stdout goes to `ExecStdio::stdout`,
+    /// stderr is split into lines on `ExecStdio::stderr`. The channel is closed
+    /// once stdout is dropped (noticed on the next output) or the command exits.
+    #[tracing::instrument(name = "ssh.exec_streaming", skip(self), err)]
+    pub async fn exec_streaming(&self, command: &str) -> Result<ExecStdio, SshError> {
+        match self.route() {
+            Route::Russh(_) => {}
+            Route::Shared(socket) => return mux::exec_streaming(socket, command).await,
+            Route::System(master) => return master.exec_streaming(command),
+        }
+        let channel = self.exec_channel(command).await?;
+        let (mut reader, writer) = channel.split();
+        let stdin = Box::pin(writer.make_writer());
+        let (mut stdout_tx, stdout) = tokio::io::duplex(STDOUT_PIPE_BYTES);
+        let (stderr_tx, stderr) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut partial = Vec::new();
+            while let Some(msg) = reader.wait().await {
+                let open = match msg {
+                    // Fails once the stdout reader is gone.
+                    ChannelMsg::Data { data } => stdout_tx.write_all(&data).await.is_ok(),
+                    ChannelMsg::ExtendedData { data, ext: 1 } => {
+                        for line in split_lines(&mut partial, &data) {
+                            let _ = stderr_tx.send(line);
+                        }
+                        true
+                    }
+                    ChannelMsg::Eof | ChannelMsg::Close => false,
+                    _ => true,
+                };
+                if !open {
+                    break;
+                }
+            }
+            if !partial.is_empty() {
+                let _ = stderr_tx.send(String::from_utf8_lossy(&partial).trim_end().to_string());
+            }
+            let _ = writer.close().await;
+        });
+
+        Ok(ExecStdio {
+            stdout,
+            stdin,
+            stderr,
+        })
+    }
+
+    /// Open an SFTP session on a fresh channel. Used when the remote shell
+    /// refuses exec pipelines but still allows the `sftp` subsystem.
+    #[tracing::instrument(name = "ssh.sftp", skip(self), err)]
+    pub async fn sftp(&self) -> Result<SftpSession, SshError> {
+        SftpSession::new(self.subsystem("sftp").await?)
+            .await
+            .map_err(|e| SshError::Sftp(e.to_string()))
+    }
+
+    /// Execute a command, write data to its stdin, then collect output.
+    ///
+    /// Data is written in `STDIN_CHUNK` pieces; `on_progress` receives the
+    /// running byte count after each one is accepted by the channel.
+    #[tracing::instrument(
+        name = "ssh.exec_with_stdin",
+        skip(self, data, on_progress),
+        fields(bytes = data.len()),
+        err
+    )]
+    pub async fn exec_with_stdin(
+        &self,
+        command: &str,
+        data: &[u8],
+        mut on_progress: impl FnMut(usize),
+    ) -> Result<CommandOutput, SshError> {
+        match self.route() {
+            Route::Russh(_) => {}
+            Route::Shared(socket) => {
+                return mux::exec(socket, command, Some(data), on_progress).await
+            }
+            Route::System(master) => return master.exec(command, Some(data), on_progress).await,
+        }
+        let mut channel = self.exec_channel(command).await?;
+        let mut sent = 0;
+        for chunk in data.chunks(STDIN_CHUNK) {
+            channel.data(chunk).await.map_err(SshError::Remote)?;
+            sent += chunk.len();
+            on_progress(sent);
+        }
+        channel.eof().await.map_err(SshError::Remote)?;
+
+        collect_channel_output(&mut channel).await
+    }
+
+    fn reverse_handle(&self) -> Result<&Arc<Mutex<client::Handle<ClientHandler>>>, SshError> {
+        match self.route() {
+            Route::System(_) => self.handle(system::REVERSE_UNSUPPORTED),
+            _ => self.handle(REVERSE_UNSHARED),
+        }
+    }
+
+    /// Request the SSH server to listen on the given port for reverse forwarding.
+    /// Returns the actual bound port (useful if 0 was passed for server-assigned port).
+    #[tracing::instrument(name = "ssh.tcpip_forward", skip(self), err)]
+    pub async fn tcpip_forward(&self, port: u16) -> Result<u16, SshError> {
+        self.reverse_handle()?
+            .lock()
+            .await
+            .tcpip_forward("127.0.0.1", port as u32)
+            .await
+            .map(|p| p as u16)
+            .map_err(SshError::Remote)
+    }
+
+    /// Cancel a previously requested remote port listening.
+    pub async fn cancel_tcpip_forward(&self, port: u16) -> Result<(), SshError> {
+        self.reverse_handle()?
+            .lock()
+            .await
+            .cancel_tcpip_forward("127.0.0.1", port as u32)
+            .await
+            .map_err(SshError::Remote)
+    }
+}
+
+/// Append `data` to `partial` and return every completed, non-empty line.
+pub(super) fn split_lines(partial: &mut Vec<u8>, data: &[u8]) -> Vec<String> {
+    partial.extend_from_slice(data);
+    let mut lines = Vec::new();
+    while let Some(pos) = partial.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = partial.drain(..=pos).collect();
+        let line = String::from_utf8_lossy(&line).trim_end().to_string();
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Drain a channel's messages into a `CommandOutput`.
+async fn collect_channel_output(
+    channel: &mut russh::Channel<Msg>,
+) -> Result<CommandOutput, SshError> {
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut exit_status = 0u32;
+
+    loop {
+        match channel.wait().await {
+            Some(ChannelMsg::Data { data }) => stdout.extend_from_slice(&data),
+            Some(ChannelMsg::ExtendedData { data, ext: 1 }) => stderr.extend_from_slice(&data),
+            Some(ChannelMsg::ExitStatus { exit_status: code }) => exit_status = code,
+            None => break,
+            _ => {}
+        }
+    }
+
+    Ok(CommandOutput {
+        stdout,
+        stderr,
+        success: exit_status == 0,
+    })
+}
+
+/// Try ssh-agent first, then IdentityFile from config, then default key locations.
+/// `SshError::Connection`, or `HostKeyMismatch` when `check_server_key`
+/// refused the key.
+fn connect_error(destination: &str, host: &str, error: russh::Error) -> SshError {
+    match error {
+        russh::Error::Keys(russh::keys::Error::KeyChanged { line }) => SshError::HostKeyMismatch {
+            host: host.to_string(),
+            line,
+        },
+        source => SshError::Connection {
+            destination: destination.to_string(),
+            source,
+        },
+    }
+}
+
+/// A refused channel open as `ChannelLimit` when it is the server's
+/// channel limit. OpenSSH refuses session channels past `MaxSessions` as
+/// administratively prohibited, which for direct-tcpip means forwarding is
+/// disabled instead.
+fn channel_open_error(error: russh::Error, session_channel: bool) -> SshError {
+    use russh::ChannelOpenFailure::{AdministrativelyProhibited, ResourceShortage};
+    match error {
+        russh::Error::ChannelOpenFailure(ResourceShortage) => {
+            SshError::ChannelLimit("resource shortage".into())
+        }
+        russh::Error::ChannelOpenFailure(AdministrativelyProhibited) if session_channel => {
+            SshError::ChannelLimit("administratively prohibited".into())
+        }
+        e => SshError::Remote(e),
+    }
+}
+
+/// Try ssh-agent, then key files; what was offered is added to `tried`.
+async fn authenticate(
+    handle: &mut client::Handle<ClientHandler>,
+    user: &str,
+    identity_files: &[PathBuf],
+    tried: &mut Vec<String>,
+) -> Result<bool, SshError> {
+    let rsa_hash = handle
+        .best_supported_rsa_hash()
+        .await
+        .ok()
+        .flatten()
+        .flatten();
+
+    // 1. Try ssh-agent
+    if let Ok(mut agent) = russh::keys::agent::client::AgentClient::connect_env().await {
+        if let Ok(identities) = agent.request_identities().await {
+            if !identities.is_empty() {
+                tried.push(format!("{} ssh-agent keys", identities.len()));
+            }
+            for key in identities {
+                match handle
+                    .authenticate_publickey_with(
+                        user,
+                        key.public_key().into_owned(),
+                        rsa_hash,
+                        &mut agent,
+                    )
+                    .await
+                {
+                    Ok(res) if res.success() => return Ok(true),
+                    _ => continue,
+                }
+            }
+        }
+    }
+
+    // 2. Try IdentityFile from SSH config
+    for path in identity_files {
+        if try_key_file(handle, user, rsa_hash, path, tried).await? {
+            return Ok(true);
+        }
+    }
+
+    // 3. Try default key files
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    let default_keys = [
+        PathBuf::from(format!("{home}/.ssh/id_ed25519")),
+        PathBuf::from(format!("{home}/.ssh/id_rsa")),
+        PathBuf::from(format!("{home}/.ssh/id_ecdsa")),
+    ];
+
+    for path in &default_keys {
+        if try_key_file(handle, user, rsa_hash, path, tried).await? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+async fn try_key_file(
+    handle: &mut client::Handle<ClientHandler>,
+    user: &str,
+    rsa_hash: Option<russh::keys::HashAlg>,
+    path: &Path,
+    tried: &mut Vec<String>,
+) -> Result<bool, SshError> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let key = match russh::keys::load_secret_key(path, None) {
+        Ok(k) => k,
+        Err(_) => {
+            tried.push(format!(
+                "{} (unreadable or passphrase-protected)",
+                path.display()
+            ));
+            return Ok(false);
+        }
+    };
+    tried.push(path.display().to_string());
+    let key = russh::keys::PrivateKeyWithHashAlg::new(Arc::new(key), rsa_hash);
+    match handle.authenticate_publickey(user, key).await {
+        Ok(res) if res.success() => Ok(true),
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refused_channels_name_the_limit() {
+        use russh::ChannelOpenFailure::*;
+        let refused =
+            |reason, session| channel_open_error(russh::Error::ChannelOpenFailure(reason), session);
+        assert!(matches!(
+            refused(ResourceShortage, false),
+            SshError::ChannelLimit(_)
+        ));
+        assert!(matches!(
+            refused(AdministrativelyProhibited, true),
+            SshError::ChannelLimit(_)
+        ));
+        assert!(matches!(
+            refused(AdministrativelyProhibited, false),
+            SshError::Remote(_)
+        ));
+        assert!(matches!(refused(ConnectFailed, true), SshError::Remote(_)));
+        assert!(refused(ResourceShortage, true)
+            .hint()
+            .unwrap()
+            .contains("MaxSessions"));
+
+        let changed = connect_error(
+            "dev@box",
+            "box.internal",
+            russh::keys::Error::KeyChanged { line: 7 }.into(),
+        );
+        assert_eq!(
+            changed.to_string(),
+            "host key for box.internal does not match ~/.ssh/known_hosts line 7"
+        );
+        assert!(changed
+            .hint()
+            .unwrap()
+            .contains("ssh-keygen -R box.internal"));
+    }
+
+    #[test]
+    fn stderr_lines_split_across_packets() {
+        let mut partial = Vec::new();
+        assert!(split_lines(&mut partial, b"thread 'main' pan").is_empty());
+        assert_eq!(
+            split_lines(&mut partial, b"icked\r\n\nnote: run with"),
+            vec!["thread 'main' panicked".to_string()]
+        );
+        assert_eq!(partial, b"note: run with");
+    }
+}
@@ -0,0 +1,346 @@
+//! The system `ssh` binary as SSH implementation (`[ssh] backend = "system"`).
+//!
+//! [`connect`] starts an OpenSSH control master (`ssh -M -N`) on a private
+//! socket, so authentication happens once and with everything OpenSSH
+//! supports: its whole config language, PKCS#11 and FIDO keys, GSSAPI,
+//! `known_hosts` checking and its own prompts. Each operation is then one
+//! `ssh -S <socket>` process multiplexed over the master: commands run as
+//! given, channels are `-W host:port` or `-s <subsystem>` with the process's
+//! stdio as the byte stream.
+//!
+//! Connections to a reverse forward would arrive at the master rather than
+//! at sshfwd, so system sessions refuse reverse forwards.
+
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf,
+};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::mpsc;
+
+use super::session::{
+    CommandOutput, ConnectStage, ExecStdio, TunnelStream, STDIN_CHUNK, STDOUT_PIPE_BYTES,
+};
+use crate::error::SshError;
+
+const SSH: &str = "ssh";
+/// How often `connect` looks for the master's control socket.
+const MASTER_POLL: Duration = Duration::from_millis(50);
+
+pub(super) const REVERSE_UNSUPPORTED: &str =
+    "reverse forwards need the built-in SSH client (`[ssh] backend = \"russh\"`)";
+
+/// A running control master; stopped, and its socket removed, on drop.
+pub struct Master {
+    destination: String,
+    dir: PathBuf,
+    socket: PathBuf,
+    _child: Child,
+}
+
+impl Drop for Master {
+    fn drop(&mut self) {
+        // The child is killed on drop; its socket would be left behind.
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A byte stream over a `-W` or `-s` process's stdio.
+pub struct ProcessStream {
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    _child: Child,
+}
+
+impl AsyncRead for ProcessStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ProcessStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stdin).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_shutdown(cx)
+    }
+}
+
+/// A fresh 0700 directory for one master's control socket. Kept short: socket
+/// paths are limited to about 100 bytes.
+fn control_dir() -> io::Result<PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    let name = format!(
+        "{}{}",
+        control_prefix(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    );
+    let dir = std::env::temp_dir().join(name);
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    Ok(dir)
+}
+
+/// Name prefix of this process's control directories.
+fn control_prefix() -> String {
+    format!("sshfwd-{}-", std::process::id())
+}
+
+/// Stop every control master of this process and remove its directory, for
+/// exits that skip destructors (`process::exit`). The `ssh -S` processes
+/// multiplexed over a master, remote agents included, end with it.
+pub fn stop_masters() {
+    let prefix = control_prefix();
+    let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    let dirs = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path());
+    for dir in dirs {
+        let socket = dir.join("ctl");
+        if socket.exists() {
+            // `-O` wants a destination; the socket alone decides.
+            let _ = std::process::Command::new(SSH)
+                .arg("-S")
+                .arg(&socket)
+                .args(["-O", "exit", "--", "sshfwd"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+/// Start a control master for `destination` and wait until it has
+/// authenticated. OpenSSH asks for passwords and host key confirmation on
+/// the terminal (or through `SSH_ASKPASS`) itself.
+pub(super) async fn connect(
+    destination: &str,
+    on_stage: &(dyn Fn(ConnectStage) + Send + Sync),
+) -> Result<Master, SshError> {
+    on_stage(ConnectStage::Connecting);
+    let dir = control_dir().map_err(|source| SshError::LocalIo {
+        path: std::env::temp_dir(),
+        source,
+    })?;
+    let socket = dir.join("ctl");
+    let mut child = Command::new(SSH)
+        .arg("-M")
+        .arg("-N")
+        .arg("-S")
+        .arg(&socket)
+        .args(["-o", "ControlPersist=no"])
+        .arg("--")
+        .arg(destination)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| SshError::System(format!("cannot run {SSH}: {e}")))?;
+    // The master only binds its socket once authenticated; there is no
+    // finer progress to report.
+    on_stage(ConnectStage::Authenticating);
+
+    loop {
+        if socket.exists() {
+            return Ok(Master {
+                destination: destination.to_string(),
+                dir,
+                socket,
+                _child: child,
+            });
+        }
+        tokio::select! {
+            _ = child.wait() => {
+                let mut stderr = String::new();
+                if let Some(mut pipe) = child.stderr.take() {
+                    let _ = pipe.read_to_string(&mut stderr).await;
+                }
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(SshError::System(format!(
+                    "{SSH} {destination}: {}",
+                    stderr.trim()
+                )));
+            }
+            _ = tokio::time::sleep(MASTER_POLL) => {}
+        }
+    }
+}
+
+impl Master {
+    /// `ssh` going through the master, options in `args`; the caller appends
+    /// the command.
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(SSH);
+        command
+            .arg("-S")
+            .arg(&self.socket)
+            .args(["-o", "ControlMaster=no", "-T"])
+            .args(args)
+            .arg("--")
+            .arg(&self.destination)
+            .kill_on_drop(true);
+        command
+    }
+
+    fn stream(&self, mut command: Command) -> Result<TunnelStream, SshError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| SshError::System(format!("cannot run {SSH}: {e}")))?;
+        Ok(TunnelStream::Process(ProcessStream {
+            stdin: child.stdin.take().expect("piped stdin"),
+            stdout: child.stdout.take().expect("piped stdout"),
+            _child: child,
+        }))
+    }
+
+    pub(super) fn open_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<TunnelStream, SshError> {
+        let target = if host.contains(':') {
+            format!("[{host}]:{port}")
+        } else {
+            format!("{host}:{port}")
+        };
+        self.stream(self.command(&["-W", &target]))
+    }
+
+    pub(super) fn subsystem(&self, name: &str) -> Result<TunnelStream, SshError> {
+        let mut command = self.command(&["-s"]);
+        command.arg(name);
+        self.stream(command)
+    }
+
+    /// Run `command`. With `stdin`, it is written (reporting progress like
+    /// `Session::exec_with_stdin`) and followed by EOF.
+    pub(super) async fn exec(
+        &self,
+        command: &str,
+        stdin: Option<&[u8]>,
+        mut on_progress: impl FnMut(usize),
+    ) -> Result<CommandOutput, SshError> {
+        let mut child = self
+            .command(&[])
+            .arg(command)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| SshError::System(format!("cannot run {SSH}: {e}")))?;
+        if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            let mut sent = 0;
+            for chunk in data.chunks(STDIN_CHUNK) {
+                // A command that exits early closes its stdin: its output
+                // tells why.
+                if pipe.write_all(chunk).await.is_err() {
+                    break;
+                }
+                sent += chunk.len();
+                on_progress(sent);
+            }
+        }
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| SshError::System(e.to_string()))?;
+        Ok(CommandOutput {
+            stdout: output.stdout,
+            stderr: output.stderr,
+            success: output.status.success(),
+        })
+    }
+
+    /// `Session::exec_streaming` on a process of its own.
+    pub(super) fn exec_streaming(&self, command: &str) -> Result<ExecStdio, SshError> {
+        let mut child = self
+            .command(&[])
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| SshError::System(format!("cannot run {SSH}: {e}")))?;
+        let stdin = child.stdin.take().expect("piped stdin");
+        let mut out = child.stdout.take().expect("piped stdout");
+        let err = child.stderr.take().expect("piped stderr");
+        let (mut stdout_tx, stdout) = tokio::io::duplex(STDOUT_PIPE_BYTES);
+        let (stderr_tx, stderr) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(err).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim_end();
+                if !line.is_empty() {
+                    let _ = stderr_tx.send(line.to_string());
+                }
+            }
+        });
+        tokio::spawn(async move {
+            // Ends when the command exits or, on its next output, once the
+            // stdout reader is gone.
+            let _ = tokio::io::copy(&mut out, &mut stdout_tx).await;
+            let _ = child.kill().await;
+        });
+
+        Ok(ExecStdio {
+            stdout,
+            stdin: Box::pin(stdin),
+            stderr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_dirs_are_private_and_distinct() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let a = control_dir().unwrap();
+        let b = control_dir().unwrap();
+        assert_ne!(a, b);
+        let mode = std::fs::metadata(&a).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert!(a.join("ctl").as_os_str().len() < 100);
+
+        stop_masters();
+        assert!(!a.exists() && !b.exists());
+    }
+}
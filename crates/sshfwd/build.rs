@@ -2,13 +2,19 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-/// Platform targets for embedded agent binaries.
-const PLATFORMS: &[(&str, &str, &str)] = &[
-    // (os, arch, directory name)
-    ("linux", "x86_64", "linux-x86_64"),
-    ("linux", "aarch64", "linux-aarch64"),
-    ("darwin", "x86_64", "darwin-x86_64"),
-    ("darwin", "aarch64", "darwin-aarch64"),
+/// `prebuilt-agents/` directories embedded when present: `<os>-<arch>` for the
+/// default (static musl on Linux) builds, `-gnu` for optional glibc builds.
+const PLATFORMS: &[&str] = &[
+    "linux-x86_64",
+    "linux-aarch64",
+    "linux-armv7",
+    "linux-riscv64",
+    "darwin-x86_64",
+    "darwin-aarch64",
+    "linux-x86_64-gnu",
+    "linux-aarch64-gnu",
+    "linux-armv7-gnu",
+    "linux-riscv64-gnu",
 ];
 
 fn main() {
@@ -17,39 +23,58 @@ fn main() {
     let prebuilt_dir = manifest_dir.join("prebuilt-agents");
 
     let mut code = String::new();
-    code.push_str("/// Returns the embedded agent binary for the given OS and architecture.\n");
+    code.push_str(
+        "/// Returns the embedded agent binary for a `prebuilt-agents/` directory name.\n",
+    );
     code.push_str("/// Returns `None` if no binary was available at build time.\n");
-    code.push_str("pub fn get_agent_binary(os: &str, arch: &str) -> Option<&'static [u8]> {\n");
-    code.push_str("    match (os, arch) {\n");
+    push_lookup(&mut code, "get_agent_binary", &prebuilt_dir, "sshfwd-agent");
+    code.push_str(
+        "\n/// Returns the detached ed25519 signature shipped with the embedded agent.\n",
+    );
+    code.push_str("/// Returns `None` if the agent was not signed at build time.\n");
+    push_lookup(
+        &mut code,
+        "get_agent_signature",
+        &prebuilt_dir,
+        "sshfwd-agent.sig",
+    );
 
-    for &(os, arch, dir_name) in PLATFORMS {
-        let binary_path = prebuilt_dir.join(dir_name).join("sshfwd-agent");
-        let canonical = binary_path.display().to_string();
+    let dest = out_dir.join("embedded_agents.rs");
+    fs::write(&dest, code).expect("failed to write embedded_agents.rs");
+
+    // Re-run if the prebuilt-agents directory structure changes
+    rerun_if_dir_changed(&prebuilt_dir);
+}
+
+/// Emit `fn_name(dir)` returning `include_bytes!` of `<dir>/<file_name>`.
+fn push_lookup(code: &mut String, fn_name: &str, prebuilt_dir: &Path, file_name: &str) {
+    code.push_str(&format!(
+        "pub fn {fn_name}(dir: &str) -> Option<&'static [u8]> {{\n"
+    ));
+    code.push_str("    match dir {\n");
 
-        // Tell cargo to re-run if the binary changes
+    for &dir_name in PLATFORMS {
+        let path = prebuilt_dir.join(dir_name).join(file_name);
+        let canonical = path.display().to_string();
+
+        // Tell cargo to re-run if the file changes
         println!("cargo:rerun-if-changed={canonical}");
 
-        if binary_path.exists() {
+        if path.exists() {
             code.push_str(&format!(
-                "        (\"{os}\", \"{arch}\") => Some(include_bytes!(\"{canonical}\")),\n"
+                "        \"{dir_name}\" => Some(include_bytes!(\"{canonical}\")),\n"
             ));
         } else {
             code.push_str(&format!(
                 "        // not found at build time: {canonical}\n"
             ));
-            code.push_str(&format!("        (\"{os}\", \"{arch}\") => None,\n"));
+            code.push_str(&format!("        \"{dir_name}\" => None,\n"));
         }
     }
 
     code.push_str("        _ => None,\n");
     code.push_str("    }\n");
     code.push_str("}\n");
-
-    let dest = out_dir.join("embedded_agents.rs");
-    fs::write(&dest, code).expect("failed to write embedded_agents.rs");
-
-    // Re-run if the prebuilt-agents directory structure changes
-    rerun_if_dir_changed(&prebuilt_dir);
 }
 
 fn rerun_if_dir_changed(dir: &Path) {
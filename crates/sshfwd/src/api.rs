@@ -0,0 +1,458 @@
+//! Local REST API (`[api]` in the config) for tools that manage forwards
+//! alongside the TUI.
+//!
+//! Listens on `127.0.0.1:<port>` only; every request needs
+//! `Authorization: Bearer <token>`. Requests are turned into
+//! `Message::Api` and answered by `app::update`, so the API sees and changes
+//! exactly the state the TUI shows.
+//!
+//! - `GET /v1/snapshot` — destination, remote ports and forwards (as `s` saves)
+//! - `GET /v1/ports` — remote listening ports
+//! - `GET /v1/forwards` — forwards with status and open connections
+//! - `POST /v1/forwards` — `{"remote_port": 5432, "local_port": 15432, "kind":
+//!   "local", "confirm": false}`; only `remote_port` is required. `202` when
+//!   the forward is starting, `403` when `[policy]` refuses it, `409` when the
+//!   policy wants confirmation and `confirm` isn't set.
+//! - `DELETE /v1/forwards/<local|reverse>/<port>` — stop a forward
+//! - `GET /v1/hosts` — connected destinations, in command-line order
+//!
+//! With several destinations, `/v1/hosts/<destination>/...` addresses one
+//! host (e.g. `/v1/hosts/db/ports`); the unprefixed paths serve the first.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use sshfwd_common::token;
+
+use crate::app::Message;
+use crate::forward::ForwardKind;
+
+/// Largest request head or body accepted.
+const MAX_REQUEST: usize = 64 * 1024;
+/// Time allowed for reading a request and for the main loop to answer it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections served at once; more are turned away with a 503.
+const MAX_CONNECTIONS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiRequest {
+    Snapshot,
+    Ports,
+    Forwards,
+    StartForward(NewForward),
+    StopForward { kind: ForwardKind, remote_port: u16 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewForward {
+    pub remote_port: u16,
+    /// Defaults to `remote_port`.
+    pub local_port: Option<u16>,
+    #[serde(default)]
+    pub kind: ForwardKind,
+    /// Accept a `[policy]` confirmation (privileged or root-owned port).
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiReply {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl ApiReply {
+    pub fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// A request waiting for the main loop's answer on `reply`.
+pub struct ApiCall {
+    pub request: ApiRequest,
+    pub reply: crossbeam_channel::Sender<ApiReply>,
+}
+
+/// A connected destination and the channel its main loop reads.
+pub type ApiHost = (String, crossbeam_channel::Sender<Message>);
+
+/// Bind the API port and serve `hosts` (at least one) from background threads.
+pub fn spawn(port: u16, token: String, hosts: Vec<ApiHost>) -> io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    std::thread::spawn(move || accept(listener, token, hosts));
+    Ok(())
+}
+
+/// Serve each connection on its own thread, up to `MAX_CONNECTIONS`.
+fn accept(listener: TcpListener, token: String, hosts: Vec<ApiHost>) {
+    let hosts = Arc::new(hosts);
+    let active = Arc::new(AtomicUsize::new(0));
+    for mut stream in listener.incoming().flatten() {
+        let _ = stream.set_write_timeout(Some(TIMEOUT));
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let _ = write_reply(&mut stream, &ApiReply::error(503, "too many connections"));
+            continue;
+        }
+        let (token, hosts, active) = (token.clone(), Arc::clone(&hosts), Arc::clone(&active));
+        std::thread::spawn(move || {
+            serve(stream, &token, &hosts);
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+fn serve(mut stream: TcpStream, token: &str, hosts: &[ApiHost]) {
+    let deadline = Deadline {
+        stream: &stream,
+        until: Instant::now() + TIMEOUT,
+    };
+    let reply = match read_request(deadline) {
+        Ok(request) => answer(request, token, hosts),
+        Err(e) => ApiReply::error(400, e),
+    };
+    let _ = write_reply(&mut stream, &reply);
+}
+
+fn answer(request: HttpRequest, token: &str, hosts: &[ApiHost]) -> ApiReply {
+    if !authorized(request.authorization.as_deref(), token) {
+        return ApiReply::error(401, "missing or wrong bearer token");
+    }
+    let (tx, path) = match host_path(&request.path) {
+        HostPath::List if request.method == "GET" => {
+            let names: Vec<&str> = hosts.iter().map(|(name, _)| name.as_str()).collect();
+            return ApiReply::ok(json!(names));
+        }
+        HostPath::List => return ApiReply::error(405, "method not allowed"),
+        HostPath::Host(name, path) => match hosts.iter().find(|(n, _)| *n == name) {
+            Some((_, tx)) => (tx, path),
+            None => return ApiReply::error(404, format!("no host {name:?}")),
+        },
+        HostPath::First => (&hosts[0].1, request.path.clone()),
+    };
+    let request = match route(&request.method, &path, &request.body) {
+        Ok(request) => request,
+        Err(reply) => return reply,
+    };
+    let (reply_tx, reply_rx) = crossbeam_channel::bounded(1);
+    let call = ApiCall {
+        request,
+        reply: reply_tx,
+    };
+    if tx.send(Message::Api(call)).is_err() {
+        return ApiReply::error(503, "sshfwd is shutting down");
+    }
+    reply_rx
+        .recv_timeout(TIMEOUT)
+        .unwrap_or_else(|_| ApiReply::error(503, "no answer from sshfwd"))
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Reads from `stream` until `until`, however slowly the client sends.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request not received in time",
+            ));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+fn read_request(stream: Deadline<'_>) -> Result<HttpRequest, String> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST as u64 * 2));
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("malformed request line".to_string());
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut authorization = None;
+    let mut content_length = 0usize;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().map_err(|_| "bad Content-Length")?;
+            }
+        }
+    }
+    if content_length > MAX_REQUEST {
+        return Err("request body too large".to_string());
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok(HttpRequest {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some((scheme, credentials)) = header.and_then(|h| h.split_once(' ')) else {
+        return false;
+    };
+    scheme.eq_ignore_ascii_case("bearer")
+        && token::matches(credentials.trim().as_bytes(), token.as_bytes())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum HostPath {
+    /// `/v1/hosts`
+    List,
+    /// `/v1/hosts/<destination>/<rest>`, with `<rest>` as `/v1/<rest>`.
+    Host(String, String),
+    /// Anything else goes to the first host.
+    First,
+}
+
+fn host_path(path: &str) -> HostPath {
+    let Some(rest) = path.trim_matches('/').strip_prefix("v1/hosts") else {
+        return HostPath::First;
+    };
+    let Some(rest) = rest.strip_prefix('/') else {
+        return if rest.is_empty() {
+            HostPath::List
+        } else {
+            HostPath::First
+        };
+    };
+    let (name, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    HostPath::Host(name.to_string(), format!("/v1/{rest}"))
+}
+
+fn route(method: &str, path: &str, body: &[u8]) -> Result<ApiRequest, ApiReply> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["v1", "snapshot"]) => Ok(ApiRequest::Snapshot),
+        ("GET", ["v1", "ports"]) => Ok(ApiRequest::Ports),
+        ("GET", ["v1", "forwards"]) => Ok(ApiRequest::Forwards),
+        ("POST", ["v1", "forwards"]) => serde_json::from_slice(body)
+            .map(ApiRequest::StartForward)
+            .map_err(|e| ApiReply::error(400, format!("invalid forward: {e}"))),
+        ("DELETE", ["v1", "forwards", kind, port]) => {
+            let kind = match *kind {
+                "local" => ForwardKind::Local,
+                "reverse" => ForwardKind::Reverse,
+                _ => return Err(ApiReply::error(404, "kind must be local or reverse")),
+            };
+            let remote_port = port
+                .parse()
+                .map_err(|_| ApiReply::error(400, format!("invalid port {port:?}")))?;
+            Ok(ApiRequest::StopForward { kind, remote_port })
+        }
+        (_, ["v1", "snapshot" | "ports" | "forwards", ..]) => {
+            Err(ApiReply::error(405, "method not allowed"))
+        }
+        _ => Err(ApiReply::error(404, "not found")),
+    }
+}
+
+fn write_reply(stream: &mut TcpStream, reply: &ApiReply) -> io::Result<()> {
+    let reason = match reply.status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Service Unavailable",
+    };
+    let body = reply.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reply.status,
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_and_bearer_auth() {
+        assert!(authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(authorized(Some("bearer  s3cret "), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!authorized(Some("Basic s3cret"), "s3cret"));
+        assert!(!authorized(None, "s3cret"));
+
+        assert_eq!(route("GET", "/v1/ports", b""), Ok(ApiRequest::Ports));
+        assert_eq!(
+            route("POST", "/v1/forwards", br#"{"remote_port": 5432}"#),
+            Ok(ApiRequest::StartForward(NewForward {
+                remote_port: 5432,
+                local_port: None,
+                kind: ForwardKind::Local,
+                confirm: false,
+            }))
+        );
+        assert_eq!(
+            route("DELETE", "/v1/forwards/reverse/9000", b""),
+            Ok(ApiRequest::StopForward {
+                kind: ForwardKind::Reverse,
+                remote_port: 9000,
+            })
+        );
+        assert_eq!(route("PUT", "/v1/ports", b"").unwrap_err().status, 405);
+        assert_eq!(route("GET", "/v2", b"").unwrap_err().status, 404);
+        assert_eq!(
+            route("POST", "/v1/forwards", b"{}").unwrap_err().status,
+            400
+        );
+    }
+
+    #[test]
+    fn host_prefix_picks_a_destination() {
+        assert_eq!(host_path("/v1/hosts"), HostPath::List);
+        assert_eq!(host_path("/v1/hosts/"), HostPath::List);
+        assert_eq!(
+            host_path("/v1/hosts/deploy@db/forwards/local/5432"),
+            HostPath::Host("deploy@db".into(), "/v1/forwards/local/5432".into())
+        );
+        assert_eq!(
+            host_path("/v1/hosts/db"),
+            HostPath::Host("db".into(), "/v1/".into())
+        );
+        assert_eq!(host_path("/v1/ports"), HostPath::First);
+        assert_eq!(host_path("/v1/hostsx"), HostPath::First);
+    }
+
+    #[test]
+    fn requests_reach_the_named_host() {
+        let (web_tx, web_rx) = crossbeam_channel::unbounded();
+        let (db_tx, db_rx) = crossbeam_channel::unbounded();
+        let hosts = vec![("web".to_string(), web_tx), ("db".to_string(), db_tx)];
+        let request = |path: &str| HttpRequest {
+            method: "GET".into(),
+            path: path.into(),
+            authorization: Some("Bearer s3cret".into()),
+            body: Vec::new(),
+        };
+        let answer_with = |rx: crossbeam_channel::Receiver<Message>| {
+            std::thread::spawn(move || {
+                if let Ok(Message::Api(call)) = rx.recv() {
+                    assert_eq!(call.request, ApiRequest::Ports);
+                    let _ = call.reply.send(ApiReply::ok(json!([])));
+                }
+            })
+        };
+
+        assert_eq!(
+            answer(request("/v1/hosts"), "s3cret", &hosts).body,
+            json!(["web", "db"])
+        );
+        let db = answer_with(db_rx);
+        assert_eq!(
+            answer(request("/v1/hosts/db/ports"), "s3cret", &hosts).status,
+            200
+        );
+        db.join().unwrap();
+        let web = answer_with(web_rx);
+        assert_eq!(answer(request("/v1/ports"), "s3cret", &hosts).status, 200);
+        web.join().unwrap();
+        assert_eq!(
+            answer(request("/v1/hosts/cache/ports"), "s3cret", &hosts).status,
+            404
+        );
+    }
+
+    #[test]
+    fn slow_requests_time_out_as_a_whole() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        std::thread::spawn(move || {
+            // A byte at a time, each well inside any per-read timeout.
+            for &b in b"GET /v1/ports HTTP/1.1\r\n" {
+                if client.write_all(&[b]).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let started = Instant::now();
+        let result = read_request(Deadline {
+            stream: &server,
+            until: started + Duration::from_millis(100),
+        });
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn connections_beyond_the_cap_are_turned_away() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        let hosts = vec![("web".to_string(), tx)];
+        std::thread::spawn(move || accept(listener, "s3cret".to_string(), hosts));
+
+        // Idle connections hold their slots until the read deadline.
+        let _held: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(addr).unwrap())
+            .collect();
+        std::thread::sleep(Duration::from_millis(100));
+        let mut extra = TcpStream::connect(addr).unwrap();
+        extra
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut reply = String::new();
+        extra.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("HTTP/1.1 503 "), "{reply}");
+        assert!(
+            reply.ends_with(r#"{"error":"too many connections"}"#),
+            "{reply}"
+        );
+    }
+}
@@ -1,23 +1,71 @@
-use std::collections::{HashMap, HashSet};
-use std::time::Instant;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+#[cfg(feature = "tui")]
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use sshfwd_common::types::{Protocol, ScanResult};
+use sshfwd_common::types::{
+    AgentCommand, AgentError, AgentErrorKind, AgentHello, CommandReply, HostInfo, KillSignal,
+    ListeningPort, ProcessInfo, ScanResult, ServiceProbe,
+};
+use sshfwd_core::clock::SleepDetector;
+use sshfwd_core::engine::SessionEvent;
 
-use crate::error::DiscoveryError;
+use crate::api::{ApiReply, ApiRequest, NewForward};
+use crate::conflict::{BindConflict, PortCheck};
+use crate::error::{agent_error_hint, DiscoveryError};
 use crate::forward::{
-    ForwardCommand, ForwardEntry, ForwardEvent, ForwardKey, ForwardKind, ForwardStatus,
+    ForwardCommand, ForwardEntry, ForwardEvent, ForwardKey, ForwardKind, ForwardStatus, Traffic,
 };
-use crate::ui::table::{build_display_rows, DisplayRow};
+use crate::history::TimelineKind;
+use crate::hooks::HookEvent;
+use crate::notify::PortChangeKind;
+use crate::rows::{build_display_rows, find_row, DisplayRow, RowKey};
+
+/// Lines requested from the agent log by `L`.
+const AGENT_LOG_TAIL_LINES: usize = 200;
+/// Entries kept in the event log (`e`); older ones are dropped.
+const EVENT_LOG_CAPACITY: usize = 200;
+/// How long the header keeps showing the warning indicator after the last warning.
+pub const WARNING_INDICATOR: Duration = Duration::from_secs(10);
+/// Agent scans taking this long are flagged; healthy ones take milliseconds.
+pub const SLOW_SCAN: Duration = Duration::from_secs(1);
+/// How long a port that appeared or restarted stays highlighted.
+pub const CHANGE_HIGHLIGHT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventLevel {
+    Info,
+    Warn,
+    Error,
+}
 
-const STALENESS_THRESHOLD_SECS: u64 = 6;
+/// One line of the event log pane.
+#[derive(Debug, Clone)]
+pub struct EventEntry {
+    pub at: Instant,
+    pub level: EventLevel,
+    pub message: String,
+}
+
+/// The header's session summary, as of the last `Tick`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Both directions, over every finished tunnel connection.
+    pub bytes: u64,
+    /// Open tunnel connections across all forwards.
+    pub connections: u32,
+    /// Since this host's session began, to the precision the header shows.
+    pub duration_secs: u64,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConnectionState {
     Connecting,
     Connected,
     Reconnecting,
-    Disconnected,
+    /// SSH session is up but scans stopped; the sidecar is respawning the agent.
+    /// Forwards keep running and the port list shows the last scan.
+    Stale,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -37,56 +85,320 @@ pub enum ModalState {
         buffer: String,
         remote_host: String,
         error: Option<String>,
+        /// Set after a failed bind: who holds the port and free ones to
+        /// pick with the arrow keys.
+        conflict: Option<BindConflict>,
+    },
+    ConfirmKill {
+        remote_port: u16,
+        pid: u32,
+        process_name: String,
+        signal: KillSignal,
+        error: Option<String>,
+    },
+    /// Tail of the remote agent log (`L`). `scroll` counts lines up from the bottom.
+    AgentLog {
+        lines: Vec<String>,
+        error: Option<String>,
+        scroll: usize,
+    },
+    /// Port appear/disappear/restart history (`t`). `port` narrows it to one
+    /// port; `scroll` counts lines up from the bottom.
+    Timeline {
+        port: Option<u16>,
+        scroll: usize,
+    },
+    /// What a forwarded web service answers through the tunnel (`v`);
+    /// `result` is `None` until the request comes back. `scroll` counts
+    /// lines down from the top.
+    Preview {
+        remote_port: u16,
+        url: String,
+        result: Option<Result<crate::preview::Preview, String>>,
+        scroll: usize,
+    },
+    /// Confirm a local forward of a privileged or root-owned port.
+    ConfirmForward {
+        remote_port: u16,
+        local_port: u16,
+        remote_host: String,
+        process: Option<ProcessInfo>,
+        reason: &'static str,
+    },
+    /// A forward was refused by the `[policy]` config section.
+    PolicyBlocked {
+        remote_port: u16,
+        process_name: Option<String>,
+        reason: String,
+    },
+    /// An error with a known remedy (`SshError::hint`).
+    Error {
+        title: &'static str,
+        message: String,
+        hint: String,
+    },
+    /// The host's startup failed: retry, retry with an edited destination
+    /// (`edit` holds the text being typed) or quit.
+    StartupFailed {
+        error: String,
+        hint: Option<String>,
+        edit: Option<String>,
+    },
+    /// A question from the host's startup; the answer goes to `Model::prompt_reply`.
+    Prompt {
+        question: String,
+        kind: PromptKind,
+        buffer: String,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PromptKind {
+    /// Masked text, e.g. the sudo password.
+    Password,
+    /// `y` / `n`; a yes answers `Some("y")`.
+    YesNo,
+}
+
+/// A question the sidecar needs answered before startup can go on (sudo
+/// password, agent download). `None` answers a cancel.
+pub struct Prompt {
+    pub question: String,
+    pub kind: PromptKind,
+    pub reply: tokio::sync::oneshot::Sender<Option<String>>,
+}
+
+/// How far a host's first connection has got, shown on its splash screen and
+/// tab until the first scan arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupStage {
+    Connecting,
+    Authenticating,
+    Deploying,
+    /// Uploading the agent binary, with the percentage sent.
+    Uploading(u8),
+    Scanning,
+}
+
+impl StartupStage {
+    pub fn label(self) -> String {
+        match self {
+            StartupStage::Connecting => "connecting".to_string(),
+            StartupStage::Authenticating => "authenticating".to_string(),
+            StartupStage::Deploying => "deploying agent".to_string(),
+            StartupStage::Uploading(percent) => format!("uploading agent {percent}%"),
+            StartupStage::Scanning => "scanning".to_string(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub enum Message {
     // Discovery events
+    AgentHello(AgentHello),
     ScanReceived(ScanResult),
     DiscoveryWarning(String),
+    /// A failure the agent reported; discovery carries on.
+    AgentError(AgentError),
     DiscoveryError(DiscoveryError),
+    AgentReply(CommandReply),
     StreamEnded,
     // Reconnect lifecycle
     ConnectionLost,
     Reconnecting,
     Reconnected,
+    /// Discovery timed out on a live session; the agent is being respawned.
+    DiscoveryStalled,
+    /// The machine slept this long; the sidecar replaces the session.
+    Resumed(Duration),
+    // Startup (first connection and agent deployment)
+    Startup(StartupStage),
+    /// The first connection or agent deployment failed; the sidecar gave up.
+    StartupFailed {
+        error: String,
+        hint: Option<String>,
+    },
+    Prompt(Prompt),
     // Local port scan
     LocalScanReceived(ScanResult),
     LocalScanError(String),
     // Keyboard
+    #[cfg(feature = "tui")]
     Key(KeyEvent),
     // Mouse
+    #[cfg(feature = "tui")]
     Mouse(crossterm::event::MouseEvent),
     // Forwarding
     ForwardEvent(ForwardEvent),
     // Timer
     Tick,
+    #[cfg(feature = "tui")]
     Resize(u16, u16),
+    /// The terminal gained (`true`) or lost focus; only paces ticks.
+    #[cfg(feature = "tui")]
+    Focus(bool),
+    /// A `[api]` request waiting for its answer.
+    Api(crate::api::ApiCall),
+    /// A preview (`v`) of the forward of `remote_port` came back.
+    Preview {
+        remote_port: u16,
+        result: Result<crate::preview::Preview, String>,
+    },
+}
+
+impl From<ForwardEvent> for Message {
+    fn from(event: ForwardEvent) -> Self {
+        Message::ForwardEvent(event)
+    }
+}
+
+/// Runs on the host's runtime thread, so scans are sorted there.
+impl From<SessionEvent> for Message {
+    fn from(event: SessionEvent) -> Self {
+        match event {
+            SessionEvent::Scan(mut scan) => {
+                crate::discovery::sort_ports(&mut scan.ports);
+                Message::ScanReceived(scan)
+            }
+            SessionEvent::LocalScan(mut scan) => {
+                crate::discovery::sort_ports(&mut scan.ports);
+                Message::LocalScanReceived(scan)
+            }
+            SessionEvent::LocalScanError(e) => Message::LocalScanError(e),
+            SessionEvent::Warning(w) => Message::DiscoveryWarning(w),
+            SessionEvent::AgentError(e) => Message::AgentError(e),
+            SessionEvent::Hello(hello) => Message::AgentHello(hello),
+            SessionEvent::Reply(reply) => Message::AgentReply(reply),
+            SessionEvent::Error(e) => Message::DiscoveryError(e),
+            SessionEvent::Stalled => Message::DiscoveryStalled,
+            SessionEvent::Resumed(slept) => Message::Resumed(slept),
+            SessionEvent::ConnectionLost => Message::ConnectionLost,
+            SessionEvent::Reconnecting => Message::Reconnecting,
+            SessionEvent::Reconnected => Message::Reconnected,
+        }
+    }
 }
 
 pub struct Model {
     pub destination: String,
     pub hostname: Option<String>,
     pub username: Option<String>,
+    /// The SSH user's uid on the remote, from the latest scan.
+    pub remote_uid: Option<u32>,
+    /// OS, kernel, uptime and load from the latest scan.
+    pub host: Option<HostInfo>,
     pub ports: Vec<sshfwd_common::types::ListeningPort>,
     pub local_ports: Vec<sshfwd_common::types::ListeningPort>,
     pub scan_index: u64,
+    /// Where the selected row currently sits in `build_display_rows`.
     pub selected_index: usize,
+    /// The selected row by identity; every `update` re-resolves
+    /// `selected_index` from it, so rows moving around don't move the
+    /// selection to another port.
+    pub selected: Option<RowKey>,
     pub connection_state: ConnectionState,
+    /// Set until the first scan arrives.
+    pub startup: Option<StartupStage>,
+    /// Why the first connection failed; the host stays on its splash screen.
+    pub startup_error: Option<String>,
+    /// What to do about `startup_error`, when known.
+    pub startup_hint: Option<String>,
+    /// Destination to start this host over with, chosen in the
+    /// `ModalState::StartupFailed` modal; taken by `Hosts::take_restart`.
+    pub restart: Option<String>,
+    /// Hint of the last `ModalState::Error`, so an error that recurs on
+    /// every reconnect doesn't reopen it.
+    pub shown_hint: Option<String>,
+    /// Answer channel of the open `ModalState::Prompt`.
+    pub prompt_reply: Option<tokio::sync::oneshot::Sender<Option<String>>>,
     pub last_scan_at: Option<Instant>,
+    /// When the shown ports were scanned, by the agent's timestamp when the
+    /// two clocks roughly agree, else when the scan arrived.
+    pub scanned_at: Option<SystemTime>,
+    /// Duration of the last scan, while scans are over `SLOW_SCAN`.
+    pub slow_scan: Option<Duration>,
+    /// Kind of the agent's last error since the last scan; repeats of it
+    /// aren't logged again.
+    pub agent_error: Option<AgentErrorKind>,
+    /// Ticks compare notes here to catch the machine sleeping.
+    pub sleep: SleepDetector,
     pub running: bool,
     pub needs_render: bool,
     pub forwards: HashMap<ForwardKey, ForwardEntry>,
     pub modal: ModalState,
     pub mode: AppMode,
     pub started_at: Instant,
+    /// Bytes moved by finished tunnel connections this session.
+    pub traffic: Traffic,
+    /// Refreshed on `Tick`; `None` until the first one.
+    pub session_stats: Option<SessionStats>,
     pub show_inactive_forwards: bool,
+    /// Hide unforwarded remote ports owned by other users (`u`).
+    pub only_mine: bool,
+    /// Hide unforwarded remote ports reachable without a tunnel: those bound
+    /// to anything but loopback (`l`).
+    pub only_loopback: bool,
+    pub show_details: bool,
+    /// Event log pane (`e`): warnings, errors and connection changes.
+    pub show_events: bool,
+    pub events: VecDeque<EventEntry>,
+    /// Warnings/errors logged since the event pane was last opened.
+    pub unseen_warnings: usize,
+    /// When the last warning/error arrived; drives the transient header indicator.
+    pub last_warning_at: Option<Instant>,
+    /// No scan for this long marks discovery `Stale` (see `DiscoveryTiming`).
+    pub stale_after: Duration,
+    /// Guardrails checked before any local forward starts.
+    pub policy: crate::policy::Policy,
+    /// Which table columns show and how wide (`[table]`).
+    pub columns: crate::columns::TableConfig,
+    /// `[audit]` log of forwards and connections, when enabled.
+    pub audit: Option<crate::audit::AuditLog>,
+    /// `[hooks]` commands, when any is configured.
+    pub hooks: Option<crate::hooks::Hooks>,
+    /// This instance's summary for `sshfwd status`.
+    pub status_file: Option<crate::status::StatusFile>,
+    /// Announces shared local forwards on the LAN (`[sharing] mdns`).
+    pub mdns: Option<crate::mdns::Advertiser>,
+    /// Per-destination color of the header, table border and selection
+    /// marker, so hosts are hard to mix up.
+    #[cfg(feature = "tui")]
+    pub accent: ratatui::style::Color,
+    /// Destination this host is the ProxyJump hop of, when it was only opened
+    /// for `--jump-hosts`.
+    pub jump_target: Option<String>,
     pub notifications_enabled: bool,
+    /// Save forward changes for the next run; off in the test harness.
+    pub persist_forwards: bool,
     pub prev_scan_ports: Option<HashSet<u16>>,
     pub notify_batch: crate::notify::NotifyBatch,
+    /// Remote port changes across scans, for the timeline (`t`).
+    pub history: crate::history::ScanHistory,
+    /// Remote ports that appeared or changed PID within `CHANGE_HIGHLIGHT`,
+    /// and when; the table highlights them.
+    pub changed_ports: HashMap<u16, (TimelineKind, Instant)>,
+    /// Commands queued for the remote agent; drained by the main loop after each update.
+    pub agent_commands: Vec<AgentCommand>,
+    #[cfg(feature = "tui")]
     pub table_state: ratatui::widgets::TableState,
+    #[cfg(feature = "tui")]
     pub table_content_area: Option<ratatui::layout::Rect>,
+    /// Open forwarded HTTP services in the browser once they start
+    /// (`[browser] open_http`).
+    pub open_http: bool,
+    /// Remote ports whose forward was started here and should open in the
+    /// browser when it's up; reactivated forwards never do.
+    pub browse_on_start: HashSet<u16>,
+    /// URLs to open, drained by the main loop after each update.
+    pub urls_to_open: Vec<String>,
+    /// Previews to fetch, drained by the main loop after each update.
+    pub previews_to_fetch: Vec<crate::preview::Request>,
+    /// Remote ports marked by dragging over rows (Forward mode); Enter/f
+    /// acts on all of them, Esc clears them.
+    pub marked: BTreeSet<u16>,
+    /// Display row where the current left-button drag started.
+    pub drag_anchor: Option<usize>,
 }
 
 impl Model {
@@ -95,28 +407,131 @@ impl Model {
             destination,
             hostname: None,
             username: None,
+            remote_uid: None,
+            host: None,
             ports: Vec::new(),
             local_ports: Vec::new(),
             scan_index: 0,
             selected_index: 0,
+            selected: None,
             connection_state: ConnectionState::Connecting,
+            startup: Some(StartupStage::Connecting),
+            startup_error: None,
+            startup_hint: None,
+            restart: None,
+            shown_hint: None,
+            prompt_reply: None,
             last_scan_at: None,
+            scanned_at: None,
+            slow_scan: None,
+            agent_error: None,
+            sleep: SleepDetector::new(),
             running: true,
             needs_render: true,
             forwards: HashMap::new(),
             modal: ModalState::None,
             mode: AppMode::Forward,
             started_at: Instant::now(),
+            traffic: Traffic::default(),
+            session_stats: None,
             show_inactive_forwards: false,
+            only_mine: false,
+            only_loopback: false,
+            show_details: false,
+            show_events: false,
+            events: VecDeque::new(),
+            unseen_warnings: 0,
+            last_warning_at: None,
+            stale_after: crate::discovery::DiscoveryTiming::default().stale_after,
+            policy: crate::policy::Policy::default(),
+            columns: crate::columns::TableConfig::default(),
+            audit: None,
+            hooks: None,
+            status_file: None,
+            mdns: None,
+            #[cfg(feature = "tui")]
+            accent: ratatui::style::Color::Cyan,
+            jump_target: None,
             notifications_enabled: true,
+            persist_forwards: true,
             prev_scan_ports: None,
             notify_batch: crate::notify::NotifyBatch::default(),
+            history: crate::history::ScanHistory::default(),
+            changed_ports: HashMap::new(),
+            agent_commands: Vec::new(),
+            #[cfg(feature = "tui")]
             table_state: ratatui::widgets::TableState::default(),
+            #[cfg(feature = "tui")]
             table_content_area: None,
+            open_http: false,
+            browse_on_start: HashSet::new(),
+            urls_to_open: Vec::new(),
+            previews_to_fetch: Vec::new(),
+            marked: BTreeSet::new(),
+            drag_anchor: None,
         }
     }
 
-    fn selected_port(&self) -> Option<u16> {
+    /// Append to the event log; warnings and errors also raise the header indicator.
+    pub fn log_event(&mut self, level: EventLevel, message: impl Into<String>) {
+        if self.events.len() == EVENT_LOG_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(EventEntry {
+            at: Instant::now(),
+            level,
+            message: message.into(),
+        });
+        if level != EventLevel::Info {
+            self.last_warning_at = Some(Instant::now());
+            if !self.show_events {
+                self.unseen_warnings += 1;
+            }
+        }
+        self.needs_render = true;
+    }
+
+    /// Whether a remote port belongs to the SSH user. Without a uid from the
+    /// agent, attributed ports not marked elevated count as the user's.
+    pub fn is_mine(&self, port: &sshfwd_common::types::ListeningPort) -> bool {
+        port.process
+            .as_ref()
+            .is_some_and(|p| match self.remote_uid {
+                Some(uid) => p.uid == uid,
+                None => !p.elevated,
+            })
+    }
+
+    /// This moment's `SessionStats`. Past a minute the duration counts whole
+    /// minutes, so a tick only redraws when the header would change.
+    pub fn current_session_stats(&self) -> SessionStats {
+        let secs = self.started_at.elapsed().as_secs();
+        SessionStats {
+            bytes: self.traffic.to_remote + self.traffic.from_remote,
+            connections: self.forwards.values().map(|e| e.active_connections).sum(),
+            duration_secs: if secs < 60 { secs } else { secs - secs % 60 },
+        }
+    }
+
+    /// Whether the header should show the warning indicator right now.
+    pub fn warning_indicator_active(&self) -> bool {
+        self.unseen_warnings > 0
+            && self
+                .last_warning_at
+                .is_some_and(|at| at.elapsed() < WARNING_INDICATOR)
+    }
+
+    /// How old the shown ports are.
+    pub fn data_age(&self) -> Option<Duration> {
+        let scanned_at = self.scanned_at?;
+        Some(
+            SystemTime::now()
+                .duration_since(scanned_at)
+                .unwrap_or_default(),
+        )
+    }
+
+    pub fn selected_port(&self) -> Option<u16> {
         let display_rows = build_display_rows(self);
         match display_rows.get(self.selected_index) {
             Some(DisplayRow::Port(i)) => Some(self.ports[*i].port),
@@ -127,6 +542,16 @@ impl Model {
         }
     }
 
+    /// The scanned port under the cursor (remote in Forward mode, local in Reverse mode).
+    pub fn selected_listening_port(&self) -> Option<&sshfwd_common::types::ListeningPort> {
+        let display_rows = build_display_rows(self);
+        match display_rows.get(self.selected_index) {
+            Some(DisplayRow::Port(i)) => self.ports.get(*i),
+            Some(DisplayRow::LocalPort(i)) => self.local_ports.get(*i),
+            _ => None,
+        }
+    }
+
     fn remote_host(&self) -> String {
         self.hostname
             .clone()
@@ -134,18 +559,25 @@ impl Model {
     }
 }
 
+/// Select the row at `index` of `display_rows`.
+fn select_row(model: &mut Model, display_rows: &[DisplayRow], index: usize) {
+    model.selected_index = index;
+    model.selected = display_rows.get(index).and_then(|row| row.key(model));
+    model.needs_render = true;
+}
+
 /// Move selection down by one, skipping separator rows.
 fn move_selection_down(model: &mut Model) {
     let display_rows = build_display_rows(model);
     let last = display_rows.len().saturating_sub(1);
     if model.selected_index < last {
         let next = model.selected_index + 1;
-        model.selected_index = if matches!(display_rows.get(next), Some(DisplayRow::Separator)) {
+        let next = if matches!(display_rows.get(next), Some(DisplayRow::Separator)) {
             (next + 1).min(last)
         } else {
             next
         };
-        model.needs_render = true;
+        select_row(model, &display_rows, next);
     }
 }
 
@@ -154,49 +586,55 @@ fn move_selection_up(model: &mut Model) {
     let display_rows = build_display_rows(model);
     if model.selected_index > 0 {
         let prev = model.selected_index - 1;
-        model.selected_index = if matches!(display_rows.get(prev), Some(DisplayRow::Separator)) {
+        let prev = if matches!(display_rows.get(prev), Some(DisplayRow::Separator)) {
             prev.saturating_sub(1)
         } else {
             prev
         };
-        model.needs_render = true;
+        select_row(model, &display_rows, prev);
     }
 }
 
-/// Recompute selected_index after display rows change (forward added/removed, scan update).
-/// Tries to keep `target_port` selected; falls back to clamping.
-fn adjust_selection(model: &mut Model, target_port: Option<u16>) {
+/// Find the selected row again after the rows may have changed (forward
+/// added/removed, scan update, filter toggled). Gone, the selection stays at
+/// its index, clamped to the rows and off the separator, on whatever row is
+/// there now.
+fn resolve_selection(model: &mut Model) {
     let display_rows = build_display_rows(model);
-    if display_rows.is_empty() {
-        model.selected_index = 0;
-        return;
-    }
-    if let Some(port) = target_port {
-        if let Some(pos) = display_rows.iter().position(|dr| match dr {
-            DisplayRow::Port(i) => model.ports[*i].port == port,
-            DisplayRow::LocalPort(i) => model.local_ports[*i].port == port,
-            DisplayRow::InactiveForward(rp) => *rp == port,
-            DisplayRow::InactiveReverseForward(rp) => *rp == port,
-            DisplayRow::Separator => false,
-        }) {
-            model.selected_index = pos;
-            return;
+    let found = model
+        .selected
+        .as_ref()
+        .and_then(|key| find_row(model, &display_rows, key));
+    let index = match found {
+        Some(pos) => {
+            // Keep the row at the same height on screen when rows appear or
+            // vanish above it.
+            #[cfg(feature = "tui")]
+            if pos != model.selected_index {
+                let shifted =
+                    (model.table_state.offset() + pos).saturating_sub(model.selected_index);
+                *model.table_state.offset_mut() = shifted;
+            }
+            pos
         }
+        None => {
+            let last_selectable = display_rows
+                .iter()
+                .rposition(|dr| dr.is_selectable())
+                .unwrap_or(0);
+            let index = model.selected_index.min(last_selectable);
+            if matches!(display_rows.get(index), Some(DisplayRow::Separator)) {
+                index.saturating_sub(1)
+            } else {
+                index
+            }
+        }
+    };
+    if index != model.selected_index {
+        model.needs_render = true;
     }
-    // Clamp to last selectable row
-    let last_selectable = display_rows
-        .iter()
-        .rposition(|dr| dr.is_selectable())
-        .unwrap_or(0);
-    if model.selected_index > last_selectable {
-        model.selected_index = last_selectable;
-    }
-    if matches!(
-        display_rows.get(model.selected_index),
-        Some(DisplayRow::Separator)
-    ) {
-        model.selected_index = model.selected_index.saturating_sub(1);
-    }
+    model.selected_index = index;
+    model.selected = display_rows.get(index).and_then(|row| row.key(model));
 }
 
 pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
@@ -204,43 +642,38 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
 
     match msg {
         Message::ScanReceived(scan) => {
-            // Remember selected port before any state changes
-            let prev_selected = model.selected_port();
-
             model.hostname = Some(scan.hostname);
             model.username = Some(scan.username);
+            model.remote_uid = scan.uid;
+            model.host = scan.host;
+            model.agent_error = None;
             model.scan_index = scan.scan_index;
             model.last_scan_at = Some(Instant::now());
+            model.scanned_at = Some(scan_time(scan.scanned_at, model.stale_after));
+            note_scan_duration(model, scan.scan_duration_ms);
+            model.startup = None;
 
             let was_connecting = model.connection_state == ConnectionState::Connecting;
             model.connection_state = ConnectionState::Connected;
 
+            // Normally already sorted by the discovery thread.
             let mut ports = scan.ports;
-            ports.sort_by(|a, b| {
-                a.port
-                    .cmp(&b.port)
-                    .then_with(|| {
-                        let pid_a = a.process.as_ref().map_or(0, |p| p.pid);
-                        let pid_b = b.process.as_ref().map_or(0, |p| p.pid);
-                        pid_a.cmp(&pid_b)
-                    })
-                    .then_with(|| {
-                        let proto_ord = |p: &Protocol| match p {
-                            Protocol::Tcp => 0u8,
-                            Protocol::Tcp6 => 1,
-                        };
-                        proto_ord(&a.protocol).cmp(&proto_ord(&b.protocol))
-                    })
-            });
+            crate::discovery::sort_ports(&mut ports);
 
             // Reconcile forwards with current scan
-            let current_remote_ports: HashSet<u16> = ports.iter().map(|p| p.port).collect();
+            // UDP ports share numbers with TCP ones but never carry a forward.
+            let current_remote_ports: HashSet<u16> = ports
+                .iter()
+                .filter(|p| p.protocol.is_tcp())
+                .map(|p| p.port)
+                .collect();
             let remote_host = model.remote_host();
             commands = crate::forward::reconcile_forwards(
                 &mut model.forwards,
                 &current_remote_ports,
                 &remote_host,
             );
+            drop_blocked_reactivations(model, &ports, &mut commands);
 
             // Detect port changes for notifications
             let port_changes = crate::notify::detect_port_changes(
@@ -251,10 +684,41 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                 &model.ports,
             );
             model.prev_scan_ports = Some(current_remote_ports);
+            for change in &port_changes {
+                let (event, scan) = match change.kind {
+                    PortChangeKind::Appeared | PortChangeKind::Reactivated => {
+                        (HookEvent::PortAppeared, &ports)
+                    }
+                    PortChangeKind::Disappeared => (HookEvent::PortDisappeared, &model.ports),
+                    PortChangeKind::Restarted { old_pid, new_pid } => {
+                        let process = change.process_name.as_deref().unwrap_or("?");
+                        model.log_event(
+                            EventLevel::Info,
+                            format!(
+                                "port {} restarted: pid {old_pid} → {new_pid} ({process})",
+                                change.port
+                            ),
+                        );
+                        continue;
+                    }
+                };
+                let process = remote_process_name(scan, change.port);
+                run_hook(model, event, change.port, None, process);
+            }
+            let changes = model.history.record(&ports);
+            let now = Instant::now();
+            for entry in model.history.timeline().iter().rev().take(changes) {
+                match entry.kind {
+                    TimelineKind::Disappeared => model.changed_ports.remove(&entry.port),
+                    kind => model.changed_ports.insert(entry.port, (kind, now)),
+                };
+            }
+            if matches!(model.modal, ModalState::Timeline { .. }) {
+                model.needs_render = true;
+            }
 
             if ports != model.ports {
                 model.ports = ports;
-                adjust_selection(model, prev_selected);
                 model.needs_render = true;
             } else if was_connecting || !commands.is_empty() {
                 model.needs_render = true;
@@ -264,11 +728,29 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                 model.notify_batch.extend(port_changes);
             }
         }
-        Message::DiscoveryWarning(_) => {}
-        Message::DiscoveryError(_) | Message::StreamEnded => {
-            // Reconnect loop handles recovery — do not exit.
+        Message::DiscoveryWarning(warning) => {
+            model.log_event(EventLevel::Warn, warning);
+        }
+        Message::AgentError(e) => agent_failed(model, e),
+        Message::AgentHello(hello) => {
+            model.log_event(
+                EventLevel::Info,
+                format!(
+                    "agent {} started ({})",
+                    hello.agent_version,
+                    hello.capabilities.join(", ")
+                ),
+            );
+        }
+        Message::AgentReply(reply) => {
+            handle_agent_reply(model, reply);
         }
+        Message::DiscoveryError(e) => discovery_failed(model, e.to_string(), e.hint()),
+        Message::StreamEnded => {}
         Message::ConnectionLost => {
+            if model.connection_state != ConnectionState::Reconnecting {
+                model.log_event(EventLevel::Warn, "connection lost, reconnecting");
+            }
             model.connection_state = ConnectionState::Reconnecting;
             for entry in model.forwards.values_mut() {
                 entry.status = ForwardStatus::Paused;
@@ -278,7 +760,47 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
         Message::Reconnecting => {
             model.needs_render = true;
         }
+        Message::DiscoveryStalled => {
+            model.log_event(
+                EventLevel::Warn,
+                "no scans from agent, respawning it (forwards stay up)",
+            );
+            model.connection_state = ConnectionState::Stale;
+            model.needs_render = true;
+        }
+        Message::Resumed(slept) => {
+            model.log_event(
+                EventLevel::Warn,
+                format!("resumed after {}s asleep, reconnecting", slept.as_secs()),
+            );
+            if model.connection_state == ConnectionState::Connected {
+                model.connection_state = ConnectionState::Stale;
+            }
+            model.needs_render = true;
+        }
+        Message::Startup(stage) => {
+            model.startup = Some(stage);
+            model.needs_render = true;
+        }
+        Message::StartupFailed { error, hint } => {
+            model.log_event(EventLevel::Error, error.clone());
+            model.startup_error = Some(error);
+            model.startup_hint = hint;
+            open_startup_failed(model);
+        }
+        Message::Prompt(prompt) => {
+            model.modal = ModalState::Prompt {
+                question: prompt.question,
+                kind: prompt.kind,
+                buffer: String::new(),
+            };
+            model.prompt_reply = Some(prompt.reply);
+            model.needs_render = true;
+        }
         Message::Reconnected => {
+            if model.scan_index > 0 {
+                model.log_event(EventLevel::Info, "reconnected");
+            }
             model.connection_state = ConnectionState::Connecting;
             model.needs_render = true;
             // Reactivate reverse forwards immediately — local forwards reactivate via
@@ -296,14 +818,18 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                 .collect();
         }
         Message::LocalScanReceived(scan) => {
-            let prev_selected = model.selected_port();
-            model.local_ports = scan.ports;
-            adjust_selection(model, prev_selected);
-            model.needs_render = true;
+            // Local ports are only on screen in reverse mode.
+            let mut ports = scan.ports;
+            crate::discovery::sort_ports(&mut ports);
+            if ports != model.local_ports {
+                model.local_ports = ports;
+                model.needs_render |= model.mode == AppMode::Reverse;
+            }
         }
-        Message::LocalScanError(_) => {
-            // Silently ignore local scan errors for now
+        Message::LocalScanError(e) => {
+            model.log_event(EventLevel::Warn, format!("local scan failed: {e}"));
         }
+        #[cfg(feature = "tui")]
         Message::Key(key) => match &model.modal {
             ModalState::None => {
                 commands = handle_normal_key(model, key);
@@ -311,8 +837,41 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
             ModalState::PortInput { .. } => {
                 commands = handle_port_input_key(model, key);
             }
+            ModalState::ConfirmKill { .. } => {
+                handle_confirm_kill_key(model, key);
+            }
+            ModalState::AgentLog { .. } => {
+                handle_agent_log_key(model, key);
+            }
+            ModalState::Timeline { .. } => {
+                handle_timeline_key(model, key);
+            }
+            ModalState::Preview { .. } => {
+                handle_preview_key(model, key);
+            }
+            ModalState::ConfirmForward { .. } => {
+                commands = handle_confirm_forward_key(model, key);
+            }
+            ModalState::PolicyBlocked { .. } | ModalState::Error { .. } => {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                    model.modal = ModalState::None;
+                    model.needs_render = true;
+                }
+            }
+            ModalState::Prompt { .. } => {
+                handle_prompt_key(model, key);
+            }
+            ModalState::StartupFailed { .. } => {
+                handle_startup_failed_key(model, key);
+            }
         },
         Message::ForwardEvent(evt) => {
+            if let Some(Err(e)) = model.audit.as_mut().map(|a| a.record(&evt)) {
+                model.log_event(EventLevel::Error, format!("audit log write failed: {e}"));
+            }
+            update_mdns(model, &evt);
+            // A closed connection changes nothing on screen.
+            let visible = !matches!(evt, ForwardEvent::ConnectionClosed { .. });
             match evt {
                 ForwardEvent::Started {
                     kind,
@@ -324,6 +883,19 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                         entry.status = ForwardStatus::Active;
                     }
                     save_forwards(model);
+                    if kind == ForwardKind::Local && model.browse_on_start.remove(&remote_port) {
+                        browse_if_http(model, remote_port, local_port);
+                    }
+                    let process = (kind == ForwardKind::Local)
+                        .then(|| remote_process_name(&model.ports, remote_port))
+                        .flatten();
+                    run_hook(
+                        model,
+                        HookEvent::ForwardStarted,
+                        remote_port,
+                        Some(local_port),
+                        process,
+                    );
                 }
                 ForwardEvent::Stopped { kind, remote_port } => {
                     let local_port = model
@@ -332,12 +904,16 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                         .map(|e| e.local_port);
                     model.forwards.remove(&ForwardKey { kind, remote_port });
                     save_forwards(model);
-                    let hint = if kind == ForwardKind::Reverse {
-                        local_port.unwrap_or(remote_port)
-                    } else {
-                        remote_port
-                    };
-                    adjust_selection(model, Some(hint));
+                    let process = (kind == ForwardKind::Local)
+                        .then(|| remote_process_name(&model.ports, remote_port))
+                        .flatten();
+                    run_hook(
+                        model,
+                        HookEvent::ForwardStopped,
+                        remote_port,
+                        local_port,
+                        process,
+                    );
                 }
                 ForwardEvent::Paused { kind, remote_port } => {
                     if let Some(entry) = model.forwards.get_mut(&ForwardKey { kind, remote_port }) {
@@ -355,6 +931,20 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                         .map(|e| e.local_port)
                         .unwrap_or(remote_port);
                     model.forwards.remove(&ForwardKey { kind, remote_port });
+                    // The port that failed is on the listening side: local
+                    // for a local forward, remote for a reverse one.
+                    let conflict = match kind {
+                        ForwardKind::Local => BindConflict::local(
+                            failed_local_port,
+                            &model.local_ports,
+                            taken_ports(model, kind),
+                        ),
+                        ForwardKind::Reverse => BindConflict::remote(
+                            remote_port,
+                            &model.ports,
+                            taken_ports(model, kind),
+                        ),
+                    };
                     model.modal = ModalState::PortInput {
                         kind,
                         remote_port,
@@ -366,13 +956,8 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                             model.remote_host()
                         },
                         error: Some(message),
+                        conflict: Some(conflict),
                     };
-                    let hint = if kind == ForwardKind::Reverse {
-                        failed_local_port
-                    } else {
-                        remote_port
-                    };
-                    adjust_selection(model, Some(hint));
                 }
                 ForwardEvent::ConnectionCountChanged {
                     kind,
@@ -383,68 +968,219 @@ pub fn update(model: &mut Model, msg: Message) -> Vec<ForwardCommand> {
                         entry.active_connections = count;
                     }
                 }
+                ForwardEvent::ConnectionDenied {
+                    remote_port,
+                    peer,
+                    reason,
+                    ..
+                } => {
+                    model.log_event(
+                        EventLevel::Warn,
+                        format!("refused {peer} on forward of :{remote_port}: {reason}"),
+                    );
+                }
+                ForwardEvent::ConnectionClosed {
+                    kind,
+                    remote_port,
+                    traffic,
+                    open_wait,
+                    ..
+                } => {
+                    model.traffic.to_remote += traffic.to_remote;
+                    model.traffic.from_remote += traffic.from_remote;
+                    if let Some(entry) = model.forwards.get_mut(&ForwardKey { kind, remote_port }) {
+                        if kind == ForwardKind::Local {
+                            entry.open_wait.record(open_wait);
+                        }
+                    }
+                }
+            }
+            model.needs_render |= visible;
+        }
+        Message::Api(call) => {
+            let (reply, api_commands) = handle_api(model, call.request);
+            let _ = call.reply.send(reply);
+            commands = api_commands;
+        }
+        Message::Preview {
+            remote_port,
+            result,
+        } => {
+            // Closed or moved on to another port meanwhile: nobody's looking.
+            if let ModalState::Preview {
+                remote_port: open,
+                result: pending @ None,
+                ..
+            } = &mut model.modal
+            {
+                if *open == remote_port {
+                    *pending = Some(result);
+                    model.needs_render = true;
+                }
             }
-            model.needs_render = true;
         }
         Message::Tick => {
             // Re-render during splash so the transition to table happens on time
             if model.started_at.elapsed().as_secs() < 2 {
                 model.needs_render = true;
             }
+            // After a sleep the session is as good as gone: show it stale now,
+            // the sidecar notices too and reconnects (`Message::Resumed`).
+            if model.sleep.check().is_some() && model.connection_state == ConnectionState::Connected
+            {
+                model.connection_state = ConnectionState::Stale;
+                model.needs_render = true;
+            }
+            let stats = model.current_session_stats();
+            if model.session_stats != Some(stats) {
+                model.session_stats = Some(stats);
+                model.needs_render = true;
+            }
+            // The header counts the data's age up once it shows it.
+            if model.data_age().is_some_and(|age| age >= model.stale_after) {
+                model.needs_render = true;
+            }
             if let Some(last) = model.last_scan_at {
-                if last.elapsed().as_secs() >= STALENESS_THRESHOLD_SECS
+                if last.elapsed() >= model.stale_after
                     && model.connection_state == ConnectionState::Connected
                 {
-                    // Mark stale-connected as disconnected; reconnect loop will
-                    // send ConnectionLost / Reconnecting separately.
-                    model.connection_state = ConnectionState::Disconnected;
+                    // Scans are late; the sidecar decides between respawning the
+                    // agent (DiscoveryStalled) and reconnecting (ConnectionLost).
+                    model.connection_state = ConnectionState::Stale;
                     model.needs_render = true;
                 }
             }
+            // Highlights of new and restarted ports wear off.
+            let highlighted = model.changed_ports.len();
+            model
+                .changed_ports
+                .retain(|_, (_, at)| at.elapsed() < CHANGE_HIGHLIGHT);
+            if model.changed_ports.len() != highlighted {
+                model.needs_render = true;
+            }
+            // Let the warning indicator disappear once it has expired.
+            if model.unseen_warnings > 0
+                && model
+                    .last_warning_at
+                    .is_some_and(|at| at.elapsed() >= WARNING_INDICATOR)
+            {
+                model.last_warning_at = None;
+                model.needs_render = true;
+            }
             // Flush batched notifications after debounce window
             model.notify_batch.flush_if_ready(&model.destination);
+            if let Some(mut status_file) = model.status_file.take() {
+                match status_file.update(model) {
+                    Ok(()) => model.status_file = Some(status_file),
+                    Err(e) => {
+                        model.log_event(EventLevel::Warn, format!("status file write failed: {e}"))
+                    }
+                }
+            }
         }
+        #[cfg(feature = "tui")]
         Message::Resize(_, _) => {
             model.needs_render = true;
         }
-        Message::Mouse(mouse) => {
-            if model.modal == ModalState::None {
-                use crossterm::event::{MouseButton, MouseEventKind};
-                match mouse.kind {
-                    MouseEventKind::ScrollUp => {
-                        move_selection_up(model);
-                    }
-                    MouseEventKind::ScrollDown => {
-                        move_selection_down(model);
-                    }
-                    MouseEventKind::Down(MouseButton::Left) => {
-                        if let Some(content) = model.table_content_area {
-                            if mouse.row >= content.y && mouse.row < content.y + content.height {
-                                let visual_row = (mouse.row - content.y) as usize;
-                                let display_idx = model.table_state.offset() + visual_row;
-                                let display_rows = build_display_rows(model);
-                                if display_idx < display_rows.len()
-                                    && !matches!(display_rows[display_idx], DisplayRow::Separator)
-                                {
-                                    model.selected_index = display_idx;
-                                    model.needs_render = true;
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+        #[cfg(feature = "tui")]
+        Message::Focus(_) => {}
+        #[cfg(feature = "tui")]
+        Message::Mouse(mouse) => handle_mouse(model, mouse),
+    }
+
+    resolve_selection(model);
+    commands
+}
+
+#[cfg(feature = "tui")]
+fn handle_mouse(model: &mut Model, mouse: crossterm::event::MouseEvent) {
+    use crossterm::event::{MouseButton, MouseEventKind};
+    // The wheel adjusts the port modal's value by one.
+    if let ModalState::PortInput { buffer, .. } = &mut model.modal {
+        let step: i32 = match mouse.kind {
+            MouseEventKind::ScrollUp => 1,
+            MouseEventKind::ScrollDown => -1,
+            _ => return,
+        };
+        let port = buffer.parse::<i32>().unwrap_or(0) + step;
+        *buffer = port.clamp(1, i32::from(u16::MAX)).to_string();
+        check_port_input(model);
+        model.needs_render = true;
+        return;
+    }
+    if model.modal != ModalState::None {
+        return;
+    }
+    match mouse.kind {
+        MouseEventKind::ScrollUp => {
+            move_selection_up(model);
+        }
+        MouseEventKind::ScrollDown => {
+            move_selection_down(model);
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            let display_rows = build_display_rows(model);
+            model.drag_anchor = None;
+            if !model.marked.is_empty() {
+                model.marked.clear();
+                model.needs_render = true;
             }
+            if let Some(display_idx) = row_at(model, &display_rows, mouse.row) {
+                select_row(model, &display_rows, display_idx);
+                model.drag_anchor = Some(display_idx);
+            }
+        }
+        // Dragging marks the ports between where it started and here.
+        MouseEventKind::Drag(MouseButton::Left) if model.mode == AppMode::Forward => {
+            let display_rows = build_display_rows(model);
+            let (Some(anchor), Some(display_idx)) =
+                (model.drag_anchor, row_at(model, &display_rows, mouse.row))
+            else {
+                return;
+            };
+            let range = anchor.min(display_idx)..=anchor.max(display_idx);
+            model.marked = display_rows[range]
+                .iter()
+                .filter_map(|row| match row {
+                    DisplayRow::Port(i) if model.ports[*i].protocol.is_tcp() => {
+                        Some(model.ports[*i].port)
+                    }
+                    _ => None,
+                })
+                .collect();
+            select_row(model, &display_rows, display_idx);
+            model.needs_render = true;
+        }
+        MouseEventKind::Up(MouseButton::Left) => {
+            model.drag_anchor = None;
         }
+        _ => {}
     }
+}
 
-    commands
+/// The selectable display row at terminal row `y`, if the table shows one there.
+#[cfg(feature = "tui")]
+fn row_at(model: &Model, display_rows: &[DisplayRow], y: u16) -> Option<usize> {
+    let content = model.table_content_area?;
+    if y < content.y || y >= content.y + content.height {
+        return None;
+    }
+    let display_idx = model.table_state.offset() + usize::from(y - content.y);
+    display_rows
+        .get(display_idx)
+        .filter(|row| !matches!(row, DisplayRow::Separator))
+        .map(|_| display_idx)
 }
 
+#[cfg(feature = "tui")]
 fn handle_normal_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand> {
     let mut commands = Vec::new();
 
     match key.code {
+        KeyCode::Esc if !model.marked.is_empty() => {
+            model.marked.clear();
+            model.needs_render = true;
+        }
         KeyCode::Char('q') | KeyCode::Esc => {
             model.running = false;
         }
@@ -458,30 +1194,47 @@ fn handle_normal_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand> {
             move_selection_up(model);
         }
         KeyCode::Char('g') if model.selected_index != 0 => {
-            model.selected_index = 0;
-            model.needs_render = true;
+            let display_rows = build_display_rows(model);
+            select_row(model, &display_rows, 0);
         }
         KeyCode::Char('G') => {
             let display_rows = build_display_rows(model);
             if let Some(last) = display_rows.iter().rposition(|dr| dr.is_selectable()) {
                 if model.selected_index != last {
-                    model.selected_index = last;
-                    model.needs_render = true;
+                    select_row(model, &display_rows, last);
                 }
             }
         }
         KeyCode::Char('m') => {
+            model.marked.clear();
             model.mode = match model.mode {
                 AppMode::Forward => AppMode::Reverse,
                 AppMode::Reverse => AppMode::Forward,
             };
-            model.selected_index = 0;
-            model.needs_render = true;
+            let display_rows = build_display_rows(model);
+            select_row(model, &display_rows, 0);
         }
         KeyCode::Char('p') => {
-            let selected_port = model.selected_port();
             model.show_inactive_forwards = !model.show_inactive_forwards;
-            adjust_selection(model, selected_port);
+            model.needs_render = true;
+        }
+        KeyCode::Char('u') if model.mode == AppMode::Forward => {
+            model.only_mine = !model.only_mine;
+            model.needs_render = true;
+        }
+        KeyCode::Char('l') if model.mode == AppMode::Forward => {
+            model.only_loopback = !model.only_loopback;
+            model.needs_render = true;
+        }
+        KeyCode::Char('d') => {
+            model.show_details = !model.show_details;
+            model.needs_render = true;
+        }
+        KeyCode::Char('e') => {
+            model.show_events = !model.show_events;
+            if model.show_events {
+                model.unseen_warnings = 0;
+            }
             model.needs_render = true;
         }
         KeyCode::Enter
@@ -490,6 +1243,9 @@ fn handle_normal_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand> {
             open_local_forward_modal(model);
         }
         KeyCode::Enter | KeyCode::Char('f') => match model.mode {
+            AppMode::Forward if !model.marked.is_empty() => {
+                commands = handle_marked_action(model);
+            }
             AppMode::Forward => {
                 commands = handle_forward_action(model);
             }
@@ -500,30 +1256,100 @@ fn handle_normal_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand> {
         KeyCode::Char('F') if model.mode == AppMode::Forward => {
             open_local_forward_modal(model);
         }
+        KeyCode::Char('x') if model.mode == AppMode::Forward => {
+            open_kill_modal(model);
+        }
+        KeyCode::Char('s') => save_snapshot(model),
+        KeyCode::Char('y') => copy_address(model),
+        KeyCode::Char('v') if model.mode == AppMode::Forward => open_preview(model),
+        KeyCode::Char('t') => {
+            model.modal = ModalState::Timeline {
+                port: None,
+                scroll: 0,
+            };
+            model.needs_render = true;
+        }
+        KeyCode::Char('r') if model.startup_error.is_some() => open_startup_failed(model),
+        KeyCode::Char('r') => {
+            model.agent_commands.push(AgentCommand::Rescan);
+        }
+        KeyCode::Char('L') => {
+            model.agent_commands.push(AgentCommand::LogTail {
+                lines: AGENT_LOG_TAIL_LINES,
+            });
+        }
         _ => {}
     }
 
     commands
 }
 
+/// The agent's `scanned_at`, unless it is further from now than a scan can
+/// take to arrive: then the clocks disagree and the arrival time is closer.
+fn scan_time(scanned_at: Option<u64>, stale_after: Duration) -> SystemTime {
+    let now = SystemTime::now();
+    let Some(at) = scanned_at.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)) else {
+        return now;
+    };
+    match now.duration_since(at) {
+        Ok(lag) if lag < stale_after => at,
+        _ => now,
+    }
+}
+
+/// Track slow scans, warning once when they start.
+fn note_scan_duration(model: &mut Model, scan_duration_ms: Option<u64>) {
+    let took = scan_duration_ms
+        .map(Duration::from_millis)
+        .filter(|&took| took >= SLOW_SCAN);
+    if let (Some(took), None) = (took, model.slow_scan) {
+        model.log_event(
+            EventLevel::Warn,
+            format!(
+                "remote scans are slow ({:.1}s); the host may be loaded",
+                took.as_secs_f64()
+            ),
+        );
+    }
+    model.slow_scan = took;
+}
+
+/// SSH only forwards TCP; say so instead of forwarding a UDP port's number.
+/// Unix sockets aren't forwarded yet either.
+fn refuse_non_tcp(model: &mut Model) -> bool {
+    let Some(port) = model.selected_listening_port() else {
+        return false;
+    };
+    if port.protocol.is_tcp() {
+        return false;
+    }
+    let message = if port.protocol.is_unix() {
+        format!(
+            "{} is a Unix socket, which sshfwd can't forward yet",
+            port.bind()
+        )
+    } else {
+        format!(
+            ":{} is {}, SSH can only forward TCP",
+            port.port, port.protocol
+        )
+    };
+    model.log_event(EventLevel::Warn, message);
+    model.show_events = true;
+    true
+}
+
 fn handle_forward_action(model: &mut Model) -> Vec<ForwardCommand> {
     let mut commands = Vec::new();
+    if refuse_non_tcp(model) {
+        return commands;
+    }
     if let Some(remote_port) = model.selected_port() {
-        let key = ForwardKey::local(remote_port);
-        if let std::collections::hash_map::Entry::Vacant(e) = model.forwards.entry(key) {
-            e.insert(ForwardEntry {
-                local_port: remote_port,
-                status: ForwardStatus::Starting,
-                active_connections: 0,
-            });
-            commands.push(ForwardCommand::Start {
-                kind: ForwardKind::Local,
-                remote_port,
-                local_port: remote_port,
-                remote_host: model.remote_host(),
-            });
-            adjust_selection(model, Some(remote_port));
-            model.needs_render = true;
+        if !model.forwards.contains_key(&ForwardKey::local(remote_port)) {
+            if check_policy(model, remote_port) {
+                let remote_host = model.remote_host();
+                commands = request_local_forward(model, remote_port, remote_port, remote_host);
+            }
         } else {
             commands.push(ForwardCommand::Stop {
                 kind: ForwardKind::Local,
@@ -534,22 +1360,722 @@ fn handle_forward_action(model: &mut Model) -> Vec<ForwardCommand> {
     commands
 }
 
-fn open_local_forward_modal(model: &mut Model) {
-    if let Some(remote_port) = model.selected_port() {
-        if !model.forwards.contains_key(&ForwardKey::local(remote_port)) {
-            model.modal = ModalState::PortInput {
-                kind: ForwardKind::Local,
-                remote_port,
+/// Enter/f with ports marked: forward each marked port not forwarded yet
+/// on the same local port, or stop them all when every one is. Ports
+/// `[policy]` refuses or wants confirmed one by one are skipped and logged.
+fn handle_marked_action(model: &mut Model) -> Vec<ForwardCommand> {
+    let mut commands = Vec::new();
+    let marked: Vec<u16> = std::mem::take(&mut model.marked)
+        .into_iter()
+        .filter(|&port| model.ports.iter().any(|p| p.port == port))
+        .collect();
+    let unforwarded: Vec<u16> = marked
+        .iter()
+        .copied()
+        .filter(|&port| !model.forwards.contains_key(&ForwardKey::local(port)))
+        .collect();
+    if unforwarded.is_empty() {
+        for remote_port in marked {
+            commands.push(ForwardCommand::Stop {
+                kind: ForwardKind::Local,
+                remote_port,
+            });
+        }
+    } else {
+        let remote_host = model.remote_host();
+        for remote_port in unforwarded {
+            let process = model
+                .ports
+                .iter()
+                .find(|p| p.port == remote_port)
+                .and_then(|p| p.process.as_ref());
+            let refusal = model
+                .policy
+                .check(
+                    remote_port,
+                    process.map(|p| p.name.as_str()),
+                    is_loopback_only(&model.ports, remote_port),
+                )
+                .err()
+                .or_else(|| {
+                    model
+                        .policy
+                        .confirmation_reason(remote_port, process)
+                        .map(|reason| format!("{reason}, forward it on its own"))
+                });
+            match refusal {
+                Some(reason) => model.log_event(
+                    EventLevel::Warn,
+                    format!("marked :{remote_port} not forwarded: {reason}"),
+                ),
+                None => commands.extend(start_local_forward(
+                    model,
+                    remote_port,
+                    remote_port,
+                    remote_host.clone(),
+                )),
+            }
+        }
+    }
+    model.needs_render = true;
+    commands
+}
+
+fn open_local_forward_modal(model: &mut Model) {
+    if refuse_non_tcp(model) {
+        return;
+    }
+    if let Some(remote_port) = model.selected_port() {
+        if !model.forwards.contains_key(&ForwardKey::local(remote_port))
+            && check_policy(model, remote_port)
+        {
+            model.modal = ModalState::PortInput {
+                kind: ForwardKind::Local,
+                remote_port,
                 local_port: remote_port,
                 buffer: remote_port.to_string(),
                 remote_host: model.remote_host(),
                 error: None,
+                conflict: None,
             };
+            check_port_input(model);
             model.needs_render = true;
         }
     }
 }
 
+/// The ports our own `kind` forwards listen on: local ports for local
+/// forwards, remote bind ports for reverse ones.
+fn taken_ports(model: &Model, kind: ForwardKind) -> Vec<u16> {
+    model
+        .forwards
+        .iter()
+        .filter(|(k, _)| k.kind == kind)
+        .map(|(k, e)| match kind {
+            ForwardKind::Local => e.local_port,
+            ForwardKind::Reverse => k.remote_port,
+        })
+        .collect()
+}
+
+/// Re-check the port modal's value as typed; why it won't do replaces any
+/// earlier error.
+fn check_port_input(model: &mut Model) {
+    let ModalState::PortInput { kind, buffer, .. } = &model.modal else {
+        return;
+    };
+    let problem = port_check(model, *kind).check(buffer).err();
+    if let ModalState::PortInput { error, .. } = &mut model.modal {
+        *error = problem;
+    }
+}
+
+/// Checks a port for a new `kind` forward against the side it listens on.
+fn port_check(model: &Model, kind: ForwardKind) -> PortCheck<'_> {
+    let taken = taken_ports(model, kind);
+    match kind {
+        ForwardKind::Local => PortCheck::local(&model.local_ports, taken),
+        ForwardKind::Reverse => PortCheck::remote(&model.ports, taken),
+    }
+}
+
+/// Start a local forward, or ask first when `[policy]` wants privileged or
+/// root-owned ports confirmed.
+fn request_local_forward(
+    model: &mut Model,
+    remote_port: u16,
+    local_port: u16,
+    remote_host: String,
+) -> Vec<ForwardCommand> {
+    let process = model
+        .ports
+        .iter()
+        .find(|p| p.port == remote_port)
+        .and_then(|p| p.process.clone());
+    match model
+        .policy
+        .confirmation_reason(remote_port, process.as_ref())
+    {
+        Some(reason) => {
+            model.modal = ModalState::ConfirmForward {
+                remote_port,
+                local_port,
+                remote_host,
+                process,
+                reason,
+            };
+            model.needs_render = true;
+            Vec::new()
+        }
+        None => start_local_forward(model, remote_port, local_port, remote_host),
+    }
+}
+
+/// Queue `http://<local address>:<local_port>/` for the browser when the
+/// probe saw `remote_port` speak HTTP.
+fn browse_if_http(model: &mut Model, remote_port: u16, local_port: u16) {
+    let http = model
+        .ports
+        .iter()
+        .find(|p| p.port == remote_port)
+        .is_some_and(|p| matches!(p.probe, Some(ServiceProbe::Http { .. })));
+    if http {
+        let address = model
+            .status_file
+            .as_ref()
+            .map_or("127.0.0.1", |s| s.local_address());
+        let url = format!("http://{address}:{local_port}/");
+        model.urls_to_open.push(url);
+    }
+}
+
+/// Track a new local forward as `Starting`, replacing any existing one.
+fn start_local_forward(
+    model: &mut Model,
+    remote_port: u16,
+    local_port: u16,
+    remote_host: String,
+) -> Vec<ForwardCommand> {
+    let mut commands = Vec::new();
+    if model.open_http {
+        model.browse_on_start.insert(remote_port);
+    }
+    let key = ForwardKey::local(remote_port);
+    if model.forwards.contains_key(&key) {
+        commands.push(ForwardCommand::Stop {
+            kind: ForwardKind::Local,
+            remote_port,
+        });
+    }
+    model.forwards.insert(
+        key,
+        ForwardEntry {
+            local_port,
+            status: ForwardStatus::Starting,
+            active_connections: 0,
+            open_wait: Default::default(),
+        },
+    );
+    commands.push(ForwardCommand::Start {
+        kind: ForwardKind::Local,
+        remote_port,
+        local_port,
+        remote_host,
+    });
+    model.needs_render = true;
+    commands
+}
+
+#[cfg(feature = "tui")]
+fn handle_confirm_forward_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand> {
+    let ModalState::ConfirmForward {
+        remote_port,
+        local_port,
+        remote_host,
+        ..
+    } = &model.modal
+    else {
+        return Vec::new();
+    };
+    let (remote_port, local_port, remote_host) = (*remote_port, *local_port, remote_host.clone());
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('n') => {
+            model.modal = ModalState::None;
+            model.needs_render = true;
+            Vec::new()
+        }
+        KeyCode::Enter | KeyCode::Char('y') => {
+            model.modal = ModalState::None;
+            start_local_forward(model, remote_port, local_port, remote_host)
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Offer retrying the failed startup, unless another modal is open.
+fn open_startup_failed(model: &mut Model) {
+    let Some(error) = model.startup_error.clone() else {
+        return;
+    };
+    if model.modal != ModalState::None {
+        return;
+    }
+    model.modal = ModalState::StartupFailed {
+        error,
+        hint: model.startup_hint.clone(),
+        edit: None,
+    };
+    model.needs_render = true;
+}
+
+#[cfg(feature = "tui")]
+fn handle_startup_failed_key(model: &mut Model, key: KeyEvent) {
+    let ModalState::StartupFailed { edit, .. } = &mut model.modal else {
+        return;
+    };
+    model.needs_render = true;
+    let destination = match (edit.as_mut(), key.code) {
+        (Some(_), KeyCode::Esc) => {
+            *edit = None;
+            return;
+        }
+        (Some(buffer), KeyCode::Enter) if !buffer.trim().is_empty() => buffer.trim().to_string(),
+        (Some(buffer), KeyCode::Backspace) => {
+            buffer.pop();
+            return;
+        }
+        (Some(buffer), KeyCode::Char(c)) if !c.is_whitespace() => {
+            buffer.push(c);
+            return;
+        }
+        (Some(_), _) => return,
+        (None, KeyCode::Char('e')) => {
+            *edit = Some(model.destination.clone());
+            return;
+        }
+        (None, KeyCode::Char('q')) => {
+            model.running = false;
+            return;
+        }
+        (None, KeyCode::Char('c')) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            model.running = false;
+            return;
+        }
+        (None, KeyCode::Esc) => {
+            model.modal = ModalState::None;
+            return;
+        }
+        (None, KeyCode::Enter | KeyCode::Char('r')) => model.destination.clone(),
+        (None, _) => return,
+    };
+    model.modal = ModalState::None;
+    model.restart = Some(destination);
+}
+
+#[cfg(feature = "tui")]
+fn handle_prompt_key(model: &mut Model, key: KeyEvent) {
+    let ModalState::Prompt { kind, buffer, .. } = &mut model.modal else {
+        return;
+    };
+    let answer = match (*kind, key.code) {
+        (_, KeyCode::Esc) | (PromptKind::YesNo, KeyCode::Char('n')) => None,
+        (PromptKind::YesNo, KeyCode::Enter | KeyCode::Char('y')) => Some("y".to_string()),
+        (PromptKind::Password, KeyCode::Enter) => Some(std::mem::take(buffer)),
+        (PromptKind::Password, KeyCode::Backspace) => {
+            buffer.pop();
+            model.needs_render = true;
+            return;
+        }
+        (PromptKind::Password, KeyCode::Char(c)) => {
+            buffer.push(c);
+            model.needs_render = true;
+            return;
+        }
+        _ => return,
+    };
+    model.modal = ModalState::None;
+    model.needs_render = true;
+    if let Some(reply) = model.prompt_reply.take() {
+        let _ = reply.send(answer);
+    }
+}
+
+/// Name of the remote process listening on `remote_port`, if known.
+fn remote_process_name(ports: &[ListeningPort], remote_port: u16) -> Option<String> {
+    ports
+        .iter()
+        .find(|p| p.port == remote_port)
+        .and_then(|p| p.process.as_ref())
+        .map(|p| p.name.clone())
+}
+
+/// Whether every address the remote listens on for `remote_port` is loopback.
+fn is_loopback_only(ports: &[ListeningPort], remote_port: u16) -> bool {
+    let mut ports = ports.iter().filter(|p| p.port == remote_port).peekable();
+    ports.peek().is_some() && ports.all(ListeningPort::is_loopback_only)
+}
+
+/// Whether `[policy]` allows a local forward of `remote_port`. When it doesn't,
+/// opens the "blocked by policy" modal and logs the refusal.
+fn check_policy(model: &mut Model, remote_port: u16) -> bool {
+    let process_name = remote_process_name(&model.ports, remote_port);
+    let loopback_only = is_loopback_only(&model.ports, remote_port);
+    let Err(reason) = model
+        .policy
+        .check(remote_port, process_name.as_deref(), loopback_only)
+    else {
+        return true;
+    };
+    model.log_event(
+        EventLevel::Warn,
+        format!("forward of :{remote_port} blocked by policy: {reason}"),
+    );
+    model.modal = ModalState::PolicyBlocked {
+        remote_port,
+        process_name,
+        reason,
+    };
+    model.needs_render = true;
+    false
+}
+
+/// Persisted forwards reactivate on their own when the port reappears; drop
+/// the ones `[policy]` no longer allows (the owner may have changed too).
+fn drop_blocked_reactivations(
+    model: &mut Model,
+    ports: &[ListeningPort],
+    commands: &mut Vec<ForwardCommand>,
+) {
+    let mut blocked = Vec::new();
+    commands.retain(|cmd| {
+        let ForwardCommand::Reactivate {
+            kind: ForwardKind::Local,
+            remote_port,
+            ..
+        } = cmd
+        else {
+            return true;
+        };
+        let process_name = remote_process_name(ports, *remote_port);
+        let loopback_only = is_loopback_only(ports, *remote_port);
+        match model
+            .policy
+            .check(*remote_port, process_name.as_deref(), loopback_only)
+        {
+            Ok(()) => true,
+            Err(reason) => {
+                blocked.push((*remote_port, reason));
+                false
+            }
+        }
+    });
+    if blocked.is_empty() {
+        return;
+    }
+    for (remote_port, reason) in blocked {
+        model.forwards.remove(&ForwardKey::local(remote_port));
+        model.log_event(
+            EventLevel::Warn,
+            format!("saved forward of :{remote_port} removed, blocked by policy: {reason}"),
+        );
+    }
+    save_forwards(model);
+}
+
+/// Open the kill confirmation for the remote process owning the selected port.
+fn open_kill_modal(model: &mut Model) {
+    let display_rows = build_display_rows(model);
+    let Some(DisplayRow::Port(i)) = display_rows.get(model.selected_index) else {
+        return;
+    };
+    let port = &model.ports[*i];
+    if let Some(process) = &port.process {
+        model.modal = ModalState::ConfirmKill {
+            remote_port: port.port,
+            pid: process.pid,
+            process_name: process.name.clone(),
+            signal: KillSignal::Term,
+            error: None,
+        };
+        model.needs_render = true;
+    }
+}
+
+#[cfg(feature = "tui")]
+fn handle_confirm_kill_key(model: &mut Model, key: KeyEvent) {
+    let ModalState::ConfirmKill {
+        remote_port,
+        pid,
+        process_name,
+        signal,
+        ..
+    } = &model.modal
+    else {
+        return;
+    };
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('n') => {
+            model.modal = ModalState::None;
+        }
+        KeyCode::Enter | KeyCode::Char('y') => {
+            model.agent_commands.push(AgentCommand::Kill {
+                pid: *pid,
+                signal: *signal,
+            });
+            model.modal = ModalState::None;
+        }
+        KeyCode::Tab => {
+            model.modal = ModalState::ConfirmKill {
+                remote_port: *remote_port,
+                pid: *pid,
+                process_name: process_name.clone(),
+                signal: match signal {
+                    KillSignal::Term => KillSignal::Kill,
+                    KillSignal::Kill => KillSignal::Term,
+                },
+                error: None,
+            };
+        }
+        _ => return,
+    }
+    model.needs_render = true;
+}
+
+#[cfg(feature = "tui")]
+fn handle_agent_log_key(model: &mut Model, key: KeyEvent) {
+    let ModalState::AgentLog { lines, scroll, .. } = &mut model.modal else {
+        return;
+    };
+    let max_scroll = lines.len().saturating_sub(1);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => {
+            model.modal = ModalState::None;
+        }
+        KeyCode::Char('k') | KeyCode::Up => *scroll = (*scroll + 1).min(max_scroll),
+        KeyCode::Char('j') | KeyCode::Down => *scroll = scroll.saturating_sub(1),
+        KeyCode::PageUp => *scroll = (*scroll + 10).min(max_scroll),
+        KeyCode::PageDown => *scroll = scroll.saturating_sub(10),
+        KeyCode::Char('g') => *scroll = max_scroll,
+        KeyCode::Char('G') => *scroll = 0,
+        _ => return,
+    }
+    model.needs_render = true;
+}
+
+/// Write the current remote ports and forwards to `~/.sshfwd/snapshots/`.
+fn save_snapshot(model: &mut Model) {
+    if model.scan_index == 0 {
+        model.log_event(EventLevel::Warn, "snapshot skipped: no scan yet");
+        return;
+    }
+    let snapshot = crate::export::Snapshot::new(
+        &model.destination,
+        model.hostname.as_deref(),
+        model.username.as_deref(),
+        model.scan_index,
+        &model.ports,
+        &model.forwards,
+    );
+    match snapshot.save() {
+        Ok(path) => {
+            let message = format!("snapshot saved to {} (+ .csv)", path.display());
+            model.log_event(EventLevel::Info, message);
+        }
+        Err(e) => model.log_event(EventLevel::Error, format!("snapshot failed: {e}")),
+    }
+    // Make the result visible without hunting for the pane.
+    model.show_events = true;
+}
+
+/// Where the selected forward is reached: the local address (a URL when the
+/// probe saw HTTP or TLS) of a local forward, the remote `host:port` of a
+/// reverse one.
+pub fn selected_address(model: &Model) -> Option<String> {
+    let port = model.selected_port()?;
+    match model.mode {
+        AppMode::Forward => {
+            let entry = model.forwards.get(&ForwardKey::local(port))?;
+            let address = model
+                .status_file
+                .as_ref()
+                .map_or("127.0.0.1", |s| s.local_address());
+            let remote = model.ports.iter().find(|p| p.port == port);
+            Some(
+                crate::status::tunnel_url(remote, address, entry.local_port)
+                    .unwrap_or_else(|| format!("{address}:{}", entry.local_port)),
+            )
+        }
+        AppMode::Reverse => {
+            let (key, _) = model
+                .forwards
+                .iter()
+                .filter(|(k, _)| k.kind == ForwardKind::Reverse)
+                .find(|(k, e)| e.local_port == port || k.remote_port == port)?;
+            Some(format!("{}:{}", model.remote_host(), key.remote_port))
+        }
+    }
+}
+
+/// `y`: copy the selected forward's address (see `selected_address`).
+#[cfg(feature = "tui")]
+fn copy_address(model: &mut Model) {
+    let Some(address) = selected_address(model) else {
+        model.log_event(EventLevel::Warn, "nothing to copy: the row isn't forwarded");
+        return;
+    };
+    match crate::clipboard::copy(&address) {
+        Ok(method) => {
+            let message = format!("copied {address} ({})", method.name());
+            model.log_event(EventLevel::Info, message);
+        }
+        Err(e) => model.log_event(EventLevel::Error, format!("copy failed: {e}")),
+    }
+    // Make the result visible without hunting for the pane.
+    model.show_events = true;
+}
+
+/// `v`: fetch a preview of the selected row's active local forward.
+#[cfg(feature = "tui")]
+fn open_preview(model: &mut Model) {
+    if let Some(remote_port) = model.selected_port() {
+        fetch_preview(model, remote_port);
+    }
+}
+
+/// Open the preview modal for the forward of `remote_port` and queue its
+/// request; warns instead if the forward isn't up.
+#[cfg(feature = "tui")]
+fn fetch_preview(model: &mut Model, remote_port: u16) {
+    let local_port = match model.forwards.get(&ForwardKey::local(remote_port)) {
+        Some(entry) if entry.status == ForwardStatus::Active => entry.local_port,
+        _ => {
+            model.log_event(
+                EventLevel::Warn,
+                format!("nothing to preview: :{remote_port} isn't forwarded"),
+            );
+            return;
+        }
+    };
+    let host = model
+        .status_file
+        .as_ref()
+        .map_or("127.0.0.1", |s| s.local_address())
+        .to_string();
+    model.modal = ModalState::Preview {
+        remote_port,
+        url: format!("http://{host}:{local_port}/"),
+        result: None,
+        scroll: 0,
+    };
+    model.previews_to_fetch.push(crate::preview::Request {
+        remote_port,
+        host,
+        port: local_port,
+    });
+    model.needs_render = true;
+}
+
+#[cfg(feature = "tui")]
+fn handle_preview_key(model: &mut Model, key: KeyEvent) {
+    let ModalState::Preview {
+        remote_port,
+        result,
+        scroll,
+        ..
+    } = &mut model.modal
+    else {
+        return;
+    };
+    let max_scroll = match result {
+        Some(Ok(preview)) => preview.line_count().saturating_sub(1),
+        _ => 0,
+    };
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => {
+            model.modal = ModalState::None;
+        }
+        KeyCode::Char('r') if result.is_some() => {
+            let remote_port = *remote_port;
+            model.modal = ModalState::None;
+            fetch_preview(model, remote_port);
+        }
+        KeyCode::Char('j') | KeyCode::Down => *scroll = (*scroll + 1).min(max_scroll),
+        KeyCode::Char('k') | KeyCode::Up => *scroll = scroll.saturating_sub(1),
+        KeyCode::PageDown => *scroll = (*scroll + 10).min(max_scroll),
+        KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+        KeyCode::Char('g') => *scroll = 0,
+        _ => return,
+    }
+    model.needs_render = true;
+}
+
+#[cfg(feature = "tui")]
+fn handle_timeline_key(model: &mut Model, key: KeyEvent) {
+    let selected = model.selected_port();
+    let ModalState::Timeline { port, scroll } = &mut model.modal else {
+        return;
+    };
+    let len = model
+        .history
+        .timeline()
+        .iter()
+        .filter(|e| port.is_none_or(|p| e.port == p))
+        .count();
+    let max_scroll = len.saturating_sub(1);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('t') => {
+            model.modal = ModalState::None;
+        }
+        // Toggle between all ports and the port selected in the table.
+        KeyCode::Tab => {
+            *port = if port.is_some() { None } else { selected };
+            *scroll = 0;
+        }
+        KeyCode::Char('k') | KeyCode::Up => *scroll = (*scroll + 1).min(max_scroll),
+        KeyCode::Char('j') | KeyCode::Down => *scroll = scroll.saturating_sub(1),
+        KeyCode::PageUp => *scroll = (*scroll + 10).min(max_scroll),
+        KeyCode::PageDown => *scroll = scroll.saturating_sub(10),
+        KeyCode::Char('g') => *scroll = max_scroll,
+        KeyCode::Char('G') => *scroll = 0,
+        _ => return,
+    }
+    model.needs_render = true;
+}
+
+/// Show log tails; surface failed agent commands by reopening the originating
+/// modal with the error.
+fn handle_agent_reply(model: &mut Model, reply: CommandReply) {
+    if model.modal != ModalState::None {
+        return;
+    }
+    if let AgentCommand::LogTail { .. } = reply.command {
+        model.modal = ModalState::AgentLog {
+            lines: reply.output,
+            error: (!reply.success).then_some(reply.message),
+            scroll: 0,
+        };
+        model.needs_render = true;
+        return;
+    }
+    if reply.success {
+        return;
+    }
+    match reply.command {
+        AgentCommand::Kill { pid, signal } => {
+            let port = model
+                .ports
+                .iter()
+                .find(|p| p.process.as_ref().is_some_and(|proc| proc.pid == pid));
+            model.modal = ModalState::ConfirmKill {
+                remote_port: port.map_or(0, |p| p.port),
+                pid,
+                process_name: port
+                    .and_then(|p| p.process.as_ref())
+                    .map(|proc| proc.name.clone())
+                    .unwrap_or_default(),
+                signal,
+                error: Some(reply.message),
+            };
+            model.needs_render = true;
+        }
+        AgentCommand::Rescan => {
+            model.log_event(
+                EventLevel::Warn,
+                format!("rescan failed: {}", reply.message),
+            );
+        }
+        AgentCommand::SetInterval { .. } => {
+            model.log_event(
+                EventLevel::Warn,
+                format!("could not slow the agent's scans: {}", reply.message),
+            );
+        }
+        AgentCommand::LogTail { .. } => {}
+    }
+}
+
 fn handle_reverse_action(model: &mut Model) -> Vec<ForwardCommand> {
     let mut commands = Vec::new();
     let display_rows = build_display_rows(model);
@@ -584,7 +2110,9 @@ fn handle_reverse_action(model: &mut Model) -> Vec<ForwardCommand> {
                     buffer: local_port.to_string(),
                     remote_host: "127.0.0.1".to_string(),
                     error: None,
+                    conflict: None,
                 };
+                check_port_input(model);
                 model.needs_render = true;
             }
         }
@@ -593,6 +2121,7 @@ fn handle_reverse_action(model: &mut Model) -> Vec<ForwardCommand> {
     commands
 }
 
+#[cfg(feature = "tui")]
 fn handle_port_input_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand> {
     let mut commands = Vec::new();
 
@@ -611,7 +2140,7 @@ fn handle_port_input_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand
             remote_host.clone(),
             buffer.clone(),
         ),
-        ModalState::None => return commands,
+        _ => return commands,
     };
 
     match key.code {
@@ -620,100 +2149,324 @@ fn handle_port_input_key(model: &mut Model, key: KeyEvent) -> Vec<ForwardCommand
             model.needs_render = true;
         }
         KeyCode::Enter => {
-            if let Ok(parsed_port) = buffer.parse::<u16>() {
-                if parsed_port > 0 {
-                    match kind {
-                        ForwardKind::Local => {
-                            let fwd_key = ForwardKey::local(remote_port);
-                            if model.forwards.contains_key(&fwd_key) {
-                                commands.push(ForwardCommand::Stop {
-                                    kind: ForwardKind::Local,
-                                    remote_port,
-                                });
-                            }
-                            model.forwards.insert(
-                                fwd_key,
-                                ForwardEntry {
-                                    local_port: parsed_port,
-                                    status: ForwardStatus::Starting,
-                                    active_connections: 0,
-                                },
-                            );
-                            commands.push(ForwardCommand::Start {
-                                kind: ForwardKind::Local,
-                                remote_port,
-                                local_port: parsed_port,
-                                remote_host,
-                            });
-                        }
-                        ForwardKind::Reverse => {
-                            // buffer holds the remote bind port; local_port is fixed
-                            let fwd_key = ForwardKey::reverse(parsed_port);
-                            if model.forwards.contains_key(&fwd_key) {
-                                commands.push(ForwardCommand::Stop {
-                                    kind: ForwardKind::Reverse,
-                                    remote_port: parsed_port,
-                                });
-                            }
-                            model.forwards.insert(
-                                fwd_key,
-                                ForwardEntry {
-                                    local_port,
-                                    status: ForwardStatus::Starting,
-                                    active_connections: 0,
-                                },
-                            );
-                            commands.push(ForwardCommand::Start {
-                                kind: ForwardKind::Reverse,
-                                remote_port: parsed_port,
-                                local_port,
-                                remote_host,
-                            });
-                        }
+            // A port that won't do keeps the modal open, saying why.
+            let parsed_port = match port_check(model, kind).check(&buffer) {
+                Ok(port) => port,
+                Err(problem) => {
+                    if let ModalState::PortInput { error, .. } = &mut model.modal {
+                        *error = Some(problem);
                     }
+                    model.needs_render = true;
+                    return commands;
+                }
+            };
+            match kind {
+                // check_policy shows the refusal in place of this modal.
+                ForwardKind::Local if !check_policy(model, remote_port) => {}
+                ForwardKind::Local => {
+                    commands = request_local_forward(model, remote_port, parsed_port, remote_host);
+                }
+                ForwardKind::Reverse => {
+                    // buffer holds the remote bind port (checked free of our
+                    // forwards); local_port is fixed
+                    model.forwards.insert(
+                        ForwardKey::reverse(parsed_port),
+                        ForwardEntry {
+                            local_port,
+                            status: ForwardStatus::Starting,
+                            active_connections: 0,
+                            open_wait: Default::default(),
+                        },
+                    );
+                    commands.push(ForwardCommand::Start {
+                        kind: ForwardKind::Reverse,
+                        remote_port: parsed_port,
+                        local_port,
+                        remote_host,
+                    });
                 }
             }
-            model.modal = ModalState::None;
-            adjust_selection(model, Some(remote_port));
+            // A policy refusal or confirmation replaced the modal with its own.
+            if matches!(model.modal, ModalState::PortInput { .. }) {
+                model.modal = ModalState::None;
+            }
             model.needs_render = true;
         }
         KeyCode::Char(c) if c.is_ascii_digit() => {
-            let mut new_buffer = buffer;
-            if new_buffer.len() < 5 {
-                new_buffer.push(c);
-                model.modal = ModalState::PortInput {
-                    kind,
-                    remote_port,
-                    local_port,
-                    buffer: new_buffer,
-                    remote_host,
-                    error: None,
-                };
-                model.needs_render = true;
+            if let ModalState::PortInput { buffer, .. } = &mut model.modal {
+                if buffer.len() < 5 {
+                    buffer.push(c);
+                    check_port_input(model);
+                    model.needs_render = true;
+                }
             }
         }
         KeyCode::Backspace => {
-            let mut new_buffer = buffer;
-            new_buffer.pop();
-            model.modal = ModalState::PortInput {
-                kind,
+            if let ModalState::PortInput { buffer, .. } = &mut model.modal {
+                buffer.pop();
+                check_port_input(model);
+                model.needs_render = true;
+            }
+        }
+        // Complete to the next free port above the typed one.
+        KeyCode::Tab => {
+            let after = buffer.parse().unwrap_or(1023);
+            if let Some(port) = port_check(model, kind).next_free(after) {
+                if let ModalState::PortInput { buffer, error, .. } = &mut model.modal {
+                    *buffer = port.to_string();
+                    *error = None;
+                    model.needs_render = true;
+                }
+            }
+        }
+        // Step through the free ports suggested after a failed bind.
+        KeyCode::Down | KeyCode::Right | KeyCode::Up | KeyCode::Left => {
+            if let ModalState::PortInput {
+                buffer,
+                conflict: Some(conflict),
+                ..
+            } = &mut model.modal
+            {
+                let forward = matches!(key.code, KeyCode::Down | KeyCode::Right);
+                if let Some(port) = step_suggestion(&conflict.suggestions, buffer, forward) {
+                    *buffer = port.to_string();
+                    check_port_input(model);
+                    model.needs_render = true;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    commands
+}
+
+/// The suggestion after (or before) the one in `buffer`; from outside the
+/// list, the first (or last) one.
+#[cfg(feature = "tui")]
+fn step_suggestion(suggestions: &[u16], buffer: &str, forward: bool) -> Option<u16> {
+    let current = suggestions.iter().position(|p| buffer.parse() == Ok(*p));
+    let next = match (current, forward) {
+        (None, true) => 0,
+        (None, false) => suggestions.len().checked_sub(1)?,
+        (Some(i), true) => (i + 1) % suggestions.len(),
+        (Some(i), false) => (i + suggestions.len() - 1) % suggestions.len(),
+    };
+    suggestions.get(next).copied()
+}
+
+fn handle_api(model: &mut Model, request: ApiRequest) -> (ApiReply, Vec<ForwardCommand>) {
+    let snapshot = || {
+        crate::export::Snapshot::new(
+            &model.destination,
+            model.hostname.as_deref(),
+            model.username.as_deref(),
+            model.scan_index,
+            &model.ports,
+            &model.forwards,
+        )
+    };
+    let reply = match request {
+        ApiRequest::Snapshot => ApiReply::ok(serde_json::to_value(snapshot()).unwrap_or_default()),
+        ApiRequest::Ports => ApiReply::ok(serde_json::to_value(&model.ports).unwrap_or_default()),
+        ApiRequest::Forwards => {
+            ApiReply::ok(serde_json::to_value(snapshot().forwards).unwrap_or_default())
+        }
+        ApiRequest::StartForward(new) => return api_start_forward(model, new),
+        ApiRequest::StopForward { kind, remote_port } => {
+            if !model
+                .forwards
+                .contains_key(&ForwardKey { kind, remote_port })
+            {
+                return (ApiReply::error(404, "no such forward"), Vec::new());
+            }
+            let reply = ApiReply {
+                status: 202,
+                body: serde_json::json!({ "stopping": remote_port }),
+            };
+            return (reply, vec![ForwardCommand::Stop { kind, remote_port }]);
+        }
+    };
+    (reply, Vec::new())
+}
+
+/// `POST /v1/forwards`: the same policy checks as the TUI, answered instead
+/// of shown as modals.
+fn api_start_forward(model: &mut Model, new: NewForward) -> (ApiReply, Vec<ForwardCommand>) {
+    let remote_port = new.remote_port;
+    let local_port = new.local_port.unwrap_or(remote_port);
+    if remote_port == 0 || local_port == 0 {
+        return (ApiReply::error(400, "ports must be non-zero"), Vec::new());
+    }
+    let commands = match new.kind {
+        ForwardKind::Local => {
+            match unattended_check(model, remote_port, new.confirm) {
+                Ok(()) => {}
+                Err(Refusal::Policy(reason)) => {
+                    model.log_event(
+                        EventLevel::Warn,
+                        format!("API forward of :{remote_port} blocked by policy: {reason}"),
+                    );
+                    return (ApiReply::error(403, reason), Vec::new());
+                }
+                Err(Refusal::NeedsConfirmation(reason)) => {
+                    return (
+                        ApiReply::error(409, format!("{reason}; resend with \"confirm\": true")),
+                        Vec::new(),
+                    );
+                }
+            }
+            let remote_host = model.remote_host();
+            start_local_forward(model, remote_port, local_port, remote_host)
+        }
+        ForwardKind::Reverse => {
+            let key = ForwardKey::reverse(remote_port);
+            let mut commands = Vec::new();
+            if model.forwards.contains_key(&key) {
+                commands.push(ForwardCommand::Stop {
+                    kind: ForwardKind::Reverse,
+                    remote_port,
+                });
+            }
+            model.forwards.insert(
+                key,
+                ForwardEntry {
+                    local_port,
+                    status: ForwardStatus::Starting,
+                    active_connections: 0,
+                    open_wait: Default::default(),
+                },
+            );
+            commands.push(ForwardCommand::Start {
+                kind: ForwardKind::Reverse,
                 remote_port,
                 local_port,
-                buffer: new_buffer,
-                remote_host,
-                error: None,
-            };
+                remote_host: "127.0.0.1".to_string(),
+            });
             model.needs_render = true;
+            commands
         }
-        _ => {}
+    };
+    let reply = ApiReply {
+        status: 202,
+        body: serde_json::json!({
+            "kind": new.kind,
+            "remote_port": remote_port,
+            "local_port": local_port,
+            "status": "starting",
+        }),
+    };
+    (reply, commands)
+}
+
+/// Why a local forward started without the TUI's modals (API, group
+/// forwards) may not go ahead.
+enum Refusal {
+    Policy(String),
+    NeedsConfirmation(&'static str),
+}
+
+fn unattended_check(model: &Model, remote_port: u16, confirm: bool) -> Result<(), Refusal> {
+    let process = model
+        .ports
+        .iter()
+        .find(|p| p.port == remote_port)
+        .and_then(|p| p.process.as_ref());
+    let loopback_only = is_loopback_only(&model.ports, remote_port);
+    model
+        .policy
+        .check(remote_port, process.map(|p| p.name.as_str()), loopback_only)
+        .map_err(Refusal::Policy)?;
+    match model.policy.confirmation_reason(remote_port, process) {
+        Some(reason) if !confirm => Err(Refusal::NeedsConfirmation(reason)),
+        _ => Ok(()),
+    }
+}
+
+/// Group forward (`A` with several hosts): start a local forward of
+/// `remote_port` on `local_port`. Hosts where the port isn't listening, or
+/// where `[policy]` refuses or wants to confirm it, are skipped with the
+/// reason in their event log.
+pub fn group_forward(model: &mut Model, remote_port: u16, local_port: u16) -> Vec<ForwardCommand> {
+    if model.forwards.contains_key(&ForwardKey::local(remote_port)) {
+        return Vec::new();
+    }
+    let skipped = if !model.ports.iter().any(|p| p.port == remote_port) {
+        Some("not listening".to_string())
+    } else {
+        match unattended_check(model, remote_port, false) {
+            Ok(()) => None,
+            Err(Refusal::Policy(reason)) => Some(format!("blocked by policy: {reason}")),
+            Err(Refusal::NeedsConfirmation(reason)) => Some(reason.to_string()),
+        }
+    };
+    if let Some(reason) = skipped {
+        model.log_event(
+            EventLevel::Warn,
+            format!("group forward of :{remote_port} skipped: {reason}"),
+        );
+        return Vec::new();
     }
+    let remote_host = model.remote_host();
+    start_local_forward(model, remote_port, local_port, remote_host)
+}
 
-    commands
+/// Keep mDNS announcements in step with local forwards.
+fn update_mdns(model: &mut Model, event: &ForwardEvent) {
+    let Some(mdns) = model.mdns.as_mut() else {
+        return;
+    };
+    let result = match *event {
+        ForwardEvent::Started {
+            kind: ForwardKind::Local,
+            remote_port,
+            local_port,
+        } => {
+            let port = model.ports.iter().find(|p| p.port == remote_port);
+            mdns.advertise(remote_port, local_port, port)
+        }
+        ForwardEvent::Stopped {
+            kind: ForwardKind::Local,
+            remote_port,
+        }
+        | ForwardEvent::Paused {
+            kind: ForwardKind::Local,
+            remote_port,
+        }
+        | ForwardEvent::BindError {
+            kind: ForwardKind::Local,
+            remote_port,
+            ..
+        } => {
+            mdns.withdraw(remote_port);
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        model.log_event(EventLevel::Warn, e);
+    }
+}
+
+fn run_hook(
+    model: &mut Model,
+    event: HookEvent,
+    port: u16,
+    local_port: Option<u16>,
+    process: Option<String>,
+) {
+    let Some(hooks) = &model.hooks else { return };
+    if let Err(e) = hooks.run(event, port, local_port, process.as_deref()) {
+        model.log_event(EventLevel::Error, e.to_string());
+    }
 }
 
 fn save_forwards(model: &Model) {
     use crate::forward::persistence::{self, PersistedForward};
 
+    if !model.persist_forwards {
+        return;
+    }
     let forwards: Vec<PersistedForward> = model
         .forwards
         .iter()
@@ -727,11 +2480,82 @@ fn save_forwards(model: &Model) {
     persistence::save_forwards(&model.destination, &forwards);
 }
 
-pub fn view(model: &mut Model, frame: &mut ratatui::Frame) {
-    let areas = crate::ui::layout_areas(frame.area());
+/// A `DiscoveryError`, as its message and hint (`journal` replays them so).
+pub(crate) fn discovery_failed(model: &mut Model, message: String, hint: Option<String>) {
+    // Reconnect loop handles recovery — do not exit.
+    model.log_event(EventLevel::Error, message.clone());
+    if let Some(hint) = hint {
+        show_error(model, "Discovery failed", message, hint);
+    }
+}
+
+/// An error the agent reported. A failing scan fails again every interval,
+/// so a kind is logged once until a scan succeeds. Remedies that need the
+/// user to reconnect differently get the modal; the rest go to the log.
+fn agent_failed(model: &mut Model, e: AgentError) {
+    if model.agent_error == Some(e.kind) {
+        return;
+    }
+    model.agent_error = Some(e.kind);
+    let level = match e.kind {
+        AgentErrorKind::NotImplemented => EventLevel::Info,
+        AgentErrorKind::IntegrityFailed => EventLevel::Error,
+        _ => EventLevel::Warn,
+    };
+    let message = format!("agent {}: {}", e.kind, e.message);
+    model.log_event(level, message.clone());
+    match agent_error_hint(e.kind) {
+        Some(hint)
+            if matches!(
+                e.kind,
+                AgentErrorKind::PermissionDenied | AgentErrorKind::IntegrityFailed
+            ) =>
+        {
+            show_error(model, "Agent error", message, hint)
+        }
+        Some(hint) => model.log_event(EventLevel::Info, hint),
+        None => {}
+    }
+}
+
+/// Explain an error and its remedy in a modal, unless another modal is open
+/// or this remedy was shown already.
+fn show_error(model: &mut Model, title: &'static str, message: String, hint: String) {
+    if model.modal != ModalState::None || model.shown_hint.as_ref() == Some(&hint) {
+        return;
+    }
+    model.shown_hint = Some(hint.clone());
+    model.modal = ModalState::Error {
+        title,
+        message,
+        hint,
+    };
+    model.needs_render = true;
+}
+
+/// Render `model` into `area`; modals are centred on the whole frame.
+#[cfg(feature = "tui")]
+pub fn view(model: &mut Model, frame: &mut ratatui::Frame, area: ratatui::layout::Rect) {
+    let areas = crate::ui::layout_areas(area, model.show_details, model.show_events);
     crate::ui::table::render(model, frame, areas.table);
+    if let Some(detail_area) = areas.detail {
+        crate::ui::detail::render(model, frame, detail_area);
+    }
+    if let Some(events_area) = areas.events {
+        crate::ui::events::render(model, frame, events_area);
+    }
     crate::ui::hotkey_bar::render(model, frame, areas.hotkey_bar);
-    if model.modal != ModalState::None {
-        crate::ui::modal::render(model, frame);
+    match model.modal {
+        ModalState::None => {}
+        ModalState::PortInput { .. } => crate::ui::modal::render(model, frame),
+        ModalState::ConfirmKill { .. } => crate::ui::modal::render_confirm_kill(model, frame),
+        ModalState::ConfirmForward { .. } => crate::ui::modal::render_confirm_forward(model, frame),
+        ModalState::PolicyBlocked { .. } => crate::ui::modal::render_policy_blocked(model, frame),
+        ModalState::Error { .. } => crate::ui::modal::render_error(model, frame),
+        ModalState::Prompt { .. } => crate::ui::modal::render_prompt(model, frame),
+        ModalState::StartupFailed { .. } => crate::ui::modal::render_startup_failed(model, frame),
+        ModalState::AgentLog { .. } => crate::ui::modal::render_agent_log(model, frame),
+        ModalState::Timeline { .. } => crate::ui::modal::render_timeline(model, frame),
+        ModalState::Preview { .. } => crate::ui::modal::render_preview(model, frame),
     }
 }
@@ -0,0 +1,271 @@
+//! Append-only audit log of forwarding activity, one JSON object per line
+//! (`~/.sshfwd/audit.jsonl` unless `[audit] path` says otherwise), for
+//! shipping to a SIEM.
+//!
+//! Records: `forward_started`, `forward_paused`, `forward_failed`,
+//! `forward_stopped` (with the forward's total bytes, connections and
+//! lifetime), `connection` (one per closed tunneled connection) and
+//! `connection_denied` (refused by `[sharing]`).
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Serialize;
+use sshfwd_common::time::{epoch_secs, format_utc};
+
+use crate::forward::{ForwardEvent, ForwardKey, ForwardKind, Traffic};
+
+pub struct AuditLog {
+    file: File,
+    /// Local user running sshfwd.
+    user: String,
+    destination: String,
+    forwards: HashMap<ForwardKey, ForwardTotals>,
+}
+
+/// Running totals of a forward since it started.
+struct ForwardTotals {
+    local_port: u16,
+    started: Instant,
+    connections: u32,
+    traffic: Traffic,
+}
+
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    ts: String,
+    user: &'a str,
+    destination: &'a str,
+    event: &'static str,
+    kind: ForwardKind,
+    remote_port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes_to_remote: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes_from_remote: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connections: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+impl AuditLog {
+    pub fn default_path() -> PathBuf {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+        PathBuf::from(home).join(".sshfwd").join("audit.jsonl")
+    }
+
+    /// Open `path` for appending, creating it (owner-only on Unix) if needed.
+    pub fn open(path: &Path, destination: &str) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        Ok(Self {
+            file: options.open(path)?,
+            user: std::env::var("USER").unwrap_or_default(),
+            destination: destination.to_string(),
+            forwards: HashMap::new(),
+        })
+    }
+
+    /// Append the record for `event`, if it is one that gets audited.
+    pub fn record(&mut self, event: &ForwardEvent) -> io::Result<()> {
+        let line = match self.build_record(event) {
+            Some(record) => serde_json::to_string(&record).expect("audit record serializes"),
+            None => return Ok(()),
+        };
+        writeln!(self.file, "{line}")
+    }
+
+    fn build_record<'a>(&'a mut self, event: &'a ForwardEvent) -> Option<Record<'a>> {
+        let record = match event {
+            ForwardEvent::Started {
+                kind,
+                remote_port,
+                local_port,
+            } => {
+                let key = ForwardKey {
+                    kind: *kind,
+                    remote_port: *remote_port,
+                };
+                self.forwards.insert(
+                    key,
+                    ForwardTotals {
+                        local_port: *local_port,
+                        started: Instant::now(),
+                        connections: 0,
+                        traffic: Traffic::default(),
+                    },
+                );
+                Record {
+                    local_port: Some(*local_port),
+                    ..self.base("forward_started", *kind, *remote_port)
+                }
+            }
+            ForwardEvent::Paused { kind, remote_port } => {
+                self.base("forward_paused", *kind, *remote_port)
+            }
+            ForwardEvent::BindError {
+                kind,
+                remote_port,
+                message,
+            } => Record {
+                message: Some(message),
+                ..self.base("forward_failed", *kind, *remote_port)
+            },
+            ForwardEvent::Stopped { kind, remote_port } => {
+                let totals = self.forwards.remove(&ForwardKey {
+                    kind: *kind,
+                    remote_port: *remote_port,
+                });
+                let base = self.base("forward_stopped", *kind, *remote_port);
+                match totals {
+                    Some(t) => Record {
+                        local_port: Some(t.local_port),
+                        bytes_to_remote: Some(t.traffic.to_remote),
+                        bytes_from_remote: Some(t.traffic.from_remote),
+                        duration_ms: Some(millis(t.started.elapsed())),
+                        connections: Some(t.connections),
+                        ..base
+                    },
+                    None => base,
+                }
+            }
+            ForwardEvent::ConnectionClosed {
+                kind,
+                remote_port,
+                peer,
+                traffic,
+                duration,
+                ..
+            } => {
+                let key = ForwardKey {
+                    kind: *kind,
+                    remote_port: *remote_port,
+                };
+                let local_port = self.forwards.get_mut(&key).map(|t| {
+                    t.connections += 1;
+                    t.traffic.to_remote += traffic.to_remote;
+                    t.traffic.from_remote += traffic.from_remote;
+                    t.local_port
+                });
+                Record {
+                    local_port,
+                    peer: Some(peer),
+                    bytes_to_remote: Some(traffic.to_remote),
+                    bytes_from_remote: Some(traffic.from_remote),
+                    duration_ms: Some(millis(*duration)),
+                    ..self.base("connection", *kind, *remote_port)
+                }
+            }
+            ForwardEvent::ConnectionDenied {
+                kind,
+                remote_port,
+                peer,
+                reason,
+            } => Record {
+                peer: Some(peer),
+                message: Some(reason),
+                ..self.base("connection_denied", *kind, *remote_port)
+            },
+            ForwardEvent::ConnectionCountChanged { .. } => return None,
+        };
+        Some(record)
+    }
+
+    fn base(&self, event: &'static str, kind: ForwardKind, remote_port: u16) -> Record<'_> {
+        Record {
+            ts: format_utc(epoch_secs(SystemTime::now())),
+            user: &self.user,
+            destination: &self.destination,
+            event,
+            kind,
+            remote_port,
+            local_port: None,
+            peer: None,
+            bytes_to_remote: None,
+            bytes_from_remote: None,
+            duration_ms: None,
+            connections: None,
+            message: None,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_roll_up_into_the_stop_record() {
+        let dir = std::env::temp_dir().join(format!("sshfwd-audit-{}", std::process::id()));
+        let path = dir.join("audit.jsonl");
+        let mut audit = AuditLog::open(&path, "dev@box").unwrap();
+
+        let events = [
+            ForwardEvent::Started {
+                kind: ForwardKind::Local,
+                remote_port: 5432,
+                local_port: 15432,
+            },
+            ForwardEvent::ConnectionCountChanged {
+                kind: ForwardKind::Local,
+                remote_port: 5432,
+                count: 1,
+            },
+            ForwardEvent::ConnectionClosed {
+                kind: ForwardKind::Local,
+                remote_port: 5432,
+                peer: "127.0.0.1:50000".into(),
+                traffic: Traffic {
+                    to_remote: 100,
+                    from_remote: 2000,
+                },
+                duration: Duration::from_millis(1500),
+                open_wait: Duration::ZERO,
+            },
+            ForwardEvent::Stopped {
+                kind: ForwardKind::Local,
+                remote_port: 5432,
+            },
+        ];
+        for event in &events {
+            audit.record(event).unwrap();
+        }
+
+        let content = fs::read_to_string(&path).unwrap();
+        let records: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["event"], "forward_started");
+        assert_eq!(records[0]["destination"], "dev@box");
+        assert_eq!(records[1]["event"], "connection");
+        assert_eq!(records[1]["local_port"], 15432);
+        assert_eq!(records[1]["duration_ms"], 1500);
+        assert_eq!(records[2]["event"], "forward_stopped");
+        assert_eq!(records[2]["connections"], 1);
+        assert_eq!(records[2]["bytes_from_remote"], 2000);
+        assert!(records[2].get("message").is_none());
+    }
+}
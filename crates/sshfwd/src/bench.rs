@@ -0,0 +1,273 @@
+//! `sshfwd bench <host>`: latency and throughput of the tunnel path.
+//!
+//! The agent runs as a loopback traffic endpoint (`--bench-server`, protocol
+//! in `sshfwd_common::bench`). The same transfers are timed over a bare
+//! `direct-tcpip` channel and through a local forward run by a
+//! `ForwardManager` with the host's `[tunnel]` settings. Similar numbers put
+//! the limit in the network or the SSH server; a slower forward points at
+//! sshfwd's side.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use sshfwd_common::bench::{self, DOWNLOAD, ECHO, UPLOAD};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::forward::tuning::TunnelOptions;
+use crate::forward::{ForwardCommand, ForwardEvent, ForwardKind, ForwardManager};
+use crate::ssh::agent::{AgentManager, AgentOptions};
+use crate::ssh::session::Session;
+
+/// Megabytes sent each way unless `--size` says otherwise.
+pub const DEFAULT_SIZE_MB: u64 = 16;
+const ROUND_TRIPS: usize = 50;
+const CHUNK: usize = 64 * 1024;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+/// A forward below this share of the bare channel's throughput is called out.
+const SLOW_FORWARD: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Measurement {
+    pub latency_median: Duration,
+    pub latency_max: Duration,
+    /// Bytes per second.
+    pub upload: f64,
+    pub download: f64,
+}
+
+/// Deploy the bench agent on `session`, measure both paths and return the
+/// printable report.
+pub async fn run(
+    session: Session,
+    options: &AgentOptions,
+    tunnel: TunnelOptions,
+    bytes: u64,
+) -> Result<String, String> {
+    let options = AgentOptions {
+        bench: true,
+        ..options.clone()
+    };
+    let stdio = AgentManager::new(session.clone())
+        .deploy_and_spawn(&options)
+        .await
+        .map_err(|e| format!("cannot start the bench agent: {e}"))?;
+    // The agent serves until its stdin closes, so both ends stay open.
+    let _stdin = stdio.stdin;
+    let mut lines = BufReader::new(stdio.stdout).lines();
+    let line = tokio::time::timeout(STARTUP_TIMEOUT, lines.next_line())
+        .await
+        .map_err(|_| "the bench agent did not start in time".to_string())?
+        .map_err(|e| e.to_string())?
+        .unwrap_or_default();
+    let port: u16 = line
+        .trim()
+        .parse()
+        .map_err(|_| format!("unexpected bench agent output {line:?}"))?;
+
+    eprintln!("Measuring the SSH channel...");
+    let channel = measure(
+        async || {
+            session
+                .open_direct_tcpip("127.0.0.1", port)
+                .await
+                .map_err(io::Error::other)
+        },
+        bytes,
+    )
+    .await
+    .map_err(|e| format!("SSH channel: {e}"))?;
+
+    eprintln!("Measuring a local forward...");
+    let local_port = start_forward(session, tunnel, port).await?;
+    let forward = measure(
+        async || {
+            let stream = TcpStream::connect(("127.0.0.1", local_port)).await?;
+            stream.set_nodelay(true)?;
+            Ok(stream)
+        },
+        bytes,
+    )
+    .await
+    .map_err(|e| format!("local forward: {e}"))?;
+
+    Ok(report(&channel, &forward, bytes))
+}
+
+/// Forward `remote_port` on an ephemeral local port; returns that port. The
+/// manager lives until the process exits.
+async fn start_forward(
+    session: Session,
+    tunnel: TunnelOptions,
+    remote_port: u16,
+) -> Result<u16, String> {
+    let (event_tx, event_rx) = crossbeam_channel::unbounded::<ForwardEvent>();
+    let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (forwarded_tx, mut forwarded_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    cmd_tx
+        .send(ForwardCommand::Start {
+            kind: ForwardKind::Local,
+            remote_port,
+            local_port: 0,
+            remote_host: "127.0.0.1".into(),
+        })
+        .map_err(|e| e.to_string())?;
+    let manager = ForwardManager::new(session, event_tx).with_tunnel_options(tunnel);
+    tokio::spawn(async move {
+        // Dropping any of the senders would stop the manager.
+        let _senders = (cmd_tx, forwarded_tx, shutdown_tx);
+        manager
+            .run(&mut cmd_rx, &mut forwarded_rx, shutdown_rx)
+            .await;
+    });
+
+    tokio::task::spawn_blocking(move || loop {
+        match event_rx.recv_timeout(STARTUP_TIMEOUT) {
+            Ok(ForwardEvent::Started { local_port, .. }) => return Ok(local_port),
+            Ok(ForwardEvent::BindError { message, .. }) => return Err(message),
+            Ok(_) => {}
+            Err(_) => return Err("the local forward did not start".to_string()),
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Time `ROUND_TRIPS` one-byte echoes, then `bytes` up and `bytes` down, each
+/// on a connection from `connect`.
+async fn measure<S>(
+    mut connect: impl AsyncFnMut() -> io::Result<S>,
+    bytes: u64,
+) -> io::Result<Measurement>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut echo = connect().await?;
+    echo.write_all(&bench::header(ECHO, 0)).await?;
+    let mut samples = Vec::with_capacity(ROUND_TRIPS);
+    let mut byte = [0; 1];
+    for _ in 0..ROUND_TRIPS {
+        let start = Instant::now();
+        echo.write_all(b"x").await?;
+        echo.flush().await?;
+        echo.read_exact(&mut byte).await?;
+        samples.push(start.elapsed());
+    }
+    samples.sort();
+    drop(echo);
+
+    let mut upload = connect().await?;
+    let start = Instant::now();
+    upload.write_all(&bench::header(UPLOAD, bytes)).await?;
+    let chunk = vec![0; CHUNK];
+    let mut left = bytes;
+    while left > 0 {
+        let n = left.min(CHUNK as u64) as usize;
+        upload.write_all(&chunk[..n]).await?;
+        left -= n as u64;
+    }
+    upload.flush().await?;
+    let mut ack = [0; 8];
+    upload.read_exact(&mut ack).await?;
+    let upload_time = start.elapsed();
+    if u64::from_be_bytes(ack) != bytes {
+        return Err(io::Error::other("upload truncated"));
+    }
+
+    let mut download = connect().await?;
+    let start = Instant::now();
+    download.write_all(&bench::header(DOWNLOAD, bytes)).await?;
+    let received =
+        tokio::io::copy(&mut (&mut download).take(bytes), &mut tokio::io::sink()).await?;
+    let download_time = start.elapsed();
+    if received != bytes {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(Measurement {
+        latency_median: samples[samples.len() / 2],
+        latency_max: samples[samples.len() - 1],
+        upload: rate(bytes, upload_time),
+        download: rate(bytes, download_time),
+    })
+}
+
+fn rate(bytes: u64, took: Duration) -> f64 {
+    bytes as f64 / took.as_secs_f64().max(f64::EPSILON)
+}
+
+/// `85.2 MB/s`, `640 KB/s`.
+fn format_rate(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1e6 {
+        format!("{:.1} MB/s", bytes_per_sec / 1e6)
+    } else {
+        format!("{:.0} KB/s", bytes_per_sec / 1e3)
+    }
+}
+
+fn format_ms(d: Duration) -> String {
+    format!("{:.1}", d.as_secs_f64() * 1000.0)
+}
+
+fn report(channel: &Measurement, forward: &Measurement, bytes: u64) -> String {
+    let line = |label: &str, latency: &str, upload: &str, download: &str| {
+        format!("  {label:<15}{latency:>16}  {upload:>12}  {download:>12}")
+    };
+    let row = |label: &str, m: &Measurement| {
+        let latency = format!(
+            "{} / {} ms",
+            format_ms(m.latency_median),
+            format_ms(m.latency_max)
+        );
+        line(
+            label,
+            &latency,
+            &format_rate(m.upload),
+            &format_rate(m.download),
+        )
+    };
+    let slow = forward.upload < channel.upload * SLOW_FORWARD
+        || forward.download < channel.download * SLOW_FORWARD;
+    let verdict = if slow {
+        "The local forward is noticeably slower than the bare SSH channel: \
+         check [tunnel] buffer_size and local load."
+    } else {
+        "The local forward keeps up with the SSH channel; the limit is the \
+         network or the SSH server."
+    };
+    format!(
+        "{ROUND_TRIPS} round trips, {} MB each way\n{}\n{}\n{}\n\n{verdict}",
+        bytes / 1_000_000,
+        line("", "latency med/max", "upload", "download"),
+        row("SSH channel", channel),
+        row("local forward", forward),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(upload_mb: f64, download_mb: f64) -> Measurement {
+        Measurement {
+            latency_median: Duration::from_micros(12_340),
+            latency_max: Duration::from_millis(30),
+            upload: upload_mb * 1e6,
+            download: download_mb * 1e6,
+        }
+    }
+
+    #[test]
+    fn report_compares_paths() {
+        let channel = measurement(85.0, 90.0);
+        let out = report(&channel, &measurement(84.0, 0.5), 16_000_000);
+        assert!(out.starts_with("50 round trips, 16 MB each way\n"));
+        assert!(out.contains("  SSH channel      12.3 / 30.0 ms     85.0 MB/s     90.0 MB/s"));
+        assert!(out.contains("500 KB/s"));
+        assert!(out.ends_with("check [tunnel] buffer_size and local load."));
+
+        let out = report(&channel, &measurement(80.0, 88.0), 16_000_000);
+        assert!(out.contains("keeps up"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Broadcast rules (`[[broadcast]]` in the config): remote ports forwarded
+//! automatically on every connected host as soon as a scan shows them.
+//!
+//! ```toml
+//! [[broadcast]]
+//! ports = [5432, "8000-8099"]
+//!
+//! [[broadcast]]
+//! processes = ["node", "python*"]
+//! ```
+//!
+//! A rule with both lists needs a match in each. Like group forwards (`A`),
+//! the `i`-th host forwards on local port `remote + i * port_offset`, and
+//! `[policy]` still applies. A port is handled once per appearance: stopping
+//! the forward keeps it stopped until the port disappears and comes back.
+
+use serde::Deserialize;
+use sshfwd_common::types::ListeningPort;
+
+use crate::policy::PortRange;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BroadcastRule {
+    pub ports: Vec<PortRange>,
+    /// Process names as shown in the table; `*` matches any run of characters.
+    pub processes: Vec<String>,
+}
+
+impl BroadcastRule {
+    fn matches(&self, port: &ListeningPort) -> bool {
+        if self.ports.is_empty() && self.processes.is_empty() {
+            return false;
+        }
+        let port_ok = self.ports.is_empty() || self.ports.iter().any(|r| r.contains(port.port));
+        let process_ok = self.processes.is_empty()
+            || port
+                .process
+                .as_ref()
+                .is_some_and(|p| self.processes.iter().any(|pat| glob(pat, &p.name)));
+        port_ok && process_ok
+    }
+}
+
+/// Whether any of `rules` wants `port` forwarded.
+pub fn matches(rules: &[BroadcastRule], port: &ListeningPort) -> bool {
+    rules.iter().any(|rule| rule.matches(port))
+}
+
+/// `*`-only glob: `python*` matches `python3`, `*sql*` matches `mysqld`.
+fn glob(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: exact match.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sshfwd_common::types::{ProcessInfo, Protocol};
+
+    fn port(port: u16, process: Option<&str>) -> ListeningPort {
+        ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: "127.0.0.1".into(),
+            port,
+            process: process.map(|name| ProcessInfo {
+                pid: 1,
+                name: name.into(),
+                cmdline: name.into(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            }),
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn rules_match_ports_and_process_patterns() {
+        #[derive(Deserialize)]
+        struct Config {
+            broadcast: Vec<BroadcastRule>,
+        }
+        let rules = toml::from_str::<Config>(
+            r#"
+            [[broadcast]]
+            ports = [5432, "8000-8099"]
+
+            [[broadcast]]
+            ports = ["3000-3999"]
+            processes = ["node", "python*"]
+            "#,
+        )
+        .unwrap()
+        .broadcast;
+
+        assert!(matches(&rules, &port(5432, None)));
+        assert!(matches(&rules, &port(8080, Some("java"))));
+        assert!(matches(&rules, &port(3000, Some("python3"))));
+        assert!(!matches(&rules, &port(3000, Some("ruby"))));
+        assert!(!matches(&rules, &port(3000, None)));
+        assert!(!matches(&[BroadcastRule::default()], &port(5432, None)));
+
+        assert!(glob("*sql*", "mysqld"));
+        assert!(glob("node", "node"));
+        assert!(!glob("node", "nodejs"));
+        assert!(!glob("a*a", "a"));
+    }
+}
@@ -0,0 +1,28 @@
+//! Opening forwarded web services in the user's browser (`[browser]`).
+
+use std::io;
+use std::process::{Command, Stdio};
+
+/// Open `url` with the desktop's handler (`open` on macOS, `xdg-open`
+/// elsewhere) without waiting for it. Over SSH there is no desktop here to
+/// open it on.
+pub fn open(url: &str) -> io::Result<()> {
+    if std::env::var_os("SSH_CONNECTION").is_some() {
+        return Err(io::Error::other("sshfwd runs over SSH, no browser here"));
+    }
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let mut child = Command::new(program)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("{program}: {e}")))?;
+    // Reap it so the opener doesn't linger as a zombie.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Copying text to the user's clipboard: the desktop's own tool when sshfwd
+//! runs on the user's machine, otherwise an OSC 52 escape sequence, which
+//! the terminal applies on the user's side through SSH and tmux.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use sshfwd_common::base64;
+
+/// How text reaches the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Pbcopy,
+    WlCopy,
+    Xclip,
+    Xsel,
+    /// The terminal's clipboard escape sequence; only terminals that allow
+    /// it (and tmux with `allow-passthrough`) act on it.
+    Osc52,
+}
+
+impl Method {
+    /// The one to use here: over SSH a desktop tool would fill the remote
+    /// machine's clipboard, so that is always OSC 52.
+    pub fn detect() -> Self {
+        detect(|name| std::env::var_os(name).is_some(), on_path)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Pbcopy => "pbcopy",
+            Method::WlCopy => "wl-copy",
+            Method::Xclip => "xclip",
+            Method::Xsel => "xsel",
+            Method::Osc52 => "OSC 52",
+        }
+    }
+
+    fn command(self) -> Option<Command> {
+        let (program, args): (&str, &[&str]) = match self {
+            Method::Pbcopy => ("pbcopy", &[]),
+            Method::WlCopy => ("wl-copy", &[]),
+            Method::Xclip => ("xclip", &["-selection", "clipboard"]),
+            Method::Xsel => ("xsel", &["--clipboard", "--input"]),
+            Method::Osc52 => return None,
+        };
+        let mut command = Command::new(program);
+        command.args(args);
+        Some(command)
+    }
+}
+
+fn detect(env: impl Fn(&str) -> bool, installed: impl Fn(&str) -> bool) -> Method {
+    if env("SSH_CONNECTION") || env("SSH_TTY") {
+        return Method::Osc52;
+    }
+    let candidates = [
+        (cfg!(target_os = "macos"), Method::Pbcopy),
+        (env("WAYLAND_DISPLAY"), Method::WlCopy),
+        (env("DISPLAY"), Method::Xclip),
+        (env("DISPLAY"), Method::Xsel),
+    ];
+    candidates
+        .into_iter()
+        .find(|(usable, method)| *usable && installed(method.name()))
+        .map_or(Method::Osc52, |(_, method)| method)
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Copy `text`, returning the method that took it. A desktop tool that
+/// fails falls back to OSC 52.
+pub fn copy(text: &str) -> io::Result<Method> {
+    let method = Method::detect();
+    if let Some(mut command) = method.command() {
+        if run(&mut command, text).is_ok() {
+            return Ok(method);
+        }
+    }
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(osc52(text, std::env::var_os("TMUX").is_some()).as_bytes())?;
+    stdout.flush()?;
+    Ok(Method::Osc52)
+}
+
+fn run(command: &mut Command, text: &str) -> io::Result<()> {
+    // xclip and wl-copy stay behind to own the selection; keep them off the
+    // terminal.
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("piped stdin")
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {status}",
+            Path::new(command.get_program()).display()
+        )))
+    }
+}
+
+/// The OSC 52 "set clipboard" sequence for `text`, wrapped for tmux to pass
+/// through to the outer terminal when `tmux` is set.
+fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64::encode(text.as_bytes()));
+    if tmux {
+        // Escapes inside a DCS passthrough are doubled.
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssh_sessions_use_osc52_and_desktops_their_tool() {
+        let env = |vars: &'static [&'static str]| move |name: &str| vars.contains(&name);
+        let all = |_: &str| true;
+
+        assert_eq!(detect(env(&["SSH_TTY", "DISPLAY"]), all), Method::Osc52);
+        if !cfg!(target_os = "macos") {
+            assert_eq!(detect(env(&["WAYLAND_DISPLAY"]), all), Method::WlCopy);
+            assert_eq!(
+                detect(env(&["DISPLAY"]), |name: &str| name == "xsel"),
+                Method::Xsel
+            );
+            assert_eq!(detect(env(&["DISPLAY"]), |_: &str| false), Method::Osc52);
+            assert_eq!(detect(env(&[]), all), Method::Osc52);
+        }
+    }
+
+    #[test]
+    fn osc52_sequences() {
+        assert_eq!(
+            osc52("http://127.0.0.1:8080/", false),
+            "\x1b]52;c;aHR0cDovLzEyNy4wLjAuMTo4MDgwLw==\x07"
+        );
+        assert_eq!(osc52("a", true), "\x1bPtmux;\x1b\x1b]52;c;YQ==\x07\x1b\\");
+    }
+}
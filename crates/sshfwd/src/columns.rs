@@ -0,0 +1,163 @@
+//! Port table columns from the `[table]` config section.
+//!
+//! ```toml
+//! [table]
+//! columns = ["fwd", "port", "bind", "pid", "command"]
+//!
+//! [table.width]
+//! pid = { max = 7 }
+//! command = { min = 40 }
+//! ```
+//!
+//! `columns` picks which columns show and in what order (default: all). Each
+//! column is as wide as its widest cell on screen, header included, within
+//! its `min` and `max`; a column without a `max` (by default only COMMAND)
+//! also takes whatever width is left.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use sshfwd_common::types::ListeningPort;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Fwd,
+    Port,
+    /// Where the port listens: `lo` for loopback only, `*` for every
+    /// address, else the address.
+    Bind,
+    Proto,
+    Pid,
+    Command,
+}
+
+impl Column {
+    pub const ALL: [Column; 6] = [
+        Column::Fwd,
+        Column::Port,
+        Column::Bind,
+        Column::Proto,
+        Column::Pid,
+        Column::Command,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Fwd => "FWD",
+            Column::Port => "PORT",
+            Column::Bind => "BIND",
+            Column::Proto => "PROTO",
+            Column::Pid => "PID",
+            Column::Command => "COMMAND",
+        }
+    }
+
+    /// Room for the longest usual cell: `||<-:65535`, a five digit port, an
+    /// IPv4 address, `unix`, a 7 digit pid and its `#`.
+    fn default_width(self) -> ColumnWidth {
+        let (min, max) = match self {
+            Column::Fwd => (3, Some(10)),
+            Column::Port => (4, Some(5)),
+            Column::Bind => (4, Some(15)),
+            Column::Proto => (5, Some(5)),
+            Column::Pid => (3, Some(8)),
+            Column::Command => (20, None),
+        };
+        ColumnWidth {
+            min: Some(min),
+            max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnWidth {
+    pub min: Option<u16>,
+    pub max: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableConfig {
+    pub columns: Option<Vec<Column>>,
+    /// Bounds by column; unset ones keep the column's default.
+    pub width: HashMap<Column, ColumnWidth>,
+}
+
+impl TableConfig {
+    /// The columns to show, in order, each once.
+    pub fn shown(&self) -> Vec<Column> {
+        let Some(columns) = self.columns.as_ref().filter(|c| !c.is_empty()) else {
+            return Column::ALL.to_vec();
+        };
+        let mut shown = Vec::new();
+        for &column in columns {
+            if !shown.contains(&column) {
+                shown.push(column);
+            }
+        }
+        shown
+    }
+
+    /// Width of `column` for a widest cell of `widest`: within its bounds,
+    /// `max` winning over a larger `min`. `true` when it may grow further:
+    /// then only its `min` is held, so an overlong cell can't squeeze the
+    /// other columns.
+    pub fn fit(&self, column: Column, widest: u16) -> (u16, bool) {
+        let default = column.default_width();
+        let set = self.width.get(&column).copied().unwrap_or_default();
+        let min = set.min.or(default.min).unwrap_or(0);
+        let max = set.max.or(default.max);
+        match max {
+            Some(max) => (widest.max(min).min(max), false),
+            None => (min, true),
+        }
+    }
+}
+
+/// The BIND column: `lo` when only the remote host can reach the port (what
+/// a forward is for), `*` when it listens on every address.
+pub fn bind_cell(port: &ListeningPort) -> String {
+    let wildcard = |addr: &str| {
+        addr == "*"
+            || addr
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_unspecified())
+    };
+    if port.protocol.is_unix() {
+        "-".to_string()
+    } else if port.is_loopback_only() {
+        "lo".to_string()
+    } else if port.bindings().iter().any(|b| wildcard(&b.addr)) {
+        "*".to_string()
+    } else {
+        port.local_addr.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shown_columns_and_fitted_widths() {
+        let config: TableConfig = toml::from_str(
+            "columns = [\"pid\", \"command\", \"pid\"]\n\
+             width.pid = { max = 6 }\n\
+             width.command = { min = 40, max = 30 }\n",
+        )
+        .unwrap();
+        assert_eq!(config.shown(), [Column::Pid, Column::Command]);
+        assert_eq!(config.fit(Column::Pid, 8), (6, false));
+        assert_eq!(config.fit(Column::Pid, 1), (3, false));
+        assert_eq!(config.fit(Column::Command, 12), (30, false));
+
+        let default = TableConfig::default();
+        assert_eq!(default.shown(), Column::ALL);
+        assert_eq!(default.fit(Column::Port, 4), (4, false));
+        assert_eq!(default.fit(Column::Command, 60), (20, true));
+        assert!(toml::from_str::<TableConfig>("columns = [\"user\"]").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Side-by-side comparison of two hosts' remote ports (`C` with several
+//! hosts open), aligned by port number.
+
+use std::collections::BTreeMap;
+
+use sshfwd_common::types::ListeningPort;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    /// Listening on both, owned by the same process name.
+    Same,
+    /// Listening on both, owned by different (or unknown) processes.
+    Process,
+    LeftOnly,
+    RightOnly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompareRow {
+    pub port: u16,
+    /// `Some("")` when the port listens but its owner is unknown.
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub difference: Difference,
+}
+
+/// Owning process name per port (the first one, for ports bound twice).
+fn by_port(ports: &[ListeningPort]) -> BTreeMap<u16, String> {
+    let mut map = BTreeMap::new();
+    for port in ports {
+        map.entry(port.port).or_insert_with(|| {
+            port.process
+                .as_ref()
+                .map(|p| p.name.clone())
+                .unwrap_or_default()
+        });
+    }
+    map
+}
+
+/// Every port listening on either side, in port order.
+pub fn compare(left: &[ListeningPort], right: &[ListeningPort]) -> Vec<CompareRow> {
+    let mut left = by_port(left);
+    let mut right = by_port(right);
+    let mut ports: Vec<u16> = left.keys().chain(right.keys()).copied().collect();
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+        .into_iter()
+        .map(|port| {
+            let (l, r) = (left.remove(&port), right.remove(&port));
+            let difference = match (&l, &r) {
+                (Some(a), Some(b)) if a == b && !a.is_empty() => Difference::Same,
+                (Some(_), Some(_)) => Difference::Process,
+                (Some(_), None) => Difference::LeftOnly,
+                _ => Difference::RightOnly,
+            };
+            CompareRow {
+                port,
+                left: l,
+                right: r,
+                difference,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{listening_port, process_info};
+
+    fn port(port: u16, process: Option<&str>) -> ListeningPort {
+        ListeningPort {
+            local_addr: "0.0.0.0".into(),
+            process: process.map(|name| process_info(1, name)),
+            ..listening_port(port)
+        }
+    }
+
+    #[test]
+    fn ports_align_and_differences_are_flagged() {
+        let canary = [
+            port(22, Some("sshd")),
+            port(8080, Some("node")),
+            port(9100, None),
+        ];
+        let prod = [
+            port(22, Some("sshd")),
+            port(5432, Some("postgres")),
+            port(8080, Some("java")),
+            port(9100, None),
+        ];
+        let rows = compare(&canary, &prod);
+        let summary: Vec<(u16, Difference)> = rows.iter().map(|r| (r.port, r.difference)).collect();
+        assert_eq!(
+            summary,
+            [
+                (22, Difference::Same),
+                (5432, Difference::RightOnly),
+                (8080, Difference::Process),
+                (9100, Difference::Process),
+            ]
+        );
+        assert_eq!(rows[1].left, None);
+        assert_eq!(rows[2].left.as_deref(), Some("node"));
+    }
+}
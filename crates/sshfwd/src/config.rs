@@ -0,0 +1,580 @@
+//! User configuration from `~/.sshfwd/config.toml`.
+//!
+//! ```toml
+//! [agent]
+//! remote_dir = "/opt/tools/sshfwd"
+//! name = "portscan-agent"
+//! sudo = true
+//! download = true
+//!
+//! [discovery]
+//! scan_interval = 5
+//!
+//! [hosts."deploy@prod-1"]
+//! color = "red"
+//!
+//! [hosts."deploy@prod-1".agent]
+//! remote_dir = "/srv/scratch/sshfwd"
+//!
+//! [hosts."deploy@prod-1".discovery]
+//! timeout = 60
+//!
+//! [group.staging]
+//! hosts = ["web1", "web2", "db1"]
+//!
+//! [[broadcast]]
+//! ports = [5432]
+//!
+//! [policy]
+//! deny_ports = [22, "3306-3307"]
+//!
+//! [audit]
+//! enabled = true
+//!
+//! [hooks]
+//! on_forward_started = "notify-send \"$HOST:$PORT on localhost:$LOCAL_PORT\""
+//!
+//! [sharing]
+//! bind_address = "0.0.0.0"
+//! allow_peers = ["192.168.1.0/24"]
+//!
+//! [ssh]
+//! backend = "system"
+//!
+//! [tunnel]
+//! keepalive = 60
+//!
+//! [table]
+//! columns = ["fwd", "port", "pid", "command"]
+//!
+//! [browser]
+//! open_http = true
+//!
+//! [hosts."deploy@prod-1".tunnel]
+//! nodelay = true
+//! ```
+//!
+//! Per-host sections override the global ones field by field; command-line
+//! flags override both.
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+use sshfwd_common::frame::Framing;
+
+use crate::broadcast::BroadcastRule;
+use crate::columns::TableConfig;
+use crate::discovery::DiscoveryTiming;
+use crate::forward::access::{ListenOptions, PeerRule};
+use crate::forward::pool::DEFAULT_MAX_OPENS;
+use crate::forward::tuning::{TunnelOptions, DEFAULT_BUFFER_SIZE};
+use crate::policy::Policy;
+use crate::ssh::session::Backend;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub agent: AgentConfig,
+    pub discovery: DiscoveryConfig,
+    /// Which remote ports may be forwarded (see `policy`).
+    pub policy: Policy,
+    /// Global only: one audit trail covers every destination.
+    pub audit: AuditConfig,
+    /// Exposing local forwards beyond loopback (global only).
+    pub sharing: SharingConfig,
+    /// Span export for diagnosing latency (global only).
+    pub telemetry: TelemetryConfig,
+    /// Commands run on port and forward events (global only).
+    pub hooks: HooksConfig,
+    /// Local REST API (global only).
+    pub api: ApiConfig,
+    /// SSH connection handling (global only).
+    pub ssh: SshConfig,
+    /// Socket options and copy buffers of tunneled connections.
+    pub tunnel: TunnelConfig,
+    /// Keyed by destination exactly as passed on the command line.
+    pub hosts: HashMap<String, HostConfig>,
+    /// Named sets of destinations, opened with `sshfwd @<name>`.
+    pub group: HashMap<String, GroupConfig>,
+    /// Ports forwarded on every connected host (global only).
+    pub broadcast: Vec<BroadcastRule>,
+    /// Port table columns and their widths (global only).
+    pub table: TableConfig,
+    /// Opening forwarded web services (global only).
+    pub browser: BrowserConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupConfig {
+    pub hosts: Vec<String>,
+    /// Group forwards (`A`) use local port `remote + i * port_offset` on the
+    /// `i`-th host (default 1000).
+    pub port_offset: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HostConfig {
+    /// Accent for this host's header and table (`"red"`, `"#ff8800"`, ...);
+    /// picked from the destination name when unset.
+    pub color: Option<String>,
+    pub agent: AgentConfig,
+    pub discovery: DiscoveryConfig,
+    pub policy: Policy,
+    pub tunnel: TunnelConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Remote directory for the agent binary, pid file and log (default `~/.sshfwd`).
+    pub remote_dir: Option<String>,
+    /// Remote binary name (default `sshfwd-agent`).
+    pub name: Option<String>,
+    /// Run the agent through `sudo`/`doas` (default false).
+    pub sudo: Option<bool>,
+    /// Download missing agents from GitHub releases without asking (default false).
+    pub download: Option<bool>,
+    /// Only report ports owned by the SSH user (default false).
+    pub only_mine: Option<bool>,
+    /// Confine the agent with a seccomp filter (Linux, default false).
+    pub seccomp: Option<bool>,
+    /// How the agent frames its output: `"json"` lines (default) or
+    /// `"msgpack"` frames, smaller and robust to stray output.
+    pub framing: Option<Framing>,
+    /// Accept agents with a newer protocol schema instead of asking for an
+    /// update (default false).
+    pub lenient_schema: Option<bool>,
+    /// Flag/variable names whose values are masked in command lines
+    /// (replaces the agent's defaults; `[]` disables them).
+    pub redact: Option<Vec<String>>,
+    /// Nice value the agent runs at (default unchanged).
+    pub nice: Option<i32>,
+    /// Milliseconds a scan may take before the agent skips process lookups
+    /// and probes for a while (default unlimited).
+    pub scan_budget: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Record forwards and connections to the audit log (default false).
+    pub enabled: bool,
+    /// Audit log file (default `~/.sshfwd/audit.jsonl`).
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserConfig {
+    /// Open `http://127.0.0.1:<local>/` when a forward you start of a port
+    /// the probe saw speak HTTP comes up (default false).
+    pub open_http: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector, e.g. `http://localhost:4318` (needs the `otel` feature).
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Serve the API on `127.0.0.1:<port>` (off when unset).
+    pub port: Option<u16>,
+    /// Bearer token every request must carry; required with `port`.
+    pub token: Option<String>,
+}
+
+/// Shell commands started on events; see `hooks` for their environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub on_port_appeared: Option<String>,
+    pub on_port_disappeared: Option<String>,
+    pub on_forward_started: Option<String>,
+    pub on_forward_stopped: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SshConfig {
+    /// Share each connection with later sshfwd processes for the same
+    /// destination, and attach to an already shared one (default false:
+    /// attached processes can't reverse-forward).
+    pub mux: Option<bool>,
+    /// `russh` (built in, the default) or `system` (the `ssh` binary).
+    pub backend: Option<Backend>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SharingConfig {
+    /// Address local forwards listen on (default `127.0.0.1`).
+    pub bind_address: Option<IpAddr>,
+    /// IPs or CIDR blocks allowed to connect from other machines.
+    pub allow_peers: Vec<PeerRule>,
+    /// HTTP Bearer token (or Basic password) required from other machines.
+    pub token: Option<String>,
+    /// Announce active local forwards as mDNS services (default false).
+    pub mdns: bool,
+}
+
+impl SharingConfig {
+    pub fn listen_options(&self) -> ListenOptions {
+        ListenOptions {
+            bind_address: self.bind_address,
+            allow_peers: self.allow_peers.clone(),
+            token: self.token.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TunnelConfig {
+    /// Bytes copied per read in each direction (default 8192); larger
+    /// helps bulk transfers.
+    pub buffer_size: Option<usize>,
+    /// Disable Nagle's algorithm on local sockets, for latency-sensitive
+    /// protocols like databases (default false).
+    pub nodelay: Option<bool>,
+    /// Seconds idle before TCP keepalive probes on local sockets (off when
+    /// unset).
+    pub keepalive: Option<u64>,
+    /// SSH channel opens in flight at once per host; further connections
+    /// queue (default 16).
+    pub max_opens: Option<usize>,
+}
+
+impl TunnelConfig {
+    pub fn options(&self) -> TunnelOptions {
+        TunnelOptions {
+            buffer_size: self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE),
+            nodelay: self.nodelay.unwrap_or(false),
+            keepalive: self.keepalive.map(Duration::from_secs),
+            max_opens: self.max_opens.unwrap_or(DEFAULT_MAX_OPENS),
+        }
+    }
+
+    /// Fill unset fields from `fallback`.
+    fn or(self, fallback: &TunnelConfig) -> TunnelConfig {
+        TunnelConfig {
+            buffer_size: self.buffer_size.or(fallback.buffer_size),
+            nodelay: self.nodelay.or(fallback.nodelay),
+            keepalive: self.keepalive.or(fallback.keepalive),
+            max_opens: self.max_opens.or(fallback.max_opens),
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Fill unset fields from `fallback`.
+    fn or(self, fallback: &AgentConfig) -> AgentConfig {
+        AgentConfig {
+            remote_dir: self.remote_dir.or_else(|| fallback.remote_dir.clone()),
+            name: self.name.or_else(|| fallback.name.clone()),
+            sudo: self.sudo.or(fallback.sudo),
+            download: self.download.or(fallback.download),
+            only_mine: self.only_mine.or(fallback.only_mine),
+            seccomp: self.seccomp.or(fallback.seccomp),
+            framing: self.framing.or(fallback.framing),
+            lenient_schema: self.lenient_schema.or(fallback.lenient_schema),
+            redact: self.redact.or_else(|| fallback.redact.clone()),
+            nice: self.nice.or(fallback.nice),
+            scan_budget: self.scan_budget.or(fallback.scan_budget),
+        }
+    }
+}
+
+/// Scan cadence and how long silence is tolerated. Unset thresholds are
+/// derived from the scan interval (see `DiscoveryTiming`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    /// Seconds between agent scans (default 2).
+    pub scan_interval: Option<u64>,
+    /// Seconds without a scan before the UI marks discovery stale (default 3 scans).
+    pub stale_after: Option<u64>,
+    /// Seconds without agent output before the agent is respawned (default 6 scans).
+    pub timeout: Option<u64>,
+    /// Respawns without a scan in between before reconnecting (default 3).
+    pub max_respawns: Option<u32>,
+    /// Also scan the ProxyJump hops in front of the host, each in its own
+    /// tab (default false).
+    pub jump_hosts: Option<bool>,
+}
+
+impl DiscoveryConfig {
+    pub fn timing(&self) -> DiscoveryTiming {
+        DiscoveryTiming::from_secs(
+            self.scan_interval,
+            self.stale_after,
+            self.timeout,
+            self.max_respawns,
+        )
+    }
+
+    /// Fill unset fields from `fallback`.
+    fn or(self, fallback: &DiscoveryConfig) -> DiscoveryConfig {
+        DiscoveryConfig {
+            scan_interval: self.scan_interval.or(fallback.scan_interval),
+            stale_after: self.stale_after.or(fallback.stale_after),
+            timeout: self.timeout.or(fallback.timeout),
+            max_respawns: self.max_respawns.or(fallback.max_respawns),
+            jump_hosts: self.jump_hosts.or(fallback.jump_hosts),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file. A missing file is an empty config; a malformed one
+    /// is an error so typos don't silently fall back to defaults.
+    pub fn load() -> Result<Config, String> {
+        let path = config_path();
+        match fs::read_to_string(&path) {
+            Ok(data) => Self::parse(&data).map_err(|e| format!("{}: {e}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    fn parse(data: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(data)
+    }
+
+    /// Replace each `@group` in `destinations` by its hosts, dropping
+    /// duplicates. Also returns the port offset of the first group named.
+    pub fn expand_groups(
+        &self,
+        destinations: &[String],
+    ) -> Result<(Vec<String>, Option<u16>), String> {
+        let mut expanded: Vec<String> = Vec::new();
+        let mut port_offset = None;
+        for destination in destinations {
+            let members = match destination.strip_prefix('@') {
+                Some(name) => {
+                    let group = self
+                        .group
+                        .get(name)
+                        .filter(|g| !g.hosts.is_empty())
+                        .ok_or_else(|| format!("no hosts in [group.{name}]"))?;
+                    port_offset = port_offset.or(group.port_offset);
+                    group.hosts.clone()
+                }
+                None => vec![destination.clone()],
+            };
+            for member in members {
+                if !expanded.contains(&member) {
+                    expanded.push(member);
+                }
+            }
+        }
+        Ok((expanded, port_offset))
+    }
+
+    /// Agent settings for `destination`, host overrides applied.
+    pub fn agent_for(&self, destination: &str) -> AgentConfig {
+        match self.hosts.get(destination) {
+            Some(host) => host.agent.clone().or(&self.agent),
+            None => self.agent.clone(),
+        }
+    }
+
+    /// Discovery settings for `destination`, host overrides applied.
+    pub fn discovery_for(&self, destination: &str) -> DiscoveryConfig {
+        match self.hosts.get(destination) {
+            Some(host) => host.discovery.clone().or(&self.discovery),
+            None => self.discovery.clone(),
+        }
+    }
+
+    /// Tunnel settings for `destination`, host overrides applied.
+    pub fn tunnel_for(&self, destination: &str) -> TunnelConfig {
+        match self.hosts.get(destination) {
+            Some(host) => host.tunnel.clone().or(&self.tunnel),
+            None => self.tunnel.clone(),
+        }
+    }
+
+    /// Configured accent color name for `destination`.
+    pub fn color_for(&self, destination: &str) -> Option<&str> {
+        self.hosts.get(destination)?.color.as_deref()
+    }
+
+    /// Forwarding policy for `destination`; a host list replaces the global one.
+    pub fn policy_for(&self, destination: &str) -> Policy {
+        match self.hosts.get(destination) {
+            Some(host) => host.policy.clone().or(&self.policy),
+            None => self.policy.clone(),
+        }
+    }
+}
+
+fn config_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home).join(".sshfwd").join("config.toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_overrides_global_per_field() {
+        let config = Config::parse(
+            r#"
+            [agent]
+            remote_dir = "/opt/sshfwd"
+            name = "portscan-agent"
+
+            [hosts."deploy@prod-1".agent]
+            remote_dir = "/srv/scratch"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.agent_for("deploy@prod-1"),
+            AgentConfig {
+                remote_dir: Some("/srv/scratch".into()),
+                name: Some("portscan-agent".into()),
+                sudo: None,
+                download: None,
+                only_mine: None,
+                seccomp: None,
+                framing: None,
+                lenient_schema: None,
+                redact: None,
+                nice: None,
+                scan_budget: None,
+            }
+        );
+        assert_eq!(
+            config.agent_for("other").remote_dir.as_deref(),
+            Some("/opt/sshfwd")
+        );
+    }
+
+    #[test]
+    fn empty_and_unknown_keys() {
+        assert_eq!(
+            Config::parse("").unwrap().agent_for("x"),
+            AgentConfig::default()
+        );
+        assert!(Config::parse("[agent]\nremote_dri = \"/x\"\n").is_err());
+        assert_eq!(
+            Config::parse("[ssh]\nbackend = \"system\"\n")
+                .unwrap()
+                .ssh
+                .backend,
+            Some(Backend::System)
+        );
+        assert!(Config::parse("[ssh]\nbackend = \"openssh\"\n").is_err());
+    }
+
+    #[test]
+    fn discovery_host_override() {
+        let config = Config::parse(
+            r#"
+            [discovery]
+            scan_interval = 5
+            max_respawns = 1
+
+            [hosts.slow.discovery]
+            timeout = 60
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.discovery_for("slow"),
+            DiscoveryConfig {
+                scan_interval: Some(5),
+                stale_after: None,
+                timeout: Some(60),
+                max_respawns: Some(1),
+                jump_hosts: None,
+            }
+        );
+        assert_eq!(config.discovery_for("other").timeout, None);
+    }
+
+    #[test]
+    fn tunnel_host_override() {
+        let config = Config::parse(
+            r#"
+            [tunnel]
+            buffer_size = 65536
+            keepalive = 60
+            max_opens = 4
+
+            [hosts.db.tunnel]
+            nodelay = true
+            buffer_size = 4096
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.tunnel_for("db").options(),
+            TunnelOptions {
+                buffer_size: 4096,
+                nodelay: true,
+                keepalive: Some(Duration::from_secs(60)),
+                max_opens: 4,
+            }
+        );
+        assert!(!config.tunnel_for("files").options().nodelay);
+        assert_eq!(config.tunnel_for("files").options().buffer_size, 65536);
+        assert_eq!(Config::default().tunnel.options(), TunnelOptions::default());
+    }
+
+    #[test]
+    fn policy_host_list_replaces_global() {
+        let config = Config::parse(
+            r#"
+            [policy]
+            deny_ports = [22]
+            allow_processes = ["node"]
+
+            [hosts.db.policy]
+            allow_processes = ["postgres"]
+            "#,
+        )
+        .unwrap();
+
+        let db = config.policy_for("db");
+        assert!(db.check(5432, Some("postgres"), false).is_ok());
+        assert!(db.check(22, Some("postgres"), false).is_err());
+        assert!(config
+            .policy_for("other")
+            .check(5432, Some("postgres"), false)
+            .is_err());
+    }
+
+    #[test]
+    fn groups_expand_in_place() {
+        let config = Config::parse(
+            r#"
+            [group.staging]
+            hosts = ["web1", "web2", "db1"]
+            port_offset = 100
+            "#,
+        )
+        .unwrap();
+
+        let args = ["db1".to_string(), "@staging".to_string(), "ops".to_string()];
+        let (hosts, offset) = config.expand_groups(&args).unwrap();
+        assert_eq!(hosts, ["db1", "web1", "web2", "ops"]);
+        assert_eq!(offset, Some(100));
+        assert!(config.expand_groups(&["@prod".to_string()]).is_err());
+    }
+}
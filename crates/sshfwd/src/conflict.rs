@@ -0,0 +1,262 @@
+//! What to offer when a forward can't bind its port: who holds it (from the
+//! latest scan of that side) and the nearest ports that look free. The port
+//! modal also checks each value as it's typed (`PortCheck`).
+
+use std::collections::HashSet;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener};
+
+use sshfwd_common::types::ListeningPort;
+
+/// Free ports suggested after a failed bind.
+const SUGGESTIONS: usize = 3;
+/// Candidates tried on either side of the failed port before giving up.
+const SEARCH_RADIUS: u16 = 100;
+/// Privileged ports are suggested from here up (80 → 8080, 443 → 8443).
+const UNPRIVILEGED_OFFSET: u16 = 8000;
+
+/// A failed bind, as shown by the port modal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindConflict {
+    /// The process holding the port, when the scan knows it.
+    pub holder: Option<String>,
+    /// Nearest free ports, closest first.
+    pub suggestions: Vec<u16>,
+}
+
+impl BindConflict {
+    /// A local listener failed: `scanned` is the local scan, `taken` the
+    /// ports our own forwards use. Candidates are confirmed by binding them
+    /// on loopback.
+    pub fn local(
+        port: u16,
+        scanned: &[ListeningPort],
+        taken: impl IntoIterator<Item = u16>,
+    ) -> Self {
+        Self::new(port, scanned, taken, bindable)
+    }
+
+    /// A remote bind failed: `scanned` is the remote scan; there is nothing
+    /// to probe, so any port the scan doesn't list counts as free.
+    pub fn remote(
+        port: u16,
+        scanned: &[ListeningPort],
+        taken: impl IntoIterator<Item = u16>,
+    ) -> Self {
+        Self::new(port, scanned, taken, |_| true)
+    }
+
+    fn new(
+        port: u16,
+        scanned: &[ListeningPort],
+        taken: impl IntoIterator<Item = u16>,
+        probe: impl Fn(u16) -> bool,
+    ) -> Self {
+        let mut taken: HashSet<u16> = taken.into_iter().collect();
+        taken.extend(scanned.iter().map(|p| p.port));
+        taken.insert(port);
+        Self {
+            holder: holder(port, scanned),
+            suggestions: nearest_free(port, &taken, probe),
+        }
+    }
+}
+
+/// A port typed into the port modal, checked against the side the forward
+/// would listen on before anything tries to bind it.
+pub struct PortCheck<'a> {
+    scanned: &'a [ListeningPort],
+    taken: HashSet<u16>,
+    /// Test-bind the port: only possible on this machine.
+    probe: bool,
+}
+
+impl<'a> PortCheck<'a> {
+    /// A local listener: `scanned` is the local scan, `taken` the ports our
+    /// own forwards use.
+    pub fn local(scanned: &'a [ListeningPort], taken: impl IntoIterator<Item = u16>) -> Self {
+        Self {
+            scanned,
+            taken: taken.into_iter().collect(),
+            probe: true,
+        }
+    }
+
+    /// A remote bind: `scanned` is the remote scan; only the scan and our
+    /// forwards can tell it's taken.
+    pub fn remote(scanned: &'a [ListeningPort], taken: impl IntoIterator<Item = u16>) -> Self {
+        Self {
+            scanned,
+            taken: taken.into_iter().collect(),
+            probe: false,
+        }
+    }
+
+    /// The port in `buffer`, or why it won't do.
+    pub fn check(&self, buffer: &str) -> Result<u16, String> {
+        let port = match buffer.parse::<u32>() {
+            Ok(port @ 1..=65535) => port as u16,
+            _ if buffer.is_empty() => return Err("Enter a port".to_string()),
+            _ => return Err("Port must be 1-65535".to_string()),
+        };
+        if self.taken.contains(&port) {
+            return Err(format!("Port {port} is used by another forward"));
+        }
+        if self.scanned.iter().any(|p| p.port == port) {
+            return Err(match holder(port, self.scanned) {
+                Some(holder) => format!("Port {port} is in use by {holder}"),
+                None => format!("Port {port} is in use"),
+            });
+        }
+        if self.probe {
+            if let Err(e) = TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+                return Err(if e.kind() == io::ErrorKind::PermissionDenied {
+                    format!("Port {port} needs root")
+                } else {
+                    format!("Port {port} is in use")
+                });
+            }
+        }
+        Ok(port)
+    }
+
+    /// The first free port above `after`, wrapping around to 1024; `None`
+    /// when `SEARCH_RADIUS` candidates are all taken.
+    pub fn next_free(&self, after: u16) -> Option<u16> {
+        let after = after.max(1023);
+        (after..=u16::MAX)
+            .skip(1)
+            .chain(1024..=after)
+            .take(usize::from(SEARCH_RADIUS))
+            .find(|&port| self.check(&port.to_string()).is_ok())
+    }
+}
+
+fn holder(port: u16, scanned: &[ListeningPort]) -> Option<String> {
+    let process = scanned
+        .iter()
+        .filter(|p| p.port == port)
+        .find_map(|p| p.process.as_ref())?;
+    Some(format!("{} ({})", process.name, process.pid))
+}
+
+/// Ports around `port`, alternating above and below, that aren't `taken`
+/// and pass `probe`. Privileged ports search from `port + 8000` instead,
+/// and never suggest another privileged port.
+fn nearest_free(port: u16, taken: &HashSet<u16>, probe: impl Fn(u16) -> bool) -> Vec<u16> {
+    let center = if port < 1024 {
+        port + UNPRIVILEGED_OFFSET
+    } else {
+        port
+    };
+    let candidates = (0..=SEARCH_RADIUS).flat_map(|d| {
+        let above = center.checked_add(d);
+        let below = (d > 0).then(|| center.checked_sub(d)).flatten();
+        above.into_iter().chain(below)
+    });
+    candidates
+        .filter(|&p| p >= 1024 && !taken.contains(&p))
+        .filter(|&p| probe(p))
+        .take(SUGGESTIONS)
+        .collect()
+}
+
+fn bindable(port: u16) -> bool {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sshfwd_common::types::{ProcessInfo, Protocol};
+
+    fn listening(port: u16, name: Option<&str>) -> ListeningPort {
+        ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: "127.0.0.1".into(),
+            port,
+            process: name.map(|name| ProcessInfo {
+                pid: 42,
+                name: name.into(),
+                cmdline: name.into(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            }),
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn suggests_the_nearest_ports_nobody_holds() {
+        let scanned = [listening(8080, Some("node")), listening(8081, None)];
+        let conflict = BindConflict::remote(8080, &scanned, [8079]);
+        assert_eq!(conflict.holder.as_deref(), Some("node (42)"));
+        assert_eq!(conflict.suggestions, [8082, 8078, 8083]);
+
+        // Probing rules out ports the scan missed.
+        let conflict = BindConflict::new(8080, &[], [], |p| p % 2 == 1);
+        assert_eq!(conflict.holder, None);
+        assert_eq!(conflict.suggestions, [8081, 8079, 8083]);
+    }
+
+    #[test]
+    fn privileged_ports_suggest_unprivileged_ones() {
+        assert_eq!(
+            BindConflict::remote(80, &[], []).suggestions,
+            [8080, 8081, 8079]
+        );
+        assert_eq!(
+            BindConflict::remote(65535, &[], []).suggestions,
+            [65534, 65533, 65532]
+        );
+    }
+
+    #[test]
+    fn typed_ports_are_checked_before_binding() {
+        let scanned = [listening(8080, Some("node")), listening(8081, None)];
+        let check = PortCheck::remote(&scanned, [8082]);
+        assert_eq!(check.check("9000"), Ok(9000));
+        assert_eq!(check.check(""), Err("Enter a port".to_string()));
+        assert_eq!(check.check("0"), Err("Port must be 1-65535".to_string()));
+        assert_eq!(
+            check.check("70000"),
+            Err("Port must be 1-65535".to_string())
+        );
+        assert_eq!(
+            check.check("8080"),
+            Err("Port 8080 is in use by node (42)".to_string())
+        );
+        assert_eq!(check.check("8081"), Err("Port 8081 is in use".to_string()));
+        assert_eq!(
+            check.check("8082"),
+            Err("Port 8082 is used by another forward".to_string())
+        );
+        assert_eq!(check.next_free(8079), Some(8083));
+        assert_eq!(check.next_free(80), Some(1024));
+        assert_eq!(check.next_free(u16::MAX), Some(1024));
+
+        let held = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = held.local_addr().unwrap().port();
+        let check = PortCheck::local(&[], []);
+        assert_eq!(
+            check.check(&port.to_string()),
+            Err(format!("Port {port} is in use"))
+        );
+    }
+
+    #[test]
+    fn local_conflicts_probe_by_binding() {
+        let held = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = held.local_addr().unwrap().port();
+        let conflict = BindConflict::local(port, &[], []);
+        assert_eq!(conflict.suggestions.len(), SUGGESTIONS);
+        assert!(!conflict.suggestions.contains(&port));
+    }
+}
@@ -1,74 +1,82 @@
-pub mod local;
+use std::cmp::Ordering;
 
-use std::path::Path;
+use sshfwd_common::types::ListeningPort;
 
-use russh::client::Msg;
-use russh::ChannelStream;
-use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+pub use sshfwd_core::discovery::*;
 
-use sshfwd_common::types::{AgentResponse, ScanResult};
-
-use crate::error::DiscoveryError;
-use crate::ssh::agent::AgentManager;
-use crate::ssh::session::Session;
-
-/// Events produced by the discovery stream.
-#[derive(Debug)]
-pub enum DiscoveryEvent {
-    Scan(ScanResult),
-    Warning(String),
-    Error(DiscoveryError),
+/// Table order: port, then PID, then protocol (tcp, tcp6, udp, udp6). Unix
+/// sockets, which have no port, come last by path.
+fn port_order(a: &ListeningPort, b: &ListeningPort) -> Ordering {
+    let pid = |p: &ListeningPort| p.process.as_ref().map_or(0, |p| p.pid);
+    let unix = |p: &ListeningPort| p.protocol.is_unix();
+    unix(a)
+        .cmp(&unix(b))
+        .then_with(|| a.port.cmp(&b.port))
+        .then_with(|| pid(a).cmp(&pid(b)))
+        .then_with(|| a.protocol.cmp(&b.protocol))
+        .then_with(|| a.local_addr.cmp(&b.local_addr))
 }
 
-/// Active discovery session — reads agent stdout line by line.
-pub struct DiscoveryStream {
-    lines: Lines<BufReader<ChannelStream<Msg>>>,
-    _session: Session, // Keep the SSH connection alive
+/// Put scanned ports in table order. Discovery threads call this before
+/// sending a scan so the UI thread only pays for the `is_sorted` check.
+pub fn sort_ports(ports: &mut [ListeningPort]) {
+    if !ports.is_sorted_by(|a, b| port_order(a, b) != Ordering::Greater) {
+        ports.sort_by(port_order);
+    }
 }
 
-impl DiscoveryStream {
-    /// Deploy the agent and start the discovery stream.
-    ///
-    /// If `local_agent_path` is provided, uses that binary directly (development override).
-    /// Otherwise, uses embedded or prebuilt binaries.
-    pub async fn start(
-        session: Session,
-        local_agent_path: Option<&Path>,
-    ) -> Result<Self, DiscoveryError> {
-        let manager = AgentManager::new(session.clone());
-
-        let stream = manager
-            .deploy_and_spawn(local_agent_path)
-            .await
-            .map_err(DiscoveryError::Ssh)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sshfwd_common::types::ProcessInfo;
+    use sshfwd_common::types::Protocol;
 
-        let reader = BufReader::new(stream);
-        let lines = reader.lines();
-
-        Ok(Self {
-            lines,
-            _session: session,
-        })
+    fn port(port: u16, pid: Option<u32>, protocol: Protocol) -> ListeningPort {
+        ListeningPort {
+            port,
+            protocol,
+            local_addr: "0.0.0.0".into(),
+            process: pid.map(|pid| ProcessInfo {
+                pid,
+                name: "svc".into(),
+                cmdline: "svc".into(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            }),
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
+        }
     }
 
-    /// Read the next event from the agent stream.
-    /// Returns None when the stream is exhausted.
-    pub async fn next_event(&mut self) -> Option<DiscoveryEvent> {
-        match self.lines.next_line().await {
-            Ok(Some(line)) => match serde_json::from_str::<AgentResponse>(&line) {
-                Ok(AgentResponse::Ok(scan)) => Some(DiscoveryEvent::Scan(scan)),
-                Ok(AgentResponse::Error(e)) => {
-                    let msg = format!("agent error ({}): {}", e.kind, e.message);
-                    Some(DiscoveryEvent::Warning(msg))
-                }
-                Err(e) => Some(DiscoveryEvent::Error(DiscoveryError::Parse(format!(
-                    "{e}: {line}"
-                )))),
-            },
-            Ok(None) => Some(DiscoveryEvent::Error(DiscoveryError::StreamEnded)),
-            Err(e) => Some(DiscoveryEvent::Error(DiscoveryError::Parse(format!(
-                "I/O error: {e}"
-            )))),
-        }
+    #[test]
+    fn ports_sort_by_port_pid_protocol_with_unix_sockets_last() {
+        let mut ports = vec![
+            port(8080, Some(7), Protocol::Tcp6),
+            port(22, Some(1), Protocol::Tcp),
+            port(8080, Some(7), Protocol::Tcp),
+            port(0, Some(3), Protocol::Unix),
+            port(8080, None, Protocol::Tcp6),
+        ];
+        sort_ports(&mut ports);
+        let order: Vec<_> = ports
+            .iter()
+            .map(|p| (p.port, p.process.as_ref().map(|p| p.pid), p.protocol))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (22, Some(1), Protocol::Tcp),
+                (8080, None, Protocol::Tcp6),
+                (8080, Some(7), Protocol::Tcp),
+                (8080, Some(7), Protocol::Tcp6),
+                (0, Some(3), Protocol::Unix),
+            ]
+        );
     }
 }
@@ -0,0 +1,214 @@
+//! Point-in-time snapshots of the remote ports and forward state, as JSON
+//! (full detail) or CSV (one row per listening socket), for attaching to
+//! tickets. `s` in the TUI writes both under `~/.sshfwd/snapshots/`;
+//! `sshfwd snapshot <host>` prints one to stdout.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use sshfwd_common::time::{epoch_secs, format_utc};
+use sshfwd_common::types::ListeningPort;
+
+use crate::forward::{ForwardEntry, ForwardKey, ForwardKind, ForwardStatus};
+
+#[derive(Debug, Serialize)]
+pub struct Snapshot<'a> {
+    pub destination: &'a str,
+    pub hostname: Option<&'a str>,
+    pub username: Option<&'a str>,
+    pub scan_index: u64,
+    /// UTC, `YYYY-MM-DDTHH:MM:SSZ`.
+    pub taken_at: String,
+    pub ports: &'a [ListeningPort],
+    pub forwards: Vec<SnapshotForward>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotForward {
+    pub kind: ForwardKind,
+    pub remote_port: u16,
+    pub local_port: u16,
+    pub status: &'static str,
+    pub active_connections: u32,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(
+        destination: &'a str,
+        hostname: Option<&'a str>,
+        username: Option<&'a str>,
+        scan_index: u64,
+        ports: &'a [ListeningPort],
+        forwards: &HashMap<ForwardKey, ForwardEntry>,
+    ) -> Self {
+        let mut forwards: Vec<SnapshotForward> = forwards
+            .iter()
+            .map(|(key, entry)| SnapshotForward {
+                kind: key.kind,
+                remote_port: key.remote_port,
+                local_port: entry.local_port,
+                status: status_str(&entry.status),
+                active_connections: entry.active_connections,
+            })
+            .collect();
+        forwards.sort_by_key(|f| (f.kind == ForwardKind::Reverse, f.remote_port));
+        Self {
+            destination,
+            hostname,
+            username,
+            scan_index,
+            taken_at: format_utc(epoch_secs(SystemTime::now())),
+            ports,
+            forwards,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("snapshot serializes")
+    }
+
+    /// One row per listening socket; local forwards of the port are joined in.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "port,protocol,address,pid,process,uid,cmdline,forward_local_port,forward_status\n",
+        );
+        for port in self.ports {
+            let forward = self
+                .forwards
+                .iter()
+                .find(|f| f.kind == ForwardKind::Local && f.remote_port == port.port);
+            let process = port.process.as_ref();
+            let fields = [
+                port.port.to_string(),
+                port.protocol.to_string(),
+                port.local_addr.clone(),
+                process.map(|p| p.pid.to_string()).unwrap_or_default(),
+                process.map(|p| p.name.clone()).unwrap_or_default(),
+                process.map(|p| p.uid.to_string()).unwrap_or_default(),
+                process.map(|p| p.cmdline.clone()).unwrap_or_default(),
+                forward
+                    .map(|f| f.local_port.to_string())
+                    .unwrap_or_default(),
+                forward.map(|f| f.status.to_string()).unwrap_or_default(),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Write `<destination>-<time>.json` and `.csv` into `~/.sshfwd/snapshots/`.
+    /// Returns the JSON path.
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+        let dir = PathBuf::from(home).join(".sshfwd").join("snapshots");
+        fs::create_dir_all(&dir)?;
+        let stem = format!(
+            "{}-{}",
+            sanitize(self.destination),
+            self.taken_at.replace([':', '-'], "")
+        );
+        let json = dir.join(format!("{stem}.json"));
+        fs::write(&json, self.to_json())?;
+        fs::write(dir.join(format!("{stem}.csv")), self.to_csv())?;
+        Ok(json)
+    }
+}
+
+fn status_str(status: &ForwardStatus) -> &'static str {
+    match status {
+        ForwardStatus::Active => "active",
+        ForwardStatus::Paused => "paused",
+        ForwardStatus::Starting => "starting",
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Destination as a file name component (`user@host:22` → `user@host_22`).
+fn sanitize(destination: &str) -> String {
+    destination
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sshfwd_common::types::{ProcessInfo, Protocol};
+
+    fn make_port(port: u16, cmdline: &str) -> ListeningPort {
+        ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: "127.0.0.1".to_string(),
+            port,
+            process: Some(ProcessInfo {
+                pid: 42,
+                name: "node".to_string(),
+                cmdline: cmdline.to_string(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            }),
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn csv_joins_local_forwards_and_quotes() {
+        let ports = vec![make_port(3000, "node server.js --title \"a, b\"")];
+        let forwards = HashMap::from([(
+            ForwardKey::local(3000),
+            ForwardEntry {
+                local_port: 13000,
+                status: ForwardStatus::Active,
+                active_connections: 2,
+                open_wait: Default::default(),
+            },
+        )]);
+        let snapshot = Snapshot::new("dev@box", Some("box"), None, 7, &ports, &forwards);
+
+        let csv = snapshot.to_csv();
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            r#"3000,tcp,127.0.0.1,42,node,1000,"node server.js --title ""a, b""",13000,active"#
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&snapshot.to_json()).unwrap();
+        assert_eq!(json["forwards"][0]["kind"], "Local");
+        assert_eq!(json["ports"][0]["port"], 3000);
+        assert!(json["taken_at"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn file_names_are_sanitized() {
+        assert_eq!(sanitize("dev@box.lan:2222"), "dev@box.lan_2222");
+        assert_eq!(sanitize("a/b"), "a_b");
+    }
+}
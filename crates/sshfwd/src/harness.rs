@@ -0,0 +1,993 @@
+//! Headless driver for `app::update` and `app::view`, for tests.
+//!
+//! A [`Harness`] feeds scripted messages to one host's model, keeps the
+//! `ForwardCommand`s they produce and renders the view into ratatui's
+//! `TestBackend`; [`Harness::screen`] returns the result as plain text, one
+//! line per row with trailing blanks cut, to compare against a snapshot.
+
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use sshfwd_common::types::{HostInfo, ListeningPort, ProcessInfo, Protocol, ScanResult};
+
+use crate::app::{self, ConnectionState, Message, Model};
+use crate::forward::ForwardCommand;
+
+pub const WIDTH: u16 = 80;
+pub const HEIGHT: u16 = 12;
+/// How long the table shows the logo before the first ports.
+const SPLASH: Duration = Duration::from_secs(1);
+
+pub struct Harness {
+    pub model: Model,
+    /// Everything `update` asked the forward manager to do, in order.
+    pub commands: Vec<ForwardCommand>,
+    terminal: Terminal<TestBackend>,
+    scan_index: u64,
+}
+
+impl Harness {
+    /// A connected host, nothing scanned yet, with notifications and
+    /// persistence off.
+    pub fn new() -> Self {
+        Self::with_size(WIDTH, HEIGHT)
+    }
+
+    pub fn with_size(width: u16, height: u16) -> Self {
+        let mut model = Model::new("dev@box".to_string());
+        model.notifications_enabled = false;
+        model.persist_forwards = false;
+        model.connection_state = ConnectionState::Connected;
+        model.startup = None;
+        // Past the startup splash.
+        model.started_at = Instant::now()
+            .checked_sub(SPLASH)
+            .unwrap_or(model.started_at);
+        Self {
+            model,
+            commands: Vec::new(),
+            terminal: Terminal::new(TestBackend::new(width, height)).expect("test backend"),
+            scan_index: 0,
+        }
+    }
+
+    pub fn send(&mut self, msg: Message) -> &mut Self {
+        self.commands.extend(app::update(&mut self.model, msg));
+        self
+    }
+
+    pub fn key(&mut self, code: KeyCode) -> &mut Self {
+        self.send(Message::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    /// Type `text`, one key per character.
+    pub fn type_str(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            self.key(KeyCode::Char(c));
+        }
+        self
+    }
+
+    /// A remote scan listing `ports` as `(port, process name)`.
+    pub fn scan(&mut self, ports: &[(u16, &str)]) -> &mut Self {
+        self.scan_ports(ports.iter().map(|&(p, name)| port(p, name)).collect())
+    }
+
+    /// A remote scan listing `ports` as given.
+    pub fn scan_ports(&mut self, ports: Vec<ListeningPort>) -> &mut Self {
+        self.scan_index += 1;
+        let scan = scan_result(self.scan_index, ports);
+        self.send(Message::ScanReceived(scan))
+    }
+
+    /// Render the view and return it as text.
+    pub fn screen(&mut self) -> String {
+        let model = &mut self.model;
+        self.terminal
+            .draw(|frame| {
+                let area = frame.area();
+                app::view(model, frame, area);
+            })
+            .expect("draw");
+        let buffer = self.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.symbol()).collect();
+                line.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A scan from the harness host, with no timing fields.
+fn scan_result(scan_index: u64, ports: Vec<ListeningPort>) -> ScanResult {
+    ScanResult {
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        hostname: "box".to_string(),
+        username: "dev".to_string(),
+        is_root: false,
+        uid: Some(1000),
+        ports,
+        warnings: Vec::new(),
+        scan_index,
+        scan_duration_ms: None,
+        scanned_at: None,
+        host: None,
+    }
+}
+
+fn port(port: u16, name: &str) -> ListeningPort {
+    ListeningPort {
+        protocol: Protocol::Tcp,
+        local_addr: "127.0.0.1".into(),
+        port,
+        process: Some(ProcessInfo {
+            pid: u32::from(port) + 1000,
+            name: name.into(),
+            cmdline: name.into(),
+            uid: 1000,
+            exe: None,
+            cwd: None,
+            start_time: None,
+            env: Default::default(),
+            elevated: false,
+            container: None,
+        }),
+        probe: None,
+        bindings: Vec::new(),
+        stats: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forward::{ForwardEvent, ForwardKind};
+    use ratatui::style::Color;
+    use sshfwd_common::time::epoch_secs;
+    use std::time::SystemTime;
+
+    fn assert_screen(h: &mut Harness, rows: &[&str]) {
+        assert_eq!(h.screen(), rows.join("\n"));
+    }
+
+    fn started(remote_port: u16, local_port: u16) -> Message {
+        Message::ForwardEvent(ForwardEvent::Started {
+            kind: ForwardKind::Local,
+            remote_port,
+            local_port,
+        })
+    }
+
+    fn with_ports() -> Harness {
+        let mut h = Harness::new();
+        h.scan(&[(8080, "node"), (5432, "postgres"), (3000, "vite")]);
+        h
+    }
+
+    #[test]
+    fn scan_renders_sorted_and_selection_follows_keys() {
+        let mut h = with_ports();
+        assert_screen(
+            &mut h,
+            &[
+                "╭ ● dev@box │ 3 remote ports │ M:Fwd ──────────────────────────────────────────╮",
+                "│  FWD  PORT  BIND  PROTO  PID   COMMAND                                       │",
+                "│▶      3000  lo    tcp    4000  vite                                          │",
+                "│       5432  lo    tcp    6432  postgres                                      │",
+                "│       8080  lo    tcp    9080  node                                          │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "╰──────────────────────────────────────────────────────────────────────────────╯",
+                " <j/k>Navigate <Enter/f>Forward <x>Kill <m>Mode <r>Refresh <d>Details <q>Quit",
+            ],
+        );
+
+        h.key(KeyCode::Char('G'));
+        assert_eq!(h.model.selected_port(), Some(8080));
+        h.key(KeyCode::Char('k'));
+        assert_eq!(h.model.selected_port(), Some(5432));
+        h.key(KeyCode::Char('g'));
+        assert_eq!(h.model.selected_port(), Some(3000));
+        assert!(h.commands.is_empty());
+    }
+
+    #[test]
+    fn enter_forwards_the_selected_port() {
+        let mut h = with_ports();
+        h.key(KeyCode::Char('j')).key(KeyCode::Enter);
+        assert!(matches!(
+            h.commands.as_slice(),
+            [ForwardCommand::Start {
+                kind: ForwardKind::Local,
+                remote_port: 5432,
+                local_port: 5432,
+                remote_host,
+            }] if remote_host == "box"
+        ));
+        // Pending until the manager reports it, then listed first.
+        assert_screen(
+            &mut h,
+            &[
+                "╭ ● dev@box │ 3 remote ports │ M:Fwd ──────────────────────────────────────────╮",
+                "│  FWD  PORT  BIND  PROTO  PID   COMMAND                                       │",
+                "│▶ ...  5432  lo    tcp    6432  postgres                                      │",
+                "│  ───  ────  ────  ─────  ────  ────────────────────                          │",
+                "│       3000  lo    tcp    4000  vite                                          │",
+                "│       8080  lo    tcp    9080  node                                          │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "╰──────────────────────────────────────────────────────────────────────────────╯",
+                " <j/k>Navigate <Enter/f>Stop <m>Mode <r>Refresh <d>Details <y>Copy <q>Quit",
+            ],
+        );
+
+        h.send(started(5432, 5432));
+        assert_screen(
+            &mut h,
+            &[
+                "╭ ● dev@box │ 3 remote ports │ 1 fwd │ M:Fwd ──────────────────────────────────╮",
+                "│  FWD      PORT  BIND  PROTO  PID   COMMAND                                   │",
+                "│▶ ->:5432  5432  lo    tcp    6432  postgres                                  │",
+                "│  ───────  ────  ────  ─────  ────  ────────────────────                      │",
+                "│           3000  lo    tcp    4000  vite                                      │",
+                "│           8080  lo    tcp    9080  node                                      │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "│                                                                              │",
+                "╰──────────────────────────────────────────────────────────────────────────────╯",
+                " <j/k>Navigate <Enter/f>Stop <m>Mode <r>Refresh <d>Details <y>Copy <q>Quit",
+            ],
+        );
+
+        // Enter again stops it.
+        h.key(KeyCode::Enter);
+        assert!(matches!(
+            h.commands.last(),
+            Some(ForwardCommand::Stop {
+                kind: ForwardKind::Local,
+                remote_port: 5432,
+            })
+        ));
+    }
+
+    #[test]
+    fn copies_the_address_of_forwarded_rows_only() {
+        let mut h = with_ports();
+        assert_eq!(app::selected_address(&h.model), None);
+        h.key(KeyCode::Enter).send(started(3000, 3000));
+        assert_eq!(
+            app::selected_address(&h.model).as_deref(),
+            Some("127.0.0.1:3000")
+        );
+    }
+
+    #[test]
+    fn udp_ports_are_listed_but_not_forwarded() {
+        let mut h = Harness::new();
+        let mut dns = port(5353, "avahi");
+        dns.protocol = Protocol::Udp;
+        h.scan_ports(vec![dns, port(5353, "avahi")]);
+        assert_eq!(h.model.ports[1].protocol, Protocol::Udp);
+
+        h.key(KeyCode::Char('j')).key(KeyCode::Enter);
+        h.key(KeyCode::Char('F'));
+        assert!(h.commands.is_empty());
+        assert_eq!(h.model.modal, app::ModalState::None);
+        let warning = &h.model.events.back().unwrap().message;
+        assert_eq!(warning, ":5353 is udp, SSH can only forward TCP");
+
+        h.key(KeyCode::Char('k')).key(KeyCode::Enter);
+        assert_eq!(h.commands.len(), 1);
+    }
+
+    #[test]
+    fn hotkey_bar_follows_the_row_and_fits_the_width() {
+        let bar = |h: &mut Harness| h.screen().lines().last().unwrap().to_string();
+        let mut h = Harness::with_size(120, 12);
+        let mut dns = port(5353, "avahi");
+        dns.protocol = Protocol::Udp;
+        h.scan_ports(vec![port(3000, "vite"), dns]);
+        assert_eq!(
+            bar(&mut h),
+            " <j/k>Navigate <Enter/f>Forward <F>Custom Port <x>Kill <u>Mine <m>Mode <r>Refresh \
+             <d>Details <e>Events <L>Log <q>Quit"
+        );
+        // Nothing to forward on a UDP row.
+        h.key(KeyCode::Char('j'));
+        assert!(bar(&mut h).starts_with(" <j/k>Navigate <g/G>Top/Bottom <x>Kill <u>Mine "));
+
+        h.key(KeyCode::Char('k')).key(KeyCode::Enter);
+        h.send(started(3000, 3000));
+        assert!(bar(&mut h).contains("<Enter/f>Stop <u>Mine"));
+
+        h.key(KeyCode::Char('x'));
+        assert_eq!(bar(&mut h), " <y>Confirm <Tab>Signal <n>Cancel");
+        h.key(KeyCode::Char('n'));
+
+        let mut narrow = Harness::with_size(40, 12);
+        narrow.scan(&[(3000, "vite")]);
+        assert_eq!(bar(&mut narrow), " <j/k>Navigate <Enter/f>Forward <q>Quit");
+    }
+
+    #[test]
+    fn header_sums_the_session_on_tick() {
+        use crate::forward::Traffic;
+
+        let mut h = with_ports();
+        h.key(KeyCode::Enter).send(started(3000, 3000));
+        h.send(Message::ForwardEvent(
+            ForwardEvent::ConnectionCountChanged {
+                kind: ForwardKind::Local,
+                remote_port: 3000,
+                count: 2,
+            },
+        ));
+        h.send(Message::ForwardEvent(ForwardEvent::ConnectionClosed {
+            kind: ForwardKind::Local,
+            remote_port: 3000,
+            peer: "127.0.0.1:50000".into(),
+            traffic: Traffic {
+                to_remote: 512,
+                from_remote: 1024,
+            },
+            duration: Duration::from_secs(1),
+            open_wait: Duration::ZERO,
+        }));
+        // Nothing until a tick refreshes it.
+        assert!(!h.screen().contains("conns"));
+
+        h.model.started_at = Instant::now() - Duration::from_secs(90);
+        h.model.needs_render = false;
+        h.send(Message::Tick);
+        assert!(h.model.needs_render);
+        let header = h.screen().lines().next().unwrap().to_string();
+        assert!(
+            header.contains("│ 1 fwd │ 2 conns · ⇅ 1.5 KiB · up 1m │ M:Fwd"),
+            "{header}"
+        );
+    }
+
+    #[test]
+    fn header_flags_slow_scans_and_old_data() {
+        let mut h = Harness::new();
+        h.send(Message::ScanReceived(ScanResult {
+            scan_duration_ms: Some(1800),
+            ..scan_result(2, Vec::new())
+        }));
+        h.send(Message::ScanReceived(ScanResult {
+            scan_duration_ms: Some(2100),
+            ..scan_result(3, Vec::new())
+        }));
+        let warnings = h.model.events.iter().filter(|e| e.message.contains("slow"));
+        assert_eq!(warnings.count(), 1);
+        assert!(h
+            .screen()
+            .lines()
+            .next()
+            .unwrap()
+            .contains("│ slow scans 2.1s │"));
+
+        h.send(Message::ScanReceived(ScanResult {
+            scan_duration_ms: Some(40),
+            ..scan_result(4, Vec::new())
+        }));
+        assert_eq!(h.model.slow_scan, None);
+
+        // The agent's timestamp counts while it is plausible.
+        let now = epoch_secs(SystemTime::now());
+        h.send(Message::ScanReceived(ScanResult {
+            scanned_at: Some(now - 3),
+            ..scan_result(5, Vec::new())
+        }));
+        assert!(h.model.data_age().unwrap() >= Duration::from_secs(2));
+        h.send(Message::ScanReceived(ScanResult {
+            scanned_at: Some(now - 3600),
+            ..scan_result(6, Vec::new())
+        }));
+        assert!(h.model.data_age().unwrap() < Duration::from_secs(2));
+
+        h.model.scanned_at = Some(SystemTime::now() - Duration::from_secs(90));
+        assert!(h
+            .screen()
+            .lines()
+            .next()
+            .unwrap()
+            .contains("│ data 1m old │"));
+    }
+
+    #[test]
+    fn host_metadata_fills_header_and_empty_details() {
+        let mut h = Harness::new();
+        h.send(Message::ScanReceived(ScanResult {
+            host: Some(HostInfo {
+                os: Some("Debian GNU/Linux 12 (bookworm)".into()),
+                kernel: Some("6.1.0-26-amd64".into()),
+                uptime_secs: Some(3 * 86_400 + 7200),
+                load_avg: Some("1.25 0.80 0.41".into()),
+                container_runtimes: vec!["docker".into()],
+                kubernetes: true,
+            }),
+            ..scan_result(1, Vec::new())
+        }));
+        h.model.show_details = true;
+        let screen = h.screen();
+        assert!(screen
+            .lines()
+            .next()
+            .unwrap()
+            .contains("│ Debian GNU/Linux 12 (bookworm) · load 1.25 │"));
+        assert!(screen.contains("Kernel  6.1.0-26-amd64"));
+        assert!(screen.contains("Uptime  3d 2h"));
+        assert!(screen.contains("Runs    docker · kubernetes node"));
+    }
+
+    #[test]
+    fn selection_follows_its_port_through_churn() {
+        let mut h = with_ports();
+        h.key(KeyCode::Char('G')).key(KeyCode::Enter);
+        h.send(started(8080, 8080));
+        // Forwarded, 8080 moved to the top.
+        assert_eq!(
+            (h.model.selected_index, h.model.selected_port()),
+            (0, Some(8080))
+        );
+
+        // New ports sort in below it; j/k step over the separator.
+        h.scan(&[
+            (8080, "node"),
+            (5432, "postgres"),
+            (3000, "vite"),
+            (1000, "a"),
+        ]);
+        assert_eq!(h.model.selected_port(), Some(8080));
+        h.key(KeyCode::Char('j'));
+        assert_eq!(
+            (h.model.selected_index, h.model.selected_port()),
+            (2, Some(1000))
+        );
+        h.key(KeyCode::Char('k'));
+        assert_eq!(h.model.selected_port(), Some(8080));
+
+        // Rows vanishing above the selection don't move it off its port.
+        h.key(KeyCode::Char('G'));
+        h.scan(&[(8080, "node"), (5432, "postgres"), (3000, "vite")]);
+        assert_eq!(
+            (h.model.selected_index, h.model.selected_port()),
+            (3, Some(5432))
+        );
+
+        // A selected port that leaves the scan hands the selection to the
+        // row now at its place, never to the separator.
+        h.key(KeyCode::Enter).send(started(5432, 5432));
+        assert_eq!(h.model.selected_port(), Some(5432));
+        h.key(KeyCode::Char('j'));
+        assert_eq!(h.model.selected_port(), Some(8080));
+        h.scan(&[(5432, "postgres"), (3000, "vite")]);
+        assert_eq!(
+            (h.model.selected_index, h.model.selected_port()),
+            (0, Some(5432))
+        );
+
+        // Paused and shown as an inactive row, a forward keeps the selection.
+        h.send(Message::ForwardEvent(ForwardEvent::Paused {
+            kind: ForwardKind::Local,
+            remote_port: 5432,
+        }));
+        h.key(KeyCode::Char('p'));
+        h.scan(&[(3000, "vite")]);
+        assert_eq!(h.model.selected_port(), Some(5432));
+        assert!(matches!(
+            crate::rows::build_display_rows(&h.model)[h.model.selected_index],
+            crate::rows::DisplayRow::InactiveForward(5432)
+        ));
+    }
+
+    #[test]
+    fn bind_column_marks_loopback_ports_and_filters_on_l() {
+        let mut h = Harness::new();
+        let mut open = port(80, "nginx");
+        open.local_addr = "0.0.0.0".into();
+        let mut lan = port(9100, "exporter");
+        lan.local_addr = "10.0.0.5".into();
+        h.scan_ports(vec![open, port(5432, "postgres"), lan]);
+        let screen = h.screen();
+        let rows: Vec<&str> = screen.lines().skip(1).take(4).collect();
+        assert_eq!(
+            rows,
+            [
+                "│  FWD  PORT  BIND      PROTO  PID    COMMAND                                  │",
+                "│▶      80    *         tcp    1080   nginx                                    │",
+                "│       5432  lo        tcp    6432   postgres                                 │",
+                "│       9100  10.0.0.5  tcp    10100  exporter                                 │",
+            ]
+        );
+
+        h.key(KeyCode::Char('l'));
+        let screen = h.screen();
+        assert!(screen.lines().next().unwrap().contains("│ loopback only "));
+        let rows: Vec<&str> = screen.lines().skip(2).take(2).collect();
+        assert_eq!(
+            rows,
+            [
+                "│▶      5432  lo    tcp    6432  postgres                                      │",
+                "│                                                                              │",
+            ]
+        );
+    }
+
+    #[test]
+    fn new_and_restarted_ports_stand_out_for_a_while() {
+        let mut h = with_ports();
+        let mut restarted = port(5432, "postgres");
+        restarted.process.as_mut().unwrap().pid = 7000;
+        h.scan_ports(vec![
+            port(3000, "vite"),
+            restarted,
+            port(8080, "node"),
+            port(9000, "api"),
+        ]);
+        let screen = h.screen();
+        let rows: Vec<&str> = screen.lines().skip(2).take(4).collect();
+        assert_eq!(
+            rows,
+            [
+                "│▶      3000  lo    tcp    4000   vite                                         │",
+                "│       5432  lo    tcp    7000↻  postgres                                     │",
+                "│       8080  lo    tcp    9080   node                                         │",
+                "│       9000  lo    tcp    10000  api                                          │",
+            ]
+        );
+        // The first column of the data rows, past the selection marker.
+        let fg = |h: &Harness, row: u16| h.terminal.backend().buffer()[(3, row)].fg;
+        assert_eq!(fg(&h, 3), Color::Magenta);
+        assert_eq!(fg(&h, 4), Color::Reset);
+        assert_eq!(fg(&h, 5), Color::Cyan);
+
+        for (_, at) in h.model.changed_ports.values_mut() {
+            *at -= app::CHANGE_HIGHLIGHT;
+        }
+        h.send(Message::Tick);
+        assert!(!h.screen().contains('↻'));
+        assert_eq!(fg(&h, 5), Color::Reset);
+    }
+
+    #[test]
+    fn dragging_marks_rows_for_enter() {
+        use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+        let mouse = |kind, row| {
+            Message::Mouse(MouseEvent {
+                kind,
+                column: 10,
+                row,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        let mut h = with_ports();
+        h.screen();
+        // Rows 2-4 hold ports 3000, 5432 and 8080.
+        h.send(mouse(MouseEventKind::Down(MouseButton::Left), 4));
+        h.send(mouse(MouseEventKind::Drag(MouseButton::Left), 3));
+        h.send(mouse(MouseEventKind::Up(MouseButton::Left), 3));
+        let screen = h.screen();
+        assert!(screen.lines().next().unwrap().contains("│ 2 marked "));
+        assert!(screen.ends_with(
+            " <j/k>Navigate <Enter/f>Forward marked <Esc>Unmark <m>Mode <d>Details <q>Quit"
+        ));
+
+        h.key(KeyCode::Enter);
+        let started: Vec<u16> = h
+            .commands
+            .iter()
+            .map(|c| match c {
+                ForwardCommand::Start { remote_port, .. } => *remote_port,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(started, [5432, 8080]);
+        assert!(h.model.marked.is_empty());
+
+        // Esc drops marks before it quits.
+        h.send(mouse(MouseEventKind::Down(MouseButton::Left), 2));
+        h.send(mouse(MouseEventKind::Drag(MouseButton::Left), 3));
+        assert_eq!(h.model.marked.len(), 2);
+        h.key(KeyCode::Esc);
+        assert!(h.model.marked.is_empty());
+        assert!(h.model.running);
+    }
+
+    #[test]
+    fn wheel_over_the_port_modal_steps_the_port() {
+        use crossterm::event::{KeyModifiers, MouseEvent, MouseEventKind};
+        let wheel = |kind| {
+            Message::Mouse(MouseEvent {
+                kind,
+                column: 40,
+                row: 6,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        let mut h = with_ports();
+        h.key(KeyCode::Char('F'));
+        h.send(wheel(MouseEventKind::ScrollUp));
+        h.send(wheel(MouseEventKind::ScrollUp));
+        h.send(wheel(MouseEventKind::ScrollDown));
+        assert!(h.screen().contains("Local port: 3001█"));
+        assert_eq!(h.model.selected_index, 0);
+    }
+
+    #[test]
+    fn started_http_forwards_open_in_the_browser() {
+        use sshfwd_common::types::ServiceProbe;
+        let mut h = Harness::new();
+        h.model.open_http = true;
+        let mut web = port(8080, "node");
+        web.probe = Some(ServiceProbe::Http {
+            status: "HTTP/1.1 200 OK".into(),
+        });
+        h.scan_ports(vec![port(5432, "postgres"), web]);
+
+        // Not HTTP: nothing to open.
+        h.key(KeyCode::Enter);
+        h.send(started(5432, 5432));
+        assert!(h.model.urls_to_open.is_empty());
+
+        h.key(KeyCode::Char('j')).key(KeyCode::Char('j'));
+        h.key(KeyCode::Char('F')).key(KeyCode::Backspace);
+        h.type_str("1").key(KeyCode::Enter);
+        h.send(started(8080, 8081));
+        assert_eq!(h.model.urls_to_open, ["http://127.0.0.1:8081/"]);
+
+        // A forward coming back on its own (reconnect) doesn't open again.
+        h.model.urls_to_open.clear();
+        h.send(started(8080, 8081));
+        assert!(h.model.urls_to_open.is_empty());
+    }
+
+    #[test]
+    fn preview_shows_what_the_forward_answers() {
+        let mut h = with_ports();
+        // Not forwarded yet: nothing to fetch.
+        h.key(KeyCode::Char('v'));
+        assert_eq!(h.model.modal, app::ModalState::None);
+        assert!(h.model.previews_to_fetch.is_empty());
+
+        h.key(KeyCode::Enter).send(started(3000, 3000));
+        h.key(KeyCode::Char('v'));
+        assert_eq!(
+            h.model.previews_to_fetch,
+            [crate::preview::Request {
+                remote_port: 3000,
+                host: "127.0.0.1".into(),
+                port: 3000,
+            }]
+        );
+        h.send(Message::Preview {
+            remote_port: 3000,
+            result: Ok(crate::preview::Preview {
+                status_line: "HTTP/1.1 200 OK".into(),
+                status: 200,
+                headers: vec![("Content-Type".into(), "text/html".into())],
+                body: "<!doctype html>\n<title>\tvite</title>".into(),
+                truncated: true,
+            }),
+        });
+        assert_screen(
+            &mut h,
+            &[
+                "╭ ● dev@box │ 3 remote ports │ 1 fwd │ ⚠ 1 warning │ M:Fwd ────────────────────╮",
+                "│ ╭ Preview · http://127.0.0.1:3000/ ────────────────────────────────────────╮ │",
+                "│▶│ HTTP/1.1 200 OK                                                          │ │",
+                "│ │ Content-Type: text/html                                                  │ │",
+                "│ │                                                                          │ │",
+                "│ │ <!doctype html>                                                          │ │",
+                "│ │ <title> vite</title>                                                     │ │",
+                "│ │ … first 1 KB of the body                                                 │ │",
+                "│ │                                                                          │ │",
+                "│ │                                                                          │ │",
+                "╰─╰ <r>Refetch  <j/k>Scroll  <Esc>Close ─────────────────────────────────────╯─╯",
+                " <r>Refetch <j/k>Scroll <Esc>Close",
+            ],
+        );
+
+        // `r` fetches again; a late answer for another port is dropped.
+        h.key(KeyCode::Char('r'));
+        assert_eq!(h.model.previews_to_fetch.len(), 2);
+        h.send(Message::Preview {
+            remote_port: 5432,
+            result: Err("connection reset".into()),
+        });
+        assert!(matches!(
+            h.model.modal,
+            app::ModalState::Preview { result: None, .. }
+        ));
+        h.key(KeyCode::Esc);
+        assert_eq!(h.model.modal, app::ModalState::None);
+    }
+
+    #[test]
+    fn configured_columns_show_in_order() {
+        let mut h = Harness::new();
+        h.model.columns = toml::from_str(
+            "columns = [\"command\", \"port\"]\nwidth.command = { min = 10, max = 10 }",
+        )
+        .unwrap();
+        h.scan(&[(3000, "vite")]);
+        let screen = h.screen();
+        let rows: Vec<&str> = screen.lines().skip(1).take(2).collect();
+        assert_eq!(
+            rows,
+            [
+                "│  COMMAND     PORT                                                            │",
+                "│▶ vite        3000                                                            │",
+            ]
+        );
+    }
+
+    #[test]
+    fn wide_commands_are_cut_to_their_cells() {
+        let mut h = Harness::with_size(60, 12);
+        h.scan(&[
+            (3000, "python3 服务器.py --端口 3000 --reload"),
+            (8080, "🚀 launch"),
+        ]);
+        let screen = h.screen();
+        let rows: Vec<&str> = screen.lines().skip(1).take(3).collect();
+        // A wide character's second cell reads as a space here.
+        assert_eq!(
+            rows,
+            [
+                "│  FWD  PORT  BIND  PROTO  PID   COMMAND                   │",
+                "│▶      3000  lo    tcp    4000  python3 服 务 器 .py --端 口  …│",
+                "│       8080  lo    tcp    9080  🚀  launch                 │",
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_port_modal_edits_the_local_port() {
+        let mut h = with_ports();
+        h.key(KeyCode::Char('F'));
+        for _ in 0..4 {
+            h.key(KeyCode::Backspace);
+        }
+        h.type_str("13000");
+        assert_screen(
+            &mut h,
+            &[
+                "╭ ● dev@box │ 3 remote ports │ M:Fwd ──────────────────────────────────────────╮",
+                "│  FWD  PORT  BIND  PROTO  PID   COMMAND                                       │",
+                "│▶      3000  lo    tcp    4000  vite                                          │",
+                "│       5432  lo╭ Forward port :3000 ──────────────────────────╮               │",
+                "│       8080  lo│                                              │               │",
+                "│               │                                              │               │",
+                "│               │  Local port: 13000█                          │               │",
+                "│               │                                              │               │",
+                "│               │  <Enter>Confirm  <Tab>Next free  <Esc>Cancel │               │",
+                "│               ╰──────────────────────────────────────────────╯               │",
+                "╰──────────────────────────────────────────────────────────────────────────────╯",
+                " <Enter>Confirm <Tab>Next free <Esc>Cancel",
+            ],
+        );
+        assert!(h.commands.is_empty());
+
+        h.key(KeyCode::Enter);
+        assert!(matches!(
+            h.commands.as_slice(),
+            [ForwardCommand::Start {
+                kind: ForwardKind::Local,
+                remote_port: 3000,
+                local_port: 13000,
+                ..
+            }]
+        ));
+        h.send(started(3000, 13000));
+        assert!(h.screen().contains("│▶ ->:13000  3000  lo    tcp"));
+    }
+
+    #[test]
+    fn port_modal_checks_the_port_as_typed() {
+        let mut h = with_ports();
+        h.key(KeyCode::Char('F'));
+        h.type_str("0").key(KeyCode::Enter);
+        h.send(started(3000, 30000));
+        h.commands.clear();
+
+        h.key(KeyCode::Char('j')).key(KeyCode::Char('F'));
+        for _ in 0..4 {
+            h.key(KeyCode::Backspace);
+        }
+        assert!(h.screen().contains("│  Enter a port"));
+        h.type_str("30000");
+        assert!(h
+            .screen()
+            .contains("│  Port 30000 is used by another forward"));
+        h.key(KeyCode::Enter);
+        assert!(h.commands.is_empty());
+        assert!(matches!(h.model.modal, app::ModalState::PortInput { .. }));
+
+        // Tab completes to a free port; the error goes with it.
+        h.key(KeyCode::Tab);
+        let app::ModalState::PortInput { buffer, error, .. } = &h.model.modal else {
+            panic!("port modal closed");
+        };
+        assert!(buffer.parse::<u16>().unwrap() > 30000);
+        assert_eq!(*error, None);
+        h.key(KeyCode::Enter);
+        assert!(matches!(
+            h.commands.as_slice(),
+            [ForwardCommand::Start {
+                remote_port: 5432,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn bind_errors_name_the_holder_and_suggest_free_ports() {
+        let mut h = with_ports();
+        h.send(Message::ForwardEvent(ForwardEvent::BindError {
+            kind: ForwardKind::Reverse,
+            remote_port: 8080,
+            message: "Address already in use".into(),
+        }));
+        h.key(KeyCode::Down).key(KeyCode::Down);
+        assert_screen(
+            &mut h,
+            &[
+                "╭ ● dev@box │ 3 remote ports │ M:Fwd ──────────────────────────────────────────╮",
+                "│  FWD  PORT  BIND  PROTO  PID   COMMAND                                       │",
+                "│▶      3000  lo╭ Reverse :8080 → remote ──────────────────────╮               │",
+                "│       5432  lo│                                              │               │",
+                "│       8080  lo│                                              │               │",
+                "│               │  Held by node (9080)                         │               │",
+                "│               │  Free:  8081   8079   8082                   │               │",
+                "│               │  Remote bind port: 8079█                     │               │",
+                "│               │                                              │               │",
+                "│               │  <Enter>Confirm  <↑↓>Free port  <Esc>Cancel  │               │",
+                "╰───────────────╰──────────────────────────────────────────────╯───────────────╯",
+                " <Enter>Confirm <↑↓>Free port <Esc>Cancel",
+            ],
+        );
+
+        h.key(KeyCode::Enter);
+        assert!(matches!(
+            h.commands.as_slice(),
+            [ForwardCommand::Start {
+                kind: ForwardKind::Reverse,
+                remote_port: 8079,
+                local_port: 8080,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn errors_with_a_remedy_open_a_modal_once() {
+        use crate::error::{DiscoveryError, SshError};
+
+        let mut h = with_ports();
+        let noexec = || {
+            Message::DiscoveryError(DiscoveryError::Ssh(SshError::NoExec {
+                dirs: vec!["/tmp/sshfwd".into()],
+            }))
+        };
+        h.send(noexec());
+        assert_eq!(
+            h.screen(),
+            [
+                "╭ ● dev@╭ Discovery failed ────────────────────────────────────────────╮───────╮",
+                "│  FWD  │                                                              │       │",
+                "│▶      │  SSH error: cannot execute the agent in /tmp/sshfwd: noexec  │       │",
+                "│       │  mount?                                                      │       │",
+                "│       │                                                              │       │",
+                "│       │  Point --remote-dir (or [agent] remote_dir) at a directory   │       │",
+                "│       │  on a filesystem that allows executables, e.g. one under     │       │",
+                "│       │  your home directory.                                        │       │",
+                "│       │                                                              │       │",
+                "│       │  <Esc>Close                                                  │       │",
+                "╰───────│                                                              │───────╯",
+                " <Esc>Cl╰──────────────────────────────────────────────────────────────╯",
+            ]
+            .join("\n")
+        );
+
+        // Dismissed, the same remedy stays away on the next reconnect.
+        h.key(KeyCode::Esc);
+        h.send(noexec());
+        assert_eq!(h.model.modal, app::ModalState::None);
+        assert!(h.screen().contains("⚠ 2 warnings"));
+    }
+
+    #[test]
+    fn agent_errors_are_logged_once_per_kind() {
+        use sshfwd_common::types::{AgentError, AgentErrorKind};
+
+        let mut h = with_ports();
+        let error = |kind| {
+            Message::AgentError(AgentError {
+                kind,
+                message: "failed to read /proc/net/tcp".into(),
+            })
+        };
+        let logged = |h: &Harness| {
+            h.model
+                .events
+                .iter()
+                .filter(|e| e.message.starts_with("agent "))
+                .count()
+        };
+        h.send(error(AgentErrorKind::PermissionDenied));
+        h.send(error(AgentErrorKind::PermissionDenied));
+        assert_eq!(logged(&h), 1);
+        assert!(h.screen().contains("--sudo-agent"));
+
+        // Logged, not a modal; logged again after a scan went through.
+        h.key(KeyCode::Esc);
+        h.send(error(AgentErrorKind::ResourceLimit));
+        assert_eq!(h.model.modal, app::ModalState::None);
+        h.send(Message::ScanReceived(scan_result(9, Vec::new())));
+        h.send(error(AgentErrorKind::ResourceLimit));
+        assert_eq!(logged(&h), 3);
+    }
+
+    #[test]
+    fn splash_shows_agent_upload_progress() {
+        let mut h = Harness::new();
+        h.model.connection_state = ConnectionState::Connecting;
+        h.send(Message::Startup(app::StartupStage::Uploading(42)));
+        let screen = h.screen();
+        assert!(screen.contains("Uploading agent 42%..."), "{screen}");
+    }
+
+    #[test]
+    fn failed_startups_can_be_retried_with_another_destination() {
+        let mut h = Harness::new();
+        h.send(Message::StartupFailed {
+            error: "Connection to dev@box failed: no route to host".into(),
+            hint: None,
+        });
+        let screen = h.screen();
+        assert!(screen.contains("no route to host"), "{screen}");
+        assert!(screen.contains("<e>Edit destination"), "{screen}");
+
+        // Closed, `r` brings the choice back.
+        h.key(KeyCode::Esc).key(KeyCode::Char('r'));
+        assert!(matches!(
+            h.model.modal,
+            app::ModalState::StartupFailed { .. }
+        ));
+
+        h.key(KeyCode::Char('e'));
+        for _ in "box".chars() {
+            h.key(KeyCode::Backspace);
+        }
+        h.type_str("box2");
+        assert!(h.screen().contains("Destination: dev@box2"));
+        h.key(KeyCode::Enter);
+        assert_eq!(h.model.restart.as_deref(), Some("dev@box2"));
+        assert_eq!(h.model.modal, app::ModalState::None);
+        assert!(h.model.running);
+    }
+
+    #[test]
+    fn escape_closes_the_modal_without_forwarding() {
+        let mut h = with_ports();
+        let table = h.screen();
+        h.key(KeyCode::Char('F')).type_str("1").key(KeyCode::Esc);
+        assert_eq!(h.screen(), table);
+        assert!(h.commands.is_empty());
+    }
+}
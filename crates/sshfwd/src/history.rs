@@ -0,0 +1,195 @@
+//! Bounded timeline of remote port changes, built by diffing consecutive scans.
+//!
+//! Only the latest per-port snapshot is kept for diffing; the timeline itself
+//! holds up to `TIMELINE_CAPACITY` entries so a long-running session answers
+//! "when did the API last restart?" without growing without bound.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::SystemTime;
+
+use sshfwd_common::types::ListeningPort;
+
+const TIMELINE_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineKind {
+    Appeared,
+    Disappeared,
+    /// Same port, different owning PID between two scans.
+    Restarted {
+        old_pid: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub at: SystemTime,
+    pub port: u16,
+    pub kind: TimelineKind,
+    pub pid: Option<u32>,
+    pub process_name: Option<String>,
+}
+
+/// Owner of a port at scan time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PortOwner {
+    pid: Option<u32>,
+    name: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanHistory {
+    /// Port → owner from the previous scan; `None` until the first scan.
+    last: Option<BTreeMap<u16, PortOwner>>,
+    timeline: VecDeque<TimelineEntry>,
+    scans: u64,
+}
+
+impl ScanHistory {
+    /// Diff `ports` against the previous scan and append the changes,
+    /// returning how many. The first scan only establishes the baseline.
+    pub fn record(&mut self, ports: &[ListeningPort]) -> usize {
+        let at = SystemTime::now();
+        let mut current = BTreeMap::new();
+        for port in ports {
+            // tcp + tcp6 listeners of one process share a port; keep the first.
+            current.entry(port.port).or_insert_with(|| PortOwner {
+                pid: port.process.as_ref().map(|p| p.pid),
+                name: port.process.as_ref().map(|p| p.name.clone()),
+            });
+        }
+        self.scans += 1;
+
+        let Some(last) = self.last.replace(current) else {
+            return 0;
+        };
+        let current = self.last.as_ref().expect("just set");
+
+        let mut changes = Vec::new();
+        for (&port, owner) in current {
+            let kind = match last.get(&port) {
+                None => TimelineKind::Appeared,
+                Some(PortOwner {
+                    pid: Some(old_pid), ..
+                }) if owner.pid.is_some_and(|pid| pid != *old_pid) => {
+                    TimelineKind::Restarted { old_pid: *old_pid }
+                }
+                Some(_) => continue,
+            };
+            changes.push((port, kind, owner.clone()));
+        }
+        for (&port, owner) in &last {
+            if !current.contains_key(&port) {
+                changes.push((port, TimelineKind::Disappeared, owner.clone()));
+            }
+        }
+
+        let count = changes.len();
+        for (port, kind, owner) in changes {
+            if self.timeline.len() == TIMELINE_CAPACITY {
+                self.timeline.pop_front();
+            }
+            self.timeline.push_back(TimelineEntry {
+                at,
+                port,
+                kind,
+                pid: owner.pid,
+                process_name: owner.name,
+            });
+        }
+        count
+    }
+
+    /// Changes, oldest first.
+    pub fn timeline(&self) -> &VecDeque<TimelineEntry> {
+        &self.timeline
+    }
+
+    /// Scans recorded this session.
+    pub fn scans(&self) -> u64 {
+        self.scans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sshfwd_common::types::{ProcessInfo, Protocol};
+
+    fn make_port(port: u16, pid: u32) -> ListeningPort {
+        ListeningPort {
+            protocol: Protocol::Tcp,
+            local_addr: "0.0.0.0".to_string(),
+            port,
+            process: Some(ProcessInfo {
+                pid,
+                name: "api".to_string(),
+                cmdline: "api".to_string(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            }),
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn first_scan_is_baseline() {
+        let mut history = ScanHistory::default();
+        history.record(&[make_port(80, 10)]);
+        assert!(history.timeline().is_empty());
+        assert_eq!(history.scans(), 1);
+    }
+
+    #[test]
+    fn records_appear_disappear_and_restart() {
+        let mut history = ScanHistory::default();
+        history.record(&[make_port(80, 10), make_port(8080, 20)]);
+        // 8080 restarted, 80 gone, 3000 new; the tcp6 twin of 3000 is folded.
+        history.record(&[
+            make_port(3000, 30),
+            make_port(3000, 30),
+            make_port(8080, 21),
+        ]);
+
+        let kinds: Vec<_> = history
+            .timeline()
+            .iter()
+            .map(|e| (e.port, e.kind, e.pid))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (3000, TimelineKind::Appeared, Some(30)),
+                (8080, TimelineKind::Restarted { old_pid: 20 }, Some(21)),
+                (80, TimelineKind::Disappeared, Some(10)),
+            ]
+        );
+
+        history.record(&[make_port(3000, 30), make_port(8080, 21)]);
+        assert_eq!(history.timeline().len(), 3);
+    }
+
+    #[test]
+    fn timeline_is_bounded() {
+        let mut history = ScanHistory::default();
+        history.record(&[]);
+        for i in 0..TIMELINE_CAPACITY {
+            let ports = if i % 2 == 0 {
+                vec![make_port(80, 1)]
+            } else {
+                vec![]
+            };
+            history.record(&ports);
+        }
+        history.record(&[make_port(80, 1)]);
+        assert_eq!(history.timeline().len(), TIMELINE_CAPACITY);
+        assert_eq!(history.timeline()[0].kind, TimelineKind::Disappeared);
+    }
+}
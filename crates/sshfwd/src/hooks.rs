@@ -0,0 +1,141 @@
+//! User commands run on port and forward events (`[hooks]` in the config).
+//!
+//! Each hook is a shell command (`sh -c`) started in the background with
+//! `HOST` (the destination), `PORT` (remote port), `LOCAL_PORT` (forwards
+//! only), `PROCESS` (remote process name, when known) and `SSHFWD_EVENT` in its
+//! environment. Output is discarded: the TUI owns the terminal.
+
+use std::io;
+use std::process::{Command, Stdio};
+
+use crate::config::HooksConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PortAppeared,
+    PortDisappeared,
+    ForwardStarted,
+    ForwardStopped,
+}
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::PortAppeared => "port_appeared",
+            HookEvent::PortDisappeared => "port_disappeared",
+            HookEvent::ForwardStarted => "forward_started",
+            HookEvent::ForwardStopped => "forward_stopped",
+        }
+    }
+}
+
+pub struct Hooks {
+    config: HooksConfig,
+    destination: String,
+}
+
+impl Hooks {
+    /// `None` when no hook is configured.
+    pub fn new(config: HooksConfig, destination: &str) -> Option<Self> {
+        (config != HooksConfig::default()).then(|| Self {
+            config,
+            destination: destination.to_string(),
+        })
+    }
+
+    fn command(&self, event: HookEvent) -> Option<&str> {
+        let command = match event {
+            HookEvent::PortAppeared => &self.config.on_port_appeared,
+            HookEvent::PortDisappeared => &self.config.on_port_disappeared,
+            HookEvent::ForwardStarted => &self.config.on_forward_started,
+            HookEvent::ForwardStopped => &self.config.on_forward_stopped,
+        };
+        command.as_deref().filter(|c| !c.trim().is_empty())
+    }
+
+    /// Start the hook for `event`, if any, without waiting for it.
+    pub fn run(
+        &self,
+        event: HookEvent,
+        port: u16,
+        local_port: Option<u16>,
+        process: Option<&str>,
+    ) -> io::Result<()> {
+        let Some(command) = self.command(event) else {
+            return Ok(());
+        };
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .envs(self.env(event, port, local_port, process))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("{} hook: {e}", event.name())))?;
+        // Reap it so finished hooks don't linger as zombies.
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+
+    fn env(
+        &self,
+        event: HookEvent,
+        port: u16,
+        local_port: Option<u16>,
+        process: Option<&str>,
+    ) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("SSHFWD_EVENT", event.name().to_string()),
+            ("HOST", self.destination.clone()),
+            ("PORT", port.to_string()),
+        ];
+        if let Some(local_port) = local_port {
+            env.push(("LOCAL_PORT", local_port.to_string()));
+        }
+        if let Some(process) = process {
+            env.push(("PROCESS", process.to_string()));
+        }
+        env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_configured_events_have_commands() {
+        assert!(Hooks::new(HooksConfig::default(), "dev@box").is_none());
+
+        let hooks = Hooks::new(
+            HooksConfig {
+                on_forward_started: Some("psql -p $LOCAL_PORT".into()),
+                on_port_appeared: Some("  ".into()),
+                ..HooksConfig::default()
+            },
+            "dev@box",
+        )
+        .unwrap();
+        assert_eq!(
+            hooks.command(HookEvent::ForwardStarted),
+            Some("psql -p $LOCAL_PORT")
+        );
+        assert_eq!(hooks.command(HookEvent::PortAppeared), None);
+        assert_eq!(hooks.command(HookEvent::ForwardStopped), None);
+
+        let env = hooks.env(
+            HookEvent::ForwardStarted,
+            5432,
+            Some(15432),
+            Some("postgres"),
+        );
+        assert!(env.contains(&("HOST", "dev@box".into())));
+        assert!(env.contains(&("LOCAL_PORT", "15432".into())));
+        assert!(env.contains(&("PROCESS", "postgres".into())));
+        let env = hooks.env(HookEvent::PortAppeared, 8080, None, None);
+        assert!(!env
+            .iter()
+            .any(|(k, _)| *k == "LOCAL_PORT" || *k == "PROCESS"));
+    }
+}
@@ -0,0 +1,550 @@
+//! Several destinations in one process (`sshfwd web1 web2 db`).
+//!
+//! Each host keeps its own `Model`, sidecar (session, discovery stream,
+//! `ForwardManager`) and command channels, so a reconnect or a modal on one
+//! host never touches another. Keys go to the host on screen; `[` / `]` and
+//! `1`–`9` switch hosts when no modal is open.
+//!
+//! `A` is the group operation: it forwards the selected remote port on every
+//! host, the `i`-th host (from 0) on local port `remote + i * port_offset` so
+//! the tunnels don't collide, or stops it everywhere if the host on screen
+//! already forwards it. `[[broadcast]]` rules (see `broadcast`) do the same
+//! on their own for matching ports in each host's scans.
+//!
+//! `C` compares the host on screen with another side by side (see
+//! `compare`); while it is open `[` / `]` pick the other host.
+
+use std::collections::BTreeSet;
+
+#[cfg(feature = "tui")]
+use crossterm::event::{KeyCode, KeyModifiers};
+#[cfg(feature = "tui")]
+use ratatui::layout::{Constraint, Layout};
+#[cfg(feature = "tui")]
+use ratatui::text::Line;
+use sshfwd_common::types::AgentCommand;
+use tokio::sync::mpsc::UnboundedSender;
+
+#[cfg(feature = "tui")]
+use crate::app::AppMode;
+use crate::app::{self, EventLevel, Message, ModalState, Model};
+use crate::broadcast::{self, BroadcastRule};
+use crate::forward::ForwardCommand;
+#[cfg(feature = "tui")]
+use crate::forward::{ForwardKey, ForwardKind};
+
+/// Local port spacing between hosts for group forwards unless
+/// `[group.<name>] port_offset` says otherwise.
+pub const DEFAULT_PORT_OFFSET: u16 = 1000;
+
+/// One destination: its model and the channels to its sidecar.
+pub struct Host {
+    pub model: Model,
+    fwd_cmd_tx: UnboundedSender<ForwardCommand>,
+    agent_cmd_tx: UnboundedSender<AgentCommand>,
+    /// This host's background channel, for answers fetched here (previews);
+    /// `None` where nothing is fetched, e.g. in a journal replay.
+    events: Option<crossbeam_channel::Sender<Message>>,
+    /// Listening ports broadcast rules already acted on; cleared when the
+    /// port disappears.
+    broadcast_seen: BTreeSet<u16>,
+}
+
+impl Host {
+    pub fn new(
+        model: Model,
+        fwd_cmd_tx: UnboundedSender<ForwardCommand>,
+        agent_cmd_tx: UnboundedSender<AgentCommand>,
+    ) -> Self {
+        Self {
+            model,
+            fwd_cmd_tx,
+            agent_cmd_tx,
+            events: None,
+            broadcast_seen: BTreeSet::new(),
+        }
+    }
+
+    /// Deliver fetched answers on `events`, the host's background channel.
+    pub fn with_events(mut self, events: crossbeam_channel::Sender<Message>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Apply `msg` and hand the resulting commands to this host's sidecar.
+    pub fn update(&mut self, msg: Message) {
+        let commands = app::update(&mut self.model, msg);
+        self.send(commands);
+    }
+
+    fn send(&mut self, commands: Vec<ForwardCommand>) {
+        for cmd in commands {
+            let _ = self.fwd_cmd_tx.send(cmd);
+        }
+        for cmd in self.model.agent_commands.drain(..) {
+            let _ = self.agent_cmd_tx.send(cmd);
+        }
+        for url in std::mem::take(&mut self.model.urls_to_open) {
+            match crate::browser::open(&url) {
+                Ok(()) => self
+                    .model
+                    .log_event(EventLevel::Info, format!("opened {url} in the browser")),
+                Err(e) => self.model.log_event(
+                    EventLevel::Warn,
+                    format!("couldn't open {url} in the browser: {e}"),
+                ),
+            }
+        }
+        for request in std::mem::take(&mut self.model.previews_to_fetch) {
+            if let Some(events) = &self.events {
+                crate::preview::spawn(request, events.clone());
+            }
+        }
+    }
+
+    /// Remove the status file and withdraw mDNS records before exiting.
+    pub fn shutdown(&mut self) {
+        if let Some(status_file) = &self.model.status_file {
+            status_file.remove();
+        }
+        if let Some(mdns) = &mut self.model.mdns {
+            mdns.shutdown();
+        }
+    }
+}
+
+pub struct Hosts {
+    pub hosts: Vec<Host>,
+    /// Index of the host on screen.
+    pub active: usize,
+    port_offset: u16,
+    broadcast: Vec<BroadcastRule>,
+    /// Side-by-side view of the active host and another (`C`).
+    #[cfg(feature = "tui")]
+    compare: Option<Compare>,
+    /// Tab bar as last drawn; other hosts are only visible through it.
+    #[cfg(feature = "tui")]
+    tabs: Option<Line<'static>>,
+    /// How much of the drawing the terminal can show.
+    #[cfg(feature = "tui")]
+    pub profile: crate::ui::profile::Profile,
+}
+
+#[cfg(feature = "tui")]
+struct Compare {
+    /// The right-hand host.
+    other: usize,
+    scroll: usize,
+}
+
+#[cfg(feature = "tui")]
+enum HostKey {
+    Switch(usize),
+    GroupForward,
+    Compare,
+}
+
+impl Hosts {
+    pub fn new(hosts: Vec<Host>, port_offset: u16, broadcast: Vec<BroadcastRule>) -> Self {
+        Self {
+            hosts,
+            active: 0,
+            port_offset,
+            broadcast,
+            #[cfg(feature = "tui")]
+            compare: None,
+            #[cfg(feature = "tui")]
+            tabs: None,
+            #[cfg(feature = "tui")]
+            profile: Default::default(),
+        }
+    }
+
+    /// Apply a background message of the `index`-th host.
+    pub fn update(&mut self, index: usize, msg: Message) {
+        let scanned = matches!(msg, Message::ScanReceived(_));
+        // A startup question brings its host on screen unless the user is
+        // busy in a modal elsewhere.
+        if matches!(msg, Message::Prompt(_))
+            && self.hosts[self.active].model.modal == ModalState::None
+        {
+            self.active = index;
+            #[cfg(feature = "tui")]
+            {
+                self.compare = None;
+            }
+        }
+        self.hosts[index].update(msg);
+        if scanned && !self.broadcast.is_empty() {
+            self.apply_broadcast(index);
+        }
+    }
+
+    /// Why the only host failed to start, with the remedy when known. Without
+    /// a terminal the caller exits with it; the TUI offers a retry instead
+    /// and only reports it once the user quits.
+    pub fn startup_failure(&self) -> Option<(&str, Option<&str>)> {
+        match &self.hosts[..] {
+            [host] => Some((
+                host.model.startup_error.as_deref()?,
+                host.model.startup_hint.as_deref(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Forward ports matching a broadcast rule that appeared in the last scan.
+    fn apply_broadcast(&mut self, index: usize) {
+        let host = &mut self.hosts[index];
+        let listening: BTreeSet<u16> = host.model.ports.iter().map(|p| p.port).collect();
+        host.broadcast_seen.retain(|port| listening.contains(port));
+        let due: BTreeSet<u16> = host
+            .model
+            .ports
+            .iter()
+            .filter(|p| !host.broadcast_seen.contains(&p.port))
+            .filter(|p| broadcast::matches(&self.broadcast, p))
+            .map(|p| p.port)
+            .collect();
+        let mut commands = Vec::new();
+        for remote_port in due {
+            host.broadcast_seen.insert(remote_port);
+            commands.extend(forward_on(
+                &mut host.model,
+                remote_port,
+                index,
+                self.port_offset,
+            ));
+        }
+        host.send(commands);
+    }
+
+    /// Quitting on any host quits the process.
+    pub fn running(&self) -> bool {
+        self.hosts.iter().all(|h| h.model.running)
+    }
+
+    /// A host whose failed startup should be tried again, with the
+    /// destination to use (see `Model::restart`).
+    pub fn take_restart(&mut self) -> Option<(usize, String)> {
+        self.hosts
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, host)| Some((index, host.model.restart.take()?)))
+    }
+
+    /// Put a freshly started `host` in place of the `index`-th one.
+    pub fn replace(&mut self, index: usize, mut host: Host) {
+        self.hosts[index].shutdown();
+        host.model.needs_render = true;
+        self.hosts[index] = host;
+    }
+
+    /// Route a terminal event: host switches and group forwards are handled
+    /// here, resizes go to every host and everything else to the host on
+    /// screen.
+    #[cfg(feature = "tui")]
+    pub fn handle_input(&mut self, msg: Message) {
+        if self.compare.is_some() {
+            if let Message::Key(key) = msg {
+                self.handle_compare_key(key.code);
+                return;
+            }
+        }
+        match self.host_key(&msg) {
+            Some(HostKey::Switch(index)) => {
+                self.active = index;
+                self.hosts[index].model.needs_render = true;
+                return;
+            }
+            Some(HostKey::GroupForward) => {
+                self.toggle_group_forward();
+                return;
+            }
+            Some(HostKey::Compare) => {
+                self.compare = Some(Compare {
+                    other: (self.active + 1) % self.hosts.len(),
+                    scroll: 0,
+                });
+                self.hosts[self.active].model.needs_render = true;
+                return;
+            }
+            None => {}
+        }
+        if let Message::Resize(w, h) = msg {
+            for host in &mut self.hosts {
+                host.update(Message::Resize(w, h));
+            }
+            return;
+        }
+        self.hosts[self.active].update(msg);
+    }
+
+    #[cfg(feature = "tui")]
+    fn host_key(&self, msg: &Message) -> Option<HostKey> {
+        let count = self.hosts.len();
+        if count < 2 || self.hosts[self.active].model.modal != ModalState::None {
+            return None;
+        }
+        let Message::Key(key) = msg else {
+            return None;
+        };
+        if key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return None;
+        }
+        match key.code {
+            KeyCode::Char(']') => Some(HostKey::Switch((self.active + 1) % count)),
+            KeyCode::Char('[') => Some(HostKey::Switch((self.active + count - 1) % count)),
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                (index < count).then_some(HostKey::Switch(index))
+            }
+            KeyCode::Char('A') => Some(HostKey::GroupForward),
+            KeyCode::Char('C') => Some(HostKey::Compare),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    fn toggle_group_forward(&mut self) {
+        let model = &self.hosts[self.active].model;
+        if model.mode != AppMode::Forward {
+            return;
+        }
+        let Some(remote_port) = model.selected_port() else {
+            return;
+        };
+        let key = ForwardKey::local(remote_port);
+        let stop = model.forwards.contains_key(&key);
+        for (i, host) in self.hosts.iter_mut().enumerate() {
+            let commands = if stop {
+                if host.model.forwards.contains_key(&key) {
+                    vec![ForwardCommand::Stop {
+                        kind: ForwardKind::Local,
+                        remote_port,
+                    }]
+                } else {
+                    Vec::new()
+                }
+            } else {
+                forward_on(&mut host.model, remote_port, i, self.port_offset)
+            };
+            host.model.needs_render = true;
+            host.send(commands);
+        }
+    }
+
+    #[cfg(feature = "tui")]
+    fn handle_compare_key(&mut self, code: KeyCode) {
+        let count = self.hosts.len();
+        let active = self.active;
+        let Some(compare) = &mut self.compare else {
+            return;
+        };
+        match code {
+            KeyCode::Char('C') | KeyCode::Char('q') | KeyCode::Esc => self.compare = None,
+            KeyCode::Char('j') | KeyCode::Down => compare.scroll += 1,
+            KeyCode::Char('k') | KeyCode::Up => compare.scroll = compare.scroll.saturating_sub(1),
+            KeyCode::Char(']') => {
+                compare.other = (compare.other + 1) % count;
+                if compare.other == active {
+                    compare.other = (compare.other + 1) % count;
+                }
+                compare.scroll = 0;
+            }
+            KeyCode::Char('[') => {
+                compare.other = (compare.other + count - 1) % count;
+                if compare.other == active {
+                    compare.other = (compare.other + count - 1) % count;
+                }
+                compare.scroll = 0;
+            }
+            _ => return,
+        }
+        self.hosts[active].model.needs_render = true;
+    }
+
+    /// The host on screen (or the one compared with it) changed, or the tab
+    /// of a host in the background did; other changes in the background wait
+    /// until their host is switched to.
+    #[cfg(feature = "tui")]
+    pub fn needs_render(&self) -> bool {
+        let changed = |index: usize| self.hosts[index].model.needs_render;
+        changed(self.active)
+            || self.compare.as_ref().is_some_and(|c| changed(c.other))
+            || (self.hosts.len() > 1 && self.tabs.as_ref() != Some(&self.build_tabs()))
+    }
+
+    #[cfg(feature = "tui")]
+    fn build_tabs(&self) -> Line<'static> {
+        let models: Vec<&Model> = self.hosts.iter().map(|h| &h.model).collect();
+        crate::ui::hosts::build_tabs(&models, self.active)
+    }
+
+    #[cfg(feature = "tui")]
+    pub fn view(&mut self, frame: &mut ratatui::Frame) {
+        let mut area = frame.area();
+        if self.hosts.len() > 1 {
+            let [tabs, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+            let line = self.build_tabs();
+            frame.render_widget(line.clone(), tabs);
+            self.tabs = Some(line);
+            area = rest;
+        }
+        match &mut self.compare {
+            Some(compare) => {
+                let left = &self.hosts[self.active].model;
+                let right = &self.hosts[compare.other].model;
+                let rows = crate::compare::compare(&left.ports, &right.ports).len();
+                compare.scroll = compare.scroll.min(rows.saturating_sub(1));
+                crate::ui::compare::render(left, right, compare.scroll, frame, area);
+            }
+            None => app::view(&mut self.hosts[self.active].model, frame, area),
+        }
+        self.profile.apply(frame.buffer_mut());
+        for host in &mut self.hosts {
+            host.model.needs_render = false;
+        }
+    }
+
+    /// Clean up before `process::exit`, which skips destructors.
+    pub fn shutdown(&mut self) {
+        for host in &mut self.hosts {
+            host.shutdown();
+        }
+        sshfwd_core::ssh::system::stop_masters();
+    }
+}
+
+/// Group forward of `remote_port` on the `index`-th host.
+fn forward_on(
+    model: &mut Model,
+    remote_port: u16,
+    index: usize,
+    port_offset: u16,
+) -> Vec<ForwardCommand> {
+    match group_local_port(remote_port, index, port_offset) {
+        Some(local_port) => app::group_forward(model, remote_port, local_port),
+        None => {
+            model.log_event(
+                EventLevel::Warn,
+                format!("group forward of :{remote_port} skipped: no local port left"),
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Local port of the `index`-th host in a group forward of `remote_port`.
+fn group_local_port(remote_port: u16, index: usize, port_offset: u16) -> Option<u16> {
+    let offset = u16::try_from(index).ok()?.checked_mul(port_offset)?;
+    remote_port.checked_add(offset)
+}
+
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    fn hosts(count: usize) -> Hosts {
+        Hosts::new(
+            (0..count)
+                .map(|i| {
+                    let (fwd_cmd_tx, _) = tokio::sync::mpsc::unbounded_channel();
+                    let (agent_cmd_tx, _) = tokio::sync::mpsc::unbounded_channel();
+                    Host::new(Model::new(format!("web{i}")), fwd_cmd_tx, agent_cmd_tx)
+                })
+                .collect(),
+            DEFAULT_PORT_OFFSET,
+            Vec::new(),
+        )
+    }
+
+    fn key(c: char) -> Message {
+        Message::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn brackets_and_digits_switch_hosts() {
+        let mut hosts = hosts(3);
+        hosts.handle_input(key('['));
+        assert_eq!(hosts.active, 2);
+        hosts.handle_input(key(']'));
+        assert_eq!(hosts.active, 0);
+        hosts.handle_input(key('2'));
+        assert_eq!(hosts.active, 1);
+        // No fourth host: the key goes to the model.
+        hosts.handle_input(key('4'));
+        assert_eq!(hosts.active, 1);
+
+        // Modals keep their keys.
+        hosts.hosts[1].model.modal = ModalState::Timeline {
+            port: None,
+            scroll: 0,
+        };
+        assert!(hosts.host_key(&key(']')).is_none());
+
+        // A single host never switches.
+        assert!(self::hosts(1).host_key(&key(']')).is_none());
+    }
+
+    #[test]
+    fn compare_picks_another_host() {
+        let mut hosts = hosts(3);
+        hosts.handle_input(key('C'));
+        assert_eq!(hosts.compare.as_ref().map(|c| c.other), Some(1));
+        hosts.handle_input(key('['));
+        // Wraps past the host on the left.
+        assert_eq!(hosts.compare.as_ref().map(|c| c.other), Some(2));
+        assert_eq!(hosts.active, 0);
+        hosts.handle_input(key('q'));
+        assert!(hosts.compare.is_none());
+        assert!(hosts.running());
+    }
+
+    #[test]
+    fn group_forwards_spread_local_ports() {
+        assert_eq!(group_local_port(443, 0, 1000), Some(443));
+        assert_eq!(group_local_port(443, 2, 1000), Some(2443));
+        assert_eq!(group_local_port(65000, 1, 1000), None);
+    }
+
+    #[test]
+    fn startup_prompt_comes_on_screen_and_replies() {
+        let mut hosts = hosts(2);
+        let (reply, mut answer) = tokio::sync::oneshot::channel();
+        hosts.update(
+            1,
+            Message::Prompt(app::Prompt {
+                question: "[sudo] password on web1".into(),
+                kind: app::PromptKind::Password,
+                reply,
+            }),
+        );
+        assert_eq!(hosts.active, 1);
+        // Digits are typed into the prompt, not host switches.
+        for c in "pw1".chars() {
+            hosts.handle_input(key(c));
+        }
+        assert_eq!(hosts.active, 1);
+        hosts.handle_input(Message::Key(KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::NONE,
+        )));
+        assert_eq!(answer.try_recv().unwrap().as_deref(), Some("pw1"));
+        assert_eq!(hosts.hosts[1].model.modal, ModalState::None);
+
+        hosts.update(
+            0,
+            Message::StartupFailed {
+                error: "unreachable".into(),
+                hint: None,
+            },
+        );
+        assert!(hosts.running() && hosts.startup_failure().is_none());
+    }
+}
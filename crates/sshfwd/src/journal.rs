@@ -0,0 +1,352 @@
+//! The message journal (`--journal <file>`) and what `sshfwd replay <file>`
+//! needs to play it back.
+//!
+//! A journal is JSON lines: a header naming the destinations, then every
+//! message the main loop applied, stamped with the milliseconds since the
+//! start and the host it went to — none for terminal input, which
+//! `Hosts::handle_input` routes itself. Replaying feeds the same messages
+//! through `app::update` into fresh models, so a "the selection jumped" report
+//! can be watched again frame by frame.
+//!
+//! API calls carry reply channels and are left out. Discovery errors come back
+//! as their message and hint, and a startup prompt without anyone waiting for
+//! its answer.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyEvent, MouseEvent};
+use serde::{Deserialize, Serialize};
+use sshfwd_common::types::{AgentError, AgentHello, CommandReply, ScanResult};
+
+use crate::app::{self, Message, Model, Prompt, PromptKind, StartupStage};
+use crate::forward::ForwardEvent;
+use crate::hosts::{self, Host, Hosts};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    destinations: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// Milliseconds since the journal was opened.
+    pub ms: u64,
+    /// Index of the host the message went to; `None` for terminal input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<usize>,
+    pub msg: Recorded,
+}
+
+/// A `Message` as journaled.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recorded {
+    Hello(AgentHello),
+    Scan(ScanResult),
+    Warning(String),
+    AgentError(AgentError),
+    Error {
+        message: String,
+        hint: Option<String>,
+    },
+    Reply(CommandReply),
+    StreamEnded,
+    ConnectionLost,
+    Reconnecting,
+    Reconnected,
+    Stalled,
+    Resumed(std::time::Duration),
+    Startup(StartupStage),
+    StartupFailed {
+        error: String,
+        hint: Option<String>,
+    },
+    Prompt {
+        question: String,
+        kind: PromptKind,
+    },
+    LocalScan(ScanResult),
+    LocalScanError(String),
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Forward(ForwardEvent),
+    Tick,
+    Resize(u16, u16),
+    Focus(bool),
+    Preview {
+        remote_port: u16,
+        result: Result<crate::preview::Preview, String>,
+    },
+}
+
+impl Recorded {
+    fn from_message(msg: &Message) -> Option<Self> {
+        Some(match msg {
+            Message::AgentHello(hello) => Self::Hello(hello.clone()),
+            Message::ScanReceived(scan) => Self::Scan(scan.clone()),
+            Message::DiscoveryWarning(w) => Self::Warning(w.clone()),
+            Message::AgentError(e) => Self::AgentError(e.clone()),
+            Message::DiscoveryError(e) => Self::Error {
+                message: e.to_string(),
+                hint: e.hint(),
+            },
+            Message::AgentReply(reply) => Self::Reply(reply.clone()),
+            Message::StreamEnded => Self::StreamEnded,
+            Message::ConnectionLost => Self::ConnectionLost,
+            Message::Reconnecting => Self::Reconnecting,
+            Message::Reconnected => Self::Reconnected,
+            Message::DiscoveryStalled => Self::Stalled,
+            Message::Resumed(slept) => Self::Resumed(*slept),
+            Message::Startup(stage) => Self::Startup(*stage),
+            Message::StartupFailed { error, hint } => Self::StartupFailed {
+                error: error.clone(),
+                hint: hint.clone(),
+            },
+            Message::Prompt(prompt) => Self::Prompt {
+                question: prompt.question.clone(),
+                kind: prompt.kind,
+            },
+            Message::LocalScanReceived(scan) => Self::LocalScan(scan.clone()),
+            Message::LocalScanError(e) => Self::LocalScanError(e.clone()),
+            Message::Key(key) => Self::Key(*key),
+            Message::Mouse(mouse) => Self::Mouse(*mouse),
+            Message::ForwardEvent(event) => Self::Forward(event.clone()),
+            Message::Tick => Self::Tick,
+            Message::Resize(w, h) => Self::Resize(*w, *h),
+            Message::Focus(focused) => Self::Focus(*focused),
+            Message::Api(_) => return None,
+            Message::Preview {
+                remote_port,
+                result,
+            } => Self::Preview {
+                remote_port: *remote_port,
+                result: result.clone(),
+            },
+        })
+    }
+
+    /// The message to apply again, or `Err` with a discovery error's message
+    /// and hint, which have no `DiscoveryError` to rebuild.
+    fn into_message(self) -> Result<Message, (String, Option<String>)> {
+        Ok(match self {
+            Self::Hello(hello) => Message::AgentHello(hello),
+            Self::Scan(scan) => Message::ScanReceived(scan),
+            Self::Warning(w) => Message::DiscoveryWarning(w),
+            Self::AgentError(e) => Message::AgentError(e),
+            Self::Error { message, hint } => return Err((message, hint)),
+            Self::Reply(reply) => Message::AgentReply(reply),
+            Self::StreamEnded => Message::StreamEnded,
+            Self::ConnectionLost => Message::ConnectionLost,
+            Self::Reconnecting => Message::Reconnecting,
+            Self::Reconnected => Message::Reconnected,
+            Self::Stalled => Message::DiscoveryStalled,
+            Self::Resumed(slept) => Message::Resumed(slept),
+            Self::Startup(stage) => Message::Startup(stage),
+            Self::StartupFailed { error, hint } => Message::StartupFailed { error, hint },
+            Self::Prompt { question, kind } => Message::Prompt(Prompt {
+                question,
+                kind,
+                reply: tokio::sync::oneshot::channel().0,
+            }),
+            Self::LocalScan(scan) => Message::LocalScanReceived(scan),
+            Self::LocalScanError(e) => Message::LocalScanError(e),
+            Self::Key(key) => Message::Key(key),
+            Self::Mouse(mouse) => Message::Mouse(mouse),
+            Self::Forward(event) => Message::ForwardEvent(event),
+            Self::Tick => Message::Tick,
+            Self::Resize(w, h) => Message::Resize(w, h),
+            Self::Focus(focused) => Message::Focus(focused),
+            Self::Preview {
+                remote_port,
+                result,
+            } => Message::Preview {
+                remote_port,
+                result,
+            },
+        })
+    }
+}
+
+/// Writes the journal; each entry is flushed as it is recorded, so a crash
+/// loses nothing.
+pub struct Journal {
+    out: LineWriter<File>,
+    started: Instant,
+}
+
+impl Journal {
+    pub fn create(path: &Path, destinations: &[String]) -> io::Result<Self> {
+        let mut journal = Self {
+            out: LineWriter::new(File::create(path)?),
+            started: Instant::now(),
+        };
+        journal.write(&Header {
+            destinations: destinations.to_vec(),
+        })?;
+        Ok(journal)
+    }
+
+    /// Record `msg`, about to be applied to `host` (`None` for input).
+    pub fn record(&mut self, host: Option<usize>, msg: &Message) -> io::Result<()> {
+        let Some(msg) = Recorded::from_message(msg) else {
+            return Ok(());
+        };
+        let ms = self.started.elapsed().as_millis() as u64;
+        self.write(&Entry { ms, host, msg })
+    }
+
+    fn write(&mut self, value: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, value)?;
+        self.out.write_all(b"\n")
+    }
+}
+
+/// A journal read back: its hosts, as fresh models that persist and notify
+/// nothing, and the entries to apply to them.
+pub struct Replay {
+    pub hosts: Hosts,
+    pub entries: Vec<Entry>,
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut lines = BufReader::new(file).lines().enumerate();
+        let parse_error =
+            |n: usize, e: &dyn std::fmt::Display| format!("{}:{}: {e}", path.display(), n + 1);
+        let header: Header = match lines.next() {
+            Some((n, line)) => {
+                let line = line.map_err(|e| parse_error(n, &e))?;
+                serde_json::from_str(&line).map_err(|e| parse_error(n, &e))?
+            }
+            None => return Err(format!("{}: empty journal", path.display())),
+        };
+        let mut entries = Vec::new();
+        for (n, line) in lines {
+            let line = line.map_err(|e| parse_error(n, &e))?;
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                // A journal cut off mid-line by a crash still replays.
+                Err(e) if e.is_eof() => break,
+                Err(e) => return Err(parse_error(n, &e)),
+            }
+        }
+
+        let hosts = header
+            .destinations
+            .into_iter()
+            .map(|destination| {
+                let mut model = Model::new(destination);
+                model.notifications_enabled = false;
+                model.persist_forwards = false;
+                // Commands have nowhere to go.
+                let (fwd_cmd_tx, _) = tokio::sync::mpsc::unbounded_channel();
+                let (agent_cmd_tx, _) = tokio::sync::mpsc::unbounded_channel();
+                Host::new(model, fwd_cmd_tx, agent_cmd_tx)
+            })
+            .collect();
+        Ok(Self {
+            hosts: Hosts::new(hosts, hosts::DEFAULT_PORT_OFFSET, Vec::new()),
+            entries,
+        })
+    }
+
+    /// When `entry` is due after the replay started, at `speed` times the
+    /// recorded pace.
+    pub fn due(entry: &Entry, speed: f64) -> Duration {
+        Duration::from_millis(entry.ms).div_f64(speed)
+    }
+
+    /// Apply one entry, as the main loop did.
+    pub fn apply(hosts: &mut Hosts, entry: Entry) {
+        let msg = match entry.msg.into_message() {
+            Ok(msg) => msg,
+            Err((message, hint)) => {
+                if let Some(host) = entry.host.and_then(|i| hosts.hosts.get_mut(i)) {
+                    app::discovery_failed(&mut host.model, message, hint);
+                }
+                return;
+            }
+        };
+        match entry.host {
+            None => hosts.handle_input(msg),
+            Some(i) if i < hosts.hosts.len() => hosts.update(i, msg),
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{DiscoveryError, SshError};
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use sshfwd_common::types::{ListeningPort, Protocol};
+
+    fn scan(ports: &[u16]) -> ScanResult {
+        ScanResult {
+            agent_version: "0.1.0".into(),
+            hostname: "box".into(),
+            username: "dev".into(),
+            is_root: false,
+            uid: Some(1000),
+            ports: ports
+                .iter()
+                .map(|&port| ListeningPort {
+                    protocol: Protocol::Tcp,
+                    local_addr: "127.0.0.1".into(),
+                    port,
+                    process: None,
+                    probe: None,
+                    bindings: Vec::new(),
+                    stats: None,
+                })
+                .collect(),
+            warnings: Vec::new(),
+            scan_index: 1,
+            scan_duration_ms: None,
+            scanned_at: None,
+            host: None,
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_session() {
+        let path = std::env::temp_dir().join(format!("sshfwd-journal-{}", std::process::id()));
+        let destinations = vec!["dev@box".to_string()];
+        let key = |c| Message::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        let mut journal = Journal::create(&path, &destinations).unwrap();
+        for (host, msg) in [
+            (Some(0), Message::ScanReceived(scan(&[8080, 22, 5432]))),
+            (None, key('j')),
+            (None, key('j')),
+            (
+                Some(0),
+                Message::DiscoveryError(DiscoveryError::Ssh(SshError::ChannelLimit(
+                    "resource shortage".into(),
+                ))),
+            ),
+        ] {
+            journal.record(host, &msg).unwrap();
+        }
+        drop(journal);
+
+        let Replay { mut hosts, entries } = Replay::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(entries.windows(2).all(|w| w[0].ms <= w[1].ms));
+        for entry in entries {
+            Replay::apply(&mut hosts, entry);
+        }
+        let model = &hosts.hosts[0].model;
+        assert_eq!(model.destination, "dev@box");
+        assert_eq!(model.selected_port(), Some(8080));
+        assert!(matches!(
+            &model.modal,
+            app::ModalState::Error { hint, .. } if hint.contains("MaxSessions")
+        ));
+    }
+}
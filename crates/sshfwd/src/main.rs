@@ -1,54 +1,307 @@
+// Without `tui` the model's interactive side (key actions, modals, view
+// state) still compiles but nothing reaches it.
+#![cfg_attr(not(feature = "tui"), allow(dead_code))]
+
+mod api;
 mod app;
+mod audit;
+mod bench;
+mod broadcast;
+mod browser;
+#[cfg(feature = "tui")]
+mod clipboard;
+mod columns;
+#[cfg(feature = "tui")]
+mod compare;
+mod config;
+mod conflict;
 mod discovery;
 pub mod embedded;
-mod error;
+#[cfg(feature = "tui")]
 mod event;
-mod forward;
+mod export;
+#[cfg(all(test, feature = "tui"))]
+mod harness;
+mod history;
+mod hooks;
+mod hosts;
+#[cfg(feature = "tui")]
+mod journal;
+mod mdns;
 mod notify;
-mod ssh;
+mod output;
+mod pace;
+#[cfg(feature = "tui")]
+mod picker;
+mod policy;
+mod preview;
+mod recent;
+mod rows;
+mod service;
+mod status;
+mod telemetry;
+#[cfg(all(test, feature = "tui"))]
+mod test_support;
+mod text;
+#[cfg(feature = "tui")]
 mod ui;
 
-use std::io;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
 use std::process;
 
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+#[cfg(feature = "tui")]
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+};
+#[cfg(feature = "tui")]
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+#[cfg(feature = "tui")]
 use crossterm::ExecutableCommand;
+#[cfg(feature = "tui")]
 use ratatui::backend::CrosstermBackend;
+#[cfg(feature = "tui")]
 use ratatui::Terminal;
+use sshfwd_common::frame::Framing;
+use sshfwd_common::types::LogLevel;
+use sshfwd_core::engine::{self, HostCommands, HostSettings, StartedSession, Transport};
+use sshfwd_core::{error, forward, ssh};
 
-use app::{Message, Model};
-use discovery::{DiscoveryEvent, DiscoveryStream};
+use app::{Message, Model, Prompt, PromptKind, StartupStage};
+use discovery::{DiscoveryEvent, DiscoveryStream, DiscoveryTiming};
+use error::{DiscoveryError, SshError};
+use forward::access::ListenOptions;
 use forward::persistence;
-use forward::{ForwardEntry, ForwardKey, ForwardManager, ForwardStatus};
+use forward::tuning::TunnelOptions;
+use forward::{ForwardEntry, ForwardKey, ForwardStatus};
+use ssh::agent::{AgentOptions, EmbeddedAgents, Secret, UploadProgress};
+use ssh::session::ConnectStage;
 
-fn main() {
-    // Single-threaded runtime: no worker pool, no work-stealing overhead.
-    // Moves to a dedicated OS thread for discovery I/O after setup.
-    let runtime = tokio::runtime::Builder::new_current_thread()
+/// Most messages applied before a frame is drawn, so a flood cannot starve
+/// the screen.
+#[cfg(feature = "tui")]
+const MAX_BURST: usize = 256;
+
+/// Flags that take a value, so `destinations` can skip it.
+const VALUE_FLAGS: &[&str] = &[
+    "--agent-path",
+    "--agent-log-level",
+    "--remote-dir",
+    "--agent-name",
+    "--scan-interval",
+    "--agent-nice",
+    "--agent-framing",
+    "--output",
+    "--ssh-backend",
+    "--journal",
+    "--record",
+];
+
+/// Single-threaded runtime: no worker pool, no work-stealing overhead.
+fn new_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
-        .expect("failed to create tokio runtime");
+        .expect("failed to create tokio runtime")
+}
 
+fn main() {
     let args: Vec<String> = std::env::args().collect();
+    let command = args.get(1).map_or("", String::as_str);
 
-    if args.len() < 2 {
-        eprintln!("Usage: sshfwd <[user@]hostname> [--agent-path <path>] [--no-notify]");
+    // Without arguments on a terminal, the host picker asks instead.
+    if args.len() < 2 && !io::stdin().is_terminal() {
+        eprintln!(
+            "Usage: sshfwd <[user@]hostname | @group>... [--agent-path <path>] [--no-notify] [--probe] \
+             [--agent-log] [--agent-log-level <level>] [--remote-dir <dir>] \
+             [--agent-name <name>] [--sudo-agent] [--download-agent] \
+             [--agent-seccomp] [--agent-framing json|msgpack] [--lenient-schema] [--agent-nice <n>] \
+             [--only-mine] [--scan-interval <secs>] \
+             [--jump-hosts] [--mux|--no-mux] [--ssh-backend russh|system] [--output tui|json|plain] \
+             [--changes-only] [--journal <file>] [--record <file>] [--ascii]\n       \
+             sshfwd --demo [--no-notify] [--output json|plain]\n       \
+             sshfwd cleanup <[user@]hostname> [--remote-dir <dir>] [--agent-name <name>]\n       \
+             sshfwd snapshot <[user@]hostname> [--csv] [agent options]\n       \
+             sshfwd bench <[user@]hostname> [--size <MB>] [agent options]\n       \
+             sshfwd replay <journal> [--speed <factor>] [--ascii]\n       \
+             sshfwd status [--format text|tmux|json]\n       \
+             sshfwd ports [--json]\n       \
+             sshfwd service <install|uninstall> <[user@]hostname>"
+        );
         process::exit(1);
     }
 
-    let destination = args[1].clone();
+    if command == "cleanup" {
+        let Some(destination) = args.get(2) else {
+            eprintln!("Usage: sshfwd cleanup <[user@]hostname>");
+            process::exit(1);
+        };
+        let config = load_config();
+        let options = agent_options(&args, destination, &config);
+        let transport = transport(&args, &config);
+        new_runtime().block_on(run_cleanup(destination, &options, transport));
+        return;
+    }
 
-    let agent_path = args
-        .iter()
-        .position(|a| a == "--agent-path")
-        .and_then(|i| args.get(i + 1))
-        .map(PathBuf::from);
+    if command == "replay" {
+        let Some(path) = args.get(2) else {
+            eprintln!("Usage: sshfwd replay <journal> [--speed <factor>]");
+            process::exit(1);
+        };
+        let speed = arg_value(&args, "--speed")
+            .map(|s| s.parse::<f64>().ok().filter(|s| *s > 0.0))
+            .unwrap_or(Some(1.0))
+            .unwrap_or_else(|| {
+                eprintln!("--speed takes a positive number");
+                process::exit(1);
+            });
+        run_replay(std::path::Path::new(path), speed, &args);
+    }
+
+    if command == "status" {
+        let format = arg_value(&args, "--format").unwrap_or_else(|| "text".to_string());
+        match status::render(&status::read_all(), &format) {
+            Ok(out) if out.is_empty() => {}
+            Ok(out) => println!("{out}"),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if command == "service" {
+        let (Some(action), Some(destination)) = (args.get(2), args.get(3)) else {
+            eprintln!("Usage: sshfwd service <install|uninstall> <[user@]hostname>");
+            process::exit(1);
+        };
+        run_service(action, destination);
+        return;
+    }
+
+    if command == "ports" {
+        let json = args.iter().any(|a| a == "--json");
+        let out = status::render_tunnels(&status::read_all(), json);
+        if !out.is_empty() {
+            println!("{out}");
+        }
+        return;
+    }
+
+    if command == "snapshot" {
+        let Some(destination) = args.get(2) else {
+            eprintln!("Usage: sshfwd snapshot <[user@]hostname> [--csv]");
+            process::exit(1);
+        };
+        let config = load_config();
+        let options = AgentOptions {
+            upload_progress: false,
+            ..agent_options(&args, destination, &config)
+        };
+        let csv = args.iter().any(|a| a == "--csv");
+        let transport = transport(&args, &config);
+        new_runtime().block_on(run_snapshot(destination, &options, csv, transport));
+        return;
+    }
+
+    if command == "bench" {
+        let Some(destination) = args.get(2) else {
+            eprintln!("Usage: sshfwd bench <[user@]hostname> [--size <MB>]");
+            process::exit(1);
+        };
+        let config = load_config();
+        let options = agent_options(&args, destination, &config);
+        let tunnel = config.tunnel_for(destination).options();
+        let size = arg_value(&args, "--size")
+            .and_then(|mb| mb.parse::<u64>().ok())
+            .filter(|&mb| mb > 0)
+            .unwrap_or(bench::DEFAULT_SIZE_MB);
+        let transport = transport(&args, &config);
+        new_runtime().block_on(run_bench(destination, &options, tunnel, size, transport));
+        return;
+    }
 
     let no_notify = args.iter().any(|a| a == "--no-notify");
+    // `None` for the TUI. Without the `tui` feature JSON lines are the
+    // default; with it, plain text when there is no terminal to draw on
+    // (`sshfwd host | tee log`, CI).
+    let output_arg = arg_value(&args, "--output").or_else(|| {
+        args.iter()
+            .any(|a| a == "--watch-plain")
+            .then(|| "plain".to_string())
+    });
+    let output_format = match output_arg.as_deref() {
+        None if !cfg!(feature = "tui") => Some(output::Format::Json),
+        None if !io::stdout().is_terminal() => Some(output::Format::Plain),
+        None => None,
+        Some("tui") if cfg!(feature = "tui") => None,
+        Some("tui") => fail(
+            "This sshfwd was built without the TUI",
+            Some("use --output json or plain, or build with the `tui` feature"),
+        ),
+        Some("json") => Some(output::Format::Json),
+        Some("plain") => Some(output::Format::Plain),
+        Some(other) => {
+            eprintln!("Unknown --output {other:?}, expected `tui`, `json` or `plain`");
+            process::exit(1);
+        }
+    };
 
-    if let Some(ref path) = agent_path {
+    let config = load_config();
+    // A made-up host instead of the destinations: no SSH, nothing saved.
+    let demo = args.iter().any(|a| a == "--demo");
+    let (destinations, port_offset) = if demo {
+        (vec![sshfwd_core::demo::DESTINATION.to_string()], None)
+    } else {
+        config
+            .expand_groups(&destinations(&args))
+            .unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(1);
+            })
+    };
+    let destinations = if destinations.is_empty() {
+        vec![pick_destination(&args)]
+    } else {
+        destinations
+    };
+    let (destinations, jump_targets) = with_jump_hosts(&args, &config, destinations);
+    // Flushes exported spans when main returns.
+    let _telemetry = telemetry::init(&config.telemetry).unwrap_or_else(|e| {
+        eprintln!("Telemetry disabled: {e}");
+        None
+    });
+    let api_token = match (&config.api.port, &config.api.token) {
+        (Some(_), None) => {
+            eprintln!("[api] port needs a token");
+            process::exit(1);
+        }
+        (_, Some(token)) if token.trim().is_empty() => {
+            eprintln!("[api] token must not be empty");
+            process::exit(1);
+        }
+        (_, token) => token.clone(),
+    };
+    if config
+        .sharing
+        .token
+        .as_ref()
+        .is_some_and(|token| token.trim().is_empty())
+    {
+        eprintln!("[sharing] token must not be empty");
+        process::exit(1);
+    }
+    let listen = config.sharing.listen_options();
+    if listen.is_unguarded() {
+        eprintln!(
+            "Refusing to share forwards on {} without [sharing] allow_peers or token",
+            listen.bind_address()
+        );
+        process::exit(1);
+    }
+    if let Some(path) = arg_value(&args, "--agent-path").map(PathBuf::from) {
         if !path.exists() {
             eprintln!(
                 "Agent binary not found at: {}\nBuild it with: cargo build -p sshfwd-agent",
@@ -58,49 +311,250 @@ fn main() {
         }
     }
 
-    // Channel for incoming reverse-forwarded connections from the SSH server.
-    // Created before Session::connect so ClientHandler can deliver incoming channels.
-    let (forwarded_tx, forwarded_rx) =
-        tokio::sync::mpsc::unbounded_channel::<crate::ssh::session::IncomingForward>();
-
-    // Connect and start discovery before entering TUI
-    eprintln!("Connecting to {destination}...");
-
-    let (initial_stream, session) = runtime.block_on(async {
-        let session = match ssh::session::Session::connect(&destination, Some(forwarded_tx)).await {
-            Ok(s) => s,
+    // One background channel per host — unbounded for infrequent discovery +
+    // tick + forward events. Hosts that fail to connect are skipped unless
+    // they are the only one.
+    let mut hosts = Vec::new();
+    let mut receivers = Vec::new();
+    let mut senders = Vec::new();
+    let pace = pace::Pace::default();
+    for destination in &destinations {
+        let (bg_tx, bg_rx) = crossbeam_channel::unbounded::<Message>();
+        let jump_target = jump_targets.get(destination).map(String::as_str);
+        let started = if demo {
+            Ok(start_demo(no_notify, bg_tx.clone()))
+        } else {
+            start_host(
+                &args,
+                &config,
+                destination,
+                jump_target,
+                &listen,
+                no_notify,
+                output_format.is_none(),
+                bg_tx.clone(),
+            )
+        };
+        match started {
+            Ok(host) => {
+                // Tick thread — plain OS thread, no async needed. It outlives
+                // restarts of the host, which reuse its channel.
+                pace.spawn_ticks(bg_tx.clone());
+                hosts.push(host);
+                receivers.push(bg_rx);
+                senders.push(bg_tx);
+            }
             Err(e) => {
-                eprintln!("Connection failed: {e}");
-                process::exit(1);
+                eprintln!("{e}");
+                if destinations.len() == 1 {
+                    process::exit(1);
+                }
             }
-        };
+        }
+    }
+    if hosts.is_empty() {
+        process::exit(1);
+    }
 
-        eprintln!("Connected. Deploying agent...");
+    // Unprefixed API paths serve the first host, `/v1/hosts/<destination>`
+    // any of them.
+    if let (Some(port), Some(token)) = (config.api.port, api_token) {
+        let api_hosts = hosts
+            .iter()
+            .map(|h| h.model.destination.clone())
+            .zip(senders.iter().cloned())
+            .collect();
+        if let Err(e) = api::spawn(port, token, api_hosts) {
+            eprintln!("Cannot serve API on 127.0.0.1:{port}: {e}");
+            process::exit(1);
+        }
+    }
 
-        // Clone session before discovery consumes it
-        let session_for_fwd = session.clone();
+    let recorder = arg_value(&args, "--record").map(|path| {
+        let started: Vec<String> = hosts.iter().map(|h| h.model.destination.clone()).collect();
+        output::Recorder::open(&PathBuf::from(&path), &started).unwrap_or_else(|e| {
+            eprintln!("Cannot write recording {path}: {e}");
+            process::exit(1);
+        })
+    });
 
-        let stream = match DiscoveryStream::start(session, agent_path.as_deref()).await {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("Discovery failed: {e}");
-                process::exit(1);
-            }
+    let hosts = hosts::Hosts::new(
+        hosts,
+        port_offset.unwrap_or(hosts::DEFAULT_PORT_OFFSET),
+        config.broadcast.clone(),
+    );
+    let signals = spawn_signals();
+    if let Some(format) = output_format {
+        // Drop original senders so bg channels close when all tasks finish
+        drop(senders);
+        let changes_only = args.iter().any(|a| a == "--changes-only");
+        run_stream_output(hosts, receivers, signals, format, changes_only, recorder);
+    }
+    #[cfg(feature = "tui")]
+    {
+        let mut hosts = hosts;
+        hosts.profile = render_profile(&args);
+        let restart = |destination: &str, jump_target: Option<&str>, bg_tx| {
+            start_host(
+                &args,
+                &config,
+                destination,
+                jump_target,
+                &listen,
+                no_notify,
+                true,
+                bg_tx,
+            )
         };
+        run_tui(
+            hosts,
+            receivers,
+            senders,
+            restart,
+            pace,
+            signals,
+            arg_value(&args, "--journal"),
+            recorder,
+        );
+    }
+}
 
-        (stream, session_for_fwd)
+/// The TUI: draw the hosts and apply keyboard and background messages until
+/// the user quits, a signal arrives or every host is gone, journaling them
+/// to `journal_path` and their events to `recorder` (`--record`).
+/// A host whose startup failed is started over with `restart` on its
+/// channel in `senders` when the user asks for a retry.
+#[cfg(feature = "tui")]
+#[allow(clippy::too_many_arguments)]
+fn run_tui(
+    mut hosts: hosts::Hosts,
+    receivers: Vec<crossbeam_channel::Receiver<Message>>,
+    senders: Vec<crossbeam_channel::Sender<Message>>,
+    mut restart: impl FnMut(
+        &str,
+        Option<&str>,
+        crossbeam_channel::Sender<Message>,
+    ) -> Result<hosts::Host, String>,
+    pace: pace::Pace,
+    signals: crossbeam_channel::Receiver<()>,
+    journal_path: Option<String>,
+    mut recorder: Option<output::Recorder>,
+) -> ! {
+    let mut journal = journal_path.map(|path| {
+        // The hosts that started, so entries' host indices line up.
+        let started: Vec<String> = hosts
+            .hosts
+            .iter()
+            .map(|host| host.model.destination.clone())
+            .collect();
+        journal::Journal::create(&PathBuf::from(&path), &started).unwrap_or_else(|e| {
+            eprintln!("Cannot write journal {path}: {e}");
+            process::exit(1);
+        })
     });
 
-    // Install panic hook that restores terminal
+    let mut terminal = enter_tui();
+
+    // Initial render
+    terminal
+        .draw(|frame| hosts.view(frame))
+        .expect("failed to draw");
+
+    let kb_rx = spawn_keyboard();
+
+    // Main loop on the main OS thread — completely independent of tokio.
+    // crossbeam::Select multiplexes keyboard + every host's background channel.
+    // Messages already queued behind the one that woke the loop (a burst of
+    // forward events, a scan and its forwards) are applied before drawing
+    // one frame for all of them.
+    let mut select = crossbeam_channel::Select::new();
+    select.recv(&kb_rx);
+    for rx in &receivers {
+        select.recv(rx);
+    }
+    let signal = select.recv(&signals);
+    let mut activity = pace::Activity::new(pace);
+    'main: while hosts.running() {
+        let mut next = Some(select.select());
+        let mut handled = 0;
+        while let Some(op) = next.take() {
+            let open = match op.index() {
+                i if i == signal => {
+                    let _ = op.recv(&signals);
+                    break 'main;
+                }
+                0 => op.recv(&kb_rx).map(|msg| {
+                    activity.input(&msg);
+                    if !matches!(msg, Message::Focus(_)) {
+                        record(&mut journal, &mut hosts, None, &msg);
+                        hosts.handle_input(msg);
+                    }
+                }),
+                i => op.recv(&receivers[i - 1]).map(|msg| {
+                    record(&mut journal, &mut hosts, Some(i - 1), &msg);
+                    if let Err(e) = recorder.as_mut().map_or(Ok(()), |r| r.record(i - 1, &msg)) {
+                        recorder = None;
+                        hosts.hosts[hosts.active]
+                            .model
+                            .log_event(app::EventLevel::Error, format!("recording stopped: {e}"));
+                    }
+                    hosts.update(i - 1, msg)
+                }),
+            };
+            if open.is_err() {
+                break 'main;
+            }
+            handled += 1;
+            if handled < MAX_BURST && hosts.running() {
+                next = select.try_select().ok();
+            }
+        }
+        activity.refresh(std::time::Instant::now());
+        while let Some((index, destination)) = hosts.take_restart() {
+            let jump_target = hosts.hosts[index].model.jump_target.clone();
+            match restart(&destination, jump_target.as_deref(), senders[index].clone()) {
+                Ok(host) => hosts.replace(index, host),
+                Err(error) => hosts.update(index, Message::StartupFailed { error, hint: None }),
+            }
+        }
+
+        if hosts.needs_render() {
+            terminal
+                .draw(|frame| hosts.view(frame))
+                .expect("failed to draw");
+        }
+    }
+
+    // Restore terminal and exit immediately. Dropping crossterm's
+    // read() thread has no clean cancellation — so skip all
+    // destructors via process::exit().
+    leave_tui();
+    hosts.shutdown();
+    if let Some((error, hint)) = hosts.startup_failure() {
+        fail(error, hint);
+    }
+    process::exit(0);
+}
+
+/// How to draw for this terminal; `--ascii` forces the limited profile.
+#[cfg(feature = "tui")]
+fn render_profile(args: &[String]) -> ui::profile::Profile {
+    ui::profile::Profile::detect(args.iter().any(|a| a == "--ascii"))
+}
+
+#[cfg(feature = "tui")]
+type Tui = Terminal<CrosstermBackend<io::BufWriter<io::Stdout>>>;
+
+/// Switch the terminal to raw mode on the alternate screen, with mouse and
+/// focus reporting, and restore it on panic.
+#[cfg(feature = "tui")]
+fn enter_tui() -> Tui {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
-        let _ = io::stdout().execute(DisableMouseCapture);
-        let _ = terminal::disable_raw_mode();
-        let _ = io::stdout().execute(LeaveAlternateScreen);
+        leave_tui();
         original_hook(info);
     }));
 
-    // Enter TUI mode
     terminal::enable_raw_mode().expect("failed to enable raw mode");
     io::stdout()
         .execute(EnterAlternateScreen)
@@ -108,15 +562,261 @@ fn main() {
     io::stdout()
         .execute(EnableMouseCapture)
         .expect("failed to enable mouse capture");
+    // Not every terminal reports focus; those just never go idle by focus.
+    let _ = io::stdout().execute(EnableFocusChange);
 
     let backend = CrosstermBackend::new(io::BufWriter::new(io::stdout()));
-    let mut terminal = Terminal::new(backend).expect("failed to create terminal");
+    Terminal::new(backend).expect("failed to create terminal")
+}
+
+#[cfg(feature = "tui")]
+fn leave_tui() {
+    let _ = io::stdout().execute(DisableFocusChange);
+    let _ = io::stdout().execute(DisableMouseCapture);
+    let _ = terminal::disable_raw_mode();
+    let _ = io::stdout().execute(LeaveAlternateScreen);
+}
+
+/// Terminal events as messages. The channel is bounded(0) (rendezvous) so
+/// the keyboard thread blocks on send() until the main loop is ready. No
+/// poll() needed; bare read() avoids the use-dev-tty poll(ZERO) bug.
+#[cfg(feature = "tui")]
+fn spawn_keyboard() -> crossbeam_channel::Receiver<Message> {
+    let (kb_tx, kb_rx) = crossbeam_channel::bounded::<Message>(0);
+    std::thread::spawn(move || {
+        while let Ok(evt) = crossterm::event::read() {
+            if let Some(msg) = event::crossterm_event_to_message(evt) {
+                if kb_tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    kb_rx
+}
+
+/// Journal `msg` for `host` (`None` for input) with `--journal`. A failed
+/// write ends the journal and says so on the host on screen.
+#[cfg(feature = "tui")]
+fn record(
+    journal: &mut Option<journal::Journal>,
+    hosts: &mut hosts::Hosts,
+    host: Option<usize>,
+    msg: &Message,
+) {
+    let Some(writer) = journal else {
+        return;
+    };
+    if let Err(e) = writer.record(host, msg) {
+        *journal = None;
+        hosts.hosts[hosts.active]
+            .model
+            .log_event(app::EventLevel::Error, format!("journal stopped: {e}"));
+    }
+}
+
+/// `sshfwd replay <journal>`: apply a journal's messages to fresh models at
+/// `speed` times the recorded pace, drawing as the main loop did. Space
+/// pauses, `q` / `Esc` / `Ctrl+C` quit; the last frame stays until a key.
+#[cfg(feature = "tui")]
+fn run_replay(path: &std::path::Path, speed: f64, args: &[String]) -> ! {
+    use crossterm::event::{KeyCode, KeyModifiers};
 
-    let mut model = Model::new(destination.clone());
+    let journal::Replay { mut hosts, entries } = journal::Replay::open(path).unwrap_or_else(|e| {
+        eprintln!("Cannot replay {e}");
+        process::exit(1);
+    });
+    hosts.profile = render_profile(args);
+    let mut terminal = enter_tui();
+    let kb_rx = spawn_keyboard();
+    let quit = |msg: &Message| match msg {
+        Message::Key(key) => {
+            matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        }
+        _ => false,
+    };
+    let pause = |msg: &Message| matches!(msg, Message::Key(key) if key.code == KeyCode::Char(' '));
+
+    let mut started = std::time::Instant::now();
+    let mut paused_at: Option<std::time::Instant> = None;
+    let mut entries = entries.into_iter().peekable();
+    'replay: while let Some(entry) = entries.peek() {
+        let due = started + journal::Replay::due(entry, speed);
+        let input = if paused_at.is_some() {
+            kb_rx.recv().ok()
+        } else {
+            kb_rx
+                .recv_timeout(due.saturating_duration_since(std::time::Instant::now()))
+                .ok()
+        };
+        match input {
+            Some(msg) if quit(&msg) => break 'replay,
+            Some(msg) if pause(&msg) => match paused_at.take() {
+                // Shift the schedule by the pause.
+                Some(at) => started += at.elapsed(),
+                None => paused_at = Some(std::time::Instant::now()),
+            },
+            Some(Message::Resize(w, h)) => hosts.handle_input(Message::Resize(w, h)),
+            Some(_) => {}
+            None if paused_at.is_none() => {
+                let entry = entries.next().expect("peeked");
+                journal::Replay::apply(&mut hosts, entry);
+            }
+            None => {}
+        }
+        if hosts.needs_render() {
+            terminal
+                .draw(|frame| hosts.view(frame))
+                .expect("failed to draw");
+        }
+        if entries.peek().is_none() {
+            // Wait on the last frame.
+            let _ = kb_rx.recv();
+        }
+    }
+
+    leave_tui();
+    process::exit(0);
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_replay(_path: &std::path::Path, _speed: f64, _args: &[String]) -> ! {
+    fail(
+        "sshfwd replay needs the TUI",
+        Some("build with the `tui` feature"),
+    );
+}
+
+/// Positional arguments: every destination (or `@group`) to open, in order,
+/// without duplicates.
+fn destinations(args: &[String]) -> Vec<String> {
+    let mut destinations: Vec<String> = Vec::new();
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            rest.next();
+        } else if !arg.starts_with("--") && !destinations.contains(arg) {
+            destinations.push(arg.clone());
+        }
+    }
+    destinations
+}
+
+/// Ask for a destination with the host picker; exits when there is no
+/// terminal or the user quits.
+#[cfg(feature = "tui")]
+fn pick_destination(args: &[String]) -> String {
+    if !io::stdin().is_terminal() {
+        eprintln!("No destination given");
+        process::exit(1);
+    }
+    let candidates = picker::candidates(recent::load_recent(), ssh::config::host_aliases());
+    match picker::run(candidates, render_profile(args)) {
+        Ok(Some(destination)) => destination,
+        Ok(None) => process::exit(0),
+        Err(e) => {
+            eprintln!("Host picker failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn pick_destination(_args: &[String]) -> String {
+    eprintln!("No destination given");
+    process::exit(1);
+}
+
+/// With `--jump-hosts` (or `[discovery] jump_hosts`), open each destination's
+/// ProxyJump hops too, in front of it. Also returns the destination each hop
+/// leads to.
+fn with_jump_hosts(
+    args: &[String],
+    config: &config::Config,
+    destinations: Vec<String>,
+) -> (Vec<String>, HashMap<String, String>) {
+    let flag = args.iter().any(|a| a == "--jump-hosts");
+    let mut expanded: Vec<String> = Vec::new();
+    let mut targets = HashMap::new();
+    for destination in destinations {
+        if flag || config.discovery_for(&destination).jump_hosts == Some(true) {
+            for hop in ssh::config::jump_hosts(&destination) {
+                if !expanded.contains(&hop) {
+                    targets.insert(hop.clone(), destination.clone());
+                    expanded.push(hop);
+                }
+            }
+        }
+        if !expanded.contains(&destination) {
+            expanded.push(destination);
+        }
+    }
+    (expanded, targets)
+}
+
+/// Open `destination`'s tab and start its sidecar thread, which reports to
+/// `bg_tx`: the sidecar connects and deploys the agent (reporting
+/// each `StartupStage`, asking for a sudo password or agent download in a
+/// modal, or on the terminal without `tui`), then keeps the session alive.
+/// `jump_target` is set when the host is only open as the ProxyJump hop of
+/// another.
+#[allow(clippy::too_many_arguments)]
+fn start_host(
+    args: &[String],
+    config: &config::Config,
+    destination: &str,
+    jump_target: Option<&str>,
+    listen: &ListenOptions,
+    no_notify: bool,
+    tui: bool,
+    bg_tx: crossbeam_channel::Sender<Message>,
+) -> Result<hosts::Host, String> {
+    #[cfg(feature = "tui")]
+    let accent = ui::accent_color(destination, config.color_for(destination))?;
+    let mut agent_options = agent_options(args, destination, config);
+    let timing = discovery_timing(args, destination, config);
+    // An error, not an exit: on a TUI restart the terminal is still raw.
+    let audit_log = config
+        .audit
+        .enabled
+        .then(|| {
+            let path = config
+                .audit
+                .path
+                .clone()
+                .unwrap_or_else(audit::AuditLog::default_path);
+            audit::AuditLog::open(&path, destination)
+                .map_err(|e| format!("Cannot open audit log {}: {e}", path.display()))
+        })
+        .transpose()?;
+    agent_options.scan_interval = (timing.scan_interval != discovery::DEFAULT_SCAN_INTERVAL)
+        .then_some(timing.scan_interval.as_secs());
+    // Hosts start side by side under the TUI: no stderr progress bars, the
+    // splash and tab show it instead.
+    agent_options.upload_progress = false;
+    agent_options.on_upload = Some(upload_progress(bg_tx.clone()));
+    let transport = transport(args, config);
+    let tunnel = config.tunnel_for(destination).options();
+
+    let mut model = Model::new(destination.to_string());
     model.notifications_enabled = !no_notify;
+    model.stale_after = timing.stale_after;
+    model.policy = config.policy_for(destination);
+    model.columns = config.table.clone();
+    model.open_http = config.browser.open_http;
+    model.audit = audit_log;
+    model.hooks = hooks::Hooks::new(config.hooks.clone(), destination);
+    model.status_file = Some(status::StatusFile::new(destination, listen.bind_address()));
+    model.mdns = mdns_advertiser(&config.sharing, destination);
+    #[cfg(feature = "tui")]
+    {
+        model.accent = accent;
+    }
+    model.jump_target = jump_target.map(str::to_string);
 
     // Load persisted forwards (all start as Paused — first scan triggers activation)
-    let persisted = persistence::load_forwards(&destination);
+    let persisted = persistence::load_forwards(destination);
     for pf in persisted {
         let key = ForwardKey {
             kind: pf.kind,
@@ -128,256 +828,755 @@ fn main() {
                 local_port: pf.local_port,
                 status: ForwardStatus::Paused,
                 active_connections: 0,
+                open_wait: Default::default(),
             },
         );
     }
 
-    // Initial render
-    terminal
-        .draw(|frame| app::view(&mut model, frame))
-        .expect("failed to draw");
-    model.needs_render = false;
-
-    // Keyboard channel — bounded(0) (rendezvous) so the keyboard thread
-    // blocks on send() until the main loop is ready. No poll() needed;
-    // bare read() avoids the use-dev-tty poll(ZERO) bug.
-    let (kb_tx, kb_rx) = crossbeam_channel::bounded::<Message>(0);
-
-    std::thread::spawn(move || {
-        while let Ok(evt) = crossterm::event::read() {
-            if let Some(msg) = event::crossterm_event_to_message(evt) {
-                if kb_tx.send(msg).is_err() {
-                    break;
-                }
-            }
-        }
-    });
-
-    // Background channel — unbounded for infrequent discovery + tick + forward events
-    let (bg_tx, bg_rx) = crossbeam_channel::unbounded::<Message>();
-
     // Forward command channel (sync → async).
     // The receiver is owned by the sidecar; it is reused across reconnect cycles
     // so that the model's command stream is never interrupted.
     let (fwd_cmd_tx, fwd_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
 
-    // Discovery + ForwardManager sidecar with transparent reconnect.
+    // Agent command channel (sync → async), reused across reconnect cycles like
+    // the forward command channel. Commands are written to the agent's stdin.
+    let (agent_cmd_tx, agent_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // Startup, then the discovery + ForwardManager sidecar with transparent
+    // reconnect. Each host gets its own runtime: the session's tasks live on
+    // the runtime that connected it.
     let disc_tx = bg_tx.clone();
     let fwd_event_tx = bg_tx.clone();
+    let destination = destination.to_string();
+    let remember = jump_target.is_none();
+    let listen = listen.clone();
     std::thread::spawn(move || {
-        runtime.block_on(run_sidecar(
-            initial_stream,
-            session,
-            forwarded_rx,
-            fwd_cmd_rx,
-            disc_tx,
-            fwd_event_tx,
-            destination,
-            agent_path,
-        ));
+        new_runtime().block_on(async move {
+            let (forwarded_tx, forwarded_rx) =
+                tokio::sync::mpsc::unbounded_channel::<crate::ssh::session::IncomingForward>();
+            let Some((initial_stream, session)) = start_session(
+                &destination,
+                forwarded_tx,
+                transport,
+                &mut agent_options,
+                tui,
+                &disc_tx,
+            )
+            .await
+            else {
+                return;
+            };
+            if remember {
+                recent::save_recent(&destination);
+            }
+            engine::run_host(
+                StartedSession {
+                    session,
+                    stream: initial_stream,
+                    forwarded: forwarded_rx,
+                },
+                HostCommands {
+                    forwards: fwd_cmd_rx,
+                    agent: agent_cmd_rx,
+                },
+                disc_tx,
+                fwd_event_tx,
+                HostSettings {
+                    destination,
+                    agent: agent_options,
+                    timing,
+                    listen,
+                    tunnel,
+                    transport,
+                },
+            )
+            .await;
+        });
     });
 
-    // Tick thread — plain OS thread, no async needed
-    let tick_tx = bg_tx.clone();
-    std::thread::spawn(move || loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
-        if tick_tx.send(Message::Tick).is_err() {
-            break;
+    Ok(hosts::Host::new(model, fwd_cmd_tx, agent_cmd_tx).with_events(bg_tx))
+}
+
+/// Report agent upload progress to the host's model as
+/// `StartupStage::Uploading`, once per percent.
+fn upload_progress(bg_tx: crossbeam_channel::Sender<Message>) -> UploadProgress {
+    let last = std::sync::atomic::AtomicU8::new(u8::MAX);
+    UploadProgress(std::sync::Arc::new(move |sent, total| {
+        let percent = (sent * 100).checked_div(total).unwrap_or(100).min(100) as u8;
+        if last.swap(percent, std::sync::atomic::Ordering::Relaxed) != percent {
+            bg_tx
+                .send(Message::Startup(StartupStage::Uploading(percent)))
+                .ok();
         }
+    }))
+}
+
+/// Open the `--demo` host: `demo::run_host` in place of the sidecar, with
+/// forwards neither loaded nor saved.
+fn start_demo(no_notify: bool, bg_tx: crossbeam_channel::Sender<Message>) -> hosts::Host {
+    let destination = sshfwd_core::demo::DESTINATION;
+    let mut model = Model::new(destination.to_string());
+    model.notifications_enabled = !no_notify;
+    model.persist_forwards = false;
+    #[cfg(feature = "tui")]
+    {
+        model.accent = ui::accent_color(destination, None).unwrap_or(model.accent);
+    }
+
+    let (fwd_cmd_tx, fwd_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (agent_cmd_tx, agent_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+    let preview_tx = bg_tx.clone();
+    let events = bg_tx;
+    std::thread::spawn(move || {
+        events.send(Message::Startup(StartupStage::Scanning)).ok();
+        new_runtime().block_on(sshfwd_core::demo::run_host(
+            HostCommands {
+                forwards: fwd_cmd_rx,
+                agent: agent_cmd_rx,
+            },
+            events.clone(),
+            events,
+        ));
     });
 
-    // Drop original sender so bg channel closes when all tasks finish
-    drop(bg_tx);
+    hosts::Host::new(model, fwd_cmd_tx, agent_cmd_tx).with_events(preview_tx)
+}
 
-    // Main loop on the main OS thread — completely independent of tokio.
-    // crossbeam::select! multiplexes keyboard + background channels.
-    while model.running {
-        crossbeam_channel::select! {
-            recv(kb_rx) -> msg => {
-                match msg {
-                    Ok(msg) => {
-                        let cmds = app::update(&mut model, msg);
-                        for cmd in cmds {
-                            let _ = fwd_cmd_tx.send(cmd);
-                        }
-                    }
-                    Err(_) => break,
+/// Connect `destination` and deploy its agent for the first time, reporting
+/// each stage on `bg_tx`. A missing sudo password or agent binary is asked
+/// for (`ask`). `None` once `StartupFailed` has been reported.
+async fn start_session(
+    destination: &str,
+    forwarded_tx: tokio::sync::mpsc::UnboundedSender<crate::ssh::session::IncomingForward>,
+    transport: Transport,
+    agent_options: &mut AgentOptions,
+    tui: bool,
+    bg_tx: &crossbeam_channel::Sender<Message>,
+) -> Option<(DiscoveryStream, ssh::session::Session)> {
+    let report = |stage: StartupStage| {
+        bg_tx.send(Message::Startup(stage)).ok();
+    };
+    let on_connect_stage = |stage: ConnectStage| {
+        report(match stage {
+            ConnectStage::Connecting => StartupStage::Connecting,
+            ConnectStage::Authenticating => StartupStage::Authenticating,
+        })
+    };
+    let result = async {
+        let session = engine::connect(
+            destination,
+            Some(forwarded_tx),
+            transport,
+            &on_connect_stage,
+        )
+        .await
+        .map_err(|e| (format!("Connection to {destination} failed: {e}"), e.hint()))?;
+        report(StartupStage::Deploying);
+
+        let mut sudo_attempts = 0;
+        loop {
+            match DiscoveryStream::start(session.clone(), agent_options).await {
+                Ok(stream) => return Ok((stream, session)),
+                // The password is kept in memory so reconnects can elevate again.
+                Err(DiscoveryError::Ssh(
+                    e @ (SshError::SudoPasswordRequired | SshError::Elevation(_)),
+                )) if sudo_attempts < 3 => {
+                    let question = if sudo_attempts > 0 {
+                        format!("{e}. [sudo] password on {destination}")
+                    } else {
+                        format!("[sudo] password on {destination}")
+                    };
+                    sudo_attempts += 1;
+                    let password = ask(bg_tx, tui, PromptKind::Password, question)
+                        .await
+                        .ok_or_else(|| {
+                            (format!("No sudo password given for {destination}"), None)
+                        })?;
+                    agent_options.sudo_password = Some(Secret(password));
                 }
-            }
-            recv(bg_rx) -> msg => {
-                match msg {
-                    Ok(msg) => {
-                        let cmds = app::update(&mut model, msg);
-                        for cmd in cmds {
-                            let _ = fwd_cmd_tx.send(cmd);
-                        }
+                Err(DiscoveryError::Ssh(SshError::AgentUnavailable(platform)))
+                    if !agent_options.download_agent =>
+                {
+                    let question = format!(
+                        "No agent bundled for {platform}. Download sshfwd-agent v{} \
+                         from GitHub releases?",
+                        env!("CARGO_PKG_VERSION")
+                    );
+                    if ask(bg_tx, tui, PromptKind::YesNo, question).await.is_none() {
+                        return Err((
+                            format!(
+                                "Discovery on {destination} failed: no agent binary for {platform}"
+                            ),
+                            None,
+                        ));
                     }
-                    Err(_) => break,
+                    agent_options.download_agent = true;
+                }
+                Err(e) => {
+                    return Err((format!("Discovery on {destination} failed: {e}"), e.hint()))
                 }
             }
         }
-
-        if model.needs_render {
-            terminal
-                .draw(|frame| app::view(&mut model, frame))
-                .expect("failed to draw");
-            model.needs_render = false;
+    };
+    match result.await {
+        Ok(started) => {
+            report(StartupStage::Scanning);
+            Some(started)
+        }
+        Err((error, hint)) => {
+            bg_tx.send(Message::StartupFailed { error, hint }).ok();
+            None
         }
     }
+}
 
-    // Restore terminal and exit immediately. Dropping crossterm's
-    // read() thread has no clean cancellation — so skip all
-    // destructors via process::exit().
-    io::stdout().execute(DisableMouseCapture).ok();
-    terminal::disable_raw_mode().ok();
-    io::stdout().execute(LeaveAlternateScreen).ok();
+/// Ask the user a startup question for one host: in a modal on its tab
+/// (`tui`), or on the terminal, one host at a time.
+async fn ask(
+    bg_tx: &crossbeam_channel::Sender<Message>,
+    tui: bool,
+    kind: PromptKind,
+    question: String,
+) -> Option<String> {
+    if tui {
+        let (reply, answer) = tokio::sync::oneshot::channel();
+        bg_tx
+            .send(Message::Prompt(Prompt {
+                question,
+                kind,
+                reply,
+            }))
+            .ok()?;
+        return answer.await.ok().flatten();
+    }
+    static TERMINAL: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    tokio::task::spawn_blocking(move || {
+        let _terminal = TERMINAL
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        match kind {
+            PromptKind::Password => prompt_password(&format!("{question}: "))
+                .map_err(|e| eprintln!("Cannot read a password: {e}"))
+                .ok(),
+            PromptKind::YesNo => confirm(&question).then(|| "y".to_string()),
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// `--output json` / `plain`: drive the models without a terminal, printing
+/// each event on stdout in `format` (and to `recorder` with `--record`).
+/// Exits when a sidecar gives up or stdout closes (e.g. `| head`).
+fn run_stream_output(
+    mut hosts: hosts::Hosts,
+    receivers: Vec<crossbeam_channel::Receiver<Message>>,
+    signals: crossbeam_channel::Receiver<()>,
+    format: output::Format,
+    changes_only: bool,
+    mut recorder: Option<output::Recorder>,
+) -> ! {
+    let mut events: Vec<output::EventWriter> = hosts
+        .hosts
+        .iter()
+        .map(|h| {
+            let writer = output::EventWriter::new(h.model.destination.clone(), format);
+            if changes_only {
+                writer.changes_only()
+            } else {
+                writer
+            }
+        })
+        .collect();
+    let mut select = crossbeam_channel::Select::new();
+    for rx in &receivers {
+        select.recv(rx);
+    }
+    let signal = select.recv(&signals);
+    let mut stdout = io::stdout().lock();
+    while hosts.running() && hosts.startup_failure().is_none() {
+        let op = select.select();
+        let i = op.index();
+        if i == signal {
+            let _ = op.recv(&signals);
+            break;
+        }
+        let Ok(msg) = op.recv(&receivers[i]) else {
+            break;
+        };
+        if events[i].write(&mut stdout, &msg).is_err() {
+            break;
+        }
+        if let Err(e) = recorder.as_mut().map_or(Ok(()), |r| r.record(i, &msg)) {
+            eprintln!("Recording stopped: {e}");
+            recorder = None;
+        }
+        hosts.update(i, msg);
+    }
+    hosts.shutdown();
+    if let Some((error, hint)) = hosts.startup_failure() {
+        fail(error, hint);
+    }
     process::exit(0);
 }
 
-/// If no discovery event arrives within this window, the session is treated as dead.
-/// The agent scans every ~2 s; 12 s gives 6× headroom before forcing a reconnect.
-const DISCOVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(12);
-
-/// Run one session cycle: drive discovery and ForwardManager concurrently.
-///
-/// Returns when the discovery stream ends or times out.  The caller is responsible for
-/// reconnecting and calling this again with a fresh session / stream.
-/// `forwarded_rx` is consumed so callers can provide a fresh one on reconnect.
-async fn run_session_cycle(
-    mut stream: DiscoveryStream,
-    session: ssh::session::Session,
-    mut forwarded_rx: tokio::sync::mpsc::UnboundedReceiver<crate::ssh::session::IncomingForward>,
-    fwd_cmd_rx: &mut tokio::sync::mpsc::UnboundedReceiver<forward::ForwardCommand>,
-    disc_tx: crossbeam_channel::Sender<Message>,
-    fwd_event_tx: crossbeam_channel::Sender<Message>,
-) {
-    // Spawn local port scanner (aborted when this cycle ends).
-    let local_scan = discovery::local::spawn_local_scan(disc_tx.clone());
+/// SIGTERM, SIGHUP and SIGINT (a closing terminal, a service stop, `kill`)
+/// as a channel, so the main loops leave the way `q` does: terminal
+/// restored, status file removed, SSH masters and remote agents stopped.
+/// Ctrl+C in the TUI is a key, not SIGINT.
+fn spawn_signals() -> crossbeam_channel::Receiver<()> {
+    use tokio::signal::unix::{signal, SignalKind};
 
-    let manager = ForwardManager::new(session, fwd_event_tx);
-    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    std::thread::spawn(move || {
+        new_runtime().block_on(async {
+            let (Ok(mut term), Ok(mut hup), Ok(mut int)) = (
+                signal(SignalKind::terminate()),
+                signal(SignalKind::hangup()),
+                signal(SignalKind::interrupt()),
+            ) else {
+                return;
+            };
+            tokio::select! {
+                _ = term.recv() => {}
+                _ = hup.recv() => {}
+                _ = int.recv() => {}
+            }
+            tx.send(()).ok();
+        })
+    });
+    rx
+}
 
-    let manager_fut = manager.run(fwd_cmd_rx, &mut forwarded_rx, shutdown_rx);
-    tokio::pin!(manager_fut);
+/// Print `error`, with `hint` below it, and exit with status 1.
+fn fail(error: &str, hint: Option<&str>) -> ! {
+    eprintln!("{error}");
+    if let Some(hint) = hint {
+        eprintln!("hint: {hint}");
+    }
+    process::exit(1);
+}
 
-    loop {
-        tokio::select! {
-            _ = &mut manager_fut => {
-                // Manager exited (cmd_tx closed or shutdown signal already fired).
-                break;
+/// `sshfwd service install|uninstall <destination>`.
+fn run_service(action: &str, destination: &str) {
+    let manager = service::Manager::current();
+    match action {
+        "install" => {
+            if persistence::load_forwards(destination).is_empty() {
+                eprintln!(
+                    "Note: no saved forwards for {destination} yet; the service \
+                     restores the forwards you start in the TUI."
+                );
             }
-            result = tokio::time::timeout(DISCOVERY_TIMEOUT, stream.next_event()) => {
-                match result {
-                    Ok(Some(DiscoveryEvent::Scan(scan))) => {
-                        disc_tx.send(Message::ScanReceived(scan)).ok();
-                    }
-                    Ok(Some(DiscoveryEvent::Warning(w))) => {
-                        disc_tx.send(Message::DiscoveryWarning(w)).ok();
-                    }
-                    Ok(Some(DiscoveryEvent::Error(e))) => {
-                        disc_tx.send(Message::DiscoveryError(e)).ok();
-                        let _ = shutdown_tx.send(());
-                        (&mut manager_fut).await;
-                        break;
-                    }
-                    Err(_) => {
-                        // No event within DISCOVERY_TIMEOUT — treat as dead session.
-                        let _ = shutdown_tx.send(());
-                        (&mut manager_fut).await;
-                        break;
-                    }
-                    Ok(None) => unreachable!("next_event always returns Some"),
+            match service::install(manager, destination) {
+                Ok((path, start)) => println!("Wrote {}\nStart it with: {start}", path.display()),
+                Err(e) => {
+                    eprintln!("Cannot install service: {e}");
+                    process::exit(1);
                 }
             }
         }
+        "uninstall" => match service::uninstall(manager, destination) {
+            Ok((path, stop)) => println!("Removed {}\nStop it with: {stop}", path.display()),
+            Err(e) => {
+                eprintln!("Cannot remove service: {e}");
+                process::exit(1);
+            }
+        },
+        other => {
+            eprintln!("Unknown service action {other:?}, expected install or uninstall");
+            process::exit(1);
+        }
     }
+}
 
-    local_scan.abort();
+/// `[sharing] mdns`, when forwards are reachable from the LAN at all.
+fn mdns_advertiser(sharing: &config::SharingConfig, destination: &str) -> Option<mdns::Advertiser> {
+    if !sharing.mdns {
+        return None;
+    }
+    if sharing.listen_options().bind_address().is_loopback() {
+        eprintln!("[sharing] mdns ignored: forwards only listen on loopback");
+        return None;
+    }
+    mdns::Advertiser::new(destination)
+        .map_err(|e| eprintln!("{e}"))
+        .ok()
 }
 
-/// Reconnect with exponential backoff until a new SSH session is established.
-/// Returns the new session together with its paired forwarded-channel receiver.
-/// The first attempt is immediate; sleep only occurs after a failed attempt.
-async fn reconnect_with_backoff(
+/// `--ssh-backend` over `[ssh] backend`. Connection sharing is off unless
+/// `--mux` or `[ssh] mux = true` turns it on (`--no-mux` wins): a process
+/// attached to another's connection can't reverse-forward.
+fn transport(args: &[String], config: &config::Config) -> Transport {
+    let backend = match arg_value(args, "--ssh-backend") {
+        Some(name) => name.parse().unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        }),
+        None => config.ssh.backend.unwrap_or_default(),
+    };
+    Transport {
+        backend,
+        mux: !args.iter().any(|a| a == "--no-mux")
+            && (args.iter().any(|a| a == "--mux") || config.ssh.mux.unwrap_or(false)),
+    }
+}
+
+/// Value following `flag` on the command line.
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// `~/.sshfwd/config.toml`; a malformed file aborts startup.
+fn load_config() -> config::Config {
+    match config::Config::load() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Invalid config: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Scan interval and staleness thresholds; `--scan-interval` overrides the
+/// config and must be a whole number of seconds.
+fn discovery_timing(
+    args: &[String],
     destination: &str,
-    disc_tx: &crossbeam_channel::Sender<Message>,
-    backoff: &mut std::time::Duration,
-) -> (
-    ssh::session::Session,
-    tokio::sync::mpsc::UnboundedReceiver<crate::ssh::session::IncomingForward>,
-) {
-    loop {
-        disc_tx.send(Message::Reconnecting).ok();
-
-        let (ftx, frx) =
-            tokio::sync::mpsc::unbounded_channel::<crate::ssh::session::IncomingForward>();
-        match ssh::session::Session::connect(destination, Some(ftx)).await {
-            Ok(new_session) => {
-                *backoff = std::time::Duration::from_secs(1);
-                return (new_session, frx);
-            }
-            Err(_) => {
-                tokio::time::sleep(*backoff).await;
-                *backoff = (*backoff * 2).min(std::time::Duration::from_secs(30));
+    config: &config::Config,
+) -> DiscoveryTiming {
+    let mut discovery = config.discovery_for(destination);
+    if let Some(secs) = usage_arg(args, "--scan-interval", "whole seconds, e.g. 5", |s| {
+        s.parse().ok()
+    }) {
+        discovery.scan_interval = Some(secs);
+    }
+    discovery.timing()
+}
+
+/// Agent settings from `~/.sshfwd/config.toml`, overridden by command-line flags.
+fn agent_options(args: &[String], destination: &str, config: &config::Config) -> AgentOptions {
+    let agent_config = config.agent_for(destination);
+
+    AgentOptions {
+        local_path: arg_value(args, "--agent-path").map(PathBuf::from),
+        probe: args.iter().any(|a| a == "--probe"),
+        destination: destination.to_string(),
+        remote_dir: arg_value(args, "--remote-dir").or(agent_config.remote_dir),
+        agent_name: arg_value(args, "--agent-name").or(agent_config.name),
+        upload_progress: true,
+        on_upload: None,
+        log_level: agent_log_level(args),
+        session_id: AgentOptions::new_session_id(),
+        sudo: args.iter().any(|a| a == "--sudo-agent") || agent_config.sudo.unwrap_or(false),
+        sudo_password: None,
+        download_agent: args.iter().any(|a| a == "--download-agent")
+            || agent_config.download.unwrap_or(false),
+        redact: agent_config.redact,
+        only_mine: args.iter().any(|a| a == "--only-mine")
+            || agent_config.only_mine.unwrap_or(false),
+        seccomp: args.iter().any(|a| a == "--agent-seccomp")
+            || agent_config.seccomp.unwrap_or(false),
+        framing: usage_arg(args, "--agent-framing", "json or msgpack", Framing::parse)
+            .or(agent_config.framing)
+            .unwrap_or_default(),
+        lenient_schema: args.iter().any(|a| a == "--lenient-schema")
+            || agent_config.lenient_schema.unwrap_or(false),
+        // Filled in from `DiscoveryTiming` by `main`.
+        scan_interval: None,
+        nice: usage_arg(
+            args,
+            "--agent-nice",
+            "a nice value from -20 to 19",
+            parse_nice,
+        )
+        .or(agent_config.nice),
+        scan_budget: agent_config.scan_budget,
+        bench: false,
+        embedded: Some(EmbeddedAgents {
+            binary: embedded::get_agent_binary,
+            signature: embedded::get_agent_signature,
+        }),
+    }
+}
+
+/// `--agent-log-level`, or `info` for a bare `--agent-log`. A level the
+/// agent wouldn't know ends startup here rather than on the remote host.
+fn agent_log_level(args: &[String]) -> Option<String> {
+    match arg_value(args, "--agent-log-level") {
+        Some(level) if LogLevel::parse(&level).is_none() => fail(
+            &format!("Unknown --agent-log-level {level:?}"),
+            Some(&format!("use {}", LogLevel::NAMES)),
+        ),
+        Some(level) => Some(level),
+        None => args
+            .iter()
+            .any(|a| a == "--agent-log")
+            .then(|| "info".to_string()),
+    }
+}
+
+/// `flag` parsed with `parse`; a value it rejects ends startup with a usage
+/// error, like `--agent-log-level`, instead of being silently ignored.
+fn usage_arg<T>(
+    args: &[String],
+    flag: &str,
+    expected: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Option<T> {
+    parse_arg(args, flag, parse).unwrap_or_else(|e| fail(&e, Some(&format!("use {expected}"))))
+}
+
+fn parse_arg<T>(
+    args: &[String],
+    flag: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<Option<T>, String> {
+    match arg_value(args, flag) {
+        Some(value) => parse(&value)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {flag} {value:?}")),
+        None => Ok(None),
+    }
+}
+
+/// A `setpriority` nice value.
+fn parse_nice(value: &str) -> Option<i32> {
+    value.parse().ok().filter(|n| (-20..=19).contains(n))
+}
+
+/// Ask a yes/no question on the terminal; anything but `y`/`yes` is no.
+fn confirm(question: &str) -> bool {
+    eprint!("{question} [y/N] ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Read a password from the terminal without echoing it. Ctrl+C or Esc aborts.
+#[cfg(feature = "tui")]
+fn prompt_password(prompt: &str) -> io::Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    eprint!("{prompt}");
+    if let Err(e) = terminal::enable_raw_mode() {
+        eprintln!();
+        return Err(e);
+    }
+    let mut password = String::new();
+    let aborted = loop {
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => break false,
+            KeyCode::Esc => break true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break true,
+            KeyCode::Char(c) => password.push(c),
+            KeyCode::Backspace => {
+                password.pop();
             }
+            _ => {}
         }
+    };
+    let _ = terminal::disable_raw_mode();
+    eprintln!();
+    if aborted {
+        process::exit(130);
     }
+    Ok(password)
 }
 
-/// Top-level sidecar: outer reconnect loop wrapping session cycles.
-#[allow(clippy::too_many_arguments)]
-async fn run_sidecar(
-    initial_stream: DiscoveryStream,
-    initial_session: ssh::session::Session,
-    initial_forwarded_rx: tokio::sync::mpsc::UnboundedReceiver<
-        crate::ssh::session::IncomingForward,
-    >,
-    mut fwd_cmd_rx: tokio::sync::mpsc::UnboundedReceiver<forward::ForwardCommand>,
-    disc_tx: crossbeam_channel::Sender<Message>,
-    fwd_event_tx: crossbeam_channel::Sender<Message>,
-    destination: String,
-    agent_path: Option<PathBuf>,
-) {
-    let mut session = initial_session;
-    let mut stream = initial_stream;
-    let mut forwarded_rx = initial_forwarded_rx;
-    let mut backoff = std::time::Duration::from_secs(1);
-
-    loop {
-        // Notify model of fresh session — triggers Reverse forward reactivation
-        // (also fires on initial startup so persisted Reverse forwards activate).
-        disc_tx.send(Message::Reconnected).ok();
-
-        // Run one session cycle (blocks until stream ends).
-        run_session_cycle(
-            stream,
-            session.clone(),
-            forwarded_rx,
-            &mut fwd_cmd_rx,
-            disc_tx.clone(),
-            fwd_event_tx.clone(),
-        )
-        .await;
-
-        // Session ended — notify model.
-        disc_tx.send(Message::ConnectionLost).ok();
-
-        // Reconnect with backoff; first attempt is immediate.
-        (session, forwarded_rx) =
-            reconnect_with_backoff(&destination, &disc_tx, &mut backoff).await;
-
-        // Deploy agent on the new session.
-        stream = loop {
-            match DiscoveryStream::start(session.clone(), agent_path.as_deref()).await {
-                Ok(s) => break s,
-                Err(_) => {
-                    // Agent deploy failed — treat as another connection loss.
-                    disc_tx.send(Message::ConnectionLost).ok();
-                    (session, forwarded_rx) =
-                        reconnect_with_backoff(&destination, &disc_tx, &mut backoff).await;
+/// Without the `tui` feature there is no raw mode to hide the input, so
+/// passwords are not read at all.
+#[cfg(not(feature = "tui"))]
+fn prompt_password(prompt: &str) -> io::Result<String> {
+    eprintln!("{prompt}");
+    Err(io::Error::other("built without the `tui` feature"))
+}
+
+/// `sshfwd cleanup <host>`: remove the agent and its files from the remote host.
+async fn run_cleanup(destination: &str, options: &AgentOptions, transport: Transport) {
+    eprintln!("Connecting to {destination}...");
+    let session = match engine::connect(destination, None, transport, &|_| {}).await {
+        Ok(s) => s,
+        Err(e) => {
+            fail(&format!("Connection failed: {e}"), e.hint().as_deref());
+        }
+    };
+
+    let remembered = ssh::install_dirs::load_install_dir(destination);
+    match ssh::agent::AgentManager::new(session)
+        .uninstall(options, remembered.as_deref())
+        .await
+    {
+        Ok(leftovers) if leftovers.is_empty() => {
+            eprintln!("Removed sshfwd agent from {destination}.");
+        }
+        Ok(leftovers) => {
+            eprintln!(
+                "Removed sshfwd agent from {destination}; left unrelated files behind: {}",
+                leftovers.join(", ")
+            );
+        }
+        Err(e) => {
+            eprintln!("Cleanup failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Print one scan of `destination` as JSON (or CSV) and exit.
+async fn run_snapshot(destination: &str, options: &AgentOptions, csv: bool, transport: Transport) {
+    let session = match engine::connect(destination, None, transport, &|_| {}).await {
+        Ok(s) => s,
+        Err(e) => {
+            fail(&format!("Connection failed: {e}"), e.hint().as_deref());
+        }
+    };
+    let mut stream = match DiscoveryStream::start(session, options).await {
+        Ok(s) => s,
+        Err(e) => {
+            fail(&format!("Failed to start agent: {e}"), e.hint().as_deref());
+        }
+    };
+    let timeout = DiscoveryTiming::default().timeout;
+    let scan = loop {
+        match tokio::time::timeout(timeout, stream.next_event()).await {
+            Ok(Some(DiscoveryEvent::Scan(scan))) => break scan,
+            Ok(Some(DiscoveryEvent::Warning(w))) => eprintln!("warning: {w}"),
+            Ok(Some(DiscoveryEvent::AgentError(e))) => {
+                eprintln!("warning: agent {}: {}", e.kind, e.message);
+                if let Some(hint) = error::agent_error_hint(e.kind) {
+                    eprintln!("hint: {hint}");
                 }
             }
+            Ok(Some(DiscoveryEvent::Hello(_) | DiscoveryEvent::Reply(_))) => {}
+            Ok(Some(DiscoveryEvent::Error(e))) => {
+                fail(&format!("Discovery failed: {e}"), e.hint().as_deref());
+            }
+            Ok(None) | Err(_) => {
+                eprintln!("No scan from agent within {}s", timeout.as_secs());
+                process::exit(1);
+            }
+        }
+    };
+
+    let snapshot = export::Snapshot::new(
+        destination,
+        Some(&scan.hostname),
+        Some(&scan.username),
+        scan.scan_index,
+        &scan.ports,
+        &std::collections::HashMap::new(),
+    );
+    if csv {
+        print!("{}", snapshot.to_csv());
+    } else {
+        println!("{}", snapshot.to_json());
+    }
+    // The agent exits on its own once the channel closes.
+    process::exit(0);
+}
+
+/// `sshfwd bench <host>`: time the SSH channel and a local forward against
+/// the agent's bench server, print the comparison and exit.
+async fn run_bench(
+    destination: &str,
+    options: &AgentOptions,
+    tunnel: TunnelOptions,
+    size_mb: u64,
+    transport: Transport,
+) {
+    eprintln!("Connecting to {destination}...");
+    let session = match engine::connect(destination, None, transport, &|_| {}).await {
+        Ok(s) => s,
+        Err(e) => {
+            fail(&format!("Connection failed: {e}"), e.hint().as_deref());
+        }
+    };
+    match bench::run(session, options, tunnel, size_mb * 1_000_000).await {
+        Ok(report) => println!("{report}"),
+        Err(e) => {
+            eprintln!("Benchmark failed: {e}");
+            process::exit(1);
+        }
+    }
+    // The bench agent exits once the channel closes.
+    process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_values_are_not_destinations() {
+        let args: Vec<String> = [
+            "sshfwd",
+            "web1",
+            "--agent-framing",
+            "msgpack",
+            "--ssh-backend",
+            "system",
+            "--no-notify",
+            "db",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(destinations(&args), ["web1", "db"]);
+    }
+
+    #[test]
+    fn unknown_agent_framing_is_a_usage_error() {
+        let args = |framing: &str| -> Vec<String> {
+            ["sshfwd", "web1", "--agent-framing", framing]
+                .map(String::from)
+                .to_vec()
+        };
+        assert_eq!(
+            parse_arg(&args("msgpack"), "--agent-framing", Framing::parse),
+            Ok(Some(Framing::Msgpack))
+        );
+        assert_eq!(
+            parse_arg(&args("xml"), "--agent-framing", Framing::parse),
+            Err(r#"Invalid --agent-framing "xml""#.to_string())
+        );
+        assert_eq!(
+            parse_arg(&args("json")[..2], "--agent-framing", Framing::parse),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn agent_nice_must_be_a_nice_value() {
+        let nice = |value: &str| {
+            let args: Vec<String> = ["sshfwd", "--agent-nice", value].map(String::from).to_vec();
+            parse_arg(&args, "--agent-nice", parse_nice)
+        };
+        assert_eq!(nice("10"), Ok(Some(10)));
+        assert_eq!(nice("-20"), Ok(Some(-20)));
+        assert!(nice("20").is_err());
+        assert!(nice("low").is_err());
+    }
+
+    #[test]
+    fn scan_interval_must_be_whole_seconds() {
+        let interval = |value: &str| {
+            let args: Vec<String> = ["sshfwd", "--scan-interval", value]
+                .map(String::from)
+                .to_vec();
+            parse_arg(&args, "--scan-interval", |s| s.parse::<u64>().ok())
         };
-        // Reconnected is sent at the top of the loop, before run_session_cycle.
+        assert_eq!(interval("5"), Ok(Some(5)));
+        assert!(interval("5s").is_err());
+        assert!(interval("-1").is_err());
     }
 }
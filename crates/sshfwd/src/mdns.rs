@@ -0,0 +1,144 @@
+//! Advertising shared local forwards over mDNS/DNS-SD (`[sharing] mdns`).
+//!
+//! Only meaningful when forwards listen beyond loopback. Each active local
+//! forward is published under its own host name, `<process>-<host>.local`
+//! (e.g. `myapp-staging.local:18080`), as `_http._tcp` — or `_https._tcp`
+//! when the agent's probe saw TLS. Ports the probe identified as some other
+//! protocol (a banner) are published as `_sshfwd._tcp`. TXT records carry the
+//! destination and remote port.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use sshfwd_common::types::{ListeningPort, ServiceProbe};
+
+/// How long exit waits for goodbye packets per service.
+const GOODBYE_TIMEOUT: Duration = Duration::from_millis(300);
+
+pub struct Advertiser {
+    daemon: ServiceDaemon,
+    destination: String,
+    /// Service full names by remote port.
+    registered: HashMap<u16, String>,
+}
+
+impl Advertiser {
+    pub fn new(destination: &str) -> Result<Self, String> {
+        let daemon = ServiceDaemon::new().map_err(|e| format!("mDNS: {e}"))?;
+        Ok(Self {
+            daemon,
+            destination: destination.to_string(),
+            registered: HashMap::new(),
+        })
+    }
+
+    /// Publish the forward of `remote_port` listening on `local_port`.
+    /// `port` is the remote socket from the latest scan, if known.
+    pub fn advertise(
+        &mut self,
+        remote_port: u16,
+        local_port: u16,
+        port: Option<&ListeningPort>,
+    ) -> Result<(), String> {
+        let process = port
+            .and_then(|p| p.process.as_ref())
+            .map(|p| p.name.as_str());
+        let name = instance_name(process, remote_port, &self.destination);
+        let properties = [
+            ("destination", self.destination.clone()),
+            ("remote_port", remote_port.to_string()),
+        ];
+        let service = ServiceInfo::new(
+            service_type(port.and_then(|p| p.probe.as_ref())),
+            &name,
+            &format!("{name}.local."),
+            "",
+            local_port,
+            &properties[..],
+        )
+        .map_err(|e| format!("mDNS {name}: {e}"))?
+        .enable_addr_auto();
+        let fullname = service.get_fullname().to_string();
+        self.daemon
+            .register(service)
+            .map_err(|e| format!("mDNS {name}: {e}"))?;
+        if let Some(previous) = self.registered.insert(remote_port, fullname.clone()) {
+            if previous != fullname {
+                let _ = self.daemon.unregister(&previous);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, remote_port: u16) {
+        if let Some(fullname) = self.registered.remove(&remote_port) {
+            let _ = self.daemon.unregister(&fullname);
+        }
+    }
+
+    /// Unregister everything, waiting briefly so peers get goodbye packets.
+    pub fn shutdown(&mut self) {
+        for (_, fullname) in self.registered.drain() {
+            if let Ok(status) = self.daemon.unregister(&fullname) {
+                let _ = status.recv_timeout(GOODBYE_TIMEOUT);
+            }
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+fn service_type(probe: Option<&ServiceProbe>) -> &'static str {
+    match probe {
+        None | Some(ServiceProbe::Http { .. }) => "_http._tcp.local.",
+        Some(ServiceProbe::Tls { .. }) => "_https._tcp.local.",
+        Some(ServiceProbe::Banner { .. }) => "_sshfwd._tcp.local.",
+    }
+}
+
+/// `<process>-<host>` as a DNS label: lowercase alphanumerics and dashes.
+fn instance_name(process: Option<&str>, remote_port: u16, destination: &str) -> String {
+    let host = destination
+        .rsplit_once('@')
+        .map_or(destination, |(_, h)| h)
+        .split('.')
+        .next()
+        .unwrap_or(destination);
+    let service = match process {
+        Some(name) => name.to_string(),
+        None => format!("port{remote_port}"),
+    };
+    let mut label = String::new();
+    for c in format!("{service}-{host}").chars() {
+        let c = c.to_ascii_lowercase();
+        if c.is_ascii_alphanumeric() {
+            label.push(c);
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label.truncate(63);
+    label.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_names_are_dns_labels() {
+        assert_eq!(
+            instance_name(Some("myapp"), 8080, "deploy@staging.corp"),
+            "myapp-staging"
+        );
+        assert_eq!(
+            instance_name(Some("Node_Server.js"), 3000, "dev"),
+            "node-server-js-dev"
+        );
+        assert_eq!(instance_name(None, 5432, "db1"), "port5432-db1");
+        assert_eq!(
+            service_type(Some(&ServiceProbe::Tls { alpn: None })),
+            "_https._tcp.local."
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
-use sshfwd_common::types::ListeningPort;
+use sshfwd_common::types::{ListeningPort, ProcessInfo};
 
 use crate::forward::{ForwardEntry, ForwardKey, ForwardStatus};
+use crate::text;
 
 pub struct PortChange {
     pub port: u16,
@@ -15,6 +16,12 @@ pub enum PortChangeKind {
     Appeared,
     Disappeared,
     Reactivated,
+    /// Still listed, but owned by another process (or the same PID running
+    /// another command) than in the previous scan: a deploy or a restart.
+    Restarted {
+        old_pid: u32,
+        new_pid: u32,
+    },
 }
 
 /// Detect port changes between two scans for notification purposes.
@@ -49,7 +56,7 @@ pub fn detect_port_changes(
             .iter()
             .find(|p| p.port == port)
             .and_then(|p| p.process.as_ref())
-            .map(|p| p.cmdline.clone());
+            .map(|p| p.command());
         changes.push(PortChange {
             port,
             kind,
@@ -57,6 +64,27 @@ pub fn detect_port_changes(
         });
     }
 
+    // Ports listed in both scans whose owner changed. Unknown owners (not
+    // root, process gone mid-scan) don't count.
+    let mut kept: Vec<u16> = new_scan_ports.intersection(prev).copied().collect();
+    kept.sort();
+    for port in kept {
+        let (Some(old), Some(new)) = (tcp_owner(old_ports, port), tcp_owner(new_ports, port))
+        else {
+            continue;
+        };
+        if old.pid != new.pid || old.cmdline != new.cmdline {
+            changes.push(PortChange {
+                port,
+                kind: PortChangeKind::Restarted {
+                    old_pid: old.pid,
+                    new_pid: new.pid,
+                },
+                process_name: Some(new.command()),
+            });
+        }
+    }
+
     // Disappeared ports (sorted for deterministic notification order)
     let mut disappeared: Vec<u16> = prev.difference(new_scan_ports).copied().collect();
     disappeared.sort();
@@ -65,7 +93,7 @@ pub fn detect_port_changes(
             .iter()
             .find(|p| p.port == port)
             .and_then(|p| p.process.as_ref())
-            .map(|p| p.cmdline.clone());
+            .map(|p| p.command());
         changes.push(PortChange {
             port,
             kind: PortChangeKind::Disappeared,
@@ -76,9 +104,20 @@ pub fn detect_port_changes(
     changes
 }
 
+/// The process listening on TCP `port`, if the scan knows it.
+fn tcp_owner(ports: &[ListeningPort], port: u16) -> Option<&ProcessInfo> {
+    ports
+        .iter()
+        .find(|p| p.port == port && p.protocol.is_tcp())
+        .and_then(|p| p.process.as_ref())
+}
+
 /// Batches port change notifications across scans, flushing after a quiet period.
 const NOTIFY_DEBOUNCE_SECS: u64 = 2;
 
+/// Cells of a command shown in a notification; popups are narrow.
+const NOTIFY_NAME_WIDTH: usize = 40;
+
 #[derive(Default)]
 pub struct NotifyBatch {
     pending: Vec<PortChange>,
@@ -111,6 +150,7 @@ impl NotifyBatch {
     }
 }
 
+#[cfg(feature = "notifications")]
 fn notify_port_changes(destination: &str, changes: &[PortChange]) {
     if changes.is_empty() {
         return;
@@ -136,12 +176,18 @@ fn notify_port_changes(destination: &str, changes: &[PortChange]) {
     });
 }
 
+/// Built without the `notifications` feature: changes only reach the event
+/// log.
+#[cfg(not(feature = "notifications"))]
+fn notify_port_changes(_destination: &str, _changes: &[PortChange]) {}
+
 /// Format changes grouped by kind.
 ///
 /// Single change:  `+ 8080 (node)`
 /// Bulk (process names dropped, groups on separate lines):
 ///   `+ 80, 443, 8080`
 ///   `- 3000`
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
 fn format_notification_body(changes: &[PortChange]) -> String {
     if changes.len() == 1 {
         let c = &changes[0];
@@ -149,9 +195,14 @@ fn format_notification_body(changes: &[PortChange]) -> String {
             PortChangeKind::Appeared => "+",
             PortChangeKind::Disappeared => "-",
             PortChangeKind::Reactivated => "~",
+            PortChangeKind::Restarted { .. } => "↻",
         };
         return match &c.process_name {
-            Some(name) => format!("{symbol} {} ({name})", c.port),
+            Some(name) => format!(
+                "{symbol} {} ({})",
+                c.port,
+                text::truncate(name, NOTIFY_NAME_WIDTH)
+            ),
             None => format!("{symbol} {}", c.port),
         };
     }
@@ -159,12 +210,14 @@ fn format_notification_body(changes: &[PortChange]) -> String {
     let mut appeared = Vec::new();
     let mut disappeared = Vec::new();
     let mut reactivated = Vec::new();
+    let mut restarted = Vec::new();
 
     for c in changes {
         match c.kind {
             PortChangeKind::Appeared => appeared.push(c.port),
             PortChangeKind::Disappeared => disappeared.push(c.port),
             PortChangeKind::Reactivated => reactivated.push(c.port),
+            PortChangeKind::Restarted { .. } => restarted.push(c.port),
         }
     }
 
@@ -177,6 +230,10 @@ fn format_notification_body(changes: &[PortChange]) -> String {
         let ports: Vec<String> = reactivated.iter().map(ToString::to_string).collect();
         lines.push(format!("~ {}", ports.join(", ")));
     }
+    if !restarted.is_empty() {
+        let ports: Vec<String> = restarted.iter().map(ToString::to_string).collect();
+        lines.push(format!("↻ {}", ports.join(", ")));
+    }
     if !disappeared.is_empty() {
         let ports: Vec<String> = disappeared.iter().map(ToString::to_string).collect();
         lines.push(format!("- {}", ports.join(", ")));
@@ -188,7 +245,7 @@ fn format_notification_body(changes: &[PortChange]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sshfwd_common::types::{ListeningPort, ProcessInfo, Protocol};
+    use sshfwd_common::types::Protocol;
 
     fn make_port(port: u16, name: &str) -> ListeningPort {
         ListeningPort {
@@ -200,7 +257,16 @@ mod tests {
                 name: name.to_string(),
                 cmdline: name.to_string(),
                 uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
             }),
+            probe: None,
+            bindings: Vec::new(),
+            stats: None,
         }
     }
 
@@ -267,6 +333,7 @@ mod tests {
                 local_port: 5432,
                 status: ForwardStatus::Starting,
                 active_connections: 0,
+                open_wait: Default::default(),
             },
         );
         let new_ports = vec![make_port(80, "nginx"), make_port(5432, "postgres")];
@@ -278,6 +345,38 @@ mod tests {
         assert_eq!(changes[0].process_name.as_deref(), Some("postgres"));
     }
 
+    #[test]
+    fn detects_restarted_processes() {
+        let ports: HashSet<u16> = [80, 3000, 8080].into();
+        let forwards = HashMap::new();
+        let old = vec![
+            make_port(80, "nginx"),
+            make_port(3000, "node"),
+            make_port(8080, "java"),
+        ];
+        let mut new = old.clone();
+        // 3000 redeployed under a new PID, 8080 exec'd another command.
+        new[1].process.as_mut().unwrap().pid = 4000;
+        new[2].process.as_mut().unwrap().cmdline = "java -jar app-2.jar".into();
+
+        let changes = detect_port_changes(Some(&ports), &ports, &forwards, &new, &old);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].port, 3000);
+        assert!(matches!(
+            changes[0].kind,
+            PortChangeKind::Restarted {
+                old_pid: 3000,
+                new_pid: 4000
+            }
+        ));
+        assert_eq!(changes[1].port, 8080);
+        assert_eq!(
+            changes[1].process_name.as_deref(),
+            Some("java -jar app-2.jar")
+        );
+        assert_eq!(format_notification_body(&changes), "↻ 3000, 8080");
+    }
+
     #[test]
     fn no_changes_when_same() {
         let prev: HashSet<u16> = [80, 443].into();
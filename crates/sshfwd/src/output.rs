@@ -0,0 +1,756 @@
+//! `--output json`: instead of the TUI, every scan, remote port change,
+//! forward transition and connection state change is printed to stdout as one
+//! JSON object per line, for `jq` pipelines and other dashboards.
+//!
+//! Every line carries `ts` (UTC), `destination` and `event`; the remaining
+//! fields depend on the event.
+//!
+//! `--output plain` (the default when stdout isn't a terminal) prints the
+//! same events as text for logs and CI: the remote port list whenever it
+//! changed, `+` marking ports that opened, `-` ones that closed and `~` ones
+//! another process took over, and a line for everything else.
+//!
+//! `--record <file>` appends the JSON lines of every host to a file as well,
+//! alongside whatever the terminal shows, with scans after the first cut
+//! down to the ports they opened, closed or restarted.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use sshfwd_common::time::{epoch_secs, format_utc};
+use sshfwd_common::types::{AgentErrorKind, ListeningPort, ProcessInfo};
+
+use crate::app::{Message, StartupStage};
+use crate::error::agent_error_hint;
+use crate::forward::{ForwardEvent, ForwardKind};
+
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    ts: String,
+    destination: &'a str,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Agent {
+        version: &'a str,
+        capabilities: &'a [String],
+    },
+    Scan {
+        hostname: &'a str,
+        username: &'a str,
+        scan_index: u64,
+        /// Remote clock, seconds since the Unix epoch.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        scanned_at: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        scan_duration_ms: Option<u64>,
+        ports: &'a [ListeningPort],
+        /// The first scan, which the rest are diffed against.
+        #[serde(skip)]
+        baseline: bool,
+    },
+    PortOpened {
+        port: u16,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        process: Option<&'a ProcessInfo>,
+    },
+    PortClosed {
+        port: u16,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        process: Option<ProcessInfo>,
+    },
+    /// Still listening, but another process (or command) owns it now.
+    PortRestarted {
+        port: u16,
+        old_pid: u32,
+        process: &'a ProcessInfo,
+    },
+    ForwardStarted {
+        kind: ForwardKind,
+        remote_port: u16,
+        local_port: u16,
+    },
+    ForwardPaused {
+        kind: ForwardKind,
+        remote_port: u16,
+    },
+    ForwardStopped {
+        kind: ForwardKind,
+        remote_port: u16,
+    },
+    ForwardFailed {
+        kind: ForwardKind,
+        remote_port: u16,
+        message: &'a str,
+    },
+    Connections {
+        kind: ForwardKind,
+        remote_port: u16,
+        count: u32,
+    },
+    ConnectionClosed {
+        kind: ForwardKind,
+        remote_port: u16,
+        peer: &'a str,
+        bytes_to_remote: u64,
+        bytes_from_remote: u64,
+        duration_ms: u64,
+        open_wait_ms: u64,
+    },
+    ConnectionDenied {
+        kind: ForwardKind,
+        remote_port: u16,
+        peer: &'a str,
+        reason: &'a str,
+    },
+    Startup {
+        stage: StartupStage,
+    },
+    StartupFailed {
+        message: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hint: Option<&'a str>,
+    },
+    ConnectionLost,
+    Reconnecting,
+    Reconnected,
+    DiscoveryStalled,
+    /// The machine slept; a reconnect follows.
+    Resumed {
+        slept_secs: u64,
+    },
+    StreamEnded,
+    Warning {
+        message: &'a str,
+    },
+    AgentError {
+        kind: AgentErrorKind,
+        message: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hint: Option<String>,
+    },
+    Error {
+        message: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        hint: Option<String>,
+    },
+}
+
+/// How `EventWriter` prints events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Plain,
+}
+
+/// Turns model messages into output lines, remembering the last scan's
+/// remote ports so openings and closings can be reported.
+pub struct EventWriter {
+    destination: String,
+    format: Format,
+    /// Write every scan's port list, not only the first.
+    every_scan: bool,
+    /// Remote ports of the previous scan, with the first owning process.
+    ports: Option<BTreeMap<u16, Option<ProcessInfo>>>,
+}
+
+impl EventWriter {
+    pub fn new(destination: String, format: Format) -> Self {
+        Self {
+            destination,
+            format,
+            every_scan: true,
+            ports: None,
+        }
+    }
+
+    /// Leave out `scan` events after the first; the port changes they
+    /// bring are still written.
+    pub fn changes_only(mut self) -> Self {
+        self.every_scan = false;
+        self
+    }
+
+    /// Write the lines for `msg` to `out`. Keyboard, timer and local scan
+    /// messages produce nothing.
+    pub fn write(&mut self, out: &mut impl Write, msg: &Message) -> io::Result<()> {
+        for line in self.lines(msg) {
+            writeln!(out, "{line}")?;
+        }
+        out.flush()
+    }
+
+    fn lines(&mut self, msg: &Message) -> Vec<String> {
+        let mut events = Vec::new();
+        match msg {
+            Message::ScanReceived(scan) => {
+                events.push(Event::Scan {
+                    hostname: &scan.hostname,
+                    username: &scan.username,
+                    scan_index: scan.scan_index,
+                    scanned_at: scan.scanned_at,
+                    scan_duration_ms: scan.scan_duration_ms,
+                    ports: &scan.ports,
+                    baseline: self.ports.is_none(),
+                });
+                let mut current: BTreeMap<u16, Option<&ProcessInfo>> = BTreeMap::new();
+                // Unix sockets have no number to open or close.
+                for port in scan.ports.iter().filter(|p| !p.protocol.is_unix()) {
+                    current.entry(port.port).or_insert(port.process.as_ref());
+                }
+                // The first scan is the baseline, not a burst of openings.
+                let previous = self.ports.replace(
+                    current
+                        .iter()
+                        .map(|(&port, process)| (port, process.cloned()))
+                        .collect(),
+                );
+                if let Some(previous) = previous {
+                    for (&port, &process) in &current {
+                        match (previous.get(&port), process) {
+                            (None, _) => events.push(Event::PortOpened { port, process }),
+                            (Some(Some(old)), Some(new))
+                                if old.pid != new.pid || old.cmdline != new.cmdline =>
+                            {
+                                events.push(Event::PortRestarted {
+                                    port,
+                                    old_pid: old.pid,
+                                    process: new,
+                                });
+                            }
+                            _ => {}
+                        }
+                    }
+                    for (port, process) in previous {
+                        if !current.contains_key(&port) {
+                            events.push(Event::PortClosed { port, process });
+                        }
+                    }
+                }
+            }
+            Message::AgentHello(hello) => events.push(Event::Agent {
+                version: &hello.agent_version,
+                capabilities: &hello.capabilities,
+            }),
+            Message::DiscoveryWarning(message) => events.push(Event::Warning { message }),
+            Message::AgentError(e) => events.push(Event::AgentError {
+                kind: e.kind,
+                message: &e.message,
+                hint: agent_error_hint(e.kind),
+            }),
+            Message::DiscoveryError(e) => events.push(Event::Error {
+                message: e.to_string(),
+                hint: e.hint(),
+            }),
+            Message::StreamEnded => events.push(Event::StreamEnded),
+            Message::ConnectionLost => events.push(Event::ConnectionLost),
+            Message::Reconnecting => events.push(Event::Reconnecting),
+            Message::Reconnected => events.push(Event::Reconnected),
+            Message::DiscoveryStalled => events.push(Event::DiscoveryStalled),
+            Message::Resumed(slept) => events.push(Event::Resumed {
+                slept_secs: slept.as_secs(),
+            }),
+            Message::Startup(stage) => events.push(Event::Startup { stage: *stage }),
+            Message::StartupFailed { error, hint } => events.push(Event::StartupFailed {
+                message: error,
+                hint: hint.as_deref(),
+            }),
+            Message::ForwardEvent(event) => events.push(forward_event(event)),
+            Message::AgentReply(_)
+            | Message::LocalScanReceived(_)
+            | Message::LocalScanError(_)
+            | Message::Tick
+            | Message::Api(_)
+            | Message::Preview { .. }
+            | Message::Prompt(_) => {}
+            #[cfg(feature = "tui")]
+            Message::Key(_) | Message::Mouse(_) | Message::Resize(..) | Message::Focus(_) => {}
+        }
+        if !self.every_scan {
+            events.retain(|e| {
+                !matches!(
+                    e,
+                    Event::Scan {
+                        baseline: false,
+                        ..
+                    }
+                )
+            });
+        }
+        let ts = format_utc(epoch_secs(SystemTime::now()));
+        match self.format {
+            Format::Json => events
+                .into_iter()
+                .map(|event| {
+                    let line = Line {
+                        ts: ts.clone(),
+                        destination: &self.destination,
+                        event,
+                    };
+                    serde_json::to_string(&line).expect("event serializes")
+                })
+                .collect(),
+            Format::Plain => plain_lines(&format!("{ts} {}", self.destination), &events),
+        }
+    }
+}
+
+/// `--record <file>`: every host's events as JSON lines (see
+/// `EventWriter::changes_only`), appended so one file can span several runs.
+pub struct Recorder {
+    out: LineWriter<File>,
+    hosts: Vec<EventWriter>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path, destinations: &[String]) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            out: LineWriter::new(file),
+            hosts: destinations
+                .iter()
+                .map(|d| EventWriter::new(d.clone(), Format::Json).changes_only())
+                .collect(),
+        })
+    }
+
+    /// Append the events `msg` brings to host `host`.
+    pub fn record(&mut self, host: usize, msg: &Message) -> io::Result<()> {
+        match self.hosts.get_mut(host) {
+            Some(events) => events.write(&mut self.out, msg),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `events` as text, each line starting with `prefix`. Port openings,
+/// closings and restarts show as markers in the scan's port list, which is
+/// only printed for the first scan and ones that changed something.
+fn plain_lines(prefix: &str, events: &[Event]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for event in events {
+        let text = match event {
+            Event::Scan {
+                ports, baseline, ..
+            } => {
+                let opened: BTreeSet<u16> = events
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::PortOpened { port, .. } => Some(*port),
+                        _ => None,
+                    })
+                    .collect();
+                let restarted: BTreeSet<u16> = events
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::PortRestarted { port, .. } => Some(*port),
+                        _ => None,
+                    })
+                    .collect();
+                let closed: Vec<(u16, Option<&ProcessInfo>)> = events
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::PortClosed { port, process } => Some((*port, process.as_ref())),
+                        _ => None,
+                    })
+                    .collect();
+                if !baseline && opened.is_empty() && closed.is_empty() && restarted.is_empty() {
+                    continue;
+                }
+                lines.push(format!("{prefix} {} remote ports", ports.len()));
+                let mut rows: Vec<(u16, String)> = ports
+                    .iter()
+                    .map(|p| {
+                        let marker = if opened.contains(&p.port) {
+                            '+'
+                        } else if restarted.contains(&p.port) {
+                            '~'
+                        } else {
+                            ' '
+                        };
+                        let number = if p.protocol.is_unix() {
+                            p.local_addr.clone()
+                        } else {
+                            p.port.to_string()
+                        };
+                        let row = format!(
+                            "  {marker} {number:>5}/{:<4} {:<15} {}",
+                            p.protocol.as_str(),
+                            crate::columns::bind_cell(p),
+                            plain_process(p.process.as_ref()),
+                        );
+                        (p.port, row)
+                    })
+                    .collect();
+                rows.extend(closed.iter().map(|&(port, process)| {
+                    let row = format!("  - {port:>5}{:22}{}", "", plain_process(process));
+                    (port, row)
+                }));
+                rows.sort_by_key(|(port, _)| *port);
+                lines.extend(rows.into_iter().map(|(_, row)| row.trim_end().to_string()));
+                continue;
+            }
+            Event::PortOpened { .. } | Event::PortClosed { .. } => continue,
+            Event::PortRestarted {
+                port,
+                old_pid,
+                process,
+            } => format!(
+                ":{port} restarted: pid {old_pid} → {}",
+                plain_process(Some(process))
+            ),
+            Event::Agent { version, .. } => format!("agent {version}"),
+            Event::ForwardStarted {
+                kind,
+                remote_port,
+                local_port,
+            } => format!(
+                "{} :{remote_port} started on local :{local_port}",
+                plain_kind(*kind)
+            ),
+            Event::ForwardPaused { kind, remote_port } => {
+                format!("{} :{remote_port} paused", plain_kind(*kind))
+            }
+            Event::ForwardStopped { kind, remote_port } => {
+                format!("{} :{remote_port} stopped", plain_kind(*kind))
+            }
+            Event::ForwardFailed {
+                kind,
+                remote_port,
+                message,
+            } => format!("{} :{remote_port} failed: {message}", plain_kind(*kind)),
+            Event::Connections {
+                kind,
+                remote_port,
+                count,
+            } => format!(
+                "{} :{remote_port} has {count} connection{}",
+                plain_kind(*kind),
+                if *count == 1 { "" } else { "s" }
+            ),
+            Event::ConnectionClosed {
+                kind,
+                remote_port,
+                peer,
+                bytes_to_remote,
+                bytes_from_remote,
+                duration_ms,
+                ..
+            } => format!(
+                "{} :{remote_port} connection from {peer} closed after {duration_ms} ms \
+                 ({bytes_to_remote} B sent, {bytes_from_remote} B received)",
+                plain_kind(*kind)
+            ),
+            Event::ConnectionDenied {
+                kind,
+                remote_port,
+                peer,
+                reason,
+            } => format!(
+                "{} :{remote_port} refused {peer}: {reason}",
+                plain_kind(*kind)
+            ),
+            Event::Startup { stage } => stage.label(),
+            Event::StartupFailed { message, hint } => plain_error(message, *hint),
+            Event::ConnectionLost => "connection lost".to_string(),
+            Event::Reconnecting => "reconnecting".to_string(),
+            Event::Reconnected => "reconnected".to_string(),
+            Event::DiscoveryStalled => "discovery stalled, restarting the agent".to_string(),
+            Event::Resumed { slept_secs } => format!("resumed after sleeping {slept_secs}s"),
+            Event::StreamEnded => "agent stream ended".to_string(),
+            Event::Warning { message } => format!("warning: {message}"),
+            Event::AgentError { message, hint, .. } => plain_error(message, hint.as_deref()),
+            Event::Error { message, hint } => plain_error(message, hint.as_deref()),
+        };
+        lines.push(format!("{prefix} {text}"));
+    }
+    lines
+}
+
+fn plain_kind(kind: ForwardKind) -> &'static str {
+    match kind {
+        ForwardKind::Local => "forward",
+        ForwardKind::Reverse => "reverse",
+    }
+}
+
+fn plain_process(process: Option<&ProcessInfo>) -> String {
+    process.map_or_else(
+        || "-".to_string(),
+        |p| format!("{} (pid {})", p.name, p.pid),
+    )
+}
+
+fn plain_error(message: &str, hint: Option<&str>) -> String {
+    match hint {
+        Some(hint) => format!("error: {message} (hint: {hint})"),
+        None => format!("error: {message}"),
+    }
+}
+
+fn forward_event(event: &ForwardEvent) -> Event<'_> {
+    match event {
+        ForwardEvent::Started {
+            kind,
+            remote_port,
+            local_port,
+        } => Event::ForwardStarted {
+            kind: *kind,
+            remote_port: *remote_port,
+            local_port: *local_port,
+        },
+        ForwardEvent::Paused { kind, remote_port } => Event::ForwardPaused {
+            kind: *kind,
+            remote_port: *remote_port,
+        },
+        ForwardEvent::Stopped { kind, remote_port } => Event::ForwardStopped {
+            kind: *kind,
+            remote_port: *remote_port,
+        },
+        ForwardEvent::BindError {
+            kind,
+            remote_port,
+            message,
+        } => Event::ForwardFailed {
+            kind: *kind,
+            remote_port: *remote_port,
+            message,
+        },
+        ForwardEvent::ConnectionCountChanged {
+            kind,
+            remote_port,
+            count,
+        } => Event::Connections {
+            kind: *kind,
+            remote_port: *remote_port,
+            count: *count,
+        },
+        ForwardEvent::ConnectionClosed {
+            kind,
+            remote_port,
+            peer,
+            traffic,
+            duration,
+            open_wait,
+        } => Event::ConnectionClosed {
+            kind: *kind,
+            remote_port: *remote_port,
+            peer,
+            bytes_to_remote: traffic.to_remote,
+            bytes_from_remote: traffic.from_remote,
+            duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+            open_wait_ms: u64::try_from(open_wait.as_millis()).unwrap_or(u64::MAX),
+        },
+        ForwardEvent::ConnectionDenied {
+            kind,
+            remote_port,
+            peer,
+            reason,
+        } => Event::ConnectionDenied {
+            kind: *kind,
+            remote_port: *remote_port,
+            peer,
+            reason,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sshfwd_common::types::{Protocol, ScanResult};
+
+    fn scan(index: u64, ports: &[u16]) -> Message {
+        Message::ScanReceived(ScanResult {
+            agent_version: "0".into(),
+            hostname: "box".into(),
+            username: "dev".into(),
+            is_root: false,
+            uid: None,
+            ports: ports
+                .iter()
+                .map(|&port| ListeningPort {
+                    protocol: Protocol::Tcp,
+                    local_addr: "127.0.0.1".into(),
+                    port,
+                    process: None,
+                    probe: None,
+                    bindings: Vec::new(),
+                    stats: None,
+                })
+                .collect(),
+            warnings: Vec::new(),
+            scan_index: index,
+            scan_duration_ms: None,
+            scanned_at: None,
+            host: None,
+        })
+    }
+
+    fn events(lines: Vec<String>) -> Vec<serde_json::Value> {
+        lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn port_changes_are_diffed_against_the_previous_scan() {
+        let mut json = EventWriter::new("dev@box".into(), Format::Json);
+
+        let first = events(json.lines(&scan(1, &[22, 5432])));
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0]["event"], "scan");
+        assert_eq!(first[0]["destination"], "dev@box");
+        assert_eq!(first[0]["ports"].as_array().unwrap().len(), 2);
+
+        let second = events(json.lines(&scan(2, &[22, 8080])));
+        assert_eq!(second.len(), 3);
+        assert_eq!(second[1]["event"], "port_opened");
+        assert_eq!(second[1]["port"], 8080);
+        assert_eq!(second[2]["event"], "port_closed");
+        assert_eq!(second[2]["port"], 5432);
+
+        let forward = events(json.lines(&Message::ForwardEvent(ForwardEvent::Started {
+            kind: ForwardKind::Local,
+            remote_port: 8080,
+            local_port: 18080,
+        })));
+        assert_eq!(forward[0]["event"], "forward_started");
+        assert_eq!(forward[0]["local_port"], 18080);
+        assert!(json.lines(&Message::Tick).is_empty());
+    }
+
+    #[test]
+    fn changes_only_writes_nothing_for_an_unchanged_scan() {
+        let mut json = EventWriter::new("dev@box".into(), Format::Json).changes_only();
+        let mut out = Vec::new();
+        json.write(&mut out, &scan(1, &[22, 5432])).unwrap();
+        assert_eq!(
+            events(
+                String::from_utf8(out)
+                    .unwrap()
+                    .lines()
+                    .map(String::from)
+                    .collect()
+            )[0]["event"],
+            "scan"
+        );
+
+        let mut out = Vec::new();
+        json.write(&mut out, &scan(2, &[22, 5432])).unwrap();
+        assert!(out.is_empty());
+
+        json.write(&mut out, &scan(3, &[22])).unwrap();
+        let written = events(
+            String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect(),
+        );
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[0]["event"], "port_closed");
+    }
+
+    #[test]
+    fn recordings_append_changes_after_the_first_scan() {
+        let path = std::env::temp_dir().join(format!("sshfwd-record-{}", std::process::id()));
+        let destinations = ["dev@box".to_string()];
+        for _ in 0..2 {
+            let mut recorder = Recorder::open(&path, &destinations).unwrap();
+            recorder.record(0, &scan(1, &[22])).unwrap();
+            recorder.record(0, &scan(2, &[22])).unwrap();
+            recorder.record(0, &scan(3, &[22, 8080])).unwrap();
+            recorder.record(0, &Message::ConnectionLost).unwrap();
+        }
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let kinds: Vec<String> = events(written.lines().map(String::from).collect())
+            .iter()
+            .map(|e| e["event"].as_str().unwrap().to_string())
+            .collect();
+        let run = ["scan", "port_opened", "connection_lost"];
+        assert_eq!(kinds, [run, run].concat());
+    }
+
+    #[test]
+    fn plain_output_lists_ports_when_they_change() {
+        let mut plain = EventWriter::new("dev@box".into(), Format::Plain);
+        let strip = |lines: Vec<String>| -> Vec<String> {
+            // Drop the timestamp; port rows have none.
+            lines
+                .into_iter()
+                .map(|l| match l.split_once(' ') {
+                    Some((ts, rest)) if !ts.is_empty() => rest.to_string(),
+                    _ => l,
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            strip(plain.lines(&scan(1, &[22, 5432]))),
+            [
+                "dev@box 2 remote ports",
+                "       22/tcp  lo              -",
+                "     5432/tcp  lo              -",
+            ]
+        );
+        assert!(plain.lines(&scan(2, &[22, 5432])).is_empty());
+        assert_eq!(
+            strip(plain.lines(&scan(3, &[22, 8080]))),
+            [
+                "dev@box 2 remote ports",
+                "       22/tcp  lo              -",
+                "  -  5432                      -",
+                "  +  8080/tcp  lo              -",
+            ]
+        );
+        let restart = |pid| {
+            let Message::ScanReceived(mut scan) = scan(4, &[22, 8080]) else {
+                unreachable!()
+            };
+            scan.ports[1].process = Some(ProcessInfo {
+                pid,
+                name: "node".into(),
+                cmdline: "node server.js".into(),
+                uid: 1000,
+                exe: None,
+                cwd: None,
+                start_time: None,
+                env: Default::default(),
+                elevated: false,
+                container: None,
+            });
+            Message::ScanReceived(scan)
+        };
+        plain.lines(&restart(100));
+        assert_eq!(
+            strip(plain.lines(&restart(200))),
+            [
+                "dev@box 2 remote ports",
+                "       22/tcp  lo              -",
+                "  ~  8080/tcp  lo              node (pid 200)",
+                "dev@box :8080 restarted: pid 100 → node (pid 200)",
+            ]
+        );
+
+        let forward = plain.lines(&Message::ForwardEvent(ForwardEvent::Started {
+            kind: ForwardKind::Local,
+            remote_port: 8080,
+            local_port: 18080,
+        }));
+        assert_eq!(
+            strip(forward),
+            ["dev@box forward :8080 started on local :18080"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! How often hosts tick. Ticks drive staleness checks, notification batches
+//! and the status file, and each one wakes the main loop; while the terminal
+//! is unfocused or untouched for `IDLE_AFTER`, they slow to one every
+//! `IDLE_TICKS` seconds.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "tui")]
+use std::time::Instant;
+
+use crate::app::Message;
+
+const TICK: Duration = Duration::from_secs(1);
+/// Seconds between ticks while idle.
+const IDLE_TICKS: u32 = 5;
+/// No input for this long counts as idle.
+const IDLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Idle flag shared by the main loop and every host's tick thread.
+#[derive(Debug, Clone, Default)]
+pub struct Pace {
+    idle: Arc<AtomicBool>,
+}
+
+impl Pace {
+    /// Send `Message::Tick` to `bg_tx` until it closes. The thread wakes every
+    /// second either way so leaving idle takes effect on the next one.
+    pub fn spawn_ticks(&self, bg_tx: crossbeam_channel::Sender<Message>) {
+        let idle = self.idle.clone();
+        std::thread::spawn(move || {
+            let mut skipped = 0;
+            loop {
+                std::thread::sleep(TICK);
+                skipped += 1;
+                if idle.load(Ordering::Relaxed) && skipped < IDLE_TICKS {
+                    continue;
+                }
+                skipped = 0;
+                if bg_tx.send(Message::Tick).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    #[cfg(feature = "tui")]
+    fn set_idle(&self, idle: bool) {
+        self.idle.store(idle, Ordering::Relaxed);
+    }
+}
+
+/// Terminal activity as seen by the main loop.
+#[cfg(feature = "tui")]
+#[derive(Debug)]
+pub struct Activity {
+    pace: Pace,
+    last_input: Instant,
+    focused: bool,
+}
+
+#[cfg(feature = "tui")]
+impl Activity {
+    pub fn new(pace: Pace) -> Self {
+        Self {
+            pace,
+            last_input: Instant::now(),
+            focused: true,
+        }
+    }
+
+    /// Note a terminal event; focus changes are consumed here.
+    pub fn input(&mut self, msg: &Message) {
+        if let Message::Focus(focused) = msg {
+            self.focused = *focused;
+        }
+        self.last_input = Instant::now();
+        self.refresh(self.last_input);
+    }
+
+    /// Re-evaluate idleness at `now`, updating the tick threads.
+    pub fn refresh(&self, now: Instant) {
+        self.pace.set_idle(self.is_idle(now));
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        !self.focused || now.duration_since(self.last_input) >= IDLE_AFTER
+    }
+}
+
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_when_unfocused_or_untouched() {
+        let pace = Pace::default();
+        let mut activity = Activity::new(pace.clone());
+        let start = activity.last_input;
+        assert!(!activity.is_idle(start));
+        assert!(activity.is_idle(start + IDLE_AFTER));
+
+        activity.input(&Message::Focus(false));
+        assert!(pace.idle.load(Ordering::Relaxed));
+        activity.input(&Message::Resize(80, 24));
+        assert!(pace.idle.load(Ordering::Relaxed));
+        activity.input(&Message::Focus(true));
+        assert!(!pace.idle.load(Ordering::Relaxed));
+
+        activity.refresh(activity.last_input + IDLE_AFTER);
+        assert!(pace.idle.load(Ordering::Relaxed));
+    }
+}
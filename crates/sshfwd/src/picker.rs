@@ -0,0 +1,272 @@
+//! Host picker shown when sshfwd starts on a terminal without a destination:
+//! recently used destinations first, then `Host` aliases from
+//! `~/.ssh/config`, narrowed by fuzzy search as you type. `Enter` connects to
+//! the highlighted host (or to the typed text when nothing matches), `Esc`
+//! quits.
+
+use std::io;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::ExecutableCommand;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use crate::ui::hotkey_spans;
+use crate::ui::profile::Profile;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub destination: String,
+    pub recent: bool,
+}
+
+/// Recent destinations, then aliases not already among them.
+pub fn candidates(recent: Vec<String>, aliases: Vec<String>) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = recent
+        .into_iter()
+        .map(|destination| Candidate {
+            destination,
+            recent: true,
+        })
+        .collect();
+    for alias in aliases {
+        if !candidates.iter().any(|c| c.destination == alias) {
+            candidates.push(Candidate {
+                destination: alias,
+                recent: false,
+            });
+        }
+    }
+    candidates
+}
+
+/// How well `query` matches `candidate` as a case-insensitive subsequence;
+/// lower is better, `None` is no match. Scores by where the match starts,
+/// then by how spread out it is.
+fn score(query: &str, candidate: &str) -> Option<(usize, usize)> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut start = None;
+    let mut gaps = 0;
+    let mut next = 0;
+    for q in query.to_lowercase().chars() {
+        let offset = candidate[next..].iter().position(|&c| c == q)?;
+        if start.is_none() {
+            start = Some(next + offset);
+        } else {
+            gaps += offset;
+        }
+        next += offset + 1;
+    }
+    Some((start.unwrap_or(0), gaps))
+}
+
+/// Candidates matching `query`, best first (ties keep list order).
+fn filter<'a>(query: &str, candidates: &'a [Candidate]) -> Vec<&'a Candidate> {
+    let mut matched: Vec<((usize, usize), &Candidate)> = candidates
+        .iter()
+        .filter_map(|c| score(query, &c.destination).map(|s| (s, c)))
+        .collect();
+    matched.sort_by_key(|(s, _)| *s);
+    matched.into_iter().map(|(_, c)| c).collect()
+}
+
+struct Picker {
+    candidates: Vec<Candidate>,
+    query: String,
+    list_state: ListState,
+}
+
+impl Picker {
+    fn matches(&self) -> Vec<&Candidate> {
+        filter(&self.query, &self.candidates)
+    }
+
+    /// The destination `Enter` would connect to.
+    fn choice(&self) -> Option<String> {
+        let matches = self.matches();
+        match matches.get(self.list_state.selected().unwrap_or(0)) {
+            Some(c) => Some(c.destination.clone()),
+            None => Some(self.query.trim().to_string()).filter(|q| !q.is_empty()),
+        }
+    }
+
+    fn view(&mut self, frame: &mut Frame) {
+        let [input, list, keys] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let prompt = Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Yellow)),
+            Span::raw(self.query.clone()),
+        ]))
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title(" sshfwd: pick a host "),
+        );
+        frame.render_widget(prompt, input);
+        frame.set_cursor_position((input.x + 3 + self.query.chars().count() as u16, input.y + 1));
+
+        let items: Vec<ListItem> = self
+            .matches()
+            .iter()
+            .map(|c| {
+                let mut spans = vec![Span::styled(
+                    c.destination.clone(),
+                    Style::default().fg(Color::Cyan),
+                )];
+                if c.recent {
+                    spans.push(Span::styled(
+                        "  recent",
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let empty = items.is_empty();
+        let list_widget = List::new(items)
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+        if empty {
+            let hint = if self.query.trim().is_empty() {
+                " No recent hosts or ~/.ssh/config aliases: type a destination"
+            } else {
+                " No match: Enter connects to what you typed"
+            };
+            frame.render_widget(
+                Paragraph::new(Line::styled(hint, Style::default().fg(Color::DarkGray))).block(
+                    Block::bordered()
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(Color::DarkGray)),
+                ),
+                list,
+            );
+        } else {
+            frame.render_stateful_widget(list_widget, list, &mut self.list_state);
+        }
+
+        let mut spans = vec![Span::raw(" ")];
+        spans.extend(hotkey_spans("↑/↓", "Select "));
+        spans.extend(hotkey_spans("Enter", "Connect "));
+        spans.extend(hotkey_spans("Esc", "Quit"));
+        frame.render_widget(Line::from(spans), keys);
+    }
+}
+
+/// Run the picker on the terminal, drawn for `profile`; `None` when the
+/// user quits.
+pub fn run(candidates: Vec<Candidate>, profile: Profile) -> io::Result<Option<String>> {
+    terminal::enable_raw_mode()?;
+    io::stdout().execute(EnterAlternateScreen)?;
+    let result = pick(candidates, profile);
+    let _ = io::stdout().execute(LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+    result
+}
+
+fn pick(candidates: Vec<Candidate>, profile: Profile) -> io::Result<Option<String>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let mut picker = Picker {
+        candidates,
+        query: String::new(),
+        list_state: ListState::default().with_selected(Some(0)),
+    };
+    loop {
+        terminal.draw(|frame| {
+            picker.view(frame);
+            profile.apply(frame.buffer_mut());
+        })?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let count = picker.matches().len();
+        let selected = picker.list_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if ctrl => return Ok(None),
+            KeyCode::Enter => {
+                if let Some(choice) = picker.choice() {
+                    return Ok(Some(choice));
+                }
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                picker
+                    .list_state
+                    .select(Some((selected + 1).min(count.saturating_sub(1))));
+            }
+            KeyCode::Char('n') if ctrl => {
+                picker
+                    .list_state
+                    .select(Some((selected + 1).min(count.saturating_sub(1))));
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                picker.list_state.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Char('p') if ctrl => {
+                picker.list_state.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.list_state.select(Some(0));
+            }
+            KeyCode::Char(c) if !ctrl => {
+                picker.query.push(c);
+                picker.list_state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_filter_prefers_early_tight_matches() {
+        let list = candidates(
+            vec!["deploy@prod-web".into()],
+            vec![
+                "prod-db".into(),
+                "staging-web".into(),
+                "deploy@prod-web".into(),
+            ],
+        );
+        assert_eq!(list.len(), 3);
+        assert!(list[0].recent && !list[1].recent);
+
+        let names = |query: &str| -> Vec<String> {
+            filter(query, &list)
+                .iter()
+                .map(|c| c.destination.clone())
+                .collect()
+        };
+        assert_eq!(names("web"), ["staging-web", "deploy@prod-web"]);
+        assert_eq!(names("PRD"), ["prod-db", "deploy@prod-web"]);
+        assert!(names("xyz").is_empty());
+        assert_eq!(names("").len(), 3);
+    }
+}
@@ -0,0 +1,280 @@
+//! Forwarding guardrails from the `[policy]` config section.
+//!
+//! ```toml
+//! [policy]
+//! deny_ports = [22, "3306-3307"]
+//! allow_processes = ["node", "python3"]
+//! confirm_privileged = false
+//! ```
+//!
+//! Rules match the remote port of a local forward and the name of the remote
+//! process owning it. A deny match always blocks; a non-empty allow list
+//! blocks everything it doesn't match (a port with an unknown owner never
+//! matches `allow_processes`). Reverse forwards are not covered.
+//!
+//! With `deny_loopback_only = true`, ports the remote binds only on loopback
+//! (`127.0.0.1`, `::1`) are refused too, unless listed in
+//! `allow_loopback_ports`.
+//!
+//! Allowed forwards of ports below 1024 or of root-owned processes still ask
+//! for confirmation unless `confirm_privileged = false`.
+
+use std::fmt;
+
+use serde::Deserialize;
+use sshfwd_common::types::ProcessInfo;
+
+/// Ports below this need root to bind, so they usually belong to system services.
+const PRIVILEGED_PORT_LIMIT: u16 = 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    pub allow_ports: Option<Vec<PortRange>>,
+    pub deny_ports: Option<Vec<PortRange>>,
+    /// Process names as shown in the table (`comm`, at most 15 characters).
+    pub allow_processes: Option<Vec<String>>,
+    pub deny_processes: Option<Vec<String>>,
+    /// Refuse ports bound only to loopback on the remote (default false).
+    pub deny_loopback_only: Option<bool>,
+    /// Loopback-only ports still allowed under `deny_loopback_only`.
+    pub allow_loopback_ports: Option<Vec<PortRange>>,
+    /// Ask before forwarding privileged or root-owned ports (default true).
+    pub confirm_privileged: Option<bool>,
+}
+
+/// A single port (`22`) or an inclusive range (`"8000-8999"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "PortSpec")]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PortSpec {
+    Port(u16),
+    Range(String),
+}
+
+impl TryFrom<PortSpec> for PortRange {
+    type Error = String;
+
+    fn try_from(spec: PortSpec) -> Result<Self, String> {
+        let invalid = |s: &str| format!("invalid port range {s:?}, expected \"LOW-HIGH\"");
+        match spec {
+            PortSpec::Port(port) => Ok(PortRange {
+                start: port,
+                end: port,
+            }),
+            PortSpec::Range(s) => {
+                let (start, end) = s.split_once('-').unwrap_or((&s, &s));
+                let start = start.trim().parse().map_err(|_| invalid(&s))?;
+                let end = end.trim().parse().map_err(|_| invalid(&s))?;
+                if start > end {
+                    return Err(invalid(&s));
+                }
+                Ok(PortRange { start, end })
+            }
+        }
+    }
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl Policy {
+    /// Fill unset lists from `fallback`.
+    pub fn or(self, fallback: &Policy) -> Policy {
+        Policy {
+            allow_ports: self.allow_ports.or_else(|| fallback.allow_ports.clone()),
+            deny_ports: self.deny_ports.or_else(|| fallback.deny_ports.clone()),
+            allow_processes: self
+                .allow_processes
+                .or_else(|| fallback.allow_processes.clone()),
+            deny_processes: self
+                .deny_processes
+                .or_else(|| fallback.deny_processes.clone()),
+            deny_loopback_only: self.deny_loopback_only.or(fallback.deny_loopback_only),
+            allow_loopback_ports: self
+                .allow_loopback_ports
+                .or_else(|| fallback.allow_loopback_ports.clone()),
+            confirm_privileged: self.confirm_privileged.or(fallback.confirm_privileged),
+        }
+    }
+
+    /// Why forwarding `remote_port` should be confirmed first, if it should.
+    pub fn confirmation_reason(
+        &self,
+        remote_port: u16,
+        process: Option<&ProcessInfo>,
+    ) -> Option<&'static str> {
+        if !self.confirm_privileged.unwrap_or(true) {
+            return None;
+        }
+        if process.is_some_and(|p| p.uid == 0) {
+            Some("owned by root")
+        } else if remote_port < PRIVILEGED_PORT_LIMIT {
+            Some("privileged port")
+        } else {
+            None
+        }
+    }
+
+    /// `Err` with the reason when forwarding `remote_port` (owned by `process`,
+    /// listening only on loopback when `loopback_only`) is not allowed.
+    pub fn check(
+        &self,
+        remote_port: u16,
+        process: Option<&str>,
+        loopback_only: bool,
+    ) -> Result<(), String> {
+        if let Some(range) = find_port(&self.deny_ports, remote_port) {
+            return Err(format!("port {range} is in deny_ports"));
+        }
+        if let Some(name) = process.filter(|name| contains_name(&self.deny_processes, name)) {
+            return Err(format!("process {name:?} is in deny_processes"));
+        }
+        if loopback_only
+            && self.deny_loopback_only.unwrap_or(false)
+            && find_port(&self.allow_loopback_ports, remote_port).is_none()
+        {
+            return Err(format!(
+                "port {remote_port} only listens on loopback (deny_loopback_only)"
+            ));
+        }
+        if self.allow_ports.as_ref().is_some_and(|l| !l.is_empty())
+            && find_port(&self.allow_ports, remote_port).is_none()
+        {
+            return Err(format!("port {remote_port} is not in allow_ports"));
+        }
+        if self.allow_processes.as_ref().is_some_and(|l| !l.is_empty())
+            && !process.is_some_and(|name| contains_name(&self.allow_processes, name))
+        {
+            return Err(match process {
+                Some(name) => format!("process {name:?} is not in allow_processes"),
+                None => "owning process is unknown and allow_processes is set".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn find_port(list: &Option<Vec<PortRange>>, port: u16) -> Option<PortRange> {
+    list.iter().flatten().find(|r| r.contains(port)).copied()
+}
+
+fn contains_name(list: &Option<Vec<String>>, name: &str) -> bool {
+    list.iter().flatten().any(|n| n == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Policy {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let policy = parse(
+            r#"
+            allow_ports = ["3000-3999"]
+            deny_ports = [3306]
+            deny_processes = ["mysqld"]
+            "#,
+        );
+        assert!(policy.check(3000, Some("node"), false).is_ok());
+        assert_eq!(
+            policy.check(3306, Some("node"), false),
+            Err("port 3306 is in deny_ports".into())
+        );
+        assert!(policy.check(3001, Some("mysqld"), false).is_err());
+        assert_eq!(
+            policy.check(22, None, false),
+            Err("port 22 is not in allow_ports".into())
+        );
+    }
+
+    #[test]
+    fn allow_processes_requires_known_owner() {
+        let policy = parse(r#"allow_processes = ["node"]"#);
+        assert!(policy.check(8080, Some("node"), false).is_ok());
+        assert!(policy.check(8080, Some("java"), false).is_err());
+        assert!(policy.check(8080, None, false).is_err());
+        assert!(Policy::default().check(22, None, false).is_ok());
+    }
+
+    #[test]
+    fn privileged_or_root_owned_needs_confirmation() {
+        let root = ProcessInfo {
+            pid: 1,
+            name: "mysqld".into(),
+            cmdline: "mysqld".into(),
+            uid: 0,
+            exe: None,
+            cwd: None,
+            start_time: None,
+            env: Default::default(),
+            elevated: true,
+            container: None,
+        };
+        let user = ProcessInfo {
+            uid: 1000,
+            ..root.clone()
+        };
+        let policy = Policy::default();
+        assert_eq!(
+            policy.confirmation_reason(3306, Some(&root)),
+            Some("owned by root")
+        );
+        assert_eq!(
+            policy.confirmation_reason(22, None),
+            Some("privileged port")
+        );
+        assert_eq!(policy.confirmation_reason(3000, Some(&user)), None);
+
+        let policy = parse("confirm_privileged = false");
+        assert_eq!(policy.confirmation_reason(22, Some(&root)), None);
+    }
+
+    #[test]
+    fn loopback_only_ports_need_an_override() {
+        let policy = parse(
+            r#"
+            deny_loopback_only = true
+            allow_loopback_ports = ["9000-9100"]
+            "#,
+        );
+        assert!(policy.check(8080, None, false).is_ok());
+        assert!(policy.check(8080, None, true).is_err());
+        assert!(policy.check(9090, None, true).is_ok());
+        assert!(Policy::default().check(8080, None, true).is_ok());
+    }
+
+    #[test]
+    fn port_range_syntax() {
+        assert!(toml::from_str::<Policy>(r#"deny_ports = ["9000-8000"]"#).is_err());
+        assert!(toml::from_str::<Policy>(r#"deny_ports = ["http"]"#).is_err());
+        let policy = parse(r#"deny_ports = [" 10 - 20 "]"#);
+        assert_eq!(
+            policy.check(15, None, false),
+            Err("port 10-20 is in deny_ports".into())
+        );
+    }
+}
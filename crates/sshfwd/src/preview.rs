@@ -0,0 +1,211 @@
+//! `v`: a quick look at what a forwarded web service answers — one `GET /`
+//! through the tunnel's local end, shown in a modal.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::Message;
+
+/// How much of the body the preview keeps.
+pub const BODY_LIMIT: usize = 1024;
+
+/// Headers worth showing, in this order; the rest are left out.
+const SHOWN_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "transfer-encoding",
+    "location",
+    "server",
+    "cache-control",
+];
+
+/// Largest response head read before giving up on finding its end.
+const HEAD_LIMIT: usize = 16 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The answer to a preview request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preview {
+    /// e.g. `HTTP/1.1 200 OK`.
+    pub status_line: String,
+    pub status: u16,
+    /// `SHOWN_HEADERS` the response had, with their names as sent.
+    pub headers: Vec<(String, String)>,
+    /// The first `BODY_LIMIT` bytes of the body, decoded lossily.
+    pub body: String,
+    /// The body went on past `BODY_LIMIT`.
+    pub truncated: bool,
+}
+
+impl Preview {
+    /// Lines the modal shows: the status, the headers, a blank line, the
+    /// body's lines and a note if the body was cut.
+    pub fn line_count(&self) -> usize {
+        2 + self.headers.len() + self.body.lines().count() + usize::from(self.truncated)
+    }
+}
+
+/// A preview to fetch for the forward of `remote_port`, from its local end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub remote_port: u16,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Fetch `request` on a thread and send it back to the host's model as
+/// `Message::Preview`.
+pub fn spawn(request: Request, tx: crossbeam_channel::Sender<Message>) {
+    std::thread::spawn(move || {
+        let result = fetch(&request.host, request.port).map_err(|e| e.to_string());
+        tx.send(Message::Preview {
+            remote_port: request.remote_port,
+            result,
+        })
+        .ok();
+    });
+}
+
+/// `GET /` from `host:port`, reading the head and at most `BODY_LIMIT`
+/// bytes of the body.
+pub fn fetch(host: &str, port: u16) -> io::Result<Preview> {
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other(format!("{host} has no address")))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: {host}:{port}\r\nUser-Agent: sshfwd\r\nAccept: */*\r\nConnection: close\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = find_head_end(&buf) {
+            break end;
+        }
+        if buf.len() > HEAD_LIMIT {
+            return Err(io::Error::other("response head is too long"));
+        }
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            return Err(io::Error::other(if buf.is_empty() {
+                "connection closed without a response"
+            } else {
+                "connection closed mid-response"
+            }));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    // One byte past the limit tells a body that fits from one that doesn't.
+    while buf.len() < head_end + BODY_LIMIT + 1 {
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            // Whatever arrived before a slow body stalled is still worth showing.
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    parse(&buf[..head_end], &buf[head_end..])
+}
+
+/// Offset just past the blank line ending the head.
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4)
+}
+
+fn parse(head: &[u8], body: &[u8]) -> io::Result<Preview> {
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default().to_string();
+    let status = status_line
+        .strip_prefix("HTTP/")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::other(format!("not an HTTP response: {status_line:?}")))?;
+    let mut headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| SHOWN_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+        .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+        .collect();
+    headers.sort_by_key(|(name, _)| {
+        let name = name.to_ascii_lowercase();
+        SHOWN_HEADERS.iter().position(|&shown| shown == name)
+    });
+    let truncated = body.len() > BODY_LIMIT;
+    let body = String::from_utf8_lossy(&body[..body.len().min(BODY_LIMIT)]).into_owned();
+    Ok(Preview {
+        status_line,
+        status,
+        headers,
+        body,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn serve(response: Vec<u8>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Read the whole request: closing on unread bytes resets the
+            // connection.
+            let mut request = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while find_head_end(&request).is_none() {
+                match stream.read(&mut chunk) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&chunk[..n]),
+                }
+            }
+            let _ = stream.write_all(&response);
+        });
+        port
+    }
+
+    #[test]
+    fn keeps_the_status_chosen_headers_and_the_body_start() {
+        let mut response = b"HTTP/1.1 404 Not Found\r\nX-Request-Id: 7\r\nServer: vite\r\n\
+            Content-Type: text/html\r\n\r\n"
+            .to_vec();
+        response.extend(std::iter::repeat_n(b'a', BODY_LIMIT + 10));
+        let preview = fetch("127.0.0.1", serve(response)).unwrap();
+        assert_eq!(preview.status_line, "HTTP/1.1 404 Not Found");
+        assert_eq!(preview.status, 404);
+        assert_eq!(
+            preview.headers,
+            [
+                ("Content-Type".to_string(), "text/html".to_string()),
+                ("Server".to_string(), "vite".to_string()),
+            ]
+        );
+        assert_eq!(preview.body.len(), BODY_LIMIT);
+        assert!(preview.truncated);
+
+        let preview = fetch("127.0.0.1", serve(b"HTTP/1.0 200 OK\r\n\r\nhello".to_vec())).unwrap();
+        assert_eq!((preview.body.as_str(), preview.truncated), ("hello", false));
+
+        let err = fetch("127.0.0.1", serve(b"SSH-2.0-OpenSSH\r\n\r\n".to_vec())).unwrap_err();
+        assert!(err.to_string().starts_with("not an HTTP response"), "{err}");
+    }
+}
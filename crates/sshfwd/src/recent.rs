@@ -0,0 +1,54 @@
+//! Destinations connected to recently, newest first, for the host picker
+//! (`~/.sshfwd/recent.json`).
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Entries kept; older ones are dropped.
+const MAX_RECENT: usize = 20;
+
+fn recent_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home).join(".sshfwd").join("recent.json")
+}
+
+pub fn load_recent() -> Vec<String> {
+    fs::read_to_string(recent_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Move `destination` to the front of the recent list.
+pub fn save_recent(destination: &str) {
+    let path = recent_path();
+    let recent = remember(load_recent(), destination);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(&recent) {
+        let _ = fs::write(&path, json);
+    }
+}
+
+fn remember(mut recent: Vec<String>, destination: &str) -> Vec<String> {
+    recent.retain(|d| d != destination);
+    recent.insert(0, destination.to_string());
+    recent.truncate(MAX_RECENT);
+    recent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_first_without_duplicates() {
+        let recent = remember(vec!["a".into(), "b".into(), "c".into()], "b");
+        assert_eq!(recent, ["b", "a", "c"]);
+        let many: Vec<String> = (0..MAX_RECENT).map(|i| i.to_string()).collect();
+        let recent = remember(many, "new");
+        assert_eq!(recent.len(), MAX_RECENT);
+        assert_eq!(recent[0], "new");
+    }
+}
@@ -0,0 +1,181 @@
+//! The port table's rows in display order: forwarded ports on top, then a
+//! separator, then the rest. Selection works on these indices, so they are
+//! built without a terminal too.
+
+use sshfwd_common::types::{ListeningPort, Protocol};
+
+use crate::app::{AppMode, Model};
+use crate::forward::{ForwardKey, ForwardKind, ForwardStatus};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayRow {
+    Port(usize),                 // index into model.ports (Forward mode)
+    LocalPort(usize),            // index into model.local_ports (Reverse mode)
+    InactiveForward(u16),        // remote port of a paused local forward not in current scan
+    InactiveReverseForward(u16), // remote bind port (ForwardKey::remote_port) of a paused reverse forward not in local scan
+    Separator,
+}
+
+impl DisplayRow {
+    pub fn is_selectable(&self) -> bool {
+        matches!(
+            self,
+            DisplayRow::Port(_)
+                | DisplayRow::LocalPort(_)
+                | DisplayRow::InactiveForward(_)
+                | DisplayRow::InactiveReverseForward(_)
+        )
+    }
+
+    /// What the row shows, wherever it sits; `None` for the separator.
+    pub fn key(&self, model: &Model) -> Option<RowKey> {
+        let scanned = |port: &ListeningPort| RowKey {
+            host: Some(port.local_addr.clone()),
+            port: port.port,
+            protocol: Some(port.protocol),
+        };
+        match self {
+            DisplayRow::Port(i) => model.ports.get(*i).map(scanned),
+            DisplayRow::LocalPort(i) => model.local_ports.get(*i).map(scanned),
+            DisplayRow::InactiveForward(port) | DisplayRow::InactiveReverseForward(port) => {
+                Some(RowKey {
+                    host: None,
+                    port: *port,
+                    protocol: None,
+                })
+            }
+            DisplayRow::Separator => None,
+        }
+    }
+}
+
+/// Identity of a selectable row across rebuilds: the bind address (a Unix
+/// socket's path), port and protocol of a scanned port, or just the port of
+/// an inactive forward, which no scan lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowKey {
+    pub host: Option<String>,
+    pub port: u16,
+    /// Tells a TCP and a UDP socket on the same address and port apart.
+    pub protocol: Option<Protocol>,
+}
+
+/// Where `key` sits in `rows`: the same row, else one with its port number
+/// (a forwarded port that left the scan is now its inactive row, and back).
+/// Unix sockets, all port 0, only match by path.
+pub fn find_row(model: &Model, rows: &[DisplayRow], key: &RowKey) -> Option<usize> {
+    let keys: Vec<Option<RowKey>> = rows.iter().map(|row| row.key(model)).collect();
+    keys.iter()
+        .position(|k| k.as_ref() == Some(key))
+        .or_else(|| {
+            let port = (key.port != 0).then_some(key.port)?;
+            keys.iter()
+                .position(|k| k.as_ref().is_some_and(|k| k.port == port))
+        })
+}
+
+pub fn build_display_rows(model: &Model) -> Vec<DisplayRow> {
+    match model.mode {
+        AppMode::Forward => build_forward_rows(model),
+        AppMode::Reverse => build_reverse_rows(model),
+    }
+}
+
+fn build_forward_rows(model: &Model) -> Vec<DisplayRow> {
+    let scan_ports: std::collections::HashSet<u16> = model.ports.iter().map(|p| p.port).collect();
+
+    let mut forwarded = Vec::new();
+    let mut non_forwarded = Vec::new();
+
+    for (i, port) in model.ports.iter().enumerate() {
+        if model.forwards.contains_key(&ForwardKey::local(port.port)) {
+            forwarded.push((port.port, DisplayRow::Port(i)));
+        } else if (model.only_mine && !model.is_mine(port))
+            || (model.only_loopback && !port.is_loopback_only())
+        {
+            continue;
+        } else {
+            non_forwarded.push(DisplayRow::Port(i));
+        }
+    }
+
+    // Merge inactive forwards with active forwards, sorted together by port
+    if model.show_inactive_forwards {
+        for (key, entry) in &model.forwards {
+            if key.kind == ForwardKind::Local
+                && entry.status == ForwardStatus::Paused
+                && !scan_ports.contains(&key.remote_port)
+            {
+                forwarded.push((
+                    key.remote_port,
+                    DisplayRow::InactiveForward(key.remote_port),
+                ));
+            }
+        }
+    }
+    forwarded.sort_by(|(port_a, row_a), (port_b, row_b)| {
+        port_a.cmp(port_b).then_with(|| match (row_a, row_b) {
+            (DisplayRow::Port(i1), DisplayRow::Port(i2)) => i1.cmp(i2),
+            (DisplayRow::Port(_), DisplayRow::InactiveForward(_)) => std::cmp::Ordering::Less,
+            (DisplayRow::InactiveForward(_), DisplayRow::Port(_)) => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        })
+    });
+
+    let has_top = !forwarded.is_empty();
+    let mut rows = Vec::with_capacity(forwarded.len() + 1 + non_forwarded.len());
+    rows.extend(forwarded.into_iter().map(|(_, dr)| dr));
+    if has_top && !non_forwarded.is_empty() {
+        rows.push(DisplayRow::Separator);
+    }
+    rows.extend(non_forwarded);
+    rows
+}
+
+fn build_reverse_rows(model: &Model) -> Vec<DisplayRow> {
+    let local_scan_ports: std::collections::HashSet<u16> =
+        model.local_ports.iter().map(|p| p.port).collect();
+
+    // Reverse forwards are keyed by remote bind port; build a local_port → key map
+    // so we can match scan rows (organized by local port) to active forwards.
+    let reverse_by_local: std::collections::HashMap<u16, ForwardKey> = model
+        .forwards
+        .iter()
+        .filter(|(k, _)| k.kind == ForwardKind::Reverse)
+        .map(|(k, e)| (e.local_port, *k))
+        .collect();
+
+    let mut reverse_forwarded = Vec::new();
+    let mut non_reverse_forwarded = Vec::new();
+
+    for (i, port) in model.local_ports.iter().enumerate() {
+        if reverse_by_local.contains_key(&port.port) {
+            reverse_forwarded.push((port.port, DisplayRow::LocalPort(i)));
+        } else {
+            non_reverse_forwarded.push(DisplayRow::LocalPort(i));
+        }
+    }
+
+    // Merge inactive reverse forwards (any status, whose local port is not in local scan).
+    // An Active forward whose local service has stopped is also effectively inactive.
+    if model.show_inactive_forwards {
+        for (key, entry) in &model.forwards {
+            if key.kind == ForwardKind::Reverse && !local_scan_ports.contains(&entry.local_port) {
+                reverse_forwarded.push((
+                    entry.local_port,
+                    DisplayRow::InactiveReverseForward(key.remote_port),
+                ));
+            }
+        }
+    }
+    reverse_forwarded.sort_by_key(|(port, _)| *port);
+
+    let has_top = !reverse_forwarded.is_empty();
+    let mut rows = Vec::with_capacity(reverse_forwarded.len() + 1 + non_reverse_forwarded.len());
+    rows.extend(reverse_forwarded.into_iter().map(|(_, dr)| dr));
+    if has_top && !non_reverse_forwarded.is_empty() {
+        rows.push(DisplayRow::Separator);
+    }
+    rows.extend(non_reverse_forwarded);
+    rows
+}
@@ -0,0 +1,220 @@
+//! `sshfwd service install|uninstall <host>`: a user-level systemd unit
+//! (Linux) or launchd agent (macOS) that runs sshfwd without a terminal
+//! (`--output json --no-notify`) so the host's persisted forwards come back
+//! after a reboot. Events go to the journal / `~/Library/Logs/sshfwd`.
+//!
+//! The service cannot answer prompts: the SSH key must be usable without a
+//! passphrase prompt (agent or unencrypted key) and the agent must not need a
+//! sudo password or a download confirmation.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Manager::Launchd
+        } else {
+            Manager::Systemd
+        }
+    }
+}
+
+/// `deploy@prod-1` → `deploy-prod-1`, safe in unit names and launchd labels.
+fn slug(destination: &str) -> String {
+    destination
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn home() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".into()))
+}
+
+/// Where the definition for `destination` lives.
+pub fn path(manager: Manager, destination: &str) -> PathBuf {
+    match manager {
+        Manager::Systemd => home()
+            .join(".config/systemd/user")
+            .join(format!("sshfwd-{}.service", slug(destination))),
+        Manager::Launchd => home()
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", label(destination))),
+    }
+}
+
+fn label(destination: &str) -> String {
+    format!("io.github.gogoout.sshfwd.{}", slug(destination))
+}
+
+/// The unit or plist running `exe` for `destination`.
+pub fn definition(manager: Manager, exe: &Path, destination: &str) -> String {
+    let args = [
+        exe.display().to_string(),
+        destination.to_string(),
+        "--output".to_string(),
+        "json".to_string(),
+        // A `scan` line every interval would fill the unrotated journal or log.
+        "--changes-only".to_string(),
+        "--no-notify".to_string(),
+    ];
+    match manager {
+        Manager::Systemd => {
+            let exec: Vec<String> = args.iter().map(|a| systemd_quote(a)).collect();
+            format!(
+                "[Unit]\n\
+                 Description=sshfwd forwards for {destination}\n\
+                 After=network-online.target\n\
+                 Wants=network-online.target\n\
+                 \n\
+                 [Service]\n\
+                 ExecStart={}\n\
+                 Restart=always\n\
+                 RestartSec=10\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=default.target\n",
+                exec.join(" ")
+            )
+        }
+        Manager::Launchd => {
+            let log = home()
+                .join("Library/Logs/sshfwd")
+                .join(format!("{}.jsonl", slug(destination)));
+            let program: String = args
+                .iter()
+                .map(|a| format!("        <string>{}</string>\n", xml_escape(a)))
+                .collect();
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+                 \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                 <plist version=\"1.0\">\n\
+                 <dict>\n\
+                 \x20   <key>Label</key>\n\
+                 \x20   <string>{}</string>\n\
+                 \x20   <key>ProgramArguments</key>\n\
+                 \x20   <array>\n\
+                 {program}\
+                 \x20   </array>\n\
+                 \x20   <key>RunAtLoad</key>\n\
+                 \x20   <true/>\n\
+                 \x20   <key>KeepAlive</key>\n\
+                 \x20   <true/>\n\
+                 \x20   <key>ThrottleInterval</key>\n\
+                 \x20   <integer>10</integer>\n\
+                 \x20   <key>StandardOutPath</key>\n\
+                 \x20   <string>{}</string>\n\
+                 \x20   <key>StandardErrorPath</key>\n\
+                 \x20   <string>{}</string>\n\
+                 </dict>\n\
+                 </plist>\n",
+                xml_escape(&label(destination)),
+                xml_escape(&log.display().to_string()),
+                xml_escape(&log.with_extension("err").display().to_string()),
+            )
+        }
+    }
+}
+
+/// Write the definition; returns its path and the command that starts it.
+pub fn install(manager: Manager, destination: &str) -> io::Result<(PathBuf, String)> {
+    let exe = std::env::current_exe()?;
+    let path = path(manager, destination);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if manager == Manager::Launchd {
+        fs::create_dir_all(home().join("Library/Logs/sshfwd"))?;
+    }
+    fs::write(&path, definition(manager, &exe, destination))?;
+    let start = match manager {
+        Manager::Systemd => format!(
+            "systemctl --user daemon-reload && systemctl --user enable --now {}",
+            file_name(&path)
+        ),
+        Manager::Launchd => format!("launchctl load -w {}", path.display()),
+    };
+    Ok((path, start))
+}
+
+/// Remove the definition; returns its path and the command that stops a
+/// running instance (which works without the file).
+pub fn uninstall(manager: Manager, destination: &str) -> io::Result<(PathBuf, String)> {
+    let path = path(manager, destination);
+    let stop = match manager {
+        Manager::Systemd => format!(
+            "systemctl --user stop {} && systemctl --user daemon-reload",
+            file_name(&path)
+        ),
+        Manager::Launchd => format!("launchctl remove {}", label(destination)),
+    };
+    fs::remove_file(&path)?;
+    Ok((path, stop))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Quote for `ExecStart=` when the word has characters systemd would split on.
+fn systemd_quote(word: &str) -> String {
+    if word
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "@%+-_./:=".contains(c))
+    {
+        word.replace('%', "%%")
+    } else {
+        format!(
+            "\"{}\"",
+            word.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('%', "%%")
+        )
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definitions_run_headless() {
+        let exe = Path::new("/home/me/.cargo/bin/sshfwd");
+        let unit = definition(Manager::Systemd, exe, "deploy@prod-1");
+        assert!(unit.contains(
+            "ExecStart=/home/me/.cargo/bin/sshfwd deploy@prod-1 --output json --changes-only --no-notify\n"
+        ));
+        assert!(unit.contains("Restart=always"));
+
+        let plist = definition(Manager::Launchd, exe, "deploy@prod-1");
+        assert!(plist.contains("<string>io.github.gogoout.sshfwd.deploy-prod-1</string>"));
+        assert!(plist.contains("<string>deploy@prod-1</string>"));
+
+        assert_eq!(systemd_quote("my host"), "\"my host\"");
+        assert_eq!(systemd_quote("100%"), "100%%");
+    }
+}
@@ -1,3 +0,0 @@
-pub mod agent;
-pub mod config;
-pub mod session;
@@ -0,0 +1,366 @@
+//! Live summary of each running instance for prompts and status lines.
+//!
+//! A running sshfwd keeps `~/.sshfwd/status/<destination>-<pid>.json` up to
+//! date (rewritten on change, refreshed every `HEARTBEAT`) and removes it on
+//! exit. `sshfwd status` reads them all, skipping files whose heartbeat is
+//! older than `STALE_AFTER` (a crashed instance), and prints e.g.
+//! `prod:3 fwd` with `--format tmux`.
+//!
+//! The files also list each forward as a `Tunnel` (local address, remote
+//! port, process, URL), which editor extensions can read — directly or via
+//! `sshfwd ports --json` — to show sshfwd tunnels next to their own
+//! forwarded ports.
+
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use sshfwd_common::time::epoch_secs;
+use sshfwd_common::types::{ListeningPort, ServiceProbe};
+
+use crate::app::{ConnectionState, Model};
+use crate::forward::{ForwardKind, ForwardStatus};
+
+const HEARTBEAT: Duration = Duration::from_secs(10);
+const STALE_AFTER: u64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceStatus {
+    pub destination: String,
+    pub hostname: Option<String>,
+    pub pid: u32,
+    /// `connecting`, `connected`, `stale` or `reconnecting`.
+    pub state: String,
+    /// Active forwards.
+    pub forwards: usize,
+    /// Remote listening ports in the latest scan.
+    pub ports: usize,
+    /// Epoch seconds of the last write.
+    #[serde(default)]
+    pub updated_at: u64,
+    #[serde(default)]
+    pub tunnels: Vec<Tunnel>,
+}
+
+/// One forward, as an editor's ports view would show it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tunnel {
+    pub kind: ForwardKind,
+    /// Where a local client connects (local forwards) or the local service
+    /// the remote reaches (reverse forwards).
+    pub local_address: String,
+    pub local_port: u16,
+    pub remote_port: u16,
+    /// `active`, `starting` or `paused`.
+    pub status: String,
+    pub process: Option<String>,
+    /// `http://` / `https://` URL when the agent's probe saw HTTP or TLS.
+    pub url: Option<String>,
+    pub connections: u32,
+}
+
+impl InstanceStatus {
+    /// `local_address` is where local forwards listen.
+    pub fn of(model: &Model, local_address: &str) -> Self {
+        let state = match model.connection_state {
+            ConnectionState::Connecting => "connecting",
+            ConnectionState::Connected => "connected",
+            ConnectionState::Stale => "stale",
+            ConnectionState::Reconnecting => "reconnecting",
+        };
+        let mut ports: Vec<u16> = model.ports.iter().map(|p| p.port).collect();
+        ports.dedup();
+        Self {
+            destination: model.destination.clone(),
+            hostname: model.hostname.clone(),
+            pid: std::process::id(),
+            state: state.to_string(),
+            forwards: model
+                .forwards
+                .values()
+                .filter(|e| e.status == ForwardStatus::Active)
+                .count(),
+            ports: ports.len(),
+            updated_at: 0,
+            tunnels: tunnels(model, local_address),
+        }
+    }
+
+    /// Short name for a status line: the host of the destination up to the
+    /// first dot (`deploy@prod.example.com` → `prod`).
+    fn label(&self) -> &str {
+        let host = self
+            .destination
+            .rsplit_once('@')
+            .map_or(self.destination.as_str(), |(_, h)| h);
+        host.split('.').next().unwrap_or(host)
+    }
+
+    fn tmux(&self) -> String {
+        match self.state.as_str() {
+            "reconnecting" => format!("{}:down", self.label()),
+            _ => format!("{}:{} fwd", self.label(), self.forwards),
+        }
+    }
+}
+
+fn tunnels(model: &Model, local_address: &str) -> Vec<Tunnel> {
+    let mut tunnels: Vec<Tunnel> = model
+        .forwards
+        .iter()
+        .map(|(key, entry)| {
+            let remote = (key.kind == ForwardKind::Local)
+                .then(|| model.ports.iter().find(|p| p.port == key.remote_port))
+                .flatten();
+            let address = match key.kind {
+                ForwardKind::Local => local_address,
+                ForwardKind::Reverse => "127.0.0.1",
+            };
+            Tunnel {
+                kind: key.kind,
+                local_address: address.to_string(),
+                local_port: entry.local_port,
+                remote_port: key.remote_port,
+                status: match entry.status {
+                    ForwardStatus::Active => "active",
+                    ForwardStatus::Starting => "starting",
+                    ForwardStatus::Paused => "paused",
+                }
+                .to_string(),
+                process: remote
+                    .and_then(|p| p.process.as_ref())
+                    .map(|p| p.name.clone()),
+                url: tunnel_url(remote, address, entry.local_port),
+                connections: entry.active_connections,
+            }
+        })
+        .collect();
+    tunnels.sort_by_key(|t| (t.kind == ForwardKind::Reverse, t.remote_port));
+    tunnels
+}
+
+/// `http(s)://address:local_port/` when the probe of the forwarded `remote`
+/// port saw HTTP or TLS.
+pub fn tunnel_url(
+    remote: Option<&ListeningPort>,
+    address: &str,
+    local_port: u16,
+) -> Option<String> {
+    let scheme = match remote.and_then(|p| p.probe.as_ref()) {
+        Some(ServiceProbe::Http { .. }) => "http",
+        Some(ServiceProbe::Tls { .. }) => "https",
+        _ => return None,
+    };
+    Some(format!("{scheme}://{address}:{local_port}/"))
+}
+
+/// Keeps this instance's status file current.
+pub struct StatusFile {
+    /// Where clients reach local forwards (`127.0.0.1` unless shared).
+    local_address: String,
+    path: PathBuf,
+    last: Option<InstanceStatus>,
+    written_at: Option<Instant>,
+}
+
+impl StatusFile {
+    pub fn new(destination: &str, bind_address: IpAddr) -> Self {
+        let name: String = destination
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "@.-_".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let local_address = if bind_address.is_unspecified() {
+            "127.0.0.1".to_string()
+        } else {
+            bind_address.to_string()
+        };
+        Self {
+            local_address,
+            path: status_dir().join(format!("{name}-{}.json", std::process::id())),
+            last: None,
+            written_at: None,
+        }
+    }
+
+    /// Where clients reach local forwards.
+    pub fn local_address(&self) -> &str {
+        &self.local_address
+    }
+
+    /// Write `model`'s status if it changed or the heartbeat is due.
+    pub fn update(&mut self, model: &Model) -> io::Result<()> {
+        let status = InstanceStatus::of(model, &self.local_address);
+        let due = self.written_at.is_none_or(|at| at.elapsed() >= HEARTBEAT);
+        if !due && self.last.as_ref() == Some(&status) {
+            return Ok(());
+        }
+        let stamped = InstanceStatus {
+            updated_at: epoch_secs(SystemTime::now()),
+            ..status.clone()
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write then rename so readers never see a partial file.
+        let tmp = self.path.with_extension("tmp");
+        fs::write(
+            &tmp,
+            serde_json::to_string(&stamped).expect("status serializes"),
+        )?;
+        fs::rename(&tmp, &self.path)?;
+        self.last = Some(status);
+        self.written_at = Some(Instant::now());
+        Ok(())
+    }
+
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn status_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
+    PathBuf::from(home).join(".sshfwd").join("status")
+}
+
+/// Status of every live instance, sorted by destination.
+pub fn read_all() -> Vec<InstanceStatus> {
+    let now = epoch_secs(SystemTime::now());
+    let Ok(entries) = fs::read_dir(status_dir()) else {
+        return Vec::new();
+    };
+    let mut statuses: Vec<InstanceStatus> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|data| serde_json::from_str::<InstanceStatus>(&data).ok())
+        .filter(|s| now.saturating_sub(s.updated_at) <= STALE_AFTER)
+        .collect();
+    statuses.sort_by(|a, b| a.destination.cmp(&b.destination).then(a.pid.cmp(&b.pid)));
+    statuses
+}
+
+/// `sshfwd ports`: every tunnel of every instance, as a table or (`json`)
+/// an array of tunnels with their `destination`.
+pub fn render_tunnels(statuses: &[InstanceStatus], json: bool) -> String {
+    if json {
+        let tunnels: Vec<serde_json::Value> = statuses
+            .iter()
+            .flat_map(|s| {
+                s.tunnels.iter().map(|t| {
+                    let mut value = serde_json::to_value(t).expect("tunnel serializes");
+                    value["destination"] = s.destination.clone().into();
+                    value
+                })
+            })
+            .collect();
+        return serde_json::to_string(&tunnels).expect("tunnels serialize");
+    }
+    let mut lines = Vec::new();
+    for s in statuses {
+        for t in &s.tunnels {
+            let arrow = match t.kind {
+                ForwardKind::Local => "<-",
+                ForwardKind::Reverse => "->",
+            };
+            lines.push(format!(
+                "{}:{} {arrow} {}:{}  {}  {}",
+                t.local_address,
+                t.local_port,
+                s.destination,
+                t.remote_port,
+                t.status,
+                t.url.as_deref().or(t.process.as_deref()).unwrap_or("")
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Render `statuses` as `format`: `text`, `tmux` or `json`.
+pub fn render(statuses: &[InstanceStatus], format: &str) -> Result<String, String> {
+    match format {
+        "tmux" => Ok(statuses
+            .iter()
+            .map(InstanceStatus::tmux)
+            .collect::<Vec<_>>()
+            .join(" ")),
+        "json" => Ok(serde_json::to_string(statuses).expect("status serializes")),
+        "text" => Ok(statuses
+            .iter()
+            .map(|s| {
+                format!(
+                    "{}  {}  {} forwards  {} ports  (pid {})",
+                    s.destination, s.state, s.forwards, s.ports, s.pid
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        other => Err(format!(
+            "unknown --format {other:?}, expected text, tmux or json"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(destination: &str, state: &str, forwards: usize) -> InstanceStatus {
+        InstanceStatus {
+            destination: destination.into(),
+            hostname: None,
+            pid: 1,
+            state: state.into(),
+            forwards,
+            ports: 7,
+            updated_at: 0,
+            tunnels: vec![Tunnel {
+                kind: ForwardKind::Local,
+                local_address: "127.0.0.1".into(),
+                local_port: 18080,
+                remote_port: 8080,
+                status: "active".into(),
+                process: Some("node".into()),
+                url: Some("http://127.0.0.1:18080/".into()),
+                connections: 0,
+            }],
+        }
+    }
+
+    #[test]
+    fn tmux_format_is_compact() {
+        let statuses = [
+            status("deploy@prod.example.com", "connected", 3),
+            status("staging", "reconnecting", 1),
+        ];
+        assert_eq!(
+            render(&statuses, "tmux").unwrap(),
+            "prod:3 fwd staging:down"
+        );
+        assert_eq!(render(&[], "tmux").unwrap(), "");
+        assert!(render(&statuses, "yaml").is_err());
+    }
+
+    #[test]
+    fn tunnels_carry_their_destination() {
+        let statuses = [status("dev@box", "connected", 1)];
+        let json: serde_json::Value =
+            serde_json::from_str(&render_tunnels(&statuses, true)).unwrap();
+        assert_eq!(json[0]["destination"], "dev@box");
+        assert_eq!(json[0]["url"], "http://127.0.0.1:18080/");
+        assert_eq!(
+            render_tunnels(&statuses, false),
+            "127.0.0.1:18080 <- dev@box:8080  active  http://127.0.0.1:18080/"
+        );
+    }
+}
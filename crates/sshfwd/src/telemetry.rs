@@ -0,0 +1,88 @@
+//! Export of tracing spans (SSH connect/exec, channel opens, agent deploy,
+//! tunnels) over OTLP/HTTP when `[telemetry] otlp_endpoint` is set. Needs the
+//! `otel` cargo feature; without it the spans cost nothing.
+
+use crate::config::TelemetryConfig;
+
+/// Flushes buffered spans when dropped.
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        let _ = self.provider.shutdown();
+    }
+}
+
+/// Install the exporter, if configured. Call outside the tokio runtime: the
+/// blocking HTTP client owns one of its own.
+pub fn init(config: &TelemetryConfig) -> Result<Option<Telemetry>, String> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    install(endpoint).map(Some)
+}
+
+#[cfg(feature = "otel")]
+fn install(endpoint: &str) -> Result<Telemetry, String> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let endpoint = traces_url(endpoint);
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&endpoint)
+        .build()
+        .map_err(|e| format!("OTLP exporter for {endpoint}: {e}"))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("sshfwd"));
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .map_err(|e| e.to_string())?;
+    Ok(Telemetry { provider })
+}
+
+#[cfg(not(feature = "otel"))]
+fn install(_endpoint: &str) -> Result<Telemetry, String> {
+    Err("[telemetry] otlp_endpoint needs sshfwd built with the `otel` feature".to_string())
+}
+
+/// The collector's base URL with the OTLP/HTTP traces path appended.
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{endpoint}/v1/traces")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_path_is_appended_once() {
+        assert_eq!(
+            traces_url("http://localhost:4318/"),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url("https://otel.corp/v1/traces"),
+            "https://otel.corp/v1/traces"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Test data for unit tests. Override fields with struct update syntax:
+//! `ListeningPort { local_addr: "0.0.0.0".into(), ..listening_port(80) }`.
+
+use sshfwd_common::types::{ListeningPort, ProcessInfo, Protocol};
+
+/// TCP on `127.0.0.1:<port>`, owner unknown.
+pub fn listening_port(port: u16) -> ListeningPort {
+    ListeningPort {
+        protocol: Protocol::Tcp,
+        local_addr: "127.0.0.1".into(),
+        port,
+        process: None,
+        bindings: Vec::new(),
+        probe: None,
+        stats: None,
+    }
+}
+
+/// `name` (also its command line) as `pid`, uid 1000.
+pub fn process_info(pid: u32, name: &str) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        name: name.into(),
+        cmdline: name.into(),
+        uid: 1000,
+        exe: None,
+        cwd: None,
+        start_time: None,
+        env: Default::default(),
+        elevated: false,
+        container: None,
+    }
+}
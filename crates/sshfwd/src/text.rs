@@ -0,0 +1,55 @@
+//! Text measured in terminal cells: CJK characters take two, combining marks
+//! none, and an emoji sequence is one unit that can't be split.
+
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Cells `text` takes on screen.
+pub fn width(text: &str) -> usize {
+    text.width()
+}
+
+/// `text` cut to at most `max` cells, ending in `…` when anything was cut.
+/// Only whole grapheme clusters are kept, so no character loses its
+/// combining marks and no wide character is cut in half.
+pub fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+    if text.width() <= max {
+        return Cow::Borrowed(text);
+    }
+    if max == 0 {
+        return Cow::Borrowed("");
+    }
+    let room = max.saturating_sub(1);
+    let mut used = 0;
+    let mut cut = 0;
+    for (at, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > room {
+            break;
+        }
+        cut = at + grapheme.len();
+    }
+    Cow::Owned(format!("{}…", &text[..cut]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_by_cells_on_grapheme_boundaries() {
+        assert_eq!(truncate("node server.js", 20), "node server.js");
+        assert_eq!(truncate("node server.js", 8), "node se…");
+        // Two cells each: the third doesn't fit next to the ellipsis.
+        assert_eq!(truncate("python3 服务器.py", 12), "python3 服…");
+        assert_eq!(width(&truncate("python3 服务器.py", 12)), 11);
+        // A family emoji (ZWJ sequence) and an accent stay whole.
+        let family = "run 👨‍👩‍👧 cafe\u{301}";
+        assert_eq!(truncate(family, 6), "run …");
+        assert_eq!(truncate(family, 11), "run 👨‍👩‍👧 cafe\u{301}");
+        assert_eq!(truncate(family, 10), "run 👨‍👩‍👧 ca…");
+        assert_eq!(truncate("abc", 0), "");
+    }
+}
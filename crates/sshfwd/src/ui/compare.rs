@@ -0,0 +1,88 @@
+use ratatui::layout::{Constraint, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Row, Table};
+use ratatui::Frame;
+
+use super::hotkey_spans;
+use crate::app::Model;
+use crate::compare::{compare, Difference};
+
+const HEADER_STYLE: Style = Style::new()
+    .fg(Color::DarkGray)
+    .add_modifier(Modifier::BOLD);
+
+/// `left` and `right`'s remote ports side by side, starting `scroll` rows in.
+/// Ports on one side only are yellow, different owners magenta.
+pub fn render(left: &Model, right: &Model, scroll: usize, frame: &mut Frame, area: Rect) {
+    let rows = compare(&left.ports, &right.ports);
+    let only_left = count(&rows, Difference::LeftOnly);
+    let only_right = count(&rows, Difference::RightOnly);
+    let differing = count(&rows, Difference::Process);
+
+    let mut title = vec![
+        Span::raw(" "),
+        Span::styled(left.destination.clone(), Style::default().fg(left.accent)),
+        Span::raw(" ⇆ "),
+        Span::styled(right.destination.clone(), Style::default().fg(right.accent)),
+        Span::styled(
+            format!(" │ {only_left} only left │ {only_right} only right │ {differing} differ "),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ];
+    if rows.is_empty() {
+        title.push(Span::styled(
+            "│ no ports yet ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let mut keys = vec![Span::raw(" ")];
+    keys.extend(hotkey_spans("j/k", "Scroll "));
+    keys.extend(hotkey_spans("[/]", "Other host "));
+    keys.extend(hotkey_spans("C/Esc", "Close "));
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Line::from(title))
+        .title_bottom(Line::from(keys));
+
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .skip(scroll)
+        .map(|row| {
+            let cell = |side: &Option<String>| match side {
+                Some(name) if name.is_empty() => "?".to_string(),
+                Some(name) => name.clone(),
+                None => "—".to_string(),
+            };
+            let style = match row.difference {
+                Difference::Same => Style::default().fg(Color::DarkGray),
+                Difference::Process => Style::default().fg(Color::Magenta),
+                Difference::LeftOnly | Difference::RightOnly => Style::default().fg(Color::Yellow),
+            };
+            Row::new([row.port.to_string(), cell(&row.left), cell(&row.right)]).style(style)
+        })
+        .collect();
+
+    let header = Row::new([
+        "PORT".to_string(),
+        left.destination.to_uppercase(),
+        right.destination.to_uppercase(),
+    ])
+    .style(HEADER_STYLE);
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(table_rows, widths)
+        .block(block)
+        .header(header)
+        .column_spacing(2);
+    frame.render_widget(table, area);
+}
+
+fn count(rows: &[crate::compare::CompareRow], difference: Difference) -> usize {
+    rows.iter().filter(|r| r.difference == difference).count()
+}
@@ -0,0 +1,213 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Paragraph};
+use ratatui::Frame;
+
+use sshfwd_common::types::{ConnectionStats, HostInfo, ListeningPort};
+
+use crate::app::{AppMode, Model};
+use crate::forward::pool::OpenWait;
+use crate::forward::ForwardKey;
+use crate::text;
+
+/// Border (2) + one line per field.
+pub const HEIGHT: u16 = 11;
+
+const LABEL_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+pub fn render(model: &Model, frame: &mut Frame, area: Rect) {
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(" Details ");
+
+    let lines = match model.selected_listening_port() {
+        Some(port) => {
+            let mut lines = vec![field(
+                "Bind",
+                format!("{} ({})", format_bind(port), port.protocol),
+            )];
+            if let Some(probe) = &port.probe {
+                lines.push(field("Service", probe.to_string()));
+            }
+            if let Some(stats) = &port.stats {
+                lines.push(field("Conns", format_stats(stats)));
+            }
+            let forward = match model.mode {
+                AppMode::Forward => model.forwards.get(&ForwardKey::local(port.port)),
+                AppMode::Reverse => None,
+            };
+            if let Some(wait) = forward.map(|e| e.open_wait).filter(|w| w.opens > 0) {
+                lines.push(field("Opens", format_open_wait(&wait)));
+            }
+            match &port.process {
+                Some(p) => {
+                    let mut process = format!("{} (pid {}, uid {})", p.name, p.pid, p.uid);
+                    if let Some(age) = p.start_time.and_then(age_secs) {
+                        process.push_str(&format!(" · started {} ago", format_age(age)));
+                    }
+                    if p.elevated {
+                        process.push_str(" · other user, seen via sudo");
+                    }
+                    lines.push(field("Process", process));
+                    if let Some(container) = &p.container {
+                        lines.push(field("Runs in", container.to_string()));
+                    }
+                    lines.push(field("Exe", p.exe.clone().unwrap_or_else(|| "-".into())));
+                    lines.push(field("Cwd", p.cwd.clone().unwrap_or_else(|| "-".into())));
+                    lines.push(field("Cmd", p.cmdline.clone()));
+                    let env = if p.env.is_empty() {
+                        "-".to_string()
+                    } else {
+                        p.env
+                            .iter()
+                            .map(|(k, v)| format!("{k}={v}"))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    };
+                    lines.push(field("Env", env));
+                }
+                None => lines.push(field("Process", "unknown (no permission?)".to_string())),
+            }
+            lines
+        }
+        // Nothing to describe: describe the host instead.
+        None => match &model.host {
+            Some(host) => host_lines(host),
+            None => vec![Line::styled(" No process selected", LABEL_STYLE)],
+        },
+    };
+
+    let room = usize::from(block.inner(area).width);
+    let lines: Vec<Line> = lines.into_iter().map(|line| fit(line, room)).collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn field(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!(" {label:<8}"), LABEL_STYLE),
+        Span::raw(value),
+    ])
+}
+
+/// `line` cut to `width` cells, its value ending in `…` when cut.
+fn fit(mut line: Line<'static>, width: usize) -> Line<'static> {
+    if let [label, value] = line.spans.as_mut_slice() {
+        let room = width.saturating_sub(label.width());
+        if value.width() > room {
+            value.content = text::truncate(&value.content, room).into_owned().into();
+        }
+    }
+    line
+}
+
+fn host_lines(host: &HostInfo) -> Vec<Line<'static>> {
+    let or_dash = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".into());
+    let mut lines = vec![
+        field("OS", or_dash(&host.os)),
+        field("Kernel", or_dash(&host.kernel)),
+        field(
+            "Uptime",
+            host.uptime_secs.map_or_else(|| "-".into(), format_age),
+        ),
+        field("Load", or_dash(&host.load_avg)),
+    ];
+    if host.runs_containers() {
+        let mut runs = host.container_runtimes.clone();
+        if host.kubernetes {
+            runs.push("kubernetes node".into());
+        }
+        lines.push(field("Runs", runs.join(" · ")));
+    }
+    lines
+}
+
+/// `addr:port`, or the port and each address with its interface when the
+/// agent named them.
+fn format_bind(port: &ListeningPort) -> String {
+    let bindings = port.bindings();
+    if port.protocol.is_unix() || bindings.len() == 1 && bindings[0].interface.is_none() {
+        return port.bind();
+    }
+    let addrs: Vec<String> = bindings.iter().map(ToString::to_string).collect();
+    format!(":{} on {}", port.port, addrs.join(", "))
+}
+
+/// `3 established · accept 0/128 · recv-q 0 · send-q 12` (backlog omitted when unknown).
+fn format_stats(stats: &ConnectionStats) -> String {
+    let mut out = format!(
+        "{} established · accept {}",
+        stats.established, stats.accept_queue
+    );
+    if stats.backlog > 0 {
+        out.push_str(&format!("/{}", stats.backlog));
+    }
+    out.push_str(&format!(
+        " · recv-q {} · send-q {}",
+        stats.recv_queue, stats.send_queue
+    ));
+    out
+}
+
+/// `12 opens · 3 queued · avg 4ms · max 40ms`.
+fn format_open_wait(wait: &OpenWait) -> String {
+    format!(
+        "{} opens · {} queued · avg {}ms · max {}ms",
+        wait.opens,
+        wait.queued,
+        wait.average().as_millis(),
+        wait.max.as_millis()
+    )
+}
+
+/// Seconds elapsed since an epoch timestamp (None if it lies in the future).
+fn age_secs(start_time: u64) -> Option<u64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    now.checked_sub(start_time)
+}
+
+/// Compact human duration: `45s`, `12m`, `3h 12m`, `2d 4h`.
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s => format!("{}d {}h", s / 86_400, s % 86_400 / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_age_units() {
+        assert_eq!(format_age(45), "45s");
+        assert_eq!(format_age(12 * 60 + 5), "12m");
+        assert_eq!(format_age(3 * 3600 + 12 * 60), "3h 12m");
+        assert_eq!(format_age(2 * 86_400 + 4 * 3600 + 59), "2d 4h");
+    }
+
+    #[test]
+    fn format_stats_hides_unknown_backlog() {
+        let mut stats = ConnectionStats {
+            established: 3,
+            accept_queue: 1,
+            backlog: 128,
+            recv_queue: 0,
+            send_queue: 12,
+        };
+        assert_eq!(
+            format_stats(&stats),
+            "3 established · accept 1/128 · recv-q 0 · send-q 12"
+        );
+        stats.backlog = 0;
+        assert_eq!(
+            format_stats(&stats),
+            "3 established · accept 1 · recv-q 0 · send-q 12"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Paragraph};
+use ratatui::Frame;
+
+use crate::app::{EventEntry, EventLevel, Model};
+use crate::ui::detail::format_age;
+
+/// Border (2) + six entries.
+pub const HEIGHT: u16 = 8;
+
+const AGE_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+pub fn render(model: &Model, frame: &mut Frame, area: Rect) {
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(" Events ");
+
+    // Newest at the bottom, like a log tail.
+    let visible = area.height.saturating_sub(2) as usize;
+    let skip = model.events.len().saturating_sub(visible);
+    let lines: Vec<Line> = if model.events.is_empty() {
+        vec![Line::styled(" No events", AGE_STYLE)]
+    } else {
+        model.events.iter().skip(skip).map(entry_line).collect()
+    };
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn entry_line(entry: &EventEntry) -> Line<'static> {
+    let (label, color) = level_label(entry.level);
+    Line::from(vec![
+        Span::styled(
+            format!(" {:>6} ", format_age(entry.at.elapsed().as_secs())),
+            AGE_STYLE,
+        ),
+        Span::styled(format!("{label:<5} "), Style::default().fg(color)),
+        Span::raw(entry.message.clone()),
+    ])
+}
+
+fn level_label(level: EventLevel) -> (&'static str, Color) {
+    match level {
+        EventLevel::Info => ("info", Color::Cyan),
+        EventLevel::Warn => ("warn", Color::Yellow),
+        EventLevel::Error => ("error", Color::Red),
+    }
+}
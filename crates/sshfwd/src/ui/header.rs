@@ -3,6 +3,7 @@ use ratatui::text::{Line, Span};
 
 use crate::app::{AppMode, ConnectionState, Model};
 use crate::forward::{ForwardKind, ForwardStatus};
+use crate::ui::detail::format_age;
 use crate::ui::{CONNECTED_CHAR, CONNECTING_CHAR, DISCONNECT_CHAR};
 
 pub fn build_title(model: &Model) -> Line<'static> {
@@ -10,7 +11,7 @@ pub fn build_title(model: &Model) -> Line<'static> {
         ConnectionState::Connecting => (CONNECTING_CHAR, Style::default().fg(Color::Yellow)),
         ConnectionState::Connected => (CONNECTED_CHAR, Style::default().fg(Color::Green)),
         ConnectionState::Reconnecting => (CONNECTING_CHAR, Style::default().fg(Color::Yellow)),
-        ConnectionState::Disconnected => (DISCONNECT_CHAR, Style::default().fg(Color::Red)),
+        ConnectionState::Stale => (DISCONNECT_CHAR, Style::default().fg(Color::Red)),
     };
 
     let conn_str = match (&model.username, &model.hostname) {
@@ -43,7 +44,7 @@ pub fn build_title(model: &Model) -> Line<'static> {
         Span::raw(" "),
         Span::styled(indicator.to_string(), indicator_style),
         Span::raw(" "),
-        Span::styled(conn_str, Style::default().fg(Color::Cyan)),
+        Span::styled(conn_str, Style::default().fg(model.accent)),
         Span::styled(
             match model.mode {
                 AppMode::Forward => format!(" │ {} remote ports ", port_count),
@@ -52,6 +53,18 @@ pub fn build_title(model: &Model) -> Line<'static> {
             Style::default().fg(Color::DarkGray),
         ),
     ];
+    if let Some(host) = &model.host {
+        let mut parts: Vec<String> = host.os.iter().cloned().collect();
+        if let Some(load) = host.load_avg.as_deref().and_then(|l| l.split(' ').next()) {
+            parts.push(format!("load {load}"));
+        }
+        if !parts.is_empty() {
+            spans.push(Span::styled(
+                format!("│ {} ", parts.join(" · ")),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+    }
 
     let fwd_kind_count = model
         .forwards
@@ -77,6 +90,63 @@ pub fn build_title(model: &Model) -> Line<'static> {
             Style::default().fg(Color::Magenta),
         ));
     }
+    if let Some(stats) = model.session_stats {
+        let mut parts = Vec::new();
+        if stats.connections > 0 {
+            parts.push(format!("{} conns", stats.connections));
+        }
+        if stats.bytes > 0 {
+            parts.push(format!("⇅ {}", format_bytes(stats.bytes)));
+        }
+        parts.push(format!("up {}", format_age(stats.duration_secs)));
+        spans.push(Span::styled(
+            format!("│ {} ", parts.join(" · ")),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if model.only_mine && model.mode == AppMode::Forward {
+        spans.push(Span::styled(
+            "│ mine only ",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if !model.marked.is_empty() && model.mode == AppMode::Forward {
+        spans.push(Span::styled(
+            format!("│ {} marked ", model.marked.len()),
+            Style::default().fg(Color::Blue),
+        ));
+    }
+
+    if model.only_loopback && model.mode == AppMode::Forward {
+        spans.push(Span::styled(
+            "│ loopback only ",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if model.warning_indicator_active() {
+        let n = model.unseen_warnings;
+        spans.push(Span::styled(
+            format!("│ ⚠ {n} warning{} ", if n == 1 { "" } else { "s" }),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    // Only once it's older than discovery tolerates; fresh data is the norm.
+    if let Some(age) = model.data_age().filter(|&age| age >= model.stale_after) {
+        spans.push(Span::styled(
+            format!("│ data {} old ", format_age(age.as_secs())),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if let Some(took) = model.slow_scan {
+        spans.push(Span::styled(
+            format!("│ slow scans {:.1}s ", took.as_secs_f64()),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
 
     spans.push(Span::raw("│ "));
     spans.push(Span::styled(mode_label, mode_style));
@@ -84,3 +154,31 @@ pub fn build_title(model: &Model) -> Line<'static> {
 
     Line::from(spans)
 }
+
+/// Compact byte count: `512 B`, `1.5 KiB`, `3.2 MiB`, `1.1 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 + 200 * 1024), "3.2 MiB");
+        assert_eq!(format_bytes(5 << 40), "5.0 TiB");
+    }
+}
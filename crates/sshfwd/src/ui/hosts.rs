@@ -0,0 +1,69 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::app::{ConnectionState, Model};
+use crate::forward::ForwardStatus;
+use crate::ui::{hotkey_spans, CONNECTED_CHAR, CONNECTING_CHAR, DISCONNECT_CHAR};
+
+/// Host switcher shown above the table when several destinations are open:
+/// ` 1 ● web1 2 fwd │ 2 ● db `, the host on screen highlighted, followed by
+/// the host keys.
+pub fn build_tabs(models: &[&Model], active: usize) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (i, model) in models.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled("│", Style::default().fg(Color::DarkGray)));
+        }
+        let base = if i == active {
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let (indicator, color) = match model.connection_state {
+            _ if model.startup_error.is_some() => (DISCONNECT_CHAR, Color::Red),
+            ConnectionState::Connecting | ConnectionState::Reconnecting => {
+                (CONNECTING_CHAR, Color::Yellow)
+            }
+            ConnectionState::Connected => (CONNECTED_CHAR, Color::Green),
+            ConnectionState::Stale => (DISCONNECT_CHAR, Color::Red),
+        };
+        let forwards = model
+            .forwards
+            .values()
+            .filter(|e| e.status == ForwardStatus::Active)
+            .count();
+        spans.push(Span::styled(format!(" {} ", i + 1), base.fg(Color::Yellow)));
+        spans.push(Span::styled(indicator.to_string(), base.fg(color)));
+        spans.push(Span::styled(
+            format!(" {} ", model.destination),
+            base.fg(model.accent),
+        ));
+        if model.startup_error.is_some() {
+            spans.push(Span::styled("failed ", base.fg(Color::Red)));
+        } else if let Some(stage) = model.startup {
+            spans.push(Span::styled(
+                format!("{} ", stage.label()),
+                base.fg(Color::DarkGray),
+            ));
+        }
+        if let Some(target) = &model.jump_target {
+            spans.push(Span::styled(
+                format!("jump → {target} "),
+                base.fg(Color::DarkGray),
+            ));
+        }
+        if forwards > 0 {
+            spans.push(Span::styled(
+                format!("{forwards} fwd "),
+                base.fg(Color::Green),
+            ));
+        }
+    }
+    spans.push(Span::raw("  "));
+    spans.extend(hotkey_spans("[/]", "Host "));
+    spans.extend(hotkey_spans("A", "All hosts "));
+    spans.extend(hotkey_spans("C", "Compare"));
+    Line::from(spans)
+}
@@ -3,23 +3,201 @@ use ratatui::text::{Line, Span};
 use ratatui::Frame;
 
 use super::hotkey_spans;
-use crate::app::{AppMode, Model};
+use crate::app::{self, AppMode, ModalState, Model, PromptKind};
+use crate::forward::{ForwardKey, ForwardKind, ForwardStatus};
+use crate::rows::{build_display_rows, DisplayRow};
+use crate::text;
+
+/// A key worth showing now. Lower `rank`s are the last to go when the bar is
+/// too narrow for all of them.
+struct Hint {
+    key: &'static str,
+    desc: &'static str,
+    rank: u8,
+}
+
+const fn hint(key: &'static str, desc: &'static str, rank: u8) -> Hint {
+    Hint { key, desc, rank }
+}
 
 pub fn render(model: &Model, frame: &mut Frame, area: Rect) {
+    let mut hints = if model.modal == ModalState::None {
+        normal_hints(model)
+    } else {
+        modal_hints(&model.modal)
+    };
+    elide(&mut hints, usize::from(area.width));
     let mut spans = vec![Span::raw(" ")];
-    spans.extend(hotkey_spans("j/k", "Navigate "));
-    spans.extend(hotkey_spans("g/G", "Top/Bottom "));
+    for hint in &hints {
+        spans.extend(hotkey_spans(hint.key, hint.desc));
+        spans.push(Span::raw(" "));
+    }
+    frame.render_widget(Line::from(spans), area);
+}
+
+/// Keep what fits in `width` columns, lowest rank first (leftmost among
+/// equals), in the original order.
+fn elide(hints: &mut Vec<Hint>, width: usize) {
+    let cost = |h: &Hint| text::width(h.key) + text::width(h.desc) + 3;
+    let mut order: Vec<usize> = (0..hints.len()).collect();
+    order.sort_by_key(|&i| hints[i].rank);
+    let mut keep = vec![false; hints.len()];
+    // The leading space.
+    let mut used = 1;
+    for i in order {
+        if used + cost(&hints[i]) <= width {
+            used += cost(&hints[i]);
+            keep[i] = true;
+        }
+    }
+    let mut keep = keep.into_iter();
+    hints.retain(|_| keep.next().unwrap_or(false));
+}
+
+/// The keys that do something for the selected row and mode.
+fn normal_hints(model: &Model) -> Vec<Hint> {
+    let rows = build_display_rows(model);
+    let row = rows.get(model.selected_index);
+    let mut hints = vec![hint("j/k", "Navigate", 2), hint("g/G", "Top/Bottom", 7)];
     match model.mode {
         AppMode::Forward => {
-            spans.extend(hotkey_spans("Enter/f", "Forward "));
-            spans.extend(hotkey_spans("F", "Custom Port "));
+            let forwarded = model
+                .selected_port()
+                .is_some_and(|p| model.forwards.contains_key(&ForwardKey::local(p)));
+            let tcp = model
+                .selected_listening_port()
+                .is_none_or(|p| p.protocol.is_tcp());
+            match row {
+                _ if !model.marked.is_empty() => {
+                    let all_forwarded = model
+                        .marked
+                        .iter()
+                        .all(|&p| model.forwards.contains_key(&ForwardKey::local(p)));
+                    let action = if all_forwarded {
+                        "Stop marked"
+                    } else {
+                        "Forward marked"
+                    };
+                    hints.push(hint("Enter/f", action, 1));
+                    hints.push(hint("Esc", "Unmark", 1));
+                }
+                Some(DisplayRow::Port(_) | DisplayRow::InactiveForward(_)) if forwarded => {
+                    hints.push(hint("Enter/f", "Stop", 1));
+                }
+                Some(DisplayRow::Port(i)) if tcp => {
+                    hints.push(hint("Enter/f", "Forward", 1));
+                    hints.push(hint("F", "Custom Port", 5));
+                    if model.ports[*i].process.is_some() {
+                        hints.push(hint("x", "Kill", 4));
+                    }
+                }
+                Some(DisplayRow::Port(i)) if model.ports[*i].process.is_some() => {
+                    hints.push(hint("x", "Kill", 4));
+                }
+                _ => {}
+            }
+            hints.push(hint("u", "Mine", 6));
+            hints.push(hint("l", "Loopback", 7));
         }
-        AppMode::Reverse => {
-            spans.extend(hotkey_spans("Enter/f", "Reverse "));
+        AppMode::Reverse => match row {
+            Some(DisplayRow::InactiveReverseForward(_)) => {
+                hints.push(hint("Enter/f", "Stop", 1));
+            }
+            Some(DisplayRow::LocalPort(i)) => {
+                let local_port = model.local_ports[*i].port;
+                let forwarded = model
+                    .forwards
+                    .iter()
+                    .any(|(k, e)| k.kind == ForwardKind::Reverse && e.local_port == local_port);
+                let action = if forwarded { "Stop" } else { "Reverse" };
+                hints.push(hint("Enter/f", action, 1));
+            }
+            _ => {}
+        },
+    }
+    hints.push(hint("m", "Mode", 3));
+    hints.push(hint("p", "Inactive", 6));
+    if model.startup_error.is_some() {
+        hints.push(hint("r", "Retry", 1));
+    } else {
+        hints.push(hint("r", "Refresh", 5));
+    }
+    hints.push(hint("d", "Details", 4));
+    hints.push(hint("e", "Events", 5));
+    hints.push(hint("t", "Timeline", 6));
+    hints.push(hint("s", "Snapshot", 7));
+    if app::selected_address(model).is_some() {
+        hints.push(hint("y", "Copy", 4));
+    }
+    if model.mode == AppMode::Forward
+        && model
+            .selected_port()
+            .and_then(|p| model.forwards.get(&ForwardKey::local(p)))
+            .is_some_and(|e| e.status == ForwardStatus::Active)
+    {
+        hints.push(hint("v", "Preview", 6));
+    }
+    hints.push(hint("L", "Log", 7));
+    hints.push(hint("q", "Quit", 0));
+    hints
+}
+
+/// The open modal's keys, as its own footer lists them.
+fn modal_hints(modal: &ModalState) -> Vec<Hint> {
+    match modal {
+        ModalState::None => Vec::new(),
+        ModalState::PortInput { conflict, .. } => {
+            let mut hints = vec![hint("Enter", "Confirm", 0)];
+            if conflict.as_ref().is_some_and(|c| !c.suggestions.is_empty()) {
+                hints.push(hint("↑↓", "Free port", 1));
+            } else {
+                hints.push(hint("Tab", "Next free", 1));
+            }
+            hints.push(hint("Esc", "Cancel", 0));
+            hints
+        }
+        ModalState::ConfirmKill { .. } => vec![
+            hint("y", "Confirm", 0),
+            hint("Tab", "Signal", 1),
+            hint("n", "Cancel", 0),
+        ],
+        ModalState::ConfirmForward { .. } => {
+            vec![hint("y", "Forward", 0), hint("n", "Cancel", 0)]
+        }
+        ModalState::AgentLog { .. } => vec![
+            hint("j/k", "Scroll", 1),
+            hint("g/G", "Top/Bottom", 2),
+            hint("Esc", "Close", 0),
+        ],
+        ModalState::Timeline { .. } => vec![
+            hint("Tab", "All/Selected port", 2),
+            hint("j/k", "Scroll", 1),
+            hint("Esc", "Close", 0),
+        ],
+        ModalState::Preview { .. } => vec![
+            hint("r", "Refetch", 1),
+            hint("j/k", "Scroll", 2),
+            hint("Esc", "Close", 0),
+        ],
+        ModalState::PolicyBlocked { .. } | ModalState::Error { .. } => {
+            vec![hint("Esc", "Close", 0)]
         }
+        ModalState::StartupFailed { edit: Some(_), .. } => {
+            vec![hint("Enter", "Connect", 0), hint("Esc", "Cancel", 0)]
+        }
+        ModalState::StartupFailed { edit: None, .. } => vec![
+            hint("r", "Retry", 0),
+            hint("e", "Edit destination", 1),
+            hint("q", "Quit", 0),
+            hint("Esc", "Close", 1),
+        ],
+        ModalState::Prompt {
+            kind: PromptKind::Password,
+            ..
+        } => vec![hint("Enter", "Confirm", 0), hint("Esc", "Cancel", 0)],
+        ModalState::Prompt {
+            kind: PromptKind::YesNo,
+            ..
+        } => vec![hint("y", "Yes", 0), hint("n/Esc", "No", 0)],
     }
-    spans.extend(hotkey_spans("m", "Mode "));
-    spans.extend(hotkey_spans("p", "Inactive "));
-    spans.extend(hotkey_spans("q", "Quit"));
-    frame.render_widget(Line::from(spans), area);
 }
@@ -1,6 +1,11 @@
+pub mod compare;
+pub mod detail;
+pub mod events;
 pub mod header;
+pub mod hosts;
 pub mod hotkey_bar;
 pub mod modal;
+pub mod profile;
 pub mod table;
 
 use ratatui::layout::{Constraint, Layout, Rect};
@@ -26,15 +31,100 @@ pub fn hotkey_spans(key: &str, desc: &str) -> [ratatui::text::Span<'static>; 4]
     ]
 }
 
+/// Accents handed out by destination name; no red (that means stale).
+const ACCENTS: [Color; 10] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::LightCyan,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+];
+
+/// Accent color of `destination`'s header, table border and selection
+/// marker: `configured` when it parses, otherwise one derived from the name
+/// (FNV-1a, so every invocation picks the same one).
+pub fn accent_color(destination: &str, configured: Option<&str>) -> Result<Color, String> {
+    if let Some(name) = configured {
+        return name
+            .parse()
+            .map_err(|_| format!("invalid color {name:?} for {destination}"));
+    }
+    let hash = destination.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    Ok(ACCENTS[(hash % ACCENTS.len() as u64) as usize])
+}
+
 pub struct LayoutAreas {
     pub table: Rect,
+    pub detail: Option<Rect>,
+    pub events: Option<Rect>,
     pub hotkey_bar: Rect,
 }
 
-pub fn layout_areas(area: Rect) -> LayoutAreas {
-    let chunks = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).split(area);
+pub fn layout_areas(area: Rect, show_details: bool, show_events: bool) -> LayoutAreas {
+    let mut constraints = vec![Constraint::Min(3)];
+    if show_details {
+        constraints.push(Constraint::Length(detail::HEIGHT));
+    }
+    if show_events {
+        constraints.push(Constraint::Length(events::HEIGHT));
+    }
+    constraints.push(Constraint::Length(1));
+    let chunks = Layout::vertical(constraints).split(area);
+
+    let mut next = 1;
+    let mut take = |shown: bool| {
+        shown.then(|| {
+            next += 1;
+            chunks[next - 1]
+        })
+    };
+    let detail = take(show_details);
+    let events = take(show_events);
     LayoutAreas {
         table: chunks[0],
-        hotkey_bar: chunks[1],
+        detail,
+        events,
+        hotkey_bar: chunks[chunks.len() - 1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_stacks_optional_panes_above_hotkey_bar() {
+        let area = Rect::new(0, 0, 80, 40);
+        let areas = layout_areas(area, true, true);
+        let detail = areas.detail.unwrap();
+        let events = areas.events.unwrap();
+        assert_eq!(detail.height, detail::HEIGHT);
+        assert_eq!(events.height, events::HEIGHT);
+        assert_eq!(events.y, detail.y + detail.height);
+        assert_eq!(areas.hotkey_bar.y, 39);
+
+        let areas = layout_areas(area, false, true);
+        assert!(areas.detail.is_none());
+        assert_eq!(areas.events.unwrap().y + events::HEIGHT, 39);
+    }
+
+    #[test]
+    fn accent_is_configured_or_stable_per_destination() {
+        assert_eq!(accent_color("prod", Some("red")), Ok(Color::Red));
+        assert_eq!(
+            accent_color("prod", Some("#ff8800")),
+            Ok(Color::Rgb(0xff, 0x88, 0x00))
+        );
+        assert!(accent_color("prod", Some("not-a-color")).is_err());
+        let picked = accent_color("deploy@staging", None).unwrap();
+        assert_eq!(accent_color("deploy@staging", None), Ok(picked));
+        assert!(ACCENTS.contains(&picked));
     }
 }
@@ -1,12 +1,15 @@
-use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::layout::{Constraint, Layout, Margin, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, BorderType, Clear, Paragraph};
+use ratatui::widgets::{Block, BorderType, Clear, Paragraph, Wrap};
 use ratatui::Frame;
 
 use super::hotkey_spans;
-use crate::app::{ModalState, Model};
+use crate::app::{ModalState, Model, PromptKind};
 use crate::forward::ForwardKind;
+use crate::history::{TimelineEntry, TimelineKind};
+use crate::text;
+use crate::ui::detail::format_age;
 
 pub fn render(model: &Model, frame: &mut Frame) {
     let ModalState::PortInput {
@@ -15,13 +18,22 @@ pub fn render(model: &Model, frame: &mut Frame) {
         local_port,
         buffer,
         error,
+        conflict,
         ..
     } = &model.modal
     else {
         return;
     };
 
-    let area = centered_rect(44, 7, frame.area());
+    // A failed bind adds the holder and the free ports, each on a line.
+    let extra = conflict.as_ref().map_or(0, |c| {
+        u16::from(c.holder.is_some()) + u16::from(!c.suggestions.is_empty())
+    });
+    // Wide enough for the footer and the reason a typed port won't do.
+    let width = error
+        .as_deref()
+        .map_or(48, |e| (text::width(e) as u16 + 6).max(48));
+    let area = centered_rect(width.min(frame.area().width), 7 + extra, frame.area());
 
     frame.render_widget(Clear, area);
 
@@ -58,6 +70,30 @@ pub fn render(model: &Model, frame: &mut Frame) {
         lines.push(Line::raw(""));
     }
 
+    let suggestions = conflict.as_ref().map_or(&[][..], |c| &c.suggestions[..]);
+    if let Some(holder) = conflict.as_ref().and_then(|c| c.holder.as_ref()) {
+        lines.push(Line::from(vec![
+            Span::raw("  Held by "),
+            Span::styled(holder.as_str(), Style::default().fg(Color::Yellow)),
+        ]));
+    }
+    if !suggestions.is_empty() {
+        let mut spans = vec![Span::raw("  Free:")];
+        for port in suggestions {
+            let style = if buffer.parse() == Ok(*port) {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(border_color)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(format!(" {port} "), style));
+        }
+        lines.push(Line::from(spans));
+    }
+
     lines.push(Line::from(vec![
         Span::raw(format!("  {}", label)),
         Span::styled(
@@ -72,6 +108,11 @@ pub fn render(model: &Model, frame: &mut Frame) {
 
     let mut hint_spans = vec![Span::raw("  ")];
     hint_spans.extend(hotkey_spans("Enter", "Confirm  "));
+    if suggestions.is_empty() {
+        hint_spans.extend(hotkey_spans("Tab", "Next free  "));
+    } else {
+        hint_spans.extend(hotkey_spans("↑↓", "Free port  "));
+    }
     hint_spans.extend(hotkey_spans("Esc", "Cancel"));
     lines.push(Line::from(hint_spans));
 
@@ -79,6 +120,586 @@ pub fn render(model: &Model, frame: &mut Frame) {
     frame.render_widget(paragraph, inner);
 }
 
+pub fn render_confirm_kill(model: &Model, frame: &mut Frame) {
+    let ModalState::ConfirmKill {
+        remote_port,
+        pid,
+        process_name,
+        signal,
+        error,
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let area = centered_rect(52, 7, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red))
+        .title(format!(" Kill process on :{} ", remote_port));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    lines.push(Line::raw(""));
+
+    if let Some(err) = error {
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(err.as_str(), Style::default().fg(Color::Red)),
+        ]));
+    } else {
+        lines.push(Line::raw(""));
+    }
+
+    lines.push(Line::from(vec![
+        Span::raw("  Send "),
+        Span::styled(
+            signal.to_string(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" to "),
+        Span::styled(
+            format!("{process_name} ({pid})"),
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("?"),
+    ]));
+
+    lines.push(Line::raw(""));
+
+    let mut hint_spans = vec![Span::raw("  ")];
+    hint_spans.extend(hotkey_spans("y", "Confirm  "));
+    hint_spans.extend(hotkey_spans("Tab", "Signal  "));
+    hint_spans.extend(hotkey_spans("n", "Cancel"));
+    lines.push(Line::from(hint_spans));
+
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, inner);
+}
+
+pub fn render_confirm_forward(model: &Model, frame: &mut Frame) {
+    let ModalState::ConfirmForward {
+        remote_port,
+        local_port,
+        process,
+        reason,
+        ..
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let area = centered_rect(60.min(frame.area().width), 9, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(format!(" Forward :{} — {} ", remote_port, reason));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let bold = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let (owner, user, cmdline) = match process {
+        Some(p) => (
+            format!("{} ({})", p.name, p.pid),
+            if p.uid == 0 {
+                "root".to_string()
+            } else {
+                format!("uid {}", p.uid)
+            },
+            p.cmdline.clone(),
+        ),
+        None => ("unknown".to_string(), "unknown".to_string(), String::new()),
+    };
+    // Leave room for the "  Command: " label.
+    let max_cmd = inner.width.saturating_sub(12) as usize;
+    let cmdline = text::truncate(&cmdline, max_cmd).into_owned();
+
+    let mut hint_spans = vec![Span::raw("  ")];
+    hint_spans.extend(hotkey_spans("y", "Forward  "));
+    hint_spans.extend(hotkey_spans("n", "Cancel"));
+
+    let lines = vec![
+        Line::raw(""),
+        Line::from(vec![Span::raw("  Process: "), Span::styled(owner, bold)]),
+        Line::from(vec![
+            Span::raw("  User:    "),
+            Span::styled(user, Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("  Command: "),
+            Span::styled(cmdline, Style::default().fg(Color::DarkGray)),
+        ]),
+        Line::raw(format!("  Forward to local port {local_port}?")),
+        Line::raw(""),
+        Line::from(hint_spans),
+    ];
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+pub fn render_policy_blocked(model: &Model, frame: &mut Frame) {
+    let ModalState::PolicyBlocked {
+        remote_port,
+        process_name,
+        reason,
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let width = (text::width(reason) as u16 + 6).clamp(44, frame.area().width);
+    let area = centered_rect(width, 8, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red))
+        .title(format!(" Forward :{} blocked by policy ", remote_port));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let bold = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let mut hint_spans = vec![Span::raw("  ")];
+    hint_spans.extend(hotkey_spans("Esc", "Close"));
+
+    let lines = vec![
+        Line::raw(""),
+        Line::from(vec![
+            Span::raw("  Process: "),
+            Span::styled(process_name.as_deref().unwrap_or("unknown"), bold),
+        ]),
+        Line::from(vec![
+            Span::raw("  "),
+            Span::styled(reason.as_str(), Style::default().fg(Color::Red)),
+        ]),
+        Line::raw("  See [policy] in ~/.sshfwd/config.toml"),
+        Line::raw(""),
+        Line::from(hint_spans),
+    ];
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+pub fn render_error(model: &Model, frame: &mut Frame) {
+    let ModalState::Error {
+        title,
+        message,
+        hint,
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let width = 64.min(frame.area().width);
+    // Wrapping breaks at words, so allow a row more than the characters need.
+    let text_width = usize::from(width.saturating_sub(6)).max(1);
+    let rows = |line: &str| text::width(line).div_ceil(text_width) as u16 + 1;
+    let area = centered_rect(width, rows(message) + rows(hint) + 5, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red))
+        .title(format!(" {title} "));
+
+    let inner = block.inner(area).inner(Margin::new(2, 0));
+    frame.render_widget(block, area);
+
+    let mut hint_spans = Vec::new();
+    hint_spans.extend(hotkey_spans("Esc", "Close"));
+
+    let lines = vec![
+        Line::raw(""),
+        Line::styled(message.as_str(), Style::default().fg(Color::Red)),
+        Line::raw(""),
+        Line::styled(hint.as_str(), Style::default().fg(Color::White)),
+        Line::raw(""),
+        Line::from(hint_spans),
+    ];
+
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), inner);
+}
+
+pub fn render_startup_failed(model: &Model, frame: &mut Frame) {
+    let ModalState::StartupFailed { error, hint, edit } = &model.modal else {
+        return;
+    };
+
+    let width = 64.min(frame.area().width);
+    let text_width = usize::from(width.saturating_sub(6)).max(1);
+    let rows = |line: &str| text::width(line).div_ceil(text_width) as u16 + 1;
+    let hint_rows = hint.as_deref().map_or(0, rows);
+    let area = centered_rect(width, rows(error) + hint_rows + 7, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red))
+        .title(format!(" {} ", model.destination));
+
+    let inner = block.inner(area).inner(Margin::new(2, 0));
+    frame.render_widget(block, area);
+
+    let mut hint_spans = Vec::new();
+    let destination = match edit {
+        Some(buffer) => {
+            hint_spans.extend(hotkey_spans("Enter", "Connect  "));
+            hint_spans.extend(hotkey_spans("Esc", "Cancel"));
+            Line::from(vec![
+                Span::styled("Destination: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format!("{buffer}\u{2588}"),
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ])
+        }
+        None => {
+            hint_spans.extend(hotkey_spans("r", "Retry  "));
+            hint_spans.extend(hotkey_spans("e", "Edit destination  "));
+            hint_spans.extend(hotkey_spans("q", "Quit  "));
+            hint_spans.extend(hotkey_spans("Esc", "Close"));
+            Line::raw("")
+        }
+    };
+
+    let mut lines = vec![
+        Line::raw(""),
+        Line::styled(error.as_str(), Style::default().fg(Color::Red)),
+        Line::raw(""),
+    ];
+    if let Some(hint) = hint {
+        lines.push(Line::styled(
+            hint.as_str(),
+            Style::default().fg(Color::White),
+        ));
+        lines.push(Line::raw(""));
+    }
+    lines.push(destination);
+    lines.push(Line::raw(""));
+    lines.push(Line::from(hint_spans));
+
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), inner);
+}
+
+pub fn render_prompt(model: &Model, frame: &mut Frame) {
+    let ModalState::Prompt {
+        question,
+        kind,
+        buffer,
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let width = (text::width(question) as u16 + 6).clamp(44, frame.area().width);
+    let area = centered_rect(width, 7, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(format!(" {} ", model.destination));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut hint_spans = vec![Span::raw("  ")];
+    let answer = match kind {
+        PromptKind::Password => {
+            hint_spans.extend(hotkey_spans("Enter", "Confirm  "));
+            hint_spans.extend(hotkey_spans("Esc", "Cancel"));
+            Line::from(vec![
+                Span::raw("  "),
+                Span::styled(
+                    format!("{}\u{2588}", "•".repeat(buffer.chars().count())),
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ])
+        }
+        PromptKind::YesNo => {
+            hint_spans.extend(hotkey_spans("y", "Yes  "));
+            hint_spans.extend(hotkey_spans("n/Esc", "No"));
+            Line::raw("")
+        }
+    };
+
+    let lines = vec![
+        Line::raw(""),
+        Line::raw(format!("  {question}")),
+        answer,
+        Line::raw(""),
+        Line::from(hint_spans),
+    ];
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+pub fn render_agent_log(model: &Model, frame: &mut Frame) {
+    let ModalState::AgentLog {
+        lines,
+        error,
+        scroll,
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let full = frame.area();
+    let area = centered_rect(
+        full.width.saturating_sub(4).min(120),
+        full.height.saturating_sub(2),
+        full,
+    );
+
+    frame.render_widget(Clear, area);
+
+    let mut hint_spans = vec![Span::raw(" ")];
+    hint_spans.extend(hotkey_spans("j/k", "Scroll  "));
+    hint_spans.extend(hotkey_spans("g/G", "Top/Bottom  "));
+    hint_spans.extend(hotkey_spans("Esc", "Close "));
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(" Agent log ")
+        .title_bottom(Line::from(hint_spans));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text: Vec<Line> = if let Some(err) = error {
+        vec![Line::styled(
+            format!(" {err}"),
+            Style::default().fg(Color::Red),
+        )]
+    } else if lines.is_empty() {
+        vec![Line::styled(
+            " (log is empty)",
+            Style::default().fg(Color::DarkGray),
+        )]
+    } else {
+        // Anchor to the bottom; `scroll` moves the window up.
+        let height = inner.height as usize;
+        let end = lines.len().saturating_sub(*scroll);
+        let start = end.saturating_sub(height);
+        lines[start..end]
+            .iter()
+            .map(|l| Line::styled(l.as_str(), level_style(l)))
+            .collect()
+    };
+
+    frame.render_widget(Paragraph::new(text), inner);
+}
+
+pub fn render_preview(model: &Model, frame: &mut Frame) {
+    let ModalState::Preview {
+        url,
+        result,
+        scroll,
+        ..
+    } = &model.modal
+    else {
+        return;
+    };
+
+    let full = frame.area();
+    let area = centered_rect(
+        full.width.saturating_sub(4).min(100),
+        full.height.saturating_sub(2),
+        full,
+    );
+
+    frame.render_widget(Clear, area);
+
+    let mut hint_spans = vec![Span::raw(" ")];
+    hint_spans.extend(hotkey_spans("r", "Refetch  "));
+    hint_spans.extend(hotkey_spans("j/k", "Scroll  "));
+    hint_spans.extend(hotkey_spans("Esc", "Close "));
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(format!(" Preview · {} ", text::truncate(url, 60)))
+        .title_bottom(Line::from(hint_spans));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let dim = Style::default().fg(Color::DarkGray);
+    let text: Vec<Line> = match result {
+        None => vec![Line::styled(" Fetching…", dim)],
+        Some(Err(e)) => vec![Line::styled(
+            format!(" {e}"),
+            Style::default().fg(Color::Red),
+        )],
+        Some(Ok(preview)) => {
+            let width = usize::from(inner.width.saturating_sub(1));
+            let status_color = match preview.status {
+                200..=299 => Color::Green,
+                300..=399 => Color::Cyan,
+                400..=499 => Color::Yellow,
+                _ => Color::Red,
+            };
+            let mut lines = vec![Line::styled(
+                format!(" {}", text::truncate(&preview.status_line, width)),
+                Style::default()
+                    .fg(status_color)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            lines.extend(preview.headers.iter().map(|(name, value)| {
+                Line::from(vec![
+                    Span::styled(format!(" {name}: "), dim),
+                    Span::raw(
+                        text::truncate(value, width.saturating_sub(name.len() + 2)).into_owned(),
+                    ),
+                ])
+            }));
+            lines.push(Line::raw(""));
+            lines.extend(preview.body.lines().map(|line| {
+                // Tabs and stray control bytes would scramble the frame.
+                let line: String = line
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .collect();
+                Line::raw(format!(" {}", text::truncate(&line, width)))
+            }));
+            if preview.truncated {
+                lines.push(Line::styled(
+                    format!(
+                        " … first {} KB of the body",
+                        crate::preview::BODY_LIMIT / 1024
+                    ),
+                    dim,
+                ));
+            }
+            lines.into_iter().skip(*scroll).collect()
+        }
+    };
+
+    frame.render_widget(Paragraph::new(text), inner);
+}
+
+pub fn render_timeline(model: &Model, frame: &mut Frame) {
+    let ModalState::Timeline { port, scroll } = &model.modal else {
+        return;
+    };
+
+    let full = frame.area();
+    let area = centered_rect(
+        full.width.saturating_sub(4).min(100),
+        full.height.saturating_sub(2),
+        full,
+    );
+
+    frame.render_widget(Clear, area);
+
+    let mut hint_spans = vec![Span::raw(" ")];
+    hint_spans.extend(hotkey_spans("Tab", "All/Selected port  "));
+    hint_spans.extend(hotkey_spans("j/k", "Scroll  "));
+    hint_spans.extend(hotkey_spans("Esc", "Close "));
+
+    let title = match port {
+        Some(p) => format!(" Timeline · :{p} "),
+        None => format!(" Timeline · {} scans ", model.history.scans()),
+    };
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(title)
+        .title_bottom(Line::from(hint_spans));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let entries: Vec<&TimelineEntry> = model
+        .history
+        .timeline()
+        .iter()
+        .filter(|e| port.is_none_or(|p| e.port == p))
+        .collect();
+    let text: Vec<Line> = if entries.is_empty() {
+        vec![Line::styled(
+            " No port changes since sshfwd connected",
+            Style::default().fg(Color::DarkGray),
+        )]
+    } else {
+        // Newest at the bottom; `scroll` moves the window up.
+        let height = inner.height as usize;
+        let end = entries.len().saturating_sub(*scroll);
+        let start = end.saturating_sub(height);
+        entries[start..end]
+            .iter()
+            .map(|e| timeline_line(e))
+            .collect()
+    };
+
+    frame.render_widget(Paragraph::new(text), inner);
+}
+
+fn timeline_line(entry: &TimelineEntry) -> Line<'static> {
+    let age = entry
+        .at
+        .elapsed()
+        .map_or_else(|_| "now".to_string(), |d| format_age(d.as_secs()));
+    let (label, color) = match entry.kind {
+        TimelineKind::Appeared => ("appeared".to_string(), Color::Green),
+        TimelineKind::Disappeared => ("gone".to_string(), Color::Red),
+        TimelineKind::Restarted { old_pid } => {
+            (format!("restarted (was {old_pid})"), Color::Yellow)
+        }
+    };
+    let process = match (&entry.process_name, entry.pid) {
+        (Some(name), Some(pid)) => format!("{name} ({pid})"),
+        _ => "-".to_string(),
+    };
+    Line::from(vec![
+        Span::styled(
+            format!(" {age:>7} ago "),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled(
+            format!(":{:<6}", entry.port),
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::styled(format!("{label:<24}"), Style::default().fg(color)),
+        Span::raw(process),
+    ])
+}
+
+fn level_style(line: &str) -> Style {
+    let level = line.split_whitespace().nth(1).unwrap_or_default();
+    match level {
+        "ERROR" => Style::default().fg(Color::Red),
+        "WARN" => Style::default().fg(Color::Yellow),
+        "DEBUG" => Style::default().fg(Color::DarkGray),
+        _ => Style::default(),
+    }
+}
+
 fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
     let vertical = Layout::vertical([
         Constraint::Fill(1),
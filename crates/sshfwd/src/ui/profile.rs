@@ -0,0 +1,202 @@
+//! Rendering for limited terminals: `TERM=dumb`, 8-color consoles and
+//! locales without UTF-8. Views draw as usual; the finished frame is then
+//! rewritten cell by cell, so no widget needs to know.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+
+/// Terminals with only the 8 basic colors (no bright variants, so DarkGray
+/// shows as black).
+const BASIC_COLOR_TERMS: [&str; 7] = ["dumb", "linux", "vt100", "vt102", "vt220", "ansi", "cons25"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Replace every non-ASCII glyph.
+    pub ascii: bool,
+    /// Stick to the 8 basic colors, without dim text.
+    pub basic_colors: bool,
+}
+
+impl Profile {
+    /// Both restrictions.
+    pub const LIMITED: Profile = Profile {
+        ascii: true,
+        basic_colors: true,
+    };
+
+    /// From `TERM`, `COLORTERM` and the locale (`LC_ALL`, `LC_CTYPE`,
+    /// `LANG`); `--ascii` forces `LIMITED`.
+    pub fn detect(force: bool) -> Self {
+        if force {
+            return Self::LIMITED;
+        }
+        detect(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    /// Rewrite a drawn frame for this profile.
+    pub fn apply(self, buffer: &mut Buffer) {
+        if self == Profile::default() {
+            return;
+        }
+        for cell in &mut buffer.content {
+            if self.ascii && !cell.symbol().is_ascii() {
+                let glyph = ascii(cell.symbol());
+                cell.set_char(glyph);
+            }
+            if self.basic_colors {
+                // Text on a gray background (hotkeys, selections) turns
+                // black on white; gray text turns plain.
+                cell.bg = match basic(cell.bg) {
+                    Color::DarkGray | Color::Gray => Color::White,
+                    color => color,
+                };
+                cell.fg = match (basic(cell.fg), cell.bg) {
+                    (Color::DarkGray | Color::Gray, _) => Color::Reset,
+                    (Color::Black, Color::Reset) => Color::Reset,
+                    (color, _) => color,
+                };
+                cell.modifier.remove(Modifier::DIM);
+            }
+        }
+    }
+}
+
+fn detect(env: impl Fn(&str) -> Option<String>) -> Profile {
+    let term = env("TERM").unwrap_or_default();
+    // The first of these that is set decides; none at all is taken as a
+    // UTF-8 terminal rather than the C locale, as over most SSH sessions.
+    let utf8 = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(&env)
+        .is_none_or(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        });
+    Profile {
+        ascii: term == "dumb" || !utf8,
+        basic_colors: BASIC_COLOR_TERMS.contains(&term.as_str()) && env("COLORTERM").is_none(),
+    }
+}
+
+/// The ASCII stand-in for a grapheme.
+fn ascii(symbol: &str) -> char {
+    let c = symbol.chars().next().unwrap_or(' ');
+    match c {
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '—' | '–' => {
+            '-'
+        }
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' => '|',
+        '\u{2500}'..='\u{257f}' => '+',
+        '\u{2580}'..='\u{259f}' => '#',
+        '●' | '•' => '*',
+        '▶' | '→' | '›' => '>',
+        '←' | '‹' => '<',
+        '↑' => '^',
+        '↓' => 'v',
+        '⇆' => '=',
+        '⚠' => '!',
+        '…' | '·' => '.',
+        _ => '?',
+    }
+}
+
+/// `color` within the 8 basic colors; DarkGray and Gray are kept for
+/// `apply` to place.
+fn basic(color: Color) -> Color {
+    match color {
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightYellow => Color::Yellow,
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan => Color::Cyan,
+        Color::Indexed(i) if i < 16 => basic(ANSI[usize::from(i % 8)]),
+        Color::Indexed(_) => Color::Reset,
+        Color::Rgb(r, g, b) => {
+            let bit = |v: u8, n: usize| usize::from(v >= 0x80) << n;
+            ANSI[bit(r, 0) | bit(g, 1) | bit(b, 2)]
+        }
+        color => color,
+    }
+}
+
+/// ANSI colors 0-7, indexed by red | green << 1 | blue << 2.
+const ANSI: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn detects_limited_terminals() {
+        let full = Profile::default();
+        assert_eq!(detect(env(&[("TERM", "xterm-256color")])), full);
+        assert_eq!(
+            detect(env(&[("TERM", "xterm"), ("LANG", "en_US.UTF-8")])),
+            full
+        );
+        assert_eq!(detect(env(&[("TERM", "dumb")])), Profile::LIMITED);
+        assert_eq!(
+            detect(env(&[("TERM", "linux"), ("LANG", "en_US.utf8")])),
+            Profile {
+                ascii: false,
+                basic_colors: true
+            }
+        );
+        // LC_ALL wins over LANG.
+        assert_eq!(
+            detect(env(&[
+                ("TERM", "xterm"),
+                ("LC_ALL", "C"),
+                ("LANG", "en_US.UTF-8")
+            ])),
+            Profile {
+                ascii: true,
+                basic_colors: false
+            }
+        );
+    }
+
+    #[test]
+    fn rewrites_glyphs_and_colors() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 8, 1));
+        buffer.set_string(0, 0, "╭─●▶", Style::default().fg(Color::DarkGray));
+        buffer.set_string(
+            4,
+            0,
+            "k",
+            Style::default().fg(Color::Black).bg(Color::DarkGray),
+        );
+        buffer.set_string(5, 0, "名", Style::default().fg(Color::LightCyan));
+        Profile::LIMITED.apply(&mut buffer);
+
+        let text: String = buffer.content.iter().map(|c| c.symbol()).collect();
+        assert_eq!(text, "+-*>k?  ");
+        assert_eq!(buffer[(0, 0)].fg, Color::Reset);
+        assert_eq!(
+            (buffer[(4, 0)].fg, buffer[(4, 0)].bg),
+            (Color::Black, Color::White)
+        );
+        assert_eq!(buffer[(5, 0)].fg, Color::Cyan);
+        assert_eq!(basic(Color::Rgb(0xff, 0x88, 0x00)), Color::Yellow);
+    }
+}
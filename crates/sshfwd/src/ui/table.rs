@@ -1,11 +1,15 @@
-use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, BorderType, Paragraph, Row, Table};
+use ratatui::widgets::{Block, BorderType, Paragraph, Row, Table, TableState};
 use ratatui::Frame;
 
 use crate::app::{AppMode, ConnectionState, Model};
+use crate::columns::Column;
 use crate::forward::{ForwardKey, ForwardKind, ForwardStatus};
+use crate::history::TimelineKind;
+use crate::rows::{build_display_rows, DisplayRow};
+use crate::text;
 use crate::ui::header;
 
 const LOGO: &[&str] = &[
@@ -19,143 +23,32 @@ const LOGO: &[&str] = &[
 const HEADER_STYLE: Style = Style::new()
     .fg(Color::DarkGray)
     .add_modifier(Modifier::BOLD);
+/// A port that appeared in a recent scan.
+const NEW_STYLE: Style = Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+/// A port whose process restarted (new PID) in a recent scan.
+const RESTARTED_STYLE: Style = Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD);
+/// A port marked by dragging, for Enter/f to act on.
+const MARKED_STYLE: Style = Style::new().bg(Color::Blue);
 const SELECTED_STYLE: Style = Style::new()
     .fg(Color::White)
     .bg(Color::DarkGray)
     .add_modifier(Modifier::BOLD);
 
-// Reused across all 5 columns of a separator row — avoids repeated allocation.
+// Reused across all columns of a separator row — avoids repeated allocation.
 const SEP: &str = "────────────────────";
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum DisplayRow {
-    Port(usize),                 // index into model.ports (Forward mode)
-    LocalPort(usize),            // index into model.local_ports (Reverse mode)
-    InactiveForward(u16),        // remote port of a paused local forward not in current scan
-    InactiveReverseForward(u16), // remote bind port (ForwardKey::remote_port) of a paused reverse forward not in local scan
-    Separator,
-}
-
-impl DisplayRow {
-    pub fn is_selectable(&self) -> bool {
-        matches!(
-            self,
-            DisplayRow::Port(_)
-                | DisplayRow::LocalPort(_)
-                | DisplayRow::InactiveForward(_)
-                | DisplayRow::InactiveReverseForward(_)
-        )
-    }
-}
-
-pub fn build_display_rows(model: &Model) -> Vec<DisplayRow> {
-    match model.mode {
-        AppMode::Forward => build_forward_rows(model),
-        AppMode::Reverse => build_reverse_rows(model),
-    }
-}
-
-fn build_forward_rows(model: &Model) -> Vec<DisplayRow> {
-    let scan_ports: std::collections::HashSet<u16> = model.ports.iter().map(|p| p.port).collect();
-
-    let mut forwarded = Vec::new();
-    let mut non_forwarded = Vec::new();
-
-    for (i, port) in model.ports.iter().enumerate() {
-        if model.forwards.contains_key(&ForwardKey::local(port.port)) {
-            forwarded.push((port.port, DisplayRow::Port(i)));
-        } else {
-            non_forwarded.push(DisplayRow::Port(i));
-        }
-    }
-
-    // Merge inactive forwards with active forwards, sorted together by port
-    if model.show_inactive_forwards {
-        for (key, entry) in &model.forwards {
-            if key.kind == ForwardKind::Local
-                && entry.status == ForwardStatus::Paused
-                && !scan_ports.contains(&key.remote_port)
-            {
-                forwarded.push((
-                    key.remote_port,
-                    DisplayRow::InactiveForward(key.remote_port),
-                ));
-            }
-        }
-    }
-    forwarded.sort_by(|(port_a, row_a), (port_b, row_b)| {
-        port_a.cmp(port_b).then_with(|| match (row_a, row_b) {
-            (DisplayRow::Port(i1), DisplayRow::Port(i2)) => i1.cmp(i2),
-            (DisplayRow::Port(_), DisplayRow::InactiveForward(_)) => std::cmp::Ordering::Less,
-            (DisplayRow::InactiveForward(_), DisplayRow::Port(_)) => std::cmp::Ordering::Greater,
-            _ => std::cmp::Ordering::Equal,
-        })
-    });
-
-    let has_top = !forwarded.is_empty();
-    let mut rows = Vec::with_capacity(forwarded.len() + 1 + non_forwarded.len());
-    rows.extend(forwarded.into_iter().map(|(_, dr)| dr));
-    if has_top && !non_forwarded.is_empty() {
-        rows.push(DisplayRow::Separator);
-    }
-    rows.extend(non_forwarded);
-    rows
-}
-
-fn build_reverse_rows(model: &Model) -> Vec<DisplayRow> {
-    let local_scan_ports: std::collections::HashSet<u16> =
-        model.local_ports.iter().map(|p| p.port).collect();
-
-    // Reverse forwards are keyed by remote bind port; build a local_port → key map
-    // so we can match scan rows (organized by local port) to active forwards.
-    let reverse_by_local: std::collections::HashMap<u16, ForwardKey> = model
-        .forwards
-        .iter()
-        .filter(|(k, _)| k.kind == ForwardKind::Reverse)
-        .map(|(k, e)| (e.local_port, *k))
-        .collect();
+const HIGHLIGHT_SYMBOL: &str = "▶ ";
+const COLUMN_SPACING: u16 = 2;
 
-    let mut reverse_forwarded = Vec::new();
-    let mut non_reverse_forwarded = Vec::new();
-
-    for (i, port) in model.local_ports.iter().enumerate() {
-        if reverse_by_local.contains_key(&port.port) {
-            reverse_forwarded.push((port.port, DisplayRow::LocalPort(i)));
-        } else {
-            non_reverse_forwarded.push(DisplayRow::LocalPort(i));
-        }
-    }
-
-    // Merge inactive reverse forwards (any status, whose local port is not in local scan).
-    // An Active forward whose local service has stopped is also effectively inactive.
-    if model.show_inactive_forwards {
-        for (key, entry) in &model.forwards {
-            if key.kind == ForwardKind::Reverse && !local_scan_ports.contains(&entry.local_port) {
-                reverse_forwarded.push((
-                    entry.local_port,
-                    DisplayRow::InactiveReverseForward(key.remote_port),
-                ));
-            }
-        }
-    }
-    reverse_forwarded.sort_by_key(|(port, _)| *port);
-
-    let has_top = !reverse_forwarded.is_empty();
-    let mut rows = Vec::with_capacity(reverse_forwarded.len() + 1 + non_reverse_forwarded.len());
-    rows.extend(reverse_forwarded.into_iter().map(|(_, dr)| dr));
-    if has_top && !non_reverse_forwarded.is_empty() {
-        rows.push(DisplayRow::Separator);
-    }
-    rows.extend(non_reverse_forwarded);
-    rows
-}
+/// A row's text by column (in `Column::ALL` order) and its style.
+type Cells = ([String; 6], Style);
 
 pub fn render(model: &mut Model, frame: &mut Frame, area: Rect) {
     let title = header::build_title(model);
 
     let block = Block::bordered()
         .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::DarkGray))
+        .border_style(Style::default().fg(model.accent))
         .title(title);
 
     let show_splash = match model.mode {
@@ -169,83 +62,163 @@ pub fn render(model: &mut Model, frame: &mut Frame, area: Rect) {
         return;
     }
 
-    let header_row = Row::new(["FWD", "PORT", "PROTO", "PID", "COMMAND"]).style(HEADER_STYLE);
-
-    let widths = [
-        Constraint::Length(9),
-        Constraint::Length(8),
-        Constraint::Length(7),
-        Constraint::Length(9),
-        Constraint::Min(20),
-    ];
+    let columns = model.columns.shown();
+    let header_row = Row::new(columns.iter().map(|c| c.title())).style(HEADER_STYLE);
 
     let display_rows = build_display_rows(model);
 
+    // Compute inner area before block is consumed by Table.
+    let inner = block.inner(area);
+
+    // Only the rows on screen are materialized: with thousands of ports,
+    // formatting every row dominates the frame.
+    let height = usize::from(inner.height.saturating_sub(1));
+    let offset = visible_offset(
+        model.table_state.offset(),
+        model.selected_index,
+        height,
+        display_rows.len(),
+    );
+
     let inactive_style = Style::default()
         .fg(Color::DarkGray)
         .add_modifier(Modifier::DIM);
 
-    let rows: Vec<Row> = display_rows
+    let cells: Vec<Option<Cells>> = display_rows
         .iter()
+        .skip(offset)
+        .take(height)
         .map(|dr| match dr {
             DisplayRow::Port(i) => {
                 let port = &model.ports[*i];
                 let fwd_cell = format_local_fwd(model, port.port);
-                make_port_row(fwd_cell, port)
+                let (mut cells, style) = port_cells(fwd_cell, port);
+                // Recent changes stand out until `CHANGE_HIGHLIGHT` passes.
+                let style = match model.changed_ports.get(&port.port) {
+                    Some((TimelineKind::Restarted { .. }, _)) => {
+                        cells[column_index(Column::Pid)].push('↻');
+                        style.patch(RESTARTED_STYLE)
+                    }
+                    Some(_) => style.patch(NEW_STYLE),
+                    None => style,
+                };
+                let style = if model.marked.contains(&port.port) {
+                    style.patch(MARKED_STYLE)
+                } else {
+                    style
+                };
+                Some((cells, style))
             }
             DisplayRow::LocalPort(i) => {
                 let port = &model.local_ports[*i];
                 let fwd_cell = format_reverse_fwd(model, port.port);
-                make_port_row(fwd_cell, port)
+                Some(port_cells(fwd_cell, port))
             }
             DisplayRow::InactiveForward(remote_port) => {
                 let local_port = model
                     .forwards
                     .get(&ForwardKey::local(*remote_port))
                     .map_or(*remote_port, |e| e.local_port);
-                Row::new([
-                    format!("||:{}", local_port),
-                    remote_port.to_string(),
-                    "-".to_string(),
-                    "-".to_string(),
-                    "(inactive)".to_string(),
-                ])
-                .style(inactive_style)
+                Some((
+                    [
+                        format!("||:{}", local_port),
+                        remote_port.to_string(),
+                        "-".to_string(),
+                        "-".to_string(),
+                        "-".to_string(),
+                        "(inactive)".to_string(),
+                    ],
+                    inactive_style,
+                ))
             }
             DisplayRow::InactiveReverseForward(remote_port) => {
                 // remote_port is the ForwardKey's remote_port (the remote bind port)
                 let entry = model.forwards.get(&ForwardKey::reverse(*remote_port));
                 let local_port = entry.map_or(*remote_port, |e| e.local_port);
-                Row::new([
-                    format!("||<-:{}", remote_port),
-                    local_port.to_string(),
-                    "-".to_string(),
-                    "-".to_string(),
-                    "(inactive)".to_string(),
-                ])
-                .style(inactive_style)
+                Some((
+                    [
+                        format!("||<-:{}", remote_port),
+                        local_port.to_string(),
+                        "-".to_string(),
+                        "-".to_string(),
+                        "-".to_string(),
+                        "(inactive)".to_string(),
+                    ],
+                    inactive_style,
+                ))
             }
-            DisplayRow::Separator => {
-                Row::new([SEP, SEP, SEP, SEP, SEP]).style(Style::default().fg(Color::DarkGray))
+            DisplayRow::Separator => None,
+        })
+        .collect();
+
+    let widths: Vec<Constraint> = columns
+        .iter()
+        .map(|&column| {
+            let at = column_index(column);
+            let widest = cells
+                .iter()
+                .flatten()
+                .map(|(cells, _)| text::width(&cells[at]))
+                .chain([column.title().len()])
+                .max()
+                .unwrap_or(0);
+            match model.columns.fit(column, widest as u16) {
+                (width, true) => Constraint::Min(width),
+                (width, false) => Constraint::Length(width),
             }
         })
         .collect();
 
-    // Compute inner area before block is consumed by Table.
-    let inner = block.inner(area);
+    // The widths the table will lay out, so a wide command (CJK, emoji) is
+    // cut with an ellipsis on a character boundary rather than by the widget.
+    let selected = (model.selected_index < display_rows.len()).then_some(model.selected_index);
+    let symbol_width = if selected.is_some() {
+        text::width(HIGHLIGHT_SYMBOL) as u16
+    } else {
+        0
+    };
+    let laid_out = Layout::horizontal(widths.iter().copied())
+        .flex(Flex::Start)
+        .spacing(COLUMN_SPACING)
+        .split(Rect::new(0, 0, inner.width.saturating_sub(symbol_width), 1));
+
+    let rows: Vec<Row> = cells
+        .into_iter()
+        .map(|cells| match cells {
+            Some((mut cells, style)) => {
+                let at = column_index(Column::Command);
+                if let Some(area) = columns
+                    .iter()
+                    .position(|&c| c == Column::Command)
+                    .map(|i| laid_out[i])
+                {
+                    cells[at] = text::truncate(&cells[at], usize::from(area.width)).into_owned();
+                }
+                Row::new(columns.iter().map(|&c| cells[column_index(c)].clone())).style(style)
+            }
+            None => {
+                Row::new(columns.iter().map(|_| SEP)).style(Style::default().fg(Color::DarkGray))
+            }
+        })
+        .collect();
 
     let table = Table::new(rows, widths)
         .block(block)
         .header(header_row)
         .row_highlight_style(SELECTED_STYLE)
-        .highlight_symbol("▶ ")
-        .column_spacing(2);
-
-    if !display_rows.is_empty() {
-        model.table_state.select(Some(model.selected_index));
-    }
-
-    frame.render_stateful_widget(table, area, &mut model.table_state);
+        .highlight_symbol(Span::styled(
+            HIGHLIGHT_SYMBOL,
+            Style::default().fg(model.accent),
+        ))
+        .column_spacing(COLUMN_SPACING)
+        .flex(Flex::Start);
+
+    // The widget sees only the visible slice; `model.table_state` keeps the
+    // offset into the full list for scrolling and mouse hit-testing.
+    let mut visible_state = TableState::default().with_selected(selected.map(|i| i - offset));
+    frame.render_stateful_widget(table, area, &mut visible_state);
+    *model.table_state.offset_mut() = offset;
+    model.table_state.select(selected);
 
     // Store content area for mouse hit-testing.
     // +1 row for the header row gives us where data rows start.
@@ -257,16 +230,39 @@ pub fn render(model: &mut Model, frame: &mut Frame, area: Rect) {
     });
 }
 
+/// First row on screen: the previous offset, moved just enough to show
+/// `selected` and clamped so the end of the list fills the viewport.
+fn visible_offset(previous: usize, selected: usize, height: usize, len: usize) -> usize {
+    let offset = previous.min(len.saturating_sub(height));
+    if selected < offset {
+        selected
+    } else if height > 0 && selected >= offset + height {
+        selected + 1 - height
+    } else {
+        offset
+    }
+}
+
 fn render_splash(model: &Model, frame: &mut Frame, area: Rect, block: Block) {
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
     let logo_height = LOGO.len() as u16;
-    let status_line = match model.connection_state {
-        ConnectionState::Connecting => "Connecting...",
-        ConnectionState::Connected => "Waiting for ports...",
-        ConnectionState::Reconnecting => "Reconnecting...",
-        ConnectionState::Disconnected => "Disconnected",
+    let status_line = match (&model.startup_error, model.startup, &model.connection_state) {
+        (Some(error), _, _) => Span::styled(error.clone(), Style::default().fg(Color::Red)),
+        (None, Some(stage), ConnectionState::Connecting) => Span::styled(
+            format!("{}...", capitalize(&stage.label())),
+            Style::default().fg(Color::DarkGray),
+        ),
+        (None, _, state) => Span::styled(
+            match state {
+                ConnectionState::Connecting => "Connecting...",
+                ConnectionState::Connected => "Waiting for ports...",
+                ConnectionState::Reconnecting => "Reconnecting...",
+                ConnectionState::Stale => "Waiting for agent...",
+            },
+            Style::default().fg(Color::DarkGray),
+        ),
     };
     // logo + blank + status = total content height
     let content_height = logo_height + 2;
@@ -285,40 +281,57 @@ fn render_splash(model: &Model, frame: &mut Frame, area: Rect, block: Block) {
         .map(|line| Line::from(Span::styled(*line, Style::default().fg(Color::Cyan))))
         .collect();
     lines.push(Line::raw(""));
-    lines.push(Line::from(Span::styled(
-        status_line,
-        Style::default().fg(Color::DarkGray),
-    )));
+    lines.push(Line::from(status_line));
 
     let paragraph = Paragraph::new(lines).centered();
     frame.render_widget(paragraph, content_area);
 }
 
-fn protocol_str(protocol: &sshfwd_common::types::Protocol) -> &'static str {
-    match protocol {
-        sshfwd_common::types::Protocol::Tcp => "tcp",
-        sshfwd_common::types::Protocol::Tcp6 => "tcp6",
-    }
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
 }
 
-/// Build a table row for a scanned port (shared between Forward and Reverse modes).
-fn make_port_row(
+/// Where `column`'s text sits in `Cells`.
+fn column_index(column: Column) -> usize {
+    Column::ALL
+        .iter()
+        .position(|&c| c == column)
+        .expect("every column")
+}
+
+/// Cells of a scanned port's row (shared between Forward and Reverse modes).
+fn port_cells(
     fwd_cell: (String, Option<Style>),
     port: &sshfwd_common::types::ListeningPort,
-) -> Row<'static> {
-    let proto = protocol_str(&port.protocol);
+) -> Cells {
+    let proto = port.protocol.as_str();
     let (pid, cmd) = match &port.process {
-        Some(p) => (p.pid.to_string(), p.cmdline.clone()),
+        // `#`: another user's process, attributed only thanks to the elevated agent.
+        Some(p) if p.elevated => (format!("{}#", p.pid), p.command()),
+        Some(p) => (p.pid.to_string(), p.command()),
         None => ("-".to_string(), "-".to_string()),
     };
-    Row::new([
-        fwd_cell.0,
-        port.port.to_string(),
-        proto.to_string(),
-        pid,
-        cmd,
-    ])
-    .style(fwd_cell.1.unwrap_or_default())
+    // A Unix socket has no port; its path leads the command instead.
+    let (number, cmd) = if port.protocol.is_unix() {
+        ("-".to_string(), format!("{}  {cmd}", port.local_addr))
+    } else {
+        (port.port.to_string(), cmd)
+    };
+    (
+        [
+            fwd_cell.0,
+            number,
+            crate::columns::bind_cell(port),
+            proto.to_string(),
+            pid,
+            cmd,
+        ],
+        fwd_cell.1.unwrap_or_default(),
+    )
 }
 
 /// Returns (display_text, optional_style_override) for the FWD column — local forward mode.
@@ -366,3 +379,21 @@ fn format_reverse_fwd(model: &Model, local_port: u16) -> (String, Option<Style>)
         None => (String::new(), None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_follows_selection_and_clamps() {
+        // Selection inside the viewport keeps the offset.
+        assert_eq!(visible_offset(10, 15, 20, 2000), 10);
+        // Scrolling down and up just far enough.
+        assert_eq!(visible_offset(10, 40, 20, 2000), 21);
+        assert_eq!(visible_offset(10, 3, 20, 2000), 3);
+        // A list that shrank below the offset fills the viewport again.
+        assert_eq!(visible_offset(500, 5, 20, 30), 5);
+        assert_eq!(visible_offset(500, 25, 20, 30), 10);
+        assert_eq!(visible_offset(3, 0, 20, 0), 0);
+    }
+}
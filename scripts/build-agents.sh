@@ -3,6 +3,18 @@ set -euo pipefail
 
 # Cross-compile sshfwd-agent for all supported platforms.
 # Output goes to prebuilt-agents/{platform}/sshfwd-agent
+#
+# Set SSHFWD_GNU_AGENTS=1 to also build optional glibc agents
+# (prebuilt-agents/linux-{arch}-gnu), which sshfwd prefers on glibc hosts so
+# user names resolve through NSS (LDAP, sssd). Needs glibc cross toolchains.
+#
+# Signing: set SSHFWD_SIGNING_KEY to an ed25519 private key (PEM, e.g. from
+# `openssl genpkey -algorithm ed25519`) to write sshfwd-agent.sig next to each
+# binary, and build both the agents and sshfwd with SSHFWD_AGENT_PUBKEY set to
+# the matching hex public key so they verify it.
+#
+# CI builds one platform per job: SSHFWD_AGENT_PLATFORMS limits the build to
+# the listed directory names, and SSHFWD_CARGO=cross builds through cross.
 
 SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
 PROJECT_ROOT="$(cd "$SCRIPT_DIR/.." && pwd)"
@@ -12,11 +24,51 @@ OUTPUT_DIR="$PROJECT_ROOT/crates/sshfwd/prebuilt-agents"
 PLATFORMS=(
     "linux-x86_64    x86_64-unknown-linux-musl"
     "linux-aarch64   aarch64-unknown-linux-musl"
+    "linux-armv7     armv7-unknown-linux-musleabihf"
+    "linux-riscv64   riscv64gc-unknown-linux-musl"
     "darwin-x86_64   x86_64-apple-darwin"
     "darwin-aarch64  aarch64-apple-darwin"
 )
 
+if [ "${SSHFWD_GNU_AGENTS:-}" = "1" ]; then
+    PLATFORMS+=(
+        "linux-x86_64-gnu    x86_64-unknown-linux-gnu"
+        "linux-aarch64-gnu   aarch64-unknown-linux-gnu"
+        "linux-armv7-gnu     armv7-unknown-linux-gnueabihf"
+        "linux-riscv64-gnu   riscv64gc-unknown-linux-gnu"
+    )
+fi
+
+if [ -n "${SSHFWD_AGENT_PLATFORMS:-}" ]; then
+    selected=()
+    for entry in "${PLATFORMS[@]}"; do
+        dir_name=$(echo "$entry" | awk '{print $1}')
+        for wanted in $SSHFWD_AGENT_PLATFORMS; do
+            if [ "$dir_name" = "$wanted" ]; then
+                selected+=("$entry")
+            fi
+        done
+    done
+    if [ ${#selected[@]} -eq 0 ]; then
+        echo "No known platform in SSHFWD_AGENT_PLATFORMS: $SSHFWD_AGENT_PLATFORMS" >&2
+        exit 1
+    fi
+    PLATFORMS=("${selected[@]}")
+fi
+
 PROFILE="release-agent"
+CARGO_CMD="${SSHFWD_CARGO:-cargo}"
+
+if [ -n "${SSHFWD_SIGNING_KEY:-}" ]; then
+    pubkey=$(openssl pkey -in "$SSHFWD_SIGNING_KEY" -pubout -outform DER | tail -c 32 | od -An -tx1 | tr -d ' \n')
+    if [ -z "${SSHFWD_AGENT_PUBKEY:-}" ]; then
+        export SSHFWD_AGENT_PUBKEY="$pubkey"
+    elif [ "$SSHFWD_AGENT_PUBKEY" != "$pubkey" ]; then
+        echo "SSHFWD_AGENT_PUBKEY does not match SSHFWD_SIGNING_KEY" >&2
+        exit 1
+    fi
+    echo "Signing with public key: $SSHFWD_AGENT_PUBKEY"
+fi
 
 echo "Building sshfwd-agent for all platforms..."
 echo "Output directory: $OUTPUT_DIR"
@@ -43,11 +95,16 @@ for entry in "${PLATFORMS[@]}"; do
     fi
 
     # Build
-    if cargo build -p sshfwd-agent --target "$target" --profile "$PROFILE" 2>&1; then
+    if "$CARGO_CMD" build -p sshfwd-agent --target "$target" --profile "$PROFILE" 2>&1; then
         # Copy binary to output directory
         mkdir -p "$dest_dir"
         src="$PROJECT_ROOT/target/$target/$PROFILE/sshfwd-agent"
         cp "$src" "$dest_dir/sshfwd-agent"
+        rm -f "$dest_dir/sshfwd-agent.sig"
+        if [ -n "${SSHFWD_SIGNING_KEY:-}" ]; then
+            openssl pkeyutl -sign -rawin -inkey "$SSHFWD_SIGNING_KEY" \
+                -in "$dest_dir/sshfwd-agent" -out "$dest_dir/sshfwd-agent.sig"
+        fi
         size=$(du -h "$dest_dir/sshfwd-agent" | awk '{print $1}')
         echo "  OK: $dest_dir/sshfwd-agent ($size)"
     else